@@ -5,4 +5,9 @@ quit-all = Quit All
 new-window = New Window
 run = Run
 run-on = Run on {$gpu}
-run-on-default = (Default)
\ No newline at end of file
+run-on-default = (Default)
+running = Running
+frequent = Frequent
+close-all = Close All
+close-windows = Close { $count } windows?
+cancel = Cancel
@@ -15,9 +15,11 @@ use cctk::{
     toplevel_info::ToplevelInfo,
     wayland_client::protocol::{
         wl_data_device_manager::DndAction, wl_output::WlOutput, wl_seat::WlSeat,
+        wl_surface::WlSurface,
     },
 };
 use cosmic::{
+    app::Core,
     applet::{
         cosmic_panel_config::{PanelAnchor, PanelSize},
         Context, Size,
@@ -27,10 +29,8 @@ use cosmic::{
     iced,
     iced::{
         event::listen_with,
-        wayland::{
-            actions::data_device::{DataFromMimeType, DndIcon},
-            popup::{destroy_popup, get_popup},
-        },
+        time,
+        wayland::actions::data_device::{DataFromMimeType, DndIcon},
         widget::{
             column, dnd_listener, dnd_source, mouse_area, row, vertical_rule, vertical_space,
             Column, Row,
@@ -42,7 +42,7 @@ use cosmic::{
     iced_sctk::commands::data_device::{
         accept_mime_type, finish_dnd, request_dnd_data, set_actions, start_drag,
     },
-    iced_style::{application, svg},
+    iced_style::svg,
     theme::{Button, Container},
     widget::{
         button, divider, horizontal_space, icon,
@@ -53,6 +53,9 @@ use cosmic::{
     },
     Apply, Command, Element, Theme,
 };
+use cosmic_applet_helper::{
+    icon_handle, AppletMessage, CosmicApplet, MouseArea, PanelEvent, PopupRequest,
+};
 use cosmic_protocols::{
     toplevel_info::v1::client::zcosmic_toplevel_handle_v1::{State, ZcosmicToplevelHandleV1},
     workspace::v1::client::zcosmic_workspace_handle_v1::ZcosmicWorkspaceHandleV1,
@@ -63,15 +66,37 @@ use futures::future::pending;
 use iced::{widget::container, Alignment, Background, Length};
 use itertools::Itertools;
 use rand::{thread_rng, Rng};
-use std::{collections::HashMap, fs, path::PathBuf, rc::Rc, str::FromStr, time::Duration};
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    rc::Rc,
+    str::FromStr,
+    time::{Duration, Instant},
+};
 use switcheroo_control::Gpu;
 use tokio::time::sleep;
 use url::Url;
 
 static MIME_TYPE: &str = "text/uri-list";
 
+/// Id of the overflow button, among those of the dock items.
+const OVERFLOW_ID: DockItemId = DockItemId::MAX;
+/// How many of the most launched apps the overflow popup lists.
+const FREQUENT_APPS: usize = 5;
+/// Windows added this soon after connecting were already open, rather than
+/// launched.
+const STARTUP_GRACE: Duration = Duration::from_secs(2);
+/// The most characters of a window's title shown while hovering its app,
+/// longer titles scroll.
+const TITLE_CHARS: usize = 40;
+/// Between the end of a scrolling title and its start.
+const MARQUEE_GAP: &str = "   ·   ";
+/// How often a scrolling title moves by a character.
+const MARQUEE_INTERVAL: Duration = Duration::from_millis(200);
+
 pub fn run() -> cosmic::iced::Result {
-    cosmic::applet::run::<CosmicAppList>(true, ())
+    cosmic_applet_helper::run_applet::<CosmicAppList>()
 }
 
 #[derive(Debug, Clone)]
@@ -164,9 +189,7 @@ impl DockItem {
 
         let app_icon = AppletIconData::new(applet);
 
-        let cosmic_icon = IconSource::from_unknown(desktop_info.icon().unwrap_or_default())
-            .as_cosmic_icon()
-            .size(app_icon.icon_size);
+        let cosmic_icon = app_icon_source(desktop_info, app_icon.icon_size);
 
         let dots = if toplevels.is_empty() {
             (0..1)
@@ -251,7 +274,7 @@ impl DockItem {
             .style(app_list_icon_style(is_focused));
 
         let icon_button: Element<_> = if interaction_enabled {
-            mouse_area(
+            let mouse_area = MouseArea::new(
                 icon_button
                     .on_press_maybe(if toplevels.is_empty() {
                         launch_on_preferred_gpu(desktop_info, gpus)
@@ -266,8 +289,16 @@ impl DockItem {
             .on_right_release(Message::Popup(*id))
             .on_middle_release({
                 launch_on_preferred_gpu(desktop_info, gpus).unwrap_or_else(|| Message::Popup(*id))
-            })
-            .into()
+            });
+            // Grouped windows show the focused one's title while hovered
+            if toplevels.len() > 1 {
+                mouse_area
+                    .on_mouse_enter(Message::Hovered(*id))
+                    .on_mouse_exit(Message::Unhovered(*id))
+                    .into()
+            } else {
+                mouse_area.into()
+            }
         } else {
             icon_button.into()
         };
@@ -288,6 +319,76 @@ impl DockItem {
             icon_button.into()
         }
     }
+
+    /// A button for each of the item's windows, with the app's icon and the
+    /// window's title, for the taskbar mode.
+    fn as_window_buttons(
+        &self,
+        applet: &Context,
+        rectangle_tracker: Option<&RectangleTracker<u32>>,
+        interaction_enabled: bool,
+        gpus: Option<&[Gpu]>,
+        focused: &[ZcosmicToplevelHandleV1],
+        max_width: f32,
+    ) -> Vec<Element<'_, Message>> {
+        let Self {
+            toplevels,
+            desktop_info,
+            id,
+            ..
+        } = self;
+
+        let app_icon = AppletIconData::new(applet);
+        let padding = applet.suggested_padding(false);
+        // Titles would wrap rather than be cut off, so they're shortened to
+        // about what fits next to the icon
+        let max_chars = ((max_width - app_icon.icon_size as f32 - 8.0 - 2.0 * padding as f32) / 8.0)
+            .max(4.0) as usize;
+
+        toplevels
+            .iter()
+            .enumerate()
+            .map(|(i, (handle, info, _))| {
+                let is_focused = focused.contains(handle);
+                let title = if info.title.chars().count() > max_chars {
+                    format!("{:.1$}...", &info.title, max_chars - 2)
+                } else {
+                    info.title.clone()
+                };
+
+                let window_button = cosmic::widget::button(
+                    row![
+                        app_icon_source(desktop_info, app_icon.icon_size),
+                        text::body(title),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .padding([padding, padding * 2])
+                .selected(is_focused)
+                .style(app_list_icon_style(is_focused));
+
+                let window_button: Element<_> = if interaction_enabled {
+                    mouse_area(window_button.on_press(Message::Toggle(handle.clone())))
+                        .on_right_release(Message::Popup(*id))
+                        .on_middle_release({
+                            launch_on_preferred_gpu(desktop_info, gpus)
+                                .unwrap_or_else(|| Message::Popup(*id))
+                        })
+                        .into()
+                } else {
+                    window_button.into()
+                };
+                let window_button = container(window_button).max_width(max_width);
+
+                // The item's menu opens at its first window
+                match rectangle_tracker {
+                    Some(tracker) if i == 0 => tracker.container(*id, window_button).into(),
+                    _ => window_button.into(),
+                }
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -298,8 +399,8 @@ struct DndOffer {
 
 #[derive(Clone, Default)]
 struct CosmicAppList {
-    core: cosmic::app::Core,
-    popup: Option<(window::Id, DockItemId, PopupType)>,
+    /// What the popup shows, if it's open.
+    popup: Option<(DockItemId, PopupType)>,
     subscription_ctr: u32,
     item_ctr: u32,
     active_list: Vec<DockItem>,
@@ -308,6 +409,8 @@ struct CosmicAppList {
     config: AppListConfig,
     wayland_sender: Option<Sender<WaylandRequest>>,
     seat: Option<WlSeat>,
+    /// The applet's surface, which toplevel rectangles are relative to.
+    surface: Option<WlSurface>,
     rectangle_tracker: Option<RectangleTracker<DockItemId>>,
     rectangles: HashMap<DockItemId, iced::Rectangle>,
     dnd_offer: Option<DndOffer>,
@@ -316,12 +419,22 @@ struct CosmicAppList {
     active_workspaces: Vec<ZcosmicWorkspaceHandleV1>,
     output_list: HashMap<WlOutput, OutputInfo>,
     locales: Vec<String>,
+    connected_at: Option<Instant>,
+    /// Apps for the overflow popup, loaded when it opens.
+    frequent: Vec<DesktopEntry<'static>>,
+    /// How many characters the hovered window's title has scrolled by.
+    title_offset: usize,
+    /// Whether the preview asks to confirm closing all of the app's windows.
+    confirm_close_all: bool,
 }
 
 #[derive(Clone, PartialEq)]
 pub enum PopupType {
     RightClickMenu,
     TopLevelList,
+    Overflow,
+    /// The title of the focused window of grouped ones, while they're hovered.
+    Title,
 }
 
 // TODO DnD after sctk merges DnD
@@ -332,16 +445,22 @@ enum Message {
     UnpinApp(DockItemId),
     Popup(DockItemId),
     TopLevelListPopup(DockItemId),
+    OverflowPopup,
     GpuRequest(Option<Vec<Gpu>>),
-    CloseRequested(window::Id),
     ClosePopup,
     Activate(ZcosmicToplevelHandleV1),
     Toggle(ZcosmicToplevelHandleV1),
+    CloseWindow(ZcosmicToplevelHandleV1),
+    /// Closes the app's windows, once confirmed if there are many.
+    CloseAll(DockItemId),
+    ConfirmCloseAll(DockItemId),
+    CancelCloseAll,
     Exec(String, Option<usize>),
     Quit(String),
     Ignore,
     NewSeat(WlSeat),
     RemovedSeat(WlSeat),
+    Surface(WlSurface),
     Rectangle(RectangleUpdate<DockItemId>),
     StartDrag(DockItemId),
     DragFinished,
@@ -354,6 +473,9 @@ enum Message {
     StopListeningForDnd,
     IncrementSubscriptionCtr,
     ConfigUpdated(AppListConfig),
+    Hovered(DockItemId),
+    Unhovered(DockItemId),
+    MarqueeTick,
 }
 
 fn index_in_list(
@@ -395,6 +517,22 @@ fn index_in_list(
     }
 }
 
+/// The part of `title` shown at once, scrolled by `offset` characters if it's
+/// too long.
+fn marquee(title: &str, offset: usize) -> String {
+    if title.chars().count() <= TITLE_CHARS {
+        return title.to_string();
+    }
+    let cycle = format!("{title}{MARQUEE_GAP}");
+    let len = cycle.chars().count();
+    cycle
+        .chars()
+        .cycle()
+        .skip(offset % len)
+        .take(TITLE_CHARS)
+        .collect()
+}
+
 async fn try_get_gpus() -> Option<Vec<Gpu>> {
     let connection = zbus::Connection::system().await.ok()?;
     let proxy = switcheroo_control::SwitcherooControlProxy::new(&connection)
@@ -419,6 +557,7 @@ const TOPLEVEL_BUTTON_HEIGHT: f32 = 130.0;
 pub fn toplevel_button<'a, Msg>(
     img: Option<WaylandImage>,
     on_press: Msg,
+    on_close: Msg,
     title: String,
     is_focused: bool,
 ) -> cosmic::widget::Button<'a, Msg>
@@ -431,14 +570,10 @@ where
             column![
                 container(if let Some(img) = img {
                     Element::from(
-                        Image::new(Handle::from_pixels(
-                            img.img.width(),
-                            img.img.height(),
-                            img.clone(),
-                        ))
-                        .width(Length::Fill)
-                        .height(Length::Fill)
-                        .content_fit(cosmic::iced_core::ContentFit::Contain),
+                        Image::new(img.thumbnail.handle)
+                            .width(Length::Fill)
+                            .height(Length::Fill)
+                            .content_fit(cosmic::iced_core::ContentFit::Contain),
                     )
                 } else {
                     Image::new(Handle::from_pixels(1, 1, vec![0, 0, 0, 255])).into()
@@ -456,9 +591,17 @@ where
                 .padding(border as u16)
                 .height(Length::Fill)
                 .width(Length::Fill),
-                container(text::body(title).horizontal_alignment(Horizontal::Center),)
-                    .width(Length::Fill)
-                    .center_x(),
+                row![
+                    text::body(title)
+                        .horizontal_alignment(Horizontal::Center)
+                        .width(Length::Fill),
+                    button::icon(from_name("window-close-symbolic").size(16))
+                        .extra_small()
+                        .on_press(on_close),
+                ]
+                .spacing(4)
+                .align_items(Alignment::Center)
+                .width(Length::Fill),
             ]
             .spacing(4)
             .align_items(Alignment::Center),
@@ -528,6 +671,15 @@ fn window_menu_style(selected: bool) -> cosmic::theme::Button {
     }
 }
 
+/// The app's icon, with named icons looked up once rather than every frame.
+fn app_icon_source(desktop_info: &DesktopEntry<'_>, size: u16) -> icon::Icon {
+    match IconSource::from_unknown(desktop_info.icon().unwrap_or_default()) {
+        IconSource::Name(name) => icon::icon(icon_handle(&name, size, false)),
+        source => source.as_cosmic_icon(),
+    }
+    .size(size)
+}
+
 fn app_list_icon_style(selected: bool) -> cosmic::theme::Button {
     Button::Custom {
         active: Box::new(move |focused, theme| {
@@ -586,16 +738,11 @@ pub fn menu_control_padding() -> Padding {
     [cosmic.space_xxs(), cosmic.space_m()].into()
 }
 
-impl cosmic::Application for CosmicAppList {
+impl CosmicApplet for CosmicAppList {
     type Message = Message;
-    type Executor = cosmic::SingleThreadExecutor;
-    type Flags = ();
     const APP_ID: &'static str = config::APP_ID;
 
-    fn init(
-        core: cosmic::app::Core,
-        _flags: Self::Flags,
-    ) -> (Self, iced::Command<cosmic::app::Message<Self::Message>>) {
+    fn init(_core: &Core) -> (Self, Command<Message>) {
         let config = Config::new(APP_ID, AppListConfig::VERSION)
             .ok()
             .and_then(|c| AppListConfig::get_entry(&c).ok())
@@ -604,7 +751,6 @@ impl cosmic::Application for CosmicAppList {
         let locales = get_languages_from_env();
 
         let mut app_list = Self {
-            core,
             pinned_list: load_desktop_entries_from_app_ids(&config.favorites, &locales)
                 .into_iter()
                 .zip(&config.favorites)
@@ -624,134 +770,167 @@ impl cosmic::Application for CosmicAppList {
 
         (
             app_list,
-            Command::perform(try_get_gpus(), |gpus| {
-                cosmic::app::Message::App(Message::GpuRequest(gpus))
-            }),
+            Command::perform(try_get_gpus(), Message::GpuRequest),
         )
     }
 
-    fn core(&self) -> &cosmic::app::Core {
-        &self.core
-    }
-
-    fn core_mut(&mut self) -> &mut cosmic::app::Core {
-        &mut self.core
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        if event == PanelEvent::PopupClosed {
+            self.popup = None;
+        }
+        Command::none()
     }
 
-    fn update(
-        &mut self,
-        message: Self::Message,
-    ) -> iced::Command<cosmic::app::Message<Self::Message>> {
+    fn popup_request(&self, message: &Message) -> Option<PopupRequest> {
         match message {
-            Message::Popup(id) => {
-                if let Some((popup_id, _toplevel, _)) = self.popup.take() {
-                    return destroy_popup(popup_id);
-                }
-                if let Some(toplevel_group) = self
+            Message::Popup(id) | Message::TopLevelListPopup(id) => self.toggle_request(*id),
+            Message::OverflowPopup => self.toggle_request(OVERFLOW_ID),
+            Message::StartDrag(_) => {
+                matches!(self.popup, Some((_, PopupType::Title))).then_some(PopupRequest::Close)
+            }
+            Message::Hovered(id) => (self.popup.is_none()
+                && self.dnd_source.is_none()
+                && self.rectangles.contains_key(id))
+            .then_some(PopupRequest::Open),
+            Message::Unhovered(id) => {
+                matches!(self.popup, Some((item, PopupType::Title)) if item == *id)
+                    .then_some(PopupRequest::Close)
+            }
+            Message::CloseAll(id) => (!self.confirms_close_all(*id)).then_some(PopupRequest::Close),
+            Message::PinApp(_)
+            | Message::UnpinApp(_)
+            | Message::Activate(_)
+            | Message::Toggle(_)
+            | Message::ConfirmCloseAll(_)
+            | Message::Quit(_)
+            | Message::ClosePopup => Some(PopupRequest::Close),
+            // The preview goes away with the app's last window
+            Message::Wayland(WaylandUpdate::Toplevel(ToplevelUpdate::Remove(handle))) => {
+                let Some((id, PopupType::TopLevelList)) = self.popup else {
+                    return None;
+                };
+                let has_windows = self
                     .active_list
                     .iter()
                     .chain(self.pinned_list.iter())
-                    .find(|t| t.id == id)
-                {
-                    let rectangle = match self.rectangles.get(&toplevel_group.id) {
-                        Some(r) => r,
-                        None => return Command::none(),
-                    };
+                    .any(|t| t.id == id && t.toplevels.iter().any(|(h, _, _)| h != handle));
+                (!has_windows).then_some(PopupRequest::Close)
+            }
+            _ => None,
+        }
+    }
+
+    fn popup_anchor(&self, _core: &Core) -> Option<iced::Rectangle<i32>> {
+        let (id, _) = self.popup.as_ref()?;
+        let iced::Rectangle {
+            x,
+            y,
+            width,
+            height,
+        } = *self.rectangles.get(id)?;
+        Some(iced::Rectangle::<i32> {
+            x: x as i32,
+            y: y as i32,
+            width: width as i32,
+            height: height as i32,
+        })
+    }
 
-                    let new_id = window::Id::unique();
-                    self.popup = Some((new_id, toplevel_group.id, PopupType::RightClickMenu));
+    fn popup_limits(&self, core: &Core) -> Limits {
+        match self.popup {
+            Some((_, PopupType::TopLevelList)) => {
+                let max_windows = 7.0;
+                let window_spacing = 8.0;
+                match core.applet.anchor {
+                    PanelAnchor::Right | PanelAnchor::Left => Limits::NONE
+                        .min_width(100.0)
+                        .min_height(30.0)
+                        .max_width(window_spacing * 2.0 + TOPLEVEL_BUTTON_WIDTH)
+                        .max_height(
+                            TOPLEVEL_BUTTON_HEIGHT * max_windows
+                                + window_spacing * (max_windows + 1.0),
+                        ),
+                    PanelAnchor::Bottom | PanelAnchor::Top => Limits::NONE
+                        .min_width(30.0)
+                        .min_height(100.0)
+                        .max_width(
+                            TOPLEVEL_BUTTON_WIDTH * max_windows
+                                + window_spacing * (max_windows + 1.0),
+                        )
+                        .max_height(window_spacing * 2.0 + TOPLEVEL_BUTTON_HEIGHT),
+                }
+            }
+            Some((_, PopupType::Title)) => {
+                Limits::NONE.min_width(1.0).min_height(1.0).max_width(400.0)
+            }
+            _ => self.popup_width().limits(),
+        }
+    }
 
-                    let mut popup_settings = self.core.applet.get_popup_settings(
-                        window::Id::MAIN,
-                        new_id,
-                        None,
-                        None,
-                        None,
-                    );
-                    let iced::Rectangle {
-                        x,
-                        y,
-                        width,
-                        height,
-                    } = *rectangle;
-                    popup_settings.positioner.anchor_rect = iced::Rectangle::<i32> {
-                        x: x as i32,
-                        y: y as i32,
-                        width: width as i32,
-                        height: height as i32,
-                    };
+    fn popup_grab(&self) -> bool {
+        // Like a tooltip, the title doesn't take the keyboard or pointer
+        !matches!(self.popup, Some((_, PopupType::Title)))
+    }
 
-                    let gpu_update = Command::perform(try_get_gpus(), |gpus| {
-                        cosmic::app::Message::App(Message::GpuRequest(gpus))
-                    });
-                    return Command::batch([gpu_update, get_popup(popup_settings)]);
+    fn update(&mut self, core: &Core, message: Message) -> Command<Message> {
+        // The title shown while hovering makes way for other popups
+        if matches!(
+            message,
+            Message::Popup(_)
+                | Message::TopLevelListPopup(_)
+                | Message::OverflowPopup
+                | Message::StartDrag(_)
+        ) && matches!(self.popup, Some((_, PopupType::Title)))
+        {
+            self.popup = None;
+        }
+
+        // Closing the popup is asked for by `popup_request`
+        match message {
+            Message::Popup(id) => {
+                if self.popup.is_none() && self.can_show(id) {
+                    self.popup = Some((id, PopupType::RightClickMenu));
+                    return Command::perform(try_get_gpus(), Message::GpuRequest);
                 }
             }
             Message::TopLevelListPopup(id) => {
-                if let Some((popup_id, _toplevel, _)) = self.popup.take() {
-                    return destroy_popup(popup_id);
-                }
-                if let Some(toplevel_group) = self
-                    .active_list
-                    .iter()
-                    .chain(self.pinned_list.iter())
-                    .find(|t| t.id == id)
-                {
-                    for (ref handle, _, _) in &toplevel_group.toplevels {
-                        if let Some(tx) = self.wayland_sender.as_ref() {
-                            let _ = tx.send(WaylandRequest::Screencopy(handle.clone()));
+                if self.popup.is_none() && self.can_show(id) {
+                    if let Some(toplevel_group) = self
+                        .active_list
+                        .iter()
+                        .chain(self.pinned_list.iter())
+                        .find(|t| t.id == id)
+                    {
+                        for (ref handle, _, _) in &toplevel_group.toplevels {
+                            if let Some(tx) = self.wayland_sender.as_ref() {
+                                let _ = tx.send(WaylandRequest::Screencopy(handle.clone()));
+                            }
                         }
                     }
-
-                    let rectangle = match self.rectangles.get(&toplevel_group.id) {
-                        Some(r) => r,
-                        None => return Command::none(),
-                    };
-
-                    let new_id = window::Id::unique();
-                    self.popup = Some((new_id, toplevel_group.id, PopupType::TopLevelList));
-
-                    let mut popup_settings = self.core.applet.get_popup_settings(
-                        window::Id::MAIN,
-                        new_id,
-                        None,
-                        None,
-                        None,
-                    );
-                    let iced::Rectangle {
-                        x,
-                        y,
-                        width,
-                        height,
-                    } = *rectangle;
-                    popup_settings.positioner.anchor_rect = iced::Rectangle::<i32> {
-                        x: x as i32,
-                        y: y as i32,
-                        width: width as i32,
-                        height: height as i32,
-                    };
-                    let max_windows = 7.0;
-                    let window_spacing = 8.0;
-                    popup_settings.positioner.size_limits = match self.core.applet.anchor {
-                        PanelAnchor::Right | PanelAnchor::Left => Limits::NONE
-                            .min_width(100.0)
-                            .min_height(30.0)
-                            .max_width(window_spacing * 2.0 + TOPLEVEL_BUTTON_WIDTH)
-                            .max_height(
-                                TOPLEVEL_BUTTON_HEIGHT * max_windows
-                                    + window_spacing * (max_windows + 1.0),
-                            ),
-                        PanelAnchor::Bottom | PanelAnchor::Top => Limits::NONE
-                            .min_width(30.0)
-                            .min_height(100.0)
-                            .max_width(
-                                TOPLEVEL_BUTTON_WIDTH * max_windows
-                                    + window_spacing * (max_windows + 1.0),
-                            )
-                            .max_height(window_spacing * 2.0 + TOPLEVEL_BUTTON_HEIGHT),
-                    };
-
-                    return get_popup(popup_settings);
+                    self.popup = Some((id, PopupType::TopLevelList));
+                    self.confirm_close_all = false;
+                }
+            }
+            Message::OverflowPopup => {
+                if self.popup.is_none() && self.can_show(OVERFLOW_ID) {
+                    let ids = self
+                        .config
+                        .frequent()
+                        .filter(|id| {
+                            !self
+                                .pinned_list
+                                .iter()
+                                .chain(self.active_list.iter())
+                                .any(|item| item.original_app_id == *id)
+                        })
+                        .take(FREQUENT_APPS)
+                        .collect_vec();
+                    self.frequent = load_desktop_entries_from_app_ids(&ids, &self.locales)
+                        .into_iter()
+                        .filter(|entry| entry.exec().is_some())
+                        .collect();
+                    self.popup = Some((OVERFLOW_ID, PopupType::Overflow));
+                    return Command::perform(try_get_gpus(), Message::GpuRequest);
                 }
             }
 
@@ -764,9 +943,6 @@ impl cosmic::Application for CosmicAppList {
                     );
                     self.pinned_list.push(entry);
                 }
-                if let Some((popup_id, _toplevel, _)) = self.popup.take() {
-                    return destroy_popup(popup_id);
-                }
             }
             Message::UnpinApp(id) => {
                 if let Some(i) = self.pinned_list.iter().position(|t| t.id == id) {
@@ -782,32 +958,55 @@ impl cosmic::Application for CosmicAppList {
                         self.active_list.push(entry);
                     }
                 }
-                if let Some((popup_id, _toplevel, _)) = self.popup.take() {
-                    return destroy_popup(popup_id);
-                }
             }
             Message::Activate(handle) => {
                 if let Some(tx) = self.wayland_sender.as_ref() {
                     let _ = tx.send(WaylandRequest::Toplevel(ToplevelRequest::Activate(handle)));
                 }
-                if let Some(p) = self.popup.take() {
-                    return destroy_popup(p.0);
-                }
             }
             Message::Toggle(handle) => {
                 if let Some(tx) = self.wayland_sender.as_ref() {
                     let _ = tx.send(WaylandRequest::Toplevel(
-                        if self.currently_active_toplevel().contains(&handle) {
+                        if self.currently_active_toplevel(core).contains(&handle) {
                             ToplevelRequest::Minimize(handle)
                         } else {
                             ToplevelRequest::Activate(handle)
                         },
                     ));
                 }
-                if let Some(p) = self.popup.take() {
-                    return destroy_popup(p.0);
+            }
+            Message::CloseWindow(handle) => {
+                if let Some(tx) = self.wayland_sender.as_ref() {
+                    let _ = tx.send(WaylandRequest::Toplevel(ToplevelRequest::Quit(handle)));
+                }
+            }
+            Message::CloseAll(id) => {
+                if self.confirms_close_all(id) {
+                    self.confirm_close_all = true;
+                } else {
+                    return self.update(core, Message::ConfirmCloseAll(id));
+                }
+            }
+            Message::ConfirmCloseAll(id) => {
+                self.confirm_close_all = false;
+                if let Some(toplevel_group) = self
+                    .active_list
+                    .iter()
+                    .chain(self.pinned_list.iter())
+                    .find(|t| t.id == id)
+                {
+                    for (handle, _, _) in &toplevel_group.toplevels {
+                        if let Some(tx) = self.wayland_sender.as_ref() {
+                            let _ = tx.send(WaylandRequest::Toplevel(ToplevelRequest::Quit(
+                                handle.clone(),
+                            )));
+                        }
+                    }
                 }
             }
+            Message::CancelCloseAll => {
+                self.confirm_close_all = false;
+            }
             Message::Quit(id) => {
                 if let Some(toplevel_group) = self
                     .active_list
@@ -823,9 +1022,6 @@ impl cosmic::Application for CosmicAppList {
                         }
                     }
                 }
-                if let Some((popup_id, _toplevel, _)) = self.popup.take() {
-                    return destroy_popup(popup_id);
-                }
             }
             Message::StartDrag(id) => {
                 if let Some((is_pinned, toplevel_group)) = self
@@ -882,8 +1078,8 @@ impl cosmic::Application for CosmicAppList {
                 }
             }
             Message::DndEnter(x, y) => {
-                let item_size = self.core.applet.suggested_size(false).0;
-                let pos_in_list = match self.core.applet.anchor {
+                let item_size = core.applet.suggested_size(false).0;
+                let pos_in_list = match core.applet.anchor {
                     PanelAnchor::Top | PanelAnchor::Bottom => x,
                     PanelAnchor::Left | PanelAnchor::Right => y,
                 };
@@ -913,8 +1109,8 @@ impl cosmic::Application for CosmicAppList {
             }
             Message::DndMotion(x, y) => {
                 if let Some(DndOffer { preview_index, .. }) = self.dnd_offer.as_mut() {
-                    let item_size = self.core.applet.suggested_size(false).0;
-                    let pos_in_list = match self.core.applet.anchor {
+                    let item_size = core.applet.suggested_size(false).0;
+                    let pos_in_list = match core.applet.anchor {
                         PanelAnchor::Top | PanelAnchor::Bottom => x,
                         PanelAnchor::Left | PanelAnchor::Right => y,
                     };
@@ -995,6 +1191,7 @@ impl cosmic::Application for CosmicAppList {
                 match event {
                     WaylandUpdate::Init(tx) => {
                         self.wayland_sender.replace(tx);
+                        self.connected_at = Some(Instant::now());
                     }
                     WaylandUpdate::Image(handle, img) => {
                         'img_update: for x in self
@@ -1032,8 +1229,7 @@ impl cosmic::Application for CosmicAppList {
                                 }
                             },
                             |_| Message::IncrementSubscriptionCtr,
-                        )
-                        .map(cosmic::app::message::app);
+                        );
                     }
                     WaylandUpdate::Toplevel(event) => match event {
                         ToplevelUpdate::Add(handle, mut info) => {
@@ -1041,6 +1237,9 @@ impl cosmic::Application for CosmicAppList {
                                 load_desktop_entries_from_app_ids(&[&info.app_id], &self.locales)
                                     .remove(0);
 
+                            let launched = self
+                                .connected_at
+                                .is_some_and(|at| at.elapsed() > STARTUP_GRACE);
                             if let Some(t) = self
                                 .active_list
                                 .iter_mut()
@@ -1049,10 +1248,24 @@ impl cosmic::Application for CosmicAppList {
                                     desktop_info.id() == new_desktop_info.id()
                                 })
                             {
+                                // A pinned app's first window
+                                if launched && t.toplevels.is_empty() {
+                                    self.config.record_launch(
+                                        &t.original_app_id,
+                                        &Config::new(APP_ID, AppListConfig::VERSION).unwrap(),
+                                    );
+                                }
                                 t.toplevels.push((handle, info, None));
+                                let id = t.id;
+                                self.set_rectangles(id);
                             } else {
                                 if info.app_id.is_empty() {
                                     info.app_id = format!("Unknown Application {}", self.item_ctr);
+                                } else if launched {
+                                    self.config.record_launch(
+                                        &info.app_id,
+                                        &Config::new(APP_ID, AppListConfig::VERSION).unwrap(),
+                                    );
                                 }
                                 self.item_ctr += 1;
 
@@ -1135,6 +1348,9 @@ impl cosmic::Application for CosmicAppList {
             Message::RemovedSeat(_) => {
                 self.seat.take();
             }
+            Message::Surface(surface) => {
+                self.surface.get_or_insert(surface);
+            }
             Message::Exec(exec, gpu_idx) => {
                 if let Some(tx) = self.wayland_sender.as_ref() {
                     let _ = tx.send(WaylandRequest::TokenRequest {
@@ -1147,17 +1363,13 @@ impl cosmic::Application for CosmicAppList {
             Message::Rectangle(u) => match u {
                 RectangleUpdate::Rectangle(r) => {
                     self.rectangles.insert(r.0, r.1);
+                    self.set_rectangles(r.0);
                 }
                 RectangleUpdate::Init(tracker) => {
                     self.rectangle_tracker.replace(tracker);
                 }
             },
-            Message::Ignore => {}
-            Message::ClosePopup => {
-                if let Some(p) = self.popup.take() {
-                    return destroy_popup(p.0);
-                }
-            }
+            Message::Ignore | Message::ClosePopup => {}
             Message::StartListeningForDnd => {
                 self.is_listening_for_dnd = true;
             }
@@ -1167,8 +1379,26 @@ impl cosmic::Application for CosmicAppList {
             Message::IncrementSubscriptionCtr => {
                 self.subscription_ctr += 1;
             }
+            Message::Hovered(id) => {
+                if self.popup.is_none()
+                    && self.dnd_source.is_none()
+                    && self.rectangles.contains_key(&id)
+                {
+                    self.popup = Some((id, PopupType::Title));
+                    self.title_offset = 0;
+                }
+            }
+            Message::Unhovered(_) => {}
+            Message::MarqueeTick => {
+                self.title_offset = self.title_offset.wrapping_add(1);
+            }
             Message::ConfigUpdated(config) => {
+                // Launches are recorded in the config too
+                let favorites_changed = self.config.favorites != config.favorites;
                 self.config = config;
+                if !favorites_changed {
+                    return Command::none();
+                }
                 // drain to active list
                 for item in self.pinned_list.drain(..) {
                     if !item.toplevels.is_empty() {
@@ -1204,11 +1434,6 @@ impl cosmic::Application for CosmicAppList {
                         })
                         .collect();
             }
-            Message::CloseRequested(id) => {
-                if Some(id) == self.popup.as_ref().map(|p| p.0) {
-                    self.popup = None;
-                }
-            }
             Message::GpuRequest(gpus) => {
                 self.gpus = gpus;
             }
@@ -1217,12 +1442,12 @@ impl cosmic::Application for CosmicAppList {
         Command::none()
     }
 
-    fn view(&self) -> Element<Message> {
-        let focused_item = self.currently_active_toplevel();
-        let theme = self.core.system_theme();
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let focused_item = self.currently_active_toplevel(core);
+        let theme = core.system_theme();
         let dot_radius = theme.cosmic().radius_xs();
-        let app_icon = AppletIconData::new(&self.core.applet);
-        let is_horizontal = match self.core.applet.anchor {
+        let app_icon = AppletIconData::new(&core.applet);
+        let is_horizontal = match core.applet.anchor {
             PanelAnchor::Top | PanelAnchor::Bottom => true,
             PanelAnchor::Left | PanelAnchor::Right => false,
         };
@@ -1231,7 +1456,7 @@ impl cosmic::Application for CosmicAppList {
             .iter()
             .map(|dock_item| {
                 dock_item.as_icon(
-                    &self.core.applet,
+                    &core.applet,
                     self.rectangle_tracker.as_ref(),
                     self.popup.is_none(),
                     self.config.enable_drag_source,
@@ -1253,7 +1478,7 @@ impl cosmic::Application for CosmicAppList {
             favorites.insert(
                 index,
                 item.as_icon(
-                    &self.core.applet,
+                    &core.applet,
                     None,
                     false,
                     self.config.enable_drag_source,
@@ -1267,31 +1492,58 @@ impl cosmic::Application for CosmicAppList {
             favorites.push(
                 container(
                     cosmic::widget::icon::from_name("starred-symbolic.symbolic")
-                        .size(self.core.applet.suggested_size(false).0),
+                        .size(core.applet.suggested_size(false).0),
                 )
-                .padding(self.core.applet.suggested_padding(false))
+                .padding(core.applet.suggested_padding(false))
                 .into(),
             );
         }
 
-        let active: Vec<_> = self
-            .active_list
-            .iter()
-            .map(|dock_item| {
-                dock_item.as_icon(
-                    &self.core.applet,
-                    self.rectangle_tracker.as_ref(),
-                    self.popup.is_none(),
-                    self.config.enable_drag_source,
-                    self.gpus.as_deref(),
-                    dock_item
-                        .toplevels
-                        .iter()
-                        .any(|y| focused_item.contains(&y.0)),
-                    dot_radius,
-                )
-            })
-            .collect();
+        let active: Vec<_> = if self.config.taskbar_mode && is_horizontal {
+            self.pinned_list
+                .iter()
+                .chain(self.active_list.iter())
+                .flat_map(|dock_item| {
+                    dock_item.as_window_buttons(
+                        &core.applet,
+                        // Pinned items are tracked by their icon
+                        self.rectangle_tracker
+                            .as_ref()
+                            .filter(|_| !self.pinned_list.iter().any(|p| p.id == dock_item.id)),
+                        self.popup.is_none(),
+                        self.gpus.as_deref(),
+                        &focused_item,
+                        self.config.taskbar_max_width as f32,
+                    )
+                })
+                .collect()
+        } else {
+            let capacity = self.active_capacity(core);
+            let mut active: Vec<_> = self
+                .active_list
+                .iter()
+                .take(capacity)
+                .map(|dock_item| {
+                    dock_item.as_icon(
+                        &core.applet,
+                        self.rectangle_tracker.as_ref(),
+                        self.popup.is_none(),
+                        self.config.enable_drag_source,
+                        self.gpus.as_deref(),
+                        dock_item
+                            .toplevels
+                            .iter()
+                            .any(|y| focused_item.contains(&y.0)),
+                        dot_radius,
+                    )
+                })
+                .collect();
+            if capacity < self.active_list.len() {
+                active.push(self.overflow_button(core));
+            }
+            active
+        };
+        let has_active = !active.is_empty();
 
         let (w, h, favorites, active, divider) = if is_horizontal {
             (
@@ -1346,21 +1598,21 @@ impl cosmic::Application for CosmicAppList {
 
         let show_pinned =
             !self.pinned_list.is_empty() || self.dnd_offer.is_some() || self.is_listening_for_dnd;
-        let content_list: Vec<Element<_>> = if show_pinned && !self.active_list.is_empty() {
+        let content_list: Vec<Element<_>> = if show_pinned && has_active {
             vec![favorites.into(), divider, active]
         } else if show_pinned {
             vec![favorites.into()]
-        } else if !self.active_list.is_empty() {
+        } else if has_active {
             vec![active]
         } else {
             vec![
                 cosmic::widget::icon::from_name("com.system76.CosmicAppList")
-                    .size(self.core.applet.suggested_size(false).0)
+                    .size(core.applet.suggested_size(false).0)
                     .into(),
             ]
         };
 
-        let mut content = match &self.core.applet.anchor {
+        let mut content = match &core.applet.anchor {
             PanelAnchor::Left | PanelAnchor::Right => container(
                 Column::with_children(content_list)
                     .spacing(4.0)
@@ -1377,27 +1629,40 @@ impl cosmic::Application for CosmicAppList {
             ),
         };
         if self.active_list.is_empty() && self.pinned_list.is_empty() {
-            let suggested_size = self.core.applet.suggested_size(false);
+            let suggested_size = core.applet.suggested_size(false);
             content = content.width(suggested_size.0).height(suggested_size.1);
         }
-        if self.popup.is_some() {
+        let content: Element<_> = if self.popup.is_some() {
             mouse_area(content)
                 .on_right_release(Message::ClosePopup)
                 .on_press(Message::ClosePopup)
                 .into()
         } else {
             content.into()
-        }
+        };
+        content.map(AppletMessage::App)
     }
 
-    fn view_window(&self, id: window::Id) -> Element<Message> {
+    fn surface_view<'a>(
+        &'a self,
+        core: &'a Core,
+        id: window::Id,
+    ) -> Element<'a, AppletMessage<Message>> {
         if let Some((_, item, _)) = self.dnd_source.as_ref().filter(|s| s.0 == id) {
-            IconSource::from_unknown(item.desktop_info.icon().unwrap_or_default())
-                .as_cosmic_icon()
-                .size(self.core.applet.suggested_size(false).0)
+            app_icon_source(&item.desktop_info, core.applet.suggested_size(false).0).into()
+        } else {
+            let suggested = core.applet.suggested_size(false);
+            iced::widget::row!()
+                .width(Length::Fixed(suggested.0 as f32))
+                .height(Length::Fixed(suggested.1 as f32))
                 .into()
-        } else if let Some((_popup_id, id, popup_type)) = self.popup.as_ref().filter(|p| id == p.0)
-        {
+        }
+    }
+
+    fn popup_view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let content: Element<_> = if let Some((_, PopupType::Overflow)) = self.popup.as_ref() {
+            self.overflow_view(core)
+        } else if let Some((id, popup_type)) = self.popup.as_ref() {
             let (
                 DockItem {
                     toplevels,
@@ -1535,61 +1800,88 @@ impl cosmic::Application for CosmicAppList {
                             ),
                         };
                     }
-                    self.core.applet.popup_container(content).into()
+                    content.into()
                 }
-                PopupType::TopLevelList => match self.core.applet.anchor {
-                    PanelAnchor::Left | PanelAnchor::Right => {
-                        let mut content = column![]
-                            .padding(8)
-                            .align_items(Alignment::Center)
-                            .spacing(8);
-                        for (handle, info, img) in toplevels {
-                            let title = if info.title.len() > 18 {
-                                format!("{:.16}...", &info.title)
-                            } else {
-                                info.title.clone()
-                            };
-                            content = content.push(toplevel_button(
-                                img.clone(),
-                                Message::Toggle(handle.clone()),
-                                title,
-                                self.currently_active_toplevel().contains(handle),
-                            ));
+                PopupType::TopLevelList => {
+                    let buttons = toplevels.iter().map(|(handle, info, img)| {
+                        let title = if info.title.len() > 18 {
+                            format!("{:.16}...", &info.title)
+                        } else {
+                            info.title.clone()
+                        };
+                        Element::from(toplevel_button(
+                            img.clone(),
+                            Message::Toggle(handle.clone()),
+                            Message::CloseWindow(handle.clone()),
+                            title,
+                            self.currently_active_toplevel(core).contains(handle),
+                        ))
+                    });
+                    let windows: Element<_> = match core.applet.anchor {
+                        PanelAnchor::Left | PanelAnchor::Right => {
+                            Column::with_children(buttons.collect::<Vec<_>>())
+                                .align_items(Alignment::Center)
+                                .spacing(8)
+                                .into()
                         }
-                        self.core.applet.popup_container(content).into()
-                    }
-                    PanelAnchor::Bottom | PanelAnchor::Top => {
-                        let mut content =
-                            row![].padding(8).align_items(Alignment::Center).spacing(8);
-                        for (handle, info, img) in toplevels {
-                            let title = if info.title.len() > 18 {
-                                format!("{:.16}...", &info.title)
-                            } else {
-                                info.title.clone()
-                            };
-                            content = content.push(toplevel_button(
-                                img.clone(),
-                                Message::Toggle(handle.clone()),
-                                title,
-                                self.currently_active_toplevel().contains(handle),
-                            ));
+                        PanelAnchor::Bottom | PanelAnchor::Top => {
+                            Row::with_children(buttons.collect::<Vec<_>>())
+                                .align_items(Alignment::Center)
+                                .spacing(8)
+                                .into()
                         }
-                        self.core.applet.popup_container(content).into()
-                    }
-                },
+                    };
+                    let actions = if self.confirm_close_all {
+                        row![
+                            text::body(fl!("close-windows", count = toplevels.len()))
+                                .width(Length::Fill),
+                            button::text(fl!("cancel")).on_press(Message::CancelCloseAll),
+                            button::destructive(fl!("close-all"))
+                                .on_press(Message::ConfirmCloseAll(*id)),
+                        ]
+                    } else {
+                        row![
+                            horizontal_space(Length::Fill),
+                            button::text(fl!("close-all")).on_press(Message::CloseAll(*id)),
+                        ]
+                    };
+                    let content =
+                        column![windows, actions.spacing(8).align_items(Alignment::Center)]
+                            .padding(8)
+                            .spacing(8);
+                    content.into()
+                }
+                PopupType::Title => {
+                    let title = self.hovered_title(core).unwrap_or_default();
+                    container(text::body(marquee(title, self.title_offset)))
+                        .padding([4, 8])
+                        .into()
+                }
+                PopupType::Overflow => unreachable!(),
             }
         } else {
-            let suggested = self.core.applet.suggested_size(false);
-            iced::widget::row!()
-                .width(Length::Fixed(suggested.0 as f32))
-                .height(Length::Fixed(suggested.1 as f32))
-                .into()
-        }
+            text::body("").into()
+        };
+        content.map(AppletMessage::App)
     }
 
-    fn subscription(&self) -> Subscription<Message> {
+    fn subscription(&self, core: &Core) -> Subscription<Message> {
         Subscription::batch(vec![
-            wayland_subscription().map(Message::Wayland),
+            // Frames are only listened to until the surface is known, since
+            // each message redraws
+            match self.surface.clone() {
+                Some(surface) => wayland_subscription(surface).map(Message::Wayland),
+                None => listen_with(|e, _| match e {
+                    cosmic::iced_runtime::core::Event::PlatformSpecific(
+                        event::PlatformSpecific::Wayland(event::wayland::Event::Frame(
+                            _,
+                            surface,
+                            id,
+                        )),
+                    ) if id == window::Id::MAIN => Some(Message::Surface(surface)),
+                    _ => None,
+                }),
+            },
             listen_with(|e, _| match e {
                 cosmic::iced_runtime::core::Event::PlatformSpecific(
                     event::PlatformSpecific::Wayland(event::wayland::Event::Seat(e, seat)),
@@ -1625,7 +1917,7 @@ impl cosmic::Application for CosmicAppList {
                 _ => None,
             }),
             rectangle_tracker_subscription(0).map(|update| Message::Rectangle(update.1)),
-            self.core.watch_config(APP_ID).map(|u| {
+            core.watch_config(APP_ID).map(|u| {
                 for why in u.errors {
                     tracing::error!(why = why.to_string(), "Error watching config");
                 }
@@ -1634,21 +1926,207 @@ impl cosmic::Application for CosmicAppList {
         ])
     }
 
-    fn style(&self) -> Option<<Theme as application::StyleSheet>::Style> {
-        Some(cosmic::applet::style())
-    }
-
-    fn on_close_requested(&self, id: window::Id) -> Option<Message> {
-        Some(Message::CloseRequested(id))
+    fn popup_subscription(&self, core: &Core) -> Subscription<Message> {
+        if self
+            .hovered_title(core)
+            .is_some_and(|title| title.chars().count() > TITLE_CHARS)
+        {
+            time::every(MARQUEE_INTERVAL).map(|_| Message::MarqueeTick)
+        } else {
+            Subscription::none()
+        }
     }
 }
 
 impl CosmicAppList {
-    fn currently_active_toplevel(&self) -> Vec<ZcosmicToplevelHandleV1> {
+    /// Tells the compositor where the item's windows are shown, so minimizing
+    /// them animates to it.
+    fn set_rectangles(&self, id: DockItemId) {
+        let (Some(tx), Some(rect)) = (self.wayland_sender.as_ref(), self.rectangles.get(&id))
+        else {
+            return;
+        };
+        let rect = iced::Rectangle::<i32> {
+            x: rect.x as i32,
+            y: rect.y as i32,
+            width: rect.width as i32,
+            height: rect.height as i32,
+        };
+        for item in self
+            .active_list
+            .iter()
+            .chain(self.pinned_list.iter())
+            .filter(|item| item.id == id)
+        {
+            for (handle, _, _) in &item.toplevels {
+                let _ = tx.send(WaylandRequest::Toplevel(ToplevelRequest::SetRectangle(
+                    handle.clone(),
+                    rect,
+                )));
+            }
+        }
+    }
+
+    /// Whether the popup can show the item `id`, which it points at.
+    fn can_show(&self, id: DockItemId) -> bool {
+        self.rectangles.contains_key(&id)
+            && (id == OVERFLOW_ID
+                || self
+                    .active_list
+                    .iter()
+                    .chain(self.pinned_list.iter())
+                    .any(|t| t.id == id))
+    }
+
+    /// Closes the popup, or opens it for the item `id` in place of the title
+    /// shown while hovering.
+    fn toggle_request(&self, id: DockItemId) -> Option<PopupRequest> {
+        match self.popup {
+            None => self.can_show(id).then_some(PopupRequest::Open),
+            Some((_, PopupType::Title)) if self.can_show(id) => Some(PopupRequest::Reopen),
+            Some(_) => Some(PopupRequest::Close),
+        }
+    }
+
+    /// Whether closing all of the app's windows is confirmed first.
+    fn confirms_close_all(&self, id: DockItemId) -> bool {
+        let count = self
+            .active_list
+            .iter()
+            .chain(self.pinned_list.iter())
+            .find(|t| t.id == id)
+            .map_or(0, |t| t.toplevels.len());
+        count > self.config.close_all_confirm as usize
+    }
+
+    /// The title shown while grouped windows are hovered, of the focused one
+    /// or else the first.
+    fn hovered_title(&self, core: &Core) -> Option<&str> {
+        let Some((id, PopupType::Title)) = &self.popup else {
+            return None;
+        };
+        let item = self
+            .active_list
+            .iter()
+            .chain(self.pinned_list.iter())
+            .find(|item| item.id == *id)?;
+        let focused = self.currently_active_toplevel(core);
+        item.toplevels
+            .iter()
+            .find(|(handle, _, _)| focused.contains(handle))
+            .or_else(|| item.toplevels.first())
+            .map(|(_, info, _)| info.title.as_str())
+    }
+
+    /// How many of the running apps fit on the panel, next to the pinned ones
+    /// and the overflow button. The list takes up to half of the output's
+    /// length, leaving the rest to the other applets.
+    fn active_capacity(&self, core: &Core) -> usize {
+        let Some((width, height)) = self
+            .output_list
+            .values()
+            .find(|info| info.name.as_deref() == Some(core.applet.output_name.as_str()))
+            .and_then(|info| info.logical_size)
+        else {
+            return self.active_list.len();
+        };
+        let app_icon = AppletIconData::new(&core.applet);
+        let (length, padding) = match core.applet.anchor {
+            PanelAnchor::Top | PanelAnchor::Bottom => (width, app_icon.padding.horizontal()),
+            PanelAnchor::Left | PanelAnchor::Right => (height, app_icon.padding.vertical()),
+        };
+        let item_length = app_icon.icon_size as f32 + padding + app_icon.icon_spacing;
+        let capacity = (length as f32 / 2.0 / item_length) as usize;
+        if self.pinned_list.len() + self.active_list.len() <= capacity {
+            self.active_list.len()
+        } else {
+            capacity.saturating_sub(self.pinned_list.len() + 1)
+        }
+    }
+
+    fn overflow_button(&self, core: &Core) -> Element<'_, Message> {
+        let app_icon = AppletIconData::new(&core.applet);
+        let is_open = self
+            .popup
+            .as_ref()
+            .is_some_and(|(_, popup_type)| *popup_type == PopupType::Overflow);
+        let overflow_button =
+            cosmic::widget::button(icon::from_name("view-more-symbolic").size(app_icon.icon_size))
+                .padding(app_icon.padding)
+                .selected(is_open)
+                .style(app_list_icon_style(is_open))
+                .on_press_maybe(self.popup.is_none().then_some(Message::OverflowPopup));
+
+        if let Some(tracker) = self.rectangle_tracker.as_ref() {
+            tracker.container(OVERFLOW_ID, overflow_button).into()
+        } else {
+            overflow_button.into()
+        }
+    }
+
+    /// The running apps that didn't fit on the panel, and the apps launched
+    /// most often that aren't on it.
+    fn overflow_view(&self, core: &Core) -> Element<'_, Message> {
+        fn menu_button<'a>(
+            desktop_info: &DesktopEntry<'_>,
+            locales: &[String],
+        ) -> cosmic::widget::Button<'a, Message> {
+            let name = desktop_info
+                .name(locales)
+                .map_or_else(|| desktop_info.id().to_string(), |name| name.to_string());
+            cosmic::widget::button(
+                row![app_icon_source(desktop_info, 24), text::body(name)]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+            )
+            .height(36)
+            .style(Button::AppletMenu)
+            .padding(menu_control_padding())
+            .width(Length::Fill)
+        }
+        let heading = |label: String| {
+            container(text::heading(label))
+                .padding(menu_control_padding())
+                .width(Length::Fill)
+        };
+
+        let mut content = column![].padding([8, 0]);
+        let hidden = &self.active_list[self.active_capacity(core)..];
+        if !hidden.is_empty() {
+            content = content.push(heading(fl!("running")));
+            for item in hidden {
+                content = content.push(
+                    menu_button(&item.desktop_info, &self.locales).on_press_maybe(
+                        match item.toplevels.as_slice() {
+                            [(handle, _, _)] => Some(Message::Toggle(handle.clone())),
+                            toplevels => toplevels
+                                .first()
+                                .map(|(handle, _, _)| Message::Activate(handle.clone())),
+                        },
+                    ),
+                );
+            }
+        }
+        if !self.frequent.is_empty() {
+            if !hidden.is_empty() {
+                content = content.push(container(divider::horizontal::default()).padding([8, 12]));
+            }
+            content = content.push(heading(fl!("frequent")));
+            for desktop_info in &self.frequent {
+                content =
+                    content.push(menu_button(desktop_info, &self.locales).on_press_maybe(
+                        launch_on_preferred_gpu(desktop_info, self.gpus.as_deref()),
+                    ));
+            }
+        }
+        content.into()
+    }
+
+    fn currently_active_toplevel(&self, core: &Core) -> Vec<ZcosmicToplevelHandleV1> {
         if self.active_workspaces.is_empty() {
             return Vec::new();
         }
-        let current_output = self.core.applet.output_name.clone();
+        let current_output = core.applet.output_name.clone();
         let mut focused_toplevels: Vec<ZcosmicToplevelHandleV1> = Vec::new();
         let active_workspaces = self.active_workspaces.clone();
         for toplevel_list in self.active_list.iter().chain(self.pinned_list.iter()) {
@@ -8,6 +8,9 @@ use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 pub const APP_ID: &str = "com.system76.CosmicAppList";
 
+/// How many apps' launches are remembered.
+const LAUNCH_HISTORY_LEN: usize = 50;
+
 #[derive(Debug, Clone, Deserialize, Serialize, Default, PartialEq, Eq)]
 pub enum TopLevelFilter {
     #[default]
@@ -21,6 +24,17 @@ pub struct AppListConfig {
     pub filter_top_levels: Option<TopLevelFilter>,
     pub favorites: Vec<String>,
     pub enable_drag_source: bool,
+    /// Shows a button with the title of each window, rather than one icon
+    /// per app, on horizontal panels. Pinned apps stay as launcher icons.
+    pub taskbar_mode: bool,
+    /// The widest a window's button gets in taskbar mode, in pixels.
+    pub taskbar_max_width: u16,
+    /// How often each app was launched, by app id, the most recently
+    /// launched first. The overflow popup lists the most frequent.
+    pub launch_history: Vec<(String, u32)>,
+    /// Closing more of an app's windows than this at once from its preview
+    /// asks for confirmation first.
+    pub close_all_confirm: u32,
 }
 
 impl Default for AppListConfig {
@@ -29,6 +43,10 @@ impl Default for AppListConfig {
             filter_top_levels: None,
             favorites: Vec::new(),
             enable_drag_source: true,
+            taskbar_mode: false,
+            taskbar_max_width: 200,
+            launch_history: Vec::new(),
+            close_all_confirm: 3,
         }
     }
 }
@@ -52,4 +70,23 @@ impl AppListConfig {
         self.favorites = favorites;
         let _ = self.write_entry(config);
     }
+
+    pub fn record_launch(&mut self, id: &str, config: &Config) {
+        let count = match self.launch_history.iter().position(|(e, _)| e == id) {
+            Some(pos) => self.launch_history.remove(pos).1,
+            None => 0,
+        };
+        self.launch_history
+            .insert(0, (id.to_string(), count.saturating_add(1)));
+        self.launch_history.truncate(LAUNCH_HISTORY_LEN);
+        let _ = self.write_entry(config);
+    }
+
+    /// App ids by how often they were launched, the most recent first among
+    /// those launched as often.
+    pub fn frequent(&self) -> impl Iterator<Item = &str> {
+        let mut history = self.launch_history.iter().collect::<Vec<_>>();
+        history.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        history.into_iter().map(|(id, _)| id.as_str())
+    }
 }
@@ -68,6 +68,8 @@ struct AppData {
     seat_state: SeatState,
     shm_state: Shm,
     activation_state: Option<ActivationState>,
+    /// The applet's surface, when it's on `conn`.
+    applet_surface: Option<WlSurface>,
 }
 
 // Workspace and toplevel handling
@@ -514,22 +516,7 @@ impl AppData {
                 };
 
                 // resize to 128x128
-                let max = img.width().max(img.height());
-                let ratio = max as f32 / 128.0;
-
-                let img = if ratio > 1.0 {
-                    let new_width = (img.width() as f32 / ratio).round();
-                    let new_height = (img.height() as f32 / ratio).round();
-
-                    image::imageops::resize(
-                        &img,
-                        new_width as u32,
-                        new_height as u32,
-                        image::imageops::FilterType::Lanczos3,
-                    )
-                } else {
-                    img
-                };
+                let img = cosmic_applet_helper::fit(img, 128, 128);
 
                 if let Err(err) =
                     tx.unbounded_send(WaylandUpdate::Image(handle, WaylandImage::new(img)))
@@ -604,24 +591,57 @@ impl ScreencopyHandler for AppData {
     }
 }
 
+/// Globals the handler binds, which the connection the applet's surface is on
+/// has to offer for it to be used.
+const TOPLEVEL_GLOBALS: [&str; 4] = [
+    "zcosmic_toplevel_info_v1",
+    "zcosmic_toplevel_manager_v1",
+    "zcosmic_screencopy_manager_v2",
+    "zcosmic_toplevel_image_source_manager_v1",
+];
+
 pub(crate) fn wayland_handler(
     tx: UnboundedSender<WaylandUpdate>,
     rx: calloop::channel::Channel<WaylandRequest>,
+    surface: WlSurface,
 ) {
-    let socket = std::env::var("X_PRIVILEGED_WAYLAND_SOCKET")
-        .ok()
-        .and_then(|fd| {
-            fd.parse::<RawFd>()
-                .ok()
-                .map(|fd| unsafe { UnixStream::from_raw_fd(fd) })
+    // Toplevel rectangles are relative to a surface on the same connection as
+    // the manager, so the applet's own connection is preferred when it has one
+    let surface_conn = surface
+        .backend()
+        .upgrade()
+        .map(Connection::from_backend)
+        .and_then(|conn| {
+            let (globals, event_queue) = registry_queue_init(&conn).ok()?;
+            let offered = globals.contents().with_list(|list| {
+                TOPLEVEL_GLOBALS
+                    .iter()
+                    .all(|name| list.iter().any(|global| global.interface == *name))
+            });
+            offered.then_some((conn, globals, event_queue))
         });
 
-    let conn = if let Some(socket) = socket {
-        Connection::from_socket(socket).unwrap()
-    } else {
-        Connection::connect_to_env().unwrap()
+    let (conn, globals, event_queue, applet_surface) = match surface_conn {
+        Some((conn, globals, event_queue)) => (conn, globals, event_queue, Some(surface)),
+        None => {
+            tracing::debug!("No toplevel manager on the applet's connection");
+            let socket = std::env::var("X_PRIVILEGED_WAYLAND_SOCKET")
+                .ok()
+                .and_then(|fd| {
+                    fd.parse::<RawFd>()
+                        .ok()
+                        .map(|fd| unsafe { UnixStream::from_raw_fd(fd) })
+                });
+
+            let conn = if let Some(socket) = socket {
+                Connection::from_socket(socket).unwrap()
+            } else {
+                Connection::connect_to_env().unwrap()
+            };
+            let (globals, event_queue) = registry_queue_init(&conn).unwrap();
+            (conn, globals, event_queue, None)
+        }
     };
-    let (globals, event_queue) = registry_queue_init(&conn).unwrap();
 
     let mut event_loop = calloop::EventLoop::<AppData>::try_new().unwrap();
     let qh = event_queue.handle();
@@ -652,6 +672,23 @@ pub(crate) fn wayland_handler(
                         let manager = &state.toplevel_manager_state.manager;
                         manager.close(&handle);
                     }
+                    ToplevelRequest::SetRectangle(handle, rect) => {
+                        let manager = &state.toplevel_manager_state.manager;
+                        if let Some(surface) = state.applet_surface.as_ref() {
+                            if manager.version()
+                                >= zcosmic_toplevel_manager_v1::REQ_SET_RECTANGLE_SINCE
+                            {
+                                manager.set_rectangle(
+                                    &handle,
+                                    surface,
+                                    rect.x,
+                                    rect.y,
+                                    rect.width,
+                                    rect.height,
+                                );
+                            }
+                        }
+                    }
                 },
                 WaylandRequest::TokenRequest {
                     app_id,
@@ -708,6 +745,7 @@ pub(crate) fn wayland_handler(
         seat_state: SeatState::new(&globals, &qh),
         shm_state: Shm::bind(&globals, &qh).unwrap(),
         activation_state: ActivationState::bind::<AppData>(&globals, &qh).ok(),
+        applet_surface,
     };
 
     loop {
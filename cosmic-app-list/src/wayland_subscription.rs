@@ -5,21 +5,21 @@
 use cctk::{
     sctk::{output::OutputInfo, reexports::calloop},
     toplevel_info::ToplevelInfo,
-    wayland_client::protocol::wl_output::WlOutput,
+    wayland_client::protocol::{wl_output::WlOutput, wl_surface::WlSurface},
 };
 use cosmic::{iced, iced::subscription};
+use cosmic_applet_helper::Thumbnail;
 use cosmic_protocols::{
     toplevel_info::v1::client::zcosmic_toplevel_handle_v1::ZcosmicToplevelHandleV1,
     workspace::v1::client::zcosmic_workspace_handle_v1::ZcosmicWorkspaceHandleV1,
 };
-use image::EncodableLayout;
 
 use futures::{
     channel::mpsc::{unbounded, UnboundedReceiver},
     SinkExt, StreamExt,
 };
 use once_cell::sync::Lazy;
-use std::{fmt::Debug, sync::Arc};
+use std::fmt::Debug;
 use tokio::sync::Mutex;
 
 use crate::wayland_handler::wayland_handler;
@@ -27,7 +27,8 @@ use crate::wayland_handler::wayland_handler;
 pub static WAYLAND_RX: Lazy<Mutex<Option<UnboundedReceiver<WaylandUpdate>>>> =
     Lazy::new(|| Mutex::new(None));
 
-pub fn wayland_subscription() -> iced::Subscription<WaylandUpdate> {
+/// Toplevel and workspace updates, once the applet's surface is known.
+pub fn wayland_subscription(surface: WlSurface) -> iced::Subscription<WaylandUpdate> {
     subscription::channel(
         std::any::TypeId::of::<WaylandUpdate>(),
         50,
@@ -35,7 +36,7 @@ pub fn wayland_subscription() -> iced::Subscription<WaylandUpdate> {
             let mut state = State::Waiting;
 
             loop {
-                state = start_listening(state, &mut output).await;
+                state = start_listening(state, &mut output, &surface).await;
             }
         },
     )
@@ -46,26 +47,24 @@ pub enum State {
     Finished,
 }
 
+/// A preview of a window, scaled down to the size it's shown at.
 #[derive(Debug, Clone)]
 pub struct WaylandImage {
-    pub img: Arc<image::RgbaImage>,
+    pub thumbnail: Thumbnail,
 }
 
 impl WaylandImage {
     pub fn new(img: image::RgbaImage) -> Self {
-        Self { img: Arc::new(img) }
-    }
-}
-
-impl AsRef<[u8]> for WaylandImage {
-    fn as_ref(&self) -> &[u8] {
-        self.img.as_bytes()
+        Self {
+            thumbnail: Thumbnail::new(img),
+        }
     }
 }
 
 async fn start_listening(
     state: State,
     output: &mut futures::channel::mpsc::Sender<WaylandUpdate>,
+    surface: &WlSurface,
 ) -> State {
     match state {
         State::Waiting => {
@@ -74,8 +73,9 @@ async fn start_listening(
                 if guard.is_none() {
                     let (calloop_tx, calloop_rx) = calloop::channel::channel();
                     let (toplevel_tx, toplevel_rx) = unbounded();
+                    let surface = surface.clone();
                     let _ = std::thread::spawn(move || {
-                        wayland_handler(toplevel_tx, calloop_rx);
+                        wayland_handler(toplevel_tx, calloop_rx, surface);
                     });
                     *guard = Some(toplevel_rx);
                     _ = output.send(WaylandUpdate::Init(calloop_tx)).await;
@@ -143,4 +143,6 @@ pub enum ToplevelRequest {
     Activate(ZcosmicToplevelHandleV1),
     Minimize(ZcosmicToplevelHandleV1),
     Quit(ZcosmicToplevelHandleV1),
+    /// Where the toplevel's icon is on the applet's surface, for minimize animations.
+    SetRectangle(ZcosmicToplevelHandleV1, iced::Rectangle<i32>),
 }
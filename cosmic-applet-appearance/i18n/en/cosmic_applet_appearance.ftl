@@ -0,0 +1,6 @@
+dark-mode = Dark mode
+sunset-to-sunrise = Dark from sunset to sunrise
+locating = Finding your location...
+location-unavailable = Location unavailable
+light-at-sunrise = Switches to light at sunrise
+dark-at-sunset = Switches to dark at sunset
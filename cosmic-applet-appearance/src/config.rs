@@ -0,0 +1,16 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
+pub const APP_ID: &str = "com.system76.CosmicAppletAppearance";
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, CosmicConfigEntry)]
+#[version = 1]
+pub struct AppearanceConfig {
+    /// Switch to the dark theme at sunset and back to light at sunrise.
+    pub sunset_to_sunrise: bool,
+    /// Last known latitude and longitude, used until GeoClue responds.
+    pub location: Option<(f64, f64)>,
+}
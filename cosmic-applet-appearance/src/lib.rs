@@ -0,0 +1,13 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+mod config;
+mod localize;
+mod window;
+
+use window::Window;
+
+pub fn run() -> cosmic::iced::Result {
+    localize::localize();
+    cosmic_applet_helper::run_applet::<Window>()
+}
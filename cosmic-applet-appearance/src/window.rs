@@ -0,0 +1,248 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::time::Duration;
+
+use crate::{
+    config::{self, AppearanceConfig},
+    fl,
+};
+use cosmic::{
+    app::Core,
+    applet::padded_control,
+    cosmic_config::{Config, CosmicConfigEntry},
+    cosmic_theme::{ThemeMode, THEME_MODE_ID},
+    iced::{
+        time,
+        widget::{column, mouse_area},
+        Command, Length, Subscription,
+    },
+    iced_widget::Column,
+    widget::{divider, text, toggler},
+    Element,
+};
+use cosmic_applet_helper::{geoclue, sun, AppletMessage, CosmicApplet};
+
+#[derive(Default)]
+pub struct Window {
+    config: AppearanceConfig,
+    config_helper: Option<Config>,
+    theme_mode: ThemeMode,
+    theme_mode_helper: Option<Config>,
+    /// Whether the sun was down when last checked, so the theme is only
+    /// switched at sunset and sunrise and manual changes stick until then.
+    night: Option<bool>,
+    /// Waiting on GeoClue.
+    locating: bool,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    ConfigChanged(AppearanceConfig),
+    ThemeModeChanged(ThemeMode),
+    Location(Result<(f64, f64), String>),
+    Tick,
+    Dark(bool),
+    ToggleDark,
+    SunsetToSunrise(bool),
+}
+
+impl Window {
+    fn set_dark(&mut self, is_dark: bool) {
+        if let Some(helper) = &self.theme_mode_helper {
+            if let Err(err) = self.theme_mode.set_is_dark(helper, is_dark) {
+                tracing::error!(?err, "Error writing theme mode");
+            }
+        }
+    }
+
+    /// Follows the schedule, if the sun has risen or set since last checked.
+    fn update_night(&mut self) {
+        let (true, Some((latitude, longitude))) =
+            (self.config.sunset_to_sunrise, self.config.location)
+        else {
+            self.night = None;
+            return;
+        };
+        let night = sun::is_night(chrono::Utc::now(), latitude, longitude);
+        if self.night != Some(night) {
+            self.night = Some(night);
+            if self.theme_mode.is_dark != night {
+                self.set_dark(night);
+            }
+        }
+    }
+
+    /// Refreshes the location, which is looked up on startup and when the
+    /// schedule is turned on.
+    fn locate(&mut self) -> Command<Message> {
+        if self.locating || !self.config.sunset_to_sunrise {
+            return Command::none();
+        }
+        self.locating = true;
+        Command::perform(geoclue::location(config::APP_ID), |res| {
+            Message::Location(res.map_err(|err| err.to_string()))
+        })
+    }
+
+    fn status(&self) -> Option<String> {
+        if !self.config.sunset_to_sunrise {
+            return None;
+        }
+        Some(match self.night {
+            None if self.locating => fl!("locating"),
+            None => fl!("location-unavailable"),
+            Some(true) => fl!("light-at-sunrise"),
+            Some(false) => fl!("dark-at-sunset"),
+        })
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = config::APP_ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::APP_ID, AppearanceConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                AppearanceConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let theme_mode_helper = ThemeMode::config().ok();
+        let theme_mode = theme_mode_helper
+            .as_ref()
+            .map(|helper| {
+                ThemeMode::get_entry(helper).unwrap_or_else(|(errs, mode)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading theme mode");
+                    }
+                    mode
+                })
+            })
+            .unwrap_or_default();
+
+        let mut window = Self {
+            config,
+            config_helper,
+            theme_mode,
+            theme_mode_helper,
+            ..Default::default()
+        };
+        window.update_night();
+        let command = window.locate();
+        (window, command)
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        // The applet's own theme follows the mode through libcosmic; this
+        // keeps the toggle in sync when it's changed elsewhere.
+        let mut subscriptions = vec![
+            core.watch_config::<AppearanceConfig>(config::APP_ID)
+                .map(|u| Message::ConfigChanged(u.config)),
+            core.watch_config::<ThemeMode>(THEME_MODE_ID)
+                .map(|u| Message::ThemeModeChanged(u.config)),
+        ];
+        if self.config.sunset_to_sunrise {
+            subscriptions.push(time::every(Duration::from_secs(60)).map(|_| Message::Tick));
+        }
+        Subscription::batch(subscriptions)
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::ConfigChanged(config) => {
+                self.config = config;
+                self.update_night();
+            }
+            Message::ThemeModeChanged(theme_mode) => {
+                self.theme_mode = theme_mode;
+            }
+            Message::Location(res) => {
+                self.locating = false;
+                match res {
+                    Ok(location) => {
+                        if let Some(helper) = &self.config_helper {
+                            if let Err(err) = self.config.set_location(helper, Some(location)) {
+                                tracing::error!(?err, "Error writing config");
+                            }
+                        }
+                        self.update_night();
+                    }
+                    Err(err) => tracing::error!("Failed to get location: {}", err),
+                }
+            }
+            Message::Tick => {
+                self.update_night();
+            }
+            Message::Dark(is_dark) => {
+                self.set_dark(is_dark);
+            }
+            Message::ToggleDark => {
+                self.set_dark(!self.theme_mode.is_dark);
+            }
+            Message::SunsetToSunrise(sunset_to_sunrise) => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_sunset_to_sunrise(helper, sunset_to_sunrise) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+                // Apply the schedule right away
+                self.night = None;
+                self.update_night();
+                return self.locate();
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        // Middle click switches between light and dark without opening the popup
+        mouse_area(
+            core.applet
+                .icon_button(if self.theme_mode.is_dark {
+                    "weather-clear-night-symbolic"
+                } else {
+                    "weather-clear-symbolic"
+                })
+                .on_press(AppletMessage::TogglePopup),
+        )
+        .on_middle_press(Message::ToggleDark.into())
+        .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut schedule = column![toggler(
+            Some(fl!("sunset-to-sunrise")),
+            self.config.sunset_to_sunrise,
+            |enabled| Message::SunsetToSunrise(enabled).into()
+        )
+        .text_size(14)
+        .width(Length::Fill)]
+        .spacing(2);
+        if let Some(status) = self.status() {
+            schedule = schedule.push(text(status).size(10));
+        }
+
+        let content = Column::new()
+            .padding([8, 0])
+            .push(padded_control(
+                toggler(Some(fl!("dark-mode")), self.theme_mode.is_dark, |is_dark| {
+                    Message::Dark(is_dark).into()
+                })
+                .text_size(14)
+                .width(Length::Fill),
+            ))
+            .push(padded_control(divider::horizontal::default()))
+            .push(padded_control(schedule));
+
+        content.into()
+    }
+}
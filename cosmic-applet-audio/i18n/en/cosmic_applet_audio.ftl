@@ -5,3 +5,29 @@ sound-settings = Sound Settings...
 disconnected = PulseAudio Disconnected
 no-device = No device selected
 unknown-artist = Unknown
+auto-switch = Automatic Switching
+auto-switch-desc = When an output is plugged in
+auto-switch-default = System default
+auto-switch-always = Always switch
+auto-switch-never = Never switch
+headphones = Headphones
+hdmi = HDMI and DisplayPort
+bluetooth = Bluetooth
+usb = USB
+monitor-input = Monitor Input
+monitor-input-source = Input
+monitor-input-latency = Latency
+volume-up = Raise the volume
+volume-down = Lower the volume
+mute-output = Mute the sound
+equalizer = Equalizer
+equalizer-flat = Flat
+equalizer-bass-boost = Bass boost
+equalizer-voice = Voice
+equalizer-custom = Custom
+equalizer-save = Save preset
+devices = Devices
+devices-desc = Rename devices, or hide those never used
+recording = Recording
+unknown-input = Unknown input
+unknown-application = Unknown application
@@ -0,0 +1,73 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Switching the default output when one is plugged in, following the user's
+//! rules.
+//!
+//! Rules only apply to outputs that appear as new sinks, such as Bluetooth,
+//! USB and HDMI devices. Headphones plugged into the built-in jack change the
+//! port of an existing sink instead, which the sound server handles itself.
+
+use cosmic_pulse_client::DeviceInfo;
+
+use crate::config::{AudioAppletConfig, AutoSwitch, DeviceKind};
+
+fn is_kind(device: &DeviceInfo, kind: DeviceKind) -> bool {
+    let name = device.name.as_deref().unwrap_or_default();
+    match kind {
+        DeviceKind::Headphones => {
+            matches!(
+                device.form_factor.as_deref(),
+                Some("headphones" | "headset")
+            )
+        }
+        DeviceKind::Hdmi => name.contains("hdmi") || name.contains("displayport"),
+        DeviceKind::Bluetooth => {
+            device.bus.as_deref() == Some("bluetooth") || name.starts_with("bluez_")
+        }
+        DeviceKind::Usb => device.bus.as_deref() == Some("usb"),
+    }
+}
+
+/// The rule for a newly added output.
+pub fn rule(config: &AudioAppletConfig, device: &DeviceInfo) -> AutoSwitch {
+    DeviceKind::ALL
+        .into_iter()
+        .filter(|kind| is_kind(device, *kind))
+        .map(|kind| config.auto_switch(kind))
+        .find(|rule| *rule != AutoSwitch::Default)
+        .unwrap_or_default()
+}
+
+/// The output to switch to after `added` outputs were plugged in, if any.
+///
+/// `previous` is the default output from before they were, so it can be
+/// restored if the sound server switched to one the rules exclude.
+pub fn switch_to<'a>(
+    config: &AudioAppletConfig,
+    outputs: &'a [DeviceInfo],
+    added: &[u32],
+    default: Option<&DeviceInfo>,
+    previous: Option<&DeviceInfo>,
+) -> Option<&'a DeviceInfo> {
+    let added = outputs
+        .iter()
+        .filter(|output| added.contains(&output.index))
+        .collect::<Vec<_>>();
+    if let Some(output) = added
+        .iter()
+        .rev()
+        .find(|output| rule(config, output) == AutoSwitch::Always)
+    {
+        return (default.map(|d| d.index) != Some(output.index)).then_some(*output);
+    }
+    let default = default?;
+    if added
+        .iter()
+        .any(|output| output.index == default.index && rule(config, output) == AutoSwitch::Never)
+    {
+        let previous = previous?;
+        return outputs.iter().find(|output| output.name == previous.name);
+    }
+    None
+}
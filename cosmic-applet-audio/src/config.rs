@@ -1,11 +1,93 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
+use std::collections::{BTreeMap, BTreeSet};
+
 use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
 use serde::{Deserialize, Serialize};
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize, CosmicConfigEntry, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, CosmicConfigEntry, PartialEq, Eq)]
 #[version = 1]
 pub struct AudioAppletConfig {
     pub show_media_controls_in_top_panel: bool,
+    /// What to do when an output of a kind is plugged in.
+    pub auto_switch: BTreeMap<DeviceKind, AutoSwitch>,
+    /// The input played on the default output while monitoring, the default
+    /// input if `None`.
+    pub loopback_source: Option<String>,
+    pub loopback_latency_msec: u32,
+    pub equalizer_preset: EqualizerPreset,
+    /// Gains of the custom preset, in dB.
+    pub equalizer_custom: [i8; 10],
+    /// Names given to devices, by their name in the sound server.
+    pub device_names: BTreeMap<String, String>,
+    /// Devices left out of the lists, by their name in the sound server.
+    pub hidden_devices: BTreeSet<String>,
+    /// Whether the applet handles the volume keys itself, showing its own
+    /// overlay, for compositors that don't.
+    pub volume_keys: bool,
+}
+
+impl Default for AudioAppletConfig {
+    fn default() -> Self {
+        Self {
+            show_media_controls_in_top_panel: false,
+            auto_switch: BTreeMap::new(),
+            loopback_source: None,
+            loopback_latency_msec: 50,
+            equalizer_preset: EqualizerPreset::default(),
+            equalizer_custom: [0; 10],
+            device_names: BTreeMap::new(),
+            hidden_devices: BTreeSet::new(),
+            volume_keys: false,
+        }
+    }
+}
+
+impl AudioAppletConfig {
+    pub fn auto_switch(&self, kind: DeviceKind) -> AutoSwitch {
+        self.auto_switch.get(&kind).copied().unwrap_or_default()
+    }
+}
+
+/// Kinds of outputs auto-switch rules apply to. An output can be of several
+/// kinds, such as Bluetooth headphones, in which case the first kind with a
+/// rule decides.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DeviceKind {
+    Headphones,
+    Hdmi,
+    Bluetooth,
+    Usb,
+}
+
+impl DeviceKind {
+    pub const ALL: [Self; 4] = [Self::Headphones, Self::Hdmi, Self::Bluetooth, Self::Usb];
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AutoSwitch {
+    /// Leave it to the sound server.
+    #[default]
+    Default,
+    Always,
+    Never,
+}
+
+impl AutoSwitch {
+    pub const ALL: [Self; 3] = [Self::Default, Self::Always, Self::Never];
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum EqualizerPreset {
+    /// No equalizer running.
+    #[default]
+    Flat,
+    BassBoost,
+    Voice,
+    Custom,
+}
+
+impl EqualizerPreset {
+    pub const ALL: [Self; 4] = [Self::Flat, Self::BassBoost, Self::Voice, Self::Custom];
 }
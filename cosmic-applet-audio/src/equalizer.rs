@@ -0,0 +1,192 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! An equalizer on the default output, as a PipeWire filter-chain.
+//!
+//! PipeWire's Pulse server can't load a filter-chain, so it runs in its own
+//! `pipewire` process. That offers a sink playing to the output it was
+//! started for, which the applet makes the default. Stopping the process
+//! removes the sink.
+
+use std::{
+    fmt::Write,
+    io,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+};
+
+use crate::config::EqualizerPreset;
+
+/// Center frequencies of the bands, in Hz.
+pub const BANDS: [u32; 10] = [31, 62, 125, 250, 500, 1000, 2000, 4000, 8000, 16000];
+/// The most a band is boosted or cut, in dB.
+pub const MAX_GAIN: i8 = 12;
+/// Name of the filter-chain's sink.
+pub const SINK_NAME: &str = "effect_input.cosmic_equalizer";
+
+/// Gains of the bands, in dB.
+pub type Gains = [i8; BANDS.len()];
+
+const BASS_BOOST: Gains = [6, 5, 4, 2, 0, 0, 0, 0, 0, 0];
+/// Cuts rumble and hiss, and lifts the frequencies of speech.
+const VOICE: Gains = [-6, -4, -2, 0, 1, 3, 4, 3, 0, -3];
+
+impl EqualizerPreset {
+    pub fn gains(self, custom: &Gains) -> Gains {
+        match self {
+            Self::Flat => [0; BANDS.len()],
+            Self::BassBoost => BASS_BOOST,
+            Self::Voice => VOICE,
+            Self::Custom => *custom,
+        }
+    }
+}
+
+/// A short label for a band, like `2k`.
+pub fn band_label(frequency: u32) -> String {
+    if frequency >= 1000 {
+        format!("{}k", frequency / 1000)
+    } else {
+        frequency.to_string()
+    }
+}
+
+/// The running filter-chain, stopped when dropped.
+pub struct Equalizer {
+    process: Child,
+    /// The output it plays to.
+    pub target: String,
+}
+
+impl Equalizer {
+    pub fn start(target: String, gains: &Gains) -> io::Result<Self> {
+        let path = config_path();
+        // One left behind by a crash would hold on to the sink's name
+        stop_stale(&path);
+        std::fs::write(&path, filter_chain(&target, gains))?;
+        let process = Command::new("pipewire")
+            .arg("-c")
+            .arg(&path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        Ok(Self { process, target })
+    }
+
+    /// Whether the process is still running, as it exits with the server.
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.process.try_wait(), Ok(None))
+    }
+}
+
+impl Drop for Equalizer {
+    fn drop(&mut self) {
+        if let Err(err) = self.process.kill() {
+            tracing::warn!(?err, "Failed to stop the equalizer");
+        }
+        _ = self.process.wait();
+    }
+}
+
+fn config_path() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("cosmic-applet-audio-equalizer.conf")
+}
+
+/// Stops `pipewire` processes started with the config at `path`.
+fn stop_stale(path: &Path) {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return;
+    };
+    let path = path.to_string_lossy();
+    for entry in entries.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let Ok(cmdline) = std::fs::read(entry.path().join("cmdline")) else {
+            continue;
+        };
+        let mut args = cmdline.split(|b| *b == 0).map(String::from_utf8_lossy);
+        let is_stale = args.next().map_or(false, |arg| arg.ends_with("pipewire"))
+            && args.any(|arg| arg == path);
+        if is_stale {
+            _ = Command::new("kill").arg(pid.to_string()).status();
+        }
+    }
+}
+
+/// A standalone PipeWire config running the filter-chain, with a shelf at
+/// either end and peaking filters between.
+fn filter_chain(target: &str, gains: &Gains) -> String {
+    let mut nodes = String::new();
+    let mut links = String::new();
+    for (i, (frequency, gain)) in BANDS.iter().zip(gains).enumerate() {
+        let label = match i {
+            0 => "bq_lowshelf",
+            i if i == BANDS.len() - 1 => "bq_highshelf",
+            _ => "bq_peaking",
+        };
+        _ = writeln!(
+            nodes,
+            "                    {{ type = builtin name = band{i} label = {label} \
+             control = {{ \"Freq\" = {frequency}.0 \"Q\" = 1.41 \"Gain\" = {gain}.0 }} }}"
+        );
+        if i > 0 {
+            _ = writeln!(
+                links,
+                "                    {{ output = \"band{}:Out\" input = \"band{i}:In\" }}",
+                i - 1
+            );
+        }
+    }
+    let target = target.replace('\\', "\\\\").replace('"', "\\\"");
+
+    format!(
+        r#"context.properties = {{
+    log.level = 0
+}}
+
+context.spa-libs = {{
+    audio.convert.* = audioconvert/libspa-audioconvert
+    support.*       = support/libspa-support
+}}
+
+context.modules = [
+    {{ name = libpipewire-module-rt flags = [ ifexists nofail ] }}
+    {{ name = libpipewire-module-protocol-native }}
+    {{ name = libpipewire-module-client-node }}
+    {{ name = libpipewire-module-adapter }}
+    {{ name = libpipewire-module-filter-chain
+        args = {{
+            node.description = "Equalizer"
+            media.name = "Equalizer"
+            filter.graph = {{
+                nodes = [
+{nodes}                ]
+                links = [
+{links}                ]
+            }}
+            audio.channels = 2
+            audio.position = [ FL FR ]
+            capture.props = {{
+                node.name = "{SINK_NAME}"
+                media.class = Audio/Sink
+            }}
+            playback.props = {{
+                node.name = "effect_output.cosmic_equalizer"
+                node.passive = true
+                target.object = "{target}"
+            }}
+        }}
+    }}
+]
+"#
+    )
+}
@@ -1,13 +1,14 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
+mod auto_switch;
+mod equalizer;
 mod localize;
-mod mouse_area;
 
-use crate::{localize::localize, pulse::DeviceInfo};
-use config::AudioAppletConfig;
+use crate::localize::localize;
+use config::{AudioAppletConfig, AutoSwitch, DeviceKind, EqualizerPreset};
 use cosmic::{
-    app::Command,
+    app::Core,
     applet::{
         cosmic_panel_config::PanelAnchor,
         menu_button, menu_control_padding, padded_control,
@@ -16,58 +17,350 @@ use cosmic::{
     cctk::sctk::reexports::calloop,
     cosmic_config::CosmicConfigEntry,
     iced::{
-        self, widget,
-        widget::{column, row, slider, text},
-        window, Alignment, Length, Limits, Subscription,
+        self, time, widget,
+        widget::{column, row, slider, text, vertical_slider},
+        window::Id,
+        Alignment, Background, Border, Color, Command, Length, Subscription,
     },
+    iced_core::Shadow,
     iced_runtime::core::alignment::Horizontal,
-    iced_style::application,
-    widget::{button, divider, horizontal_space, icon, Column, Row},
+    widget::{button, divider, dropdown, horizontal_space, icon, text_input, Column, Row},
     Element, Renderer, Theme,
 };
+use cosmic_applet_helper::{
+    global_shortcuts, AppletMessage, CosmicApplet, MouseArea, Osd, OsdMessage, PanelEvent,
+    Shortcut, ShortcutEvent,
+};
+use cosmic_pulse_client::{
+    self as pulse, percent_to_volume, volume_to_percent, DeviceInfo, StreamInfo, StreamKind,
+};
 use cosmic_settings_subscriptions::pulse as sub_pulse;
 use cosmic_time::{anim, chain, id, once_cell::sync::Lazy, Instant, Timeline};
-use iced::{
-    wayland::popup::{destroy_popup, get_popup},
-    widget::container,
-};
-use libpulse_binding::volume::Volume;
+use equalizer::Equalizer;
+use iced::widget::container;
 use mpris2_zbus::player::PlaybackStatus;
 use mpris_subscription::{MprisRequest, MprisUpdate};
 
 mod config;
 mod mpris_subscription;
-mod pulse;
 
 static SHOW_MEDIA_CONTROLS: Lazy<id::Toggler> = Lazy::new(id::Toggler::unique);
+static LOOPBACK: Lazy<id::Toggler> = Lazy::new(id::Toggler::unique);
 
 const GO_BACK: &str = "media-skip-backward-symbolic";
 const GO_NEXT: &str = "media-skip-forward-symbolic";
 const PAUSE: &str = "media-playback-pause-symbolic";
 const PLAY: &str = "media-playback-start-symbolic";
 
+const VOLUME_UP: &str = "volume-up";
+const VOLUME_DOWN: &str = "volume-down";
+const MUTE: &str = "mute";
+/// How much the volume keys change the volume, in percent.
+const VOLUME_STEP: f64 = 5.0;
+/// How often the streams are checked for recordings.
+const STREAMS_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
 pub fn run() -> cosmic::iced::Result {
     localize();
-    cosmic::applet::run::<Audio>(true, ())
+    cosmic_applet_helper::run_applet::<Audio>()
 }
 
 #[derive(Default)]
 pub struct Audio {
-    core: cosmic::app::Core,
     is_open: IsOpen,
     current_output: Option<DeviceInfo>,
     current_input: Option<DeviceInfo>,
     outputs: Vec<DeviceInfo>,
     inputs: Vec<DeviceInfo>,
     pulse_state: PulseState,
-    popup: Option<window::Id>,
     timeline: Timeline,
     config: AudioAppletConfig,
     player_status: Option<mpris_subscription::PlayerStatus>,
     token_tx: Option<calloop::channel::Sender<TokenRequest>>,
+    /// Outputs plugged in since the default output was last checked.
+    added_outputs: Vec<u32>,
+    /// The default output from before they were.
+    previous_output: Option<DeviceInfo>,
+    auto_switch_options: Vec<String>,
+    /// All inputs, including monitors of outputs.
+    sources: Vec<DeviceInfo>,
+    /// The sources offered for monitoring.
+    loopback_sources: Vec<DeviceInfo>,
+    loopback_source_names: Vec<String>,
+    /// Index of the loaded loopback module, while monitoring.
+    loopback: Option<u32>,
+    osd: Osd,
+    equalizer: Option<Equalizer>,
+    /// Whether the equalizer's sink is made the default once it shows up.
+    equalizer_pending: bool,
+    /// The custom preset being edited.
+    equalizer_gains: equalizer::Gains,
+    equalizer_preset_labels: Vec<String>,
+    /// Whether device names were edited since they were saved.
+    device_names_edited: bool,
+    /// Streams recording from an input, rather than a monitor of an output.
+    recording: Vec<StreamInfo>,
 }
 
 impl Audio {
+    fn write_config(&self) {
+        if let Ok(helper) = cosmic::cosmic_config::Config::new(
+            <Self as cosmic::Application>::APP_ID,
+            AudioAppletConfig::VERSION,
+        ) {
+            if let Err(err) = self.config.write_entry(&helper) {
+                tracing::error!(?err, "Error writing config");
+            }
+        }
+    }
+
+    /// Keeps the streams recording from an input, which aren't paused.
+    fn update_recording(&mut self, streams: Vec<StreamInfo>) {
+        self.recording = streams
+            .into_iter()
+            .filter(|stream| stream.kind == StreamKind::Recording && !stream.corked)
+            .filter(|stream| {
+                !self
+                    .sources
+                    .iter()
+                    .find(|source| source.index == stream.device)
+                    .and_then(|source| source.name.as_ref())
+                    .is_some_and(|name| name.contains("monitor"))
+            })
+            .collect();
+    }
+
+    /// A row for each app recording, with the input it records from.
+    fn recording_view(&self) -> Element<Message> {
+        let mut content = column![padded_control(
+            row![
+                icon::from_name("media-record-symbolic")
+                    .size(16)
+                    .symbolic(true),
+                text(fl!("recording")).size(14),
+            ]
+            .spacing(8)
+            .align_items(Alignment::Center)
+        )];
+        for stream in &self.recording {
+            let source = self
+                .sources
+                .iter()
+                .find(|source| source.index == stream.device)
+                .map_or_else(|| fl!("unknown-input"), |source| self.device_name(source));
+            content = content.push(padded_control(
+                row![
+                    column![
+                        text(
+                            stream
+                                .application
+                                .clone()
+                                .unwrap_or_else(|| fl!("unknown-application"))
+                        )
+                        .size(14),
+                        text(source).size(10),
+                    ]
+                    .width(Length::Fill),
+                    button::icon(
+                        icon::from_name(if stream.mute {
+                            "microphone-sensitivity-muted-symbolic"
+                        } else {
+                            "audio-input-microphone-symbolic"
+                        })
+                        .size(16)
+                        .symbolic(true),
+                    )
+                    .style(cosmic::theme::Button::Icon)
+                    .on_press(Message::MuteRecording(stream.index, !stream.mute)),
+                    button::icon(
+                        icon::from_name("process-stop-symbolic")
+                            .size(16)
+                            .symbolic(true)
+                    )
+                    .style(cosmic::theme::Button::Icon)
+                    .on_press(Message::StopRecording(stream.index)),
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center),
+            ));
+        }
+        content.into()
+    }
+
+    /// The name the user gave the device, or its description.
+    fn device_name(&self, device: &DeviceInfo) -> String {
+        device
+            .name
+            .as_ref()
+            .and_then(|name| self.config.device_names.get(name))
+            .map(|label| label.trim())
+            .filter(|label| !label.is_empty())
+            .map_or_else(|| pretty_name(device.description.clone()), String::from)
+    }
+
+    fn is_hidden(&self, device: &DeviceInfo) -> bool {
+        device
+            .name
+            .as_ref()
+            .is_some_and(|name| self.config.hidden_devices.contains(name))
+    }
+
+    /// Lists the sources that aren't hidden for monitoring, along with the one
+    /// monitored even if it is.
+    fn update_loopback_sources(&mut self) {
+        let selected = self.loopback_source();
+        self.loopback_sources = self
+            .sources
+            .iter()
+            .filter(|source| !self.is_hidden(source) || source.name == selected)
+            .cloned()
+            .collect();
+        self.loopback_source_names = self
+            .loopback_sources
+            .iter()
+            .map(|source| self.device_name(source))
+            .collect();
+    }
+
+    fn save_device_names(&mut self) {
+        if !self.device_names_edited {
+            return;
+        }
+        self.device_names_edited = false;
+        self.config
+            .device_names
+            .retain(|_, label| !label.trim().is_empty());
+        for label in self.config.device_names.values_mut() {
+            *label = label.trim().to_string();
+        }
+        self.write_config();
+        self.update_loopback_sources();
+    }
+
+    fn loopback_source(&self) -> Option<String> {
+        self.config
+            .loopback_source
+            .clone()
+            .or_else(|| self.current_input.as_ref()?.name.clone())
+    }
+
+    fn start_loopback(&mut self) {
+        let Some(source) = self.loopback_source() else {
+            return;
+        };
+        let latency_msec = self.config.loopback_latency_msec;
+        if let Some(conn) = self.pulse_state.connection() {
+            conn.send(pulse::Message::LoadLoopback {
+                source,
+                latency_msec,
+            });
+        }
+    }
+
+    fn stop_loopback(&mut self) {
+        if let Some(module) = self.loopback.take() {
+            if let Some(conn) = self.pulse_state.connection() {
+                conn.send(pulse::Message::UnloadModule(module));
+            }
+        }
+    }
+
+    fn restart_loopback(&mut self) {
+        if self.loopback.is_some() {
+            self.stop_loopback();
+            self.start_loopback();
+        }
+    }
+
+    /// The output the equalizer plays to, or should.
+    fn equalizer_target(&self) -> Option<String> {
+        let output = self.current_output.as_ref()?.name.clone()?;
+        if output == equalizer::SINK_NAME {
+            self.equalizer
+                .as_ref()
+                .map(|equalizer| equalizer.target.clone())
+        } else {
+            Some(output)
+        }
+    }
+
+    /// Starts the equalizer with the configured preset, playing to `target`.
+    fn start_equalizer(&mut self, target: Option<String>) {
+        self.stop_equalizer();
+        let preset = self.config.equalizer_preset;
+        if preset == EqualizerPreset::Flat {
+            return;
+        }
+        let Some(target) = target else {
+            return;
+        };
+        match Equalizer::start(target, &preset.gains(&self.config.equalizer_custom)) {
+            Ok(equalizer) => {
+                self.equalizer = Some(equalizer);
+                self.equalizer_pending = true;
+            }
+            Err(err) => tracing::error!(?err, "Failed to start the equalizer"),
+        }
+    }
+
+    /// Plays to the equalizer's output again and stops the equalizer.
+    fn stop_equalizer(&mut self) {
+        self.equalizer_pending = false;
+        let Some(equalizer) = self.equalizer.take() else {
+            return;
+        };
+        let is_default = self.current_output.as_ref().map_or(false, |output| {
+            output.name.as_deref() == Some(equalizer::SINK_NAME)
+        });
+        let target = self
+            .outputs
+            .iter()
+            .find(|output| output.name.as_ref() == Some(&equalizer.target))
+            .cloned();
+        if let (true, Some(target), Some(conn)) =
+            (is_default, target, self.pulse_state.connection())
+        {
+            conn.send(pulse::Message::SetDefaultSink(target));
+        }
+        // So a new equalizer's sink isn't mistaken for the old one's
+        self.outputs
+            .retain(|output| output.name.as_deref() != Some(equalizer::SINK_NAME));
+        drop(equalizer);
+    }
+
+    /// Makes the equalizer's sink the default once it's there.
+    fn activate_equalizer(&mut self) {
+        if !self.equalizer_pending {
+            return;
+        }
+        let Some(sink) = self
+            .outputs
+            .iter()
+            .find(|output| output.name.as_deref() == Some(equalizer::SINK_NAME))
+            .cloned()
+        else {
+            return;
+        };
+        if let Some(conn) = self.pulse_state.connection() {
+            conn.send(pulse::Message::SetDefaultSink(sink));
+            self.equalizer_pending = false;
+        }
+    }
+
+    /// The default output, or the one the equalizer plays to while it's the
+    /// default.
+    fn shown_output(&self) -> Option<&DeviceInfo> {
+        let output = self.current_output.as_ref()?;
+        if output.name.as_deref() != Some(equalizer::SINK_NAME) {
+            return Some(output);
+        }
+        let target = &self.equalizer.as_ref()?.target;
+        self.outputs
+            .iter()
+            .find(|output| output.name.as_ref() == Some(target))
+            .or(Some(output))
+    }
+
     fn update_output(&mut self, output: Option<DeviceInfo>) {
         self.current_output = output;
     }
@@ -112,6 +405,8 @@ enum IsOpen {
     None,
     Output,
     Input,
+    AutoSwitch,
+    Devices,
 }
 
 #[derive(Debug, Clone)]
@@ -126,8 +421,6 @@ pub enum Message {
     OutputChanged(String),
     InputChanged(String),
     Pulse(pulse::Event),
-    TogglePopup,
-    CloseRequested(window::Id),
     ToggleMediaControlsInTopPanel(chain::Toggler, bool),
     Frame(Instant),
     ConfigChanged(AudioAppletConfig),
@@ -136,10 +429,32 @@ pub enum Message {
     Token(TokenUpdate),
     OpenSettings,
     PulseSub(sub_pulse::Event),
+    Sinks(pulse::SinkEvent),
+    AutoSwitchToggle,
+    SetAutoSwitch(DeviceKind, usize),
+    ToggleLoopback(chain::Toggler, bool),
+    SetLoopbackSource(usize),
+    SetLoopbackLatency(u32),
+    /// Writes the latency and restarts monitoring with it, once the slider is
+    /// released.
+    ApplyLoopbackLatency,
+    Shortcut(ShortcutEvent),
+    Osd(OsdMessage),
+    SetEqualizerPreset(usize),
+    SetEqualizerGain(usize, i8),
+    /// Saves the custom preset and applies it.
+    SaveEqualizerPreset,
+    DevicesToggle,
+    RenameDevice(String, String),
+    SaveDeviceNames,
+    ToggleDeviceHidden(String),
+    PollStreams,
+    MuteRecording(u32, bool),
+    StopRecording(u32),
 }
 
 impl Audio {
-    fn playback_buttons(&self) -> Option<Element<Message>> {
+    fn playback_buttons<'a>(&'a self, core: &'a Core) -> Option<Element<'a, Message>> {
         if self.player_status.is_some() && self.config.show_media_controls_in_top_panel {
             let mut elements = Vec::with_capacity(3);
             if self
@@ -149,8 +464,7 @@ impl Audio {
                 .unwrap_or_default()
             {
                 elements.push(
-                    self.core
-                        .applet
+                    core.applet
                         .icon_button(GO_BACK)
                         .on_press(Message::MprisRequest(MprisRequest::Previous))
                         .into(),
@@ -158,8 +472,7 @@ impl Audio {
             }
             if let Some(play) = self.is_play() {
                 elements.push(
-                    self.core
-                        .applet
+                    core.applet
                         .icon_button(if play { PLAY } else { PAUSE })
                         .on_press(if play {
                             Message::MprisRequest(MprisRequest::Play)
@@ -176,15 +489,14 @@ impl Audio {
                 .unwrap_or_default()
             {
                 elements.push(
-                    self.core
-                        .applet
+                    core.applet
                         .icon_button(GO_NEXT)
                         .on_press(Message::MprisRequest(MprisRequest::Next))
                         .into(),
                 )
             }
 
-            Some(match self.core.applet.anchor {
+            Some(match core.applet.anchor {
                 PanelAnchor::Left | PanelAnchor::Right => Column::with_children(elements)
                     .align_items(Alignment::Center)
                     .into(),
@@ -282,78 +594,55 @@ impl Audio {
     }
 }
 
-impl cosmic::Application for Audio {
+impl CosmicApplet for Audio {
     type Message = Message;
-    type Executor = cosmic::SingleThreadExecutor;
-    type Flags = ();
     const APP_ID: &'static str = "com.system76.CosmicAppletAudio";
 
-    fn init(core: cosmic::app::Core, _flags: ()) -> (Self, Command<Message>) {
+    fn init(_core: &Core) -> (Self, Command<Message>) {
         (
             Self {
-                core,
                 is_open: IsOpen::None,
                 current_output: None,
                 current_input: None,
                 outputs: vec![],
                 inputs: vec![],
                 token_tx: None,
+                auto_switch_options: AutoSwitch::ALL.into_iter().map(auto_switch_label).collect(),
+                equalizer_preset_labels: EqualizerPreset::ALL
+                    .into_iter()
+                    .map(equalizer_preset_label)
+                    .collect(),
                 ..Default::default()
             },
             Command::none(),
         )
     }
 
-    fn core(&self) -> &cosmic::app::Core {
-        &self.core
-    }
-
-    fn core_mut(&mut self) -> &mut cosmic::app::Core {
-        &mut self.core
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        match event {
+            PanelEvent::PopupOpened => {
+                self.timeline = Timeline::new();
+                if let Some(conn) = self.pulse_state.connection() {
+                    conn.send(pulse::Message::UpdateConnection);
+                    conn.send(pulse::Message::GetDefaultSink);
+                    conn.send(pulse::Message::GetDefaultSource);
+                    conn.send(pulse::Message::GetSinks);
+                    conn.send(pulse::Message::GetSources);
+                }
+            }
+            PanelEvent::PopupClosed => self.save_device_names(),
+        }
+        Command::none()
     }
 
-    fn style(&self) -> Option<<Theme as application::StyleSheet>::Style> {
-        Some(cosmic::applet::style())
+    fn on_surface_closed(&mut self, _core: &Core, id: Id) {
+        self.osd.closed(id);
     }
 
-    fn update(&mut self, message: Message) -> Command<Message> {
+    fn update(&mut self, core: &Core, message: Message) -> Command<Message> {
         match message {
             Message::Frame(now) => self.timeline.now(now),
             Message::Ignore => {}
-            Message::TogglePopup => {
-                if let Some(p) = self.popup.take() {
-                    return destroy_popup(p);
-                } else {
-                    if let Some(conn) = self.pulse_state.connection() {
-                        conn.send(pulse::Message::UpdateConnection);
-                    }
-                    let new_id = window::Id::unique();
-                    self.popup.replace(new_id);
-                    self.timeline = Timeline::new();
-
-                    let mut popup_settings = self.core.applet.get_popup_settings(
-                        window::Id::MAIN,
-                        new_id,
-                        None,
-                        None,
-                        None,
-                    );
-                    popup_settings.positioner.size_limits = Limits::NONE
-                        .min_height(1.0)
-                        .min_width(1.0)
-                        .max_width(400.0)
-                        .max_height(1080.0);
-
-                    if let Some(conn) = self.pulse_state.connection() {
-                        conn.send(pulse::Message::GetDefaultSink);
-                        conn.send(pulse::Message::GetDefaultSource);
-                        conn.send(pulse::Message::GetSinks);
-                        conn.send(pulse::Message::GetSources);
-                    }
-
-                    return get_popup(popup_settings);
-                }
-            }
             Message::SetOutputVolume(vol) => {
                 self.current_output
                     .as_mut()
@@ -460,13 +749,19 @@ impl cosmic::Application for Audio {
                         conn.send(pulse::Message::GetSources);
                         conn.send(pulse::Message::GetDefaultSink);
                         conn.send(pulse::Message::GetDefaultSource);
+                        conn.send(pulse::Message::GetStreams);
                     }
                 }
                 pulse::Event::MessageReceived(msg) => {
                     match msg {
                         // This is where we match messages from the subscription to app state
-                        pulse::Message::SetSinks(sinks) => self.outputs = sinks,
+                        pulse::Message::SetSinks(sinks) => {
+                            self.outputs = sinks;
+                            self.activate_equalizer();
+                        }
                         pulse::Message::SetSources(sources) => {
+                            self.sources = sources.clone();
+                            self.update_loopback_sources();
                             self.inputs = sources
                                 .into_iter()
                                 .filter(|source| {
@@ -480,10 +775,57 @@ impl cosmic::Application for Audio {
                         }
                         pulse::Message::SetDefaultSink(sink) => {
                             self.update_output(Some(sink));
+                            if let Some(equalizer) = &mut self.equalizer {
+                                if !equalizer.is_running() {
+                                    self.equalizer = None;
+                                }
+                            }
+                            // Equalize the output chosen instead, like when
+                            // headphones are plugged in
+                            let moved = match (&self.equalizer, &self.current_output) {
+                                (Some(equalizer), Some(output)) => {
+                                    !self.equalizer_pending
+                                        && output.name.as_ref() != Some(&equalizer.target)
+                                        && output.name.as_deref() != Some(equalizer::SINK_NAME)
+                                }
+                                _ => false,
+                            };
+                            if moved
+                                || (self.equalizer.is_none()
+                                    && self.config.equalizer_preset != EqualizerPreset::Flat)
+                            {
+                                self.start_equalizer(self.equalizer_target());
+                            }
+                            if !self.added_outputs.is_empty() {
+                                let added = std::mem::take(&mut self.added_outputs);
+                                let previous = self.previous_output.take();
+                                if let Some(output) = auto_switch::switch_to(
+                                    &self.config,
+                                    &self.outputs,
+                                    &added,
+                                    self.current_output.as_ref(),
+                                    previous.as_ref(),
+                                ) {
+                                    let output = output.clone();
+                                    if let Some(conn) = self.pulse_state.connection() {
+                                        conn.send(pulse::Message::SetDefaultSink(output));
+                                    }
+                                }
+                            }
                         }
                         pulse::Message::SetDefaultSource(source) => {
                             self.update_input(Some(source));
                         }
+                        pulse::Message::SetStreams(streams) => {
+                            self.update_recording(streams);
+                        }
+                        pulse::Message::LoopbackLoaded(module) => {
+                            if let Some(previous) = self.loopback.replace(module) {
+                                if let Some(conn) = self.pulse_state.connection() {
+                                    conn.send(pulse::Message::UnloadModule(previous));
+                                }
+                            }
+                        }
                         pulse::Message::Disconnected => {
                             panic!("Subscription error handling is bad. This should never happen.")
                         }
@@ -494,6 +836,11 @@ impl cosmic::Application for Audio {
                 }
                 pulse::Event::Disconnected => {
                     self.pulse_state.disconnected();
+                    self.recording.clear();
+                    // Loaded modules are gone if the server restarted.
+                    self.loopback = None;
+                    self.equalizer = None;
+                    self.equalizer_pending = false;
                     if let Some(mut conn) = self.pulse_state.connection().cloned() {
                         _ = tokio::spawn(async move {
                             tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
@@ -502,24 +849,177 @@ impl cosmic::Application for Audio {
                     }
                 }
             },
+            Message::Sinks(event) => {
+                let Some(conn) = self.pulse_state.connection() else {
+                    return Command::none();
+                };
+                match event {
+                    pulse::SinkEvent::New(index) => {
+                        if self.added_outputs.is_empty() {
+                            self.previous_output = self.current_output.clone();
+                        }
+                        self.added_outputs.push(index);
+                        // The default sink is checked after the sinks arrive.
+                        conn.send(pulse::Message::GetSinks);
+                        conn.send(pulse::Message::GetDefaultSink);
+                    }
+                    pulse::SinkEvent::Removed(_) => {
+                        conn.send(pulse::Message::GetSinks);
+                        conn.send(pulse::Message::GetDefaultSink);
+                    }
+                }
+            }
+            Message::PollStreams => {
+                if let PulseState::Connected(conn) = &mut self.pulse_state {
+                    conn.send(pulse::Message::GetSources);
+                    conn.send(pulse::Message::GetStreams);
+                }
+            }
+            Message::MuteRecording(index, mute) => {
+                if let PulseState::Connected(conn) = &mut self.pulse_state {
+                    conn.send(pulse::Message::SetSourceOutputMute(index, mute));
+                    conn.send(pulse::Message::GetStreams);
+                }
+            }
+            Message::StopRecording(index) => {
+                if let PulseState::Connected(conn) = &mut self.pulse_state {
+                    conn.send(pulse::Message::KillSourceOutput(index));
+                    conn.send(pulse::Message::GetStreams);
+                }
+            }
+            Message::DevicesToggle => {
+                self.save_device_names();
+                self.is_open = if self.is_open == IsOpen::Devices {
+                    IsOpen::None
+                } else {
+                    IsOpen::Devices
+                }
+            }
+            Message::RenameDevice(name, label) => {
+                self.config.device_names.insert(name, label);
+                self.device_names_edited = true;
+            }
+            Message::SaveDeviceNames => {
+                self.save_device_names();
+            }
+            Message::ToggleDeviceHidden(name) => {
+                if !self.config.hidden_devices.remove(&name) {
+                    self.config.hidden_devices.insert(name);
+                }
+                self.write_config();
+                self.update_loopback_sources();
+            }
+            Message::AutoSwitchToggle => {
+                self.is_open = if self.is_open == IsOpen::AutoSwitch {
+                    IsOpen::None
+                } else {
+                    IsOpen::AutoSwitch
+                }
+            }
+            Message::SetAutoSwitch(kind, i) => {
+                self.config.auto_switch.insert(kind, AutoSwitch::ALL[i]);
+                self.write_config();
+            }
+            Message::ToggleLoopback(chain, enabled) => {
+                self.timeline.set_chain(chain).start();
+                if enabled {
+                    self.start_loopback();
+                } else {
+                    self.stop_loopback();
+                }
+            }
+            Message::SetLoopbackSource(i) => {
+                let Some(source) = self.loopback_sources.get(i) else {
+                    return Command::none();
+                };
+                self.config.loopback_source = source.name.clone();
+                self.write_config();
+                self.update_loopback_sources();
+                self.restart_loopback();
+            }
+            Message::SetLoopbackLatency(latency_msec) => {
+                self.config.loopback_latency_msec = latency_msec;
+            }
+            Message::ApplyLoopbackLatency => {
+                self.write_config();
+                self.restart_loopback();
+            }
+            Message::SetEqualizerPreset(i) => {
+                let Some(&preset) = EqualizerPreset::ALL.get(i) else {
+                    return Command::none();
+                };
+                let target = self.equalizer_target();
+                self.config.equalizer_preset = preset;
+                self.equalizer_gains = self.config.equalizer_custom;
+                self.write_config();
+                self.start_equalizer(target);
+            }
+            Message::SetEqualizerGain(band, gain) => {
+                if let Some(gain_mut) = self.equalizer_gains.get_mut(band) {
+                    *gain_mut = gain;
+                }
+            }
+            Message::SaveEqualizerPreset => {
+                let target = self.equalizer_target();
+                self.config.equalizer_custom = self.equalizer_gains;
+                self.write_config();
+                self.start_equalizer(target);
+            }
             Message::ToggleMediaControlsInTopPanel(chain, enabled) => {
                 self.timeline.set_chain(chain).start();
                 self.config.show_media_controls_in_top_panel = enabled;
-                if let Ok(helper) =
-                    cosmic::cosmic_config::Config::new(Self::APP_ID, AudioAppletConfig::VERSION)
-                {
-                    if let Err(err) = self.config.write_entry(&helper) {
-                        tracing::error!(?err, "Error writing config");
+                self.write_config();
+            }
+            Message::Shortcut(ShortcutEvent::Activated(id)) => {
+                let command = match id.as_str() {
+                    VOLUME_UP | VOLUME_DOWN => {
+                        let change = if id == VOLUME_UP {
+                            VOLUME_STEP
+                        } else {
+                            -VOLUME_STEP
+                        };
+                        let volume =
+                            (self.current_output_volume_percent() + change).clamp(0.0, 100.0);
+                        self.update(core, Message::SetOutputVolume(volume))
                     }
-                }
+                    MUTE => self.update(core, Message::SetOutputMute(!self.current_output_mute())),
+                    _ => return Command::none(),
+                };
+                return Command::batch([
+                    command,
+                    self.osd.show(
+                        self.output_icon_name(),
+                        self.current_output_volume_percent(),
+                        Message::Osd,
+                    ),
+                ]);
             }
-            Message::CloseRequested(id) => {
-                if Some(id) == self.popup {
-                    self.popup = None;
+            Message::Shortcut(ShortcutEvent::Bound { conflicts, .. }) => {
+                if !conflicts.is_empty() {
+                    tracing::warn!(
+                        ?conflicts,
+                        "Volume keys already used by another application"
+                    );
                 }
             }
+            Message::Shortcut(ShortcutEvent::Error(err)) => {
+                tracing::warn!(%err, "Failed to register the volume keys");
+            }
+            Message::Shortcut(ShortcutEvent::Deactivated(_)) => {}
+            Message::Osd(message) => return self.osd.update(message, Message::Osd),
             Message::ConfigChanged(c) => {
+                if c.equalizer_custom != self.config.equalizer_custom {
+                    self.equalizer_gains = c.equalizer_custom;
+                }
+                // Keep names being typed
+                let device_names = self
+                    .device_names_edited
+                    .then(|| self.config.device_names.clone());
                 self.config = c;
+                if let Some(device_names) = device_names {
+                    self.config.device_names = device_names;
+                }
+                self.update_loopback_sources();
             }
             Message::Mpris(mpris_subscription::MprisUpdate::Player(p)) => {
                 self.player_status = Some(p);
@@ -623,13 +1123,10 @@ impl cosmic::Application for Audio {
         Command::none()
     }
 
-    fn subscription(&self) -> Subscription<Message> {
-        Subscription::batch(vec![
+    fn subscription(&self, core: &Core) -> Subscription<Message> {
+        let subscriptions = Subscription::batch(vec![
             pulse::connect().map(Message::Pulse),
-            self.timeline
-                .as_subscription()
-                .map(|(_, now)| Message::Frame(now)),
-            self.core.watch_config(Self::APP_ID).map(|u| {
+            core.watch_config(Self::APP_ID).map(|u| {
                 for err in u.errors {
                     tracing::error!(?err, "Error watching config");
                 }
@@ -638,49 +1135,99 @@ impl cosmic::Application for Audio {
             mpris_subscription::mpris_subscription(0).map(Message::Mpris),
             activation_token_subscription(0).map(Message::Token),
             sub_pulse::subscription().map(Message::PulseSub),
+            pulse::sink_events(0).map(Message::Sinks),
+        ]);
+        // The compositor's settings daemon already handles the volume keys
+        if !self.config.volume_keys {
+            return subscriptions;
+        }
+        Subscription::batch([
+            subscriptions,
+            Osd::subscription().map(Message::Osd),
+            global_shortcuts(
+                0,
+                vec![
+                    Shortcut {
+                        id: VOLUME_UP.to_string(),
+                        description: fl!("volume-up"),
+                        preferred_trigger: Some("XF86AudioRaiseVolume".to_string()),
+                    },
+                    Shortcut {
+                        id: VOLUME_DOWN.to_string(),
+                        description: fl!("volume-down"),
+                        preferred_trigger: Some("XF86AudioLowerVolume".to_string()),
+                    },
+                    Shortcut {
+                        id: MUTE.to_string(),
+                        description: fl!("mute-output"),
+                        preferred_trigger: Some("XF86AudioMute".to_string()),
+                    },
+                ],
+            )
+            .map(Message::Shortcut),
         ])
     }
 
-    fn view(&self) -> Element<Message> {
-        let btn = self
-            .core
+    fn popup_subscription(&self, _core: &Core) -> Subscription<Message> {
+        self.timeline
+            .as_subscription()
+            .map(|(_, now)| Message::Frame(now))
+    }
+
+    fn panel_subscription(&self, _core: &Core) -> Subscription<Message> {
+        time::every(STREAMS_INTERVAL).map(|_| Message::PollStreams)
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let btn = core
             .applet
             .icon_button(self.output_icon_name())
-            .on_press(Message::TogglePopup);
-        let btn = crate::mouse_area::MouseArea::new(btn).on_mouse_wheel(|delta| {
+            .on_press(AppletMessage::TogglePopup);
+        let btn = MouseArea::new(btn).on_mouse_wheel(|delta| {
             let change = match delta {
                 iced::mouse::ScrollDelta::Lines { x, y } => (x + y) * 5.,
                 iced::mouse::ScrollDelta::Pixels { y, .. } => y / 40.3125,
             };
             if change.abs() < f32::EPSILON {
-                return Message::Ignore;
+                return Message::Ignore.into();
             }
             let new_volume = self
                 .current_output
                 .as_ref()
                 .map_or(0f64, |v| volume_to_percent(v.volume.avg()) + change as f64)
                 .clamp(0.0, 100.0);
-            Message::SetOutputVolume(new_volume)
+            Message::SetOutputVolume(new_volume).into()
         });
-        if let Some(playback_buttons) = self.playback_buttons() {
-            match self.core.applet.anchor {
-                PanelAnchor::Left | PanelAnchor::Right => {
-                    Column::with_children(vec![playback_buttons, btn.into()])
-                        .align_items(Alignment::Center)
-                        .into()
-                }
-                PanelAnchor::Top | PanelAnchor::Bottom => {
-                    Row::with_children(vec![playback_buttons, btn.into()])
-                        .align_items(Alignment::Center)
-                        .into()
-                }
-            }
-        } else {
-            btn.into()
+        let mut children = Vec::with_capacity(3);
+        children.extend(
+            self.playback_buttons(core)
+                .map(|buttons| buttons.map(AppletMessage::App)),
+        );
+        children.push(btn.into());
+        if !self.recording.is_empty() {
+            children.push(recording_dot().map(AppletMessage::App));
+        }
+        if children.len() == 1 {
+            return children.remove(0);
+        }
+        match core.applet.anchor {
+            PanelAnchor::Left | PanelAnchor::Right => Column::with_children(children)
+                .align_items(Alignment::Center)
+                .into(),
+            PanelAnchor::Top | PanelAnchor::Bottom => Row::with_children(children)
+                .align_items(Alignment::Center)
+                .into(),
+        }
+    }
+
+    fn surface_view<'a>(&'a self, _core: &'a Core, id: Id) -> Element<'a, AppletMessage<Message>> {
+        if self.osd.is_surface(id) {
+            return self.osd.view();
         }
+        row![].into()
     }
 
-    fn view_window(&self, _id: window::Id) -> Element<Message> {
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
         let audio_disabled = matches!(self.pulse_state, PulseState::Disconnected(_));
         let out_f64 = self.current_output_volume_percent();
         let in_f64 = self.current_input_volume_percent();
@@ -742,16 +1289,19 @@ impl cosmic::Application for Audio {
                 revealer(
                     self.is_open == IsOpen::Output,
                     fl!("output"),
-                    match &self.current_output {
-                        Some(output) => pretty_name(output.description.clone()),
+                    match self.shown_output() {
+                        Some(output) => self.device_name(output),
                         None => String::from("No device selected"),
                     },
                     self.outputs
-                        .clone()
-                        .into_iter()
+                        .iter()
+                        .filter(
+                            |output| output.name.as_deref() != Some(equalizer::SINK_NAME)
+                                && !self.is_hidden(output)
+                        )
                         .map(|output| (
                             output.name.clone().unwrap_or_default(),
-                            pretty_name(output.description)
+                            self.device_name(output)
                         ))
                         .collect(),
                     Message::OutputToggle,
@@ -761,15 +1311,15 @@ impl cosmic::Application for Audio {
                     self.is_open == IsOpen::Input,
                     fl!("input"),
                     match &self.current_input {
-                        Some(input) => pretty_name(input.description.clone()),
+                        Some(input) => self.device_name(input),
                         None => fl!("no-device"),
                     },
                     self.inputs
-                        .clone()
-                        .into_iter()
+                        .iter()
+                        .filter(|input| !self.is_hidden(input))
                         .map(|input| (
                             input.name.clone().unwrap_or_default(),
-                            pretty_name(input.description)
+                            self.device_name(input)
                         ))
                         .collect(),
                     Message::InputToggle,
@@ -846,9 +1396,206 @@ impl cosmic::Application for Audio {
                     .padding(menu_control_padding()),
             );
         }
+        let mut auto_switch = column![revealer_head(
+            self.is_open == IsOpen::AutoSwitch,
+            fl!("auto-switch"),
+            fl!("auto-switch-desc"),
+            Message::AutoSwitchToggle,
+        )];
+        if self.is_open == IsOpen::AutoSwitch {
+            for kind in DeviceKind::ALL {
+                let selected = AutoSwitch::ALL
+                    .iter()
+                    .position(|rule| *rule == self.config.auto_switch(kind));
+                auto_switch = auto_switch.push(
+                    row![
+                        text(device_kind_label(kind)).size(14).width(Length::Fill),
+                        dropdown(&self.auto_switch_options, selected, move |i| {
+                            Message::SetAutoSwitch(kind, i)
+                        }),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center)
+                    .padding([8, 48]),
+                );
+            }
+        }
+
+        let mut devices = column![revealer_head(
+            self.is_open == IsOpen::Devices,
+            fl!("devices"),
+            fl!("devices-desc"),
+            Message::DevicesToggle,
+        )];
+        if self.is_open == IsOpen::Devices {
+            let outputs = self
+                .outputs
+                .iter()
+                .filter(|output| output.name.as_deref() != Some(equalizer::SINK_NAME));
+            for (label, list) in [
+                (fl!("output"), outputs.collect::<Vec<_>>()),
+                (fl!("input"), self.inputs.iter().collect()),
+            ] {
+                devices = devices.push(container(text(label).size(12)).padding([8, 48, 0, 48]));
+                for device in list {
+                    let Some(name) = device.name.clone() else {
+                        continue;
+                    };
+                    let hidden = self.config.hidden_devices.contains(&name);
+                    let label = self
+                        .config
+                        .device_names
+                        .get(&name)
+                        .map_or("", String::as_str);
+                    let rename_name = name.clone();
+                    devices = devices.push(
+                        row![
+                            text_input(pretty_name(device.description.clone()), label)
+                                .on_input(move |label| {
+                                    Message::RenameDevice(rename_name.clone(), label)
+                                })
+                                .on_submit(Message::SaveDeviceNames)
+                                .width(Length::Fill),
+                            button::icon(
+                                icon::from_name(if hidden {
+                                    "view-conceal-symbolic"
+                                } else {
+                                    "view-reveal-symbolic"
+                                })
+                                .size(16)
+                                .symbolic(true),
+                            )
+                            .style(cosmic::theme::Button::Icon)
+                            .on_press(Message::ToggleDeviceHidden(name)),
+                        ]
+                        .spacing(8)
+                        .align_items(Alignment::Center)
+                        .padding([4, 48]),
+                    );
+                }
+            }
+        }
+
+        let loopback_source = self.loopback_source();
+        let mut loopback = column![container(
+            anim!(
+                // toggler
+                LOOPBACK,
+                &self.timeline,
+                Some(fl!("monitor-input")),
+                self.loopback.is_some(),
+                Message::ToggleLoopback,
+            )
+            .text_size(14)
+            .width(Length::Fill)
+        )
+        .padding([0, 24])]
+        .spacing(8);
+        if self.loopback.is_some() {
+            let selected = self
+                .loopback_sources
+                .iter()
+                .position(|source| source.name == loopback_source);
+            loopback = loopback.push(
+                row![
+                    text(fl!("monitor-input-source"))
+                        .size(14)
+                        .width(Length::Fill),
+                    dropdown(
+                        &self.loopback_source_names,
+                        selected,
+                        Message::SetLoopbackSource
+                    ),
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center)
+                .padding([0, 24]),
+            );
+            loopback = loopback.push(
+                row![
+                    text(fl!("monitor-input-latency")).size(14),
+                    slider(
+                        10..=500,
+                        self.config.loopback_latency_msec,
+                        Message::SetLoopbackLatency
+                    )
+                    .on_release(Message::ApplyLoopbackLatency),
+                    text(format!("{} ms", self.config.loopback_latency_msec))
+                        .size(14)
+                        .width(Length::Fixed(56.0))
+                        .horizontal_alignment(Horizontal::Right),
+                ]
+                .spacing(12)
+                .align_items(Alignment::Center)
+                .padding([0, 24]),
+            );
+        }
+
+        let selected_preset = EqualizerPreset::ALL
+            .iter()
+            .position(|preset| *preset == self.config.equalizer_preset);
+        let mut equalizer_content = column![row![
+            text(fl!("equalizer")).size(14).width(Length::Fill),
+            dropdown(
+                &self.equalizer_preset_labels,
+                selected_preset,
+                Message::SetEqualizerPreset
+            ),
+        ]
+        .spacing(8)
+        .align_items(Alignment::Center)
+        .padding([0, 24])]
+        .spacing(8);
+        if self.config.equalizer_preset == EqualizerPreset::Custom {
+            let bands: Vec<Element<Message>> = equalizer::BANDS
+                .iter()
+                .zip(self.equalizer_gains)
+                .enumerate()
+                .map(|(i, (frequency, gain))| {
+                    column![
+                        // Sliders need values convertible from `u8`
+                        vertical_slider(
+                            -i16::from(equalizer::MAX_GAIN)..=i16::from(equalizer::MAX_GAIN),
+                            i16::from(gain),
+                            move |gain| Message::SetEqualizerGain(i, gain as i8)
+                        )
+                        .height(Length::Fixed(96.0)),
+                        text(equalizer::band_label(*frequency)).size(10),
+                    ]
+                    .spacing(4)
+                    .align_items(Alignment::Center)
+                    .width(Length::Fill)
+                    .into()
+                })
+                .collect();
+            equalizer_content = equalizer_content.push(Row::with_children(bands).padding([0, 24]));
+            let changed = self.equalizer_gains != self.config.equalizer_custom;
+            equalizer_content = equalizer_content.push(
+                row![
+                    horizontal_space(Length::Fill),
+                    button::standard(fl!("equalizer-save"))
+                        .on_press_maybe(changed.then_some(Message::SaveEqualizerPreset)),
+                ]
+                .padding([0, 24]),
+            );
+        }
+
+        if !self.recording.is_empty() {
+            audio_content = audio_content
+                .push(padded_control(divider::horizontal::default()))
+                .push(self.recording_view());
+        }
+
         let content = column![
             audio_content,
             padded_control(divider::horizontal::default()),
+            auto_switch,
+            devices,
+            padded_control(divider::horizontal::default()),
+            loopback,
+            padded_control(divider::horizontal::default()),
+            equalizer_content,
+            padded_control(divider::horizontal::default()),
             container(
                 anim!(
                     // toggler
@@ -868,11 +1615,7 @@ impl cosmic::Application for Audio {
         .align_items(Alignment::Start)
         .padding([8, 0]);
 
-        self.core.applet.popup_container(container(content)).into()
-    }
-
-    fn on_close_requested(&self, id: window::Id) -> Option<Message> {
-        Some(Message::CloseRequested(id))
+        Element::from(content).map(AppletMessage::App)
     }
 }
 
@@ -901,6 +1644,26 @@ fn revealer(
     }
 }
 
+/// Shown next to the panel button while an app records audio.
+fn recording_dot() -> Element<'static, Message> {
+    container(widget::vertical_space(Length::Fixed(0.0)))
+        .padding(3.0)
+        .style(<Theme as container::StyleSheet>::Style::Custom(Box::new(
+            |theme| container::Appearance {
+                text_color: Some(Color::TRANSPARENT),
+                background: Some(Background::Color(theme.cosmic().destructive_color().into())),
+                border: Border {
+                    radius: 3.0.into(),
+                    width: 0.0,
+                    color: Color::TRANSPARENT,
+                },
+                shadow: Shadow::default(),
+                icon_color: Some(Color::TRANSPARENT),
+            },
+        )))
+        .into()
+}
+
 fn revealer_head(
     _open: bool,
     title: String,
@@ -914,6 +1677,32 @@ fn revealer_head(
     .on_press(toggle)
 }
 
+fn device_kind_label(kind: DeviceKind) -> String {
+    match kind {
+        DeviceKind::Headphones => fl!("headphones"),
+        DeviceKind::Hdmi => fl!("hdmi"),
+        DeviceKind::Bluetooth => fl!("bluetooth"),
+        DeviceKind::Usb => fl!("usb"),
+    }
+}
+
+fn equalizer_preset_label(preset: EqualizerPreset) -> String {
+    match preset {
+        EqualizerPreset::Flat => fl!("equalizer-flat"),
+        EqualizerPreset::BassBoost => fl!("equalizer-bass-boost"),
+        EqualizerPreset::Voice => fl!("equalizer-voice"),
+        EqualizerPreset::Custom => fl!("equalizer-custom"),
+    }
+}
+
+fn auto_switch_label(rule: AutoSwitch) -> String {
+    match rule {
+        AutoSwitch::Default => fl!("auto-switch-default"),
+        AutoSwitch::Always => fl!("auto-switch-always"),
+        AutoSwitch::Never => fl!("auto-switch-never"),
+    }
+}
+
 fn pretty_name(name: Option<String>) -> String {
     match name {
         Some(n) => n,
@@ -956,15 +1745,3 @@ impl Default for IsOpen {
         Self::None
     }
 }
-
-fn volume_to_percent(volume: Volume) -> f64 {
-    volume.0 as f64 * 100. / Volume::NORMAL.0 as f64
-}
-
-fn percent_to_volume(percent: f64) -> Volume {
-    Volume(
-        (percent / 100. * Volume::NORMAL.0 as f64)
-            .clamp(0., Volume::NORMAL.0 as f64)
-            .round() as u32,
-    )
-}
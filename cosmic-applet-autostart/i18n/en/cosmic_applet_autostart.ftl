@@ -0,0 +1,4 @@
+start-on-login = Start on login
+loading = Loading...
+no-entries = No startup applications
+failed = Failed: { $error }
@@ -0,0 +1,159 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! XDG autostart entries. Entries are turned off by writing an override with
+//! `Hidden=true` to the user's autostart directory, which takes precedence
+//! over the system-wide one.
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use freedesktop_desktop_entry::{get_languages_from_env, DesktopEntry};
+
+const DESKTOP: &str = "COSMIC";
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub file_name: String,
+    pub name: String,
+    pub comment: Option<String>,
+    pub icon: Option<String>,
+    pub enabled: bool,
+}
+
+fn user_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("autostart"))
+}
+
+/// System directories, most important first.
+fn system_dirs() -> Vec<PathBuf> {
+    std::env::var("XDG_CONFIG_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/etc/xdg".to_string())
+        .split(':')
+        .map(|dir| Path::new(dir).join("autostart"))
+        .collect()
+}
+
+/// Paths of every entry by file name, with overrides applied.
+fn paths() -> BTreeMap<String, PathBuf> {
+    let mut paths = BTreeMap::new();
+    let dirs = system_dirs().into_iter().rev().chain(user_dir());
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if file_name.ends_with(".desktop") {
+                paths.insert(file_name, entry.path());
+            }
+        }
+    }
+    paths
+}
+
+/// Whether a semicolon separated list contains this desktop.
+fn lists_desktop(list: Option<&str>) -> bool {
+    list.map_or(false, |list| list.split(';').any(|d| d == DESKTOP))
+}
+
+pub async fn entries() -> Vec<Entry> {
+    tokio::task::spawn_blocking(|| {
+        let locales = get_languages_from_env();
+        let mut entries = paths()
+            .into_iter()
+            .filter_map(|(file_name, path)| {
+                let data = fs::read_to_string(&path).ok()?;
+                let entry = DesktopEntry::from_str(&path, &data, &locales).ok()?;
+                // Entries meant for other desktops never start here
+                if (entry.desktop_entry("OnlyShowIn").is_some()
+                    && !lists_desktop(entry.desktop_entry("OnlyShowIn")))
+                    || lists_desktop(entry.desktop_entry("NotShowIn"))
+                {
+                    return None;
+                }
+                Some(Entry {
+                    name: entry
+                        .name(&locales)
+                        .map(|name| name.to_string())
+                        .unwrap_or_else(|| file_name.trim_end_matches(".desktop").to_string()),
+                    comment: entry.comment(&locales).map(|c| c.to_string()),
+                    icon: entry.icon().map(str::to_string),
+                    enabled: entry.desktop_entry("Hidden") != Some("true")
+                        && entry.desktop_entry("X-GNOME-Autostart-enabled") != Some("false"),
+                    file_name,
+                })
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+        entries
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Sets `key` in the `[Desktop Entry]` group, or removes it if `value` is
+/// `None`, keeping the rest of the file as it was.
+fn set_key(contents: &str, key: &str, value: Option<&str>) -> String {
+    let mut lines = Vec::new();
+    let mut in_main = false;
+    let mut done = false;
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            if in_main && !done {
+                lines.extend(value.map(|value| format!("{key}={value}")));
+                done = true;
+            }
+            in_main = trimmed == "[Desktop Entry]";
+        } else if in_main
+            && trimmed
+                .split_once('=')
+                .map_or(false, |(k, _)| k.trim() == key)
+        {
+            if !done {
+                lines.extend(value.map(|value| format!("{key}={value}")));
+                done = true;
+            }
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+    if in_main && !done {
+        lines.extend(value.map(|value| format!("{key}={value}")));
+    }
+    lines.join("\n") + "\n"
+}
+
+pub async fn set_enabled(file_name: String, enabled: bool) -> io::Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let dir = user_dir().ok_or_else(|| io::Error::other("no config directory"))?;
+        let source = paths()
+            .remove(&file_name)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+        let contents = fs::read_to_string(source)?;
+        let contents = set_key(
+            &contents,
+            "Hidden",
+            Some(if enabled { "false" } else { "true" }),
+        );
+        let contents = if enabled {
+            set_key(&contents, "X-GNOME-Autostart-enabled", None)
+        } else {
+            contents
+        };
+
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(&file_name);
+        let tmp = dir.join(format!(".{file_name}.tmp"));
+        fs::write(&tmp, contents)?;
+        fs::rename(tmp, path)
+    })
+    .await
+    .map_err(io::Error::other)?
+}
@@ -0,0 +1,153 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::{
+    autostart::{self, Entry},
+    fl,
+};
+use cosmic::{
+    app::Core,
+    applet::padded_control,
+    iced::{
+        widget::{column, row},
+        Alignment, Command, Length,
+    },
+    iced_widget::Column,
+    widget::{icon, scrollable, text, toggler},
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent};
+
+const ID: &str = "com.system76.CosmicAppletAutostart";
+const ICON: &str = "com.system76.CosmicAppletAutostart-symbolic";
+
+#[derive(Default)]
+pub struct Window {
+    entries: Vec<Entry>,
+    loading: bool,
+    error: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    Entries(Vec<Entry>),
+    Toggle(usize, bool),
+    Toggled(Result<(), String>),
+}
+
+impl Window {
+    fn load(&mut self) -> Command<Message> {
+        self.loading = true;
+        Command::perform(autostart::entries(), Message::Entries)
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        (Self::default(), Command::none())
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        if event == PanelEvent::PopupOpened {
+            self.error = None;
+            return self.load();
+        }
+        Command::none()
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::Entries(entries) => {
+                self.loading = false;
+                self.entries = entries;
+            }
+            Message::Toggle(i, enabled) => {
+                let Some(entry) = self.entries.get_mut(i) else {
+                    return Command::none();
+                };
+                entry.enabled = enabled;
+                self.error = None;
+                return Command::perform(
+                    autostart::set_enabled(entry.file_name.clone(), enabled),
+                    |res| Message::Toggled(res.map_err(|err| err.to_string())),
+                );
+            }
+            Message::Toggled(res) => {
+                if let Err(err) = res {
+                    tracing::error!("Failed to write autostart override: {}", err);
+                    self.error = Some(err);
+                    // Show what's actually on disk
+                    return self.load();
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        core.applet
+            .icon_button(ICON)
+            .on_press(AppletMessage::TogglePopup)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = vec![padded_control(text(fl!("start-on-login")).size(14)).into()];
+
+        if self.entries.is_empty() {
+            content.push(
+                padded_control(
+                    text(if self.loading {
+                        fl!("loading")
+                    } else {
+                        fl!("no-entries")
+                    })
+                    .size(10),
+                )
+                .into(),
+            );
+        } else {
+            let entries = self
+                .entries
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| {
+                    let mut label = column![text(&entry.name).size(14)];
+                    if let Some(comment) = &entry.comment {
+                        label = label.push(text(comment).size(10));
+                    }
+                    padded_control(
+                        row![
+                            icon::from_name(
+                                entry.icon.as_deref().unwrap_or("application-x-executable")
+                            )
+                            .size(24),
+                            label.width(Length::Fill),
+                            toggler(None, entry.enabled, move |enabled| {
+                                Message::Toggle(i, enabled).into()
+                            })
+                            .width(Length::Shrink),
+                        ]
+                        .spacing(8)
+                        .align_items(Alignment::Center),
+                    )
+                    .into()
+                })
+                .collect::<Vec<_>>();
+            content.push(
+                scrollable(Column::with_children(entries))
+                    .height(Length::Shrink)
+                    .into(),
+            );
+        }
+
+        if let Some(err) = &self.error {
+            content.push(padded_control(text(fl!("failed", error = err.as_str())).size(10)).into());
+        }
+
+        Column::with_children(content).padding([8, 0]).into()
+    }
+}
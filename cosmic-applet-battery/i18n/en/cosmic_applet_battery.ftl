@@ -12,4 +12,31 @@ hours = h
 until-empty = until empty
 power-settings = Power and Battery Settings...
 dgpu-running = Discrete GPU is active and can reduce battery life
-dgpu-applications = Applications using {$gpu_name} discrete GPU
\ No newline at end of file
+dgpu-applications = Applications using {$gpu_name} discrete GPU
+suspend = Suspend
+suspend-then-hibernate = Suspend then hibernate
+hybrid-sleep = Hybrid sleep
+lid-close-action = When the lid is closed
+notify = Only warn
+hibernate = Hibernate
+power-off = Power off
+critical-action = When the battery is critical
+critical-percentage = Critical level
+critical-battery = Battery critically low ({ $percent }%)
+critical-battery-notify = Plug in the computer to keep working.
+critical-battery-suspend = The computer will suspend in a minute unless it's plugged in.
+critical-battery-hibernate = The computer will hibernate in a minute unless it's plugged in.
+critical-battery-power-off = The computer will power off in a minute unless it's plugged in.
+ups = UPS
+ups-on-battery = On battery
+ups-on-mains = On mains power
+ups-load = Load { $load }%
+ups-alarms = Warn when the UPS is on battery
+ups-primary = Show the UPS as the battery
+ups-on-battery-alarm = { $name } is on battery
+ups-on-battery-body = Mains power is out. Save your work.
+ups-on-battery-body-runtime = Mains power is out. The battery lasts about { $runtime }.
+ups-low = { $name } battery low
+ups-low-body = Save your work and shut down the computer.
+ups-restored = Power restored
+ups-restored-body = { $name } is back on mains power.
@@ -2,30 +2,37 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use crate::{
-    backend::{power_profile_subscription, Power, PowerProfileRequest, PowerProfileUpdate},
-    config,
+    backend::{
+        logind, notifications, power_profile_subscription,
+        ups::{ups_subscription, Ups},
+        Power, PowerProfileRequest, PowerProfileUpdate,
+    },
+    config::{self, BatteryConfig, CriticalAction, SleepAction},
     dgpu::{dgpu_subscription, Entry, GpuUpdate},
     fl,
 };
 use cosmic::{
+    app::Core,
     applet::{
         cosmic_panel_config::PanelAnchor,
         menu_button, padded_control,
         token::subscription::{activation_token_subscription, TokenRequest, TokenUpdate},
     },
     cctk::sctk::reexports::calloop,
+    cosmic_config::{Config, CosmicConfigEntry},
     iced::{
         alignment::Horizontal,
-        wayland::popup::{destroy_popup, get_popup},
         widget::{column, container, row, slider, text},
-        window, Alignment, Length, Subscription,
+        window::Id,
+        Alignment, Command, Length, Limits, Subscription,
     },
     iced_core::{alignment::Vertical, Background, Border, Color, Shadow},
-    iced_runtime::core::layout::Limits,
-    iced_style::application,
     iced_widget::{Column, Row},
-    widget::{divider, horizontal_space, icon, scrollable, vertical_space},
-    Command, Element, Theme,
+    widget::{divider, dropdown, horizontal_space, icon, scrollable, toggler, vertical_space},
+    Element, Theme,
+};
+use cosmic_applet_helper::{
+    AppletMessage, CosmicApplet, Osd, OsdMessage, PanelEvent, PopupRequest, PopupWidth,
 };
 use cosmic_settings_subscriptions::{
     settings_daemon,
@@ -58,9 +65,38 @@ fn format_duration(duration: Duration) -> String {
 }
 
 pub fn run() -> cosmic::iced::Result {
-    cosmic::applet::run::<CosmicBatteryApplet>(true, ())
+    cosmic_applet_helper::run_applet::<CosmicBatteryApplet>()
+}
+
+fn sleep_action_label(action: SleepAction) -> String {
+    match action {
+        SleepAction::Suspend => fl!("suspend"),
+        SleepAction::SuspendThenHibernate => fl!("suspend-then-hibernate"),
+        SleepAction::HybridSleep => fl!("hybrid-sleep"),
+    }
+}
+
+fn critical_action_label(action: CriticalAction) -> String {
+    match action {
+        CriticalAction::Notify => fl!("notify"),
+        CriticalAction::Suspend => fl!("suspend"),
+        CriticalAction::Hibernate => fl!("hibernate"),
+        CriticalAction::PowerOff => fl!("power-off"),
+    }
+}
+
+/// The UPS's model, if it's known.
+fn ups_name(ups: &Ups) -> String {
+    if ups.name.is_empty() {
+        fl!("ups")
+    } else {
+        ups.name.clone()
+    }
 }
 
+/// How long plugging in stops the critical battery action.
+const CRITICAL_GRACE: Duration = Duration::from_secs(60);
+
 static MAX_CHARGE: Lazy<id::Toggler> = Lazy::new(id::Toggler::unique);
 
 #[derive(Clone, Default)]
@@ -72,7 +108,6 @@ struct GPUData {
 
 #[derive(Clone, Default)]
 struct CosmicBatteryApplet {
-    core: cosmic::app::Core,
     icon_name: String,
     display_icon_name: String,
     charging_limit: bool,
@@ -84,7 +119,8 @@ struct CosmicBatteryApplet {
     kbd_brightness: Option<i32>,
     max_screen_brightness: Option<i32>,
     screen_brightness: Option<i32>,
-    popup: Option<window::Id>,
+    /// Whether the popup is open.
+    popup_open: bool,
     settings_daemon_sender: Option<UnboundedSender<settings_daemon::Request>>,
     kbd_sender: Option<UnboundedSender<KeyboardBacklightRequest>>,
     power_profile: Power,
@@ -92,6 +128,23 @@ struct CosmicBatteryApplet {
     timeline: Timeline,
     token_tx: Option<calloop::channel::Sender<TokenRequest>>,
     zbus_connection: Option<zbus::Connection>,
+    config: BatteryConfig,
+    config_helper: Option<Config>,
+    /// Sleep actions logind supports, and their labels.
+    sleep_actions: Vec<SleepAction>,
+    sleep_action_labels: Vec<String>,
+    /// Critical battery actions logind supports, and their labels.
+    critical_actions: Vec<CriticalAction>,
+    critical_action_labels: Vec<String>,
+    /// Whether the battery was critical since last charging, so it's only
+    /// acted on once.
+    critical: bool,
+    /// Shows brightness changes made outside of the popup, like with the
+    /// brightness keys.
+    osd: Osd,
+    upses: Vec<Ups>,
+    /// Whether UPower found no battery, so the applet only runs to show a UPS.
+    no_battery: bool,
 }
 
 impl CosmicBatteryApplet {
@@ -151,6 +204,161 @@ impl CosmicBatteryApplet {
             format!("cosmic-applet-battery-display-brightness-{screen_brightness}-symbolic",);
     }
 
+    /// Whether a changed brightness should be shown in the overlay: the popup's
+    /// sliders show it already, and the first value read isn't a change.
+    fn shows_brightness_change(&self, previous: Option<i32>, brightness: i32) -> bool {
+        !self.popup_open && previous.map_or(false, |previous| previous != brightness)
+    }
+
+    fn is_critical(&self) -> bool {
+        self.on_battery && self.battery_percent <= self.config.critical_percentage as f64
+    }
+
+    /// Warns once the battery turns critical, and carries out the critical
+    /// action if it still is after [`CRITICAL_GRACE`].
+    fn check_critical(&mut self) -> Command<Message> {
+        if !self.is_critical() {
+            self.critical = false;
+            return Command::none();
+        }
+        if self.critical {
+            return Command::none();
+        }
+        self.critical = true;
+
+        let action = self.config.critical_action;
+        let body = match action {
+            CriticalAction::Notify => fl!("critical-battery-notify"),
+            CriticalAction::Suspend => fl!("critical-battery-suspend"),
+            CriticalAction::Hibernate => fl!("critical-battery-hibernate"),
+            CriticalAction::PowerOff => fl!("critical-battery-power-off"),
+        };
+        let summary = fl!("critical-battery", percent = self.battery_percent.round());
+        tokio::spawn(async move {
+            if let Err(err) = notifications::critical_battery(summary, body).await {
+                tracing::error!(?err, "Failed to notify about the critical battery");
+            }
+        });
+
+        if action == CriticalAction::Notify {
+            return Command::none();
+        }
+        Command::perform(tokio::time::sleep(CRITICAL_GRACE), |_| {
+            Message::CriticalGraceOver
+        })
+    }
+
+    /// Warns about UPSes switching to their battery, running low, and mains
+    /// power coming back.
+    fn ups_alarms(&self, upses: &[Ups]) {
+        if !self.config.ups_alarms {
+            return;
+        }
+        for ups in upses {
+            let previous = self.upses.iter().find(|u| u.name == ups.name);
+            let was_on_battery = previous.is_some_and(|u| u.on_battery);
+            let was_low = previous.is_some_and(|u| u.low);
+            let name = ups_name(ups);
+            let alarm = if ups.low && !was_low {
+                Some((fl!("ups-low", name = name.as_str()), fl!("ups-low-body")))
+            } else if ups.on_battery && !was_on_battery {
+                let body = match ups.runtime {
+                    Some(runtime) => fl!(
+                        "ups-on-battery-body-runtime",
+                        runtime = format_duration(runtime)
+                    ),
+                    None => fl!("ups-on-battery-body"),
+                };
+                Some((fl!("ups-on-battery-alarm", name = name.as_str()), body))
+            } else if !ups.on_battery && was_on_battery {
+                Some((
+                    fl!("ups-restored"),
+                    fl!("ups-restored-body", name = name.as_str()),
+                ))
+            } else {
+                None
+            };
+            if let Some((summary, body)) = alarm {
+                tokio::spawn(async move {
+                    if let Err(err) = notifications::ups_alarm(summary, body).await {
+                        tracing::error!(?err, "Failed to notify about the UPS");
+                    }
+                });
+            }
+        }
+    }
+
+    /// Whether the UPS is shown like a battery.
+    fn ups_is_primary(&self) -> bool {
+        self.no_battery && self.config.ups_primary
+    }
+
+    fn ups_view(&self) -> Vec<Element<Message>> {
+        let mut content = Vec::with_capacity(self.upses.len() + 3);
+        for ups in &self.upses {
+            let mut details = vec![format!("{:.0}%", ups.percentage)];
+            if ups.on_battery {
+                details.push(fl!("ups-on-battery"));
+                if let Some(runtime) = ups.runtime {
+                    details.push(format!(
+                        "{} {}",
+                        format_duration(runtime),
+                        fl!("until-empty")
+                    ));
+                }
+            } else {
+                details.push(fl!("ups-on-mains"));
+            }
+            if let Some(load) = ups.load {
+                details.push(fl!("ups-load", load = format!("{load:.0}")));
+            }
+            content.push(
+                padded_control(
+                    row![
+                        icon::from_name("uninterruptible-power-supply-symbolic")
+                            .size(24)
+                            .symbolic(true),
+                        column![
+                            text(ups_name(ups)).size(14),
+                            text(details.join(" · ")).size(10)
+                        ]
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+        }
+        content.push(
+            padded_control(
+                toggler(
+                    Some(fl!("ups-alarms")),
+                    self.config.ups_alarms,
+                    Message::SetUpsAlarms,
+                )
+                .text_size(14)
+                .width(Length::Fill),
+            )
+            .into(),
+        );
+        if self.no_battery {
+            content.push(
+                padded_control(
+                    toggler(
+                        Some(fl!("ups-primary")),
+                        self.config.ups_primary,
+                        Message::SetUpsPrimary,
+                    )
+                    .text_size(14)
+                    .width(Length::Fill),
+                )
+                .into(),
+            );
+        }
+        content.push(padded_control(divider::horizontal::default()).into());
+        content
+    }
+
     fn set_charging_limit(&mut self, limit: bool) {
         self.charging_limit = limit;
         self.update_battery(self.battery_percent, self.on_battery);
@@ -159,8 +367,6 @@ impl CosmicBatteryApplet {
 
 #[derive(Debug, Clone)]
 enum Message {
-    TogglePopup,
-    CloseRequested(window::Id),
     SetKbdBrightness(i32),
     SetScreenBrightness(i32),
     SetChargingLimit(chain::Toggler, bool),
@@ -178,48 +384,78 @@ enum Message {
     OpenSettings,
     SettingsDaemon(settings_daemon::Event),
     ZbusConnection(zbus::Result<zbus::Connection>),
+    SleepActions(Vec<SleepAction>),
+    Sleep(SleepAction),
+    Slept(Result<(), String>),
+    SetLidCloseAction(usize),
+    CriticalActions(Vec<CriticalAction>),
+    SetCriticalAction(usize),
+    SetCriticalPercentage(u8),
+    /// Writes the percentage, once the slider is released.
+    ApplyCriticalPercentage,
+    CriticalGraceOver,
+    ConfigChanged(BatteryConfig),
+    Osd(OsdMessage),
+    Ups(Vec<Ups>),
+    SetUpsAlarms(bool),
+    SetUpsPrimary(bool),
 }
 
-impl cosmic::Application for CosmicBatteryApplet {
+impl CosmicApplet for CosmicBatteryApplet {
     type Message = Message;
-    type Executor = cosmic::SingleThreadExecutor;
-    type Flags = ();
     const APP_ID: &'static str = config::APP_ID;
 
-    fn init(
-        core: cosmic::app::Core,
-        _flags: Self::Flags,
-    ) -> (
-        Self,
-        cosmic::iced::Command<cosmic::app::Message<Self::Message>>,
-    ) {
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::CONFIG_ID, BatteryConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                BatteryConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
         (
             Self {
-                core,
                 icon_name: "battery-symbolic".to_string(),
                 display_icon_name: "display-brightness-symbolic".to_string(),
                 token_tx: None,
+                config,
+                config_helper,
 
                 ..Default::default()
             },
-            cosmic::iced::Command::perform(zbus::Connection::session(), |res| {
-                cosmic::app::Message::App(Message::ZbusConnection(res))
-            }),
+            Command::batch([
+                Command::perform(zbus::Connection::session(), Message::ZbusConnection),
+                Command::perform(logind::sleep_actions(), Message::SleepActions),
+                Command::perform(logind::critical_actions(), Message::CriticalActions),
+            ]),
         )
     }
 
-    fn core(&self) -> &cosmic::app::Core {
-        &self.core
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        self.popup_open = event == PanelEvent::PopupOpened;
+        if self.popup_open {
+            if let Some(tx) = &self.kbd_sender {
+                let _ = tx.send(KeyboardBacklightRequest::Get);
+            }
+            self.timeline = Timeline::new();
+            if let Some(tx) = self.power_profile_sender.as_ref() {
+                let _ = tx.send(PowerProfileRequest::Get);
+            }
+        }
+        Command::none()
     }
 
-    fn core_mut(&mut self) -> &mut cosmic::app::Core {
-        &mut self.core
+    fn on_surface_closed(&mut self, _core: &Core, id: Id) {
+        self.osd.closed(id);
     }
 
-    fn update(
-        &mut self,
-        message: Self::Message,
-    ) -> cosmic::iced::Command<cosmic::app::Message<Self::Message>> {
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
         match message {
             Message::Frame(now) => self.timeline.now(now),
             Message::SetKbdBrightness(brightness) => {
@@ -242,36 +478,6 @@ impl cosmic::Application for CosmicBatteryApplet {
             Message::Errored(why) => {
                 tracing::error!("{}", why);
             }
-            Message::TogglePopup => {
-                if let Some(p) = self.popup.take() {
-                    return destroy_popup(p);
-                } else {
-                    if let Some(tx) = &self.kbd_sender {
-                        let _ = tx.send(KeyboardBacklightRequest::Get);
-                    }
-                    self.timeline = Timeline::new();
-
-                    let new_id = window::Id::unique();
-                    self.popup.replace(new_id);
-
-                    let mut popup_settings = self.core.applet.get_popup_settings(
-                        window::Id::MAIN,
-                        new_id,
-                        None,
-                        None,
-                        None,
-                    );
-                    popup_settings.positioner.size_limits = Limits::NONE
-                        .max_width(372.0)
-                        .min_width(300.0)
-                        .min_height(200.0)
-                        .max_height(1080.0);
-                    if let Some(tx) = self.power_profile_sender.as_ref() {
-                        let _ = tx.send(PowerProfileRequest::Get);
-                    }
-                    return get_popup(popup_settings);
-                }
-            }
             Message::UpowerDevice(event) => match event {
                 DeviceDbusEvent::Update {
                     on_battery,
@@ -280,9 +486,14 @@ impl cosmic::Application for CosmicBatteryApplet {
                 } => {
                     self.update_battery(percent, on_battery);
                     self.time_remaining = Duration::from_secs(time_to_empty as u64);
+                    return self.check_critical();
                 }
                 DeviceDbusEvent::NoBattery => {
-                    std::process::exit(0);
+                    // Desktops only keep the applet for their UPS
+                    if !self.config.ups_primary {
+                        std::process::exit(0);
+                    }
+                    self.no_battery = true;
                 }
             },
             Message::KeyboardBacklight(event) => match event {
@@ -293,7 +504,16 @@ impl cosmic::Application for CosmicBatteryApplet {
                     self.max_kbd_brightness = Some(max_brightness);
                 }
                 KeyboardBacklightUpdate::Brightness(brightness) => {
-                    self.kbd_brightness = Some(brightness);
+                    let previous = self.kbd_brightness.replace(brightness);
+                    if let Some(max_brightness) = self.max_kbd_brightness {
+                        if self.shows_brightness_change(previous, brightness) {
+                            return self.osd.show(
+                                "keyboard-brightness-symbolic",
+                                100. * brightness as f64 / max_brightness.max(1) as f64,
+                                Message::Osd,
+                            );
+                        }
+                    }
                 }
             },
             Message::InitProfile(tx, profile) => {
@@ -311,11 +531,7 @@ impl cosmic::Application for CosmicBatteryApplet {
                     let _ = tx.send(PowerProfileRequest::Set(profile));
                 }
             }
-            Message::CloseRequested(id) => {
-                if Some(id) == self.popup {
-                    self.popup = None;
-                }
-            }
+            Message::Osd(message) => return self.osd.update(message, Message::Osd),
             Message::OpenSettings => {
                 let exec = "cosmic-settings power".to_string();
                 if let Some(tx) = self.token_tx.as_ref() {
@@ -373,6 +589,103 @@ impl cosmic::Application for CosmicBatteryApplet {
             Message::ZbusConnection(Ok(conn)) => {
                 self.zbus_connection = Some(conn);
             }
+            Message::SleepActions(actions) => {
+                self.sleep_action_labels =
+                    actions.iter().copied().map(sleep_action_label).collect();
+                self.sleep_actions = actions;
+            }
+            Message::Sleep(action) => {
+                return Command::perform(logind::sleep(action), |res| {
+                    Message::Slept(res.map_err(|e| e.to_string()))
+                });
+            }
+            Message::Slept(Err(why)) => {
+                tracing::error!("Failed to sleep: {}", why);
+            }
+            Message::Slept(Ok(())) => {}
+            Message::SetLidCloseAction(i) => {
+                let Some(&action) = self.sleep_actions.get(i) else {
+                    return Command::none();
+                };
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_lid_close_action(helper, action) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+            Message::CriticalActions(actions) => {
+                self.critical_action_labels =
+                    actions.iter().copied().map(critical_action_label).collect();
+                self.critical_actions = actions;
+            }
+            Message::SetCriticalAction(i) => {
+                let Some(&action) = self.critical_actions.get(i) else {
+                    return Command::none();
+                };
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_critical_action(helper, action) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+            Message::SetCriticalPercentage(percentage) => {
+                self.config.critical_percentage = percentage;
+            }
+            Message::ApplyCriticalPercentage => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.write_entry(helper) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+                return self.check_critical();
+            }
+            Message::CriticalGraceOver => {
+                // Plugging in or a changed configuration cancels it
+                if self.critical && self.is_critical() {
+                    let action = self.config.critical_action;
+                    tokio::spawn(async move {
+                        if let Err(err) = logind::critical(action).await {
+                            tracing::error!(
+                                ?err,
+                                "Failed to carry out the critical battery action"
+                            );
+                        }
+                    });
+                }
+            }
+            Message::ConfigChanged(config) => {
+                self.config = config;
+                if self.no_battery && !self.config.ups_primary {
+                    std::process::exit(0);
+                }
+                return self.check_critical();
+            }
+            Message::Ups(upses) => {
+                self.ups_alarms(&upses);
+                self.upses = upses;
+                if self.ups_is_primary() {
+                    if let Some(ups) = self.upses.first() {
+                        let (percentage, on_battery) = (ups.percentage, ups.on_battery);
+                        self.time_remaining = ups.runtime.unwrap_or_default();
+                        self.update_battery(percentage, on_battery);
+                        return self.check_critical();
+                    }
+                }
+            }
+            Message::SetUpsAlarms(alarms) => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_ups_alarms(helper, alarms) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+            Message::SetUpsPrimary(primary) => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_ups_primary(helper, primary) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
             Message::SettingsDaemon(event) => match event {
                 settings_daemon::Event::Sender(tx) => {
                     self.settings_daemon_sender = Some(tx);
@@ -381,19 +694,28 @@ impl cosmic::Application for CosmicBatteryApplet {
                     self.max_screen_brightness = Some(max_brightness);
                 }
                 settings_daemon::Event::DisplayBrightness(brightness) => {
-                    self.screen_brightness = Some(brightness);
+                    let previous = self.screen_brightness.replace(brightness);
+                    self.update_display();
+                    if self.shows_brightness_change(previous, brightness) {
+                        if let Some(percent) = self.screen_brightness_percent() {
+                            return self.osd.show(
+                                self.display_icon_name.clone(),
+                                percent * 100.,
+                                Message::Osd,
+                            );
+                        }
+                    }
                 }
             },
         }
         Command::none()
     }
 
-    fn view(&self) -> Element<Message> {
-        let btn = self
-            .core
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let btn = core
             .applet
             .icon_button(&self.icon_name)
-            .on_press(Message::TogglePopup)
+            .on_press(AppletMessage::TogglePopup)
             .into();
 
         if !self.gpus.is_empty() {
@@ -414,7 +736,7 @@ impl cosmic::Application for CosmicBatteryApplet {
                 )))
                 .into();
 
-            match self.core.applet.anchor {
+            match core.applet.anchor {
                 PanelAnchor::Left | PanelAnchor::Right => Column::with_children(vec![btn, dot])
                     .align_items(Alignment::Center)
                     .into(),
@@ -427,8 +749,20 @@ impl cosmic::Application for CosmicBatteryApplet {
         }
     }
 
-    fn view_window(&self, _id: window::Id) -> Element<Message> {
-        let name = text(fl!("battery")).size(14);
+    fn surface_view<'a>(&'a self, _core: &'a Core, id: Id) -> Element<'a, AppletMessage<Message>> {
+        if self.osd.is_surface(id) {
+            return self.osd.view();
+        }
+        row![].into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let name = text(if self.ups_is_primary() {
+            fl!("ups")
+        } else {
+            fl!("battery")
+        })
+        .size(14);
         let description = text(if !self.on_battery {
             format!("{}%", self.battery_percent)
         } else {
@@ -452,6 +786,11 @@ impl cosmic::Application for CosmicBatteryApplet {
             )
             .into(),
             padded_control(divider::horizontal::default()).into(),
+        ];
+        if !self.upses.is_empty() {
+            content.extend(self.ups_view());
+        }
+        content.extend([
             menu_button(
                 row![
                     column![
@@ -530,7 +869,7 @@ impl cosmic::Application for CosmicBatteryApplet {
             )
             .into(),
             padded_control(divider::horizontal::default()).into(),
-        ];
+        ]);
 
         if let Some(max_screen_brightness) = self.max_screen_brightness {
             if let Some(screen_brightness) = self.screen_brightness {
@@ -688,19 +1027,105 @@ impl cosmic::Application for CosmicBatteryApplet {
             content.push(padded_control(divider::horizontal::default()).into());
         }
 
+        let extra_sleep_actions = self
+            .sleep_actions
+            .iter()
+            .filter(|action| **action != SleepAction::Suspend)
+            .collect::<Vec<_>>();
+        for &action in &extra_sleep_actions {
+            content.push(
+                menu_button(
+                    text(sleep_action_label(action))
+                        .size(14)
+                        .width(Length::Fill),
+                )
+                .on_press(Message::Sleep(action))
+                .into(),
+            );
+        }
+        if self.sleep_actions.len() > 1 {
+            let selected = self
+                .sleep_actions
+                .iter()
+                .position(|action| *action == self.config.lid_close_action);
+            content.push(
+                padded_control(
+                    row![
+                        text(fl!("lid-close-action")).size(14).width(Length::Fill),
+                        dropdown(
+                            &self.sleep_action_labels,
+                            selected,
+                            Message::SetLidCloseAction
+                        ),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+        }
+        if self.critical_actions.len() > 1 {
+            let selected = self
+                .critical_actions
+                .iter()
+                .position(|action| *action == self.config.critical_action);
+            content.push(
+                padded_control(
+                    row![
+                        text(fl!("critical-action")).size(14).width(Length::Fill),
+                        dropdown(
+                            &self.critical_action_labels,
+                            selected,
+                            Message::SetCriticalAction
+                        ),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+        }
+        content.push(
+            padded_control(
+                row![
+                    text(fl!("critical-percentage")).size(14),
+                    slider(
+                        1..=20,
+                        self.config.critical_percentage,
+                        Message::SetCriticalPercentage
+                    )
+                    .on_release(Message::ApplyCriticalPercentage)
+                    .width(Length::Fill),
+                    text(format!("{}%", self.config.critical_percentage))
+                        .size(14)
+                        .width(Length::Fixed(36.0))
+                        .horizontal_alignment(Horizontal::Right),
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center),
+            )
+            .into(),
+        );
+        content.push(padded_control(divider::horizontal::default()).into());
+
         content.push(
             menu_button(text(fl!("power-settings")).size(14).width(Length::Fill))
                 .on_press(Message::OpenSettings)
                 .into(),
         );
 
-        self.core
-            .applet
-            .popup_container(Column::with_children(content).padding([8, 0]))
-            .into()
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+
+    fn popup_request(&self, message: &Message) -> Option<PopupRequest> {
+        matches!(message, Message::Sleep(_)).then_some(PopupRequest::Close)
+    }
+
+    fn popup_limits(&self, _core: &Core) -> Limits {
+        PopupWidth::Standard.limits().min_height(200.0)
     }
 
-    fn subscription(&self) -> Subscription<Message> {
+    fn subscription(&self, core: &Core) -> Subscription<Message> {
         let mut subscriptions = vec![
             device_subscription(0).map(Message::UpowerDevice),
             kbd_backlight_subscription(0).map(Message::KeyboardBacklight),
@@ -713,22 +1138,28 @@ impl cosmic::Application for CosmicBatteryApplet {
                 GpuUpdate::On(path, name, list) => Message::GpuOn(path, name, list),
                 GpuUpdate::Off(path) => Message::GpuOff(path),
             }),
-            self.timeline
-                .as_subscription()
-                .map(|(_, now)| Message::Frame(now)),
             activation_token_subscription(0).map(Message::Token),
+            ups_subscription(0).map(Message::Ups),
+            Osd::subscription().map(Message::Osd),
         ];
         if let Some(conn) = self.zbus_connection.clone() {
             subscriptions.push(settings_daemon::subscription(conn).map(Message::SettingsDaemon));
         }
+        if self.sleep_actions.contains(&self.config.lid_close_action) {
+            subscriptions.push(
+                logind::lid_subscription(0, self.config.lid_close_action).map(Message::Errored),
+            );
+        }
+        subscriptions.push(
+            core.watch_config::<BatteryConfig>(config::CONFIG_ID)
+                .map(|u| Message::ConfigChanged(u.config)),
+        );
         Subscription::batch(subscriptions)
     }
 
-    fn on_close_requested(&self, id: window::Id) -> Option<Message> {
-        Some(Message::CloseRequested(id))
-    }
-
-    fn style(&self) -> Option<<Theme as application::StyleSheet>::Style> {
-        Some(cosmic::applet::style())
+    fn popup_subscription(&self, _core: &Core) -> Subscription<Message> {
+        self.timeline
+            .as_subscription()
+            .map(|(_, now)| Message::Frame(now))
     }
 }
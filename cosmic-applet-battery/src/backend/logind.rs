@@ -0,0 +1,177 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Sleep actions through logind, and carrying out the configured one when the
+//! lid is closed or the battery is critically low.
+
+use std::{fmt::Debug, hash::Hash};
+
+use cosmic::iced::{
+    self,
+    futures::{SinkExt, StreamExt},
+    subscription,
+};
+use zbus::{proxy, zvariant::OwnedFd, Connection, Result};
+
+use crate::config::{CriticalAction, SleepAction};
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Manager {
+    fn can_suspend(&self) -> Result<String>;
+
+    fn can_suspend_then_hibernate(&self) -> Result<String>;
+
+    fn can_hybrid_sleep(&self) -> Result<String>;
+
+    fn can_hibernate(&self) -> Result<String>;
+
+    fn can_power_off(&self) -> Result<String>;
+
+    fn suspend(&self, interactive: bool) -> Result<()>;
+
+    fn suspend_then_hibernate(&self, interactive: bool) -> Result<()>;
+
+    fn hybrid_sleep(&self, interactive: bool) -> Result<()>;
+
+    fn hibernate(&self, interactive: bool) -> Result<()>;
+
+    fn power_off(&self, interactive: bool) -> Result<()>;
+
+    fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> Result<OwnedFd>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.UPower",
+    default_service = "org.freedesktop.UPower",
+    default_path = "/org/freedesktop/UPower"
+)]
+trait UPower {
+    #[zbus(property)]
+    fn lid_is_present(&self) -> Result<bool>;
+
+    #[zbus(property)]
+    fn lid_is_closed(&self) -> Result<bool>;
+}
+
+/// Whether logind would allow an action, possibly after authenticating.
+fn supported(answer: Result<String>) -> bool {
+    matches!(answer.as_deref(), Ok("yes" | "challenge"))
+}
+
+/// The sleep actions logind supports on this system.
+pub async fn sleep_actions() -> Vec<SleepAction> {
+    let Ok(conn) = Connection::system().await else {
+        return vec![SleepAction::Suspend];
+    };
+    let Ok(manager) = ManagerProxy::new(&conn).await else {
+        return vec![SleepAction::Suspend];
+    };
+    let mut actions = Vec::new();
+    if supported(manager.can_suspend().await) {
+        actions.push(SleepAction::Suspend);
+    }
+    if supported(manager.can_suspend_then_hibernate().await) {
+        actions.push(SleepAction::SuspendThenHibernate);
+    }
+    if supported(manager.can_hybrid_sleep().await) {
+        actions.push(SleepAction::HybridSleep);
+    }
+    actions
+}
+
+/// The critical battery actions logind supports on this system.
+pub async fn critical_actions() -> Vec<CriticalAction> {
+    let mut actions = vec![CriticalAction::Notify];
+    let Ok(conn) = Connection::system().await else {
+        return actions;
+    };
+    let Ok(manager) = ManagerProxy::new(&conn).await else {
+        return actions;
+    };
+    if supported(manager.can_suspend().await) {
+        actions.push(CriticalAction::Suspend);
+    }
+    if supported(manager.can_hibernate().await) {
+        actions.push(CriticalAction::Hibernate);
+    }
+    if supported(manager.can_power_off().await) {
+        actions.push(CriticalAction::PowerOff);
+    }
+    actions
+}
+
+/// Carries out `action` without asking to authenticate, as nobody may be
+/// there to answer.
+pub async fn critical(action: CriticalAction) -> Result<()> {
+    let conn = Connection::system().await?;
+    let manager = ManagerProxy::new(&conn).await?;
+    match action {
+        CriticalAction::Notify => Ok(()),
+        CriticalAction::Suspend => manager.suspend(false).await,
+        CriticalAction::Hibernate => manager.hibernate(false).await,
+        CriticalAction::PowerOff => manager.power_off(false).await,
+    }
+}
+
+async fn perform(manager: &ManagerProxy<'_>, action: SleepAction) -> Result<()> {
+    match action {
+        SleepAction::Suspend => manager.suspend(true).await,
+        SleepAction::SuspendThenHibernate => manager.suspend_then_hibernate(true).await,
+        SleepAction::HybridSleep => manager.hybrid_sleep(true).await,
+    }
+}
+
+pub async fn sleep(action: SleepAction) -> Result<()> {
+    let conn = Connection::system().await?;
+    let manager = ManagerProxy::new(&conn).await?;
+    perform(&manager, action).await
+}
+
+/// Takes over closing the lid from logind to put the system to sleep with
+/// `action` instead.
+///
+/// logind suspends on lid close by default, so this does nothing for
+/// [`SleepAction::Suspend`], leaving logind's own configuration in charge.
+pub fn lid_subscription<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+    action: SleepAction,
+) -> iced::Subscription<String> {
+    subscription::channel((id, action), 1, move |mut output| async move {
+        if action != SleepAction::Suspend {
+            if let Err(err) = handle_lid(action).await {
+                let _ = output.send(err.to_string()).await;
+            }
+        }
+        iced::futures::future::pending().await
+    })
+}
+
+async fn handle_lid(action: SleepAction) -> Result<()> {
+    let conn = Connection::system().await?;
+    let upower = UPowerProxy::new(&conn).await?;
+    if !upower.lid_is_present().await? {
+        return Ok(());
+    }
+    let manager = ManagerProxy::new(&conn).await?;
+    // Blocks logind from handling the lid switch for as long as it's held.
+    let _inhibitor = manager
+        .inhibit(
+            "handle-lid-switch",
+            "COSMIC Battery Applet",
+            "Closing the lid runs the configured sleep action",
+            "block",
+        )
+        .await?;
+
+    let mut closed = upower.receive_lid_is_closed_changed().await;
+    while let Some(change) = closed.next().await {
+        if change.get().await? {
+            perform(&manager, action).await?;
+        }
+    }
+    Ok(())
+}
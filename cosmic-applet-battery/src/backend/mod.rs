@@ -8,8 +8,11 @@ use zbus::{Connection, Result};
 
 use self::{power_daemon::PowerDaemonProxy, power_profiles::PowerProfilesProxy};
 
+pub mod logind;
+pub mod notifications;
 mod power_daemon;
 mod power_profiles;
+pub mod ups;
 
 #[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
 pub enum Power {
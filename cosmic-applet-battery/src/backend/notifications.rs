@@ -0,0 +1,54 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::collections::HashMap;
+
+use zbus::{proxy, zvariant::Value, Connection, Result};
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> Result<u32>;
+}
+
+/// Warns that the battery is critically low, until dismissed.
+pub async fn critical_battery(summary: String, body: String) -> Result<()> {
+    notify("battery-level-0-symbolic", &summary, &body).await
+}
+
+/// Warns about a UPS running on its battery, until dismissed.
+pub async fn ups_alarm(summary: String, body: String) -> Result<()> {
+    notify("uninterruptible-power-supply-symbolic", &summary, &body).await
+}
+
+async fn notify(icon: &str, summary: &str, body: &str) -> Result<()> {
+    let conn = Connection::session().await?;
+    NotificationsProxy::new(&conn)
+        .await?
+        .notify(
+            "Battery",
+            0,
+            icon,
+            summary,
+            body,
+            &[],
+            HashMap::from([("urgency", Value::from(2u8))]),
+            0,
+        )
+        .await?;
+    Ok(())
+}
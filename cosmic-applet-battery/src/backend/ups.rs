@@ -0,0 +1,161 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Uninterruptible power supplies, from UPower and from a local NUT server.
+//!
+//! UPower lists UPSes it drives itself, without their load. Those NUT drives
+//! are read with `upsc`, which has the load too. Both are polled, as UPSes
+//! report slowly anyway.
+
+use std::{fmt::Debug, hash::Hash, time::Duration};
+
+use cosmic::iced::{self, futures::SinkExt, subscription};
+use tokio::process::Command;
+use zbus::{proxy, zvariant::OwnedObjectPath, Connection, Result};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// UPower's `Type` of a UPS.
+const UPOWER_UPS: u32 = 3;
+/// UPower's `State` while discharging.
+const UPOWER_DISCHARGING: u32 = 2;
+/// UPower's `WarningLevel`s from low on.
+const UPOWER_LOW: u32 = 3;
+
+#[proxy(
+    interface = "org.freedesktop.UPower",
+    default_service = "org.freedesktop.UPower",
+    default_path = "/org/freedesktop/UPower"
+)]
+trait UPower {
+    fn enumerate_devices(&self) -> Result<Vec<OwnedObjectPath>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.UPower.Device",
+    default_service = "org.freedesktop.UPower"
+)]
+trait Device {
+    #[zbus(property, name = "Type")]
+    fn type_(&self) -> Result<u32>;
+
+    #[zbus(property)]
+    fn model(&self) -> Result<String>;
+
+    #[zbus(property)]
+    fn percentage(&self) -> Result<f64>;
+
+    #[zbus(property)]
+    fn state(&self) -> Result<u32>;
+
+    #[zbus(property)]
+    fn time_to_empty(&self) -> Result<i64>;
+
+    #[zbus(property)]
+    fn warning_level(&self) -> Result<u32>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ups {
+    pub name: String,
+    pub percentage: f64,
+    /// Whether mains power is out and the UPS is running on its battery.
+    pub on_battery: bool,
+    /// Whether the UPS reports its battery as low.
+    pub low: bool,
+    /// How long the battery is expected to last at the current load.
+    pub runtime: Option<Duration>,
+    /// The load, in percent of what the UPS can supply. Only NUT knows it.
+    pub load: Option<f64>,
+}
+
+/// The UPSes connected, each time they change.
+pub fn ups_subscription<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+) -> iced::Subscription<Vec<Ups>> {
+    subscription::channel(id, 5, move |mut output| async move {
+        let conn = Connection::system().await.ok();
+        let mut last = None;
+        loop {
+            let mut upses = match &conn {
+                Some(conn) => upower(conn).await.unwrap_or_else(|err| {
+                    tracing::debug!(?err, "Failed to list UPower's UPSes");
+                    Vec::new()
+                }),
+                None => Vec::new(),
+            };
+            upses.extend(nut().await);
+            if last.as_ref() != Some(&upses) {
+                _ = output.send(upses.clone()).await;
+                last = Some(upses);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    })
+}
+
+async fn upower(conn: &Connection) -> Result<Vec<Ups>> {
+    let mut upses = Vec::new();
+    for path in UPowerProxy::new(conn).await?.enumerate_devices().await? {
+        let device = DeviceProxy::builder(conn).path(path)?.build().await?;
+        if device.type_().await? != UPOWER_UPS {
+            continue;
+        }
+        let time_to_empty = device.time_to_empty().await.unwrap_or_default();
+        upses.push(Ups {
+            name: device.model().await.unwrap_or_default(),
+            percentage: device.percentage().await?,
+            on_battery: device.state().await? == UPOWER_DISCHARGING,
+            low: device.warning_level().await.unwrap_or_default() >= UPOWER_LOW,
+            runtime: (time_to_empty > 0).then(|| Duration::from_secs(time_to_empty as u64)),
+            load: None,
+        });
+    }
+    Ok(upses)
+}
+
+async fn upsc(args: &[&str]) -> Option<String> {
+    let output = Command::new("upsc").args(args).output().await.ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// UPSes of the local NUT server, if there is one.
+async fn nut() -> Vec<Ups> {
+    let Some(names) = upsc(&["-l"]).await else {
+        return Vec::new();
+    };
+    let mut upses = Vec::new();
+    for name in names.lines().map(str::trim).filter(|name| !name.is_empty()) {
+        let Some(variables) = upsc(&[name]).await else {
+            continue;
+        };
+        let variable = |key: &str| {
+            variables.lines().find_map(|line| {
+                let (k, v) = line.split_once(':')?;
+                (k.trim() == key).then(|| v.trim().to_string())
+            })
+        };
+        let number = |key: &str| variable(key).and_then(|v| v.parse::<f64>().ok());
+        let Some(percentage) = number("battery.charge") else {
+            continue;
+        };
+        let status = variable("ups.status").unwrap_or_default();
+        let has_status = |flag: &str| status.split_whitespace().any(|s| s == flag);
+        upses.push(Ups {
+            name: variable("device.model")
+                .or_else(|| variable("ups.model"))
+                .unwrap_or_else(|| name.to_string()),
+            percentage,
+            on_battery: has_status("OB"),
+            low: has_status("LB"),
+            runtime: number("battery.runtime")
+                .filter(|secs| *secs > 0.0)
+                .map(Duration::from_secs_f64),
+            load: number("ups.load"),
+        });
+    }
+    upses
+}
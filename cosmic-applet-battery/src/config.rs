@@ -1,4 +1,57 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
 pub const APP_ID: &str = "com.system76.CosmicAppletButton";
+pub const CONFIG_ID: &str = "com.system76.CosmicAppletBattery";
+
+/// Ways of putting the system to sleep.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub enum SleepAction {
+    #[default]
+    Suspend,
+    /// Suspend, and hibernate if the system is still asleep after a while.
+    SuspendThenHibernate,
+    /// Save to disk and suspend, so the session survives the battery running out.
+    HybridSleep,
+}
+
+/// What to do when the battery is critically low.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub enum CriticalAction {
+    /// Only warn, leaving UPower's own critical action in place.
+    #[default]
+    Notify,
+    Suspend,
+    Hibernate,
+    PowerOff,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, CosmicConfigEntry)]
+#[version = 1]
+pub struct BatteryConfig {
+    /// What closing the lid does.
+    pub lid_close_action: SleepAction,
+    /// What to do once the battery is discharging at or below
+    /// `critical_percentage`.
+    pub critical_action: CriticalAction,
+    pub critical_percentage: u8,
+    /// Show a UPS like a battery on computers without one.
+    pub ups_primary: bool,
+    /// Warn when a UPS switches to its battery, and when it runs low.
+    pub ups_alarms: bool,
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        Self {
+            lid_close_action: SleepAction::default(),
+            critical_action: CriticalAction::default(),
+            critical_percentage: 5,
+            ups_primary: false,
+            ups_alarms: true,
+        }
+    }
+}
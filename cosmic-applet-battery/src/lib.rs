@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 mod app;
-mod backend;
+pub mod backend;
 mod config;
 mod dgpu;
 mod localize;
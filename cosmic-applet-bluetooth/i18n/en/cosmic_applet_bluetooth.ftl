@@ -9,4 +9,8 @@ unsuccessful = Pairing Unsuccessful
 check-device = Make sure {$deviceName} is turned on, in range, and is ready to pair.
 try-again = Try Again
 discoverable = Discoverable
-pairable = Pairable
\ No newline at end of file
+pairable = Pairable
+show-all-devices = Show all devices
+battery = {$percentage}%
+heart-rate = {$bpm} bpm
+tethering = Connect to internet via this device
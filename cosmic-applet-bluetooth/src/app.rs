@@ -1,7 +1,6 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
-use crate::bluetooth::{BluerDeviceStatus, BluerRequest, BluerState};
 use cosmic::{
     applet::token::subscription::{activation_token_subscription, TokenRequest, TokenUpdate},
     cctk::sctk::reexports::calloop,
@@ -11,43 +10,44 @@ use cosmic::{
     applet::{menu_button, padded_control},
     iced::{
         self,
-        wayland::popup::{destroy_popup, get_popup},
         widget::{column, container, row, scrollable, text, Column},
         Alignment, Length, Subscription,
     },
-    iced_runtime::core::{
-        alignment::{Horizontal, Vertical},
-        layout::Limits,
-        window,
-    },
-    iced_style::application,
-    widget::{button, divider, icon},
-    Command, Element, Theme,
+    iced_runtime::core::alignment::{Horizontal, Vertical},
+    widget::{button, divider, icon, toggler},
+    Command, Element,
 };
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent};
 use cosmic_time::{anim, chain, id, once_cell::sync::Lazy, Instant, Timeline};
 use std::{collections::HashMap, time::Duration};
 use tokio::sync::mpsc::Sender;
 
-use crate::{
-    bluetooth::{bluetooth_subscription, BluerDevice, BluerEvent},
-    config, fl,
+use cosmic_bluez_client::{
+    bluetooth_subscription, BluerAgentEvent, BluerDevice, BluerDeviceStatus, BluerEvent,
+    BluerRequest, BluerState,
 };
 
+use crate::{config, fl};
+
 static BLUETOOTH_ENABLED: Lazy<id::Toggler> = Lazy::new(id::Toggler::unique);
 
+/// Devices weaker than this, in dBm, are most likely someone else's.
+const MIN_RSSI: i16 = -80;
+
 pub fn run() -> cosmic::iced::Result {
-    cosmic::applet::run::<CosmicBluetoothApplet>(false, ())
+    cosmic_applet_helper::run_applet::<CosmicBluetoothApplet>()
 }
 
 #[derive(Default)]
 struct CosmicBluetoothApplet {
-    core: cosmic::app::Core,
     icon_name: String,
-    popup: Option<window::Id>,
+    popup_open: bool,
     bluer_state: BluerState,
     bluer_sender: Option<Sender<BluerRequest>>,
     // UI state
     show_visible_devices: bool,
+    /// Whether to list unnamed and distant devices too.
+    show_all_devices: bool,
     request_confirmation: Option<(BluerDevice, String, Sender<bool>)>,
     token_tx: Option<calloop::channel::Sender<TokenRequest>>,
     timeline: Timeline,
@@ -66,9 +66,8 @@ impl CosmicBluetoothApplet {
 
 #[derive(Debug, Clone)]
 enum Message {
-    TogglePopup,
-    CloseRequested(window::Id),
     ToggleVisibleDevices(bool),
+    ShowAllDevices(bool),
     Ignore,
     BluetoothEvent(BluerEvent),
     Request(BluerRequest),
@@ -80,19 +79,14 @@ enum Message {
     ToggleBluetooth(chain::Toggler, bool),
 }
 
-impl cosmic::Application for CosmicBluetoothApplet {
+impl CosmicApplet for CosmicBluetoothApplet {
     type Message = Message;
-    type Executor = cosmic::SingleThreadExecutor;
-    type Flags = ();
     const APP_ID: &'static str = config::APP_ID;
+    const AUTOSIZE: bool = false;
 
-    fn init(
-        core: cosmic::app::Core,
-        _flags: Self::Flags,
-    ) -> (Self, iced::Command<cosmic::app::Message<Self::Message>>) {
+    fn init(_core: &cosmic::app::Core) -> (Self, iced::Command<Message>) {
         (
             Self {
-                core,
                 icon_name: "bluetooth-symbolic".to_string(),
                 token_tx: None,
                 ..Default::default()
@@ -101,59 +95,32 @@ impl cosmic::Application for CosmicBluetoothApplet {
         )
     }
 
-    fn core(&self) -> &cosmic::app::Core {
-        &self.core
-    }
-
-    fn core_mut(&mut self) -> &mut cosmic::app::Core {
-        &mut self.core
+    fn on_panel_event(&mut self, _core: &cosmic::app::Core, event: PanelEvent) -> Command<Message> {
+        self.popup_open = event == PanelEvent::PopupOpened;
+        if !self.popup_open {
+            return Command::none();
+        }
+        self.timeline = Timeline::new();
+        let tx = self.bluer_sender.as_ref().cloned();
+        iced::Command::perform(
+            async {
+                if let Some(tx) = tx {
+                    let _ = tx.send(BluerRequest::StateUpdate).await;
+                }
+            },
+            |_| Message::Ignore,
+        )
     }
 
-    fn update(
-        &mut self,
-        message: Self::Message,
-    ) -> iced::Command<cosmic::app::Message<Self::Message>> {
+    fn update(&mut self, _core: &cosmic::app::Core, message: Message) -> iced::Command<Message> {
         match message {
-            Message::TogglePopup => {
-                if let Some(p) = self.popup.take() {
-                    return destroy_popup(p);
-                } else {
-                    // TODO request update of state maybe
-                    let new_id = window::Id::unique();
-                    self.popup.replace(new_id);
-                    self.timeline = Timeline::new();
-
-                    let mut popup_settings = self.core.applet.get_popup_settings(
-                        window::Id::MAIN,
-                        new_id,
-                        None,
-                        None,
-                        None,
-                    );
-
-                    popup_settings.positioner.size_limits = Limits::NONE
-                        .min_height(1.0)
-                        .min_width(1.0)
-                        .max_height(800.0)
-                        .max_width(400.0);
-                    let tx = self.bluer_sender.as_ref().cloned();
-                    return Command::batch(vec![
-                        iced::Command::perform(
-                            async {
-                                if let Some(tx) = tx {
-                                    let _ = tx.send(BluerRequest::StateUpdate).await;
-                                }
-                            },
-                            |_| cosmic::app::message::app(Message::Ignore),
-                        ),
-                        get_popup(popup_settings),
-                    ]);
-                }
-            }
             Message::Ignore => {}
             Message::ToggleVisibleDevices(enabled) => {
                 self.show_visible_devices = enabled;
             }
+            Message::ShowAllDevices(enabled) => {
+                self.show_all_devices = enabled;
+            }
             Message::BluetoothEvent(e) => match e {
                 BluerEvent::RequestResponse {
                     req,
@@ -161,7 +128,7 @@ impl cosmic::Application for CosmicBluetoothApplet {
                     err_msg,
                 } => {
                     if let Some(err_msg) = err_msg {
-                        eprintln!("bluetooth request error: {}", err_msg);
+                        tracing::error!("bluetooth request error: {}", err_msg);
                     }
                     if self.bluer_state.bluetooth_enabled != state.bluetooth_enabled {
                         self.timeline
@@ -177,7 +144,7 @@ impl cosmic::Application for CosmicBluetoothApplet {
                     // TODO special handling for some requests
                     match req {
                         BluerRequest::StateUpdate
-                            if self.popup.is_some() && self.bluer_sender.is_some() =>
+                            if self.popup_open && self.bluer_sender.is_some() =>
                         {
                             let tx = self.bluer_sender.as_ref().cloned().unwrap();
                             tokio::spawn(async move {
@@ -198,30 +165,26 @@ impl cosmic::Application for CosmicBluetoothApplet {
                 }
                 BluerEvent::Finished => {
                     // TODO should this exit with an error causing a restart?
-                    eprintln!("bluetooth subscription finished. exiting...");
+                    tracing::info!("bluetooth subscription finished. exiting...");
                     std::process::exit(0);
                 }
                 // TODO handle agent events
                 BluerEvent::AgentEvent(event) => match event {
-                    crate::bluetooth::BluerAgentEvent::DisplayPinCode(_d, _code) => {}
-                    crate::bluetooth::BluerAgentEvent::DisplayPasskey(_d, _code) => {}
-                    crate::bluetooth::BluerAgentEvent::RequestPinCode(_d) => {
+                    BluerAgentEvent::DisplayPinCode(_d, _code) => {}
+                    BluerAgentEvent::DisplayPasskey(_d, _code) => {}
+                    BluerAgentEvent::RequestPinCode(_d) => {
                         // TODO anything to be done here?
                     }
-                    crate::bluetooth::BluerAgentEvent::RequestPasskey(_d) => {
+                    BluerAgentEvent::RequestPasskey(_d) => {
                         // TODO anything to be done here?
                     }
-                    crate::bluetooth::BluerAgentEvent::RequestConfirmation(d, code, tx) => {
+                    BluerAgentEvent::RequestConfirmation(d, code, tx) => {
                         self.request_confirmation.replace((d, code, tx));
                     }
-                    crate::bluetooth::BluerAgentEvent::RequestDeviceAuthorization(_d, _tx) => {
+                    BluerAgentEvent::RequestDeviceAuthorization(_d, _tx) => {
                         // TODO anything to be done here?
                     }
-                    crate::bluetooth::BluerAgentEvent::RequestServiceAuthorization(
-                        _d,
-                        _service,
-                        _tx,
-                    ) => {
+                    BluerAgentEvent::RequestServiceAuthorization(_d, _service, _tx) => {
                         // my headphones seem to always request this
                         // doesn't seem to be defined in the UX mockups
                         // dbg!(
@@ -291,11 +254,7 @@ impl cosmic::Application for CosmicBluetoothApplet {
                     });
                 }
             }
-            Message::CloseRequested(id) => {
-                if Some(id) == self.popup {
-                    self.popup = None;
-                }
-            }
+
             Message::OpenSettings => {
                 let exec = "cosmic-settings bluetooth".to_string();
                 if let Some(tx) = self.token_tx.as_ref() {
@@ -340,15 +299,17 @@ impl cosmic::Application for CosmicBluetoothApplet {
         Command::none()
     }
 
-    fn view(&self) -> Element<Message> {
-        self.core
-            .applet
+    fn view<'a>(&'a self, core: &'a cosmic::app::Core) -> Element<'a, AppletMessage<Message>> {
+        core.applet
             .icon_button(&self.icon_name)
-            .on_press(Message::TogglePopup)
+            .on_press(AppletMessage::TogglePopup)
             .into()
     }
 
-    fn view_window(&self, _id: window::Id) -> Element<Message> {
+    fn popup_view<'a>(
+        &'a self,
+        core: &'a cosmic::app::Core,
+    ) -> Element<'a, AppletMessage<Message>> {
         let mut known_bluetooth = vec![];
         for dev in self.bluer_state.devices.iter().filter(|d| {
             !self
@@ -369,6 +330,23 @@ impl cosmic::Application for CosmicBluetoothApplet {
 
             match &dev.status {
                 BluerDeviceStatus::Connected => {
+                    if let Some(bpm) = dev.heart_rate {
+                        row = row.push(
+                            text(fl!("heart-rate", HashMap::from_iter(vec![("bpm", bpm)])))
+                                .size(14)
+                                .vertical_alignment(Vertical::Center),
+                        );
+                    }
+                    if let Some(percentage) = dev.battery {
+                        row = row.push(
+                            text(fl!(
+                                "battery",
+                                HashMap::from_iter(vec![("percentage", percentage)])
+                            ))
+                            .size(14)
+                            .vertical_alignment(Vertical::Center),
+                        );
+                    }
                     row = row.push(
                         text(fl!("connected"))
                             .size(14)
@@ -393,6 +371,9 @@ impl cosmic::Application for CosmicBluetoothApplet {
                         BluerDeviceStatus::Connected => {
                             Message::Request(BluerRequest::DisconnectDevice(dev.address))
                         }
+                        BluerDeviceStatus::Disconnected if dev.le_only => {
+                            Message::Request(BluerRequest::ConnectDevice(dev.address))
+                        }
                         BluerDeviceStatus::Disconnected => {
                             Message::Request(BluerRequest::PairDevice(dev.address))
                         }
@@ -407,6 +388,24 @@ impl cosmic::Application for CosmicBluetoothApplet {
                     })
                     .into(),
             );
+            if dev.nap && dev.status == BluerDeviceStatus::Connected {
+                let address = dev.address;
+                known_bluetooth.push(
+                    padded_control(
+                        toggler(Some(fl!("tethering")), dev.tethered, move |enabled| {
+                            Message::Request(if enabled {
+                                BluerRequest::ConnectTethering(address)
+                            } else {
+                                BluerRequest::DisconnectTethering(address)
+                            })
+                        })
+                        .text_size(14)
+                        .width(Length::Fill),
+                    )
+                    .padding([8, 48])
+                    .into(),
+                );
+            }
         }
 
         let mut content = column![column![padded_control(
@@ -504,7 +503,7 @@ impl cosmic::Application for CosmicBluetoothApplet {
                         .width(Length::Fill)
                         .on_press(Message::Confirm),
                     ]
-                    .spacing(self.core.system_theme().cosmic().space_xxs())
+                    .spacing(core.system_theme().cosmic().space_xxs())
                     .width(Length::Fill)
                     .align_items(Alignment::Center)
                 )
@@ -514,16 +513,34 @@ impl cosmic::Application for CosmicBluetoothApplet {
         }
         let mut visible_devices_count = 0;
         if self.show_visible_devices && self.bluer_state.bluetooth_enabled {
-            let mut visible_devices = column![];
-            for dev in self.bluer_state.devices.iter().filter(|d| {
-                matches!(
-                    d.status,
-                    BluerDeviceStatus::Disconnected | BluerDeviceStatus::Pairing
-                ) && !self
-                    .request_confirmation
-                    .as_ref()
-                    .map_or(false, |(dev, _, _)| d.address == dev.address)
-            }) {
+            let mut visible_devices = column![padded_control(
+                toggler(
+                    Some(fl!("show-all-devices")),
+                    self.show_all_devices,
+                    Message::ShowAllDevices
+                )
+                .text_size(14)
+                .width(Length::Fill)
+            )];
+            let mut devices: Vec<_> = self
+                .bluer_state
+                .devices
+                .iter()
+                .filter(|d| {
+                    matches!(
+                        d.status,
+                        BluerDeviceStatus::Disconnected | BluerDeviceStatus::Pairing
+                    ) && !self
+                        .request_confirmation
+                        .as_ref()
+                        .map_or(false, |(dev, _, _)| d.address == dev.address)
+                        && (self.show_all_devices
+                            || d.has_name && d.rssi.map_or(true, |rssi| rssi >= MIN_RSSI))
+                })
+                .collect();
+            // Strongest first, then devices whose signal isn't known
+            devices.sort_by_key(|d| std::cmp::Reverse(d.rssi.unwrap_or(i16::MIN)));
+            for dev in devices {
                 let row = row![
                     icon::from_name(dev.icon.as_str()).size(16).symbolic(true),
                     text(dev.name.clone())
@@ -532,10 +549,14 @@ impl cosmic::Application for CosmicBluetoothApplet {
                 ]
                 .align_items(Alignment::Center)
                 .spacing(12);
-                visible_devices = visible_devices.push(
-                    menu_button(row.width(Length::Fill))
-                        .on_press(Message::Request(BluerRequest::PairDevice(dev.address))),
-                );
+                // LE devices connect without pairing, and pair if they ask to
+                let request = if dev.le_only {
+                    BluerRequest::ConnectDevice(dev.address)
+                } else {
+                    BluerRequest::PairDevice(dev.address)
+                };
+                visible_devices = visible_devices
+                    .push(menu_button(row.width(Length::Fill)).on_press(Message::Request(request)));
                 visible_devices_count += 1;
             }
             list_column.push(visible_devices.into());
@@ -555,24 +576,19 @@ impl cosmic::Application for CosmicBluetoothApplet {
             content = content.push(Column::with_children(list_column));
         }
 
-        self.core.applet.popup_container(content).into()
+        Element::from(content).map(AppletMessage::App)
     }
 
-    fn subscription(&self) -> Subscription<Message> {
+    fn subscription(&self, _core: &cosmic::app::Core) -> Subscription<Message> {
         Subscription::batch(vec![
             activation_token_subscription(0).map(Message::Token),
             bluetooth_subscription(0).map(Message::BluetoothEvent),
-            self.timeline
-                .as_subscription()
-                .map(|(_, now)| Message::Frame(now)),
         ])
     }
 
-    fn style(&self) -> Option<<Theme as application::StyleSheet>::Style> {
-        Some(cosmic::applet::style())
-    }
-
-    fn on_close_requested(&self, id: window::Id) -> Option<Message> {
-        Some(Message::CloseRequested(id))
+    fn popup_subscription(&self, _core: &cosmic::app::Core) -> Subscription<Message> {
+        self.timeline
+            .as_subscription()
+            .map(|(_, now)| Message::Frame(now))
     }
 }
@@ -0,0 +1,14 @@
+keep-awake = Keep awake
+inhibit-reason = Keeping the screen awake
+off = The screen turns off when idle
+until-turned-off = Until turned off
+minutes-remaining = { $minutes ->
+    [one] 1 minute remaining
+    *[other] { $minutes } minutes remaining
+}
+for-minutes = For { $minutes } minutes
+for-hours = { $hours ->
+    [one] For 1 hour
+    *[other] For { $hours } hours
+}
+also-keeping-awake = Also keeping the screen awake
@@ -0,0 +1,58 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Idle inhibitor locks, through logind.
+
+use std::sync::Arc;
+
+use zbus::{proxy, zvariant::OwnedFd, Connection};
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Manager {
+    /// Inhibit method
+    fn inhibit(&self, what: &str, who: &str, why: &str, mode: &str) -> zbus::Result<OwnedFd>;
+
+    /// ListInhibitors method
+    #[allow(clippy::type_complexity)]
+    fn list_inhibitors(&self) -> zbus::Result<Vec<(String, String, String, String, u32, u32)>>;
+}
+
+/// Held idle inhibitor, released when the last clone is dropped.
+#[derive(Debug, Clone)]
+pub struct Inhibitor(#[allow(dead_code)] Arc<OwnedFd>);
+
+pub async fn inhibit(why: String) -> zbus::Result<Inhibitor> {
+    let connection = Connection::system().await?;
+    let fd = ManagerProxy::new(&connection)
+        .await?
+        .inhibit("idle", "COSMIC", &why, "block")
+        .await?;
+    Ok(Inhibitor(Arc::new(fd)))
+}
+
+#[derive(Debug, Clone)]
+pub struct OtherInhibitor {
+    pub who: String,
+    pub why: String,
+}
+
+/// Idle inhibitors held by other applications.
+pub async fn other_inhibitors() -> zbus::Result<Vec<OtherInhibitor>> {
+    let connection = Connection::system().await?;
+    let inhibitors = ManagerProxy::new(&connection)
+        .await?
+        .list_inhibitors()
+        .await?;
+    let pid = std::process::id();
+    Ok(inhibitors
+        .into_iter()
+        .filter(|(what, .., inhibitor_pid)| {
+            *inhibitor_pid != pid && what.split(':').any(|w| w == "idle")
+        })
+        .map(|(_, who, why, ..)| OtherInhibitor { who, why })
+        .collect())
+}
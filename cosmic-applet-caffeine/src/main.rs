@@ -0,0 +1,17 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic_applet_helper::logging;
+
+const APPLET: &str = env!("CARGO_PKG_NAME");
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+fn main() -> cosmic::iced::Result {
+    logging::init(APPLET);
+    let _span = logging::applet_span().entered();
+
+    tracing::info!("Starting caffeine applet with version {VERSION}");
+    cosmic_applet_helper::install_panic_hook(APPLET, VERSION);
+
+    cosmic_applet_caffeine::run()
+}
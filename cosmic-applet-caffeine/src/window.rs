@@ -0,0 +1,235 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    fl,
+    inhibit::{inhibit, other_inhibitors, Inhibitor, OtherInhibitor},
+};
+use cosmic::{
+    app::Core,
+    applet::{menu_button, padded_control},
+    iced::{
+        time,
+        widget::{column, mouse_area, row},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_widget::Column,
+    widget::{container, divider, horizontal_space, icon, text, toggler},
+    Element,
+};
+use cosmic_applet_helper::{icon_button, AppletMessage, CosmicApplet, PanelEvent};
+
+const ID: &str = "com.system76.CosmicAppletCaffeine";
+const ICON: &str = "com.system76.CosmicAppletCaffeine-symbolic";
+const ACTIVE_ICON: &str = "com.system76.CosmicAppletCaffeine-active-symbolic";
+const DURATIONS: [u64; 2] = [30, 60];
+/// How often a timed inhibition is checked for expiry while the popup, which
+/// counts down every second, is closed.
+const EXPIRY_CHECK: Duration = Duration::from_secs(15);
+
+#[derive(Default)]
+pub struct Window {
+    inhibitor: Option<Inhibitor>,
+    /// When a timed inhibition ends.
+    until: Option<Instant>,
+    /// Whether a request for an inhibitor is in flight.
+    pending: bool,
+    others: Vec<OtherInhibitor>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    Toggle,
+    /// Stay awake for this many minutes, or until turned off if `None`.
+    Enable(Option<u64>),
+    Inhibited(Result<Inhibitor, String>),
+    OtherInhibitors(Vec<OtherInhibitor>),
+    Tick,
+}
+
+impl Window {
+    fn enable(&mut self, minutes: Option<u64>) -> Command<Message> {
+        self.until = minutes.map(|m| Instant::now() + Duration::from_secs(m * 60));
+        if self.inhibitor.is_some() || self.pending {
+            return Command::none();
+        }
+        self.pending = true;
+        Command::perform(inhibit(fl!("inhibit-reason")), |res| {
+            Message::Inhibited(res.map_err(|err| err.to_string()))
+        })
+    }
+
+    fn disable(&mut self) {
+        self.inhibitor = None;
+        self.until = None;
+    }
+
+    fn remaining(&self) -> Option<Duration> {
+        self.until
+            .map(|until| until.saturating_duration_since(Instant::now()))
+    }
+
+    fn status(&self) -> String {
+        match (self.inhibitor.is_some(), self.remaining()) {
+            (false, _) => fl!("off"),
+            (true, None) => fl!("until-turned-off"),
+            (true, Some(remaining)) => {
+                let minutes = remaining.as_secs().div_ceil(60);
+                fl!("minutes-remaining", minutes = minutes)
+            }
+        }
+    }
+
+    fn refresh_others() -> Command<Message> {
+        Command::perform(other_inhibitors(), |res| {
+            let others = res.unwrap_or_else(|err| {
+                tracing::error!("Failed to list idle inhibitors: {}", err);
+                Vec::new()
+            });
+            Message::OtherInhibitors(others)
+        })
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        (Self::default(), Command::none())
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        match event {
+            PanelEvent::PopupOpened => Self::refresh_others(),
+            PanelEvent::PopupClosed => Command::none(),
+        }
+    }
+
+    fn subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        if self.until.is_some() && self.inhibitor.is_some() {
+            time::every(EXPIRY_CHECK).map(|_| Message::Tick)
+        } else {
+            Subscription::none()
+        }
+    }
+
+    fn popup_subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        if self.until.is_some() && self.inhibitor.is_some() {
+            time::every(Duration::from_secs(1)).map(|_| Message::Tick)
+        } else {
+            Subscription::none()
+        }
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::Toggle => {
+                if self.inhibitor.is_some() {
+                    self.disable();
+                } else {
+                    return self.enable(None);
+                }
+            }
+            Message::Enable(minutes) => {
+                return self.enable(minutes);
+            }
+            Message::Inhibited(res) => {
+                self.pending = false;
+                match res {
+                    Ok(inhibitor) => self.inhibitor = Some(inhibitor),
+                    Err(err) => {
+                        tracing::error!("Failed to inhibit idle: {}", err);
+                        self.until = None;
+                    }
+                }
+            }
+            Message::OtherInhibitors(others) => {
+                self.others = others;
+            }
+            Message::Tick => {
+                if self.remaining() == Some(Duration::ZERO) {
+                    self.disable();
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        // Middle click toggles without opening the popup
+        mouse_area(
+            icon_button(
+                core,
+                if self.inhibitor.is_some() {
+                    ACTIVE_ICON
+                } else {
+                    ICON
+                },
+                fl!("keep-awake"),
+            )
+            .on_press(AppletMessage::TogglePopup),
+        )
+        .on_middle_press(Message::Toggle.into())
+        .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = vec![
+            padded_control(
+                column![
+                    toggler(Some(fl!("keep-awake")), self.inhibitor.is_some(), |_| {
+                        Message::Toggle.into()
+                    })
+                    .text_size(14)
+                    .width(Length::Fill),
+                    text(self.status()).size(10),
+                ]
+                .spacing(2),
+            )
+            .into(),
+            padded_control(divider::horizontal::default()).into(),
+        ];
+
+        for minutes in DURATIONS {
+            let label = if minutes % 60 == 0 {
+                fl!("for-hours", hours = minutes / 60)
+            } else {
+                fl!("for-minutes", minutes = minutes)
+            };
+            content.push(
+                menu_button(text(label).size(14))
+                    .on_press(Message::Enable(Some(minutes)).into())
+                    .into(),
+            );
+        }
+        content.push(
+            menu_button(text(fl!("until-turned-off")).size(14))
+                .on_press(Message::Enable(None).into())
+                .into(),
+        );
+
+        if !self.others.is_empty() {
+            content.push(padded_control(divider::horizontal::default()).into());
+            content.push(padded_control(text(fl!("also-keeping-awake")).size(14)).into());
+            for other in &self.others {
+                content.push(
+                    padded_control(
+                        row![
+                            container(icon::from_name("dialog-information-symbolic").size(16)),
+                            column![text(&other.who).size(14), text(&other.why).size(10)],
+                            horizontal_space(Length::Fill),
+                        ]
+                        .spacing(8)
+                        .align_items(Alignment::Center),
+                    )
+                    .into(),
+                );
+            }
+        }
+
+        Column::with_children(content).padding([8, 0]).into()
+    }
+}
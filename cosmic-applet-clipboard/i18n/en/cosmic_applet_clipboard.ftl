@@ -0,0 +1,5 @@
+search = Search clipboard history...
+empty = Nothing has been copied yet
+no-matches = No matching entries
+privacy-mode = Ignore password managers
+clear-history = Clear history
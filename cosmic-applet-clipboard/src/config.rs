@@ -0,0 +1,25 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
+pub const APP_ID: &str = "com.system76.CosmicAppletClipboard";
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, CosmicConfigEntry)]
+#[version = 1]
+pub struct ClipboardConfig {
+    /// Don't record entries copied from password managers.
+    pub privacy_mode: bool,
+    /// Number of unpinned entries to keep.
+    pub max_entries: usize,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            privacy_mode: true,
+            max_entries: 50,
+        }
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::sync::Arc;
+
+use cosmic::widget::image;
+
+const TEXT_MIME_TYPES: &[&str] = &[
+    "text/plain;charset=utf-8",
+    "text/plain",
+    "UTF8_STRING",
+    "STRING",
+    "TEXT",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Contents {
+    Text(String),
+    Image { mime_type: String, data: Arc<[u8]> },
+}
+
+impl Contents {
+    /// Picks the mime type to read from a selection offering `mime_types`.
+    pub fn preferred_mime_type(mime_types: &[String]) -> Option<&str> {
+        TEXT_MIME_TYPES
+            .iter()
+            .find_map(|text| mime_types.iter().find(|m| m == text))
+            .or_else(|| mime_types.iter().find(|m| *m == "image/png"))
+            .or_else(|| mime_types.iter().find(|m| m.starts_with("image/")))
+            .map(String::as_str)
+    }
+
+    /// Builds contents from data read as `mime_type`, skipping empty text.
+    pub fn from_data(mime_type: &str, data: Vec<u8>) -> Option<Self> {
+        if mime_type.starts_with("image/") {
+            return Some(Contents::Image {
+                mime_type: mime_type.to_string(),
+                data: data.into(),
+            });
+        }
+        let text = String::from_utf8(data).ok()?;
+        (!text.trim().is_empty()).then_some(Contents::Text(text))
+    }
+
+    pub fn mime_types(&self) -> Vec<String> {
+        match self {
+            Contents::Text(_) => TEXT_MIME_TYPES.iter().map(|m| m.to_string()).collect(),
+            Contents::Image { mime_type, .. } => vec![mime_type.clone()],
+        }
+    }
+
+    pub fn data(&self) -> Arc<[u8]> {
+        match self {
+            Contents::Text(text) => text.as_bytes().into(),
+            Contents::Image { data, .. } => data.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub id: u64,
+    pub contents: Contents,
+    pub pinned: bool,
+    /// Decoded once, rather than on every redraw.
+    pub image: Option<image::Handle>,
+}
+
+impl Entry {
+    /// Single line summary of text entries.
+    pub fn preview(&self) -> Option<String> {
+        let Contents::Text(text) = &self.contents else {
+            return None;
+        };
+        let line = text.trim().lines().next().unwrap_or_default();
+        let mut preview = line.chars().take(80).collect::<String>();
+        if preview.len() < line.len() || text.trim().lines().nth(1).is_some() {
+            preview.push('…');
+        }
+        Some(preview)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<Entry>,
+    next_id: u64,
+}
+
+impl History {
+    /// Adds newly copied contents to the top, moving it if it was already
+    /// in the history.
+    pub fn push(&mut self, contents: Contents, max_entries: usize) {
+        let pinned = if let Some(i) = self.entries.iter().position(|e| e.contents == contents) {
+            self.entries.remove(i).pinned
+        } else {
+            false
+        };
+        let image = match &contents {
+            Contents::Image { data, .. } => Some(image::Handle::from_memory(data.to_vec())),
+            Contents::Text(_) => None,
+        };
+        self.entries.insert(
+            0,
+            Entry {
+                id: self.next_id,
+                contents,
+                pinned,
+                image,
+            },
+        );
+        self.next_id += 1;
+        self.truncate(max_entries);
+    }
+
+    /// Drops the oldest unpinned entries beyond `max_entries`.
+    pub fn truncate(&mut self, max_entries: usize) {
+        let mut unpinned = 0;
+        self.entries.retain(|entry| {
+            if entry.pinned {
+                return true;
+            }
+            unpinned += 1;
+            unpinned <= max_entries
+        });
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    pub fn toggle_pin(&mut self, id: u64) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.id == id) {
+            entry.pinned = !entry.pinned;
+        }
+    }
+
+    pub fn remove(&mut self, id: u64) {
+        self.entries.retain(|e| e.id != id);
+    }
+
+    /// Removes everything that isn't pinned.
+    pub fn clear(&mut self) {
+        self.entries.retain(|e| e.pinned);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Pinned entries first, then the most recent. Images are hidden while
+    /// searching.
+    pub fn search<'a>(&'a self, query: &'a str) -> impl Iterator<Item = &'a Entry> + 'a {
+        let query = query.to_lowercase();
+        let matches = move |entry: &&Entry| match &entry.contents {
+            Contents::Text(text) => query.is_empty() || text.to_lowercase().contains(&query),
+            Contents::Image { .. } => query.is_empty(),
+        };
+        let pinned = self.entries.iter().filter(|e| e.pinned);
+        let unpinned = self.entries.iter().filter(|e| !e.pinned);
+        pinned.chain(unpinned).filter(matches)
+    }
+}
@@ -0,0 +1,337 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Watches and sets the clipboard through the wlr-data-control protocol.
+
+use std::{
+    fs::File,
+    io::{Read, Write},
+    os::{
+        fd::{AsFd, FromRawFd, RawFd},
+        unix::net::UnixStream,
+    },
+    sync::Mutex,
+};
+
+use cosmic::{
+    cctk::{
+        sctk::{
+            self,
+            reexports::{calloop, calloop_wayland_source::WaylandSource},
+            registry::{ProvidesRegistryState, RegistryState},
+            seat::{SeatHandler, SeatState},
+        },
+        wayland_client::{
+            event_created_child, globals::registry_queue_init, protocol::wl_seat::WlSeat,
+            Connection, Dispatch, Proxy, QueueHandle,
+        },
+    },
+    iced_futures::futures::channel::mpsc::UnboundedSender,
+};
+use wayland_protocols_wlr::data_control::v1::client::{
+    zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1},
+    zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+    zwlr_data_control_offer_v1::{self, ZwlrDataControlOfferV1},
+    zwlr_data_control_source_v1::{self, ZwlrDataControlSourceV1},
+};
+
+use crate::{
+    history::Contents,
+    wayland_subscription::{WaylandRequest, WaylandUpdate},
+};
+
+/// Mime types password managers add to mark a copied secret.
+const SECRET_MIME_TYPES: &[&str] = &[
+    "x-kde-passwordManagerHint",
+    "application/x-nspasteboard-concealed-type",
+];
+
+/// Larger selections are not kept in the history.
+const MAX_SIZE: u64 = 16 * 1024 * 1024;
+
+struct AppData {
+    exit: bool,
+    tx: UnboundedSender<WaylandUpdate>,
+    conn: Connection,
+    queue_handle: QueueHandle<Self>,
+    registry_state: RegistryState,
+    seat_state: SeatState,
+    manager: ZwlrDataControlManagerV1,
+    devices: Vec<(WlSeat, ZwlrDataControlDeviceV1)>,
+    privacy_mode: bool,
+}
+
+impl AppData {
+    fn add_device(&mut self, seat: WlSeat) {
+        let device = self.manager.get_data_device(&seat, &self.queue_handle, ());
+        self.devices.push((seat, device));
+    }
+
+    /// Reads the new selection and sends it to the applet.
+    fn receive(&self, offer: ZwlrDataControlOfferV1) {
+        let mime_types = offer
+            .data::<Mutex<Vec<String>>>()
+            .map(|mime_types| mime_types.lock().unwrap().clone())
+            .unwrap_or_default();
+
+        if self.privacy_mode
+            && mime_types
+                .iter()
+                .any(|m| SECRET_MIME_TYPES.contains(&m.as_str()))
+        {
+            offer.destroy();
+            return;
+        }
+
+        let Some(mime_type) = Contents::preferred_mime_type(&mime_types).map(str::to_string) else {
+            offer.destroy();
+            return;
+        };
+
+        let (read, write) = match rustix::pipe::pipe_with(rustix::pipe::PipeFlags::CLOEXEC) {
+            Ok(pipe) => pipe,
+            Err(err) => {
+                tracing::error!("Failed to create pipe for selection: {err}");
+                offer.destroy();
+                return;
+            }
+        };
+        offer.receive(mime_type.clone(), write.as_fd());
+        offer.destroy();
+        drop(write);
+        if let Err(err) = self.conn.flush() {
+            tracing::error!("Failed to flush wayland connection: {err}");
+        }
+
+        let tx = self.tx.clone();
+        std::thread::spawn(move || {
+            let mut data = Vec::new();
+            if let Err(err) = File::from(read).take(MAX_SIZE + 1).read_to_end(&mut data) {
+                tracing::error!("Failed to read selection: {err}");
+                return;
+            }
+            if data.len() as u64 > MAX_SIZE {
+                return;
+            }
+            if let Some(contents) = Contents::from_data(&mime_type, data) {
+                let _ = tx.unbounded_send(WaylandUpdate::Selection(contents));
+            }
+        });
+    }
+
+    fn set_selection(&self, contents: Contents) {
+        for (_, device) in &self.devices {
+            let source = self
+                .manager
+                .create_data_source(&self.queue_handle, contents.clone());
+            for mime_type in contents.mime_types() {
+                source.offer(mime_type);
+            }
+            device.set_selection(Some(&source));
+        }
+    }
+}
+
+impl ProvidesRegistryState for AppData {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+
+    sctk::registry_handlers!();
+}
+
+impl SeatHandler for AppData {
+    fn seat_state(&mut self) -> &mut SeatState {
+        &mut self.seat_state
+    }
+
+    fn new_seat(&mut self, _: &Connection, _: &QueueHandle<Self>, seat: WlSeat) {
+        self.add_device(seat);
+    }
+
+    fn new_capability(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: WlSeat,
+        _: sctk::seat::Capability,
+    ) {
+    }
+
+    fn remove_capability(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: WlSeat,
+        _: sctk::seat::Capability,
+    ) {
+    }
+
+    fn remove_seat(&mut self, _: &Connection, _: &QueueHandle<Self>, seat: WlSeat) {
+        self.devices.retain(|(s, device)| {
+            if s == &seat {
+                device.destroy();
+            }
+            s != &seat
+        });
+    }
+}
+
+impl Dispatch<ZwlrDataControlManagerV1, ()> for AppData {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrDataControlManagerV1,
+        _: <ZwlrDataControlManagerV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrDataControlDeviceV1, ()> for AppData {
+    fn event(
+        state: &mut Self,
+        device: &ZwlrDataControlDeviceV1,
+        event: zwlr_data_control_device_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_device_v1::Event::Selection { id: Some(offer) } => {
+                state.receive(offer);
+            }
+            zwlr_data_control_device_v1::Event::PrimarySelection { id: Some(offer) } => {
+                offer.destroy();
+            }
+            zwlr_data_control_device_v1::Event::Finished => {
+                state.devices.retain(|(_, d)| d != device);
+                device.destroy();
+            }
+            _ => {}
+        }
+    }
+
+    event_created_child!(AppData, ZwlrDataControlDeviceV1, [
+        zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ZwlrDataControlOfferV1, Mutex::new(Vec::new())),
+    ]);
+}
+
+impl Dispatch<ZwlrDataControlOfferV1, Mutex<Vec<String>>> for AppData {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrDataControlOfferV1,
+        event: zwlr_data_control_offer_v1::Event,
+        mime_types: &Mutex<Vec<String>>,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_data_control_offer_v1::Event::Offer { mime_type } = event {
+            mime_types.lock().unwrap().push(mime_type);
+        }
+    }
+}
+
+impl Dispatch<ZwlrDataControlSourceV1, Contents> for AppData {
+    fn event(
+        _: &mut Self,
+        source: &ZwlrDataControlSourceV1,
+        event: zwlr_data_control_source_v1::Event,
+        contents: &Contents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_data_control_source_v1::Event::Send { fd, .. } => {
+                let data = contents.data();
+                std::thread::spawn(move || {
+                    if let Err(err) = File::from(fd).write_all(&data) {
+                        tracing::error!("Failed to send selection: {err}");
+                    }
+                });
+            }
+            zwlr_data_control_source_v1::Event::Cancelled => source.destroy(),
+            _ => {}
+        }
+    }
+}
+
+pub(crate) fn wayland_handler(
+    tx: UnboundedSender<WaylandUpdate>,
+    rx: calloop::channel::Channel<WaylandRequest>,
+) {
+    let socket = std::env::var("X_PRIVILEGED_WAYLAND_SOCKET")
+        .ok()
+        .and_then(|fd| {
+            fd.parse::<RawFd>()
+                .ok()
+                .map(|fd| unsafe { UnixStream::from_raw_fd(fd) })
+        });
+
+    let conn = if let Some(socket) = socket {
+        Connection::from_socket(socket).unwrap()
+    } else {
+        Connection::connect_to_env().unwrap()
+    };
+    let (globals, event_queue) = registry_queue_init(&conn).unwrap();
+
+    let mut event_loop = calloop::EventLoop::<AppData>::try_new().unwrap();
+    let qh = event_queue.handle();
+    let wayland_source = WaylandSource::new(conn.clone(), event_queue);
+    let handle = event_loop.handle();
+    wayland_source
+        .insert(handle.clone())
+        .expect("Failed to insert wayland source.");
+
+    if handle
+        .insert_source(rx, |event, _, state| match event {
+            calloop::channel::Event::Msg(req) => match req {
+                WaylandRequest::SetSelection(contents) => state.set_selection(contents),
+                WaylandRequest::PrivacyMode(privacy_mode) => state.privacy_mode = privacy_mode,
+            },
+            calloop::channel::Event::Closed => {
+                state.exit = true;
+            }
+        })
+        .is_err()
+    {
+        return;
+    }
+
+    let manager = match globals.bind::<ZwlrDataControlManagerV1, _, _>(&qh, 1..=2, ()) {
+        Ok(manager) => manager,
+        Err(err) => {
+            tracing::error!("Compositor doesn't support wlr-data-control: {err}");
+            return;
+        }
+    };
+
+    let seat_state = SeatState::new(&globals, &qh);
+    let seats = seat_state.seats().collect::<Vec<_>>();
+    let mut app_data = AppData {
+        exit: false,
+        tx,
+        conn,
+        queue_handle: qh,
+        registry_state: RegistryState::new(&globals),
+        seat_state,
+        manager,
+        devices: Vec::new(),
+        // Until the applet sends its config
+        privacy_mode: true,
+    };
+    for seat in seats {
+        app_data.add_device(seat);
+    }
+
+    loop {
+        if app_data.exit {
+            break;
+        }
+        event_loop.dispatch(None, &mut app_data).unwrap();
+    }
+}
+
+sctk::delegate_seat!(AppData);
+sctk::delegate_registry!(AppData);
@@ -0,0 +1,246 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::{
+    config::{self, ClipboardConfig},
+    fl,
+    history::History,
+    wayland_subscription::{wayland_subscription, WaylandRequest, WaylandUpdate},
+};
+use cosmic::{
+    app::Core,
+    applet::{menu_button, padded_control},
+    cctk::sctk::reexports::calloop,
+    cosmic_config::{Config, CosmicConfigEntry},
+    iced::{widget::row, Alignment, Command, Length, Limits, Subscription},
+    iced_widget::Column,
+    widget::{button, divider, icon, image, scrollable, search_input, text, toggler},
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent, PopupRequest, PopupWidth};
+
+const ICON: &str = "com.system76.CosmicAppletClipboard-symbolic";
+
+#[derive(Default)]
+pub struct Window {
+    config: ClipboardConfig,
+    config_helper: Option<Config>,
+    history: History,
+    search: String,
+    wayland_tx: Option<calloop::channel::Sender<WaylandRequest>>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    ConfigChanged(ClipboardConfig),
+    Wayland(WaylandUpdate),
+    Search(String),
+    Paste(u64),
+    TogglePin(u64),
+    Remove(u64),
+    Clear,
+    PrivacyMode(bool),
+}
+
+impl Window {
+    fn send(&self, request: WaylandRequest) {
+        if let Some(tx) = self.wayland_tx.as_ref() {
+            let _ = tx.send(request);
+        } else {
+            tracing::error!("Wayland tx is None");
+        }
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = config::APP_ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::APP_ID, ClipboardConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                ClipboardConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let window = Self {
+            config,
+            config_helper,
+            ..Default::default()
+        };
+        (window, Command::none())
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        if event == PanelEvent::PopupOpened {
+            self.search.clear();
+        }
+        Command::none()
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        Subscription::batch(vec![
+            wayland_subscription().map(Message::Wayland),
+            core.watch_config::<ClipboardConfig>(config::APP_ID)
+                .map(|u| Message::ConfigChanged(u.config)),
+        ])
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::ConfigChanged(config) => {
+                if config.privacy_mode != self.config.privacy_mode {
+                    self.send(WaylandRequest::PrivacyMode(config.privacy_mode));
+                }
+                self.history.truncate(config.max_entries);
+                self.config = config;
+            }
+            Message::Wayland(update) => match update {
+                WaylandUpdate::Init(tx) => {
+                    let _ = tx.send(WaylandRequest::PrivacyMode(self.config.privacy_mode));
+                    self.wayland_tx = Some(tx);
+                }
+                WaylandUpdate::Finished => {
+                    self.wayland_tx = None;
+                }
+                WaylandUpdate::Selection(contents) => {
+                    self.history.push(contents, self.config.max_entries);
+                }
+            },
+            Message::Search(search) => {
+                self.search = search;
+            }
+            Message::Paste(id) => {
+                if let Some(entry) = self.history.get(id) {
+                    self.send(WaylandRequest::SetSelection(entry.contents.clone()));
+                }
+            }
+            Message::TogglePin(id) => {
+                self.history.toggle_pin(id);
+            }
+            Message::Remove(id) => {
+                self.history.remove(id);
+            }
+            Message::Clear => {
+                self.history.clear();
+            }
+            Message::PrivacyMode(privacy_mode) => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_privacy_mode(helper, privacy_mode) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+                self.send(WaylandRequest::PrivacyMode(privacy_mode));
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        core.applet
+            .icon_button(ICON)
+            .on_press(AppletMessage::TogglePopup)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = vec![padded_control(
+            search_input(fl!("search"), &self.search)
+                .on_input(Message::Search)
+                .on_clear(Message::Search(String::new()))
+                .on_submit(
+                    self.history
+                        .search(&self.search)
+                        .next()
+                        .map(|entry| Message::Paste(entry.id))
+                        .unwrap_or(Message::Search(self.search.clone())),
+                ),
+        )
+        .into()];
+
+        let entries = self
+            .history
+            .search(&self.search)
+            .map(|entry| {
+                let preview: Element<_> = match (&entry.image, entry.preview()) {
+                    (Some(handle), _) => image(handle.clone()).height(Length::Fixed(64.0)).into(),
+                    (None, Some(preview)) => text(preview).size(14).width(Length::Fill).into(),
+                    (None, None) => text("").into(),
+                };
+                menu_button(
+                    row![
+                        cosmic::widget::container(preview).width(Length::Fill),
+                        button::icon(
+                            icon::from_name(if entry.pinned {
+                                "pin-symbolic"
+                            } else {
+                                "view-pin-symbolic"
+                            })
+                            .size(16)
+                        )
+                        .on_press(Message::TogglePin(entry.id)),
+                        button::icon(icon::from_name("edit-delete-symbolic").size(16))
+                            .on_press(Message::Remove(entry.id)),
+                    ]
+                    .spacing(4)
+                    .align_items(Alignment::Center),
+                )
+                .on_press(Message::Paste(entry.id))
+                .into()
+            })
+            .collect::<Vec<_>>();
+
+        if entries.is_empty() {
+            content.push(
+                padded_control(text(if self.history.is_empty() {
+                    fl!("empty")
+                } else {
+                    fl!("no-matches")
+                }))
+                .into(),
+            );
+        } else {
+            content.push(
+                scrollable(Column::with_children(entries))
+                    .height(Length::Shrink)
+                    .into(),
+            );
+        }
+
+        content.push(padded_control(divider::horizontal::default()).into());
+        content.push(
+            padded_control(
+                toggler(
+                    Some(fl!("privacy-mode")),
+                    self.config.privacy_mode,
+                    Message::PrivacyMode,
+                )
+                .text_size(14)
+                .width(Length::Fill),
+            )
+            .into(),
+        );
+        content.push(
+            menu_button(text(fl!("clear-history")).size(14))
+                .on_press(Message::Clear)
+                .into(),
+        );
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+
+    fn popup_request(&self, message: &Message) -> Option<PopupRequest> {
+        matches!(message, Message::Paste(_)).then_some(PopupRequest::Close)
+    }
+
+    fn popup_limits(&self, _core: &Core) -> Limits {
+        PopupWidth::Standard.limits().min_height(200.0)
+    }
+}
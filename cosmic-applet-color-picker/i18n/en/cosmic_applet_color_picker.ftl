@@ -0,0 +1,4 @@
+pick-color = Pick color
+copy-as = Copy as
+copied = Copied { $value }
+clear-history = Clear history
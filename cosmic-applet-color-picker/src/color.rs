@@ -0,0 +1,93 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum ColorFormat {
+    #[default]
+    Hex,
+    Rgb,
+    Hsl,
+}
+
+impl ColorFormat {
+    pub const ALL: [ColorFormat; 3] = [ColorFormat::Hex, ColorFormat::Rgb, ColorFormat::Hsl];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ColorFormat::Hex => "HEX",
+            ColorFormat::Rgb => "RGB",
+            ColorFormat::Hsl => "HSL",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Rgb {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    /// From components between 0 and 1, as the portal returns them.
+    pub fn from_f64(r: f64, g: f64, b: f64) -> Self {
+        let channel = |c: f64| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Self {
+            r: channel(r),
+            g: channel(g),
+            b: channel(b),
+        }
+    }
+
+    /// Hue in degrees, saturation and lightness in percent.
+    fn hsl(self) -> (u16, u8, u8) {
+        let [r, g, b] = [self.r, self.g, self.b].map(|c| f32::from(c) / 255.0);
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let lightness = (max + min) / 2.0;
+        let delta = max - min;
+        if delta == 0.0 {
+            return (0, 0, (lightness * 100.0).round() as u8);
+        }
+        let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+        let hue = if max == r {
+            60.0 * ((g - b) / delta).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / delta + 2.0)
+        } else {
+            60.0 * ((r - g) / delta + 4.0)
+        };
+        (
+            hue.round() as u16 % 360,
+            (saturation * 100.0).round() as u8,
+            (lightness * 100.0).round() as u8,
+        )
+    }
+
+    pub fn format(self, format: ColorFormat) -> String {
+        match format {
+            ColorFormat::Hex => format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b),
+            ColorFormat::Rgb => format!("rgb({}, {}, {})", self.r, self.g, self.b),
+            ColorFormat::Hsl => {
+                let (h, s, l) = self.hsl();
+                format!("hsl({h}, {s}%, {l}%)")
+            }
+        }
+    }
+}
+
+impl From<Rgb> for cosmic::iced::Color {
+    fn from(rgb: Rgb) -> Self {
+        cosmic::iced::Color::from_rgb8(rgb.r, rgb.g, rgb.b)
+    }
+}
+
+/// Asks the portal to pick a color from the screen.
+pub async fn pick() -> ashpd::Result<Rgb> {
+    // Give the popup time to close, so it isn't in the way
+    tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+    let color = ashpd::desktop::Color::pick().send().await?.response()?;
+    Ok(Rgb::from_f64(color.red(), color.green(), color.blue()))
+}
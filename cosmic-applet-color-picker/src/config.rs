@@ -0,0 +1,32 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
+use crate::color::{ColorFormat, Rgb};
+
+pub const APP_ID: &str = "com.system76.CosmicAppletColorPicker";
+
+/// Number of picked colors to remember.
+pub const MAX_HISTORY: usize = 12;
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq, CosmicConfigEntry)]
+#[version = 1]
+pub struct ColorPickerConfig {
+    /// Most recently picked first.
+    pub history: Vec<Rgb>,
+    /// Format a picked color is copied in.
+    pub format: ColorFormat,
+}
+
+impl ColorPickerConfig {
+    /// `history` with `color` moved to the front.
+    pub fn with_picked(&self, color: Rgb) -> Vec<Rgb> {
+        let mut history = self.history.clone();
+        history.retain(|c| *c != color);
+        history.insert(0, color);
+        history.truncate(MAX_HISTORY);
+        history
+    }
+}
@@ -0,0 +1,239 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::{
+    color::{self, ColorFormat, Rgb},
+    config::{self, ColorPickerConfig},
+    fl,
+};
+use cosmic::{
+    app::Core,
+    applet::{menu_button, padded_control},
+    cosmic_config::{Config, CosmicConfigEntry},
+    iced::{
+        clipboard,
+        widget::{container, row},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_core::{Background, Border, Color, Shadow},
+    iced_widget::Column,
+    widget::{
+        button, divider, horizontal_space, icon,
+        segmented_button::{Entity, SingleSelectModel},
+        segmented_control, text, vertical_space,
+    },
+    Element, Theme,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent, PopupRequest};
+
+const ICON: &str = "com.system76.CosmicAppletColorPicker-symbolic";
+const SWATCH_SIZE: f32 = 24.0;
+
+#[derive(Default)]
+pub struct Window {
+    config: ColorPickerConfig,
+    config_helper: Option<Config>,
+    formats: SingleSelectModel,
+    /// The value last copied to the clipboard.
+    copied: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    ConfigChanged(ColorPickerConfig),
+    Pick,
+    Picked(Result<Rgb, String>),
+    Copy(String),
+    Format(Entity),
+    ClearHistory,
+}
+
+fn swatch<'a>(rgb: Rgb) -> Element<'a, Message> {
+    let color = Color::from(rgb);
+    container(vertical_space(Length::Fixed(0.0)))
+        .width(Length::Fixed(SWATCH_SIZE))
+        .height(Length::Fixed(SWATCH_SIZE))
+        .style(<Theme as container::StyleSheet>::Style::Custom(Box::new(
+            move |theme: &Theme| container::Appearance {
+                text_color: None,
+                background: Some(Background::Color(color)),
+                border: Border {
+                    radius: 4.0.into(),
+                    width: 1.0,
+                    color: theme.cosmic().bg_divider().into(),
+                },
+                shadow: Shadow::default(),
+                icon_color: None,
+            },
+        )))
+        .into()
+}
+
+impl Window {
+    fn copy(&mut self, value: String) -> Command<Message> {
+        self.copied = Some(value.clone());
+        clipboard::write(value)
+    }
+
+    fn swatch_row(&self, rgb: Rgb) -> Element<Message> {
+        let mut content = row![swatch(rgb)].spacing(4).align_items(Alignment::Center);
+        for format in ColorFormat::ALL {
+            let value = rgb.format(format);
+            content = content.push(button::text(value.clone()).on_press(Message::Copy(value)));
+        }
+        padded_control(content.push(horizontal_space(Length::Fill))).into()
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = config::APP_ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::APP_ID, ColorPickerConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                ColorPickerConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let mut formats = SingleSelectModel::default();
+        for format in ColorFormat::ALL {
+            let entity = formats.insert().text(format.label()).data(format).id();
+            if format == config.format {
+                formats.activate(entity);
+            }
+        }
+
+        let window = Self {
+            config,
+            config_helper,
+            formats,
+            ..Default::default()
+        };
+        (window, Command::none())
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        if event == PanelEvent::PopupOpened {
+            self.copied = None;
+        }
+        Command::none()
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        core.watch_config::<ColorPickerConfig>(config::APP_ID)
+            .map(|u| Message::ConfigChanged(u.config))
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::ConfigChanged(config) => {
+                self.config = config;
+            }
+            Message::Pick => {
+                return Command::perform(color::pick(), |res| {
+                    Message::Picked(res.map_err(|err| err.to_string()))
+                });
+            }
+            Message::Picked(res) => match res {
+                Ok(rgb) => {
+                    let history = self.config.with_picked(rgb);
+                    if let Some(helper) = &self.config_helper {
+                        if let Err(err) = self.config.set_history(helper, history) {
+                            tracing::error!(?err, "Error writing config");
+                        }
+                    }
+                    return self.copy(rgb.format(self.config.format));
+                }
+                // Also the result of the user cancelling
+                Err(err) => tracing::debug!("No color picked: {}", err),
+            },
+            Message::Copy(value) => {
+                return self.copy(value);
+            }
+            Message::Format(entity) => {
+                self.formats.activate(entity);
+                if let (Some(helper), Some(format)) = (
+                    &self.config_helper,
+                    self.formats.data::<ColorFormat>(entity).copied(),
+                ) {
+                    if let Err(err) = self.config.set_format(helper, format) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+            Message::ClearHistory => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_history(helper, Vec::new()) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        core.applet
+            .icon_button(ICON)
+            .on_press(AppletMessage::TogglePopup)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = vec![
+            menu_button(
+                row![
+                    icon::from_name("color-select-symbolic")
+                        .size(16)
+                        .symbolic(true),
+                    text(fl!("pick-color")).size(14),
+                    horizontal_space(Length::Fill),
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center),
+            )
+            .on_press(Message::Pick)
+            .into(),
+            padded_control(
+                row![
+                    text(fl!("copy-as")).size(14),
+                    segmented_control::horizontal(&self.formats).on_activate(Message::Format),
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center),
+            )
+            .into(),
+        ];
+
+        if let Some(copied) = self.copied.as_ref() {
+            content
+                .push(padded_control(text(fl!("copied", value = copied.as_str())).size(10)).into());
+        }
+
+        if !self.config.history.is_empty() {
+            content.push(padded_control(divider::horizontal::default()).into());
+            content.extend(self.config.history.iter().map(|rgb| self.swatch_row(*rgb)));
+            content.push(padded_control(divider::horizontal::default()).into());
+            content.push(
+                menu_button(text(fl!("clear-history")).size(14))
+                    .on_press(Message::ClearHistory)
+                    .into(),
+            );
+        }
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+
+    fn popup_request(&self, message: &Message) -> Option<PopupRequest> {
+        // The popup would be in the way of picking
+        matches!(message, Message::Pick).then_some(PopupRequest::Close)
+    }
+}
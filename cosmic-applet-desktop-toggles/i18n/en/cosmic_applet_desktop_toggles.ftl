@@ -0,0 +1,6 @@
+focus-follows-cursor = Focus follows cursor
+focus-follows-cursor-description = Windows are focused when hovered
+cursor-follows-focus = Cursor follows focus
+cursor-follows-focus-description = The cursor moves to windows focused with the keyboard
+span-displays = Workspaces span displays
+span-displays-description = Switching workspaces changes all displays together
@@ -0,0 +1,160 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::fl;
+use cosmic::{
+    app::Core,
+    applet::padded_control,
+    cosmic_config::{Config, CosmicConfigEntry},
+    iced::{widget::column, Command, Length, Subscription},
+    iced_widget::Column,
+    widget::{divider, text, toggler},
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet};
+use cosmic_comp_config::{
+    workspace::{WorkspaceConfig, WorkspaceMode},
+    CosmicCompConfig,
+};
+
+const ID: &str = "com.system76.CosmicAppletDesktopToggles";
+const ICON: &str = "com.system76.CosmicAppletDesktopToggles-symbolic";
+const COMP_CONFIG_ID: &str = "com.system76.CosmicComp";
+
+#[derive(Default)]
+pub struct Window {
+    config: CosmicCompConfig,
+    config_helper: Option<Config>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    CompConfig(Box<CosmicCompConfig>),
+    FocusFollowsCursor(bool),
+    CursorFollowsFocus(bool),
+    SpanDisplays(bool),
+}
+
+impl Window {
+    fn set_config<T>(
+        &mut self,
+        set: impl FnOnce(
+            &mut CosmicCompConfig,
+            &Config,
+            T,
+        ) -> Result<bool, cosmic::cosmic_config::Error>,
+        value: T,
+    ) {
+        if let Some(helper) = &self.config_helper {
+            if let Err(err) = set(&mut self.config, helper, value) {
+                tracing::error!(?err, "Error writing compositor config");
+            }
+        }
+    }
+
+    fn spans_displays(&self) -> bool {
+        matches!(self.config.workspaces.workspace_mode, WorkspaceMode::Global)
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(COMP_CONFIG_ID, CosmicCompConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                CosmicCompConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading compositor config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let window = Self {
+            config,
+            config_helper,
+        };
+        (window, Command::none())
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        core.watch_config::<CosmicCompConfig>(COMP_CONFIG_ID)
+            .map(|u| Message::CompConfig(Box::new(u.config)))
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::CompConfig(config) => {
+                self.config = *config;
+            }
+            Message::FocusFollowsCursor(enabled) => {
+                self.set_config(CosmicCompConfig::set_focus_follows_cursor, enabled);
+            }
+            Message::CursorFollowsFocus(enabled) => {
+                self.set_config(CosmicCompConfig::set_cursor_follows_focus, enabled);
+            }
+            Message::SpanDisplays(enabled) => {
+                let workspaces = WorkspaceConfig {
+                    workspace_mode: if enabled {
+                        WorkspaceMode::Global
+                    } else {
+                        WorkspaceMode::OutputBound
+                    },
+                    ..self.config.workspaces.clone()
+                };
+                self.set_config(CosmicCompConfig::set_workspaces, workspaces);
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        core.applet
+            .icon_button(ICON)
+            .on_press(AppletMessage::TogglePopup)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let toggle = |label: String, description: String, value: bool, f: fn(bool) -> Message| {
+            padded_control(
+                column![
+                    toggler(Some(label), value, move |value| f(value).into())
+                        .text_size(14)
+                        .width(Length::Fill),
+                    text(description).size(10),
+                ]
+                .spacing(2),
+            )
+        };
+
+        let content = Column::new()
+            .padding([8, 0])
+            .push(toggle(
+                fl!("focus-follows-cursor"),
+                fl!("focus-follows-cursor-description"),
+                self.config.focus_follows_cursor,
+                Message::FocusFollowsCursor,
+            ))
+            .push(toggle(
+                fl!("cursor-follows-focus"),
+                fl!("cursor-follows-focus-description"),
+                self.config.cursor_follows_focus,
+                Message::CursorFollowsFocus,
+            ))
+            .push(padded_control(divider::horizontal::default()))
+            .push(toggle(
+                fl!("span-displays"),
+                fl!("span-displays-description"),
+                self.spans_displays(),
+                Message::SpanDisplays,
+            ));
+
+        content.into()
+    }
+}
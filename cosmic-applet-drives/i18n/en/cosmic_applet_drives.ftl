@@ -0,0 +1,11 @@
+no-drives = No removable drives
+volume = { $size } volume
+free-of = { $free } free of { $size }
+not-mounted = Not mounted
+mount = Mount
+unmount = Unmount
+safely-remove = Safely remove
+writing = Writing data, don't unplug
+finishing-writes = Finishing writes, don't unplug
+safe-to-unplug = { $name } can be safely unplugged
+failed = Failed: { $error }
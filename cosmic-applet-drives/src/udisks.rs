@@ -0,0 +1,280 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Removable drives and their filesystems, from UDisks2.
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    hash::Hash,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use cosmic::{
+    iced::{self, subscription},
+    iced_futures::futures::{self, FutureExt, SinkExt, StreamExt},
+};
+use zbus::{
+    fdo::ObjectManagerProxy,
+    message::Type as MessageType,
+    names::OwnedInterfaceName,
+    proxy,
+    zvariant::{OwnedValue, Value},
+    Connection, MatchRule, MessageStream,
+};
+
+const SERVICE: &str = "org.freedesktop.UDisks2";
+const PATH: &str = "/org/freedesktop/UDisks2";
+
+#[proxy(
+    interface = "org.freedesktop.UDisks2.Filesystem",
+    default_service = "org.freedesktop.UDisks2"
+)]
+trait Filesystem {
+    /// Mount method
+    fn mount(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<String>;
+
+    /// Unmount method
+    fn unmount(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.UDisks2.Drive",
+    default_service = "org.freedesktop.UDisks2"
+)]
+trait Drive {
+    /// Eject method
+    fn eject(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+
+    /// PowerOff method
+    fn power_off(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+}
+
+type Properties = HashMap<String, OwnedValue>;
+
+fn interface<'a>(
+    interfaces: &'a HashMap<OwnedInterfaceName, Properties>,
+    name: &str,
+) -> Option<&'a Properties> {
+    interfaces
+        .iter()
+        .find(|(interface, _)| interface.as_str() == name)
+        .map(|(_, properties)| properties)
+}
+
+fn property<T: TryFrom<OwnedValue>>(properties: &Properties, name: &str) -> Option<T> {
+    properties.get(name)?.try_clone().ok()?.try_into().ok()
+}
+
+/// Decodes a NUL terminated byte string property.
+fn byte_string(bytes: Vec<u8>) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+#[derive(Debug, Clone)]
+pub struct Volume {
+    /// UDisks object path of the block device.
+    pub path: String,
+    pub label: String,
+    /// Kernel name, such as `sdb1`.
+    pub device: String,
+    pub size: u64,
+    pub mount_point: Option<PathBuf>,
+    /// Space available for unprivileged use, when mounted.
+    pub free: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Drive {
+    /// UDisks object path of the drive.
+    pub path: String,
+    pub name: String,
+    pub can_eject: bool,
+    pub can_power_off: bool,
+    pub volumes: Vec<Volume>,
+}
+
+impl Drive {
+    pub fn is_mounted(&self) -> bool {
+        self.volumes.iter().any(|v| v.mount_point.is_some())
+    }
+
+    /// Whether the kernel is still writing to any of the drive's volumes.
+    pub fn writes_in_flight(&self) -> bool {
+        self.volumes.iter().any(|volume| {
+            let path = Path::new("/sys/class/block")
+                .join(&volume.device)
+                .join("inflight");
+            std::fs::read_to_string(path)
+                .ok()
+                .map_or(false, |inflight| {
+                    inflight
+                        .split_whitespace()
+                        .nth(1)
+                        .and_then(|writes| writes.parse::<u64>().ok())
+                        .map_or(false, |writes| writes > 0)
+                })
+        })
+    }
+}
+
+pub fn free_space(mount_point: &Path) -> Option<u64> {
+    let stat = rustix::fs::statvfs(mount_point).ok()?;
+    Some(stat.f_bavail * stat.f_frsize)
+}
+
+/// Drives with filesystems that aren't part of the system, such as USB
+/// sticks and SD cards.
+pub async fn drives(conn: &Connection) -> zbus::Result<Vec<Drive>> {
+    let objects = ObjectManagerProxy::builder(conn)
+        .destination(SERVICE)?
+        .path(PATH)?
+        .build()
+        .await?
+        .get_managed_objects()
+        .await?;
+
+    let mut drives: Vec<Drive> = Vec::new();
+    for (path, interfaces) in &objects {
+        let (Some(block), Some(filesystem)) = (
+            interface(interfaces, "org.freedesktop.UDisks2.Block"),
+            interface(interfaces, "org.freedesktop.UDisks2.Filesystem"),
+        ) else {
+            continue;
+        };
+        if property(block, "HintSystem").unwrap_or(true)
+            || property(block, "HintIgnore").unwrap_or(false)
+        {
+            continue;
+        }
+        let Some(drive_path) = property::<zbus::zvariant::OwnedObjectPath>(block, "Drive")
+            .map(|p| p.to_string())
+            .filter(|p| p != "/")
+        else {
+            continue;
+        };
+
+        let mount_point = property::<Vec<Vec<u8>>>(filesystem, "MountPoints")
+            .and_then(|mount_points| mount_points.into_iter().next())
+            .map(|mount_point| PathBuf::from(byte_string(mount_point)));
+        let device = property(block, "Device")
+            .map(byte_string)
+            .unwrap_or_default();
+        let volume = Volume {
+            path: path.to_string(),
+            label: property(block, "IdLabel").unwrap_or_default(),
+            device: device.rsplit('/').next().unwrap_or_default().to_string(),
+            size: property(block, "Size").unwrap_or_default(),
+            free: mount_point.as_deref().and_then(free_space),
+            mount_point,
+        };
+
+        if let Some(drive) = drives.iter_mut().find(|d| d.path == drive_path) {
+            drive.volumes.push(volume);
+            continue;
+        }
+        let Some(drive_properties) = objects
+            .iter()
+            .find(|(path, _)| path.as_str() == drive_path)
+            .and_then(|(_, interfaces)| interface(interfaces, "org.freedesktop.UDisks2.Drive"))
+        else {
+            continue;
+        };
+        let vendor: String = property(drive_properties, "Vendor").unwrap_or_default();
+        let model: String = property(drive_properties, "Model").unwrap_or_default();
+        drives.push(Drive {
+            path: drive_path,
+            name: format!("{} {}", vendor.trim(), model.trim())
+                .trim()
+                .to_string(),
+            can_eject: property(drive_properties, "Ejectable").unwrap_or(false),
+            can_power_off: property(drive_properties, "CanPowerOff").unwrap_or(false),
+            volumes: vec![volume],
+        });
+    }
+
+    drives.sort_by(|a, b| a.name.cmp(&b.name));
+    for drive in &mut drives {
+        drive.volumes.sort_by(|a, b| a.device.cmp(&b.device));
+    }
+    Ok(drives)
+}
+
+async fn filesystem(conn: &Connection, path: String) -> zbus::Result<FilesystemProxy<'static>> {
+    FilesystemProxy::builder(conn).path(path)?.build().await
+}
+
+pub async fn mount(path: String) -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    filesystem(&conn, path).await?.mount(HashMap::new()).await?;
+    Ok(())
+}
+
+pub async fn unmount(path: String) -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    filesystem(&conn, path).await?.unmount(HashMap::new()).await
+}
+
+/// Unmounts every volume of the drive, which waits for pending writes, then
+/// powers it off or ejects its media.
+pub async fn safely_remove(drive: Drive) -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    for volume in drive.volumes.iter().filter(|v| v.mount_point.is_some()) {
+        filesystem(&conn, volume.path.clone())
+            .await?
+            .unmount(HashMap::new())
+            .await?;
+    }
+    let proxy = DriveProxy::builder(&conn).path(drive.path)?.build().await?;
+    if drive.can_power_off {
+        proxy.power_off(HashMap::new()).await
+    } else if drive.can_eject {
+        proxy.eject(HashMap::new()).await
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum UDisksUpdate {
+    Drives(Vec<Drive>),
+    Finished,
+}
+
+pub fn udisks_subscription<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+) -> iced::Subscription<UDisksUpdate> {
+    subscription::channel(id, 50, move |mut output| async move {
+        if let Err(err) = run(&mut output).await {
+            tracing::error!("Failed to monitor UDisks2: {}", err);
+        }
+        let _ = output.send(UDisksUpdate::Finished).await;
+        futures::future::pending().await
+    })
+}
+
+async fn run(output: &mut futures::channel::mpsc::Sender<UDisksUpdate>) -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    let rule = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(SERVICE)?
+        .path_namespace(PATH)?
+        .build();
+    let mut signals = MessageStream::for_match_rule(rule, &conn, None).await?;
+
+    loop {
+        _ = output
+            .send(UDisksUpdate::Drives(drives(&conn).await?))
+            .await;
+
+        if signals.next().await.is_none() {
+            return Ok(());
+        }
+        // Plugging in a drive emits a burst of signals, so read them all
+        // before listing drives again
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        while let Some(Some(_)) = signals.next().now_or_never() {}
+    }
+}
@@ -0,0 +1,280 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::{collections::HashSet, path::PathBuf, time::Duration};
+
+use crate::{
+    fl,
+    udisks::{self, udisks_subscription, Drive, UDisksUpdate, Volume},
+};
+use cosmic::{
+    app::Core,
+    applet::{menu_button, padded_control},
+    iced::{
+        time,
+        widget::{column, row},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_widget::Column,
+    widget::{button, divider, horizontal_space, icon, text},
+    Element,
+};
+use cosmic_applet_helper::{format_size, AppletMessage, CosmicApplet, PanelEvent, PopupRequest};
+
+const ID: &str = "com.system76.CosmicAppletDrives";
+const ICON: &str = "com.system76.CosmicAppletDrives-symbolic";
+
+#[derive(Default)]
+pub struct Window {
+    drives: Vec<Drive>,
+    /// Object paths of drives and volumes with an operation in flight.
+    busy: HashSet<String>,
+    /// Drives the kernel is still writing to.
+    writing: HashSet<String>,
+    /// Name of the last drive that was safely removed.
+    removed: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    UDisks(UDisksUpdate),
+    Mount(String),
+    Unmount(String),
+    SafelyRemove(String),
+    Open(PathBuf),
+    /// An operation on the object at the path finished.
+    Done(String, Result<(), String>),
+    /// A drive, by path and name, was safely removed.
+    Removed(String, String, Result<(), String>),
+    Tick,
+}
+
+impl Window {
+    fn update_writing(&mut self) {
+        self.writing = self
+            .drives
+            .iter()
+            .filter(|drive| drive.writes_in_flight())
+            .map(|drive| drive.path.clone())
+            .collect();
+    }
+
+    fn perform(
+        &mut self,
+        path: String,
+        fut: impl std::future::Future<Output = zbus::Result<()>> + Send + 'static,
+    ) -> Command<Message> {
+        self.busy.insert(path.clone());
+        self.error = None;
+        Command::perform(fut, move |res| {
+            Message::Done(path, res.map_err(|err| err.to_string()))
+        })
+    }
+
+    fn volume_view<'a>(&self, volume: &'a Volume) -> Element<'a, Message> {
+        let label = if volume.label.is_empty() {
+            fl!("volume", size = format_size(volume.size))
+        } else {
+            volume.label.clone()
+        };
+        let details = match (&volume.mount_point, volume.free) {
+            (Some(_), Some(free)) => fl!(
+                "free-of",
+                free = format_size(free),
+                size = format_size(volume.size)
+            ),
+            (Some(_), None) => format_size(volume.size),
+            (None, _) => fl!("not-mounted"),
+        };
+        let busy = self.busy.contains(&volume.path);
+        let action = if volume.mount_point.is_some() {
+            button::standard(fl!("unmount"))
+                .on_press_maybe((!busy).then(|| Message::Unmount(volume.path.clone())))
+        } else {
+            button::standard(fl!("mount"))
+                .on_press_maybe((!busy).then(|| Message::Mount(volume.path.clone())))
+        };
+
+        menu_button(
+            row![
+                icon::from_name("drive-harddisk-symbolic")
+                    .size(16)
+                    .symbolic(true),
+                column![text(label).size(14), text(details).size(10)],
+                horizontal_space(Length::Fill),
+                action.extra_small(),
+            ]
+            .spacing(8)
+            .align_items(Alignment::Center),
+        )
+        .on_press_maybe(volume.mount_point.clone().map(Message::Open))
+        .into()
+    }
+
+    fn drive_view<'a>(&self, drive: &'a Drive) -> Vec<Element<'a, Message>> {
+        let busy = self.busy.contains(&drive.path);
+        let status = if busy {
+            Some(fl!("finishing-writes"))
+        } else if self.writing.contains(&drive.path) {
+            Some(fl!("writing"))
+        } else {
+            None
+        };
+
+        let mut header = column![text(&drive.name).size(14)];
+        if let Some(status) = status {
+            header = header.push(text(status).size(10));
+        }
+        let mut content = vec![padded_control(
+            row![
+                header,
+                horizontal_space(Length::Fill),
+                button::standard(fl!("safely-remove"))
+                    .extra_small()
+                    .on_press_maybe((!busy).then(|| Message::SafelyRemove(drive.path.clone()))),
+            ]
+            .spacing(8)
+            .align_items(Alignment::Center),
+        )
+        .into()];
+        content.extend(drive.volumes.iter().map(|volume| self.volume_view(volume)));
+        content
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        (Self::default(), Command::none())
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        if event == PanelEvent::PopupOpened {
+            self.removed = None;
+            self.error = None;
+            self.update_writing();
+        }
+        Command::none()
+    }
+
+    fn subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        udisks_subscription(0).map(Message::UDisks)
+    }
+
+    fn popup_subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        // Free space and writes in flight aren't signalled, so poll them while
+        // they're shown
+        if self.drives.iter().any(Drive::is_mounted) {
+            time::every(Duration::from_secs(1)).map(|_| Message::Tick)
+        } else {
+            Subscription::none()
+        }
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::UDisks(update) => match update {
+                UDisksUpdate::Drives(drives) => {
+                    self.drives = drives;
+                    self.update_writing();
+                }
+                UDisksUpdate::Finished => {
+                    self.drives.clear();
+                }
+            },
+            Message::Mount(path) => {
+                return self.perform(path.clone(), udisks::mount(path));
+            }
+            Message::Unmount(path) => {
+                return self.perform(path.clone(), udisks::unmount(path));
+            }
+            Message::SafelyRemove(path) => {
+                let Some(drive) = self.drives.iter().find(|d| d.path == path).cloned() else {
+                    return Command::none();
+                };
+                self.busy.insert(path.clone());
+                self.error = None;
+                self.removed = None;
+                let name = drive.name.clone();
+                return Command::perform(udisks::safely_remove(drive), move |res| {
+                    Message::Removed(path, name, res.map_err(|err| err.to_string()))
+                });
+            }
+            Message::Open(mount_point) => {
+                let mut cmd = std::process::Command::new("xdg-open");
+                cmd.arg(mount_point);
+                cosmic::process::spawn(cmd);
+            }
+            Message::Done(path, res) => {
+                self.busy.remove(&path);
+                if let Err(err) = res {
+                    tracing::error!("Failed to mount or unmount {}: {}", path, err);
+                    self.error = Some(err);
+                }
+            }
+            Message::Removed(path, name, res) => {
+                self.busy.remove(&path);
+                match res {
+                    Ok(()) => {
+                        self.removed = Some(name);
+                    }
+                    Err(err) => {
+                        tracing::error!("Failed to safely remove {}: {}", path, err);
+                        self.error = Some(err);
+                    }
+                }
+            }
+            Message::Tick => {
+                self.update_writing();
+                for drive in &mut self.drives {
+                    for volume in &mut drive.volumes {
+                        if let Some(mount_point) = volume.mount_point.as_deref() {
+                            volume.free = udisks::free_space(mount_point);
+                        }
+                    }
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        core.applet
+            .icon_button(ICON)
+            .on_press(AppletMessage::TogglePopup)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = Vec::new();
+
+        if self.drives.is_empty() {
+            content.push(padded_control(text(fl!("no-drives")).size(14)).into());
+        }
+        for (i, drive) in self.drives.iter().enumerate() {
+            if i > 0 {
+                content.push(padded_control(divider::horizontal::default()).into());
+            }
+            content.extend(self.drive_view(drive));
+        }
+
+        if let Some(err) = self.error.as_ref() {
+            content.push(padded_control(divider::horizontal::default()).into());
+            content.push(padded_control(text(fl!("failed", error = err.as_str())).size(10)).into());
+        } else if let Some(name) = self.removed.as_ref() {
+            content.push(padded_control(divider::horizontal::default()).into());
+            content.push(
+                padded_control(text(fl!("safe-to-unplug", name = name.as_str())).size(14)).into(),
+            );
+        }
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+
+    fn popup_request(&self, message: &Message) -> Option<PopupRequest> {
+        matches!(message, Message::Open(_)).then_some(PopupRequest::Close)
+    }
+}
@@ -0,0 +1,3 @@
+search = Search emoji
+no-matches = No matching emoji
+no-recents = Emoji you pick will show up here
@@ -0,0 +1,67 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
+pub const APP_ID: &str = "com.system76.CosmicAppletEmoji";
+
+/// Number of recently used emoji to remember.
+pub const MAX_RECENTS: usize = 32;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum SkinTone {
+    #[default]
+    Default,
+    Light,
+    MediumLight,
+    Medium,
+    MediumDark,
+    Dark,
+}
+
+impl SkinTone {
+    pub const ALL: [SkinTone; 6] = [
+        SkinTone::Default,
+        SkinTone::Light,
+        SkinTone::MediumLight,
+        SkinTone::Medium,
+        SkinTone::MediumDark,
+        SkinTone::Dark,
+    ];
+
+    fn to_emojis(self) -> emojis::SkinTone {
+        match self {
+            SkinTone::Default => emojis::SkinTone::Default,
+            SkinTone::Light => emojis::SkinTone::Light,
+            SkinTone::MediumLight => emojis::SkinTone::MediumLight,
+            SkinTone::Medium => emojis::SkinTone::Medium,
+            SkinTone::MediumDark => emojis::SkinTone::MediumDark,
+            SkinTone::Dark => emojis::SkinTone::Dark,
+        }
+    }
+
+    /// The variant of `emoji` in this skin tone, if it has one.
+    pub fn apply(self, emoji: &'static emojis::Emoji) -> &'static emojis::Emoji {
+        emoji.with_skin_tone(self.to_emojis()).unwrap_or(emoji)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq, CosmicConfigEntry)]
+#[version = 1]
+pub struct EmojiConfig {
+    /// Most recently used first.
+    pub recents: Vec<String>,
+    pub skin_tone: SkinTone,
+}
+
+impl EmojiConfig {
+    /// `recents` with `emoji` moved to the front.
+    pub fn with_recent(&self, emoji: &str) -> Vec<String> {
+        let mut recents = self.recents.clone();
+        recents.retain(|recent| recent != emoji);
+        recents.insert(0, emoji.to_string());
+        recents.truncate(MAX_RECENTS);
+        recents
+    }
+}
@@ -0,0 +1,300 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::{
+    config::{self, EmojiConfig, SkinTone},
+    fl,
+};
+use cosmic::{
+    app::Core,
+    applet::padded_control,
+    cosmic_config::{Config, CosmicConfigEntry},
+    iced::{
+        alignment::{Horizontal, Vertical},
+        clipboard,
+        widget::scrollable::{Direction, Properties},
+        Command, Length, Limits, Subscription,
+    },
+    iced_widget::{Column, Row},
+    widget::{button, divider, scrollable, search_input, text},
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent, PopupRequest, PopupWidth};
+
+const ICON: &str = "com.system76.CosmicAppletEmoji-symbolic";
+const COLUMNS: usize = 8;
+/// Shown in the skin tone selector.
+const SKIN_TONE_SAMPLE: &str = "👋";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Category {
+    #[default]
+    Recent,
+    Group(emojis::Group),
+}
+
+#[derive(Default)]
+pub struct Window {
+    config: EmojiConfig,
+    config_helper: Option<Config>,
+    category: Category,
+    search: String,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    ConfigChanged(EmojiConfig),
+    Search(String),
+    Category(Category),
+    SkinTone(SkinTone),
+    Select(&'static str),
+}
+
+fn emoji_button(
+    label: &'static str,
+    size: u16,
+    selected: bool,
+    message: Message,
+) -> Element<'static, Message> {
+    button(
+        text(label)
+            .size(size)
+            .horizontal_alignment(Horizontal::Center)
+            .vertical_alignment(Vertical::Center),
+    )
+    .style(if selected {
+        button::Style::Suggested
+    } else {
+        button::Style::Text
+    })
+    .width(Length::Fixed(36.0))
+    .height(Length::Fixed(36.0))
+    .on_press(message)
+    .into()
+}
+
+impl Window {
+    /// Emoji to show for the current search or category, in the chosen skin
+    /// tone.
+    fn emojis(&self) -> Vec<&'static emojis::Emoji> {
+        let search = self.search.trim().to_lowercase();
+        let emojis: Vec<_> = if !search.is_empty() {
+            emojis::iter()
+                .filter(|emoji| {
+                    emoji.name().contains(&search)
+                        || emoji.shortcodes().any(|code| code.contains(&search))
+                })
+                .collect()
+        } else {
+            match self.category {
+                Category::Recent => self
+                    .config
+                    .recents
+                    .iter()
+                    .filter_map(|recent| emojis::get(recent))
+                    .collect(),
+                Category::Group(group) => group.emojis().collect(),
+            }
+        };
+        emojis
+            .into_iter()
+            .map(|emoji| self.config.skin_tone.apply(emoji))
+            .collect()
+    }
+
+    fn category_tabs(&self) -> Element<Message> {
+        let mut tabs = vec![emoji_button(
+            "🕒",
+            16,
+            self.category == Category::Recent,
+            Message::Category(Category::Recent),
+        )];
+        for group in emojis::Group::iter() {
+            let Some(first) = group.emojis().next() else {
+                continue;
+            };
+            let category = Category::Group(group);
+            tabs.push(emoji_button(
+                first.as_str(),
+                16,
+                self.category == category,
+                Message::Category(category),
+            ));
+        }
+        scrollable(Row::with_children(tabs).spacing(2))
+            .direction(Direction::Horizontal(Properties::default()))
+            .into()
+    }
+
+    fn skin_tones(&self) -> Element<Message> {
+        let sample = emojis::get(SKIN_TONE_SAMPLE).expect("sample is an emoji");
+        Row::with_children(
+            SkinTone::ALL
+                .into_iter()
+                .map(|tone| {
+                    emoji_button(
+                        tone.apply(sample).as_str(),
+                        16,
+                        self.config.skin_tone == tone,
+                        Message::SkinTone(tone),
+                    )
+                })
+                .collect::<Vec<_>>(),
+        )
+        .spacing(2)
+        .into()
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = config::APP_ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::APP_ID, EmojiConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                EmojiConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let window = Self {
+            config,
+            config_helper,
+            ..Default::default()
+        };
+        (window, Command::none())
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        if event == PanelEvent::PopupOpened {
+            self.search.clear();
+            // Start on recents, unless there are none yet
+            self.category = if self.config.recents.is_empty() {
+                Category::Group(emojis::Group::SmileysAndEmotion)
+            } else {
+                Category::Recent
+            };
+        }
+        Command::none()
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        core.watch_config::<EmojiConfig>(config::APP_ID)
+            .map(|u| Message::ConfigChanged(u.config))
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::ConfigChanged(config) => {
+                self.config = config;
+            }
+            Message::Search(search) => {
+                self.search = search;
+            }
+            Message::Category(category) => {
+                self.category = category;
+                self.search.clear();
+            }
+            Message::SkinTone(skin_tone) => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_skin_tone(helper, skin_tone) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+            Message::Select(emoji) => {
+                let recents = self.config.with_recent(emoji);
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_recents(helper, recents) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+                return clipboard::write(emoji.to_string());
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        core.applet
+            .icon_button(ICON)
+            .on_press(AppletMessage::TogglePopup)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let emojis = self.emojis();
+        let mut content = vec![
+            padded_control(
+                search_input(fl!("search"), &self.search)
+                    .on_input(Message::Search)
+                    .on_clear(Message::Search(String::new()))
+                    .on_submit(
+                        emojis
+                            .first()
+                            .map(|emoji| Message::Select(emoji.as_str()))
+                            .unwrap_or(Message::Search(self.search.clone())),
+                    ),
+            )
+            .into(),
+            padded_control(self.category_tabs()).into(),
+            padded_control(divider::horizontal::default()).into(),
+        ];
+
+        if emojis.is_empty() {
+            let status = if !self.search.trim().is_empty() {
+                fl!("no-matches")
+            } else {
+                fl!("no-recents")
+            };
+            content.push(padded_control(text(status).size(14)).into());
+        } else {
+            let rows = emojis
+                .chunks(COLUMNS)
+                .map(|chunk| {
+                    Row::with_children(
+                        chunk
+                            .iter()
+                            .map(|emoji| {
+                                emoji_button(
+                                    emoji.as_str(),
+                                    20,
+                                    false,
+                                    Message::Select(emoji.as_str()),
+                                )
+                            })
+                            .collect::<Vec<_>>(),
+                    )
+                    .spacing(2)
+                    .into()
+                })
+                .collect::<Vec<_>>();
+            content.push(
+                padded_control(
+                    scrollable(Column::with_children(rows).spacing(2)).height(Length::Fixed(300.0)),
+                )
+                .into(),
+            );
+        }
+
+        content.push(padded_control(divider::horizontal::default()).into());
+        content.push(padded_control(self.skin_tones()).into());
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+
+    fn popup_request(&self, message: &Message) -> Option<PopupRequest> {
+        matches!(message, Message::Select(_)).then_some(PopupRequest::Close)
+    }
+
+    fn popup_limits(&self, _core: &Core) -> Limits {
+        PopupWidth::Standard.limits().min_height(200.0)
+    }
+}
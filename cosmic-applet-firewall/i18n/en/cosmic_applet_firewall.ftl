@@ -0,0 +1,7 @@
+unavailable = Firewall service isn't running
+block-all = Block all connections
+blocking = All network traffic is blocked
+default-zone = Default zone: { $zone }
+no-connections = No active connections
+applying = Applying...
+failed = Failed: { $error }
@@ -0,0 +1,171 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Zones and panic mode from firewalld. Changes are authorized by firewalld
+//! through polkit, which may ask for a password.
+
+use std::{collections::HashMap, fmt::Debug, hash::Hash, time::Duration};
+
+use cosmic::{
+    iced::{self, subscription},
+    iced_futures::futures::{self, stream, FutureExt, SinkExt, StreamExt},
+};
+use zbus::{message::Type as MessageType, proxy, Connection, MatchRule, MessageStream};
+
+const SERVICE: &str = "org.fedoraproject.FirewallD1";
+
+#[proxy(
+    interface = "org.fedoraproject.FirewallD1",
+    default_service = "org.fedoraproject.FirewallD1",
+    default_path = "/org/fedoraproject/FirewallD1"
+)]
+trait FirewallD {
+    /// queryPanicMode method
+    #[zbus(name = "queryPanicMode")]
+    fn query_panic_mode(&self) -> zbus::Result<bool>;
+
+    /// enablePanicMode method
+    #[zbus(name = "enablePanicMode", allow_interactive_auth)]
+    fn enable_panic_mode(&self) -> zbus::Result<()>;
+
+    /// disablePanicMode method
+    #[zbus(name = "disablePanicMode", allow_interactive_auth)]
+    fn disable_panic_mode(&self) -> zbus::Result<()>;
+
+    /// getDefaultZone method
+    #[zbus(name = "getDefaultZone")]
+    fn get_default_zone(&self) -> zbus::Result<String>;
+}
+
+#[proxy(
+    interface = "org.fedoraproject.FirewallD1.zone",
+    default_service = "org.fedoraproject.FirewallD1",
+    default_path = "/org/fedoraproject/FirewallD1"
+)]
+trait Zone {
+    /// getZones method
+    #[zbus(name = "getZones")]
+    fn get_zones(&self) -> zbus::Result<Vec<String>>;
+
+    /// getActiveZones method
+    #[zbus(name = "getActiveZones")]
+    fn get_active_zones(&self) -> zbus::Result<HashMap<String, HashMap<String, Vec<String>>>>;
+
+    /// changeZoneOfInterface method
+    #[zbus(name = "changeZoneOfInterface", allow_interactive_auth)]
+    fn change_zone_of_interface(&self, zone: &str, interface: &str) -> zbus::Result<String>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Status {
+    /// Dropping all traffic.
+    pub panic: bool,
+    pub default_zone: String,
+    pub zones: Vec<String>,
+    /// Network interfaces and their zone.
+    pub interfaces: Vec<(String, String)>,
+}
+
+async fn status(conn: &Connection) -> zbus::Result<Status> {
+    let firewalld = FirewallDProxy::new(conn).await?;
+    let zone = ZoneProxy::new(conn).await?;
+
+    let mut interfaces = zone
+        .get_active_zones()
+        .await?
+        .into_iter()
+        .flat_map(|(zone, settings)| {
+            settings
+                .get("interfaces")
+                .cloned()
+                .unwrap_or_default()
+                .into_iter()
+                .map(move |interface| (interface, zone.clone()))
+        })
+        .collect::<Vec<_>>();
+    interfaces.sort();
+
+    Ok(Status {
+        panic: firewalld.query_panic_mode().await?,
+        default_zone: firewalld.get_default_zone().await?,
+        zones: zone.get_zones().await?,
+        interfaces,
+    })
+}
+
+pub async fn set_panic(panic: bool) -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    let firewalld = FirewallDProxy::new(&conn).await?;
+    if panic {
+        firewalld.enable_panic_mode().await
+    } else {
+        firewalld.disable_panic_mode().await
+    }
+}
+
+/// Moves `interface` to `zone` until it's reconnected or firewalld reloads.
+pub async fn set_zone(interface: String, zone: String) -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    ZoneProxy::new(&conn)
+        .await?
+        .change_zone_of_interface(&zone, &interface)
+        .await
+        .map(|_| ())
+}
+
+#[derive(Debug, Clone)]
+pub enum FirewallUpdate {
+    Status(Status),
+    /// firewalld isn't running.
+    Unavailable,
+    Finished,
+}
+
+pub fn firewall_subscription<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+) -> iced::Subscription<FirewallUpdate> {
+    subscription::channel(id, 50, move |mut output| async move {
+        if let Err(err) = run(&mut output).await {
+            tracing::error!("Failed to monitor firewalld: {}", err);
+        }
+        let _ = output.send(FirewallUpdate::Finished).await;
+        futures::future::pending().await
+    })
+}
+
+async fn run(output: &mut futures::channel::mpsc::Sender<FirewallUpdate>) -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    let changes = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(SERVICE)?
+        .build();
+    // Also notice firewalld starting and stopping
+    let owner = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender("org.freedesktop.DBus")?
+        .member("NameOwnerChanged")?
+        .arg(0, SERVICE)?
+        .build();
+    let mut signals = stream::select(
+        MessageStream::for_match_rule(changes, &conn, None).await?,
+        MessageStream::for_match_rule(owner, &conn, None).await?,
+    );
+
+    loop {
+        let update = match status(&conn).await {
+            Ok(status) => FirewallUpdate::Status(status),
+            Err(err) => {
+                tracing::debug!("firewalld unavailable: {}", err);
+                FirewallUpdate::Unavailable
+            }
+        };
+        _ = output.send(update).await;
+
+        if signals.next().await.is_none() {
+            return Ok(());
+        }
+        // Reloading emits a burst of signals, so read them all first
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        while let Some(Some(_)) = signals.next().now_or_never() {}
+    }
+}
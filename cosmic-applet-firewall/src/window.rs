@@ -0,0 +1,169 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::{
+    firewalld::{self, firewall_subscription, FirewallUpdate, Status},
+    fl,
+};
+use cosmic::{
+    app::Core,
+    applet::padded_control,
+    iced::{
+        widget::{column, row},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_widget::Column,
+    widget::{container, divider, dropdown, text, toggler},
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent};
+
+const ID: &str = "com.system76.CosmicAppletFirewall";
+
+#[derive(Default)]
+pub struct Window {
+    /// `None` while firewalld isn't running.
+    status: Option<Status>,
+    /// Waiting on firewalld, which may be waiting on polkit.
+    pending: bool,
+    error: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    Firewall(FirewallUpdate),
+    Panic(bool),
+    /// Moves the interface at the first index to the zone at the second.
+    SetZone(usize, usize),
+    Done(Result<(), String>),
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        (Self::default(), Command::none())
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        if event == PanelEvent::PopupOpened {
+            self.error = None;
+        }
+        Command::none()
+    }
+
+    fn subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        firewall_subscription(0).map(Message::Firewall)
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::Firewall(update) => match update {
+                FirewallUpdate::Status(status) => self.status = Some(status),
+                FirewallUpdate::Unavailable | FirewallUpdate::Finished => self.status = None,
+            },
+            Message::Panic(panic) => {
+                self.pending = true;
+                self.error = None;
+                return Command::perform(firewalld::set_panic(panic), |res| {
+                    Message::Done(res.map_err(|err| err.to_string()))
+                });
+            }
+            Message::SetZone(interface, zone) => {
+                let Some(status) = self.status.as_ref() else {
+                    return Command::none();
+                };
+                let (Some((interface, _)), Some(zone)) =
+                    (status.interfaces.get(interface), status.zones.get(zone))
+                else {
+                    return Command::none();
+                };
+                self.pending = true;
+                self.error = None;
+                return Command::perform(
+                    firewalld::set_zone(interface.clone(), zone.clone()),
+                    |res| Message::Done(res.map_err(|err| err.to_string())),
+                );
+            }
+            Message::Done(res) => {
+                self.pending = false;
+                if let Err(err) = res {
+                    tracing::error!("Failed to change firewall: {}", err);
+                    self.error = Some(err);
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        core.applet
+            .icon_button(match &self.status {
+                None => "security-low-symbolic",
+                Some(status) if status.panic => "action-unavailable-symbolic",
+                Some(_) => "security-high-symbolic",
+            })
+            .on_press(AppletMessage::TogglePopup)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let Some(status) = self.status.as_ref() else {
+            return Column::with_children(vec![
+                padded_control(text(fl!("unavailable")).size(14)).into()
+            ])
+            .padding([8, 0])
+            .into();
+        };
+
+        let mut content = vec![
+            padded_control(
+                column![
+                    toggler(Some(fl!("block-all")), status.panic, |panic| {
+                        Message::Panic(panic).into()
+                    })
+                    .text_size(14)
+                    .width(Length::Fill),
+                    text(if status.panic {
+                        fl!("blocking")
+                    } else {
+                        fl!("default-zone", zone = status.default_zone.as_str())
+                    })
+                    .size(10),
+                ]
+                .spacing(2),
+            )
+            .into(),
+            padded_control(divider::horizontal::default()).into(),
+        ];
+
+        if status.interfaces.is_empty() {
+            content.push(padded_control(text(fl!("no-connections")).size(14)).into());
+        }
+        for (i, (interface, zone)) in status.interfaces.iter().enumerate() {
+            let selected = status.zones.iter().position(|z| z == zone);
+            content.push(
+                padded_control(
+                    row![
+                        text(interface).size(14).width(Length::Fill),
+                        container(dropdown(&status.zones, selected, move |zone| {
+                            Message::SetZone(i, zone).into()
+                        })),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+        }
+
+        if self.pending {
+            content.push(padded_control(text(fl!("applying")).size(10)).into());
+        } else if let Some(err) = &self.error {
+            content.push(padded_control(text(fl!("failed", error = err.as_str())).size(10)).into());
+        }
+
+        Column::with_children(content).padding([8, 0]).into()
+    }
+}
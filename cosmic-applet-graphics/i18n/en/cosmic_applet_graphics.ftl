@@ -0,0 +1,24 @@
+graphics-mode = Graphics mode
+integrated = Integrated graphics
+integrated-desc = Lower power use, the discrete GPU is turned off
+hybrid = Hybrid graphics
+hybrid-desc = Applications can use the discrete GPU on request
+discrete = Discrete graphics
+discrete-desc = Everything renders on the discrete GPU
+compute = Compute graphics
+compute-desc = The discrete GPU is only available for computation
+default-gpu = Default
+active = Active
+suspended = Suspended
+unknown = Unknown state
+dgpu-processes = These applications are keeping the discrete GPU active
+no-gpus = No GPUs found
+launch-on-dgpu = Launch on discrete GPU…
+restart-required = Restart to finish switching graphics mode
+log-out-required = Log out to finish switching graphics mode
+restart-now = Restart now
+log-out-now = Log out now
+later = Later
+external-gpu = External
+disconnect = Disconnect
+egpu-in-use = Close these applications to disconnect the GPU
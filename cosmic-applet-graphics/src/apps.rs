@@ -0,0 +1,65 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Installed applications that can be launched on the discrete GPU.
+
+use freedesktop_desktop_entry as fde;
+use freedesktop_desktop_entry::{get_languages_from_env, DesktopEntry};
+
+/// Maximum number of search results shown in the popup.
+pub const MAX_RESULTS: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct App {
+    pub id: String,
+    pub name: String,
+    pub icon: Option<String>,
+    pub exec: String,
+}
+
+pub async fn load_apps() -> Vec<App> {
+    tokio::task::spawn_blocking(|| {
+        let locales = get_languages_from_env();
+        let mut apps = fde::Iter::new(fde::default_paths())
+            .filter_map(|path| DesktopEntry::from_path(path, &locales).ok())
+            .filter(|entry| !entry.no_display() && entry.type_() == Some("Application"))
+            .filter_map(|entry| {
+                Some(App {
+                    id: entry.id().to_string(),
+                    name: entry.name(&locales)?.to_string(),
+                    icon: entry.icon().map(str::to_string),
+                    exec: entry.exec()?.to_string(),
+                })
+            })
+            .collect::<Vec<_>>();
+        apps.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+        // The same application may be installed in several data dirs
+        apps.dedup_by(|a, b| a.id == b.id);
+        apps
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Applications whose name matches `query`, with prefix matches first.
+pub fn search<'a>(apps: &'a [App], query: &str) -> Vec<&'a App> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut results = apps
+        .iter()
+        .filter_map(|app| {
+            let name = app.name.to_lowercase();
+            let pos = name.find(&query)?;
+            Some((pos != 0, app))
+        })
+        .collect::<Vec<_>>();
+    results.sort_by_key(|(not_prefix, _)| *not_prefix);
+    results
+        .into_iter()
+        .map(|(_, app)| app)
+        .take(MAX_RESULTS)
+        .collect()
+}
@@ -0,0 +1,348 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Status of the GPUs in the system, gathered from sysfs, switcheroo-control
+//! and the file descriptors of running processes, and safely disconnecting
+//! external GPUs.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use cosmic::iced::{self, futures::SinkExt, subscription};
+use tokio::{io::AsyncWriteExt, process::Command, time};
+
+/// Processes that always hold the GPUs open, and can't be closed by the user.
+const IGNORED_PROCESSES: &[&str] = &["cosmic-comp", "Xwayland"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerState {
+    Active,
+    Suspended,
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+pub struct Process {
+    pub pid: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct GpuStatus {
+    pub name: String,
+    /// Whether this is the GPU used for rendering by default.
+    pub default: bool,
+    pub power_state: PowerState,
+    /// Processes using the GPU. Only collected for non-default GPUs, since
+    /// everything uses the default one.
+    pub processes: Vec<Process>,
+    /// Environment variables that make an application render on this GPU.
+    pub environment: Vec<(String, String)>,
+    /// PCI slot, such as `0000:01:00.0`.
+    pub slot: String,
+    /// Connected through a port that can be unplugged, like Thunderbolt.
+    pub external: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum DisconnectError {
+    /// Applications need to be closed first.
+    InUse(Vec<Process>),
+    Failed(String),
+}
+
+#[derive(Debug, Default)]
+struct SwitcherooGpu {
+    name: String,
+    default: bool,
+    environment: Vec<(String, String)>,
+}
+
+async fn switcheroo_gpus() -> Vec<SwitcherooGpu> {
+    let Ok(connection) = zbus::Connection::system().await else {
+        return Vec::new();
+    };
+    let Ok(proxy) = switcheroo_control::SwitcherooControlProxy::new(&connection).await else {
+        return Vec::new();
+    };
+    let gpus = match proxy.get_gpus().await {
+        Ok(gpus) => gpus,
+        Err(err) => {
+            tracing::debug!("switcheroo-control unavailable: {}", err);
+            return Vec::new();
+        }
+    };
+
+    gpus.into_iter()
+        .map(|gpu| SwitcherooGpu {
+            environment: gpu
+                .environment
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            name: gpu.name,
+            default: gpu.default,
+        })
+        .collect()
+}
+
+/// A DRM card found in sysfs.
+struct Card {
+    /// PCI slot, such as `0000:01:00.0`.
+    slot: String,
+    driver: Option<String>,
+    boot_vga: bool,
+    external: bool,
+    power_state: PowerState,
+    /// Device nodes under `/dev` that belong to this GPU.
+    nodes: Vec<PathBuf>,
+}
+
+fn file_name(path: &Path) -> Option<String> {
+    Some(path.file_name()?.to_string_lossy().into_owned())
+}
+
+fn read_trimmed(path: impl AsRef<Path>) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn cards() -> Vec<Card> {
+    let Ok(entries) = fs::read_dir("/sys/class/drm") else {
+        return Vec::new();
+    };
+
+    // Both `cardN` and `renderDN` nodes, keyed by the PCI device they belong to
+    let mut nodes: HashMap<PathBuf, Vec<(String, PathBuf)>> = HashMap::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let is_card = name.starts_with("card") && !name.contains('-');
+        if !is_card && !name.starts_with("renderD") {
+            continue;
+        }
+        if let Ok(device) = fs::canonicalize(entry.path().join("device")) {
+            nodes
+                .entry(device)
+                .or_default()
+                .push((name.clone(), PathBuf::from("/dev/dri").join(name)));
+        }
+    }
+
+    let mut cards = nodes
+        .into_iter()
+        .filter(|(_, nodes)| nodes.iter().any(|(name, _)| name.starts_with("card")))
+        .map(|(device, nodes)| {
+            let driver = fs::read_link(device.join("driver"))
+                .ok()
+                .and_then(|driver| file_name(&driver));
+            let mut nodes = nodes.into_iter().map(|(_, path)| path).collect::<Vec<_>>();
+            if driver.as_deref() == Some("nvidia") {
+                // The proprietary driver is mostly used through its own nodes
+                nodes.extend(nvidia_nodes());
+            }
+            Card {
+                slot: file_name(&device).unwrap_or_default(),
+                boot_vga: read_trimmed(device.join("boot_vga")).as_deref() == Some("1"),
+                // Set for devices behind external facing ports, since Linux 5.15
+                external: read_trimmed(device.join("removable")).as_deref() == Some("removable"),
+                power_state: match read_trimmed(device.join("power_state")).as_deref() {
+                    Some("D0") => PowerState::Active,
+                    Some("D3cold" | "D3hot") => PowerState::Suspended,
+                    _ => match read_trimmed(device.join("power/runtime_status")).as_deref() {
+                        Some("active") => PowerState::Active,
+                        Some("suspended") => PowerState::Suspended,
+                        _ => PowerState::Unknown,
+                    },
+                },
+                driver,
+                nodes,
+            }
+        })
+        .collect::<Vec<_>>();
+    cards.sort_by(|a, b| a.slot.cmp(&b.slot));
+    cards
+}
+
+fn nvidia_nodes() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir("/dev") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            file_name(path).map_or(false, |name| {
+                name.strip_prefix("nvidia").map_or(false, |n| {
+                    n.chars().all(|c| c.is_ascii_digit()) && !n.is_empty()
+                })
+            })
+        })
+        .collect()
+}
+
+/// Scans the open file descriptors of every process we can access, for ones
+/// referring to the given device nodes.
+fn processes_using(nodes: &[PathBuf]) -> Vec<Process> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    let mut processes = entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse::<u32>().ok()?;
+            let uses_gpu = fs::read_dir(entry.path().join("fd"))
+                .ok()?
+                .flatten()
+                .filter_map(|fd| fs::read_link(fd.path()).ok())
+                .any(|target| nodes.contains(&target));
+            if !uses_gpu {
+                return None;
+            }
+            let name = read_trimmed(entry.path().join("comm"))?;
+            (!IGNORED_PROCESSES.contains(&name.as_str())).then_some(Process { pid, name })
+        })
+        .collect::<Vec<_>>();
+    processes.sort_by_key(|process| process.pid);
+    processes
+}
+
+/// Matches a switcheroo-control GPU to a card, by the PCI slot in `DRI_PRIME`
+/// when available.
+fn matches_card(gpu: &SwitcherooGpu, card: &Card) -> bool {
+    let slot = format!("pci-{}", card.slot.replace([':', '.'], "_"));
+    gpu.environment
+        .iter()
+        .any(|(key, value)| key == "DRI_PRIME" && *value == slot)
+}
+
+/// Render offload variables for a GPU unknown to switcheroo-control, matching
+/// what `prime-run` sets for the proprietary NVIDIA driver.
+fn offload_environment(card: &Card) -> Vec<(String, String)> {
+    let vars: &[(&str, &str)] = if card.driver.as_deref() == Some("nvidia") {
+        &[
+            ("__NV_PRIME_RENDER_OFFLOAD", "1"),
+            ("__VK_LAYER_NV_optimus", "NVIDIA_only"),
+            ("__GLX_VENDOR_LIBRARY_NAME", "nvidia"),
+        ]
+    } else {
+        &[("DRI_PRIME", "1")]
+    };
+    vars.iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+pub async fn gpu_status() -> Vec<GpuStatus> {
+    let mut switcheroo = switcheroo_gpus().await;
+
+    tokio::task::spawn_blocking(move || {
+        let cards = cards();
+        let mut gpus = Vec::with_capacity(cards.len());
+        for card in &cards {
+            let matched = switcheroo
+                .iter()
+                .position(|gpu| matches_card(gpu, card))
+                .or_else(|| {
+                    // The default GPU, and the proprietary NVIDIA driver, don't
+                    // use `DRI_PRIME`.
+                    switcheroo.iter().position(|gpu| {
+                        !gpu.environment.iter().any(|(key, _)| key == "DRI_PRIME")
+                            && gpu.default == card.boot_vga
+                    })
+                })
+                .map(|i| switcheroo.remove(i))
+                .unwrap_or_default();
+
+            let default = if matched.name.is_empty() {
+                card.boot_vga
+            } else {
+                matched.default
+            };
+            gpus.push(GpuStatus {
+                name: if matched.name.is_empty() {
+                    card.slot.clone()
+                } else {
+                    matched.name
+                },
+                default,
+                // Checking processes would wake up a suspended GPU. An external
+                // GPU is checked even as the default, to be unplugged.
+                processes: if default && !card.external || card.power_state == PowerState::Suspended
+                {
+                    Vec::new()
+                } else {
+                    processes_using(&card.nodes)
+                },
+                power_state: card.power_state,
+                environment: if matched.environment.is_empty() && !default {
+                    offload_environment(card)
+                } else {
+                    matched.environment
+                },
+                slot: card.slot.clone(),
+                external: card.external,
+            });
+        }
+        gpus
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Removes the external GPU in `slot` from the PCI bus, so it can be unplugged,
+/// unless applications are still using it.
+pub async fn safe_disconnect(slot: String) -> Result<(), DisconnectError> {
+    let card_slot = slot.clone();
+    let processes = tokio::task::spawn_blocking(move || {
+        cards()
+            .into_iter()
+            .find(|card| card.slot == card_slot)
+            .map(|card| processes_using(&card.nodes))
+    })
+    .await
+    .map_err(|err| DisconnectError::Failed(err.to_string()))?
+    .ok_or_else(|| DisconnectError::Failed(format!("No GPU in slot {slot}")))?;
+    if !processes.is_empty() {
+        return Err(DisconnectError::InUse(processes));
+    }
+
+    let failed = |err: std::io::Error| DisconnectError::Failed(err.to_string());
+    let mut child = Command::new("pkexec")
+        .args(["tee", &format!("/sys/bus/pci/devices/{slot}/remove")])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .map_err(failed)?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(b"1").await.map_err(failed)?;
+    }
+    let status = child.wait().await.map_err(failed)?;
+    if !status.success() {
+        return Err(DisconnectError::Failed(format!(
+            "pkexec exited with {status}"
+        )));
+    }
+    Ok(())
+}
+
+pub fn gpu_subscription<I: 'static + std::hash::Hash + Copy + Send + Sync + std::fmt::Debug>(
+    id: I,
+) -> iced::Subscription<Vec<GpuStatus>> {
+    subscription::channel(id, 50, move |mut output| async move {
+        let mut interval = time::interval(Duration::from_secs(3));
+        interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            if output.send(gpu_status().await).await.is_err() {
+                break;
+            }
+        }
+
+        iced::futures::future::pending().await
+    })
+}
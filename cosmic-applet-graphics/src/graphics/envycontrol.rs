@@ -0,0 +1,79 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Fallback for systems without a graphics switching daemon, using the
+//! `envycontrol` command.
+
+use tokio::process::Command;
+use zbus::Result;
+
+use super::{GraphicsBackend, GraphicsMode, RequiredAction};
+
+#[derive(Debug, Clone)]
+pub struct EnvyControl;
+
+async fn envycontrol(args: &[&str], privileged: bool) -> Result<String> {
+    let output = if privileged {
+        Command::new("pkexec")
+            .arg("envycontrol")
+            .args(args)
+            .output()
+            .await?
+    } else {
+        Command::new("envycontrol").args(args).output().await?
+    };
+    if !output.status.success() {
+        return Err(zbus::Error::Failure(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+impl EnvyControl {
+    pub async fn new() -> Result<Self> {
+        envycontrol(&["--query"], false).await?;
+        Ok(Self)
+    }
+}
+
+impl GraphicsBackend for EnvyControl {
+    async fn supported_modes(&self) -> Result<Vec<GraphicsMode>> {
+        Ok(vec![
+            GraphicsMode::Integrated,
+            GraphicsMode::Hybrid,
+            GraphicsMode::Discrete,
+        ])
+    }
+
+    async fn get_graphics(&self) -> Result<GraphicsMode> {
+        let mode = envycontrol(&["--query"], false).await?;
+        match mode.as_str() {
+            "integrated" => Ok(GraphicsMode::Integrated),
+            "hybrid" => Ok(GraphicsMode::Hybrid),
+            "nvidia" => Ok(GraphicsMode::Discrete),
+            _ => Err(zbus::Error::Failure(format!(
+                "Unknown graphics mode: {}",
+                mode
+            ))),
+        }
+    }
+
+    async fn set_graphics(
+        &self,
+        _from: GraphicsMode,
+        mode: GraphicsMode,
+    ) -> Result<Option<RequiredAction>> {
+        let mode = match mode {
+            GraphicsMode::Integrated => "integrated",
+            GraphicsMode::Hybrid => "hybrid",
+            GraphicsMode::Discrete => "nvidia",
+            GraphicsMode::Compute => {
+                return Err(zbus::Error::Unsupported);
+            }
+        };
+        envycontrol(&["--switch", mode], true).await?;
+        // Changes are applied to the initramfs and Xorg config
+        Ok(Some(RequiredAction::Restart))
+    }
+}
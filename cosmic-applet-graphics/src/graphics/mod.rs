@@ -0,0 +1,117 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Graphics mode switching, through whichever daemon or tool the system has.
+
+use zbus::{Connection, Result};
+
+use self::{envycontrol::EnvyControl, supergfxctl::SuperGfxCtl, system76_power::System76Power};
+
+mod envycontrol;
+mod supergfxctl;
+mod system76_power;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsMode {
+    Integrated,
+    Hybrid,
+    Discrete,
+    Compute,
+}
+
+/// What needs to happen before a new graphics mode takes effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequiredAction {
+    LogOut,
+    Restart,
+}
+
+pub trait GraphicsBackend {
+    /// Modes that can be switched to, in the order they are shown.
+    async fn supported_modes(&self) -> Result<Vec<GraphicsMode>>;
+
+    async fn get_graphics(&self) -> Result<GraphicsMode>;
+
+    /// Switches from the mode the session was started with to `mode`,
+    /// returning what is needed for it to take effect.
+    async fn set_graphics(
+        &self,
+        from: GraphicsMode,
+        mode: GraphicsMode,
+    ) -> Result<Option<RequiredAction>>;
+}
+
+#[derive(Debug, Clone)]
+pub enum Backend {
+    System76Power(System76Power),
+    SuperGfxCtl(SuperGfxCtl),
+    EnvyControl(EnvyControl),
+}
+
+/// Finds the first available backend, preferring the daemons.
+pub async fn detect() -> Option<Backend> {
+    if let Ok(conn) = Connection::system().await {
+        match System76Power::new(&conn).await {
+            Ok(Some(backend)) => return Some(Backend::System76Power(backend)),
+            Ok(None) => {}
+            Err(err) => tracing::debug!("system76-power unavailable: {}", err),
+        }
+        match SuperGfxCtl::new(&conn).await {
+            Ok(backend) => return Some(Backend::SuperGfxCtl(backend)),
+            Err(err) => tracing::debug!("supergfxctl unavailable: {}", err),
+        }
+    }
+    match EnvyControl::new().await {
+        Ok(backend) => Some(Backend::EnvyControl(backend)),
+        Err(err) => {
+            tracing::debug!("envycontrol unavailable: {}", err);
+            None
+        }
+    }
+}
+
+impl GraphicsBackend for Backend {
+    async fn supported_modes(&self) -> Result<Vec<GraphicsMode>> {
+        match self {
+            Self::System76Power(backend) => backend.supported_modes().await,
+            Self::SuperGfxCtl(backend) => backend.supported_modes().await,
+            Self::EnvyControl(backend) => backend.supported_modes().await,
+        }
+    }
+
+    async fn get_graphics(&self) -> Result<GraphicsMode> {
+        match self {
+            Self::System76Power(backend) => backend.get_graphics().await,
+            Self::SuperGfxCtl(backend) => backend.get_graphics().await,
+            Self::EnvyControl(backend) => backend.get_graphics().await,
+        }
+    }
+
+    async fn set_graphics(
+        &self,
+        from: GraphicsMode,
+        mode: GraphicsMode,
+    ) -> Result<Option<RequiredAction>> {
+        match self {
+            Self::System76Power(backend) => backend.set_graphics(from, mode).await,
+            Self::SuperGfxCtl(backend) => backend.set_graphics(from, mode).await,
+            Self::EnvyControl(backend) => backend.set_graphics(from, mode).await,
+        }
+    }
+}
+
+/// Current mode and the supported modes.
+pub async fn get_current_graphics(backend: Backend) -> Result<(GraphicsMode, Vec<GraphicsMode>)> {
+    Ok((
+        backend.get_graphics().await?,
+        backend.supported_modes().await?,
+    ))
+}
+
+pub async fn set_graphics(
+    backend: Backend,
+    from: GraphicsMode,
+    mode: GraphicsMode,
+) -> Result<Option<RequiredAction>> {
+    backend.set_graphics(from, mode).await
+}
@@ -0,0 +1,96 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use zbus::{proxy, Connection, Result};
+
+use super::{GraphicsBackend, GraphicsMode, RequiredAction};
+
+#[proxy(
+    interface = "org.supergfxctl.Daemon",
+    default_service = "org.supergfxctl.Daemon",
+    default_path = "/org/supergfxctl/Gfx"
+)]
+trait Daemon {
+    /// Mode method
+    fn mode(&self) -> zbus::Result<u32>;
+
+    /// SetMode method, returns the action required from the user
+    fn set_mode(&self, mode: u32) -> zbus::Result<u32>;
+
+    /// Supported method
+    fn supported(&self) -> zbus::Result<Vec<u32>>;
+
+    /// Version method
+    fn version(&self) -> zbus::Result<String>;
+}
+
+// `GfxMode` values
+const HYBRID: u32 = 0;
+const INTEGRATED: u32 = 1;
+const NVIDIA_NO_MODESET: u32 = 2;
+const ASUS_MUX_DGPU: u32 = 5;
+
+// `UserActionRequired` values
+const LOGOUT: u32 = 0;
+const REBOOT: u32 = 1;
+
+fn to_mode(mode: u32) -> Option<GraphicsMode> {
+    match mode {
+        HYBRID => Some(GraphicsMode::Hybrid),
+        INTEGRATED => Some(GraphicsMode::Integrated),
+        NVIDIA_NO_MODESET => Some(GraphicsMode::Compute),
+        ASUS_MUX_DGPU => Some(GraphicsMode::Discrete),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SuperGfxCtl(DaemonProxy<'static>);
+
+impl SuperGfxCtl {
+    pub async fn new(conn: &Connection) -> Result<Self> {
+        let proxy = DaemonProxy::new(conn).await?;
+        // The proxy is created lazily, so make sure the daemon is there
+        proxy.version().await?;
+        Ok(Self(proxy))
+    }
+}
+
+impl GraphicsBackend for SuperGfxCtl {
+    async fn supported_modes(&self) -> Result<Vec<GraphicsMode>> {
+        let supported = self.0.supported().await?;
+        Ok([
+            GraphicsMode::Integrated,
+            GraphicsMode::Hybrid,
+            GraphicsMode::Discrete,
+            GraphicsMode::Compute,
+        ]
+        .into_iter()
+        .filter(|mode| supported.iter().any(|m| to_mode(*m) == Some(*mode)))
+        .collect())
+    }
+
+    async fn get_graphics(&self) -> Result<GraphicsMode> {
+        let mode = self.0.mode().await?;
+        to_mode(mode)
+            .ok_or_else(|| zbus::Error::Failure(format!("Unsupported graphics mode: {}", mode)))
+    }
+
+    async fn set_graphics(
+        &self,
+        _from: GraphicsMode,
+        mode: GraphicsMode,
+    ) -> Result<Option<RequiredAction>> {
+        let mode = match mode {
+            GraphicsMode::Integrated => INTEGRATED,
+            GraphicsMode::Hybrid => HYBRID,
+            GraphicsMode::Discrete => ASUS_MUX_DGPU,
+            GraphicsMode::Compute => NVIDIA_NO_MODESET,
+        };
+        Ok(match self.0.set_mode(mode).await? {
+            LOGOUT => Some(RequiredAction::LogOut),
+            REBOOT => Some(RequiredAction::Restart),
+            _ => None,
+        })
+    }
+}
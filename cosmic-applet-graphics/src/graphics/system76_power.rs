@@ -0,0 +1,81 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use zbus::{proxy, Connection, Result};
+
+use super::{GraphicsBackend, GraphicsMode, RequiredAction};
+
+#[proxy(
+    interface = "com.system76.PowerDaemon",
+    default_path = "/com/system76/PowerDaemon",
+    assume_defaults = true
+)]
+trait PowerDaemon {
+    /// GetGraphics method
+    fn get_graphics(&self) -> zbus::Result<String>;
+
+    /// GetSwitchable method
+    fn get_switchable(&self) -> zbus::Result<bool>;
+
+    /// SetGraphics method
+    fn set_graphics(&self, vendor: &str) -> zbus::Result<()>;
+}
+
+#[derive(Debug, Clone)]
+pub struct System76Power(PowerDaemonProxy<'static>);
+
+impl System76Power {
+    /// Returns `None` if the daemon is running, but graphics switching isn't
+    /// supported on this system.
+    pub async fn new(conn: &Connection) -> Result<Option<Self>> {
+        let proxy = PowerDaemonProxy::new(conn).await?;
+        Ok(proxy.get_switchable().await?.then_some(Self(proxy)))
+    }
+}
+
+impl GraphicsBackend for System76Power {
+    async fn supported_modes(&self) -> Result<Vec<GraphicsMode>> {
+        Ok(vec![
+            GraphicsMode::Integrated,
+            GraphicsMode::Hybrid,
+            GraphicsMode::Discrete,
+            GraphicsMode::Compute,
+        ])
+    }
+
+    async fn get_graphics(&self) -> Result<GraphicsMode> {
+        let vendor = self.0.get_graphics().await?;
+        match vendor.as_str() {
+            "integrated" => Ok(GraphicsMode::Integrated),
+            "hybrid" => Ok(GraphicsMode::Hybrid),
+            "nvidia" => Ok(GraphicsMode::Discrete),
+            "compute" => Ok(GraphicsMode::Compute),
+            _ => Err(zbus::Error::Failure(format!(
+                "Unknown graphics mode: {}",
+                vendor
+            ))),
+        }
+    }
+
+    async fn set_graphics(
+        &self,
+        from: GraphicsMode,
+        mode: GraphicsMode,
+    ) -> Result<Option<RequiredAction>> {
+        let vendor = match mode {
+            GraphicsMode::Integrated => "integrated",
+            GraphicsMode::Hybrid => "hybrid",
+            GraphicsMode::Discrete => "nvidia",
+            GraphicsMode::Compute => "compute",
+        };
+        self.0.set_graphics(vendor).await?;
+
+        // Switching between modes that both load the NVIDIA driver only needs
+        // a new session, anything else changes what is loaded at boot.
+        Ok(Some(match (from, mode) {
+            (GraphicsMode::Hybrid, GraphicsMode::Discrete)
+            | (GraphicsMode::Discrete, GraphicsMode::Hybrid) => RequiredAction::LogOut,
+            _ => RequiredAction::Restart,
+        }))
+    }
+}
@@ -0,0 +1,63 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Watches udev for GPUs being attached and detached, like external GPUs
+//! connected over Thunderbolt.
+
+use std::os::fd::{AsRawFd, RawFd};
+
+use cosmic::iced::{self, futures::SinkExt, subscription};
+use tokio::io::{unix::AsyncFd, Interest};
+use udev::EventType;
+
+struct WrappedSocket(udev::MonitorSocket);
+
+impl AsRawFd for WrappedSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+unsafe impl Send for WrappedSocket {}
+unsafe impl Sync for WrappedSocket {}
+
+fn monitor() -> std::io::Result<AsyncFd<WrappedSocket>> {
+    let socket = udev::MonitorBuilder::new()?
+        .match_subsystem("drm")?
+        .listen()?;
+    AsyncFd::with_interest(WrappedSocket(socket), Interest::READABLE)
+}
+
+/// Emits when a GPU is added or removed.
+pub fn hotplug_subscription<I: 'static + std::hash::Hash + Copy + Send + Sync + std::fmt::Debug>(
+    id: I,
+) -> iced::Subscription<()> {
+    subscription::channel(id, 10, move |mut output| async move {
+        let monitor = match monitor() {
+            Ok(monitor) => monitor,
+            Err(err) => {
+                tracing::error!("Failed to watch for GPUs: {}", err);
+                iced::futures::future::pending().await
+            }
+        };
+
+        loop {
+            let Ok(mut guard) = monitor.readable().await else {
+                break;
+            };
+            let changed = monitor.get_ref().0.iter().any(|event| {
+                matches!(event.event_type(), EventType::Add | EventType::Remove)
+                    && event
+                        .sysname()
+                        .to_str()
+                        .is_some_and(|name| name.starts_with("card") && !name.contains('-'))
+            });
+            guard.clear_ready();
+            if changed && output.send(()).await.is_err() {
+                break;
+            }
+        }
+
+        iced::futures::future::pending().await
+    })
+}
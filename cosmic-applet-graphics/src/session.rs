@@ -0,0 +1,37 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Applying a graphics mode change by ending the session or restarting.
+
+use logind_zbus::manager::ManagerProxy;
+use zbus::{proxy, Connection};
+
+use crate::graphics::RequiredAction;
+
+#[proxy(
+    interface = "com.system76.CosmicSession",
+    default_service = "com.system76.CosmicSession",
+    default_path = "/com/system76/CosmicSession"
+)]
+trait CosmicSession {
+    fn exit(&self) -> zbus::Result<()>;
+}
+
+async fn restart() -> zbus::Result<()> {
+    let connection = Connection::system().await?;
+    let manager_proxy = ManagerProxy::new(&connection).await?;
+    manager_proxy.reboot(true).await
+}
+
+async fn log_out() -> zbus::Result<()> {
+    let connection = Connection::session().await?;
+    let cosmic_session = CosmicSessionProxy::new(&connection).await?;
+    cosmic_session.exit().await
+}
+
+pub async fn apply(action: RequiredAction) -> zbus::Result<()> {
+    match action {
+        RequiredAction::LogOut => log_out().await,
+        RequiredAction::Restart => restart().await,
+    }
+}
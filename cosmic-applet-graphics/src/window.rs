@@ -0,0 +1,548 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::{
+    apps::{load_apps, search, App},
+    fl,
+    gpu::{
+        gpu_status, gpu_subscription, safe_disconnect, DisconnectError, GpuStatus, PowerState,
+        Process,
+    },
+    graphics::{detect, get_current_graphics, set_graphics, Backend, GraphicsMode, RequiredAction},
+    hotplug::hotplug_subscription,
+    session,
+};
+use cosmic::{
+    app::Core,
+    applet::{
+        cosmic_panel_config::PanelAnchor,
+        menu_button, padded_control,
+        token::subscription::{activation_token_subscription, TokenRequest, TokenUpdate},
+    },
+    cctk::sctk::reexports::calloop,
+    desktop::IconSource,
+    iced::{
+        widget::{column, container, row},
+        Alignment, Command, Length, Limits, Subscription,
+    },
+    iced_core::{Background, Border, Color, Shadow},
+    iced_widget::{Column, Row},
+    widget::{
+        button, divider, horizontal_space, icon, scrollable, search_input, text, vertical_space,
+    },
+    Element, Theme,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent, PopupRequest, PopupWidth};
+
+const ID: &str = "com.system76.CosmicAppletGraphics";
+const ICON: &str = "com.system76.CosmicAppletGraphics-symbolic";
+
+#[derive(Default)]
+pub struct Window {
+    /// `None` if graphics switching isn't supported.
+    backend: Option<Backend>,
+    supported_modes: Vec<GraphicsMode>,
+    graphics_mode: Option<GraphicsMode>,
+    /// Mode that is being switched to.
+    pending: Option<GraphicsMode>,
+    /// Mode the current session was started with.
+    session_mode: Option<GraphicsMode>,
+    /// Needed for the selected mode to take effect.
+    required_action: Option<RequiredAction>,
+    /// Whether the restart prompt was dismissed with "Later".
+    prompt_dismissed: bool,
+    gpus: Vec<GpuStatus>,
+    /// Slot of the external GPU being disconnected.
+    disconnecting: Option<String>,
+    /// Slot of the external GPU that couldn't be disconnected, and the
+    /// applications using it.
+    disconnect_blocked: Option<(String, Vec<Process>)>,
+    apps: Vec<App>,
+    search: String,
+    token_tx: Option<calloop::channel::Sender<TokenRequest>>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    Backend(Option<Backend>),
+    CurrentGraphics(Option<(GraphicsMode, Vec<GraphicsMode>)>),
+    SelectGraphicsMode(GraphicsMode),
+    GraphicsModeSet(GraphicsMode, Result<Option<RequiredAction>, String>),
+    GpuStatus(Vec<GpuStatus>),
+    Hotplug,
+    Disconnect(String),
+    Disconnected(String, Result<(), DisconnectError>),
+    Apps(Vec<App>),
+    Search(String),
+    LaunchOnDgpu(String),
+    Token(TokenUpdate),
+    ApplyNow,
+    ApplyLater,
+    Applied(Result<(), String>),
+}
+
+impl Window {
+    fn prompt_view(&self, action: RequiredAction) -> Element<Message> {
+        let (message, apply) = match action {
+            RequiredAction::LogOut => (fl!("log-out-required"), fl!("log-out-now")),
+            RequiredAction::Restart => (fl!("restart-required"), fl!("restart-now")),
+        };
+
+        let mut content = Column::new()
+            .push(text(message).size(14).width(Length::Fill))
+            .spacing(8);
+        if !self.prompt_dismissed {
+            content = content.push(
+                row![
+                    horizontal_space(Length::Fill),
+                    button::standard(fl!("later")).on_press(Message::ApplyLater),
+                    button::suggested(apply).on_press(Message::ApplyNow),
+                ]
+                .spacing(8),
+            );
+        }
+        padded_control(content).into()
+    }
+
+    /// The GPU applications are offloaded to, if there is one. An external GPU
+    /// is preferred, having been attached to be used.
+    fn dgpu(&self) -> Option<&GpuStatus> {
+        self.gpus
+            .iter()
+            .find(|gpu| !gpu.default && gpu.external)
+            .or_else(|| self.gpus.iter().find(|gpu| !gpu.default))
+    }
+
+    fn launcher_view(&self) -> Vec<Element<Message>> {
+        let mut content = vec![padded_control(
+            search_input(fl!("launch-on-dgpu"), &self.search)
+                .on_input(Message::Search)
+                .on_clear(Message::Search(String::new()))
+                .on_submit(
+                    search(&self.apps, &self.search)
+                        .first()
+                        .map(|app| Message::LaunchOnDgpu(app.exec.clone()))
+                        .unwrap_or(Message::Search(self.search.clone())),
+                ),
+        )
+        .into()];
+
+        for app in search(&self.apps, &self.search) {
+            content.push(
+                menu_button(
+                    row![
+                        IconSource::from_unknown(app.icon.as_deref().unwrap_or_default())
+                            .as_cosmic_icon()
+                            .size(24),
+                        text(&app.name).size(14).width(Length::Fill),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .on_press(Message::LaunchOnDgpu(app.exec.clone()))
+                .into(),
+            );
+        }
+        content
+    }
+
+    fn mode_button(&self, mode: GraphicsMode) -> Element<Message> {
+        let (title, description) = match mode {
+            GraphicsMode::Integrated => (fl!("integrated"), fl!("integrated-desc")),
+            GraphicsMode::Hybrid => (fl!("hybrid"), fl!("hybrid-desc")),
+            GraphicsMode::Discrete => (fl!("discrete"), fl!("discrete-desc")),
+            GraphicsMode::Compute => (fl!("compute"), fl!("compute-desc")),
+        };
+        let selected = self.pending.unwrap_or_else(|| {
+            self.graphics_mode
+                .expect("mode buttons are only shown when switchable")
+        }) == mode;
+
+        let button = menu_button(
+            row![
+                column![text(title).size(14), text(description).size(10)].width(Length::Fill),
+                if selected {
+                    container(
+                        icon::from_name("emblem-ok-symbolic")
+                            .size(12)
+                            .symbolic(true),
+                    )
+                } else {
+                    container(horizontal_space(1.0))
+                }
+            ]
+            .align_items(Alignment::Center),
+        );
+
+        if self.pending.is_none() {
+            button.on_press(Message::SelectGraphicsMode(mode)).into()
+        } else {
+            button.into()
+        }
+    }
+
+    fn gpu_view<'a>(&'a self, gpu: &'a GpuStatus) -> Element<'a, Message> {
+        let state = match gpu.power_state {
+            PowerState::Active => fl!("active"),
+            PowerState::Suspended => fl!("suspended"),
+            PowerState::Unknown => fl!("unknown"),
+        };
+        let mut description = state;
+        if gpu.default {
+            description = format!("{} · {}", fl!("default-gpu"), description);
+        }
+        if gpu.external {
+            description = format!("{} · {}", fl!("external-gpu"), description);
+        }
+
+        let mut row = row![
+            icon::from_name(if gpu.power_state == PowerState::Suspended {
+                "system-suspend-symbolic"
+            } else {
+                ICON
+            })
+            .size(24)
+            .symbolic(true),
+            column![text(gpu.name.trim()).size(14), text(description).size(10)].width(Length::Fill),
+        ]
+        .spacing(8)
+        .align_items(Alignment::Center);
+        if gpu.external {
+            let mut disconnect = button::standard(fl!("disconnect"));
+            if self.disconnecting.is_none() {
+                disconnect = disconnect.on_press(Message::Disconnect(gpu.slot.clone()));
+            }
+            row = row.push(disconnect);
+        }
+
+        let mut content = Column::new().push(row).spacing(8);
+        if let Some((_, processes)) = self
+            .disconnect_blocked
+            .as_ref()
+            .filter(|(slot, _)| *slot == gpu.slot)
+        {
+            content = content.push(text(fl!("egpu-in-use")).size(10));
+            for process in processes {
+                content = content.push(row![
+                    text(&process.name).size(14).width(Length::Fill),
+                    text(process.pid.to_string()).size(10),
+                ]);
+            }
+        }
+        padded_control(content).into()
+    }
+
+    /// A dot next to the panel icon, in the accent color or else the success
+    /// color.
+    fn badge(accent: bool) -> Element<'static, Message> {
+        container(vertical_space(Length::Fixed(0.0)))
+            .padding(2.0)
+            .style(<Theme as container::StyleSheet>::Style::Custom(Box::new(
+                move |theme| container::Appearance {
+                    text_color: Some(Color::TRANSPARENT),
+                    background: Some(Background::Color(if accent {
+                        theme.cosmic().accent_color().into()
+                    } else {
+                        theme.cosmic().success_color().into()
+                    })),
+                    border: Border {
+                        radius: 2.0.into(),
+                        width: 0.0,
+                        color: Color::TRANSPARENT,
+                    },
+                    shadow: Shadow::default(),
+                    icon_color: Some(Color::TRANSPARENT),
+                },
+            )))
+            .into()
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let window = Self {
+            ..Default::default()
+        };
+        (
+            window,
+            Command::batch([
+                Command::perform(detect(), |backend| Message::Backend(backend)),
+                Command::perform(gpu_status(), |gpus| Message::GpuStatus(gpus)),
+            ]),
+        )
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        if event != PanelEvent::PopupOpened {
+            return Command::none();
+        }
+        self.search.clear();
+        Command::batch([
+            Command::perform(gpu_status(), |gpus| Message::GpuStatus(gpus)),
+            Command::perform(load_apps(), |apps| Message::Apps(apps)),
+        ])
+    }
+
+    fn subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        Subscription::batch(vec![
+            activation_token_subscription(0).map(Message::Token),
+            hotplug_subscription(0).map(|()| Message::Hotplug),
+        ])
+    }
+
+    fn popup_subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        // Processes are only scanned while they're visible
+        gpu_subscription(0).map(Message::GpuStatus)
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::Backend(backend) => {
+                self.backend = backend;
+                if let Some(backend) = self.backend.clone() {
+                    return Command::perform(get_current_graphics(backend), |res| {
+                        Message::CurrentGraphics(
+                            res.map_err(|err| {
+                                tracing::error!("Failed to get graphics mode: {}", err);
+                            })
+                            .ok(),
+                        )
+                    });
+                }
+            }
+            Message::CurrentGraphics(current) => {
+                let mode = current.as_ref().map(|(mode, _)| *mode);
+                if self.session_mode.is_none() {
+                    self.session_mode = mode;
+                }
+                self.graphics_mode = mode;
+                self.supported_modes = current.map(|(_, modes)| modes).unwrap_or_default();
+            }
+            Message::SelectGraphicsMode(mode) => {
+                let (Some(backend), Some(from)) = (self.backend.clone(), self.graphics_mode) else {
+                    return Command::none();
+                };
+                if from != mode && self.pending.is_none() {
+                    self.pending = Some(mode);
+                    let from = self.session_mode.unwrap_or(from);
+                    return Command::perform(set_graphics(backend, from, mode), move |res| {
+                        Message::GraphicsModeSet(mode, res.map_err(|err| err.to_string()))
+                    });
+                }
+            }
+            Message::GraphicsModeSet(mode, res) => {
+                self.pending = None;
+                match res {
+                    Ok(required_action) => {
+                        self.graphics_mode = Some(mode);
+                        self.prompt_dismissed = false;
+                        // Switching back to the mode in use needs nothing
+                        self.required_action = if self.session_mode == Some(mode) {
+                            None
+                        } else {
+                            required_action
+                        };
+                    }
+                    Err(err) => tracing::error!("Failed to set graphics mode: {}", err),
+                }
+            }
+            Message::ApplyNow => {
+                if let Some(action) = self.required_action {
+                    return Command::perform(session::apply(action), |res| {
+                        Message::Applied(res.map_err(|err| err.to_string()))
+                    });
+                }
+            }
+            Message::ApplyLater => {
+                self.prompt_dismissed = true;
+            }
+            Message::Applied(res) => {
+                if let Err(err) = res {
+                    tracing::error!("Failed to apply graphics mode: {}", err);
+                }
+            }
+            Message::GpuStatus(gpus) => {
+                self.gpus = gpus;
+                if let Some((slot, _)) = &self.disconnect_blocked {
+                    // Offer to try again once the applications are closed
+                    let in_use = self
+                        .gpus
+                        .iter()
+                        .any(|gpu| gpu.slot == *slot && !gpu.processes.is_empty());
+                    if !in_use {
+                        self.disconnect_blocked = None;
+                    }
+                }
+            }
+            Message::Hotplug => {
+                return Command::perform(gpu_status(), |gpus| Message::GpuStatus(gpus));
+            }
+            Message::Disconnect(slot) => {
+                self.disconnecting = Some(slot.clone());
+                self.disconnect_blocked = None;
+                return Command::perform(safe_disconnect(slot.clone()), move |res| {
+                    Message::Disconnected(slot.clone(), res)
+                });
+            }
+            Message::Disconnected(slot, res) => {
+                self.disconnecting = None;
+                match res {
+                    Ok(()) => {
+                        return Command::perform(gpu_status(), |gpus| Message::GpuStatus(gpus));
+                    }
+                    Err(DisconnectError::InUse(processes)) => {
+                        self.disconnect_blocked = Some((slot, processes));
+                    }
+                    Err(DisconnectError::Failed(err)) => {
+                        tracing::error!("Failed to disconnect external GPU: {}", err);
+                    }
+                }
+            }
+            Message::Apps(apps) => {
+                self.apps = apps;
+            }
+            Message::Search(search) => {
+                self.search = search;
+            }
+            Message::LaunchOnDgpu(exec) => {
+                self.search.clear();
+                if let Some(tx) = self.token_tx.as_ref() {
+                    let _ = tx.send(TokenRequest {
+                        app_id: Self::APP_ID.to_string(),
+                        exec,
+                    });
+                } else {
+                    tracing::error!("Wayland tx is None");
+                }
+            }
+            Message::Token(u) => match u {
+                TokenUpdate::Init(tx) => {
+                    self.token_tx = Some(tx);
+                }
+                TokenUpdate::Finished => {
+                    self.token_tx = None;
+                }
+                TokenUpdate::ActivationToken { token, exec, .. } => {
+                    let mut envs = Vec::new();
+                    if let Some(token) = token {
+                        envs.push(("XDG_ACTIVATION_TOKEN".to_string(), token.clone()));
+                        envs.push(("DESKTOP_STARTUP_ID".to_string(), token));
+                    }
+                    if let Some(dgpu) = self.dgpu() {
+                        envs.extend(dgpu.environment.iter().cloned());
+                    }
+                    tokio::task::spawn_blocking(|| {
+                        cosmic::desktop::spawn_desktop_exec(exec, envs);
+                    });
+                }
+            },
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let btn = core
+            .applet
+            .icon_button(ICON)
+            .on_press(AppletMessage::TogglePopup)
+            .into();
+
+        let mut children = vec![btn];
+        // Until the pending mode is applied
+        if self.required_action.is_some() {
+            children.push(Self::badge(true).map(AppletMessage::App));
+        }
+        if self.gpus.iter().any(|gpu| gpu.external) {
+            children.push(Self::badge(false).map(AppletMessage::App));
+        }
+        if children.len() == 1 {
+            return children.remove(0);
+        }
+
+        match core.applet.anchor {
+            PanelAnchor::Left | PanelAnchor::Right => Column::with_children(children)
+                .spacing(2)
+                .align_items(Alignment::Center)
+                .into(),
+            PanelAnchor::Top | PanelAnchor::Bottom => Row::with_children(children)
+                .spacing(2)
+                .align_items(Alignment::Center)
+                .into(),
+        }
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = Vec::new();
+
+        if self.graphics_mode.is_some() {
+            content.push(
+                padded_control(text(fl!("graphics-mode")).size(14))
+                    .width(Length::Fill)
+                    .into(),
+            );
+            for mode in &self.supported_modes {
+                content.push(self.mode_button(*mode));
+            }
+            if let Some(action) = self.required_action {
+                content.push(self.prompt_view(action));
+            }
+            content.push(padded_control(divider::horizontal::default()).into());
+        }
+
+        for gpu in &self.gpus {
+            content.push(self.gpu_view(gpu));
+        }
+
+        if self.dgpu().is_some() {
+            content.push(padded_control(divider::horizontal::default()).into());
+            content.extend(self.launcher_view());
+        }
+
+        // Processes keeping a non-default GPU awake
+        let processes = self
+            .gpus
+            .iter()
+            .filter(|gpu| !gpu.default)
+            .flat_map(|gpu| &gpu.processes)
+            .collect::<Vec<_>>();
+        if !processes.is_empty() {
+            content.push(padded_control(divider::horizontal::default()).into());
+            content.push(
+                padded_control(text(fl!("dgpu-processes")).size(10))
+                    .width(Length::Fill)
+                    .into(),
+            );
+            let list = processes
+                .into_iter()
+                .map(|process| {
+                    padded_control(row![
+                        text(&process.name).size(14).width(Length::Fill),
+                        text(process.pid.to_string()).size(10),
+                    ])
+                    .into()
+                })
+                .collect::<Vec<_>>();
+            content.push(
+                scrollable(Column::with_children(list))
+                    .height(Length::Shrink)
+                    .into(),
+            );
+        } else if self.gpus.is_empty() {
+            content.push(padded_control(text(fl!("no-gpus")).size(14)).into());
+        }
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+
+    fn popup_request(&self, message: &Message) -> Option<PopupRequest> {
+        matches!(message, Message::ApplyLater | Message::LaunchOnDgpu(_))
+            .then_some(PopupRequest::Close)
+    }
+
+    fn popup_limits(&self, _core: &Core) -> Limits {
+        PopupWidth::Standard.limits().min_height(200.0)
+    }
+}
@@ -0,0 +1 @@
+applet-crashed = Crashed, click to restart
@@ -0,0 +1,37 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Names for the panel buttons, for screen readers.
+//!
+//! A button showing only an icon gives a screen reader nothing to read, so it
+//! needs a name. Text, togglers, sliders and buttons with text are read from
+//! their content, which covers most of a popup. Containers aren't part of the
+//! accessibility tree, so there's nothing to name a popup by; its first text
+//! is read instead.
+
+use std::borrow::Cow;
+
+use cosmic::{
+    app::Core,
+    widget::{icon, Button},
+};
+
+/// The applet's panel button with a named icon, called `label` by screen
+/// readers.
+pub fn icon_button<'a, M: 'static>(
+    core: &Core,
+    icon_name: &'a str,
+    label: impl Into<Cow<'a, str>>,
+) -> Button<'a, M> {
+    core.applet.icon_button(icon_name).name(label)
+}
+
+/// The applet's panel button with an icon from a handle, called `label` by
+/// screen readers.
+pub fn icon_button_from_handle<'a, M: 'static>(
+    core: &Core,
+    handle: icon::Handle,
+    label: impl Into<Cow<'a, str>>,
+) -> Button<'a, M> {
+    core.applet.icon_button_from_handle(handle).name(label)
+}
@@ -0,0 +1,50 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Crash reports, and keeping a panicking applet from taking its panel
+//! surface down with it.
+
+use std::{
+    backtrace::Backtrace,
+    cell::Cell,
+    panic::{self, AssertUnwindSafe, PanicInfo},
+    thread,
+};
+
+/// Logs a report for every panic, whether or not it's caught, with the
+/// applet it happened in.
+pub fn install_panic_hook(applet: &str, version: &'static str) {
+    let applet = applet.to_string();
+    panic::set_hook(Box::new(move |info: &PanicInfo<'_>| {
+        let message = info
+            .payload()
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("Box<dyn Any>");
+        let location = info
+            .location()
+            .map(|location| location.to_string())
+            .unwrap_or_default();
+        tracing::error!(
+            applet = %applet,
+            version,
+            thread = thread::current().name().unwrap_or("<unnamed>"),
+            location = %location,
+            "Applet panicked: {}\n{}",
+            message,
+            Backtrace::force_capture()
+        );
+    }));
+}
+
+/// Runs `f`, marking the applet as crashed if it panics.
+pub(crate) fn guard<T>(crashed: &Cell<bool>, f: impl FnOnce() -> T) -> Option<T> {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(value) => Some(value),
+        Err(_) => {
+            crashed.set(true);
+            None
+        }
+    }
+}
@@ -0,0 +1,61 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Right-to-left layouts, for languages like Arabic and Hebrew. iced lays
+//! everything out from the left, so applets mirror it themselves.
+
+use cosmic::iced::alignment::Horizontal;
+use i18n_embed::{unic_langid::LanguageIdentifier, DesktopLanguageRequester};
+use once_cell::sync::Lazy;
+
+/// Languages written right to left, unless a script is given.
+const RTL_LANGUAGES: &[&str] = &[
+    "ar", "arc", "ckb", "dv", "fa", "he", "ks", "ps", "sd", "ug", "ur", "yi",
+];
+/// Scripts written right to left.
+const RTL_SCRIPTS: &[&str] = &["Adlm", "Arab", "Hebr", "Nkoo", "Rohg", "Syrc", "Thaa"];
+
+static RTL: Lazy<bool> = Lazy::new(|| {
+    DesktopLanguageRequester::requested_languages()
+        .first()
+        .map_or(false, is_rtl_language)
+});
+
+fn is_rtl_language(language: &LanguageIdentifier) -> bool {
+    match language.script {
+        Some(script) => RTL_SCRIPTS.contains(&script.as_str()),
+        None => RTL_LANGUAGES.contains(&language.language.as_str()),
+    }
+}
+
+/// Whether the user's language is written right to left.
+pub fn is_rtl() -> bool {
+    *RTL
+}
+
+/// Where lines start, for aligning text and content.
+pub fn start_alignment() -> Horizontal {
+    if is_rtl() {
+        Horizontal::Right
+    } else {
+        Horizontal::Left
+    }
+}
+
+/// Where lines end, like for values after a label.
+pub fn end_alignment() -> Horizontal {
+    if is_rtl() {
+        Horizontal::Left
+    } else {
+        Horizontal::Right
+    }
+}
+
+/// `items` given in reading order, in the order to lay them out from the left,
+/// such as an icon and its label.
+pub fn reading_order<T>(mut items: Vec<T>) -> Vec<T> {
+    if is_rtl() {
+        items.reverse();
+    }
+    items
+}
@@ -0,0 +1,89 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Approximate location from GeoClue, for applets following the sun or the
+//! local weather.
+
+use std::time::Duration;
+
+use cosmic::iced_futures::futures::StreamExt;
+use zbus::{proxy, zvariant::OwnedObjectPath, Connection};
+
+/// GeoClue's `GCLUE_ACCURACY_LEVEL_CITY`; enough to know when the sun sets or
+/// for a forecast.
+const ACCURACY_CITY: u32 = 4;
+
+#[proxy(
+    interface = "org.freedesktop.GeoClue2.Manager",
+    default_service = "org.freedesktop.GeoClue2",
+    default_path = "/org/freedesktop/GeoClue2/Manager"
+)]
+trait Manager {
+    /// GetClient method
+    fn get_client(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.GeoClue2.Client",
+    default_service = "org.freedesktop.GeoClue2"
+)]
+trait Client {
+    /// Start method
+    fn start(&self) -> zbus::Result<()>;
+
+    /// Stop method
+    fn stop(&self) -> zbus::Result<()>;
+
+    /// LocationUpdated signal
+    #[zbus(signal)]
+    fn location_updated(&self, old: OwnedObjectPath, new: OwnedObjectPath) -> zbus::Result<()>;
+
+    /// DesktopId property
+    #[zbus(property)]
+    fn set_desktop_id(&self, id: &str) -> zbus::Result<()>;
+
+    /// RequestedAccuracyLevel property
+    #[zbus(property)]
+    fn set_requested_accuracy_level(&self, level: u32) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.GeoClue2.Location",
+    default_service = "org.freedesktop.GeoClue2"
+)]
+trait Location {
+    /// Latitude property
+    #[zbus(property)]
+    fn latitude(&self) -> zbus::Result<f64>;
+
+    /// Longitude property
+    #[zbus(property)]
+    fn longitude(&self) -> zbus::Result<f64>;
+}
+
+/// Latitude and longitude, waiting up to 30 seconds for a fix. GeoClue asks
+/// its agent whether the applet with `desktop_id` may locate the device.
+pub async fn location(desktop_id: &str) -> zbus::Result<(f64, f64)> {
+    let connection = Connection::system().await?;
+    let client_path = ManagerProxy::new(&connection).await?.get_client().await?;
+    let client = ClientProxy::builder(&connection)
+        .path(client_path)?
+        .build()
+        .await?;
+    client.set_desktop_id(desktop_id).await?;
+    client.set_requested_accuracy_level(ACCURACY_CITY).await?;
+
+    let mut updates = client.receive_location_updated().await?;
+    client.start().await?;
+    let update = tokio::time::timeout(Duration::from_secs(30), updates.next()).await;
+    let _ = client.stop().await;
+
+    let Ok(Some(update)) = update else {
+        return Err(zbus::Error::Failure("no location from GeoClue".to_string()));
+    };
+    let location = LocationProxy::builder(&connection)
+        .path(update.args()?.new)?
+        .build()
+        .await?;
+    Ok((location.latitude().await?, location.longitude().await?))
+}
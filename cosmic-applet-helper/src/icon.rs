@@ -0,0 +1,54 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Named icons, looked up once per icon theme.
+//!
+//! Looking up a named icon searches the theme's directories each time, which
+//! adds up for applets drawing dozens of icons every frame. The renderer keeps
+//! icons parsed and recolored per handle and color, so handing it the same
+//! handle each frame also saves the parsing and recoloring.
+
+use std::{cell::RefCell, collections::HashMap};
+
+use cosmic::widget::icon;
+
+thread_local! {
+    static CACHE: RefCell<Cache> = RefCell::default();
+}
+
+#[derive(Default)]
+struct Cache {
+    /// The icon theme the handles were looked up in.
+    theme: String,
+    handles: HashMap<(String, u16, bool), icon::Handle>,
+}
+
+fn lookup(name: &str, size: u16, symbolic: bool) -> icon::Handle {
+    let named = icon::from_name(name).size(size).symbolic(symbolic);
+    match named.clone().path() {
+        Some(path) => {
+            let mut handle = icon::from_path(path);
+            handle.symbolic = symbolic;
+            handle
+        }
+        None => named.handle(),
+    }
+}
+
+/// Handle for a named icon, looked up on first use and again after the icon
+/// theme changes.
+pub fn icon_handle(name: &str, size: u16, symbolic: bool) -> icon::Handle {
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let theme = cosmic::icon_theme::default();
+        if cache.theme != theme {
+            cache.theme = theme;
+            cache.handles.clear();
+        }
+        cache
+            .handles
+            .entry((name.to_string(), size, symbolic))
+            .or_insert_with(|| lookup(name, size, symbolic))
+            .clone()
+    })
+}
@@ -0,0 +1,154 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Images scaled down to the size they're shown at, such as album art, window
+//! previews and the images of notifications. A full size image would be kept
+//! in memory by its handle, and uploaded to the GPU at that size.
+
+use std::{cell::Cell, collections::HashMap, hash::Hash, path::Path};
+
+use cosmic::widget::image::Handle;
+use image::{imageops, RgbaImage};
+
+/// A decoded image and its handle, which keeps the widget from uploading it
+/// again on every view.
+#[derive(Debug, Clone)]
+pub struct Thumbnail {
+    pub handle: Handle,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Thumbnail {
+    pub fn new(img: RgbaImage) -> Self {
+        Self {
+            width: img.width(),
+            height: img.height(),
+            handle: Handle::from_pixels(img.width(), img.height(), img.into_raw()),
+        }
+    }
+
+    /// The memory taken by its pixels.
+    pub fn bytes(&self) -> usize {
+        self.width as usize * self.height as usize * 4
+    }
+}
+
+/// Scales `img` down to fit in `max_width` by `max_height` pixels, keeping its
+/// aspect ratio. Smaller images are kept as they are.
+pub fn fit(img: RgbaImage, max_width: u32, max_height: u32) -> RgbaImage {
+    let ratio =
+        (img.width() as f32 / max_width as f32).max(img.height() as f32 / max_height as f32);
+    if ratio <= 1.0 {
+        return img;
+    }
+    imageops::thumbnail(
+        &img,
+        ((img.width() as f32 / ratio).round() as u32).max(1),
+        ((img.height() as f32 / ratio).round() as u32).max(1),
+    )
+}
+
+/// Decodes the image at `path` and scales it down with [`fit`]. Blocks, so
+/// it's run with `tokio::task::spawn_blocking`.
+pub fn decode_file(path: &Path, max_width: u32, max_height: u32) -> Option<Thumbnail> {
+    match image::open(path) {
+        Ok(img) => Some(Thumbnail::new(fit(img.into_rgba8(), max_width, max_height))),
+        Err(err) => {
+            tracing::warn!(?err, "Failed to decode {}", path.display());
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    thumbnail: Thumbnail,
+    /// When it was last used, by the cache's clock.
+    used: Cell<u64>,
+}
+
+/// Thumbnails dropping the least recently used ones past a budget of bytes.
+/// Getting one counts as using it, so views can keep what they show.
+#[derive(Debug, Clone)]
+pub struct ImageCache<K> {
+    entries: HashMap<K, Entry>,
+    bytes: usize,
+    budget: usize,
+    clock: Cell<u64>,
+}
+
+/// Room for a few dozen thumbnails the size of a panel popup's.
+impl<K> Default for ImageCache<K> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+            bytes: 0,
+            budget: 8 * 1024 * 1024,
+            clock: Cell::new(0),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone> ImageCache<K> {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            bytes: 0,
+            budget,
+            clock: Cell::new(0),
+        }
+    }
+
+    fn tick(&self) -> u64 {
+        let now = self.clock.get() + 1;
+        self.clock.set(now);
+        now
+    }
+
+    pub fn get(&self, key: &K) -> Option<&Thumbnail> {
+        let entry = self.entries.get(key)?;
+        entry.used.set(self.tick());
+        Some(&entry.thumbnail)
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub fn insert(&mut self, key: K, thumbnail: Thumbnail) {
+        self.remove(&key);
+        self.bytes += thumbnail.bytes();
+        let used = Cell::new(self.tick());
+        self.entries.insert(key.clone(), Entry { thumbnail, used });
+        while self.bytes > self.budget {
+            // The new one is kept even if it's over budget by itself
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .filter(|(k, _)| **k != key)
+                .min_by_key(|(_, entry)| entry.used.get())
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.bytes -= entry.thumbnail.bytes();
+        }
+    }
+
+    pub fn retain(&mut self, mut f: impl FnMut(&K) -> bool) {
+        self.entries.retain(|key, _| f(key));
+        self.bytes = self.entries.values().map(|e| e.thumbnail.bytes()).sum();
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.bytes = 0;
+    }
+}
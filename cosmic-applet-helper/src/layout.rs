@@ -0,0 +1,114 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Popup sizes fitting the output the panel is on, from a small laptop screen
+//! to a 4K monitor.
+//!
+//! Popups are one of a few widths, and tall ones are kept within the output.
+//! Wide popups place their sections side by side when the output has room for
+//! it, and in one column on small outputs.
+
+use std::sync::Mutex;
+
+use cosmic::{
+    iced::{Alignment, Length, Limits, Size},
+    iced_widget::{Column, Row},
+    Element,
+};
+
+/// The logical size of the panel's output, once it's known.
+static OUTPUT_SIZE: Mutex<Option<Size>> = Mutex::new(None);
+
+/// Outputs smaller than this, in logical pixels, are compact. A 1366x768
+/// laptop screen is.
+const COMPACT_SIZE: Size = Size::new(1280.0, 800.0);
+/// Room left around popups for the panel and the gaps next to it.
+const OUTPUT_MARGIN: f32 = 96.0;
+/// The height popups are limited to while the output's size isn't known.
+const DEFAULT_MAX_HEIGHT: f32 = 1080.0;
+/// Between the columns of a wide popup.
+const COLUMN_SPACING: u16 = 8;
+
+pub(crate) fn set_output_size(size: Option<Size>) {
+    *OUTPUT_SIZE.lock().unwrap() = size;
+}
+
+/// The logical size of the panel's output, if it's known yet.
+pub fn output_size() -> Option<Size> {
+    *OUTPUT_SIZE.lock().unwrap()
+}
+
+/// How much room the output leaves popups.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// Small outputs, where popups are kept to one column.
+    Compact,
+    Regular,
+}
+
+/// The breakpoint of the panel's output, [`Breakpoint::Regular`] until its
+/// size is known.
+pub fn breakpoint() -> Breakpoint {
+    match output_size() {
+        Some(size) if size.width < COMPACT_SIZE.width || size.height < COMPACT_SIZE.height => {
+            Breakpoint::Compact
+        }
+        _ => Breakpoint::Regular,
+    }
+}
+
+/// The width class of a popup.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PopupWidth {
+    /// For a few toggles or a short list.
+    Narrow,
+    #[default]
+    Standard,
+    /// Room for two columns, on outputs that aren't compact.
+    Wide,
+}
+
+impl PopupWidth {
+    /// The smallest and largest width of the popup, in logical pixels.
+    pub fn range(self) -> (f32, f32) {
+        match (self, breakpoint()) {
+            (Self::Narrow, _) => (240.0, 300.0),
+            (Self::Standard, _) | (Self::Wide, Breakpoint::Compact) => (300.0, 372.0),
+            (Self::Wide, Breakpoint::Regular) => (372.0, 744.0),
+        }
+    }
+
+    /// Size limits of the popup, which is also kept from growing taller than
+    /// the output.
+    pub fn limits(self) -> Limits {
+        let (min_width, max_width) = self.range();
+        let max_height = output_size().map_or(DEFAULT_MAX_HEIGHT, |size| {
+            (size.height - OUTPUT_MARGIN).max(1.0)
+        });
+        Limits::NONE
+            .min_width(min_width)
+            .max_width(max_width)
+            .min_height(1.0)
+            .max_height(max_height)
+    }
+}
+
+/// Sections of a wide popup, placed side by side in two columns, or one under
+/// the other on compact outputs. The first half of the sections goes in the
+/// first column, in reading order.
+pub fn columns<'a, M: 'a>(sections: Vec<Element<'a, M>>) -> Element<'a, M> {
+    if breakpoint() == Breakpoint::Compact || sections.len() < 2 {
+        return Column::with_children(sections).into();
+    }
+
+    let mut first = sections;
+    let second = first.split_off((first.len() + 1) / 2);
+    let columns = crate::reading_order(vec![
+        Column::with_children(first).width(Length::Fill).into(),
+        Column::with_children(second).width(Length::Fill).into(),
+    ]);
+    Row::with_children(columns)
+        .spacing(COLUMN_SPACING)
+        .align_items(Alignment::Start)
+        .into()
+}
@@ -0,0 +1,634 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! The parts every applet shares, so an applet only describes its panel button
+//! and popup.
+//!
+//! An applet implements [`CosmicApplet`] and starts with [`run_applet`]. The
+//! [`Applet`] wrapper owns the [`Core`] and the popup: it opens and closes the
+//! popup on [`AppletMessage::TogglePopup`], or the [`PopupRequest`] of one of
+//! the applet's messages, places it with [`popup_settings`], applies the
+//! applet theme and tells the applet through [`CosmicApplet::on_panel_event`].
+//!
+//! The popup is also closed when it loses keyboard focus or the user clicks
+//! outside of it, after asking [`CosmicApplet::on_dismiss`].
+//!
+//! Popups and the helper's own widgets are mirrored for languages written
+//! right to left, and applets mirror their rows with [`reading_order`] and
+//! [`start_alignment`].
+//!
+//! Panel buttons showing only an icon are named for screen readers with
+//! [`icon_button`].
+//!
+//! Keyboard shortcuts working outside of the applet, like opening its popup,
+//! are registered with [`global_shortcuts`], and changes they make to the
+//! volume or a brightness are shown with an [`Osd`].
+//!
+//! Images are decoded at the size they're shown at, and kept within a memory
+//! budget, with [`ImageCache`].
+//!
+//! If the applet panics, its panel button is replaced by one restarting it,
+//! rather than leaving a gap in the panel.
+//!
+//! Popups are one of the [`PopupWidth`] classes, sized for the panel's output,
+//! and wide ones lay their sections out in [`columns`] where there's room.
+//!
+//! The stages of the startup are timed, for the [`startup::TimingLayer`] to
+//! log, and state only the popup needs is built when it's first opened with
+//! [`CosmicApplet::init_popup`].
+//!
+//! Applets log at the levels of a config entry they share, set up with
+//! [`logging::init`], and the work on each surface is in its
+//! [`logging::surface_span`].
+//!
+//! Panel buttons reacting to more than a click, like scrolling or a right
+//! click, are wrapped in a [`MouseArea`].
+//!
+//! Subscriptions only updating what's on screen, like timers and polling, are
+//! in [`CosmicApplet::panel_subscription`] and
+//! [`CosmicApplet::popup_subscription`], which stop while the screens are off,
+//! as told by [`screen_off_subscription`].
+//!
+//! Applets needing to know roughly where the device is ask GeoClue with
+//! [`geoclue::location`], and whether the sun is down there with
+//! [`sun::is_night`].
+//!
+//! Byte counts and rates are shown with [`format_bytes`], [`format_rate`] and
+//! [`format_size`].
+
+mod a11y;
+mod crash;
+mod direction;
+pub mod geoclue;
+mod icon;
+mod images;
+mod layout;
+mod localize;
+pub mod logging;
+mod mouse_area;
+mod osd;
+mod position;
+mod screen;
+mod shortcuts;
+pub mod startup;
+pub mod sun;
+mod units;
+
+pub use a11y::{icon_button, icon_button_from_handle};
+pub use crash::install_panic_hook;
+pub use direction::{end_alignment, is_rtl, reading_order, start_alignment};
+pub use icon::icon_handle;
+pub use images::{decode_file, fit, ImageCache, Thumbnail};
+pub use layout::{breakpoint, columns, output_size, Breakpoint, PopupWidth};
+pub use mouse_area::MouseArea;
+pub use osd::{Osd, OsdMessage};
+pub use position::popup_settings;
+pub use screen::screen_off_subscription;
+pub use shortcuts::{global_shortcuts, Shortcut, ShortcutEvent};
+use startup::Stage;
+pub use units::{format_bytes, format_rate, format_size};
+
+use std::{
+    cell::Cell,
+    fmt::Debug,
+    time::{Duration, Instant},
+};
+
+use cosmic::{
+    app::Core,
+    applet::cosmic_panel_config::PanelAnchor,
+    cctk::sctk::output::OutputInfo,
+    iced::{
+        event::{
+            listen_with,
+            wayland::{self, OutputEvent, PopupEvent},
+            PlatformSpecific,
+        },
+        wayland::popup::{destroy_popup, get_popup},
+        window::{self, Id},
+        Alignment, Command, Event, Length, Limits, Rectangle, Size, Subscription,
+    },
+    iced_style::application,
+    iced_widget::{container, row, Row},
+    widget::{self, button, text},
+    Element, Theme,
+};
+
+/// Messages handled by [`Applet`], wrapping the applet's own.
+#[derive(Debug, Clone)]
+pub enum AppletMessage<M> {
+    TogglePopup,
+    ClosePopup,
+    PopupClosed(Id),
+    Dismiss(Id, Dismissal),
+    /// Starts the applet again after it crashed.
+    Restart,
+    /// An output was added or changed, possibly the panel's.
+    Output(Box<OutputInfo>),
+    /// The screens were turned off, or back on.
+    ScreenOff(bool),
+    App(M),
+}
+
+impl<M> From<M> for AppletMessage<M> {
+    fn from(message: M) -> Self {
+        Self::App(message)
+    }
+}
+
+/// Why a popup is being closed without the applet asking for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dismissal {
+    FocusLost,
+    /// The compositor has already closed the popup, so this can't be refused.
+    ClickedOutside,
+}
+
+/// Changes to the popup asked for by an applet's own message, for popups
+/// opened by something other than the panel button, like a shortcut.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PopupRequest {
+    Toggle,
+    /// Opens the popup if it's closed.
+    Open,
+    Close,
+    /// Opens the popup again, placed anew, for applets pointing it at one of
+    /// several buttons. The applet isn't told the popup closed in between.
+    Reopen,
+}
+
+/// Changes to the applet's surfaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelEvent {
+    PopupOpened,
+    PopupClosed,
+}
+
+pub trait CosmicApplet: Sized + 'static {
+    type Message: Clone + Debug + Send + 'static;
+
+    const APP_ID: &'static str;
+
+    /// Whether the panel surface is sized to the panel button, rather than
+    /// to the panel's suggested size.
+    const AUTOSIZE: bool = true;
+
+    fn init(core: &Core) -> (Self, Command<Self::Message>);
+
+    /// Called before the popup first opens, for state and connections only it
+    /// needs, so they don't slow down the applet's startup.
+    fn init_popup(&mut self, _core: &Core) -> Command<Self::Message> {
+        Command::none()
+    }
+
+    fn update(&mut self, core: &Core, message: Self::Message) -> Command<Self::Message>;
+
+    /// The change to the popup `message` asks for, made once the applet
+    /// handled it. It's asked before, so it can depend on what the popup
+    /// shows.
+    fn popup_request(&self, _message: &Self::Message) -> Option<PopupRequest> {
+        None
+    }
+
+    /// The button shown in the panel.
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Self::Message>>;
+
+    /// The popup's content, placed in the applet's popup container.
+    fn popup_view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Self::Message>>;
+
+    /// The content of the applet's other surfaces, like the icon of a drag.
+    fn surface_view<'a>(
+        &'a self,
+        _core: &'a Core,
+        _id: Id,
+    ) -> Element<'a, AppletMessage<Self::Message>> {
+        row![].into()
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, _event: PanelEvent) -> Command<Self::Message> {
+        Command::none()
+    }
+
+    /// Called when the compositor closed one of the applet's other surfaces.
+    fn on_surface_closed(&mut self, _core: &Core, _id: Id) {}
+
+    /// Whether the popup should close, for applets that need to keep it open
+    /// while it isn't focused, such as during a drag.
+    fn on_dismiss(&mut self, _core: &Core, _dismissal: Dismissal) -> bool {
+        true
+    }
+
+    fn subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        Subscription::none()
+    }
+
+    /// Subscriptions only updating the panel button, such as polling. They're
+    /// stopped while the screens are off.
+    fn panel_subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        Subscription::none()
+    }
+
+    /// Subscriptions only needed while the popup is shown, such as graphs,
+    /// level meters and countdowns. They're stopped while it's closed or the
+    /// screens are off, so they don't keep waking the applet up.
+    fn popup_subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        Subscription::none()
+    }
+
+    /// The part of the panel surface the popup points at, for applets showing
+    /// several buttons. The whole surface by default.
+    fn popup_anchor(&self, _core: &Core) -> Option<Rectangle<i32>> {
+        None
+    }
+
+    fn popup_width(&self) -> PopupWidth {
+        PopupWidth::Standard
+    }
+
+    fn popup_limits(&self, _core: &Core) -> Limits {
+        self.popup_width().limits()
+    }
+
+    /// Whether the popup takes the keyboard and pointer, which popups shown
+    /// like a tooltip don't.
+    fn popup_grab(&self) -> bool {
+        true
+    }
+}
+
+/// Clicking the panel button takes focus from the popup before the click is
+/// handled, so it would reopen the popup it just closed.
+const REOPEN_DELAY: Duration = Duration::from_millis(250);
+
+pub struct Applet<A> {
+    core: Core,
+    popup: Option<Id>,
+    /// When the popup was last closed for losing focus.
+    dismissed: Option<Instant>,
+    /// Whether the applet panicked. Set from `view` too, hence the `Cell`.
+    crashed: Cell<bool>,
+    /// Whether [`CosmicApplet::init_popup`] was called.
+    popup_ready: bool,
+    /// Whether the panel button was built, ending the startup.
+    started: Cell<bool>,
+    /// Whether the screens are off, stopping the subscriptions only updating
+    /// what's shown.
+    screen_off: bool,
+    applet: A,
+}
+
+impl<A: CosmicApplet> Applet<A> {
+    fn map(
+        command: Command<A::Message>,
+    ) -> Command<cosmic::app::Message<AppletMessage<A::Message>>> {
+        command.map(|message| cosmic::app::message::app(AppletMessage::App(message)))
+    }
+
+    fn panel_event(
+        &mut self,
+        event: PanelEvent,
+    ) -> Command<cosmic::app::Message<AppletMessage<A::Message>>> {
+        if self.crashed.get() {
+            return Command::none();
+        }
+        crash::guard(&self.crashed, || {
+            Self::map(self.applet.on_panel_event(&self.core, event))
+        })
+        .unwrap_or_else(Command::none)
+    }
+
+    fn open_popup(&mut self) -> Command<cosmic::app::Message<AppletMessage<A::Message>>> {
+        let init_popup = if self.popup_ready {
+            Command::none()
+        } else {
+            self.popup_ready = true;
+            let _span = tracing::info_span!(target: startup::TARGET, "popup_init").entered();
+            crash::guard(&self.crashed, || {
+                Self::map(self.applet.init_popup(&self.core))
+            })
+            .unwrap_or_else(Command::none)
+        };
+        if self.crashed.get() {
+            return Command::none();
+        }
+        Command::batch([
+            init_popup,
+            self.create_popup(),
+            self.panel_event(PanelEvent::PopupOpened),
+        ])
+    }
+
+    fn create_popup(&mut self) -> Command<cosmic::app::Message<AppletMessage<A::Message>>> {
+        let new_id = Id::unique();
+        self.popup.replace(new_id);
+        let _span = logging::surface_span(new_id).entered();
+        let mut popup_settings = popup_settings(
+            &self.core,
+            A::APP_ID,
+            Id::MAIN,
+            new_id,
+            self.applet.popup_anchor(&self.core),
+        );
+        popup_settings.positioner.size_limits = self.applet.popup_limits(&self.core);
+        popup_settings.grab = self.applet.popup_grab();
+        get_popup(popup_settings)
+    }
+
+    /// Whether the popup was just closed for losing focus, to the click on
+    /// the panel that took it.
+    fn just_dismissed(&mut self) -> bool {
+        self.dismissed
+            .take()
+            .map_or(false, |dismissed| dismissed.elapsed() < REOPEN_DELAY)
+    }
+
+    fn toggle_popup(&mut self) -> Command<cosmic::app::Message<AppletMessage<A::Message>>> {
+        if self.popup.is_some() {
+            self.close_popup()
+        } else if self.crashed.get() || self.just_dismissed() {
+            Command::none()
+        } else {
+            self.open_popup()
+        }
+    }
+
+    fn request_popup(
+        &mut self,
+        request: PopupRequest,
+    ) -> Command<cosmic::app::Message<AppletMessage<A::Message>>> {
+        match request {
+            // Applets with several buttons open the popup from their own
+            // messages, and have already readied what it would show
+            PopupRequest::Toggle | PopupRequest::Open | PopupRequest::Reopen
+                if self.popup.is_none() && self.just_dismissed() =>
+            {
+                self.panel_event(PanelEvent::PopupClosed)
+            }
+            PopupRequest::Toggle => self.toggle_popup(),
+            PopupRequest::Open if self.popup.is_none() => self.open_popup(),
+            PopupRequest::Open => Command::none(),
+            PopupRequest::Close => self.close_popup(),
+            PopupRequest::Reopen => match self.popup.take() {
+                Some(id) => Command::batch([destroy_popup(id), self.create_popup()]),
+                None => self.open_popup(),
+            },
+        }
+    }
+
+    fn close_popup(&mut self) -> Command<cosmic::app::Message<AppletMessage<A::Message>>> {
+        let Some(id) = self.popup.take() else {
+            return Command::none();
+        };
+        Command::batch([destroy_popup(id), self.panel_event(PanelEvent::PopupClosed)])
+    }
+
+    fn crashed_view(&self) -> Element<AppletMessage<A::Message>> {
+        let icon = widget::icon::from_name("dialog-error-symbolic")
+            .size(self.core.applet.suggested_size(true).0)
+            .symbolic(true);
+        let content: Element<_> = match self.core.applet.anchor {
+            PanelAnchor::Left | PanelAnchor::Right => icon.into(),
+            PanelAnchor::Top | PanelAnchor::Bottom => Row::with_children(reading_order(vec![
+                icon.into(),
+                text(fl!("applet-crashed")).into(),
+            ]))
+            .spacing(4)
+            .align_items(Alignment::Center)
+            .into(),
+        };
+        button(content)
+            .padding(self.core.applet.suggested_padding(true))
+            .on_press(AppletMessage::Restart)
+            .style(cosmic::theme::Button::AppletIcon)
+            .into()
+    }
+}
+
+impl<A: CosmicApplet> cosmic::Application for Applet<A> {
+    type Executor = cosmic::SingleThreadExecutor;
+    type Flags = ();
+    type Message = AppletMessage<A::Message>;
+    const APP_ID: &'static str = A::APP_ID;
+
+    fn core(&self) -> &Core {
+        &self.core
+    }
+
+    fn core_mut(&mut self) -> &mut Core {
+        &mut self.core
+    }
+
+    fn init(
+        core: Core,
+        _flags: Self::Flags,
+    ) -> (Self, Command<cosmic::app::Message<Self::Message>>) {
+        startup::stage(Stage::AppletInit);
+        let (applet, command) = A::init(&core);
+        startup::stage(Stage::FirstFrame);
+        let window = Self {
+            core,
+            popup: None,
+            dismissed: None,
+            crashed: Cell::new(false),
+            popup_ready: false,
+            started: Cell::new(false),
+            screen_off: false,
+            applet,
+        };
+        (window, Self::map(command))
+    }
+
+    fn on_close_requested(&self, id: Id) -> Option<Self::Message> {
+        Some(AppletMessage::PopupClosed(id))
+    }
+
+    fn subscription(&self) -> Subscription<Self::Message> {
+        if self.crashed.get() {
+            return Subscription::none();
+        }
+        let subscription = crash::guard(&self.crashed, || {
+            self.applet.subscription(&self.core).map(AppletMessage::App)
+        })
+        .unwrap_or_else(Subscription::none);
+        let subscription = Subscription::batch([
+            subscription,
+            listen_with(|event, _status| match event {
+                Event::PlatformSpecific(PlatformSpecific::Wayland(wayland::Event::Output(
+                    OutputEvent::Created(Some(info)) | OutputEvent::InfoUpdate(info),
+                    _,
+                ))) => Some(AppletMessage::Output(Box::new(info))),
+                _ => None,
+            }),
+            screen_off_subscription().map(AppletMessage::ScreenOff),
+        ]);
+        let subscription = if self.screen_off {
+            subscription
+        } else {
+            let panel_subscription = crash::guard(&self.crashed, || {
+                self.applet
+                    .panel_subscription(&self.core)
+                    .map(AppletMessage::App)
+            })
+            .unwrap_or_else(Subscription::none);
+            Subscription::batch([subscription, panel_subscription])
+        };
+        if self.popup.is_none() {
+            return subscription;
+        }
+        let popup_subscription = if self.screen_off {
+            Subscription::none()
+        } else {
+            crash::guard(&self.crashed, || {
+                self.applet
+                    .popup_subscription(&self.core)
+                    .map(AppletMessage::App)
+            })
+            .unwrap_or_else(Subscription::none)
+        };
+        Subscription::batch([
+            subscription,
+            popup_subscription,
+            listen_with(|event, _status| match event {
+                Event::Window(id, window::Event::Unfocused) => {
+                    Some(AppletMessage::Dismiss(id, Dismissal::FocusLost))
+                }
+                Event::PlatformSpecific(PlatformSpecific::Wayland(wayland::Event::Popup(
+                    PopupEvent::Done,
+                    _,
+                    id,
+                ))) => Some(AppletMessage::Dismiss(id, Dismissal::ClickedOutside)),
+                _ => None,
+            }),
+        ])
+    }
+
+    fn update(&mut self, message: Self::Message) -> Command<cosmic::app::Message<Self::Message>> {
+        match message {
+            AppletMessage::TogglePopup => self.toggle_popup(),
+            AppletMessage::ClosePopup => self.close_popup(),
+            AppletMessage::PopupClosed(id) => {
+                if self.popup.as_ref() != Some(&id) {
+                    if !self.crashed.get() {
+                        crash::guard(&self.crashed, || {
+                            self.applet.on_surface_closed(&self.core, id)
+                        });
+                    }
+                    return Command::none();
+                }
+                self.popup = None;
+                self.panel_event(PanelEvent::PopupClosed)
+            }
+            AppletMessage::Dismiss(id, dismissal) => {
+                if self.popup.as_ref() != Some(&id) {
+                    return Command::none();
+                }
+                let dismiss = self.crashed.get()
+                    || crash::guard(&self.crashed, || {
+                        self.applet.on_dismiss(&self.core, dismissal)
+                    })
+                    .unwrap_or(true);
+                match dismissal {
+                    Dismissal::FocusLost if dismiss => {
+                        self.dismissed = Some(Instant::now());
+                        self.close_popup()
+                    }
+                    Dismissal::FocusLost => Command::none(),
+                    Dismissal::ClickedOutside => {
+                        self.popup = None;
+                        self.panel_event(PanelEvent::PopupClosed)
+                    }
+                }
+            }
+            AppletMessage::Restart => {
+                let Some((applet, command)) = crash::guard(&self.crashed, || A::init(&self.core))
+                else {
+                    return Command::none();
+                };
+                self.applet = applet;
+                self.crashed.set(false);
+                self.popup_ready = false;
+                Self::map(command)
+            }
+            AppletMessage::Output(info) => {
+                if info.name.as_deref() == Some(self.core.applet.output_name.as_str()) {
+                    layout::set_output_size(
+                        info.logical_size
+                            .map(|(width, height)| Size::new(width as f32, height as f32)),
+                    );
+                }
+                Command::none()
+            }
+            AppletMessage::ScreenOff(screen_off) => {
+                self.screen_off = screen_off;
+                Command::none()
+            }
+            AppletMessage::App(_) if self.crashed.get() => Command::none(),
+            AppletMessage::App(message) => {
+                let Some(request) =
+                    crash::guard(&self.crashed, || self.applet.popup_request(&message))
+                else {
+                    return self.close_popup();
+                };
+                match crash::guard(&self.crashed, || self.applet.update(&self.core, message)) {
+                    Some(command) => Command::batch([
+                        Self::map(command),
+                        request.map_or_else(Command::none, |request| self.request_popup(request)),
+                    ]),
+                    None => self.close_popup(),
+                }
+            }
+        }
+    }
+
+    fn view(&self) -> Element<Self::Message> {
+        let _span = logging::surface_span(Id::MAIN).entered();
+        let view = if self.crashed.get() {
+            None
+        } else {
+            crash::guard(&self.crashed, || self.applet.view(&self.core))
+        };
+        if !self.started.replace(true) {
+            startup::finish();
+        }
+        view.unwrap_or_else(|| self.crashed_view())
+    }
+
+    fn view_window(&self, id: Id) -> Element<Self::Message> {
+        let _span = logging::surface_span(id).entered();
+        if self.popup != Some(id) {
+            return if self.crashed.get() {
+                None
+            } else {
+                crash::guard(&self.crashed, || self.applet.surface_view(&self.core, id))
+            }
+            .unwrap_or_else(|| row![].into());
+        }
+        let content = if self.crashed.get() {
+            None
+        } else {
+            crash::guard(&self.crashed, || self.applet.popup_view(&self.core))
+        };
+        let mut content = content.unwrap_or_else(|| row![].into());
+        if is_rtl() {
+            // Applets' content is laid out from the left, so what doesn't
+            // fill the popup is moved to its right
+            content = container(content)
+                .width(Length::Fill)
+                .align_x(start_alignment())
+                .into();
+        }
+        self.core.applet.popup_container(content).into()
+    }
+
+    fn style(&self) -> Option<<Theme as application::StyleSheet>::Style> {
+        Some(cosmic::applet::style())
+    }
+}
+
+/// Runs the applet.
+pub fn run_applet<A: CosmicApplet>() -> cosmic::iced::Result {
+    startup::stage(Stage::Toolkit);
+    localize::localize();
+
+    cosmic::applet::run::<Applet<A>>(A::AUTOSIZE, ())
+}
@@ -0,0 +1,135 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Logging, configured for all applets from one cosmic-config entry.
+//!
+//! The entry has the level every applet logs at, levels for some applets
+//! overriding it, and whether events go to the systemd journal too. `RUST_LOG`
+//! still overrides the entry, for debugging from a terminal. The entry is read
+//! when the applet starts, so changes apply once it's restarted.
+//!
+//! Events are in the span of [`applet_span`], naming the applet, and those of
+//! a surface in its [`surface_span`]. In the journal, the applet is also the
+//! syslog identifier of every event, including those of other threads.
+
+use std::{collections::HashMap, sync::OnceLock};
+
+use cosmic::{
+    cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry},
+    iced::window::Id,
+};
+use serde::{Deserialize, Serialize};
+use tracing::Span;
+use tracing_subscriber::{prelude::*, EnvFilter};
+
+use crate::startup::{self, TimingLayer};
+
+pub const CONFIG_ID: &str = "com.system76.CosmicApplets.Logging";
+
+/// The applet logging, once [`init`] was called.
+static APPLET: OnceLock<String> = OnceLock::new();
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, CosmicConfigEntry)]
+#[version = 1]
+pub struct LoggingConfig {
+    /// The level of the applets without one of their own, or any filter
+    /// `RUST_LOG` accepts.
+    pub level: String,
+    /// Levels by the applets' binary names, like `cosmic-applet-network`.
+    pub applets: HashMap<String, String>,
+    /// Whether events also go to the systemd journal.
+    pub journald: bool,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "error".to_string(),
+            applets: HashMap::new(),
+            journald: false,
+        }
+    }
+}
+
+impl LoggingConfig {
+    pub fn load() -> Self {
+        match cosmic_config::Config::new(CONFIG_ID, Self::VERSION) {
+            Ok(config) => Self::get_entry(&config).unwrap_or_else(|(_errs, config)| config),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// The filter of `applet`'s level.
+    pub fn level(&self, applet: &str) -> &str {
+        self.applets.get(applet).unwrap_or(&self.level)
+    }
+}
+
+/// Sets up logging for `applet`, named like its binary, and forwards the
+/// events of the `log` crate.
+pub fn init(applet: &str) {
+    _ = APPLET.set(applet.to_string());
+    let config = LoggingConfig::load();
+
+    let mut invalid = None;
+    let level = config.level(applet);
+    if let Err(err) = EnvFilter::try_new(level) {
+        invalid = Some((level.to_string(), err));
+    }
+
+    let journald = if config.journald {
+        match tracing_journald::layer() {
+            Ok(layer) => Some(
+                layer
+                    .with_syslog_identifier(applet.to_string())
+                    .with_filter(filter(&config, applet)),
+            ),
+            Err(err) => {
+                eprintln!("Failed to connect to the journal: {err}");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // The filters are per layer, so the spans of the startup reach the
+    // `TimingLayer` whatever the level is.
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(filter(&config, applet)))
+        .with(journald)
+        .with(TimingLayer)
+        .init();
+    let _ = tracing_log::LogTracer::init();
+
+    if let Some((level, err)) = invalid {
+        tracing::warn!(%err, "Invalid log level `{level}` for {applet}");
+    }
+}
+
+/// The filter of `RUST_LOG`, or else of `applet`'s level in `config`. The
+/// latter also shows the startup timings.
+fn filter(config: &LoggingConfig, applet: &str) -> EnvFilter {
+    EnvFilter::try_from_default_env().unwrap_or_else(|_| {
+        EnvFilter::try_new(config.level(applet))
+            .unwrap_or_else(|_| EnvFilter::new(LoggingConfig::default().level))
+            .add_directive(
+                format!("{}=info", startup::TARGET)
+                    .parse()
+                    .expect("valid directive"),
+            )
+    })
+}
+
+/// The applet's span, for the main thread to enter for as long as it runs.
+///
+/// It's at the error level, so it's enabled whatever the level is.
+pub fn applet_span() -> Span {
+    let applet = APPLET.get().map_or("", String::as_str);
+    tracing::error_span!("applet", name = applet)
+}
+
+/// The span of work on the applet's panel button or popup with `id`.
+pub fn surface_span(id: Id) -> Span {
+    tracing::error_span!("surface", id = ?id)
+}
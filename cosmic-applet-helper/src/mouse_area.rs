@@ -11,7 +11,7 @@ use cosmic::iced_core::{
 
 /// Emit messages on mouse events.
 #[allow(missing_debug_implementations)]
-pub struct MouseArea<'a, Message, Theme = crate::Theme, Renderer = crate::Renderer> {
+pub struct MouseArea<'a, Message, Theme = cosmic::Theme, Renderer = cosmic::Renderer> {
     content: Element<'a, Message, Theme, Renderer>,
     on_drag: Option<Message>,
     on_press: Option<Message>,
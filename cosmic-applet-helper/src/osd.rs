@@ -0,0 +1,333 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! The overlay shown at the bottom of the screen for a moment when the volume
+//! or a brightness changes, so every applet gives the same feedback.
+//!
+//! Only one applet shows the overlay at a time: whichever owns [`BUS_NAME`]
+//! while it's shown. Others ask the owner over D-Bus to show their change
+//! instead of opening an overlay of their own, which is why applets with an
+//! overlay run its [`Osd::subscription`]. The overlay keeps clear of panels
+//! and docks at the bottom of the screen, from their config.
+
+use std::{any::TypeId, sync::OnceLock, time::Duration};
+
+use cosmic::{
+    applet::cosmic_panel_config::{CosmicPanelConfig, PanelAnchor},
+    cosmic_config::{Config, ConfigGet, CosmicConfigEntry},
+    iced::{
+        self,
+        alignment::{Horizontal, Vertical},
+        subscription,
+        wayland::actions::layer_surface::{IcedMargin, SctkLayerSurfaceSettings},
+        widget::{container, progress_bar, row, text},
+        window, Alignment, Background, Border, Command, Length, Limits,
+    },
+    iced_futures::futures::{self, SinkExt},
+    iced_sctk::commands::layer_surface::{
+        destroy_layer_surface, get_layer_surface, Anchor, KeyboardInteractivity, Layer,
+    },
+    widget::icon,
+    Element, Theme,
+};
+use zbus::{
+    fdo::{DBusProxy, RequestNameFlags, RequestNameReply},
+    names::WellKnownName,
+    proxy, Connection,
+};
+
+use crate::position::PANEL_CONFIG;
+
+/// Owned by the applet showing the overlay.
+const BUS_NAME: &str = "com.system76.CosmicApplets.Osd";
+const PATH: &str = "/com/system76/CosmicApplets/Osd";
+
+/// How long the overlay stays after the last change.
+const TIMEOUT: Duration = Duration::from_millis(1500);
+const WIDTH: u32 = 280;
+const HEIGHT: u32 = 64;
+/// Distance from the bottom edge, or the panels along it.
+const GAP: i32 = 32;
+
+/// The connection serving requests to show the overlay, once its
+/// subscription started.
+static CONNECTION: OnceLock<Connection> = OnceLock::new();
+
+/// Passed back to [`Osd::update`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum OsdMessage {
+    /// Whether this applet owns the overlay now, rather than another one
+    /// showing the change.
+    Claimed(bool),
+    /// Another applet's change, shown while this one owns the overlay.
+    Show(String, f64),
+    Timeout(OsdTimeout),
+    /// Another applet may show the overlay again.
+    Released,
+}
+
+/// The change an overlay timed out after.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OsdTimeout(u32);
+
+/// The overlay's surface and what it shows.
+#[derive(Debug, Clone, Default)]
+pub struct Osd {
+    surface: Option<window::Id>,
+    /// Counts changes, so only the last one hides the overlay.
+    generation: u32,
+    icon_name: String,
+    /// In percent.
+    value: f64,
+}
+
+struct Server(futures::channel::mpsc::Sender<OsdMessage>);
+
+#[zbus::interface(name = "com.system76.CosmicApplets.Osd")]
+impl Server {
+    async fn show(&self, icon_name: String, value: f64) {
+        _ = self
+            .0
+            .clone()
+            .send(OsdMessage::Show(icon_name, value))
+            .await;
+    }
+}
+
+#[proxy(
+    interface = "com.system76.CosmicApplets.Osd",
+    default_service = "com.system76.CosmicApplets.Osd",
+    default_path = "/com/system76/CosmicApplets/Osd"
+)]
+trait Owner {
+    fn show(&self, icon_name: &str, value: f64) -> zbus::Result<()>;
+}
+
+impl Osd {
+    /// Requests from other applets to show their changes while this one owns
+    /// the overlay. Without it, every applet opens an overlay of its own.
+    pub fn subscription() -> iced::Subscription<OsdMessage> {
+        struct Serve;
+
+        subscription::channel(TypeId::of::<Serve>(), 10, |output| async move {
+            if let Err(err) = serve(output).await {
+                tracing::warn!(%err, "Failed to serve the overlay");
+            }
+            futures::future::pending().await
+        })
+    }
+
+    /// Shows `value`, in percent, next to `icon_name`, opening the overlay if
+    /// no applet shows it already.
+    pub fn show<M: Send + 'static>(
+        &mut self,
+        icon_name: impl Into<String>,
+        value: f64,
+        map: fn(OsdMessage) -> M,
+    ) -> Command<M> {
+        self.icon_name = icon_name.into();
+        self.value = value.clamp(0.0, 100.0);
+        self.generation = self.generation.wrapping_add(1);
+        if self.surface.is_some() {
+            return self.hide_later(map);
+        }
+        let (icon_name, value) = (self.icon_name.clone(), self.value);
+        Command::perform(claim(icon_name, value), move |owned| {
+            map(OsdMessage::Claimed(owned))
+        })
+    }
+
+    pub fn update<M: Send + 'static>(
+        &mut self,
+        message: OsdMessage,
+        map: fn(OsdMessage) -> M,
+    ) -> Command<M> {
+        match message {
+            OsdMessage::Claimed(true) if self.surface.is_none() => {
+                let id = window::Id::unique();
+                self.surface = Some(id);
+                Command::batch([get_layer_surface(settings(id)), self.hide_later(map)])
+            }
+            OsdMessage::Claimed(_) => Command::none(),
+            OsdMessage::Show(icon_name, value) => self.show(icon_name, value, map),
+            OsdMessage::Timeout(timeout) => self.hide(timeout, map),
+            OsdMessage::Released => Command::none(),
+        }
+    }
+
+    fn hide_later<M: Send + 'static>(&self, map: fn(OsdMessage) -> M) -> Command<M> {
+        let timeout = OsdTimeout(self.generation);
+        Command::perform(tokio::time::sleep(TIMEOUT), move |_| {
+            map(OsdMessage::Timeout(timeout))
+        })
+    }
+
+    /// Closes the overlay, unless it changed again since, and lets other
+    /// applets show it.
+    fn hide<M: Send + 'static>(
+        &mut self,
+        timeout: OsdTimeout,
+        map: fn(OsdMessage) -> M,
+    ) -> Command<M> {
+        if timeout.0 != self.generation {
+            return Command::none();
+        }
+        match self.surface.take() {
+            Some(id) => Command::batch([
+                destroy_layer_surface(id),
+                Command::perform(release(), move |()| map(OsdMessage::Released)),
+            ]),
+            None => Command::none(),
+        }
+    }
+
+    /// Whether `id` is the overlay's surface, to pick its view.
+    pub fn is_surface(&self, id: window::Id) -> bool {
+        self.surface == Some(id)
+    }
+
+    /// Forgets the surface once the compositor closed it.
+    pub fn closed(&mut self, id: window::Id) {
+        if self.is_surface(id) {
+            self.surface = None;
+        }
+    }
+
+    pub fn view<'a, M: 'a>(&'a self) -> Element<'a, M> {
+        container(
+            row![
+                icon::from_name(self.icon_name.as_str())
+                    .size(32)
+                    .symbolic(true),
+                progress_bar(0.0..=100.0, self.value as f32).height(Length::Fixed(6.0)),
+                text(format!("{}%", self.value.round()))
+                    .size(16)
+                    .width(Length::Fixed(40.0))
+                    .horizontal_alignment(Horizontal::Right),
+            ]
+            .spacing(12)
+            .align_items(Alignment::Center),
+        )
+        .padding([12, 16])
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .align_y(Vertical::Center)
+        .style(<Theme as container::StyleSheet>::Style::Custom(Box::new(
+            |theme| {
+                let cosmic = theme.cosmic();
+                container::Appearance {
+                    text_color: Some(cosmic.background.on.into()),
+                    background: Some(Background::Color(cosmic.background.base.into())),
+                    border: Border {
+                        radius: cosmic.corner_radii.radius_l.into(),
+                        width: 1.0,
+                        color: cosmic.bg_divider().into(),
+                    },
+                    ..Default::default()
+                }
+            },
+        )))
+        .into()
+    }
+}
+
+async fn serve(output: futures::channel::mpsc::Sender<OsdMessage>) -> zbus::Result<()> {
+    let conn = Connection::session().await?;
+    conn.object_server().at(PATH, Server(output)).await?;
+    _ = CONNECTION.set(conn);
+    Ok(())
+}
+
+/// Takes the overlay over, or has its owner show the change. Without a bus,
+/// the overlay is this applet's own.
+async fn claim(icon_name: String, value: f64) -> bool {
+    let Some(conn) = CONNECTION.get() else {
+        return true;
+    };
+    let reply = match DBusProxy::new(conn).await {
+        Ok(dbus) => {
+            dbus.request_name(
+                WellKnownName::from_static_str_unchecked(BUS_NAME),
+                RequestNameFlags::DoNotQueue.into(),
+            )
+            .await
+        }
+        Err(err) => Err(err.into()),
+    };
+    match reply {
+        Ok(RequestNameReply::PrimaryOwner | RequestNameReply::AlreadyOwner) => true,
+        Ok(_) => {
+            let shown = match OwnerProxy::new(conn).await {
+                Ok(owner) => owner.show(&icon_name, value).await,
+                Err(err) => Err(err),
+            };
+            // The owner may have just closed it
+            shown.is_err()
+        }
+        Err(err) => {
+            tracing::debug!(%err, "Failed to claim the overlay");
+            true
+        }
+    }
+}
+
+async fn release() {
+    let Some(conn) = CONNECTION.get() else {
+        return;
+    };
+    if let Ok(dbus) = DBusProxy::new(conn).await {
+        _ = dbus
+            .release_name(WellKnownName::from_static_str_unchecked(BUS_NAME))
+            .await;
+    }
+}
+
+/// How far from the bottom edge the overlay is, past panels and docks there
+/// that don't reserve their space, which the compositor keeps it clear of
+/// already.
+fn bottom_margin() -> i32 {
+    let entries = Config::new(PANEL_CONFIG, 1)
+        .and_then(|config| config.get::<Vec<String>>("entries"))
+        .unwrap_or_default();
+    let panels = entries.iter().filter_map(|entry| {
+        let config = Config::new(
+            &format!("{PANEL_CONFIG}.{entry}"),
+            CosmicPanelConfig::VERSION,
+        );
+        CosmicPanelConfig::get_entry(&config.ok()?).ok()
+    });
+    let covered = panels
+        .filter(|panel| panel.anchor == PanelAnchor::Bottom)
+        .filter(|panel| !panel.exclusive_zone || panel.autohide.is_some())
+        .map(|panel| {
+            let applet = panel.size.get_applet_icon_size(true) as i32
+                + 2 * i32::from(panel.size.get_applet_padding(true));
+            let gap = if panel.anchor_gap {
+                i32::from(panel.margin)
+            } else {
+                0
+            };
+            applet + 2 * panel.padding as i32 + gap
+        })
+        .max()
+        .unwrap_or(0);
+    covered + GAP
+}
+
+fn settings(id: window::Id) -> SctkLayerSurfaceSettings {
+    SctkLayerSurfaceSettings {
+        id,
+        layer: Layer::Overlay,
+        keyboard_interactivity: KeyboardInteractivity::None,
+        anchor: Anchor::BOTTOM,
+        namespace: "osd".into(),
+        margin: IcedMargin {
+            bottom: bottom_margin(),
+            ..Default::default()
+        },
+        exclusive_zone: 0,
+        size: Some((Some(WIDTH), Some(HEIGHT))),
+        size_limits: Limits::NONE.min_width(1.0).min_height(1.0),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Where popups open, so applets at the ends of a panel or dock don't put
+//! them offscreen.
+
+use cosmic::{
+    app::Core,
+    applet::cosmic_panel_config::{CosmicPanelConfig, PanelAnchor},
+    cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner::{Anchor, Gravity},
+    cosmic_config::{Config, CosmicConfigEntry},
+    iced::{wayland::actions::popup::SctkPopupSettings, window::Id, Rectangle},
+};
+
+use crate::direction::is_rtl;
+
+pub(crate) const PANEL_CONFIG: &str = "com.system76.CosmicPanel";
+
+// xdg_positioner constraint adjustments
+const SLIDE_X: u32 = 1;
+const SLIDE_Y: u32 = 2;
+const FLIP_X: u32 = 4;
+const FLIP_Y: u32 = 8;
+
+/// Which part of the panel an applet was added to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Segment {
+    Start,
+    Center,
+    End,
+}
+
+/// Reads the panel's config to find the applet, as the panel doesn't tell
+/// applets where it placed them.
+fn segment(core: &Core, app_id: &str) -> Segment {
+    let Some(config) = Config::new(
+        &format!("{}.{}", PANEL_CONFIG, core.applet.panel_type),
+        CosmicPanelConfig::VERSION,
+    )
+    .ok()
+    .and_then(|config| CosmicPanelConfig::get_entry(&config).ok()) else {
+        return Segment::Center;
+    };
+    match config.plugins_wings {
+        Some((start, _)) if start.iter().any(|id| id == app_id) => Segment::Start,
+        Some((_, end)) if end.iter().any(|id| id == app_id) => Segment::End,
+        _ => Segment::Center,
+    }
+}
+
+/// Popup settings for an applet, anchored to `anchor_rect` within the panel
+/// surface, or to the whole surface if `None`.
+///
+/// Popups of applets at the start or end of the panel grow towards its
+/// middle instead of being centered, and slide or flip to stay on the output.
+/// Horizontal panels are laid out in reading order, so for right-to-left
+/// languages their start is on the right.
+pub fn popup_settings(
+    core: &Core,
+    app_id: &str,
+    parent: Id,
+    id: Id,
+    anchor_rect: Option<Rectangle<i32>>,
+) -> SctkPopupSettings {
+    let mut settings = core.applet.get_popup_settings(parent, id, None, None, None);
+    if let Some(anchor_rect) = anchor_rect {
+        settings.positioner.anchor_rect = anchor_rect;
+    }
+    settings.positioner.constraint_adjustment |= SLIDE_X | SLIDE_Y | FLIP_X | FLIP_Y;
+
+    let segment = match (&core.applet.anchor, segment(core, app_id)) {
+        (PanelAnchor::Top | PanelAnchor::Bottom, Segment::Start) if is_rtl() => Segment::End,
+        (PanelAnchor::Top | PanelAnchor::Bottom, Segment::End) if is_rtl() => Segment::Start,
+        (_, segment) => segment,
+    };
+    let placement = match (&core.applet.anchor, segment) {
+        (_, Segment::Center) => None,
+        (PanelAnchor::Top, Segment::Start) => Some((Anchor::BottomLeft, Gravity::BottomRight)),
+        (PanelAnchor::Top, Segment::End) => Some((Anchor::BottomRight, Gravity::BottomLeft)),
+        (PanelAnchor::Bottom, Segment::Start) => Some((Anchor::TopLeft, Gravity::TopRight)),
+        (PanelAnchor::Bottom, Segment::End) => Some((Anchor::TopRight, Gravity::TopLeft)),
+        (PanelAnchor::Left, Segment::Start) => Some((Anchor::TopRight, Gravity::BottomRight)),
+        (PanelAnchor::Left, Segment::End) => Some((Anchor::BottomRight, Gravity::TopRight)),
+        (PanelAnchor::Right, Segment::Start) => Some((Anchor::TopLeft, Gravity::BottomLeft)),
+        (PanelAnchor::Right, Segment::End) => Some((Anchor::BottomLeft, Gravity::TopLeft)),
+    };
+    if let Some((anchor, gravity)) = placement {
+        settings.positioner.anchor = anchor;
+        settings.positioner.gravity = gravity;
+    }
+    settings
+}
@@ -0,0 +1,125 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Whether the screens are off, so work only shown on them can stop.
+//!
+//! cosmic-idle turns the outputs off once the session was idle for the
+//! `screen_off_time` of its config. The compositor tells when the session was
+//! idle for as long through the ext-idle-notify protocol, which is taken as
+//! the screens turning off. The timeout is read when the subscription starts.
+
+use std::thread;
+
+use cosmic::{
+    cctk::{
+        sctk::reexports::protocols::ext::idle_notify::v1::client::{
+            ext_idle_notification_v1::{self, ExtIdleNotificationV1},
+            ext_idle_notifier_v1::ExtIdleNotifierV1,
+        },
+        wayland_client::{
+            delegate_noop,
+            globals::{registry_queue_init, GlobalListContents},
+            protocol::{
+                wl_registry::{self, WlRegistry},
+                wl_seat::WlSeat,
+            },
+            Connection, Dispatch, QueueHandle,
+        },
+    },
+    cosmic_config::{Config, ConfigGet},
+    iced::{
+        self,
+        futures::{channel::mpsc, SinkExt, StreamExt},
+        subscription,
+    },
+};
+
+const IDLE_CONFIG_ID: &str = "com.system76.CosmicIdle";
+/// cosmic-idle's timeout if it was never set, in milliseconds.
+const DEFAULT_SCREEN_OFF_TIME: u32 = 15 * 60 * 1000;
+
+/// Emits `true` when the screens turn off, and `false` when they're back on.
+pub fn screen_off_subscription() -> iced::Subscription<bool> {
+    struct ScreenOff;
+
+    subscription::channel(
+        std::any::TypeId::of::<ScreenOff>(),
+        1,
+        |mut output| async move {
+            // The screens are never turned off otherwise
+            if let Some(timeout) = screen_off_time() {
+                let (tx, mut rx) = mpsc::unbounded();
+                thread::spawn(move || {
+                    if let Err(err) = watch(timeout, tx) {
+                        tracing::debug!("Idle notifications unavailable: {}", err);
+                    }
+                });
+                while let Some(off) = rx.next().await {
+                    _ = output.send(off).await;
+                }
+            }
+            iced::futures::future::pending().await
+        },
+    )
+}
+
+/// How long the session is idle for before cosmic-idle turns the screens off.
+fn screen_off_time() -> Option<u32> {
+    Config::new(IDLE_CONFIG_ID, 1)
+        .ok()
+        .and_then(|config| config.get::<Option<u32>>("screen_off_time").ok())
+        .unwrap_or(Some(DEFAULT_SCREEN_OFF_TIME))
+}
+
+struct State {
+    tx: mpsc::UnboundedSender<bool>,
+}
+
+fn watch(timeout: u32, tx: mpsc::UnboundedSender<bool>) -> Result<(), Box<dyn std::error::Error>> {
+    let connection = Connection::connect_to_env()?;
+    let (globals, mut queue) = registry_queue_init::<State>(&connection)?;
+    let qh = queue.handle();
+    let notifier: ExtIdleNotifierV1 = globals.bind(&qh, 1..=1, ())?;
+    let seat: WlSeat = globals.bind(&qh, 1..=1, ())?;
+    let _notification = notifier.get_idle_notification(timeout, &seat, &qh, ());
+
+    let mut state = State { tx };
+    // Until the subscription is dropped
+    while !state.tx.is_closed() {
+        queue.blocking_dispatch(&mut state)?;
+    }
+    Ok(())
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for State {
+    fn event(
+        _state: &mut Self,
+        _registry: &WlRegistry,
+        _event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtIdleNotificationV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _notification: &ExtIdleNotificationV1,
+        event: ext_idle_notification_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let off = match event {
+            ext_idle_notification_v1::Event::Idled => true,
+            ext_idle_notification_v1::Event::Resumed => false,
+            _ => return,
+        };
+        _ = state.tx.unbounded_send(off);
+    }
+}
+
+delegate_noop!(State: ExtIdleNotifierV1);
+delegate_noop!(State: ignore WlSeat);
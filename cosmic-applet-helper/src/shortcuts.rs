@@ -0,0 +1,258 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Keyboard shortcuts working anywhere on the desktop, through the global
+//! shortcuts portal.
+//!
+//! The portal lets the user confirm or change the triggers, so applets only
+//! suggest one. A shortcut left without a trigger, usually because another
+//! application already uses it, is reported as a conflict.
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    hash::Hash,
+    pin::pin,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use cosmic::iced::{
+    self,
+    futures::{stream, SinkExt, StreamExt},
+    subscription,
+};
+use serde::de::DeserializeOwned;
+use zbus::{
+    proxy,
+    zvariant::{self, ObjectPath, OwnedObjectPath, Value},
+    Connection,
+};
+
+const PORTAL: &str = "org.freedesktop.portal.Desktop";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Shortcut {
+    /// Unique within the applet, and reported back on activation.
+    pub id: String,
+    /// Shown to the user when confirming the shortcut.
+    pub description: String,
+    /// In the format of the shortcuts specification, like `LOGO+c`.
+    pub preferred_trigger: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ShortcutEvent {
+    /// The shortcuts were registered.
+    Bound {
+        /// The ids of the shortcuts and descriptions of their triggers.
+        triggers: Vec<(String, String)>,
+        /// The ids of the shortcuts left without a trigger.
+        conflicts: Vec<String>,
+    },
+    Activated(String),
+    Deactivated(String),
+    Error(String),
+}
+
+#[proxy(
+    interface = "org.freedesktop.portal.GlobalShortcuts",
+    default_service = "org.freedesktop.portal.Desktop",
+    default_path = "/org/freedesktop/portal/desktop"
+)]
+trait GlobalShortcuts {
+    fn create_session(&self, options: HashMap<&str, Value<'_>>) -> zbus::Result<OwnedObjectPath>;
+
+    fn bind_shortcuts(
+        &self,
+        session_handle: &ObjectPath<'_>,
+        shortcuts: &[(&str, HashMap<&str, Value<'_>>)],
+        parent_window: &str,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(signal)]
+    fn activated(
+        &self,
+        session_handle: ObjectPath<'_>,
+        shortcut_id: &str,
+        timestamp: u64,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn deactivated(
+        &self,
+        session_handle: ObjectPath<'_>,
+        shortcut_id: &str,
+        timestamp: u64,
+        options: HashMap<&str, Value<'_>>,
+    ) -> zbus::Result<()>;
+}
+
+#[derive(Debug, zvariant::DeserializeDict, zvariant::Type)]
+#[zvariant(signature = "dict")]
+struct SessionResults {
+    session_handle: Option<String>,
+}
+
+#[derive(Debug, zvariant::DeserializeDict, zvariant::Type)]
+#[zvariant(signature = "dict")]
+struct BindResults {
+    shortcuts: Option<Vec<(String, BoundShortcut)>>,
+}
+
+#[derive(Debug, zvariant::DeserializeDict, zvariant::Type)]
+#[zvariant(signature = "dict")]
+struct BoundShortcut {
+    trigger_description: Option<String>,
+}
+
+/// Registers `shortcuts` for as long as the subscription runs, reporting when
+/// they're pressed.
+pub fn global_shortcuts<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+    shortcuts: Vec<Shortcut>,
+) -> iced::Subscription<ShortcutEvent> {
+    subscription::channel((id, shortcuts.clone()), 4, move |mut output| async move {
+        if let Err(err) = run(&shortcuts, &mut output).await {
+            let _ = output.send(ShortcutEvent::Error(err.to_string())).await;
+        }
+        iced::futures::future::pending().await
+    })
+}
+
+async fn run(
+    shortcuts: &[Shortcut],
+    output: &mut iced::futures::channel::mpsc::Sender<ShortcutEvent>,
+) -> zbus::Result<()> {
+    let conn = Connection::session().await?;
+    let portal = GlobalShortcutsProxy::new(&conn).await?;
+
+    let token = handle_token();
+    let session_token = handle_token();
+    let results: SessionResults = request(&conn, &token, async {
+        portal
+            .create_session(HashMap::from([
+                ("handle_token", Value::from(token.as_str())),
+                ("session_handle_token", Value::from(session_token.as_str())),
+            ]))
+            .await
+    })
+    .await?;
+    let session = results
+        .session_handle
+        .ok_or_else(|| zbus::Error::Failure("No session handle".to_string()))?;
+    let session = ObjectPath::try_from(session)?;
+
+    // Listen before binding, so an early press isn't missed
+    let activated = portal.receive_activated().await?;
+    let deactivated = portal.receive_deactivated().await?;
+
+    let options: Vec<HashMap<&str, Value<'_>>> = shortcuts
+        .iter()
+        .map(|shortcut| {
+            let mut options =
+                HashMap::from([("description", Value::from(shortcut.description.as_str()))]);
+            if let Some(trigger) = &shortcut.preferred_trigger {
+                options.insert("preferred_trigger", Value::from(trigger.as_str()));
+            }
+            options
+        })
+        .collect();
+    let requested: Vec<(&str, HashMap<&str, Value<'_>>)> = shortcuts
+        .iter()
+        .map(|shortcut| shortcut.id.as_str())
+        .zip(options)
+        .collect();
+    let token = handle_token();
+    let results: BindResults = request(&conn, &token, async {
+        portal
+            .bind_shortcuts(
+                &session,
+                &requested,
+                "",
+                HashMap::from([("handle_token", Value::from(token.as_str()))]),
+            )
+            .await
+    })
+    .await?;
+
+    let triggers: Vec<(String, String)> = results
+        .shortcuts
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(id, bound)| (id, bound.trigger_description.unwrap_or_default()))
+        .collect();
+    let conflicts = shortcuts
+        .iter()
+        .filter(|shortcut| {
+            !triggers
+                .iter()
+                .any(|(id, trigger)| *id == shortcut.id && !trigger.is_empty())
+        })
+        .map(|shortcut| shortcut.id.clone())
+        .collect();
+    let _ = output
+        .send(ShortcutEvent::Bound {
+            triggers,
+            conflicts,
+        })
+        .await;
+
+    let activated = activated.map(|signal| {
+        let args = signal.args().ok()?;
+        (*args.session_handle() == session)
+            .then(|| ShortcutEvent::Activated(args.shortcut_id().to_string()))
+    });
+    let deactivated = deactivated.map(|signal| {
+        let args = signal.args().ok()?;
+        (*args.session_handle() == session)
+            .then(|| ShortcutEvent::Deactivated(args.shortcut_id().to_string()))
+    });
+    let mut events = pin!(stream::select(activated, deactivated));
+    while let Some(event) = events.next().await {
+        if let Some(event) = event {
+            let _ = output.send(event).await;
+        }
+    }
+    Ok(())
+}
+
+fn handle_token() -> String {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    format!(
+        "cosmic_applet_{}_{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Makes a portal request and waits for its results.
+async fn request<R: DeserializeOwned + zvariant::Type>(
+    conn: &Connection,
+    token: &str,
+    call: impl std::future::Future<Output = zbus::Result<OwnedObjectPath>>,
+) -> zbus::Result<R> {
+    // The request's path is known ahead, so the response can't come before
+    // it's listened for
+    let sender = conn
+        .unique_name()
+        .map(|name| name.trim_start_matches(':').replace('.', "_"))
+        .unwrap_or_default();
+    let path = format!("/org/freedesktop/portal/desktop/request/{sender}/{token}");
+    let proxy = zbus::Proxy::new(conn, PORTAL, path, "org.freedesktop.portal.Request").await?;
+    let mut responses = proxy.receive_signal("Response").await?;
+
+    call.await?;
+
+    let response = responses
+        .next()
+        .await
+        .ok_or_else(|| zbus::Error::Failure("No response".to_string()))?;
+    let (code, results): (u32, R) = response.body().deserialize()?;
+    match code {
+        0 => Ok(results),
+        1 => Err(zbus::Error::Failure("Cancelled by the user".to_string())),
+        _ => Err(zbus::Error::Failure("Request failed".to_string())),
+    }
+}
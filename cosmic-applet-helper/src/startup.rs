@@ -0,0 +1,83 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! How long the stages of an applet's startup take, as spans whose durations
+//! [`TimingLayer`] logs. They're logged at the info level with the
+//! `applet_startup` target, which is shown whatever the configured level is.
+//! A `RUST_LOG` filter needs `applet_startup=info` to show them.
+//!
+//! The stages follow each other: `toolkit` is libcosmic reading the panel's
+//! config and loading the theme, `applet_init` the applet loading its own
+//! config, and `first_frame` building the panel button for the first time.
+//! They're all in a `startup` span, timing the whole. The first opening of
+//! the popup is timed as `popup_init`.
+
+use std::{sync::Mutex, time::Instant};
+
+use tracing::{span, Span, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+pub const TARGET: &str = "applet_startup";
+
+/// The `startup` span and the span of its current stage.
+static STAGES: Mutex<Option<(Span, Span)>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Stage {
+    Toolkit,
+    AppletInit,
+    FirstFrame,
+}
+
+/// Ends the current stage, and starts `next`. The first stage starts the
+/// `startup` span.
+pub(crate) fn stage(next: Stage) {
+    let Ok(mut stages) = STAGES.lock() else {
+        return;
+    };
+    let startup = match stages.take() {
+        Some((startup, _current)) => startup,
+        None => tracing::info_span!(target: TARGET, "startup"),
+    };
+    let next = match next {
+        Stage::Toolkit => tracing::info_span!(target: TARGET, parent: &startup, "toolkit"),
+        Stage::AppletInit => tracing::info_span!(target: TARGET, parent: &startup, "applet_init"),
+        Stage::FirstFrame => tracing::info_span!(target: TARGET, parent: &startup, "first_frame"),
+    };
+    *stages = Some((startup, next));
+}
+
+/// Ends the last stage, and the startup. Later calls do nothing.
+pub(crate) fn finish() {
+    if let Ok(mut stages) = STAGES.lock() {
+        stages.take();
+    }
+}
+
+struct Started(Instant);
+
+/// Logs how long each span of [`TARGET`] was open, when it closes.
+pub struct TimingLayer;
+
+impl<S> Layer<S> for TimingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if attrs.metadata().target() != TARGET {
+            return;
+        }
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(Started(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        if let Some(Started(started)) = span.extensions().get::<Started>() {
+            tracing::info!(target: TARGET, "{} took {:?}", span.name(), started.elapsed());
+        }
+    }
+}
@@ -0,0 +1,42 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Solar position, using NOAA's approximate equations.
+
+use std::f64::consts::PI;
+
+use chrono::{DateTime, Datelike, Timelike, Utc};
+
+/// Elevation of the sun's center at sunrise and sunset, accounting for
+/// refraction and the size of the sun's disc.
+const SUNSET_ELEVATION: f64 = -0.833;
+
+/// Elevation of the sun above the horizon, in degrees.
+pub fn elevation(time: DateTime<Utc>, latitude: f64, longitude: f64) -> f64 {
+    let hour = time.hour() as f64 + time.minute() as f64 / 60.0 + time.second() as f64 / 3600.0;
+    let gamma = 2.0 * PI / 365.0 * (time.ordinal0() as f64 + (hour - 12.0) / 24.0);
+
+    let eqtime = 229.18
+        * (0.000075 + 0.001868 * gamma.cos()
+            - 0.032077 * gamma.sin()
+            - 0.014615 * (2.0 * gamma).cos()
+            - 0.040849 * (2.0 * gamma).sin());
+    let declination = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+        - 0.006758 * (2.0 * gamma).cos()
+        + 0.000907 * (2.0 * gamma).sin()
+        - 0.002697 * (3.0 * gamma).cos()
+        + 0.00148 * (3.0 * gamma).sin();
+
+    let true_solar_minutes = hour * 60.0 + eqtime + 4.0 * longitude;
+    let hour_angle = (true_solar_minutes / 4.0 - 180.0).to_radians();
+
+    let latitude = latitude.to_radians();
+    let cos_zenith =
+        latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos();
+    90.0 - cos_zenith.clamp(-1.0, 1.0).acos().to_degrees()
+}
+
+/// Whether the sun is down.
+pub fn is_night(time: DateTime<Utc>, latitude: f64, longitude: f64) -> bool {
+    elevation(time, latitude, longitude) < SUNSET_ELEVATION
+}
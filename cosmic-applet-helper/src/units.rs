@@ -0,0 +1,36 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Byte counts and rates, formatted the same way by every applet.
+
+const BINARY: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+const DECIMAL: [&str; 5] = ["B", "kB", "MB", "GB", "TB"];
+
+fn format(bytes: u64, step: f64, units: &[&str]) -> String {
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= step && unit < units.len() - 1 {
+        value /= step;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, units[0])
+    } else {
+        format!("{:.1} {}", value, units[unit])
+    }
+}
+
+/// Formats a byte count with a binary unit, such as `1.5 GiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    format(bytes, 1024.0, &BINARY)
+}
+
+/// Formats a rate with a binary unit, such as `1.5 MiB/s`.
+pub fn format_rate(bytes_per_second: u64) -> String {
+    format!("{}/s", format_bytes(bytes_per_second))
+}
+
+/// Formats a size in decimal units, as drives are labelled.
+pub fn format_size(bytes: u64) -> String {
+    format(bytes, 1000.0, &DECIMAL)
+}
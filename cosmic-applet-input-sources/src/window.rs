@@ -9,27 +9,22 @@ use cosmic::iced::{alignment, Alignment, Length};
 use cosmic::{
     app::Core,
     applet::{self},
-    cosmic_config::{self, ConfigSet},
+    cosmic_config::{self, ConfigSet, CosmicConfigEntry},
     iced::{
-        wayland::popup::{destroy_popup, get_popup},
         widget::{column, row},
-        window::Id,
-        Command, Limits,
+        Command,
     },
     iced_futures::Subscription,
-    iced_runtime::core::window,
-    iced_style::application,
     prelude::*,
     widget::{self, horizontal_space, vertical_space},
 };
+use cosmic_applet_helper::{AppletMessage, CosmicApplet};
 use cosmic_comp_config::CosmicCompConfig;
 use xkb_data::KeyboardLayouts;
 
 pub const ID: &str = "com.system76.CosmicAppletInputSources";
 
 pub struct Window {
-    core: Core,
-    popup: Option<Id>,
     config: Config,
     #[allow(dead_code)]
     config_handler: Option<cosmic_config::Config>,
@@ -42,84 +37,78 @@ pub struct Window {
 #[derive(Clone, Debug)]
 pub enum Message {
     Config(Config),
-    TogglePopup,
-    PopupClosed(Id),
     CompConfig(CosmicCompConfig),
     SetActiveLayout(ActiveLayout),
+    ShowKeyboardLayout(ActiveLayout),
     KeyboardSettings,
 }
 
-#[derive(Debug)]
-pub struct Flags {
-    pub config_handler: Option<cosmic_config::Config>,
-    pub config: Config,
-    pub comp_config: CosmicCompConfig,
-    pub comp_config_handler: Option<cosmic_config::Config>,
-    pub layouts: KeyboardLayouts,
-}
-
-impl cosmic::Application for Window {
-    type Executor = cosmic::SingleThreadExecutor;
-    type Flags = Flags;
+impl CosmicApplet for Window {
     type Message = Message;
-
     const APP_ID: &'static str = ID;
+    const AUTOSIZE: bool = false;
 
-    fn core(&self) -> &Core {
-        &self.core
-    }
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let layouts = match xkb_data::keyboard_layouts() {
+            Ok(layouts) => layouts,
+            Err(why) => {
+                tracing::error!("could not get keyboard layouts data: {:?}", why);
+                std::process::exit(0);
+            }
+        };
 
-    fn core_mut(&mut self) -> &mut Core {
-        &mut self.core
-    }
+        let (config_handler, config) = match cosmic_config::Config::new(ID, CONFIG_VERSION) {
+            Ok(config_handler) => {
+                let config = match Config::get_entry(&config_handler) {
+                    Ok(ok) => ok,
+                    Err((errs, config)) => {
+                        tracing::error!("errors loading config: {:?}", errs);
+                        config
+                    }
+                };
+                (Some(config_handler), config)
+            }
+            Err(err) => {
+                tracing::error!("failed to create config handler: {}", err);
+                (None, Config::default())
+            }
+        };
 
-    fn init(
-        core: Core,
-        flags: Self::Flags,
-    ) -> (Self, Command<cosmic::app::Message<Self::Message>>) {
-        let window = Window {
-            comp_config_handler: flags.comp_config_handler,
-            layouts: flags.layouts,
-            core,
-            config: flags.config,
-            config_handler: flags.config_handler,
-            popup: None,
-            comp_config: flags.comp_config,
+        let (comp_config_handler, comp_config) = match cosmic_config::Config::new(
+            "com.system76.CosmicComp",
+            CosmicCompConfig::VERSION,
+        ) {
+            Ok(config_handler) => {
+                let config = match CosmicCompConfig::get_entry(&config_handler) {
+                    Ok(ok) => ok,
+                    Err((errs, config)) => {
+                        tracing::error!("errors loading config: {:?}", errs);
+                        config
+                    }
+                };
+                (Some(config_handler), config)
+            }
+            Err(err) => {
+                tracing::error!("failed to create config handler: {}", err);
+                (None, CosmicCompConfig::default())
+            }
+        };
+
+        let mut window = Window {
+            comp_config_handler,
+            layouts,
+            config,
+            config_handler,
+            comp_config,
             active_layouts: Vec::new(),
         };
+        window.active_layouts = window.update_xkb();
         (window, Command::none())
     }
 
-    fn on_close_requested(&self, id: window::Id) -> Option<Message> {
-        Some(Message::PopupClosed(id))
-    }
-
-    fn update(&mut self, message: Self::Message) -> Command<cosmic::app::Message<Self::Message>> {
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
         match message {
             Message::Config(config) => self.config = config,
-            Message::TogglePopup => {
-                return if let Some(p) = self.popup.take() {
-                    destroy_popup(p)
-                } else {
-                    let new_id = Id::unique();
-                    self.popup.replace(new_id);
-                    let mut popup_settings =
-                        self.core
-                            .applet
-                            .get_popup_settings(Id::MAIN, new_id, None, None, None);
-                    popup_settings.positioner.size_limits = Limits::NONE
-                        .max_width(372.0)
-                        .min_width(300.0)
-                        .min_height(1.)
-                        .max_height(1080.0);
-                    get_popup(popup_settings)
-                }
-            }
-            Message::PopupClosed(id) => {
-                if self.popup.as_ref() == Some(&id) {
-                    self.popup = None;
-                }
-            }
             Message::CompConfig(config) => {
                 self.comp_config = config;
                 self.active_layouts = self.update_xkb();
@@ -129,6 +118,16 @@ impl cosmic::Application for Window {
                 cmd.arg("keyboard");
                 cosmic::process::spawn(cmd);
             }
+            Message::ShowKeyboardLayout(layout) => {
+                let mut cmd = std::process::Command::new("gkbd-keyboard-display");
+                cmd.arg("-l");
+                if layout.variant.is_empty() {
+                    cmd.arg(&layout.layout);
+                } else {
+                    cmd.arg(format!("{}\t{}", layout.layout, layout.variant));
+                }
+                cosmic::process::spawn(cmd);
+            }
             Message::SetActiveLayout(active_layout) => {
                 let Some(i) = self
                     .active_layouts
@@ -165,11 +164,11 @@ impl cosmic::Application for Window {
         Command::none()
     }
 
-    fn view(&self) -> Element<Self::Message> {
-        let input_source_text = self.core.applet.text(
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let input_source_text = core.applet.text(
             self.active_layouts
                 .first()
-                .map_or(String::new(), |l| l.layout.clone()),
+                .map_or(String::new(), |l| l.layout.to_uppercase()),
         );
 
         cosmic::widget::button(
@@ -177,8 +176,8 @@ impl cosmic::Application for Window {
                 column!(
                     input_source_text,
                     horizontal_space(Length::Fixed(
-                        (self.core.applet.suggested_size(true).0
-                            + 2 * self.core.applet.suggested_padding(true))
+                        (core.applet.suggested_size(true).0
+                            + 2 * core.applet.suggested_padding(true))
                             as f32
                     ))
                 )
@@ -186,44 +185,59 @@ impl cosmic::Application for Window {
                 .height(Length::Shrink)
                 .align_items(Alignment::Center),
                 vertical_space(Length::Fixed(
-                    (self.core.applet.suggested_size(true).1
-                        + 2 * self.core.applet.suggested_padding(true)) as f32
+                    (core.applet.suggested_size(true).1 + 2 * core.applet.suggested_padding(true))
+                        as f32
                 ))
             )
             .align_items(Alignment::Center)
             .width(Length::Shrink)
             .height(Length::Shrink),
         )
-        .on_press(Message::TogglePopup)
+        .on_press(AppletMessage::TogglePopup)
         .style(cosmic::theme::Button::AppletIcon)
         .into()
     }
 
-    fn view_window(&self, _id: Id) -> Element<Self::Message> {
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
         let mut content_list =
             widget::column::with_capacity(4 + self.active_layouts.len()).padding([8, 0]);
-        for layout in &self.active_layouts {
+        for (i, layout) in self.active_layouts.iter().enumerate() {
             let group = widget::column::with_capacity(2)
                 .push(widget::text::body(layout.description.clone()))
-                .push(widget::text::caption(layout.layout.clone()));
+                .push(widget::text::caption(layout.code()));
+            let mut button_row = widget::row::with_capacity(3)
+                .push(group)
+                .push(horizontal_space(Length::Fill))
+                .align_items(Alignment::Center);
+            if i == 0 {
+                button_row = button_row.push(
+                    widget::icon::from_name("object-select-symbolic")
+                        .size(16)
+                        .icon(),
+                );
+            }
             content_list = content_list.push(
-                applet::menu_button(group).on_press(Message::SetActiveLayout(layout.clone())),
+                applet::menu_button(button_row).on_press(Message::SetActiveLayout(layout.clone())),
             );
         }
-        if !self.active_layouts.is_empty() {
+        if let Some(active) = self.active_layouts.first() {
             content_list = content_list.push(
                 applet::padded_control(widget::divider::horizontal::default()).apply(Element::from),
             );
+            content_list = content_list.push(
+                applet::menu_button(widget::text::body(fl!("show-keyboard-layout")))
+                    .on_press(Message::ShowKeyboardLayout(active.clone())),
+            );
         }
         content_list = content_list.push(
             applet::menu_button(widget::text::body(fl!("keyboard-settings")))
                 .on_press(Message::KeyboardSettings),
         );
 
-        self.core.applet.popup_container(content_list).into()
+        Element::from(content_list).map(AppletMessage::App)
     }
 
-    fn subscription(&self) -> Subscription<Self::Message> {
+    fn subscription(&self, core: &Core) -> Subscription<Message> {
         struct ConfigSubscription;
         let config = cosmic_config::config_subscription(
             std::any::TypeId::of::<ConfigSubscription>(),
@@ -240,25 +254,18 @@ impl cosmic::Application for Window {
             }
             Message::Config(update.config)
         });
-        let xbg_config = self
-            .core
-            .watch_config("com.system76.CosmicComp")
-            .map(|update| {
-                if !update.errors.is_empty() {
-                    tracing::error!(
-                        "errors loading config {:?}: {:?}",
-                        update.keys,
-                        update.errors
-                    );
-                }
-                Message::CompConfig(update.config)
-            });
+        let xbg_config = core.watch_config("com.system76.CosmicComp").map(|update| {
+            if !update.errors.is_empty() {
+                tracing::error!(
+                    "errors loading config {:?}: {:?}",
+                    update.keys,
+                    update.errors
+                );
+            }
+            Message::CompConfig(update.config)
+        });
         Subscription::batch(vec![config, xbg_config])
     }
-
-    fn style(&self) -> Option<<Theme as application::StyleSheet>::Style> {
-        Some(cosmic::applet::style())
-    }
 }
 impl Window {
     fn update_xkb(&self) -> Vec<ActiveLayout> {
@@ -273,7 +280,6 @@ impl Window {
             .chain(std::iter::repeat(""));
 
         for (layout, variant) in layouts.zip(variants) {
-            println!("{} : {}", layout, variant);
             for xkb_layout in self.layouts.layouts() {
                 if layout != xkb_layout.name() {
                     continue;
@@ -315,3 +321,14 @@ pub struct ActiveLayout {
     description: String,
     variant: String,
 }
+
+impl ActiveLayout {
+    /// The layout code, with its variant if there is one, such as `us+dvorak`.
+    fn code(&self) -> String {
+        if self.variant.is_empty() {
+            self.layout.clone()
+        } else {
+            format!("{}+{}", self.layout, self.variant)
+        }
+    }
+}
@@ -0,0 +1,7 @@
+location-services = Location services
+in-use = Apps are using your location
+not-in-use = No apps are using your location
+apps-denied = Apps can't use your location
+cannot-turn-off = Location services can't be turned off, as GeoClue doesn't allow this applet as its agent
+used-by = Using your location
+blocked = Denied location
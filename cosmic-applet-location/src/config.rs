@@ -0,0 +1,20 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
+pub const APP_ID: &str = "com.system76.CosmicAppletLocation";
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, CosmicConfigEntry)]
+#[version = 1]
+pub struct LocationConfig {
+    /// Whether apps may use location services at all.
+    pub enabled: bool,
+}
+
+impl Default for LocationConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
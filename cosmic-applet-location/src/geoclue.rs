@@ -0,0 +1,221 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Location use through GeoClue. The applet registers as GeoClue's agent,
+//! which decides whether apps may locate the device at all.
+
+use std::{fmt::Debug, hash::Hash, time::Duration};
+
+use cosmic::{
+    iced::{self, subscription},
+    iced_futures::futures::{self, SinkExt, StreamExt},
+};
+use tokio::sync::mpsc;
+use zbus::{fdo::IntrospectableProxy, proxy, Connection};
+
+use crate::config::APP_ID;
+
+const SERVICE: &str = "org.freedesktop.GeoClue2";
+const AGENT_PATH: &str = "/org/freedesktop/GeoClue2/Agent";
+const CLIENT_PATH: &str = "/org/freedesktop/GeoClue2/Client";
+/// `GCLUE_ACCURACY_LEVEL_NONE`
+const ACCURACY_NONE: u32 = 0;
+/// `GCLUE_ACCURACY_LEVEL_EXACT`
+const ACCURACY_EXACT: u32 = 8;
+/// Clients starting and stopping isn't signalled, so they're polled.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[proxy(
+    interface = "org.freedesktop.GeoClue2.Manager",
+    default_service = "org.freedesktop.GeoClue2",
+    default_path = "/org/freedesktop/GeoClue2/Manager"
+)]
+trait Manager {
+    /// AddAgent method
+    fn add_agent(&self, id: &str) -> zbus::Result<()>;
+
+    /// InUse property
+    #[zbus(property)]
+    fn in_use(&self) -> zbus::Result<bool>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.GeoClue2.Client",
+    default_service = "org.freedesktop.GeoClue2"
+)]
+trait Client {
+    /// DesktopId property
+    #[zbus(property)]
+    fn desktop_id(&self) -> zbus::Result<String>;
+
+    /// Active property
+    #[zbus(property)]
+    fn active(&self) -> zbus::Result<bool>;
+}
+
+struct Agent {
+    enabled: bool,
+    /// Apps that asked for location, and whether they were allowed.
+    requests: mpsc::UnboundedSender<(String, bool)>,
+}
+
+#[zbus::interface(name = "org.freedesktop.GeoClue2.Agent")]
+impl Agent {
+    fn authorize_app(&self, desktop_id: &str, req_accuracy_level: u32) -> (bool, u32) {
+        let _ = self.requests.send((desktop_id.to_string(), self.enabled));
+        if self.enabled {
+            (true, req_accuracy_level)
+        } else {
+            (false, ACCURACY_NONE)
+        }
+    }
+
+    #[zbus(property)]
+    fn max_accuracy_level(&self) -> u32 {
+        if self.enabled {
+            ACCURACY_EXACT
+        } else {
+            ACCURACY_NONE
+        }
+    }
+}
+
+/// Desktop IDs of the clients currently locating the device.
+async fn active_apps(conn: &Connection) -> zbus::Result<Vec<String>> {
+    let xml = IntrospectableProxy::builder(conn)
+        .destination(SERVICE)?
+        .path(CLIENT_PATH)?
+        .build()
+        .await?
+        .introspect()
+        .await?;
+
+    let mut apps = Vec::new();
+    for node in xml.split("<node name=\"").skip(1) {
+        let Some(name) = node.split('"').next() else {
+            continue;
+        };
+        let client = ClientProxy::builder(conn)
+            .path(format!("{CLIENT_PATH}/{name}"))?
+            .build()
+            .await?;
+        if client.active().await.unwrap_or(false) {
+            if let Ok(desktop_id) = client.desktop_id().await {
+                if !apps.contains(&desktop_id) {
+                    apps.push(desktop_id);
+                }
+            }
+        }
+    }
+    Ok(apps)
+}
+
+#[derive(Debug, Clone)]
+pub enum LocationUpdate {
+    /// Location services can be turned on and off through the sender.
+    Init(mpsc::UnboundedSender<bool>),
+    /// GeoClue didn't accept us as its agent, so location services can't be
+    /// turned off.
+    NoAgent,
+    Status {
+        in_use: bool,
+        apps: Vec<String>,
+    },
+    Requested {
+        desktop_id: String,
+        allowed: bool,
+    },
+    Finished,
+}
+
+pub fn location_subscription<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+) -> iced::Subscription<LocationUpdate> {
+    subscription::channel(id, 50, move |mut output| async move {
+        if let Err(err) = run(&mut output).await {
+            tracing::error!("Failed to monitor GeoClue: {}", err);
+        }
+        let _ = output.send(LocationUpdate::Finished).await;
+        futures::future::pending().await
+    })
+}
+
+async fn run(output: &mut futures::channel::mpsc::Sender<LocationUpdate>) -> zbus::Result<()> {
+    let (requests_tx, mut requests_rx) = mpsc::unbounded_channel();
+    let (enabled_tx, mut enabled_rx) = mpsc::unbounded_channel();
+    let conn = zbus::connection::Builder::system()?
+        .serve_at(
+            AGENT_PATH,
+            Agent {
+                enabled: true,
+                requests: requests_tx,
+            },
+        )?
+        .build()
+        .await?;
+
+    let manager = ManagerProxy::new(&conn).await?;
+    if let Err(err) = manager.add_agent(APP_ID).await {
+        tracing::warn!("Failed to register as GeoClue agent: {}", err);
+        _ = output.send(LocationUpdate::NoAgent).await;
+    }
+    _ = output.send(LocationUpdate::Init(enabled_tx)).await;
+
+    let mut in_use_changed = manager.receive_in_use_changed().await;
+    let mut poll = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        tokio::select! {
+            Some(enabled) = enabled_rx.recv() => {
+                let agent = conn
+                    .object_server()
+                    .interface::<_, Agent>(AGENT_PATH)
+                    .await?;
+                agent.get_mut().await.enabled = enabled;
+                agent
+                    .get()
+                    .await
+                    .max_accuracy_level_changed(agent.signal_context())
+                    .await?;
+                continue;
+            }
+            Some((desktop_id, allowed)) = requests_rx.recv() => {
+                _ = output
+                    .send(LocationUpdate::Requested { desktop_id, allowed })
+                    .await;
+                continue;
+            }
+            Some(_) = in_use_changed.next() => {}
+            _ = poll.tick() => {}
+        }
+
+        let in_use = manager.in_use().await.unwrap_or(false);
+        let apps = if in_use {
+            active_apps(&conn).await.unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        _ = output.send(LocationUpdate::Status { in_use, apps }).await;
+    }
+}
+
+/// The name of an app from its desktop entry, falling back to its ID.
+pub fn app_name(desktop_id: &str) -> String {
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .unwrap_or_else(|_| "/usr/local/share:/usr/share".to_string());
+    let home = std::env::var("XDG_DATA_HOME").ok().or_else(|| {
+        std::env::var("HOME")
+            .ok()
+            .map(|home| format!("{home}/.local/share"))
+    });
+    home.into_iter()
+        .chain(data_dirs.split(':').map(str::to_string))
+        .find_map(|dir| {
+            let entry =
+                std::fs::read_to_string(format!("{dir}/applications/{desktop_id}.desktop")).ok()?;
+            entry
+                .lines()
+                .find_map(|line| line.strip_prefix("Name="))
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| desktop_id.to_string())
+}
@@ -0,0 +1,217 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::{
+    config::{self, LocationConfig},
+    fl,
+    geoclue::{app_name, location_subscription, LocationUpdate},
+};
+use cosmic::{
+    app::Core,
+    applet::padded_control,
+    cosmic_config::{Config, CosmicConfigEntry},
+    iced::{
+        widget::{column, row},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_widget::{Column, Row},
+    widget::{divider, icon, text, toggler},
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent};
+use tokio::sync::mpsc;
+
+const ACTIVE_ICON: &str = "location-services-active-symbolic";
+const DISABLED_ICON: &str = "location-services-disabled-symbolic";
+
+#[derive(Default)]
+pub struct Window {
+    config: LocationConfig,
+    config_helper: Option<Config>,
+    enabled_tx: Option<mpsc::UnboundedSender<bool>>,
+    /// Whether we're GeoClue's agent, and can turn location services off.
+    is_agent: bool,
+    in_use: bool,
+    /// Names of the apps using location.
+    apps: Vec<String>,
+    /// Names of apps denied location since it was turned off.
+    blocked: Vec<String>,
+    /// Keeps the button around while its popup is open.
+    popup_open: bool,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    ConfigChanged(LocationConfig),
+    Location(LocationUpdate),
+    Enable(bool),
+}
+
+impl Window {
+    fn send_enabled(&self) {
+        if let Some(tx) = self.enabled_tx.as_ref() {
+            let _ = tx.send(self.config.enabled);
+        }
+    }
+
+    fn apps_view<'a>(label: String, apps: &'a [String]) -> Vec<Element<'a, Message>> {
+        let mut content = vec![padded_control(text(label).size(14)).into()];
+        for app in apps {
+            content.push(
+                padded_control(
+                    row![
+                        icon::from_name("application-x-executable-symbolic").size(16),
+                        text(app).size(14),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+        }
+        content
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = config::APP_ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::APP_ID, LocationConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                LocationConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let window = Self {
+            config,
+            config_helper,
+            is_agent: true,
+            ..Default::default()
+        };
+        (window, Command::none())
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        self.popup_open = event == PanelEvent::PopupOpened;
+        Command::none()
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        Subscription::batch(vec![
+            location_subscription(0).map(Message::Location),
+            core.watch_config::<LocationConfig>(config::APP_ID)
+                .map(|u| Message::ConfigChanged(u.config)),
+        ])
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::ConfigChanged(config) => {
+                let changed = config.enabled != self.config.enabled;
+                self.config = config;
+                if changed {
+                    self.blocked.clear();
+                    self.send_enabled();
+                }
+            }
+            Message::Location(update) => match update {
+                LocationUpdate::Init(tx) => {
+                    self.enabled_tx = Some(tx);
+                    self.send_enabled();
+                }
+                LocationUpdate::NoAgent => {
+                    self.is_agent = false;
+                }
+                LocationUpdate::Status { in_use, apps } => {
+                    self.in_use = in_use;
+                    self.apps = apps.iter().map(|id| app_name(id)).collect();
+                }
+                LocationUpdate::Requested {
+                    desktop_id,
+                    allowed,
+                } => {
+                    let name = app_name(&desktop_id);
+                    if !allowed && !self.blocked.contains(&name) {
+                        self.blocked.push(name);
+                    }
+                }
+                LocationUpdate::Finished => {
+                    self.enabled_tx = None;
+                    self.in_use = false;
+                    self.apps.clear();
+                }
+            },
+            Message::Enable(enabled) => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_enabled(helper, enabled) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+                self.blocked.clear();
+                self.send_enabled();
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        // Only shown while location is in use, or turned off
+        if self.config.enabled && !self.in_use && !self.popup_open {
+            return Row::new().into();
+        }
+        core.applet
+            .icon_button(if self.config.enabled {
+                ACTIVE_ICON
+            } else {
+                DISABLED_ICON
+            })
+            .on_press(AppletMessage::TogglePopup)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let status = if !self.is_agent {
+            fl!("cannot-turn-off")
+        } else if !self.config.enabled {
+            fl!("apps-denied")
+        } else if self.in_use {
+            fl!("in-use")
+        } else {
+            fl!("not-in-use")
+        };
+        let mut content = vec![padded_control(
+            column![
+                toggler(
+                    Some(fl!("location-services")),
+                    self.config.enabled,
+                    Message::Enable
+                )
+                .text_size(14)
+                .width(Length::Fill),
+                text(status).size(10),
+            ]
+            .spacing(2),
+        )
+        .into()];
+
+        if !self.apps.is_empty() {
+            content.push(padded_control(divider::horizontal::default()).into());
+            content.extend(Self::apps_view(fl!("used-by"), &self.apps));
+        }
+        if !self.blocked.is_empty() {
+            content.push(padded_control(divider::horizontal::default()).into());
+            content.extend(Self::apps_view(fl!("blocked"), &self.blocked));
+        }
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+}
@@ -0,0 +1,4 @@
+no-players = Nothing is playing
+unknown-title = Unknown title
+playlists = Playlists
+show-player = Show { $player }
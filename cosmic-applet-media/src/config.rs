@@ -0,0 +1,25 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
+pub const APP_ID: &str = "com.system76.CosmicAppletMedia";
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, CosmicConfigEntry)]
+#[version = 1]
+pub struct MediaConfig {
+    /// Show the track title next to the icon on horizontal panels.
+    pub show_title: bool,
+    /// Characters of the title shown at once; longer titles scroll.
+    pub title_width: usize,
+}
+
+impl Default for MediaConfig {
+    fn default() -> Self {
+        Self {
+            show_title: true,
+            title_width: 24,
+        }
+    }
+}
@@ -0,0 +1,400 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! MPRIS client, tracking every media player on the session bus.
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    hash::Hash,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use cosmic::{
+    iced::{self, subscription},
+    iced_futures::futures::{self, stream::SelectAll, SinkExt, StreamExt},
+};
+use zbus::{
+    fdo::{DBusProxy, PropertiesChangedStream, PropertiesProxy},
+    proxy,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue},
+    Connection,
+};
+
+const BUS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+
+#[proxy(
+    interface = "org.mpris.MediaPlayer2",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+trait MediaPlayer2 {
+    /// Raise method
+    fn raise(&self) -> zbus::Result<()>;
+
+    /// CanRaise property
+    #[zbus(property)]
+    fn can_raise(&self) -> zbus::Result<bool>;
+
+    /// Identity property
+    #[zbus(property)]
+    fn identity(&self) -> zbus::Result<String>;
+}
+
+#[proxy(
+    interface = "org.mpris.MediaPlayer2.Player",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+trait Player {
+    /// Next method
+    fn next(&self) -> zbus::Result<()>;
+
+    /// Previous method
+    fn previous(&self) -> zbus::Result<()>;
+
+    /// PlayPause method
+    fn play_pause(&self) -> zbus::Result<()>;
+
+    /// SetPosition method
+    fn set_position(&self, track_id: &ObjectPath<'_>, position: i64) -> zbus::Result<()>;
+
+    /// PlaybackStatus property
+    #[zbus(property)]
+    fn playback_status(&self) -> zbus::Result<String>;
+
+    /// Metadata property
+    #[zbus(property)]
+    fn metadata(&self) -> zbus::Result<HashMap<String, OwnedValue>>;
+
+    /// Volume property
+    #[zbus(property)]
+    fn volume(&self) -> zbus::Result<f64>;
+    #[zbus(property)]
+    fn set_volume(&self, value: f64) -> zbus::Result<()>;
+
+    /// Position property
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> zbus::Result<i64>;
+
+    /// CanGoNext property
+    #[zbus(property)]
+    fn can_go_next(&self) -> zbus::Result<bool>;
+
+    /// CanGoPrevious property
+    #[zbus(property)]
+    fn can_go_previous(&self) -> zbus::Result<bool>;
+
+    /// CanPlay property
+    #[zbus(property)]
+    fn can_play(&self) -> zbus::Result<bool>;
+
+    /// CanPause property
+    #[zbus(property)]
+    fn can_pause(&self) -> zbus::Result<bool>;
+
+    /// CanSeek property
+    #[zbus(property)]
+    fn can_seek(&self) -> zbus::Result<bool>;
+}
+
+#[proxy(
+    interface = "org.mpris.MediaPlayer2.Playlists",
+    default_path = "/org/mpris/MediaPlayer2"
+)]
+trait Playlists {
+    /// ActivatePlaylist method
+    fn activate_playlist(&self, playlist_id: &ObjectPath<'_>) -> zbus::Result<()>;
+
+    /// GetPlaylists method
+    fn get_playlists(
+        &self,
+        index: u32,
+        max_count: u32,
+        order: &str,
+        reverse_order: bool,
+    ) -> zbus::Result<Vec<(OwnedObjectPath, String, String)>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    Stopped,
+}
+
+#[derive(Debug, Clone)]
+pub struct Playlist {
+    pub id: OwnedObjectPath,
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlayerStatus {
+    /// Bus name, such as `org.mpris.MediaPlayer2.spotify`.
+    pub name: String,
+    pub identity: String,
+    pub title: Option<String>,
+    pub artists: Vec<String>,
+    pub album: Option<String>,
+    pub art: Option<PathBuf>,
+    pub track_id: Option<OwnedObjectPath>,
+    pub length: Option<Duration>,
+    /// Position when the status was read, at `updated`.
+    pub position: Duration,
+    pub updated: Instant,
+    pub status: PlaybackStatus,
+    /// `None` if the player doesn't expose its volume.
+    pub volume: Option<f64>,
+    pub playlists: Vec<Playlist>,
+    pub can_go_next: bool,
+    pub can_go_previous: bool,
+    pub can_play_pause: bool,
+    pub can_seek: bool,
+    pub can_raise: bool,
+}
+
+impl PlayerStatus {
+    async fn new(conn: &Connection, name: &str) -> zbus::Result<Self> {
+        let player = PlayerProxy::builder(conn)
+            .destination(name.to_string())?
+            .build()
+            .await?;
+        let media_player = MediaPlayer2Proxy::builder(conn)
+            .destination(name.to_string())?
+            .build()
+            .await?;
+
+        let metadata = player.metadata().await.unwrap_or_default();
+        let string = |key: &str| {
+            metadata
+                .get(key)
+                .and_then(|v| String::try_from(v.try_clone().ok()?).ok())
+                .filter(|s| !s.is_empty())
+        };
+        let title = string("xesam:title").or_else(|| {
+            // Fall back to the file name, as some players leave the title out
+            let url = url::Url::parse(&string("xesam:url")?).ok()?;
+            let path = url.to_file_path().ok()?;
+            Some(path.file_name()?.to_string_lossy().into_owned())
+        });
+        let artists = metadata
+            .get("xesam:artist")
+            .and_then(|v| Vec::<String>::try_from(v.try_clone().ok()?).ok())
+            .unwrap_or_default();
+        let art = string("mpris:artUrl")
+            .and_then(|u| url::Url::parse(&u).ok())
+            .filter(|u| u.scheme() == "file")
+            .and_then(|u| u.to_file_path().ok());
+        let track_id = metadata
+            .get("mpris:trackid")
+            .and_then(|v| OwnedObjectPath::try_from(v.try_clone().ok()?).ok());
+        let length = metadata
+            .get("mpris:length")
+            .and_then(|v| {
+                i64::try_from(v)
+                    .ok()
+                    .or_else(|| u64::try_from(v).ok().map(|l| l as i64))
+            })
+            .filter(|l| *l > 0)
+            .map(|l| Duration::from_micros(l as u64));
+
+        let status = match player.playback_status().await?.as_str() {
+            "Playing" => PlaybackStatus::Playing,
+            "Paused" => PlaybackStatus::Paused,
+            _ => PlaybackStatus::Stopped,
+        };
+        let can_play = player.can_play().await.unwrap_or_default();
+        let can_pause = player.can_pause().await.unwrap_or_default();
+
+        Ok(Self {
+            name: name.to_string(),
+            identity: media_player
+                .identity()
+                .await
+                .unwrap_or_else(|_| name.trim_start_matches(BUS_PREFIX).to_string()),
+            title,
+            artists,
+            album: string("xesam:album"),
+            art,
+            track_id,
+            length,
+            position: Duration::from_micros(player.position().await.unwrap_or(0).max(0) as u64),
+            updated: Instant::now(),
+            status,
+            volume: player.volume().await.ok(),
+            playlists: playlists(conn, name).await.unwrap_or_default(),
+            can_go_next: player.can_go_next().await.unwrap_or_default(),
+            can_go_previous: player.can_go_previous().await.unwrap_or_default(),
+            can_play_pause: if status == PlaybackStatus::Playing {
+                can_pause
+            } else {
+                can_play
+            },
+            can_seek: player.can_seek().await.unwrap_or_default(),
+            can_raise: media_player.can_raise().await.unwrap_or_default(),
+        })
+    }
+
+    /// Current position, extrapolated while playing.
+    pub fn position(&self) -> Duration {
+        let position = if self.status == PlaybackStatus::Playing {
+            self.position + self.updated.elapsed()
+        } else {
+            self.position
+        };
+        self.length.map_or(position, |length| position.min(length))
+    }
+}
+
+async fn playlists(conn: &Connection, name: &str) -> zbus::Result<Vec<Playlist>> {
+    let playlists = PlaylistsProxy::builder(conn)
+        .destination(name.to_string())?
+        .build()
+        .await?;
+    Ok(playlists
+        .get_playlists(0, 20, "UserDefined", false)
+        .await?
+        .into_iter()
+        .map(|(id, name, _icon)| Playlist { id, name })
+        .collect())
+}
+
+#[derive(Debug, Clone)]
+pub enum Control {
+    PlayPause,
+    Next,
+    Previous,
+    SetPosition(OwnedObjectPath, Duration),
+    SetVolume(f64),
+    ActivatePlaylist(OwnedObjectPath),
+    Raise,
+}
+
+/// Sends `control` to the player with the bus name `name`.
+pub async fn control(name: String, control: Control) -> zbus::Result<()> {
+    let conn = Connection::session().await?;
+    let player = PlayerProxy::builder(&conn)
+        .destination(name.clone())?
+        .build()
+        .await?;
+    match control {
+        Control::PlayPause => player.play_pause().await,
+        Control::Next => player.next().await,
+        Control::Previous => player.previous().await,
+        Control::SetPosition(track_id, position) => {
+            player
+                .set_position(&track_id, position.as_micros() as i64)
+                .await
+        }
+        Control::SetVolume(volume) => player.set_volume(volume).await,
+        Control::ActivatePlaylist(id) => {
+            PlaylistsProxy::builder(&conn)
+                .destination(name)?
+                .build()
+                .await?
+                .activate_playlist(&id)
+                .await
+        }
+        Control::Raise => {
+            MediaPlayer2Proxy::builder(&conn)
+                .destination(name)?
+                .build()
+                .await?
+                .raise()
+                .await
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum MprisUpdate {
+    /// Every player, sorted by bus name.
+    Players(Vec<PlayerStatus>),
+    Finished,
+}
+
+pub fn mpris_subscription<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+) -> iced::Subscription<MprisUpdate> {
+    subscription::channel(id, 50, move |mut output| async move {
+        if let Err(err) = run(&mut output).await {
+            tracing::error!("Failed to monitor mpris players: {}", err);
+        }
+        let _ = output.send(MprisUpdate::Finished).await;
+        futures::future::pending().await
+    })
+}
+
+async fn properties_changed(
+    conn: &Connection,
+    name: &str,
+) -> zbus::Result<PropertiesChangedStream<'static>> {
+    PropertiesProxy::builder(conn)
+        .destination(name.to_string())?
+        .path(OBJECT_PATH)?
+        .build()
+        .await?
+        .receive_properties_changed()
+        .await
+}
+
+async fn run(output: &mut futures::channel::mpsc::Sender<MprisUpdate>) -> zbus::Result<()> {
+    let conn = Connection::session().await?;
+    let dbus = DBusProxy::new(&conn).await?;
+    let mut owner_changed = dbus.receive_name_owner_changed().await?;
+
+    let mut names = dbus
+        .list_names()
+        .await?
+        .into_iter()
+        .map(|name| name.to_string())
+        .filter(|name| name.starts_with(BUS_PREFIX))
+        .collect::<Vec<_>>();
+    names.sort();
+
+    loop {
+        let mut players = Vec::with_capacity(names.len());
+        let mut changes = SelectAll::new();
+        for name in &names {
+            match PlayerStatus::new(&conn, name).await {
+                Ok(status) => players.push(status),
+                Err(err) => tracing::error!("Failed to get status of {}: {}", name, err),
+            }
+            if let Ok(stream) = properties_changed(&conn, name).await {
+                changes.push(stream);
+            }
+        }
+        _ = output.send(MprisUpdate::Players(players)).await;
+
+        // Wait for something that needs the players to be read again. Position
+        // changes are extrapolated, so only other player properties matter.
+        loop {
+            tokio::select! {
+                Some(signal) = owner_changed.next() => {
+                    let Ok(args) = signal.args() else {
+                        continue;
+                    };
+                    let name = args.name().to_string();
+                    if !name.starts_with(BUS_PREFIX) {
+                        continue;
+                    }
+                    names.retain(|n| n != &name);
+                    if args.new_owner().is_some() {
+                        names.push(name);
+                        names.sort();
+                    }
+                    break;
+                }
+                Some(signal) = changes.next(), if !changes.is_empty() => {
+                    if signal.args().map_or(true, |args| {
+                        args.interface_name().as_str() == "org.mpris.MediaPlayer2.Player"
+                    }) {
+                        break;
+                    }
+                }
+                else => return Ok(()),
+            }
+        }
+    }
+}
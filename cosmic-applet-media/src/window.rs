@@ -0,0 +1,501 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    config::{self, MediaConfig},
+    fl,
+    mpris::{control, mpris_subscription, Control, MprisUpdate, PlaybackStatus, PlayerStatus},
+};
+use cosmic::{
+    app::Core,
+    applet::{cosmic_panel_config::PanelAnchor, menu_button, padded_control},
+    cosmic_config::{Config, CosmicConfigEntry},
+    iced::{
+        time,
+        widget::{row, slider},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_widget::{Column, Row},
+    widget::{button, divider, horizontal_space, icon, image, text},
+    Element,
+};
+use cosmic_applet_helper::{
+    decode_file, AppletMessage, CosmicApplet, ImageCache, PanelEvent, Thumbnail,
+};
+
+const ICON: &str = "com.system76.CosmicAppletMedia-symbolic";
+/// Separates the end of a scrolling title from its start.
+const MARQUEE_GAP: &str = "   ·   ";
+/// Album art is shown at 64 by 64, decoded for scales up to 2.
+const ART_SIZE: u32 = 128;
+
+#[derive(Default)]
+pub struct Window {
+    config: MediaConfig,
+    players: Vec<PlayerStatus>,
+    /// Bus name of the player chosen in the popup.
+    selected: Option<String>,
+    /// Characters the panel title has scrolled by.
+    marquee_offset: usize,
+    /// Position while the seek slider is dragged.
+    seeking: Option<Duration>,
+    playlists_expanded: bool,
+    /// Album art of the players, by path.
+    art: ImageCache<PathBuf>,
+    /// Album art being decoded.
+    art_pending: HashSet<PathBuf>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    ConfigChanged(MediaConfig),
+    Mpris(MprisUpdate),
+    Tick,
+    SelectPlayer(String),
+    Control(Control),
+    ControlDone(Result<(), String>),
+    Seek(f64),
+    SeekReleased,
+    Volume(f64),
+    TogglePlaylists,
+    Art(PathBuf, Option<Thumbnail>),
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+impl Window {
+    /// The chosen player, otherwise the one playing, otherwise the first.
+    fn player(&self) -> Option<&PlayerStatus> {
+        self.selected
+            .as_ref()
+            .and_then(|name| self.players.iter().find(|p| &p.name == name))
+            .or_else(|| {
+                self.players
+                    .iter()
+                    .find(|p| p.status == PlaybackStatus::Playing)
+            })
+            .or_else(|| {
+                self.players
+                    .iter()
+                    .find(|p| p.status == PlaybackStatus::Paused)
+            })
+            .or_else(|| self.players.first())
+    }
+
+    fn player_mut(&mut self) -> Option<&mut PlayerStatus> {
+        let name = self.player()?.name.clone();
+        self.players.iter_mut().find(|p| p.name == name)
+    }
+
+    fn control(&self, c: Control) -> Command<Message> {
+        let Some(player) = self.player() else {
+            return Command::none();
+        };
+        Command::perform(control(player.name.clone(), c), |res| {
+            Message::ControlDone(res.map_err(|err| err.to_string()))
+        })
+    }
+
+    /// The part of the title shown on the panel, scrolling if it's too long.
+    fn marquee(&self, title: &str) -> String {
+        let width = self.config.title_width.max(1);
+        if title.chars().count() <= width {
+            return title.to_string();
+        }
+        let cycle = format!("{title}{MARQUEE_GAP}");
+        let len = cycle.chars().count();
+        cycle
+            .chars()
+            .cycle()
+            .skip(self.marquee_offset % len)
+            .take(width)
+            .collect()
+    }
+
+    fn scrolling(&self) -> bool {
+        self.config.show_title
+            && self.player().map_or(false, |p| {
+                p.status == PlaybackStatus::Playing
+                    && p.title
+                        .as_ref()
+                        .map_or(false, |t| t.chars().count() > self.config.title_width)
+            })
+    }
+
+    fn players_view(&self, current: &PlayerStatus) -> Element<Message> {
+        let buttons = self
+            .players
+            .iter()
+            .map(|player| {
+                if player.name == current.name {
+                    button::suggested(&player.identity).into()
+                } else {
+                    button::standard(&player.identity)
+                        .on_press(Message::SelectPlayer(player.name.clone()))
+                        .into()
+                }
+            })
+            .collect();
+        padded_control(Row::with_children(buttons).spacing(8)).into()
+    }
+
+    /// Decodes the album art of the players that isn't yet, and forgets
+    /// that of players gone.
+    fn load_art(&mut self) -> Command<Message> {
+        let paths = self
+            .players
+            .iter()
+            .filter_map(|p| p.art.clone())
+            .collect::<HashSet<_>>();
+        self.art.retain(|path| paths.contains(path));
+        let commands = paths
+            .into_iter()
+            .filter(|path| !self.art.contains(path) && self.art_pending.insert(path.clone()))
+            .map(|path| {
+                let decode_path = path.clone();
+                Command::perform(
+                    tokio::task::spawn_blocking(move || {
+                        decode_file(&decode_path, ART_SIZE, ART_SIZE)
+                    }),
+                    move |art| (Message::Art(path, art.ok().flatten())),
+                )
+            })
+            .collect::<Vec<_>>();
+        Command::batch(commands)
+    }
+
+    fn track_view<'a>(player: &'a PlayerStatus, art: Option<&Thumbnail>) -> Element<'a, Message> {
+        let art: Element<_> = match art {
+            Some(art) => image(art.handle.clone())
+                .width(Length::Fixed(64.0))
+                .height(Length::Fixed(64.0))
+                .into(),
+            None => icon::from_name("audio-x-generic-symbolic")
+                .size(64)
+                .symbolic(true)
+                .into(),
+        };
+        let mut details = Column::new()
+            .spacing(2)
+            .push(text(player.title.clone().unwrap_or_else(|| fl!("unknown-title"))).size(14));
+        if !player.artists.is_empty() {
+            details = details.push(text(player.artists.join(", ")).size(12));
+        }
+        if let Some(album) = &player.album {
+            details = details.push(text(album).size(10));
+        }
+        padded_control(
+            row![art, details]
+                .spacing(12)
+                .align_items(Alignment::Center),
+        )
+        .into()
+    }
+
+    fn seek_view(&self, player: &PlayerStatus) -> Option<Element<Message>> {
+        let length = player.length?;
+        player.track_id.as_ref()?;
+        let position = self.seeking.unwrap_or_else(|| player.position());
+        let mut seek = slider(
+            0.0..=length.as_secs_f64(),
+            position.as_secs_f64(),
+            Message::Seek,
+        );
+        if player.can_seek {
+            seek = seek.on_release(Message::SeekReleased);
+        }
+        Some(
+            padded_control(
+                row![
+                    text(format_duration(position)).size(10),
+                    seek,
+                    text(format_duration(length)).size(10),
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center),
+            )
+            .into(),
+        )
+    }
+
+    fn transport_view(player: &PlayerStatus) -> Element<Message> {
+        let control = |name: &'static str, enabled: bool, c: Control| {
+            button::icon(icon::from_name(name).size(24).symbolic(true))
+                .extra_small()
+                .on_press_maybe(enabled.then_some(Message::Control(c)))
+        };
+        padded_control(
+            row![
+                horizontal_space(Length::Fill),
+                control(
+                    "media-skip-backward-symbolic",
+                    player.can_go_previous,
+                    Control::Previous
+                ),
+                control(
+                    if player.status == PlaybackStatus::Playing {
+                        "media-playback-pause-symbolic"
+                    } else {
+                        "media-playback-start-symbolic"
+                    },
+                    player.can_play_pause,
+                    Control::PlayPause
+                ),
+                control(
+                    "media-skip-forward-symbolic",
+                    player.can_go_next,
+                    Control::Next
+                ),
+                horizontal_space(Length::Fill),
+            ]
+            .spacing(16)
+            .align_items(Alignment::Center),
+        )
+        .into()
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = config::APP_ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config = Config::new(config::APP_ID, MediaConfig::VERSION)
+            .map(|helper| {
+                MediaConfig::get_entry(&helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let window = Self {
+            config,
+            ..Default::default()
+        };
+        (window, Command::none())
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        if event == PanelEvent::PopupOpened {
+            self.playlists_expanded = false;
+        }
+        Command::none()
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        Subscription::batch(vec![
+            mpris_subscription(0).map(Message::Mpris),
+            core.watch_config::<MediaConfig>(config::APP_ID)
+                .map(|u| Message::ConfigChanged(u.config)),
+        ])
+    }
+
+    fn panel_subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        if self.scrolling() {
+            time::every(Duration::from_millis(300)).map(|_| Message::Tick)
+        } else {
+            Subscription::none()
+        }
+    }
+
+    fn popup_subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        // Moves the seek slider along
+        if self
+            .player()
+            .map_or(false, |p| p.status == PlaybackStatus::Playing)
+        {
+            time::every(Duration::from_millis(300)).map(|_| Message::Tick)
+        } else {
+            Subscription::none()
+        }
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::ConfigChanged(config) => {
+                self.config = config;
+            }
+            Message::Mpris(MprisUpdate::Players(players)) => {
+                let title = self.player().and_then(|p| p.title.clone());
+                self.players = players;
+                if self.player().and_then(|p| p.title.clone()) != title {
+                    self.marquee_offset = 0;
+                }
+                return self.load_art();
+            }
+            Message::Mpris(MprisUpdate::Finished) => {
+                self.players.clear();
+                self.art.clear();
+            }
+            Message::Art(path, art) => {
+                self.art_pending.remove(&path);
+                let shown = self.players.iter().any(|p| p.art.as_ref() == Some(&path));
+                if let Some(art) = art.filter(|_| shown) {
+                    self.art.insert(path, art);
+                }
+            }
+            Message::Tick => {
+                if self.scrolling() {
+                    self.marquee_offset = self.marquee_offset.wrapping_add(1);
+                }
+            }
+            Message::SelectPlayer(name) => {
+                self.selected = Some(name);
+                self.marquee_offset = 0;
+            }
+            Message::Control(c) => {
+                return self.control(c);
+            }
+            Message::ControlDone(res) => {
+                if let Err(err) = res {
+                    tracing::error!("Failed to control player: {}", err);
+                }
+            }
+            Message::Seek(position) => {
+                self.seeking = Some(Duration::from_secs_f64(position.max(0.0)));
+            }
+            Message::SeekReleased => {
+                let Some(position) = self.seeking.take() else {
+                    return Command::none();
+                };
+                let Some(player) = self.player_mut() else {
+                    return Command::none();
+                };
+                let Some(track_id) = player.track_id.clone() else {
+                    return Command::none();
+                };
+                player.position = position;
+                player.updated = Instant::now();
+                return self.control(Control::SetPosition(track_id, position));
+            }
+            Message::Volume(volume) => {
+                let volume = volume / 100.0;
+                if let Some(player) = self.player_mut() {
+                    player.volume = Some(volume);
+                }
+                return self.control(Control::SetVolume(volume));
+            }
+            Message::TogglePlaylists => {
+                self.playlists_expanded = !self.playlists_expanded;
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let btn = core
+            .applet
+            .icon_button(ICON)
+            .on_press(AppletMessage::TogglePopup)
+            .into();
+
+        let title = self.player().and_then(|p| p.title.as_deref());
+        let (Some(title), true) = (title, self.config.show_title) else {
+            return btn;
+        };
+        match core.applet.anchor {
+            PanelAnchor::Left | PanelAnchor::Right => btn,
+            PanelAnchor::Top | PanelAnchor::Bottom => {
+                Row::with_children(vec![btn, text(self.marquee(title)).size(12).into()])
+                    .align_items(Alignment::Center)
+                    .spacing(4)
+                    .into()
+            }
+        }
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let Some(player) = self.player() else {
+            return Column::new()
+                .padding([8, 0])
+                .push(padded_control(text(fl!("no-players")).size(14)))
+                .into();
+        };
+
+        let mut content = Vec::new();
+        if self.players.len() > 1 {
+            content.push(self.players_view(player));
+            content.push(padded_control(divider::horizontal::default()).into());
+        }
+        content.push(Self::track_view(
+            player,
+            player.art.as_ref().and_then(|path| self.art.get(path)),
+        ));
+        content.extend(self.seek_view(player));
+        content.push(Self::transport_view(player));
+        if let Some(volume) = player.volume {
+            content.push(
+                padded_control(
+                    row![
+                        icon::from_name("audio-volume-high-symbolic")
+                            .size(24)
+                            .symbolic(true),
+                        slider(0.0..=100.0, volume * 100.0, Message::Volume),
+                    ]
+                    .spacing(12)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+        }
+
+        if !player.playlists.is_empty() {
+            content.push(padded_control(divider::horizontal::default()).into());
+            content.push(
+                menu_button(
+                    row![
+                        text(fl!("playlists")).size(14).width(Length::Fill),
+                        icon::from_name(if self.playlists_expanded {
+                            "go-up-symbolic"
+                        } else {
+                            "go-down-symbolic"
+                        })
+                        .size(16)
+                        .symbolic(true),
+                    ]
+                    .align_items(Alignment::Center),
+                )
+                .on_press(Message::TogglePlaylists)
+                .into(),
+            );
+            if self.playlists_expanded {
+                for playlist in &player.playlists {
+                    content.push(
+                        menu_button(text(&playlist.name).size(14))
+                            .on_press(Message::Control(Control::ActivatePlaylist(
+                                playlist.id.clone(),
+                            )))
+                            .into(),
+                    );
+                }
+            }
+        }
+
+        if player.can_raise {
+            content.push(padded_control(divider::horizontal::default()).into());
+            content.push(
+                menu_button(text(fl!("show-player", player = player.identity.as_str())).size(14))
+                    .on_press(Message::Control(Control::Raise))
+                    .into(),
+            );
+        }
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+}
@@ -0,0 +1,5 @@
+mute-all = Mute all microphones
+no-microphones = No microphones found
+sync-led = Keyboard mute light
+sync-led-description = Where the keyboard has one the user may control
+disconnected = Not connected to the sound server
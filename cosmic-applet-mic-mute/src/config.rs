@@ -0,0 +1,20 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
+pub const APP_ID: &str = "com.system76.CosmicAppletMicMute";
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, CosmicConfigEntry)]
+#[version = 1]
+pub struct MicMuteConfig {
+    /// Light the keyboard's microphone mute LED while muted.
+    pub sync_led: bool,
+}
+
+impl Default for MicMuteConfig {
+    fn default() -> Self {
+        Self { sync_led: true }
+    }
+}
@@ -0,0 +1,21 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! The keyboard's microphone mute LED.
+
+use std::fs;
+
+/// Lights the microphone mute LED, if there's one the user may control.
+pub fn set(on: bool) {
+    let Ok(leds) = fs::read_dir("/sys/class/leds") else {
+        return;
+    };
+    for led in leds.flatten() {
+        if !led.file_name().to_string_lossy().ends_with("::micmute") {
+            continue;
+        }
+        if let Err(err) = fs::write(led.path().join("brightness"), if on { "1" } else { "0" }) {
+            tracing::debug!("Failed to set {:?}: {}", led.path(), err);
+        }
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::{
+    config::{self, MicMuteConfig},
+    fl, led,
+};
+use cosmic::{
+    app::Core,
+    applet::padded_control,
+    cosmic_config::{Config, CosmicConfigEntry},
+    iced::{
+        widget::{column, mouse_area, row},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_core::{Background, Border, Color, Shadow},
+    iced_widget::Column,
+    widget::{container, divider, icon, text, toggler},
+    Element, Theme,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet};
+use cosmic_pulse_client as pulse;
+
+const ICON: &str = "audio-input-microphone-symbolic";
+const MUTED_ICON: &str = "microphone-sensitivity-muted-symbolic";
+
+#[derive(Default)]
+pub struct Window {
+    config: MicMuteConfig,
+    config_helper: Option<Config>,
+    pulse: Option<pulse::Connection>,
+    audio: pulse::Model,
+    error: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    ConfigChanged(MicMuteConfig),
+    Pulse(pulse::Event),
+    PulseChanged(pulse::Facility),
+    Toggle,
+    SyncLed(bool),
+}
+
+fn muted_style(theme: &Theme) -> container::Appearance {
+    let cosmic = theme.cosmic();
+    container::Appearance {
+        text_color: None,
+        background: Some(Background::Color(cosmic.destructive_color().into())),
+        border: Border {
+            radius: cosmic.radius_xl().into(),
+            width: 0.0,
+            color: Color::TRANSPARENT,
+        },
+        shadow: Shadow::default(),
+        icon_color: None,
+    }
+}
+
+impl Window {
+    /// Microphones and other inputs, leaving out monitors of outputs.
+    fn sources(&self) -> impl Iterator<Item = &pulse::DeviceInfo> {
+        self.audio.sources.iter().filter(|source| {
+            !source
+                .name
+                .as_ref()
+                .map_or(false, |name| name.ends_with(".monitor"))
+        })
+    }
+
+    /// Muted only once every source is, so a new microphone isn't missed.
+    fn muted(&self) -> bool {
+        self.sources().next().is_some() && self.sources().all(|source| source.mute)
+    }
+
+    fn refresh(&mut self) {
+        if let Some(conn) = self.pulse.as_mut().filter(|_| self.audio.connected) {
+            conn.send(pulse::Message::GetSources);
+        }
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = config::APP_ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::APP_ID, MicMuteConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                MicMuteConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let window = Self {
+            config,
+            config_helper,
+            ..Default::default()
+        };
+        (window, Command::none())
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        Subscription::batch(vec![
+            pulse::connect().map(Message::Pulse),
+            pulse::server_events(0).map(Message::PulseChanged),
+            core.watch_config::<MicMuteConfig>(config::APP_ID)
+                .map(|u| Message::ConfigChanged(u.config)),
+        ])
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::ConfigChanged(config) => {
+                self.config = config;
+            }
+            Message::Pulse(event) => {
+                let was_muted = self.muted();
+                self.audio.update(&event);
+                match event {
+                    pulse::Event::Init(mut conn) => {
+                        conn.send(pulse::Message::UpdateConnection);
+                        self.pulse = Some(conn);
+                    }
+                    pulse::Event::Connected => self.refresh(),
+                    pulse::Event::Disconnected => {
+                        tracing::error!("Disconnected from the audio server");
+                        self.error = Some(fl!("disconnected"));
+                    }
+                    pulse::Event::MessageReceived(pulse::Message::SetSources(_)) => {
+                        self.error = None;
+                        if self.config.sync_led && self.muted() != was_muted {
+                            led::set(self.muted());
+                        }
+                    }
+                    pulse::Event::MessageReceived(_) => {}
+                }
+            }
+            Message::PulseChanged(pulse::Facility::Source | pulse::Facility::Server) => {
+                self.refresh();
+            }
+            Message::PulseChanged(_) => {}
+            Message::Toggle => {
+                let muted = !self.muted();
+                let names = self
+                    .sources()
+                    .filter(|source| source.mute != muted)
+                    .filter_map(|source| source.name.clone())
+                    .collect::<Vec<_>>();
+                if let Some(conn) = self.pulse.as_mut() {
+                    for name in names {
+                        conn.send(pulse::Message::SetSourceMuteByName(name, muted));
+                    }
+                }
+                self.refresh();
+            }
+            Message::SyncLed(sync_led) => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_sync_led(helper, sync_led) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+                led::set(sync_led && self.muted());
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        // Clicking mutes, the sources are a right click away
+        let btn = core
+            .applet
+            .icon_button(if self.muted() { MUTED_ICON } else { ICON })
+            .on_press(Message::Toggle.into());
+        let btn: Element<_> = if self.muted() {
+            container(btn)
+                .style(<Theme as container::StyleSheet>::Style::Custom(Box::new(
+                    muted_style,
+                )))
+                .into()
+        } else {
+            btn.into()
+        };
+        mouse_area(btn)
+            .on_right_press(AppletMessage::TogglePopup)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = vec![
+            padded_control(
+                toggler(Some(fl!("mute-all")), self.muted(), |_| Message::Toggle)
+                    .text_size(14)
+                    .width(Length::Fill),
+            )
+            .into(),
+            padded_control(divider::horizontal::default()).into(),
+        ];
+
+        if self.sources().next().is_none() {
+            content.push(padded_control(text(fl!("no-microphones")).size(14)).into());
+        }
+        for source in self.sources() {
+            content.push(
+                padded_control(
+                    row![
+                        icon::from_name(if source.mute { MUTED_ICON } else { ICON })
+                            .size(16)
+                            .symbolic(true),
+                        text(source.description.as_deref().unwrap_or_default())
+                            .size(14)
+                            .width(Length::Fill),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+        }
+
+        content.push(padded_control(divider::horizontal::default()).into());
+        content.push(
+            padded_control(
+                column![
+                    toggler(
+                        Some(fl!("sync-led")),
+                        self.config.sync_led,
+                        Message::SyncLed
+                    )
+                    .text_size(14)
+                    .width(Length::Fill),
+                    text(fl!("sync-led-description")).size(10),
+                ]
+                .spacing(2),
+            )
+            .into(),
+        );
+
+        if let Some(err) = &self.error {
+            content.push(padded_control(text(err).size(10)).into());
+        }
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+}
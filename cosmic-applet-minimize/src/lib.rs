@@ -8,34 +8,34 @@ pub(crate) mod window_image;
 
 use crate::localize::localize;
 use cosmic::{
-    app::Command,
+    app::Core,
     applet::cosmic_panel_config::PanelAnchor,
     cctk::{
         cosmic_protocols::toplevel_info::v1::client::zcosmic_toplevel_handle_v1::ZcosmicToplevelHandleV1,
         sctk::reexports::calloop, toplevel_info::ToplevelInfo,
     },
     desktop::DesktopEntryData,
-    iced::{widget::text, Length, Subscription},
+    iced::{
+        widget::{row, text},
+        Command, Length, Subscription,
+    },
 };
 
-use cosmic::{
-    iced_style::application,
-    iced_widget::{Column, Row},
-};
+use cosmic::iced_widget::{Column, Row};
 
-use cosmic::{widget::tooltip, Element, Theme};
+use cosmic::{widget::tooltip, Element};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet};
 use wayland_subscription::{
     ToplevelRequest, ToplevelUpdate, WaylandImage, WaylandRequest, WaylandUpdate,
 };
 
 pub fn run() -> cosmic::iced::Result {
     localize();
-    cosmic::applet::run::<Minimize>(true, ())
+    cosmic_applet_helper::run_applet::<Minimize>()
 }
 
 #[derive(Default)]
 struct Minimize {
-    core: cosmic::app::Core,
     apps: Vec<(
         ZcosmicToplevelHandleV1,
         ToplevelInfo,
@@ -51,35 +51,20 @@ enum Message {
     Activate(ZcosmicToplevelHandleV1),
 }
 
-impl cosmic::Application for Minimize {
+impl CosmicApplet for Minimize {
     type Message = Message;
-    type Executor = cosmic::SingleThreadExecutor;
-    type Flags = ();
     const APP_ID: &'static str = "com.system76.CosmicAppletMinimize";
 
-    fn init(core: cosmic::app::Core, _flags: ()) -> (Self, Command<Message>) {
+    fn init(_core: &Core) -> (Self, Command<Message>) {
         (
             Self {
-                core,
                 ..Default::default()
             },
             Command::none(),
         )
     }
 
-    fn core(&self) -> &cosmic::app::Core {
-        &self.core
-    }
-
-    fn core_mut(&mut self) -> &mut cosmic::app::Core {
-        &mut self.core
-    }
-
-    fn style(&self) -> Option<<Theme as application::StyleSheet>::Style> {
-        Some(cosmic::applet::style())
-    }
-
-    fn update(&mut self, message: Message) -> Command<Message> {
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
         match message {
             Message::Wayland(update) => match update {
                 WaylandUpdate::Init(tx) => {
@@ -126,14 +111,14 @@ impl cosmic::Application for Minimize {
         Command::none()
     }
 
-    fn subscription(&self) -> Subscription<Message> {
+    fn subscription(&self, _core: &Core) -> Subscription<Message> {
         wayland_subscription::wayland_subscription().map(Message::Wayland)
     }
 
-    fn view(&self) -> Element<Message> {
-        let (width, _) = self.core.applet.suggested_size(false);
-        let padding = self.core.applet.suggested_padding(false);
-        let theme = self.core.system_theme().cosmic();
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let (width, _) = core.applet.suggested_size(false);
+        let padding = core.applet.suggested_padding(false);
+        let theme = core.system_theme().cosmic();
         let space_xxs = theme.space_xxs();
         let icon_buttons = self.apps.iter().map(|(handle, _, data, img)| {
             tooltip(
@@ -141,14 +126,14 @@ impl cosmic::Application for Minimize {
                     img.clone(),
                     &data.icon,
                     width as f32,
-                    Message::Activate(handle.clone()),
+                    Message::Activate(handle.clone()).into(),
                     padding,
                 )),
                 data.name.clone(),
                 // tooltip::Position::FollowCursor,
                 // FIXME tooltip fails to appear when created as indicated in design
                 // maybe it should be a subsurface
-                match self.core.applet.anchor {
+                match core.applet.anchor {
                     PanelAnchor::Left => tooltip::Position::Right,
                     PanelAnchor::Right => tooltip::Position::Left,
                     PanelAnchor::Top => tooltip::Position::Bottom,
@@ -163,10 +148,7 @@ impl cosmic::Application for Minimize {
         // TODO optional dividers on ends if detects app list neighbor
         // not sure the best way to tell if there is an adjacent app-list
 
-        if matches!(
-            self.core.applet.anchor,
-            PanelAnchor::Top | PanelAnchor::Bottom
-        ) {
+        if matches!(core.applet.anchor, PanelAnchor::Top | PanelAnchor::Bottom) {
             Row::with_children(icon_buttons)
                 .align_items(cosmic::iced_core::Alignment::Center)
                 .height(Length::Shrink)
@@ -182,4 +164,8 @@ impl cosmic::Application for Minimize {
                 .into()
         }
     }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        row![].into()
+    }
 }
@@ -0,0 +1,7 @@
+input = Input
+unsupported = This monitor doesn't support DDC/CI
+no-i2c = The i2c-dev kernel module isn't loaded
+permission-denied = No permission to control monitors. Install the udev rule, or add yourself to the i2c group.
+searching = Searching for monitors...
+no-monitors = No external monitors support DDC/CI
+refresh = Refresh
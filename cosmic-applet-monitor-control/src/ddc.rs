@@ -0,0 +1,170 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! External monitors over DDC/CI, through `/dev/i2c-*`. DDC/CI is slow, so
+//! monitors are handled on their own thread.
+
+use std::{fmt::Debug, fs, hash::Hash, sync::mpsc, thread};
+
+use cosmic::{
+    iced::{self, subscription},
+    iced_futures::futures::{self, SinkExt},
+};
+use ddc_hi::{Ddc, Display};
+use rustix::fs::Access;
+
+/// VCP feature codes, from the MCCS standard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Brightness = 0x10,
+    Contrast = 0x12,
+    InputSource = 0x60,
+}
+
+/// Common input source values, as most monitors don't list theirs.
+pub const INPUTS: [(u16, &str); 6] = [
+    (0x0f, "DisplayPort 1"),
+    (0x10, "DisplayPort 2"),
+    (0x11, "HDMI 1"),
+    (0x12, "HDMI 2"),
+    (0x1b, "USB-C"),
+    (0x03, "DVI"),
+];
+
+#[derive(Debug, Clone)]
+pub struct Monitor {
+    pub name: String,
+    /// Current and maximum values.
+    pub brightness: Option<(u16, u16)>,
+    pub contrast: Option<(u16, u16)>,
+    pub input: Option<u16>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    Granted,
+    /// The `i2c-dev` module isn't loaded.
+    NoDevices,
+    /// The udev rule granting access isn't installed.
+    Denied,
+}
+
+/// Whether the user may open any i2c device.
+pub fn permission() -> Permission {
+    let Ok(entries) = fs::read_dir("/dev") else {
+        return Permission::NoDevices;
+    };
+    let devices = entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("i2c-"))
+        .map(|entry| entry.path())
+        .collect::<Vec<_>>();
+    if devices.is_empty() {
+        Permission::NoDevices
+    } else if devices
+        .iter()
+        .any(|path| rustix::fs::access(path, Access::READ_OK | Access::WRITE_OK).is_ok())
+    {
+        Permission::Granted
+    } else {
+        Permission::Denied
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Request {
+    Refresh,
+    /// Sets a feature of the monitor at an index.
+    Set(usize, Feature, u16),
+}
+
+#[derive(Debug, Clone)]
+pub enum DdcUpdate {
+    Init(mpsc::Sender<Request>),
+    Permission(Permission),
+    Monitors(Vec<Monitor>),
+    Finished,
+}
+
+pub fn ddc_subscription<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+) -> iced::Subscription<DdcUpdate> {
+    subscription::channel(id, 50, move |mut output| async move {
+        let (tx, rx) = mpsc::channel();
+        _ = output.send(DdcUpdate::Init(tx)).await;
+        let worker_output = output.clone();
+        if let Err(err) = thread::Builder::new()
+            .name("ddc".to_string())
+            .spawn(move || worker(rx, worker_output))
+        {
+            tracing::error!("Failed to start DDC thread: {}", err);
+            _ = output.send(DdcUpdate::Finished).await;
+        }
+        futures::future::pending().await
+    })
+}
+
+fn read(display: &mut Display, feature: Feature) -> Option<(u16, u16)> {
+    match display.handle.get_vcp_feature(feature as u8) {
+        Ok(value) => Some((value.value(), value.maximum())),
+        Err(err) => {
+            tracing::debug!(
+                "Failed to read {:?} of {}: {}",
+                feature,
+                display.info.id,
+                err
+            );
+            None
+        }
+    }
+}
+
+fn monitor(display: &mut Display) -> Monitor {
+    let name = display
+        .info
+        .model_name
+        .clone()
+        .or_else(|| display.info.manufacturer_id.clone())
+        .unwrap_or_else(|| display.info.id.clone());
+    Monitor {
+        name,
+        brightness: read(display, Feature::Brightness),
+        contrast: read(display, Feature::Contrast),
+        // The input is in the low byte
+        input: read(display, Feature::InputSource).map(|(value, _)| value & 0xff),
+    }
+}
+
+fn worker(rx: mpsc::Receiver<Request>, mut output: futures::channel::mpsc::Sender<DdcUpdate>) {
+    let mut displays = Vec::new();
+    // Refresh on startup, then whenever asked
+    let requests = std::iter::once(Request::Refresh).chain(rx.iter());
+    for request in requests {
+        match request {
+            Request::Refresh => {
+                let permission = permission();
+                _ = output.try_send(DdcUpdate::Permission(permission));
+                if permission != Permission::Granted {
+                    continue;
+                }
+                displays = Display::enumerate();
+            }
+            Request::Set(i, feature, value) => {
+                let Some(display) = displays.get_mut(i) else {
+                    continue;
+                };
+                if let Err(err) = display.handle.set_vcp_feature(feature as u8, value) {
+                    tracing::error!(
+                        "Failed to set {:?} of {}: {}",
+                        feature,
+                        display.info.id,
+                        err
+                    );
+                }
+            }
+        }
+        let monitors = displays.iter_mut().map(monitor).collect();
+        _ = output.try_send(DdcUpdate::Monitors(monitors));
+    }
+    _ = output.try_send(DdcUpdate::Finished);
+}
@@ -0,0 +1,256 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::sync::mpsc;
+
+use crate::{
+    ddc::{ddc_subscription, DdcUpdate, Feature, Monitor, Permission, Request, INPUTS},
+    fl,
+};
+use cosmic::{
+    app::Core,
+    applet::{menu_button, padded_control},
+    iced::{
+        alignment::Horizontal,
+        widget::{row, slider},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_widget::Column,
+    widget::{container, divider, dropdown, icon, text},
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent};
+
+const ID: &str = "com.system76.CosmicAppletMonitorControl";
+const ICON: &str = "com.system76.CosmicAppletMonitorControl-symbolic";
+
+pub struct Window {
+    monitors: Vec<Monitor>,
+    permission: Permission,
+    /// Waiting for monitors to be listed.
+    refreshing: bool,
+    inputs: Vec<String>,
+    ddc_tx: Option<mpsc::Sender<Request>>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    Ddc(DdcUpdate),
+    Refresh,
+    /// Previews a feature of the monitor at an index while dragging.
+    Slide(usize, Feature, u16),
+    /// Writes the previewed value, once the slider is released.
+    Release(usize, Feature),
+    Input(usize, usize),
+}
+
+impl Window {
+    fn send(&self, request: Request) {
+        if let Some(tx) = self.ddc_tx.as_ref() {
+            let _ = tx.send(request);
+        } else {
+            tracing::error!("DDC tx is None");
+        }
+    }
+
+    fn value_mut(&mut self, i: usize, feature: Feature) -> Option<&mut (u16, u16)> {
+        let monitor = self.monitors.get_mut(i)?;
+        match feature {
+            Feature::Brightness => monitor.brightness.as_mut(),
+            Feature::Contrast => monitor.contrast.as_mut(),
+            Feature::InputSource => None,
+        }
+    }
+
+    fn slider_view(
+        i: usize,
+        feature: Feature,
+        icon_name: &'static str,
+        (value, max): (u16, u16),
+    ) -> Element<'static, Message> {
+        padded_control(
+            row![
+                icon::from_name(icon_name).size(24).symbolic(true),
+                slider(0..=max.max(1), value, move |v| Message::Slide(
+                    i, feature, v
+                ))
+                .on_release(Message::Release(i, feature)),
+                text(format!(
+                    "{}%",
+                    u32::from(value) * 100 / u32::from(max.max(1))
+                ))
+                .size(16)
+                .width(Length::Fixed(48.0))
+                .horizontal_alignment(Horizontal::Right),
+            ]
+            .spacing(12)
+            .align_items(Alignment::Center),
+        )
+        .into()
+    }
+
+    fn monitor_view(&self, i: usize, monitor: &Monitor) -> Vec<Element<Message>> {
+        let mut content = vec![padded_control(text(&monitor.name).size(14)).into()];
+        if let Some(brightness) = monitor.brightness {
+            content.push(Self::slider_view(
+                i,
+                Feature::Brightness,
+                "display-brightness-symbolic",
+                brightness,
+            ));
+        }
+        if let Some(contrast) = monitor.contrast {
+            content.push(Self::slider_view(
+                i,
+                Feature::Contrast,
+                "preferences-color-symbolic",
+                contrast,
+            ));
+        }
+        if let Some(input) = monitor.input {
+            let selected = INPUTS.iter().position(|(value, _)| *value == input);
+            content.push(
+                padded_control(
+                    row![
+                        text(fl!("input")).size(14).width(Length::Fill),
+                        container(dropdown(&self.inputs, selected, move |input| {
+                            Message::Input(i, input)
+                        })),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+        }
+        if monitor.brightness.is_none() && monitor.contrast.is_none() && monitor.input.is_none() {
+            content.push(padded_control(text(fl!("unsupported")).size(10)).into());
+        }
+        content
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let window = Self {
+            monitors: Vec::new(),
+            permission: Permission::Granted,
+            refreshing: true,
+            inputs: INPUTS.iter().map(|(_, name)| name.to_string()).collect(),
+            ddc_tx: None,
+        };
+        (window, Command::none())
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        if event == PanelEvent::PopupOpened {
+            // Monitors may have been plugged in or changed by hand
+            self.refreshing = true;
+            self.send(Request::Refresh);
+        }
+        Command::none()
+    }
+
+    fn subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        ddc_subscription(0).map(Message::Ddc)
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::Ddc(update) => match update {
+                DdcUpdate::Init(tx) => {
+                    self.ddc_tx = Some(tx);
+                }
+                DdcUpdate::Permission(permission) => {
+                    self.permission = permission;
+                    if permission != Permission::Granted {
+                        self.refreshing = false;
+                        self.monitors.clear();
+                    }
+                }
+                DdcUpdate::Monitors(monitors) => {
+                    self.refreshing = false;
+                    self.monitors = monitors;
+                }
+                DdcUpdate::Finished => {
+                    self.ddc_tx = None;
+                }
+            },
+            Message::Refresh => {
+                self.refreshing = true;
+                self.send(Request::Refresh);
+            }
+            Message::Slide(i, feature, value) => {
+                if let Some((current, _)) = self.value_mut(i, feature) {
+                    *current = value;
+                }
+            }
+            Message::Release(i, feature) => {
+                if let Some((value, _)) = self.value_mut(i, feature).copied() {
+                    self.send(Request::Set(i, feature, value));
+                }
+            }
+            Message::Input(i, input) => {
+                if let (Some(monitor), Some((value, _))) =
+                    (self.monitors.get_mut(i), INPUTS.get(input))
+                {
+                    monitor.input = Some(*value);
+                    self.send(Request::Set(i, Feature::InputSource, *value));
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        core.applet
+            .icon_button(ICON)
+            .on_press(AppletMessage::TogglePopup)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = Vec::new();
+        match self.permission {
+            Permission::NoDevices => {
+                content.push(padded_control(text(fl!("no-i2c")).size(14)).into());
+            }
+            Permission::Denied => {
+                content.push(padded_control(text(fl!("permission-denied")).size(14)).into());
+            }
+            Permission::Granted if self.monitors.is_empty() => {
+                content.push(
+                    padded_control(
+                        text(if self.refreshing {
+                            fl!("searching")
+                        } else {
+                            fl!("no-monitors")
+                        })
+                        .size(14),
+                    )
+                    .into(),
+                );
+            }
+            Permission::Granted => {
+                for (i, monitor) in self.monitors.iter().enumerate() {
+                    if i > 0 {
+                        content.push(padded_control(divider::horizontal::default()).into());
+                    }
+                    content.extend(self.monitor_view(i, monitor));
+                }
+            }
+        }
+
+        content.push(padded_control(divider::horizontal::default()).into());
+        content.push(
+            menu_button(text(fl!("refresh")).size(14))
+                .on_press_maybe((!self.refreshing).then_some(Message::Refresh))
+                .into(),
+        );
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+}
@@ -17,4 +17,41 @@ enter-password = Enter the password or encryption key
 router-wps-button = You can also connect by pressing the "WPS" button on the router
 unable-to-connect = Unable to connect to network
 check-wifi-connection = Make sure Wi-Fi is connected to the internet and the password is correct
-reset = Reset
\ No newline at end of file
+reset = Reset
+roam-automatically = Roam automatically
+proxy = Proxy
+proxy-none = No proxy
+proxy-auto = Automatic
+proxy-manual = Manual
+proxy-pac-url = Configuration URL
+proxy-http = HTTP
+proxy-socks = SOCKS
+proxy-host = Host
+proxy-port = Port
+proxy-apply = Apply
+metered-connection = Metered connection
+top-talkers = Top talkers
+no-network-activity = No network activity
+ipv6-settings = IPv6
+ipv6-auto = Automatic
+ipv6-dhcp = DHCP only
+ipv6-manual = Manual
+ipv6-link-local = Link-local only
+ipv6-shared = Shared to other computers
+ipv6-ignore = Not configured
+ipv6-disabled = Disabled
+ipv6-stable-privacy = Private addresses
+ipv6-eui64 = Hardware addresses
+ipv6-enabled = Use IPv6
+ipv6-privacy = Privacy extensions
+test-speed = Test speed
+speed-test-downloading = Measuring download…
+speed-test-uploading = Measuring upload…
+speed-test-failed = Speed test failed
+speed-test-metered = Unavailable on metered connections
+disconnect = Disconnect
+group-owner = Group owner
+wifi-direct = Wi-Fi Direct
+wifi-direct-searching = Searching for devices…
+wifi-direct-unavailable = Wi-Fi Direct isn’t available
+wifi-direct-group-owner = This device owns the group
@@ -1,29 +1,27 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, time::Duration};
 
 use cosmic::{
-    app::Command,
+    app::Core,
     applet::{
+        cosmic_panel_config::PanelAnchor,
         menu_button, menu_control_padding, padded_control,
         token::subscription::{activation_token_subscription, TokenRequest, TokenUpdate},
     },
     cctk::sctk::reexports::calloop,
+    cosmic_config::{Config, CosmicConfigEntry},
     iced::{
-        wayland::popup::{destroy_popup, get_popup},
         widget::{column, row},
-        Alignment, Length, Subscription,
+        Alignment, Background, Border, Command, Length, Subscription,
     },
-    iced_runtime::core::{
-        alignment::{Horizontal, Vertical},
-        layout::Limits,
-        window,
-    },
-    iced_style::application,
-    iced_widget::Row,
+    iced_runtime::core::alignment::{Horizontal, Vertical},
+    iced_widget::{vertical_space, Row},
     widget::{
-        button, container, divider, icon, icon::from_name, scrollable, text, text_input, Column,
+        button, container, divider, horizontal_space, icon, icon::from_name, scrollable, text,
+        text_input, toggler, Column,
     },
     Element, Theme,
 };
+use cosmic_applet_helper::{format_rate, AppletMessage, CosmicApplet, PanelEvent};
 use cosmic_dbus_networkmanager::interface::enums::{
     ActiveConnectionState, DeviceState, NmConnectivityState,
 };
@@ -32,18 +30,29 @@ use cosmic_time::{anim, chain, id, once_cell::sync::Lazy, Instant, Timeline};
 use futures::channel::mpsc::UnboundedSender;
 use zbus::Connection;
 
+use cosmic_nm_client::{
+    active_conns::active_conns_subscription,
+    available_wifi::AccessPoint,
+    current_networks::ActiveConnectionInfo,
+    devices::devices_subscription,
+    ipv6::{Ipv6Change, Ipv6Method},
+    metered, network_manager_subscription,
+    proxy::{self, ProxyKind, ProxyMode, ProxySettings},
+    wireless_enabled::wireless_enabled_subscription,
+    NetworkManagerEvent, NetworkManagerRequest, NetworkManagerState,
+};
+
 use crate::{
-    config, fl,
-    network_manager::{
-        active_conns::active_conns_subscription, available_wifi::AccessPoint,
-        current_networks::ActiveConnectionInfo, devices::devices_subscription,
-        network_manager_subscription, wireless_enabled::wireless_enabled_subscription,
-        NetworkManagerEvent, NetworkManagerRequest, NetworkManagerState,
-    },
+    config::{self, NetworkAppletConfig},
+    fl,
+    metered::metered_subscription,
+    p2p::{self, p2p_subscription, P2pPeer, P2pRole, P2pUpdate},
+    speed_test::{speed_test_subscription, SpeedTestUpdate},
+    talkers::{talkers_subscription, Talker},
 };
 
 pub fn run() -> cosmic::iced::Result {
-    cosmic::applet::run::<CosmicNetworkApplet>(false, ())
+    cosmic_applet_helper::run_applet::<CosmicNetworkApplet>()
 }
 
 #[derive(Debug, Clone)]
@@ -85,12 +94,13 @@ impl From<NewConnectionState> for AccessPoint {
 
 static WIFI: Lazy<id::Toggler> = Lazy::new(id::Toggler::unique);
 static AIRPLANE_MODE: Lazy<id::Toggler> = Lazy::new(id::Toggler::unique);
+static METERED: Lazy<id::Toggler> = Lazy::new(id::Toggler::unique);
 
 #[derive(Default)]
 struct CosmicNetworkApplet {
-    core: cosmic::app::Core,
     icon_name: String,
-    popup: Option<window::Id>,
+    /// Whether the popup is open.
+    popup_open: bool,
     nm_state: NetworkManagerState,
     // UI state
     nm_sender: Option<UnboundedSender<NetworkManagerRequest>>,
@@ -101,6 +111,29 @@ struct CosmicNetworkApplet {
     toggle_wifi_ctr: u128,
     token_tx: Option<calloop::channel::Sender<TokenRequest>>,
     failed_known_ssids: HashSet<String>,
+    /// The network whose access points are listed.
+    expanded_bssids: Option<String>,
+    /// The connection whose IPv6 settings are shown.
+    expanded_ipv6: Option<String>,
+    show_proxy: bool,
+    proxy: ProxySettings,
+    /// The port as typed, as it may not be a valid one yet.
+    proxy_port: String,
+    /// Whether the primary connection is metered.
+    metered: bool,
+    show_talkers: bool,
+    /// Processes using the network the most, while they're shown.
+    talkers: Vec<Talker>,
+    config: NetworkAppletConfig,
+    /// Counts speed tests, so each one starts a new subscription.
+    speed_test_run: u32,
+    /// The progress or result of the last speed test.
+    speed_test: Option<SpeedTestUpdate>,
+    show_p2p: bool,
+    /// Wi-Fi Direct peers, while they're searched for.
+    p2p: Option<P2pUpdate>,
+    /// The peer being connected to.
+    p2p_connecting: Option<String>,
 }
 
 fn wifi_icon(strength: u8) -> &'static str {
@@ -115,7 +148,449 @@ fn wifi_icon(strength: u8) -> &'static str {
     }
 }
 
+fn frequency_band(frequency: u32) -> &'static str {
+    if frequency >= 5925 {
+        "6 GHz"
+    } else if frequency >= 5000 {
+        "5 GHz"
+    } else {
+        "2.4 GHz"
+    }
+}
+
 impl CosmicNetworkApplet {
+    /// Button listing the access points of a network with several, for
+    /// pinning its connection to one.
+    fn bssids_button(&self, ssid: &str) -> Option<Element<Message>> {
+        let ap = self
+            .nm_state
+            .wireless_access_points
+            .iter()
+            .find(|ap| ap.ssid == ssid)?;
+        if ap.bssids.len() < 2 && ap.pinned_bssid.is_none() {
+            return None;
+        }
+        let icon_name = if self.expanded_bssids.as_deref() == Some(ssid) {
+            "go-down-symbolic"
+        } else {
+            "go-next-symbolic"
+        };
+        Some(
+            button::icon(from_name(icon_name).size(16))
+                .icon_size(16)
+                .on_press(Message::ToggleBssids(ssid.to_string()))
+                .into(),
+        )
+    }
+
+    fn bssid_list(&self, ssid: &str, connected_bssid: Option<&str>) -> Option<Element<Message>> {
+        if self.expanded_bssids.as_deref() != Some(ssid) {
+            return None;
+        }
+        let ap = self
+            .nm_state
+            .wireless_access_points
+            .iter()
+            .find(|ap| ap.ssid == ssid)?;
+        let checkmark = |checked: bool| -> Element<Message> {
+            if checked {
+                icon::from_name("emblem-ok-symbolic")
+                    .size(12)
+                    .symbolic(true)
+                    .into()
+            } else {
+                horizontal_space(12.0).into()
+            }
+        };
+
+        let mut list = column![menu_button(
+            row![
+                text::body(fl!("roam-automatically")).width(Length::Fill),
+                checkmark(ap.pinned_bssid.is_none()),
+            ]
+            .align_items(Alignment::Center)
+            .spacing(8)
+        )
+        .padding([8, 48])
+        .on_press(Message::PinBssid(ssid.to_string(), None))];
+        for bssid in &ap.bssids {
+            let connected =
+                connected_bssid.is_some_and(|b| b.eq_ignore_ascii_case(&bssid.hw_address));
+            let pinned = ap
+                .pinned_bssid
+                .as_ref()
+                .is_some_and(|b| b.eq_ignore_ascii_case(&bssid.hw_address));
+            let mut description =
+                format!("{} · {}%", frequency_band(bssid.frequency), bssid.strength);
+            if connected {
+                description = format!("{description} · {}", fl!("connected"));
+            }
+            list = list.push(
+                menu_button(
+                    row![
+                        icon::from_name(wifi_icon(bssid.strength))
+                            .size(16)
+                            .symbolic(true),
+                        column![text::body(&bssid.hw_address), text(description).size(10)]
+                            .width(Length::Fill),
+                        checkmark(pinned),
+                    ]
+                    .align_items(Alignment::Center)
+                    .spacing(8),
+                )
+                .padding([8, 48])
+                .on_press(Message::PinBssid(
+                    ssid.to_string(),
+                    (!pinned).then(|| bssid.hw_address.clone()),
+                )),
+            );
+        }
+        Some(list.into())
+    }
+
+    /// How the connection uses IPv6, which expands to change it.
+    fn ipv6_view(&self, name: &str) -> Option<Element<Message>> {
+        let settings = self.nm_state.ipv6.get(name)?;
+        let expanded = self.expanded_ipv6.as_deref() == Some(name);
+        let method = match settings.method {
+            Ipv6Method::Auto => fl!("ipv6-auto"),
+            Ipv6Method::Dhcp => fl!("ipv6-dhcp"),
+            Ipv6Method::Manual => fl!("ipv6-manual"),
+            Ipv6Method::LinkLocal => fl!("ipv6-link-local"),
+            Ipv6Method::Shared => fl!("ipv6-shared"),
+            Ipv6Method::Ignore => fl!("ipv6-ignore"),
+            Ipv6Method::Disabled => fl!("ipv6-disabled"),
+        };
+        let mode = if !settings.enabled() {
+            method
+        } else if settings.private {
+            format!("{method} · {}", fl!("ipv6-stable-privacy"))
+        } else {
+            format!("{method} · {}", fl!("ipv6-eui64"))
+        };
+
+        let mut col = column![menu_button(
+            row![
+                column![text::body(fl!("ipv6-settings")), text(mode).size(10)].width(Length::Fill),
+                icon::from_name(if expanded {
+                    "go-down-symbolic"
+                } else {
+                    "go-next-symbolic"
+                })
+                .size(16)
+                .symbolic(true),
+            ]
+            .align_items(Alignment::Center)
+            .spacing(8)
+        )
+        .padding([8, 48])
+        .on_press(Message::ToggleIpv6(name.to_string()))];
+        if !expanded {
+            return Some(col.into());
+        }
+        for addr in &settings.addresses {
+            col = col.push(
+                container(text(format!("{}: {}", fl!("ipv6"), addr)).size(12)).padding([0, 48]),
+            );
+        }
+        let enabled_name = name.to_string();
+        col = col.push(
+            container(
+                toggler(
+                    Some(fl!("ipv6-enabled")),
+                    settings.enabled(),
+                    move |enabled| {
+                        Message::SetIpv6(enabled_name.clone(), Ipv6Change::Enabled(enabled))
+                    },
+                )
+                .text_size(14)
+                .width(Length::Fill),
+            )
+            .padding([8, 48]),
+        );
+        if settings.enabled() {
+            let private_name = name.to_string();
+            col = col.push(
+                container(
+                    toggler(
+                        Some(fl!("ipv6-privacy")),
+                        settings.private,
+                        move |private| {
+                            Message::SetIpv6(private_name.clone(), Ipv6Change::Private(private))
+                        },
+                    )
+                    .text_size(14)
+                    .width(Length::Fill),
+                )
+                .padding([8, 48]),
+            );
+        }
+        Some(col.into())
+    }
+
+    fn proxy_view(&self) -> Element<Message> {
+        let mode_label = |mode| match mode {
+            ProxyMode::None => fl!("proxy-none"),
+            ProxyMode::Auto => fl!("proxy-auto"),
+            ProxyMode::Manual => fl!("proxy-manual"),
+        };
+        let dropdown_icon = if self.show_proxy {
+            "go-down-symbolic"
+        } else {
+            "go-next-symbolic"
+        };
+        let mut col = column![menu_button(row![
+            column![
+                text::body(fl!("proxy")),
+                text(mode_label(self.proxy.mode)).size(10),
+            ]
+            .width(Length::Fill),
+            container(icon::from_name(dropdown_icon).size(14).symbolic(true))
+                .align_x(Horizontal::Center)
+                .align_y(Vertical::Center)
+                .width(Length::Fixed(24.0))
+                .height(Length::Fixed(24.0)),
+        ])
+        .on_press(Message::ToggleProxy)];
+        if !self.show_proxy {
+            return col.into();
+        }
+
+        for mode in [ProxyMode::None, ProxyMode::Auto, ProxyMode::Manual] {
+            let checkmark: Element<_> = if self.proxy.mode == mode {
+                icon::from_name("emblem-ok-symbolic")
+                    .size(12)
+                    .symbolic(true)
+                    .into()
+            } else {
+                horizontal_space(12.0).into()
+            };
+            col = col.push(
+                menu_button(
+                    row![text::body(mode_label(mode)).width(Length::Fill), checkmark]
+                        .align_items(Alignment::Center),
+                )
+                .padding([8, 48])
+                .on_press(Message::ProxyMode(mode)),
+            );
+        }
+
+        let settings: Option<Element<_>> = match self.proxy.mode {
+            ProxyMode::None => None,
+            ProxyMode::Auto => Some(
+                text_input(fl!("proxy-pac-url"), &self.proxy.pac_url)
+                    .on_input(Message::ProxyPacUrl)
+                    .on_paste(Message::ProxyPacUrl)
+                    .on_submit(Message::ApplyProxy)
+                    .into(),
+            ),
+            ProxyMode::Manual => {
+                let kind_button = |kind, label| {
+                    let button = if self.proxy.kind == kind {
+                        button::suggested(label)
+                    } else {
+                        button::standard(label)
+                    };
+                    button.on_press(Message::ProxyKind(kind))
+                };
+                Some(
+                    column![
+                        row![
+                            kind_button(ProxyKind::Http, fl!("proxy-http")),
+                            kind_button(ProxyKind::Socks, fl!("proxy-socks")),
+                        ]
+                        .spacing(8),
+                        row![
+                            text_input(fl!("proxy-host"), &self.proxy.host)
+                                .on_input(Message::ProxyHost)
+                                .on_paste(Message::ProxyHost)
+                                .on_submit(Message::ApplyProxy)
+                                .width(Length::Fill),
+                            text_input(fl!("proxy-port"), &self.proxy_port)
+                                .on_input(Message::ProxyPort)
+                                .on_submit(Message::ApplyProxy)
+                                .width(Length::Fixed(72.0)),
+                        ]
+                        .spacing(8),
+                    ]
+                    .spacing(8)
+                    .into(),
+                )
+            }
+        };
+        if let Some(settings) = settings {
+            col = col.push(
+                padded_control(
+                    column![
+                        settings,
+                        button::suggested(fl!("proxy-apply")).on_press(Message::ApplyProxy)
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::End),
+                )
+                .padding([8, 48]),
+            );
+        }
+        col.into()
+    }
+
+    fn talkers_view(&self) -> Element<Message> {
+        let dropdown_icon = if self.show_talkers {
+            "go-down-symbolic"
+        } else {
+            "go-next-symbolic"
+        };
+        let mut col = column![menu_button(row![
+            text::body(fl!("top-talkers")).width(Length::Fill),
+            container(icon::from_name(dropdown_icon).size(14).symbolic(true))
+                .align_x(Horizontal::Center)
+                .align_y(Vertical::Center)
+                .width(Length::Fixed(24.0))
+                .height(Length::Fixed(24.0)),
+        ])
+        .on_press(Message::ToggleTalkers)];
+        if !self.show_talkers {
+            return col.into();
+        }
+
+        if self.talkers.is_empty() {
+            col = col
+                .push(padded_control(text(fl!("no-network-activity")).size(12)).padding([8, 48]));
+        }
+        for talker in &self.talkers {
+            col = col.push(
+                padded_control(
+                    row![
+                        text::body(&talker.name).width(Length::Fill),
+                        text(format!(
+                            "↓ {}  ↑ {}",
+                            format_rate(talker.received),
+                            format_rate(talker.sent)
+                        ))
+                        .size(12),
+                    ]
+                    .align_items(Alignment::Center)
+                    .spacing(8),
+                )
+                .padding([8, 48]),
+            );
+        }
+        col.into()
+    }
+
+    fn p2p_peer_view<'a>(&self, interface: &str, peer: &'a P2pPeer) -> Element<'a, Message> {
+        let status = if peer.connected {
+            Some(fl!("connected"))
+        } else if self.p2p_connecting.as_ref() == Some(&peer.path) {
+            Some(fl!("connecting"))
+        } else if peer.group_owner {
+            Some(fl!("group-owner"))
+        } else {
+            None
+        };
+        let mut label = column![text::body(&peer.name)];
+        if let Some(status) = status {
+            label = label.push(text(status).size(10));
+        }
+        let button = if peer.connected {
+            button::standard(fl!("disconnect"))
+                .on_press(Message::P2pDisconnect(interface.to_string()))
+        } else {
+            let connect =
+                Message::P2pConnect(interface.to_string(), peer.path.clone(), peer.group_owner);
+            button::standard(fl!("connect"))
+                .on_press_maybe(self.p2p_connecting.is_none().then_some(connect))
+        };
+        padded_control(
+            row![label.width(Length::Fill), button]
+                .align_items(Alignment::Center)
+                .spacing(8),
+        )
+        .padding([8, 48])
+        .into()
+    }
+
+    /// Wi-Fi Direct peers, like TVs to cast the screen to.
+    fn p2p_view(&self) -> Element<Message> {
+        let dropdown_icon = if self.show_p2p {
+            "go-down-symbolic"
+        } else {
+            "go-next-symbolic"
+        };
+        let mut col = column![menu_button(row![
+            text::body(fl!("wifi-direct")).width(Length::Fill),
+            container(icon::from_name(dropdown_icon).size(14).symbolic(true))
+                .align_x(Horizontal::Center)
+                .align_y(Vertical::Center)
+                .width(Length::Fixed(24.0))
+                .height(Length::Fixed(24.0)),
+        ])
+        .on_press(Message::ToggleP2p)];
+        if !self.show_p2p {
+            return col.into();
+        }
+
+        let note = |label: String| padded_control(text(label).size(12)).padding([8, 48]);
+        match &self.p2p {
+            None => col = col.push(note(fl!("wifi-direct-searching"))),
+            Some(P2pUpdate::Unavailable) => col = col.push(note(fl!("wifi-direct-unavailable"))),
+            Some(P2pUpdate::State(state)) => {
+                if state.role == P2pRole::GroupOwner {
+                    col = col.push(note(fl!("wifi-direct-group-owner")));
+                }
+                if state.peers.is_empty() {
+                    col = col.push(note(fl!("wifi-direct-searching")));
+                }
+                for peer in &state.peers {
+                    col = col.push(self.p2p_peer_view(&state.interface, peer));
+                }
+            }
+        }
+        col.into()
+    }
+
+    fn speed_test_running(&self) -> bool {
+        matches!(
+            self.speed_test,
+            Some(SpeedTestUpdate::Downloading | SpeedTestUpdate::Uploading { .. })
+        )
+    }
+
+    /// A button to measure the connection's speed, with its progress or result.
+    fn speed_test_view(&self) -> Element<Message> {
+        let status = match &self.speed_test {
+            _ if self.metered && !self.speed_test_running() => fl!("speed-test-metered"),
+            None => String::new(),
+            Some(SpeedTestUpdate::Downloading) => fl!("speed-test-downloading"),
+            Some(SpeedTestUpdate::Uploading { download }) => format!(
+                "↓ {}  {}",
+                format_rate(*download),
+                fl!("speed-test-uploading")
+            ),
+            Some(SpeedTestUpdate::Done { download, upload }) => {
+                format!("↓ {}  ↑ {}", format_rate(*download), format_rate(*upload))
+            }
+            Some(SpeedTestUpdate::Failed(err)) if err.is_empty() => fl!("speed-test-failed"),
+            Some(SpeedTestUpdate::Failed(err)) => format!("{}: {err}", fl!("speed-test-failed")),
+        };
+        let mut label = column![text::body(fl!("test-speed"))];
+        if !status.is_empty() {
+            label = label.push(text(status).size(10));
+        }
+        let button = if self.speed_test_running() {
+            button::standard(fl!("cancel")).on_press(Message::CancelSpeedTest)
+        } else {
+            button::standard(fl!("test-speed"))
+                .on_press_maybe((!self.metered).then_some(Message::StartSpeedTest))
+        };
+        padded_control(
+            row![label.width(Length::Fill), button]
+                .align_items(Alignment::Center)
+                .spacing(8),
+        )
+        .into()
+    }
+
     fn update_nm_state(&mut self, mut new_state: NetworkManagerState) {
         self.update_togglers(&new_state);
         // check for failed conns that can be reset
@@ -208,8 +683,6 @@ impl CosmicNetworkApplet {
 pub(crate) enum Message {
     ActivateKnownWifi(String),
     Disconnect(String),
-    TogglePopup,
-    CloseRequested(window::Id),
     ToggleAirplaneMode(bool),
     ToggleWiFi(bool),
     ToggleVisibleNetworks,
@@ -222,67 +695,83 @@ pub(crate) enum Message {
     Token(TokenUpdate),
     OpenSettings,
     ResetFailedKnownSsid(String),
+    ToggleBssids(String),
+    PinBssid(String, Option<String>),
+    ToggleIpv6(String),
+    SetIpv6(String, Ipv6Change),
+    ToggleProxy,
+    ProxySettings(Result<ProxySettings, String>),
+    ProxyMode(ProxyMode),
+    ProxyKind(ProxyKind),
+    ProxyPacUrl(String),
+    ProxyHost(String),
+    ProxyPort(String),
+    ApplyProxy,
+    ProxyApplied(Result<(), String>),
+    Metered(bool),
+    SetMetered(bool),
+    ToggleTalkers,
+    Talkers(Vec<Talker>),
+    MeteredSet(Result<(), String>),
+    ConfigChanged(NetworkAppletConfig),
+    StartSpeedTest,
+    CancelSpeedTest,
+    SpeedTest(SpeedTestUpdate),
+    ToggleP2p,
+    P2p(P2pUpdate),
+    /// Connects the interface to the peer, joining its group if it owns one.
+    P2pConnect(String, String, bool),
+    P2pDisconnect(String),
+    P2pRequestDone(Result<(), String>),
     // Errored(String),
 }
 
-impl cosmic::Application for CosmicNetworkApplet {
+impl CosmicApplet for CosmicNetworkApplet {
     type Message = Message;
-    type Executor = cosmic::SingleThreadExecutor;
-    type Flags = ();
     const APP_ID: &'static str = config::APP_ID;
+    const AUTOSIZE: bool = false;
 
-    fn init(core: cosmic::app::Core, _flags: ()) -> (Self, Command<Message>) {
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config = Config::new(config::APP_ID, NetworkAppletConfig::VERSION)
+            .ok()
+            .map(|helper| {
+                NetworkAppletConfig::get_entry(&helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
         (
             Self {
-                core,
                 icon_name: "network-offline-symbolic".to_string(),
                 token_tx: None,
+                config,
                 ..Default::default()
             },
             Command::none(),
         )
     }
 
-    fn core(&self) -> &cosmic::app::Core {
-        &self.core
-    }
-
-    fn core_mut(&mut self) -> &mut cosmic::app::Core {
-        &mut self.core
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        self.popup_open = event == PanelEvent::PopupOpened;
+        if !self.popup_open {
+            self.show_visible_networks = false;
+            return Command::none();
+        }
+        self.timeline = Timeline::new();
+        if let Some(tx) = self.nm_sender.as_mut() {
+            let _ = tx.unbounded_send(NetworkManagerRequest::Reload);
+        }
+        Command::perform(proxy::proxy_settings(), |res| {
+            Message::ProxySettings(res.map_err(|e| e.to_string()))
+        })
     }
 
-    fn update(&mut self, message: Message) -> Command<Message> {
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
         match message {
             Message::Frame(now) => self.timeline.now(now),
-            Message::TogglePopup => {
-                if let Some(p) = self.popup.take() {
-                    self.show_visible_networks = false;
-                    return destroy_popup(p);
-                } else {
-                    // TODO request update of state maybe
-                    let new_id = window::Id::unique();
-                    self.popup.replace(new_id);
-                    self.timeline = Timeline::new();
-
-                    let mut popup_settings = self.core.applet.get_popup_settings(
-                        window::Id::MAIN,
-                        new_id,
-                        None,
-                        None,
-                        None,
-                    );
-
-                    popup_settings.positioner.size_limits = Limits::NONE
-                        .min_height(1.0)
-                        .min_width(1.0)
-                        .max_height(800.0)
-                        .max_width(400.0);
-                    if let Some(tx) = self.nm_sender.as_mut() {
-                        let _ = tx.unbounded_send(NetworkManagerRequest::Reload);
-                    }
-                    return get_popup(popup_settings);
-                }
-            }
             // Message::Errored(_) => todo!(),
             Message::ToggleAirplaneMode(enabled) => {
                 self.toggle_wifi_ctr += 1;
@@ -472,11 +961,6 @@ impl cosmic::Application for CosmicNetworkApplet {
                 };
                 let _ = tx.unbounded_send(NetworkManagerRequest::Disconnect(ssid));
             }
-            Message::CloseRequested(id) => {
-                if Some(id) == self.popup {
-                    self.popup = None;
-                }
-            }
             Message::OpenSettings => {
                 let exec = "cosmic-settings network".to_string();
                 if let Some(tx) = self.token_tx.as_ref() {
@@ -503,6 +987,188 @@ impl cosmic::Application for CosmicNetworkApplet {
                     cosmic::process::spawn(cmd);
                 }
             },
+            Message::ToggleBssids(ssid) => {
+                if self.expanded_bssids.as_ref() == Some(&ssid) {
+                    self.expanded_bssids = None;
+                } else {
+                    self.expanded_bssids = Some(ssid);
+                }
+            }
+            Message::PinBssid(ssid, bssid) => {
+                if let Some(ap) = self
+                    .nm_state
+                    .wireless_access_points
+                    .iter_mut()
+                    .find(|ap| ap.ssid == ssid)
+                {
+                    ap.pinned_bssid.clone_from(&bssid);
+                }
+                if let Some(tx) = self.nm_sender.as_ref() {
+                    let _ = tx.unbounded_send(NetworkManagerRequest::PinBssid { ssid, bssid });
+                }
+            }
+            Message::ToggleIpv6(name) => {
+                if self.expanded_ipv6.as_ref() == Some(&name) {
+                    self.expanded_ipv6 = None;
+                } else {
+                    self.expanded_ipv6 = Some(name);
+                }
+            }
+            Message::SetIpv6(name, change) => {
+                let Some(settings) = self.nm_state.ipv6.get_mut(&name) else {
+                    return Command::none();
+                };
+                match change {
+                    Ipv6Change::Enabled(enabled) => {
+                        settings.method = if enabled {
+                            Ipv6Method::Auto
+                        } else {
+                            Ipv6Method::Disabled
+                        };
+                    }
+                    Ipv6Change::Private(private) => settings.private = private,
+                }
+                if let Some(tx) = self.nm_sender.as_ref() {
+                    let _ = tx.unbounded_send(NetworkManagerRequest::SetIpv6 {
+                        uuid: settings.uuid.clone(),
+                        change,
+                    });
+                }
+            }
+            Message::ToggleProxy => {
+                self.show_proxy = !self.show_proxy;
+            }
+            Message::ToggleTalkers => {
+                self.show_talkers = !self.show_talkers;
+                self.talkers.clear();
+            }
+            Message::Talkers(talkers) => {
+                self.talkers = talkers;
+            }
+            Message::ToggleP2p => {
+                self.show_p2p = !self.show_p2p;
+                self.p2p = None;
+                // A peer declining isn't noticed, so hiding the peers gives up
+                self.p2p_connecting = None;
+            }
+            Message::P2p(update) => {
+                if let (Some(peer), P2pUpdate::State(state)) = (&self.p2p_connecting, &update) {
+                    if state.peers.iter().any(|p| p.path == *peer && p.connected) {
+                        self.p2p_connecting = None;
+                    }
+                }
+                self.p2p = Some(update);
+            }
+            Message::P2pConnect(interface, peer, join) => {
+                self.p2p_connecting = Some(peer.clone());
+                return Command::perform(p2p::connect(interface, peer, join), |res| {
+                    Message::P2pRequestDone(res.map_err(|e| e.to_string()))
+                });
+            }
+            Message::P2pDisconnect(interface) => {
+                return Command::perform(p2p::disconnect(interface), |res| {
+                    Message::P2pRequestDone(res.map_err(|e| e.to_string()))
+                });
+            }
+            Message::P2pRequestDone(Ok(())) => {}
+            Message::P2pRequestDone(Err(err)) => {
+                self.p2p_connecting = None;
+                tracing::error!("Wi-Fi Direct request failed: {}", err);
+            }
+            Message::ProxySettings(Ok(settings)) => {
+                self.proxy_port = if settings.port == 0 {
+                    String::new()
+                } else {
+                    settings.port.to_string()
+                };
+                self.proxy = settings;
+            }
+            Message::ProxySettings(Err(err)) => {
+                tracing::error!("Failed to read proxy settings: {}", err);
+            }
+            Message::ProxyMode(mode) => {
+                self.proxy.mode = mode;
+                // Manual proxies and PAC URLs need filling in first
+                if mode == ProxyMode::None {
+                    return self.update(Message::ApplyProxy);
+                }
+            }
+            Message::ProxyKind(kind) => {
+                self.proxy.kind = kind;
+            }
+            Message::ProxyPacUrl(url) => {
+                self.proxy.pac_url = url;
+            }
+            Message::ProxyHost(host) => {
+                self.proxy.host = host;
+            }
+            Message::ProxyPort(port) => {
+                if let Ok(port) = port.parse() {
+                    self.proxy.port = port;
+                }
+                if port.is_empty() || port.parse::<u16>().is_ok() {
+                    self.proxy_port = port;
+                }
+            }
+            Message::ApplyProxy => {
+                let Some(conn) = self.conn.clone() else {
+                    return Command::none();
+                };
+                let settings = self.proxy.clone();
+                return Command::perform(
+                    async move { proxy::set_proxy_settings(&conn, &settings).await },
+                    |res| Message::ProxyApplied(res.map_err(|e| e.to_string())),
+                );
+            }
+            Message::Metered(metered) => {
+                if metered != self.metered {
+                    let chain = if metered {
+                        chain::Toggler::on(METERED.clone(), 1.)
+                    } else {
+                        chain::Toggler::off(METERED.clone(), 1.)
+                    };
+                    self.timeline.set_chain(chain).start();
+                }
+                self.metered = metered;
+                // Stop measuring once the connection costs data
+                if metered && self.speed_test_running() {
+                    self.speed_test = None;
+                }
+            }
+            Message::SetMetered(metered) => {
+                let Some(conn) = self.conn.clone() else {
+                    return Command::none();
+                };
+                return Command::perform(
+                    async move { metered::set_metered(&conn, metered).await },
+                    |res| Message::MeteredSet(res.map_err(|e| e.to_string())),
+                );
+            }
+            Message::ConfigChanged(config) => {
+                self.config = config;
+            }
+            Message::StartSpeedTest => {
+                if !self.metered {
+                    self.speed_test_run = self.speed_test_run.wrapping_add(1);
+                    self.speed_test = Some(SpeedTestUpdate::Downloading);
+                }
+            }
+            Message::CancelSpeedTest => {
+                self.speed_test = None;
+            }
+            Message::SpeedTest(update) => {
+                if self.speed_test_running() {
+                    self.speed_test = Some(update);
+                }
+            }
+            Message::MeteredSet(Ok(())) => {}
+            Message::MeteredSet(Err(err)) => {
+                tracing::error!("Failed to set whether the connection is metered: {}", err);
+            }
+            Message::ProxyApplied(Ok(())) => {}
+            Message::ProxyApplied(Err(err)) => {
+                tracing::error!("Failed to set proxy settings: {}", err);
+            }
             Message::ResetFailedKnownSsid(ssid) => {
                 let ap = if let Some(pos) = self
                     .nm_state
@@ -539,15 +1205,50 @@ impl cosmic::Application for CosmicNetworkApplet {
         Command::none()
     }
 
-    fn view(&self) -> Element<Message> {
-        self.core
-            .applet
-            .icon_button(&self.icon_name)
-            .on_press(Message::TogglePopup)
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        if !self.metered {
+            return core
+                .applet
+                .icon_button(&self.icon_name)
+                .on_press(AppletMessage::TogglePopup)
+                .into();
+        }
+
+        // A dot next to the icon marks a metered connection
+        let icon = icon::from_name(self.icon_name.as_str())
+            .size(core.applet.suggested_size(true).0)
+            .symbolic(true);
+        let badge = container(vertical_space(Length::Fixed(0.0)))
+            .width(Length::Fixed(4.0))
+            .height(Length::Fixed(4.0))
+            .style(<Theme as container::StyleSheet>::Style::Custom(Box::new(
+                |theme| container::Appearance {
+                    background: Some(Background::Color(theme.cosmic().warning_color().into())),
+                    border: Border {
+                        radius: 2.0.into(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            )));
+        let content: Element<_> = match core.applet.anchor {
+            PanelAnchor::Left | PanelAnchor::Right => column![icon, badge]
+                .spacing(2)
+                .align_items(Alignment::Center)
+                .into(),
+            PanelAnchor::Top | PanelAnchor::Bottom => row![icon, badge]
+                .spacing(2)
+                .align_items(Alignment::Center)
+                .into(),
+        };
+        button(content)
+            .padding(core.applet.suggested_padding(true))
+            .style(cosmic::theme::Button::AppletIcon)
+            .on_press(AppletMessage::TogglePopup)
             .into()
     }
 
-    fn view_window(&self, _id: window::Id) -> Element<Message> {
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
         let mut vpn_ethernet_col = column![];
         let mut known_wifi = Vec::new();
         for conn in &self.nm_state.active_conns {
@@ -589,34 +1290,37 @@ impl cosmic::Application for CosmicNetworkApplet {
                         ipv4.push(text(format!("{}: {}", fl!("ipv4"), addr)).size(12).into());
                     }
 
-                    vpn_ethernet_col = vpn_ethernet_col.push(column![
-                        row![
-                            icon(
-                                icon::from_name(self.icon_name.clone())
-                                    .symbolic(true)
-                                    .into()
-                            )
-                            .size(40),
-                            Column::with_children(ipv4),
-                            text::body(format!(
-                                "{} - {speed} {}",
-                                fl!("connected"),
-                                fl!("megabits-per-second")
-                            ))
-                            .width(Length::Fill)
-                            .horizontal_alignment(Horizontal::Right),
-                        ]
-                        .align_items(Alignment::Center)
-                        .spacing(8)
-                        .padding(menu_control_padding()),
-                        padded_control(divider::horizontal::default()),
-                    ]);
+                    vpn_ethernet_col = vpn_ethernet_col.push(column![row![
+                        icon(
+                            icon::from_name(self.icon_name.clone())
+                                .symbolic(true)
+                                .into()
+                        )
+                        .size(40),
+                        Column::with_children(ipv4),
+                        text::body(format!(
+                            "{} - {speed} {}",
+                            fl!("connected"),
+                            fl!("megabits-per-second")
+                        ))
+                        .width(Length::Fill)
+                        .horizontal_alignment(Horizontal::Right),
+                    ]
+                    .align_items(Alignment::Center)
+                    .spacing(8)
+                    .padding(menu_control_padding()),]);
+                    if let Some(ipv6) = self.ipv6_view(name) {
+                        vpn_ethernet_col = vpn_ethernet_col.push(ipv6);
+                    }
+                    vpn_ethernet_col =
+                        vpn_ethernet_col.push(padded_control(divider::horizontal::default()));
                 }
                 ActiveConnectionInfo::WiFi {
                     name,
                     ip_addresses,
                     state,
                     strength,
+                    bssid,
                     ..
                 } => {
                     let mut ipv4 = Vec::with_capacity(ip_addresses.len());
@@ -659,6 +1363,9 @@ impl cosmic::Application for CosmicNetworkApplet {
                             .into(),
                         )
                     }
+                    if let Some(bssids_button) = self.bssids_button(name) {
+                        btn_content.push(bssids_button);
+                    }
 
                     known_wifi.push(Element::from(
                         column![menu_button(
@@ -669,6 +1376,12 @@ impl cosmic::Application for CosmicNetworkApplet {
                         .on_press(Message::Disconnect(name.clone()))]
                         .align_items(Alignment::Center),
                     ));
+                    if let Some(bssid_list) = self.bssid_list(name, Some(bssid)) {
+                        known_wifi.push(bssid_list);
+                    }
+                    if let Some(ipv6) = self.ipv6_view(name) {
+                        known_wifi.push(ipv6);
+                    }
                 }
             };
         }
@@ -761,6 +1474,9 @@ impl cosmic::Application for CosmicNetworkApplet {
                             .into(),
                     )
                 }
+                if let Some(bssids_button) = self.bssids_button(&known.ssid) {
+                    btn_content.push(bssids_button);
+                }
 
                 let mut btn = menu_button(
                     Row::with_children(btn_content)
@@ -779,6 +1495,9 @@ impl cosmic::Application for CosmicNetworkApplet {
                     _ => btn,
                 };
                 known_wifi.push(Element::from(row![btn].align_items(Alignment::Center)));
+                if let Some(bssid_list) = self.bssid_list(&known.ssid, None) {
+                    known_wifi.push(bssid_list);
+                }
             }
 
             let has_known_wifi = !known_wifi.is_empty();
@@ -929,43 +1648,93 @@ impl cosmic::Application for CosmicNetworkApplet {
             }
         }
 
-        self.core
-            .applet
-            .popup_container(content.padding([8, 0, 8, 0]))
-            .into()
+        if !self.nm_state.active_conns.is_empty() {
+            content = content
+                .push(padded_control(divider::horizontal::default()))
+                .push(padded_control(
+                    anim!(
+                        //toggler
+                        METERED,
+                        &self.timeline,
+                        fl!("metered-connection"),
+                        self.metered,
+                        |_chain, enable| { Message::SetMetered(enable) },
+                    )
+                    .text_size(14)
+                    .width(Length::Fill),
+                ));
+        }
+
+        content = content
+            .push(padded_control(divider::horizontal::default()))
+            .push(self.proxy_view())
+            .push(padded_control(divider::horizontal::default()))
+            .push(self.talkers_view())
+            .push(padded_control(divider::horizontal::default()))
+            .push(self.p2p_view());
+        if self.config.speed_test {
+            content = content
+                .push(padded_control(divider::horizontal::default()))
+                .push(self.speed_test_view());
+        }
+
+        Element::from(content.padding([8, 0, 8, 0])).map(AppletMessage::App)
     }
 
-    fn subscription(&self) -> Subscription<Message> {
+    fn subscription(&self, core: &Core) -> Subscription<Message> {
         let network_sub = network_manager_subscription(0).map(Message::NetworkManagerEvent);
-        let timeline = self
-            .timeline
-            .as_subscription()
-            .map(|(_, now)| Message::Frame(now));
         let token_sub = activation_token_subscription(0).map(Message::Token);
+        // Dropping the subscription cancels the test
+        let speed_test_sub = if self.speed_test_running() {
+            speed_test_subscription(
+                self.speed_test_run,
+                self.config.speed_test_download_url.clone(),
+                self.config.speed_test_upload_url.clone(),
+            )
+            .map(Message::SpeedTest)
+        } else {
+            Subscription::none()
+        };
+        let config_sub = core
+            .watch_config::<NetworkAppletConfig>(config::APP_ID)
+            .map(|u| Message::ConfigChanged(u.config));
 
         if let Some(conn) = self.conn.as_ref() {
-            let has_popup = self.popup.is_some();
             Subscription::batch(vec![
-                timeline,
                 network_sub,
                 token_sub,
                 active_conns_subscription(self.toggle_wifi_ctr, conn.clone())
                     .map(Message::NetworkManagerEvent),
-                devices_subscription(self.toggle_wifi_ctr, has_popup, conn.clone())
+                devices_subscription(self.toggle_wifi_ctr, self.popup_open, conn.clone())
                     .map(Message::NetworkManagerEvent),
                 wireless_enabled_subscription(self.toggle_wifi_ctr, conn.clone())
                     .map(Message::NetworkManagerEvent),
+                metered_subscription(0, conn.clone()).map(Message::Metered),
+                speed_test_sub,
+                config_sub,
             ])
         } else {
-            Subscription::batch(vec![timeline, network_sub, token_sub])
+            Subscription::batch(vec![network_sub, token_sub, speed_test_sub, config_sub])
         }
     }
 
-    fn style(&self) -> Option<<Theme as application::StyleSheet>::Style> {
-        Some(cosmic::applet::style())
-    }
-
-    fn on_close_requested(&self, id: window::Id) -> Option<Message> {
-        Some(Message::CloseRequested(id))
+    fn popup_subscription(&self, _core: &Core) -> Subscription<Message> {
+        // Attributing traffic walks every process, so only while it's shown
+        let talkers_sub = if self.show_talkers {
+            talkers_subscription(Duration::from_secs(2)).map(Message::Talkers)
+        } else {
+            Subscription::none()
+        };
+        // Searching keeps the Wi-Fi radio busy, so only while it's shown
+        let p2p_sub = if self.show_p2p {
+            p2p_subscription().map(Message::P2p)
+        } else {
+            Subscription::none()
+        };
+        let timeline = self
+            .timeline
+            .as_subscription()
+            .map(|(_, now)| Message::Frame(now));
+        Subscription::batch([timeline, talkers_sub, p2p_sub])
     }
 }
@@ -1 +1,26 @@
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
 pub const APP_ID: &str = "com.system76.CosmicAppletNetwork";
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, CosmicConfigEntry)]
+#[version = 1]
+pub struct NetworkAppletConfig {
+    /// Show the "Test speed" button.
+    pub speed_test: bool,
+    /// Downloaded from to measure the download speed.
+    pub speed_test_download_url: String,
+    /// Uploaded to to measure the upload speed.
+    pub speed_test_upload_url: String,
+}
+
+impl Default for NetworkAppletConfig {
+    fn default() -> Self {
+        Self {
+            speed_test: true,
+            speed_test_download_url: "https://speed.cloudflare.com/__down?bytes=25000000"
+                .to_string(),
+            speed_test_upload_url: "https://speed.cloudflare.com/__up".to_string(),
+        }
+    }
+}
@@ -3,7 +3,10 @@
 mod app;
 mod config;
 mod localize;
-mod network_manager;
+mod metered;
+mod p2p;
+mod speed_test;
+mod talkers;
 
 use crate::localize::localize;
 
@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Shares whether the network is metered on the session bus, so other parts
+//! of the desktop, like the updates applet, can hold off large downloads.
+//!
+//! It's the `Metered` property of `com.system76.CosmicNetwork`, at
+//! `/com/system76/CosmicNetwork` of the service of the same name.
+
+use std::{fmt::Debug, hash::Hash, pin::pin};
+
+use cosmic::iced::{self, subscription};
+use cosmic_nm_client::metered::metered_changes;
+use futures::{SinkExt, StreamExt};
+use zbus::Connection;
+
+const NAME: &str = "com.system76.CosmicNetwork";
+const PATH: &str = "/com/system76/CosmicNetwork";
+
+struct Network {
+    metered: bool,
+}
+
+#[zbus::interface(name = "com.system76.CosmicNetwork")]
+impl Network {
+    #[zbus(property)]
+    fn metered(&self) -> bool {
+        self.metered
+    }
+}
+
+/// Whether the network is metered, published on the session bus as it changes.
+pub fn metered_subscription<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+    conn: Connection,
+) -> iced::Subscription<bool> {
+    subscription::channel(id, 4, move |mut output| async move {
+        if let Err(err) = run(&conn, &mut output).await {
+            tracing::error!("Failed to watch whether the network is metered: {}", err);
+        }
+        futures::future::pending().await
+    })
+}
+
+async fn run(
+    conn: &Connection,
+    output: &mut futures::channel::mpsc::Sender<bool>,
+) -> zbus::Result<()> {
+    let session = zbus::connection::Builder::session()?
+        .serve_at(PATH, Network { metered: false })?
+        .build()
+        .await?;
+    // Another panel's network applet may have it already
+    if let Err(err) = session.request_name(NAME).await {
+        tracing::warn!("Failed to request {}: {}", NAME, err);
+    }
+
+    let mut changes = pin!(metered_changes(conn).await?);
+    while let Some(metered) = changes.next().await {
+        let network = session
+            .object_server()
+            .interface::<_, Network>(PATH)
+            .await?;
+        if network.get().await.metered != metered {
+            network.get_mut().await.metered = metered;
+            network
+                .get()
+                .await
+                .metered_changed(network.signal_context())
+                .await?;
+        }
+        _ = output.send(metered).await;
+    }
+    Ok(())
+}
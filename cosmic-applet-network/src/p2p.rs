@@ -0,0 +1,291 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Wi-Fi Direct peers, like TVs to cast the screen to, found through
+//! wpa_supplicant's D-Bus interface.
+//!
+//! Peers are searched for while the section is shown. wpa_supplicant stops a
+//! search after [`FIND_TIMEOUT_SECS`], so it's started again as long as it's
+//! subscribed to. Connecting uses push-button setup, which some peers ask to
+//! confirm on their side.
+
+use std::{collections::HashMap, pin::pin, time::Duration};
+
+use cosmic::{
+    iced::{self, subscription},
+    iced_futures::futures::{
+        self,
+        future::{self, Either},
+        stream, FutureExt, SinkExt, StreamExt,
+    },
+};
+use zbus::{
+    message::Type as MessageType,
+    proxy,
+    zvariant::{ObjectPath, OwnedObjectPath, Value},
+    Connection, MatchRule, MessageStream,
+};
+
+const SERVICE: &str = "fi.w1.wpa_supplicant1";
+/// How long each search runs for.
+const FIND_TIMEOUT_SECS: i32 = 60;
+/// Set in a peer's group capability while it's a group owner.
+const GROUP_CAPAB_GROUP_OWNER: u8 = 0x01;
+
+#[proxy(
+    interface = "fi.w1.wpa_supplicant1",
+    default_service = "fi.w1.wpa_supplicant1",
+    default_path = "/fi/w1/wpa_supplicant1"
+)]
+trait Supplicant {
+    #[zbus(property)]
+    fn interfaces(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[proxy(
+    interface = "fi.w1.wpa_supplicant1.Interface.P2PDevice",
+    default_service = "fi.w1.wpa_supplicant1"
+)]
+trait P2PDevice {
+    fn find(&self, args: HashMap<&str, Value<'_>>) -> zbus::Result<()>;
+
+    fn connect(&self, args: HashMap<&str, Value<'_>>) -> zbus::Result<String>;
+
+    fn disconnect(&self) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn peers(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+
+    /// `GO`, `client` or `device`.
+    #[zbus(property)]
+    fn role(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn group(&self) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(property, name = "PeerGO")]
+    fn peer_go(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "fi.w1.wpa_supplicant1.Peer",
+    default_service = "fi.w1.wpa_supplicant1"
+)]
+trait Peer {
+    #[zbus(property)]
+    fn device_name(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn device_address(&self) -> zbus::Result<Vec<u8>>;
+
+    #[zbus(property)]
+    fn group_capability(&self) -> zbus::Result<u8>;
+}
+
+#[proxy(
+    interface = "fi.w1.wpa_supplicant1.Group",
+    default_service = "fi.w1.wpa_supplicant1"
+)]
+trait Group {
+    #[zbus(property)]
+    fn members(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum P2pRole {
+    /// Not in a group.
+    Device,
+    GroupOwner,
+    Client,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct P2pPeer {
+    /// The peer's object path, identifying it to wpa_supplicant.
+    pub path: String,
+    pub name: String,
+    pub address: String,
+    /// Whether the peer owns a group, which is joined rather than formed.
+    pub group_owner: bool,
+    /// Whether the peer is in this device's group.
+    pub connected: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct P2pState {
+    /// The object path of the Wi-Fi interface searching.
+    pub interface: String,
+    pub role: P2pRole,
+    pub peers: Vec<P2pPeer>,
+}
+
+#[derive(Debug, Clone)]
+pub enum P2pUpdate {
+    State(P2pState),
+    /// wpa_supplicant isn't running, can't be reached, or no interface
+    /// supports Wi-Fi Direct.
+    Unavailable,
+}
+
+/// Searches for peers while subscribed.
+pub fn p2p_subscription() -> iced::Subscription<P2pUpdate> {
+    subscription::channel(
+        std::any::TypeId::of::<P2pUpdate>(),
+        10,
+        |mut output| async move {
+            if let Err(err) = run(&mut output).await {
+                tracing::debug!("Wi-Fi Direct unavailable: {}", err);
+            }
+            _ = output.send(P2pUpdate::Unavailable).await;
+            futures::future::pending().await
+        },
+    )
+}
+
+async fn run(output: &mut futures::channel::mpsc::Sender<P2pUpdate>) -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    let Some(device) = p2p_device(&conn).await? else {
+        return Ok(());
+    };
+    let changes = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(SERVICE)?
+        .build();
+    // Also notice wpa_supplicant stopping
+    let owner = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender("org.freedesktop.DBus")?
+        .member("NameOwnerChanged")?
+        .arg(0, SERVICE)?
+        .build();
+    let mut signals = stream::select(
+        MessageStream::for_match_rule(changes, &conn, None).await?,
+        MessageStream::for_match_rule(owner, &conn, None).await?,
+    );
+
+    let find_timeout = Duration::from_secs(FIND_TIMEOUT_SECS as u64);
+    loop {
+        device
+            .find(HashMap::from([("Timeout", Value::from(FIND_TIMEOUT_SECS))]))
+            .await?;
+        let mut search = pin!(tokio::time::sleep(find_timeout));
+        loop {
+            _ = output
+                .send(P2pUpdate::State(state(&conn, &device).await?))
+                .await;
+
+            match future::select(signals.next(), &mut search).await {
+                Either::Left((Some(_), _)) => {}
+                Either::Left((None, _)) => return Ok(()),
+                // The search ended, so it's started again
+                Either::Right(_) => break,
+            }
+            // Peers are found in bursts
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            while let Some(Some(_)) = signals.next().now_or_never() {}
+        }
+    }
+}
+
+/// The first interface supporting Wi-Fi Direct.
+async fn p2p_device(conn: &Connection) -> zbus::Result<Option<P2PDeviceProxy<'static>>> {
+    for path in SupplicantProxy::new(conn).await?.interfaces().await? {
+        let device = P2PDeviceProxy::builder(conn)
+            .path(path)?
+            .cache_properties(zbus::CacheProperties::No)
+            .build()
+            .await?;
+        if device.role().await.is_ok() {
+            return Ok(Some(device));
+        }
+    }
+    Ok(None)
+}
+
+async fn state(conn: &Connection, device: &P2PDeviceProxy<'_>) -> zbus::Result<P2pState> {
+    let role = match device.role().await?.as_str() {
+        "GO" => P2pRole::GroupOwner,
+        "client" => P2pRole::Client,
+        _ => P2pRole::Device,
+    };
+    let mut connected = Vec::new();
+    if role != P2pRole::Device {
+        if let Ok(peer_go) = device.peer_go().await {
+            connected.push(peer_go);
+        }
+        if let Ok(group) = device.group().await {
+            let group = GroupProxy::builder(conn)
+                .path(group)?
+                .cache_properties(zbus::CacheProperties::No)
+                .build()
+                .await?;
+            connected.extend(group.members().await.unwrap_or_default());
+        }
+    }
+
+    let mut peers = Vec::new();
+    for path in device.peers().await? {
+        let peer = PeerProxy::builder(conn)
+            .path(path.clone())?
+            .cache_properties(zbus::CacheProperties::No)
+            .build()
+            .await?;
+        let address = peer
+            .device_address()
+            .await
+            .unwrap_or_default()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<Vec<_>>()
+            .join(":");
+        let name = peer.device_name().await.unwrap_or_default();
+        peers.push(P2pPeer {
+            name: if name.is_empty() {
+                address.clone()
+            } else {
+                name
+            },
+            address,
+            group_owner: peer.group_capability().await.unwrap_or_default()
+                & GROUP_CAPAB_GROUP_OWNER
+                != 0,
+            connected: connected.contains(&path),
+            path: path.to_string(),
+        });
+    }
+    peers.sort_by(|a, b| b.connected.cmp(&a.connected).then(a.name.cmp(&b.name)));
+
+    Ok(P2pState {
+        interface: device.inner().path().to_string(),
+        role,
+        peers,
+    })
+}
+
+async fn interface_device(
+    conn: &Connection,
+    interface: String,
+) -> zbus::Result<P2PDeviceProxy<'static>> {
+    P2PDeviceProxy::builder(conn).path(interface)?.build().await
+}
+
+/// Connects to the peer with push-button setup, joining its group if it owns
+/// one.
+pub async fn connect(interface: String, peer: String, join: bool) -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    let peer = ObjectPath::try_from(peer.as_str())?;
+    interface_device(&conn, interface)
+        .await?
+        .connect(HashMap::from([
+            ("peer", Value::from(peer)),
+            ("wps_method", Value::from("pbc")),
+            ("join", Value::from(join)),
+        ]))
+        .await?;
+    Ok(())
+}
+
+/// Leaves the group, or ends it if this device owns it.
+pub async fn disconnect(interface: String) -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    interface_device(&conn, interface).await?.disconnect().await
+}
@@ -0,0 +1,119 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! A quick measurement of the download and upload speed, for the "Test speed"
+//! button.
+//!
+//! `curl` downloads a file from the configured endpoint and then uploads zeros
+//! to it, reporting the average speed of each. The test runs while it's
+//! subscribed to, so cancelling it drops the subscription, which kills `curl`.
+
+use std::process::Stdio;
+
+use cosmic::{
+    iced::{self, subscription},
+    iced_futures::futures::SinkExt,
+};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+/// How much is uploaded, in bytes.
+const UPLOAD_BYTES: usize = 10_000_000;
+/// The longest each direction is measured for, in seconds.
+const MAX_TIME_SECS: &str = "15";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpeedTestUpdate {
+    Downloading,
+    /// The download speed, in bytes per second.
+    Uploading {
+        download: u64,
+    },
+    Done {
+        download: u64,
+        upload: u64,
+    },
+    Failed(String),
+}
+
+/// Runs the test against the endpoints, once for each `run`.
+pub fn speed_test_subscription(
+    run: u32,
+    download_url: String,
+    upload_url: String,
+) -> iced::Subscription<SpeedTestUpdate> {
+    subscription::channel(
+        (std::any::TypeId::of::<SpeedTestUpdate>(), run),
+        4,
+        move |mut output| async move {
+            _ = output.send(SpeedTestUpdate::Downloading).await;
+            let update = match download(&download_url).await {
+                Ok(download) => {
+                    _ = output.send(SpeedTestUpdate::Uploading { download }).await;
+                    match upload(&upload_url).await {
+                        Ok(upload) => SpeedTestUpdate::Done { download, upload },
+                        Err(err) => SpeedTestUpdate::Failed(err),
+                    }
+                }
+                Err(err) => SpeedTestUpdate::Failed(err),
+            };
+            _ = output.send(update).await;
+            futures::future::pending().await
+        },
+    )
+}
+
+fn curl() -> Command {
+    let mut command = Command::new("curl");
+    command
+        .args([
+            "--silent",
+            "--show-error",
+            "--fail",
+            "--output",
+            "/dev/null",
+        ])
+        .args(["--max-time", MAX_TIME_SECS])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    command
+}
+
+/// The speed `curl` wrote out, which is still measured when it stops at the
+/// time limit.
+fn speed(output: &std::process::Output) -> Result<u64, String> {
+    let speed = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|speed| *speed > 0.0);
+    match speed {
+        Some(speed) => Ok(speed as u64),
+        None => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+    }
+}
+
+async fn download(url: &str) -> Result<u64, String> {
+    let output = curl()
+        .args(["--write-out", "%{speed_download}", url])
+        .output()
+        .await
+        .map_err(|err| err.to_string())?;
+    speed(&output)
+}
+
+async fn upload(url: &str) -> Result<u64, String> {
+    let mut child = curl()
+        .args(["--write-out", "%{speed_upload}", "--data-binary", "@-", url])
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|err| err.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Stopping at the time limit closes stdin early
+        _ = stdin.write_all(&vec![0; UPLOAD_BYTES]).await;
+    }
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|err| err.to_string())?;
+    speed(&output)
+}
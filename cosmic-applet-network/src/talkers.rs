@@ -0,0 +1,373 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Network throughput of processes, for the "Top talkers" section.
+//!
+//! The kernel counts the bytes of each TCP socket, which `sock_diag` dumps
+//! along with the socket's inode. The inodes are matched to processes through
+//! their file descriptors in `/proc`, so only the user's own processes are
+//! found. UDP sockets have no such counters and aren't included.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    time::{Duration, Instant},
+};
+
+use cosmic::{
+    iced::{self, subscription},
+    iced_futures::futures::SinkExt,
+};
+use rustix::net::{netlink, AddressFamily, RecvFlags, SendFlags, SocketType};
+
+/// How many processes are listed.
+const TOP_TALKERS: usize = 5;
+
+const SOCK_DIAG_BY_FAMILY: u16 = 20;
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+const NLM_F_REQUEST: u16 = 0x1;
+const NLM_F_DUMP: u16 = 0x300;
+const IPPROTO_TCP: u8 = 6;
+const INET_DIAG_INFO: u16 = 2;
+const NLMSG_HDRLEN: usize = 16;
+/// Offset of the inode in `struct inet_diag_msg`.
+const DIAG_MSG_INODE: usize = 68;
+const DIAG_MSG_LEN: usize = 72;
+/// Offsets of `tcpi_bytes_acked` and `tcpi_bytes_received` in
+/// `struct tcp_info`, there since Linux 4.1.
+const TCP_INFO_BYTES_ACKED: usize = 120;
+const TCP_INFO_BYTES_RECEIVED: usize = 128;
+
+/// A process's throughput, in bytes per second.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Talker {
+    pub name: String,
+    pub received: u64,
+    pub sent: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Counters {
+    received: u64,
+    sent: u64,
+}
+
+/// Samples every `interval` while subscribed.
+pub fn talkers_subscription(interval: Duration) -> iced::Subscription<Vec<Talker>> {
+    subscription::channel(
+        (std::any::TypeId::of::<Talker>(), interval),
+        10,
+        move |mut output| async move {
+            let mut sampler = Sampler::default();
+            let mut timer = tokio::time::interval(interval);
+            timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                timer.tick().await;
+                match sampler.sample() {
+                    Ok(Some(talkers)) => _ = output.send(talkers).await,
+                    Ok(None) => {}
+                    Err(err) => {
+                        tracing::error!("Failed to read socket counters: {}", err);
+                        std::future::pending::<()>().await;
+                    }
+                }
+            }
+        },
+    )
+}
+
+#[derive(Default)]
+struct Sampler {
+    /// Counters of each socket, by inode, as of `last`.
+    sockets: HashMap<u32, Counters>,
+    last: Option<Instant>,
+}
+
+impl Sampler {
+    /// The processes that sent or received the most since the last sample,
+    /// or `None` for the first sample.
+    fn sample(&mut self) -> io::Result<Option<Vec<Talker>>> {
+        let mut sockets = tcp_counters(AddressFamily::INET)?;
+        sockets.extend(tcp_counters(AddressFamily::INET6)?);
+        let now = Instant::now();
+        let previous = std::mem::replace(&mut self.sockets, sockets);
+        let Some(elapsed) = self.last.replace(now).map(|last| now - last) else {
+            return Ok(None);
+        };
+        let seconds = elapsed.as_secs_f64().max(0.001);
+
+        let mut by_name: HashMap<String, Counters> = HashMap::new();
+        for (inode, pid) in socket_owners() {
+            let Some(current) = self.sockets.get(&inode) else {
+                continue;
+            };
+            // Sockets opened since the last sample count from zero
+            let previous = previous.get(&inode).copied().unwrap_or_default();
+            let received = current.received.saturating_sub(previous.received);
+            let sent = current.sent.saturating_sub(previous.sent);
+            if received == 0 && sent == 0 {
+                continue;
+            }
+            let Some(name) = process_name(pid) else {
+                continue;
+            };
+            let counters = by_name.entry(name).or_default();
+            counters.received += received;
+            counters.sent += sent;
+        }
+
+        let mut talkers = by_name
+            .into_iter()
+            .map(|(name, counters)| Talker {
+                name,
+                received: (counters.received as f64 / seconds) as u64,
+                sent: (counters.sent as f64 / seconds) as u64,
+            })
+            .collect::<Vec<_>>();
+        talkers.sort_by_key(|t| std::cmp::Reverse(t.received + t.sent));
+        talkers.truncate(TOP_TALKERS);
+        Ok(Some(talkers))
+    }
+}
+
+/// Byte counters of every TCP socket of `family`, by inode.
+fn tcp_counters(family: AddressFamily) -> io::Result<HashMap<u32, Counters>> {
+    let fd = rustix::net::socket(
+        AddressFamily::NETLINK,
+        SocketType::RAW,
+        Some(netlink::SOCK_DIAG),
+    )?;
+
+    // struct nlmsghdr, then struct inet_diag_req_v2 for every state
+    let mut request = Vec::with_capacity(NLMSG_HDRLEN + 56);
+    request.extend_from_slice(&((NLMSG_HDRLEN + 56) as u32).to_ne_bytes());
+    request.extend_from_slice(&SOCK_DIAG_BY_FAMILY.to_ne_bytes());
+    request.extend_from_slice(&(NLM_F_REQUEST | NLM_F_DUMP).to_ne_bytes());
+    request.extend_from_slice(&[0; 8]);
+    request.extend_from_slice(&[
+        family.as_raw() as u8,
+        IPPROTO_TCP,
+        (1 << (INET_DIAG_INFO - 1)) as u8,
+        0,
+    ]);
+    request.extend_from_slice(&u32::MAX.to_ne_bytes());
+    request.extend_from_slice(&[0; 48]);
+    rustix::net::send(&fd, &request, SendFlags::empty())?;
+
+    let mut counters = HashMap::new();
+    let mut buf = vec![0; 32 * 1024];
+    loop {
+        let len = rustix::net::recv(&fd, &mut buf, RecvFlags::empty())?;
+        if parse_messages(&buf[..len], &mut counters)? {
+            return Ok(counters);
+        }
+    }
+}
+
+/// Adds the sockets in a batch of netlink messages to `counters`, returning
+/// whether the dump is done.
+fn parse_messages(mut messages: &[u8], counters: &mut HashMap<u32, Counters>) -> io::Result<bool> {
+    while messages.len() >= NLMSG_HDRLEN {
+        let msg_len = u32::from_ne_bytes(messages[0..4].try_into().unwrap()) as usize;
+        let msg_type = u16::from_ne_bytes(messages[4..6].try_into().unwrap());
+        if msg_len < NLMSG_HDRLEN || msg_len > messages.len() {
+            return Err(io::Error::from(io::ErrorKind::InvalidData));
+        }
+        match msg_type {
+            NLMSG_DONE => return Ok(true),
+            NLMSG_ERROR => {
+                let errno = messages
+                    .get(NLMSG_HDRLEN..NLMSG_HDRLEN + 4)
+                    .map_or(0, |e| i32::from_ne_bytes(e.try_into().unwrap()));
+                return Err(io::Error::from_raw_os_error(-errno));
+            }
+            SOCK_DIAG_BY_FAMILY => {
+                if let Some((inode, socket)) = parse_diag_msg(&messages[NLMSG_HDRLEN..msg_len]) {
+                    counters.insert(inode, socket);
+                }
+            }
+            _ => {}
+        }
+        messages = &messages[align(msg_len).min(messages.len())..];
+    }
+    Ok(false)
+}
+
+/// The inode and counters in a `struct inet_diag_msg` and its attributes.
+fn parse_diag_msg(msg: &[u8]) -> Option<(u32, Counters)> {
+    let inode = u32::from_ne_bytes(msg.get(DIAG_MSG_INODE..DIAG_MSG_LEN)?.try_into().ok()?);
+    let mut attrs = msg.get(DIAG_MSG_LEN..)?;
+    // struct rtattr, a length and type followed by the data
+    while attrs.len() >= 4 {
+        let attr_len = u16::from_ne_bytes([attrs[0], attrs[1]]) as usize;
+        let attr_type = u16::from_ne_bytes([attrs[2], attrs[3]]);
+        if attr_len < 4 || attr_len > attrs.len() {
+            return None;
+        }
+        if attr_type == INET_DIAG_INFO {
+            let info = &attrs[4..attr_len];
+            let counter = |offset: usize| {
+                Some(u64::from_ne_bytes(
+                    info.get(offset..offset + 8)?.try_into().ok()?,
+                ))
+            };
+            return Some((
+                inode,
+                Counters {
+                    received: counter(TCP_INFO_BYTES_RECEIVED)?,
+                    sent: counter(TCP_INFO_BYTES_ACKED)?,
+                },
+            ));
+        }
+        attrs = &attrs[align(attr_len).min(attrs.len())..];
+    }
+    None
+}
+
+/// Netlink messages and attributes are aligned to 4 bytes.
+fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// Socket inodes and the processes with them open.
+fn socket_owners() -> Vec<(u32, u32)> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    let mut owners = Vec::new();
+    for entry in entries.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|p| p.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        // Other users' processes can't be read
+        let Ok(fds) = fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        for fd in fds.flatten() {
+            let Ok(target) = fs::read_link(fd.path()) else {
+                continue;
+            };
+            let inode = target
+                .to_str()
+                .and_then(|t| t.strip_prefix("socket:["))
+                .and_then(|t| t.strip_suffix(']'))
+                .and_then(|t| t.parse::<u32>().ok());
+            if let Some(inode) = inode {
+                owners.push((inode, pid));
+            }
+        }
+    }
+    // A socket shared by processes, like after a fork, counts once
+    owners.sort_unstable();
+    owners.dedup_by_key(|(inode, _)| *inode);
+    owners
+}
+
+fn process_name(pid: u32) -> Option<String> {
+    let comm = fs::read_to_string(format!("/proc/{pid}/comm")).ok()?;
+    Some(comm.trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `struct nlmsghdr` of `msg_type` around `payload`, padded.
+    fn message(msg_type: u16, payload: &[u8]) -> Vec<u8> {
+        let mut message = Vec::new();
+        message.extend_from_slice(&((NLMSG_HDRLEN + payload.len()) as u32).to_ne_bytes());
+        message.extend_from_slice(&msg_type.to_ne_bytes());
+        message.extend_from_slice(&[0; 10]);
+        message.extend_from_slice(payload);
+        message.resize(align(message.len()), 0);
+        message
+    }
+
+    /// A `struct rtattr` of `attr_type` around `data`, padded.
+    fn attr(attr_type: u16, data: &[u8]) -> Vec<u8> {
+        let mut attr = Vec::new();
+        attr.extend_from_slice(&((4 + data.len()) as u16).to_ne_bytes());
+        attr.extend_from_slice(&attr_type.to_ne_bytes());
+        attr.extend_from_slice(data);
+        attr.resize(align(attr.len()), 0);
+        attr
+    }
+
+    /// A `struct inet_diag_msg` of a socket, with `attrs` after it.
+    fn diag_msg(inode: u32, attrs: &[Vec<u8>]) -> Vec<u8> {
+        let mut msg = vec![0; DIAG_MSG_INODE];
+        msg.extend_from_slice(&inode.to_ne_bytes());
+        attrs.iter().for_each(|attr| msg.extend_from_slice(attr));
+        msg
+    }
+
+    /// A `struct tcp_info` with its byte counters set.
+    fn tcp_info(received: u64, sent: u64) -> Vec<u8> {
+        let mut info = vec![0; 232];
+        info[TCP_INFO_BYTES_ACKED..][..8].copy_from_slice(&sent.to_ne_bytes());
+        info[TCP_INFO_BYTES_RECEIVED..][..8].copy_from_slice(&received.to_ne_bytes());
+        info
+    }
+
+    #[test]
+    fn diag_msg_counters() {
+        // The info follows another attribute of an odd length
+        let msg = diag_msg(
+            42,
+            &[attr(1, &[0; 5]), attr(INET_DIAG_INFO, &tcp_info(1000, 200))],
+        );
+        let (inode, counters) = parse_diag_msg(&msg).unwrap();
+        assert_eq!(inode, 42);
+        assert_eq!((counters.received, counters.sent), (1000, 200));
+    }
+
+    #[test]
+    fn diag_msg_without_info() {
+        assert!(parse_diag_msg(&diag_msg(42, &[attr(1, &[0; 8])])).is_none());
+        // Kernels before 4.1 have a shorter `struct tcp_info`
+        let short = attr(INET_DIAG_INFO, &tcp_info(1000, 200)[..104]);
+        assert!(parse_diag_msg(&diag_msg(42, &[short])).is_none());
+        assert!(parse_diag_msg(&[0; DIAG_MSG_INODE]).is_none());
+    }
+
+    #[test]
+    fn diag_msg_with_bad_attr_length() {
+        let mut attr = attr(INET_DIAG_INFO, &tcp_info(1000, 200));
+        attr[0..2].copy_from_slice(&u16::MAX.to_ne_bytes());
+        assert!(parse_diag_msg(&diag_msg(42, &[attr])).is_none());
+    }
+
+    #[test]
+    fn messages_until_done() {
+        let info = |received, sent| vec![attr(INET_DIAG_INFO, &tcp_info(received, sent))];
+        let mut batch = message(SOCK_DIAG_BY_FAMILY, &diag_msg(1, &info(10, 20)));
+        batch.extend(message(SOCK_DIAG_BY_FAMILY, &diag_msg(2, &info(30, 40))));
+        let mut counters = HashMap::new();
+        assert!(!parse_messages(&batch, &mut counters).unwrap());
+        assert_eq!(counters.len(), 2);
+        assert_eq!(counters[&2].received, 30);
+
+        let done = message(NLMSG_DONE, &0i32.to_ne_bytes());
+        assert!(parse_messages(&done, &mut counters).unwrap());
+    }
+
+    #[test]
+    fn error_messages() {
+        let error = message(NLMSG_ERROR, &(-enoent()).to_ne_bytes());
+        let err = parse_messages(&error, &mut HashMap::new()).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(enoent()));
+
+        let mut truncated = message(SOCK_DIAG_BY_FAMILY, &diag_msg(1, &[]));
+        truncated.truncate(NLMSG_HDRLEN + 4);
+        let err = parse_messages(&truncated, &mut HashMap::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    fn enoent() -> i32 {
+        rustix::io::Errno::NOENT.raw_os_error()
+    }
+}
@@ -0,0 +1,7 @@
+night-light = Night light
+sunset-to-sunrise = Sunset to sunrise
+active-until-sunrise = On until sunrise
+starts-at-sunset = Turns on at sunset
+locating = Finding your location...
+location-unavailable = Location unavailable, staying on
+unsupported = Night light isn't supported by the compositor
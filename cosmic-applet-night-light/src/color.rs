@@ -0,0 +1,45 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+/// Color temperature of an unfiltered display.
+pub const NEUTRAL: u32 = 6500;
+
+/// RGB multipliers for a black body at `temperature` kelvin, relative to
+/// 6500K, following Tanner Helland's approximation.
+pub fn whitepoint(temperature: u32) -> [f64; 3] {
+    let t = temperature.clamp(1000, 40000) as f64 / 100.0;
+
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698727446 * (t - 60.0).powf(-0.1332047592)
+    };
+    let green = if t <= 66.0 {
+        99.4708025861 * t.ln() - 161.1195681661
+    } else {
+        288.1221695283 * (t - 60.0).powf(-0.0755148492)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.5177312231 * (t - 10.0).ln() - 305.0447927307
+    };
+
+    [red, green, blue].map(|c| (c / 255.0).clamp(0.0, 1.0))
+}
+
+/// Gamma ramps in the layout `zwlr_gamma_control_v1.set_gamma` expects: all
+/// red values, then green, then blue.
+pub fn gamma_ramps(size: usize, temperature: u32) -> Vec<u16> {
+    let whitepoint = whitepoint(temperature);
+    let mut ramps = Vec::with_capacity(size * 3);
+    for channel in whitepoint {
+        for i in 0..size {
+            let value = i as f64 / (size.max(2) - 1) as f64 * channel;
+            ramps.push((value * u16::MAX as f64).round() as u16);
+        }
+    }
+    ramps
+}
@@ -0,0 +1,30 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
+pub const APP_ID: &str = "com.system76.CosmicAppletNightLight";
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, CosmicConfigEntry)]
+#[version = 1]
+pub struct NightLightConfig {
+    pub enabled: bool,
+    /// Color temperature in kelvin while the filter is active.
+    pub temperature: u32,
+    /// Only filter between sunset and sunrise.
+    pub sunset_to_sunrise: bool,
+    /// Last known latitude and longitude, used until GeoClue responds.
+    pub location: Option<(f64, f64)>,
+}
+
+impl Default for NightLightConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            temperature: 4000,
+            sunset_to_sunrise: true,
+            location: None,
+        }
+    }
+}
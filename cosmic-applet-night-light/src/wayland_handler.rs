@@ -0,0 +1,273 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Applies the color temperature through the wlr-gamma-control protocol.
+//!
+//! Gamma ramps are reset by the compositor when their control is destroyed,
+//! so controls are kept for as long as the filter is active.
+
+use std::{
+    fs::File,
+    io::{Seek, Write},
+    os::{
+        fd::{AsFd, FromRawFd, RawFd},
+        unix::net::UnixStream,
+    },
+};
+
+use cosmic::{
+    cctk::{
+        sctk::{
+            self,
+            output::{OutputHandler, OutputState},
+            reexports::{calloop, calloop_wayland_source::WaylandSource},
+            registry::{ProvidesRegistryState, RegistryState},
+        },
+        wayland_client::{
+            globals::registry_queue_init, protocol::wl_output::WlOutput, Connection, Dispatch,
+            Proxy, QueueHandle,
+        },
+    },
+    iced_futures::futures::channel::mpsc::UnboundedSender,
+};
+use wayland_protocols_wlr::gamma_control::v1::client::{
+    zwlr_gamma_control_manager_v1::ZwlrGammaControlManagerV1,
+    zwlr_gamma_control_v1::{self, ZwlrGammaControlV1},
+};
+
+use crate::{
+    color::gamma_ramps,
+    wayland_subscription::{WaylandRequest, WaylandUpdate},
+};
+
+struct Output {
+    wl_output: WlOutput,
+    control: Option<ZwlrGammaControlV1>,
+    /// Number of entries per channel, once the compositor sends it.
+    size: Option<usize>,
+    /// Another client already controls this output's gamma.
+    failed: bool,
+}
+
+struct AppData {
+    exit: bool,
+    queue_handle: QueueHandle<Self>,
+    registry_state: RegistryState,
+    output_state: OutputState,
+    manager: ZwlrGammaControlManagerV1,
+    outputs: Vec<Output>,
+    temperature: Option<u32>,
+}
+
+impl AppData {
+    fn apply(&mut self) {
+        for output in &mut self.outputs {
+            match self.temperature {
+                Some(temperature) => match (&output.control, output.size) {
+                    (Some(control), Some(size)) => set_gamma(control, size, temperature),
+                    // Applied once the size is known
+                    (Some(_), None) => {}
+                    (None, _) if !output.failed => {
+                        output.control = Some(self.manager.get_gamma_control(
+                            &output.wl_output,
+                            &self.queue_handle,
+                            output.wl_output.clone(),
+                        ));
+                    }
+                    (None, _) => {}
+                },
+                None => {
+                    if let Some(control) = output.control.take() {
+                        control.destroy();
+                    }
+                    output.size = None;
+                    output.failed = false;
+                }
+            }
+        }
+    }
+}
+
+fn set_gamma(control: &ZwlrGammaControlV1, size: usize, temperature: u32) {
+    let ramps = gamma_ramps(size, temperature);
+    let bytes = ramps
+        .iter()
+        .flat_map(|value| value.to_ne_bytes())
+        .collect::<Vec<_>>();
+
+    let fd = match rustix::fs::memfd_create(
+        "night-light-gamma",
+        rustix::fs::MemfdFlags::CLOEXEC | rustix::fs::MemfdFlags::ALLOW_SEALING,
+    ) {
+        Ok(fd) => fd,
+        Err(err) => {
+            tracing::error!("Failed to create gamma ramp fd: {err}");
+            return;
+        }
+    };
+    let mut file = File::from(fd);
+    if let Err(err) = file.write_all(&bytes).and_then(|_| file.rewind()) {
+        tracing::error!("Failed to write gamma ramps: {err}");
+        return;
+    }
+    control.set_gamma(file.as_fd());
+}
+
+impl ProvidesRegistryState for AppData {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+
+    sctk::registry_handlers!(OutputState);
+}
+
+impl OutputHandler for AppData {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.output_state
+    }
+
+    fn new_output(&mut self, _: &Connection, _: &QueueHandle<Self>, wl_output: WlOutput) {
+        self.outputs.push(Output {
+            wl_output,
+            control: None,
+            size: None,
+            failed: false,
+        });
+        self.apply();
+    }
+
+    fn update_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: WlOutput) {}
+
+    fn output_destroyed(&mut self, _: &Connection, _: &QueueHandle<Self>, wl_output: WlOutput) {
+        self.outputs.retain(|output| {
+            if output.wl_output != wl_output {
+                return true;
+            }
+            if let Some(control) = &output.control {
+                control.destroy();
+            }
+            false
+        });
+    }
+}
+
+impl Dispatch<ZwlrGammaControlManagerV1, ()> for AppData {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrGammaControlManagerV1,
+        _: <ZwlrGammaControlManagerV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrGammaControlV1, WlOutput> for AppData {
+    fn event(
+        state: &mut Self,
+        control: &ZwlrGammaControlV1,
+        event: zwlr_gamma_control_v1::Event,
+        wl_output: &WlOutput,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let Some(output) = state
+            .outputs
+            .iter_mut()
+            .find(|output| &output.wl_output == wl_output)
+        else {
+            return;
+        };
+        match event {
+            zwlr_gamma_control_v1::Event::GammaSize { size } => {
+                output.size = Some(size as usize);
+                if let Some(temperature) = state.temperature {
+                    set_gamma(control, size as usize, temperature);
+                }
+            }
+            zwlr_gamma_control_v1::Event::Failed => {
+                tracing::warn!("Gamma control failed, another client may be using it");
+                control.destroy();
+                output.control = None;
+                output.size = None;
+                output.failed = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+pub(crate) fn wayland_handler(
+    tx: UnboundedSender<WaylandUpdate>,
+    rx: calloop::channel::Channel<WaylandRequest>,
+) {
+    let socket = std::env::var("X_PRIVILEGED_WAYLAND_SOCKET")
+        .ok()
+        .and_then(|fd| {
+            fd.parse::<RawFd>()
+                .ok()
+                .map(|fd| unsafe { UnixStream::from_raw_fd(fd) })
+        });
+
+    let conn = if let Some(socket) = socket {
+        Connection::from_socket(socket).unwrap()
+    } else {
+        Connection::connect_to_env().unwrap()
+    };
+    let (globals, event_queue) = registry_queue_init(&conn).unwrap();
+
+    let mut event_loop = calloop::EventLoop::<AppData>::try_new().unwrap();
+    let qh = event_queue.handle();
+    let wayland_source = WaylandSource::new(conn.clone(), event_queue);
+    let handle = event_loop.handle();
+    wayland_source
+        .insert(handle.clone())
+        .expect("Failed to insert wayland source.");
+
+    let manager = match globals.bind::<ZwlrGammaControlManagerV1, _, _>(&qh, 1..=1, ()) {
+        Ok(manager) => manager,
+        Err(err) => {
+            tracing::error!("Compositor doesn't support wlr-gamma-control: {err}");
+            let _ = tx.unbounded_send(WaylandUpdate::Unsupported);
+            return;
+        }
+    };
+
+    if handle
+        .insert_source(rx, |event, _, state| match event {
+            calloop::channel::Event::Msg(WaylandRequest::SetTemperature(temperature)) => {
+                if state.temperature != temperature {
+                    state.temperature = temperature;
+                    state.apply();
+                }
+            }
+            calloop::channel::Event::Closed => {
+                state.exit = true;
+            }
+        })
+        .is_err()
+    {
+        return;
+    }
+
+    let mut app_data = AppData {
+        exit: false,
+        queue_handle: qh.clone(),
+        registry_state: RegistryState::new(&globals),
+        output_state: OutputState::new(&globals, &qh),
+        manager,
+        outputs: Vec::new(),
+        temperature: None,
+    };
+
+    loop {
+        if app_data.exit {
+            break;
+        }
+        event_loop.dispatch(None, &mut app_data).unwrap();
+    }
+}
+
+sctk::delegate_output!(AppData);
+sctk::delegate_registry!(AppData);
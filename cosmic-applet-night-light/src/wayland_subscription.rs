@@ -0,0 +1,88 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::{
+    cctk::sctk::reexports::calloop,
+    iced::{self, subscription},
+    iced_futures::futures,
+};
+use futures::{
+    channel::mpsc::{unbounded, UnboundedReceiver},
+    SinkExt, StreamExt,
+};
+use once_cell::sync::Lazy;
+use tokio::sync::Mutex;
+
+use crate::wayland_handler::wayland_handler;
+
+pub static WAYLAND_RX: Lazy<Mutex<Option<UnboundedReceiver<WaylandUpdate>>>> =
+    Lazy::new(|| Mutex::new(None));
+
+pub fn wayland_subscription() -> iced::Subscription<WaylandUpdate> {
+    subscription::channel(
+        std::any::TypeId::of::<WaylandUpdate>(),
+        50,
+        move |mut output| async move {
+            let mut state = State::Waiting;
+
+            loop {
+                state = start_listening(state, &mut output).await;
+            }
+        },
+    )
+}
+
+pub enum State {
+    Waiting,
+    Finished,
+}
+
+async fn start_listening(
+    state: State,
+    output: &mut futures::channel::mpsc::Sender<WaylandUpdate>,
+) -> State {
+    match state {
+        State::Waiting => {
+            let mut guard = WAYLAND_RX.lock().await;
+            let rx = {
+                if guard.is_none() {
+                    let (calloop_tx, calloop_rx) = calloop::channel::channel();
+                    let (gamma_tx, gamma_rx) = unbounded();
+                    let _ = std::thread::spawn(move || {
+                        wayland_handler(gamma_tx, calloop_rx);
+                    });
+                    *guard = Some(gamma_rx);
+                    _ = output.send(WaylandUpdate::Init(calloop_tx)).await;
+                }
+                guard.as_mut().unwrap()
+            };
+            match rx.next().await {
+                Some(u) => {
+                    _ = output.send(u).await;
+                    State::Waiting
+                }
+                None => {
+                    _ = output.send(WaylandUpdate::Finished).await;
+                    tracing::error!("Wayland handler thread died");
+                    State::Finished
+                }
+            }
+        }
+        State::Finished => iced::futures::future::pending().await,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum WaylandUpdate {
+    Init(calloop::channel::Sender<WaylandRequest>),
+    Finished,
+    /// The compositor doesn't support wlr-gamma-control.
+    Unsupported,
+}
+
+#[derive(Clone, Debug)]
+pub enum WaylandRequest {
+    /// Color temperature to apply to every output, or `None` to restore
+    /// the default ramps.
+    SetTemperature(Option<u32>),
+}
@@ -0,0 +1,283 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::time::Duration;
+
+use crate::{
+    color::NEUTRAL,
+    config::{self, NightLightConfig},
+    fl,
+    wayland_subscription::{wayland_subscription, WaylandRequest, WaylandUpdate},
+};
+use cosmic::{
+    app::Core,
+    applet::padded_control,
+    cctk::sctk::reexports::calloop,
+    cosmic_config::{Config, CosmicConfigEntry},
+    iced::{
+        alignment::Horizontal,
+        time,
+        widget::{column, row, slider},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_widget::Column,
+    widget::{divider, icon, text, toggler},
+    Element,
+};
+use cosmic_applet_helper::{geoclue, sun, AppletMessage, CosmicApplet};
+
+const MIN_TEMPERATURE: u32 = 1500;
+
+#[derive(Default)]
+pub struct Window {
+    config: NightLightConfig,
+    config_helper: Option<Config>,
+    /// Whether the sun is down at the configured location.
+    night: bool,
+    /// Waiting on GeoClue.
+    locating: bool,
+    unsupported: bool,
+    wayland_tx: Option<calloop::channel::Sender<WaylandRequest>>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    ConfigChanged(NightLightConfig),
+    Wayland(WaylandUpdate),
+    Location(Result<(f64, f64), String>),
+    Tick,
+    Enabled(bool),
+    SunsetToSunrise(bool),
+    Temperature(u32),
+    TemperatureReleased,
+}
+
+impl Window {
+    /// Whether the filter should be applied right now.
+    fn active(&self) -> bool {
+        self.config.enabled
+            && (!self.config.sunset_to_sunrise || self.config.location.is_none() || self.night)
+    }
+
+    fn update_night(&mut self) {
+        if let Some((latitude, longitude)) = self.config.location {
+            self.night = sun::is_night(chrono::Utc::now(), latitude, longitude);
+        }
+    }
+
+    fn apply(&self) {
+        let Some(tx) = self.wayland_tx.as_ref() else {
+            return;
+        };
+        let temperature = self.active().then_some(self.config.temperature);
+        let _ = tx.send(WaylandRequest::SetTemperature(temperature));
+    }
+
+    /// Refreshes the location, which is looked up on startup and when the
+    /// schedule is turned on.
+    fn locate(&mut self) -> Command<Message> {
+        if self.locating || !self.config.sunset_to_sunrise {
+            return Command::none();
+        }
+        self.locating = true;
+        Command::perform(geoclue::location(config::APP_ID), |res| {
+            Message::Location(res.map_err(|err| err.to_string()))
+        })
+    }
+
+    fn status(&self) -> Option<String> {
+        if !self.config.enabled || !self.config.sunset_to_sunrise {
+            return None;
+        }
+        Some(if self.config.location.is_none() {
+            if self.locating {
+                fl!("locating")
+            } else {
+                fl!("location-unavailable")
+            }
+        } else if self.night {
+            fl!("active-until-sunrise")
+        } else {
+            fl!("starts-at-sunset")
+        })
+    }
+
+    fn set_config<T>(
+        &mut self,
+        set: impl FnOnce(
+            &mut NightLightConfig,
+            &Config,
+            T,
+        ) -> Result<bool, cosmic::cosmic_config::Error>,
+        value: T,
+    ) {
+        if let Some(helper) = &self.config_helper {
+            if let Err(err) = set(&mut self.config, helper, value) {
+                tracing::error!(?err, "Error writing config");
+            }
+        }
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = config::APP_ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::APP_ID, NightLightConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                NightLightConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let mut window = Self {
+            config,
+            config_helper,
+            ..Default::default()
+        };
+        window.update_night();
+        let command = window.locate();
+        (window, command)
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        let mut subscriptions = vec![
+            wayland_subscription().map(Message::Wayland),
+            core.watch_config::<NightLightConfig>(config::APP_ID)
+                .map(|u| Message::ConfigChanged(u.config)),
+        ];
+        if self.config.enabled && self.config.sunset_to_sunrise {
+            subscriptions.push(time::every(Duration::from_secs(60)).map(|_| Message::Tick));
+        }
+        Subscription::batch(subscriptions)
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::ConfigChanged(config) => {
+                self.config = config;
+                self.update_night();
+                self.apply();
+            }
+            Message::Wayland(update) => match update {
+                WaylandUpdate::Init(tx) => {
+                    self.wayland_tx = Some(tx);
+                    self.apply();
+                }
+                WaylandUpdate::Finished => {
+                    self.wayland_tx = None;
+                }
+                WaylandUpdate::Unsupported => {
+                    self.unsupported = true;
+                }
+            },
+            Message::Location(res) => {
+                self.locating = false;
+                match res {
+                    Ok(location) => {
+                        self.set_config(NightLightConfig::set_location, Some(location));
+                        self.update_night();
+                        self.apply();
+                    }
+                    Err(err) => tracing::error!("Failed to get location: {}", err),
+                }
+            }
+            Message::Tick => {
+                self.update_night();
+                self.apply();
+            }
+            Message::Enabled(enabled) => {
+                self.set_config(NightLightConfig::set_enabled, enabled);
+                self.apply();
+            }
+            Message::SunsetToSunrise(sunset_to_sunrise) => {
+                self.set_config(NightLightConfig::set_sunset_to_sunrise, sunset_to_sunrise);
+                self.update_night();
+                self.apply();
+                return self.locate();
+            }
+            Message::Temperature(temperature) => {
+                // Previewed while dragging, saved on release
+                self.config.temperature = temperature;
+                self.apply();
+            }
+            Message::TemperatureReleased => {
+                let temperature = self.config.temperature;
+                self.set_config(NightLightConfig::set_temperature, temperature);
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        core.applet
+            .icon_button(if self.active() {
+                "night-light-symbolic"
+            } else {
+                "night-light-disabled-symbolic"
+            })
+            .on_press(AppletMessage::TogglePopup)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = Column::new().padding([8, 0]);
+
+        if self.unsupported {
+            content = content.push(padded_control(text(fl!("unsupported")).size(14)));
+            return Element::from(content).map(AppletMessage::App);
+        }
+
+        let mut enabled = column![toggler(
+            Some(fl!("night-light")),
+            self.config.enabled,
+            Message::Enabled
+        )
+        .text_size(14)
+        .width(Length::Fill)];
+        if let Some(status) = self.status() {
+            enabled = enabled.push(text(status).size(10));
+        }
+        content = content
+            .push(padded_control(enabled))
+            .push(padded_control(divider::horizontal::default()))
+            .push(padded_control(
+                row![
+                    icon::from_name("night-light-symbolic")
+                        .size(24)
+                        .symbolic(true),
+                    slider(
+                        MIN_TEMPERATURE..=NEUTRAL,
+                        self.config.temperature.clamp(MIN_TEMPERATURE, NEUTRAL),
+                        Message::Temperature
+                    )
+                    .step(100u32)
+                    .on_release(Message::TemperatureReleased),
+                    text(format!("{}K", self.config.temperature))
+                        .size(16)
+                        .width(Length::Fixed(56.0))
+                        .horizontal_alignment(Horizontal::Right),
+                ]
+                .spacing(12)
+                .align_items(Alignment::Center),
+            ))
+            .push(padded_control(
+                toggler(
+                    Some(fl!("sunset-to-sunrise")),
+                    self.config.sunset_to_sunrise,
+                    Message::SunsetToSunrise,
+                )
+                .text_size(14)
+                .width(Length::Fill),
+            ));
+
+        Element::from(content).map(AppletMessage::App)
+    }
+}
@@ -0,0 +1,3 @@
+default-note = Notes
+note-name = Note name
+format-markdown = Format as markdown
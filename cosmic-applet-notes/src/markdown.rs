@@ -0,0 +1,74 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Light markdown rendering: headings, lists, task lists, quotes and code
+//! blocks, without inline formatting.
+
+use cosmic::{
+    font::FONT_BOLD,
+    iced::{Font, Length},
+    iced_widget::{Column, Row},
+    widget::{container, text},
+    Element,
+};
+
+fn bullet<'a, M: 'a>(marker: &'a str, content: &'a str, indent: usize) -> Element<'a, M> {
+    Row::with_children(vec![
+        text(marker).size(14).into(),
+        text(content).size(14).width(Length::Fill).into(),
+    ])
+    .spacing(6)
+    .padding([0, 0, 0, 12 * indent as u16])
+    .into()
+}
+
+fn line<'a, M: 'a>(line: &'a str) -> Element<'a, M> {
+    let trimmed = line.trim_start();
+    let indent = (line.len() - trimmed.len()) / 2;
+
+    let heading = trimmed.chars().take_while(|c| *c == '#').count();
+    if (1..=3).contains(&heading) && trimmed[heading..].starts_with(' ') {
+        let size = [20, 17, 15][heading - 1];
+        return text(trimmed[heading..].trim())
+            .size(size)
+            .font(FONT_BOLD)
+            .into();
+    }
+
+    for (prefix, marker) in [
+        ("- [ ] ", "☐"),
+        ("- [x] ", "☑"),
+        ("- [X] ", "☑"),
+        ("- ", "•"),
+        ("* ", "•"),
+    ] {
+        if let Some(content) = trimmed.strip_prefix(prefix) {
+            return bullet(marker, content, indent);
+        }
+    }
+
+    if let Some(quote) = trimmed.strip_prefix('>') {
+        return container(text(quote.trim()).size(14))
+            .padding([0, 0, 0, 12])
+            .into();
+    }
+
+    text(line).size(14).width(Length::Fill).into()
+}
+
+pub fn view<'a, M: 'a>(markdown: &'a str) -> Element<'a, M> {
+    let mut lines = Vec::new();
+    let mut in_code = false;
+    for l in markdown.lines() {
+        if l.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        lines.push(if in_code {
+            text(l).size(13).font(Font::MONOSPACE).into()
+        } else {
+            line(l)
+        });
+    }
+    Column::with_children(lines).spacing(4).into()
+}
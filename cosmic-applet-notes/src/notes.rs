@@ -0,0 +1,61 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Notes, stored as plain text files in the user's data directory.
+
+use std::{fs, io, path::PathBuf};
+
+const EXTENSION: &str = "txt";
+
+fn dir() -> io::Result<PathBuf> {
+    dirs::data_dir()
+        .map(|dir| dir.join("cosmic-applet-notes"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))
+}
+
+fn path(name: &str) -> io::Result<PathBuf> {
+    Ok(dir()?.join(name).with_extension(EXTENSION))
+}
+
+/// A name usable as a file name, or `None` if there's nothing left of it.
+pub fn sanitize(name: &str) -> Option<String> {
+    let name = name.trim().replace('/', "-");
+    let name = name.trim_start_matches('.');
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Names of every note, sorted.
+pub fn list() -> Vec<String> {
+    let Ok(entries) = dir().and_then(fs::read_dir) else {
+        return Vec::new();
+    };
+    let mut names = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != EXTENSION {
+                return None;
+            }
+            Some(path.file_stem()?.to_string_lossy().into_owned())
+        })
+        .collect::<Vec<_>>();
+    names.sort_by_key(|name| name.to_lowercase());
+    names
+}
+
+pub fn load(name: &str) -> String {
+    path(name).and_then(fs::read_to_string).unwrap_or_default()
+}
+
+/// Saves a note through a temporary file, so it isn't lost to a crash
+/// halfway through writing.
+pub fn save(name: &str, text: &str) -> io::Result<()> {
+    fs::create_dir_all(dir()?)?;
+    let path = path(name)?;
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, text)?;
+    fs::rename(tmp, path)
+}
+
+pub fn delete(name: &str) -> io::Result<()> {
+    fs::remove_file(path(name)?)
+}
@@ -0,0 +1,280 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    config::{self, NotesConfig},
+    fl, markdown, notes,
+};
+use cosmic::{
+    app::Core,
+    applet::padded_control,
+    cosmic_config::{Config, CosmicConfigEntry},
+    iced::{
+        time,
+        widget::{row, text_editor},
+        Alignment, Command, Length, Limits, Subscription,
+    },
+    iced_widget::Column,
+    widget::{button, container, divider, dropdown, icon, scrollable, text_input, toggler},
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent, PopupWidth};
+
+const ICON: &str = "com.system76.CosmicAppletNotes-symbolic";
+/// How long after the last edit a note is saved.
+const AUTOSAVE_DELAY: Duration = Duration::from_secs(1);
+const EDITOR_HEIGHT: f32 = 300.0;
+
+#[derive(Default)]
+pub struct Window {
+    config: NotesConfig,
+    config_helper: Option<Config>,
+    names: Vec<String>,
+    current: String,
+    content: text_editor::Content,
+    /// Text of `content`, kept for the markdown view.
+    text: String,
+    /// When the note was last edited, if it hasn't been saved since.
+    edited: Option<Instant>,
+    /// Name being typed for a new note.
+    new_name: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    ConfigChanged(NotesConfig),
+    Edit(text_editor::Action),
+    Select(usize),
+    NewNote,
+    NewName(String),
+    CreateNote,
+    CancelNewNote,
+    Delete,
+    Markdown(bool),
+    Autosave,
+}
+
+impl Window {
+    fn save(&mut self) {
+        if self.edited.take().is_some() {
+            if let Err(err) = notes::save(&self.current, &self.text) {
+                tracing::error!("Failed to save note {}: {}", self.current, err);
+            }
+        }
+    }
+
+    /// Saves the current note, and opens another.
+    fn open(&mut self, name: String) {
+        self.save();
+        self.text = notes::load(&name);
+        self.content = text_editor::Content::with_text(&self.text);
+        self.current = name;
+        if !self.names.contains(&self.current) {
+            self.names.push(self.current.clone());
+            self.names.sort_by_key(|name| name.to_lowercase());
+        }
+        if let Some(helper) = &self.config_helper {
+            if let Err(err) = self.config.set_current(helper, self.current.clone()) {
+                tracing::error!(?err, "Error writing config");
+            }
+        }
+    }
+
+    fn header(&self) -> Element<Message> {
+        if let Some(new_name) = self.new_name.as_ref() {
+            return row![
+                text_input(fl!("note-name"), new_name)
+                    .on_input(Message::NewName)
+                    .on_submit(Message::CreateNote)
+                    .width(Length::Fill),
+                button::icon(icon::from_name("object-select-symbolic").size(16))
+                    .on_press_maybe(notes::sanitize(new_name).map(|_| Message::CreateNote)),
+                button::icon(icon::from_name("window-close-symbolic").size(16))
+                    .on_press(Message::CancelNewNote),
+            ]
+            .spacing(4)
+            .align_items(Alignment::Center)
+            .into();
+        }
+
+        let selected = self.names.iter().position(|name| name == &self.current);
+        row![
+            container(dropdown(&self.names, selected, Message::Select)).width(Length::Fill),
+            button::icon(icon::from_name("list-add-symbolic").size(16)).on_press(Message::NewNote),
+            button::icon(icon::from_name("edit-delete-symbolic").size(16))
+                .on_press(Message::Delete),
+        ]
+        .spacing(4)
+        .align_items(Alignment::Center)
+        .into()
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = config::APP_ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::APP_ID, NotesConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                NotesConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let names = notes::list();
+        let current = if names.contains(&config.current) {
+            config.current.clone()
+        } else {
+            names
+                .first()
+                .cloned()
+                .unwrap_or_else(|| fl!("default-note"))
+        };
+        let mut window = Self {
+            config,
+            config_helper,
+            names,
+            ..Default::default()
+        };
+        window.open(current);
+        (window, Command::none())
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        match event {
+            PanelEvent::PopupOpened => self.new_name = None,
+            PanelEvent::PopupClosed => self.save(),
+        }
+        Command::none()
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        let mut subscriptions = vec![core
+            .watch_config::<NotesConfig>(config::APP_ID)
+            .map(|u| Message::ConfigChanged(u.config))];
+        if self.edited.is_some() {
+            subscriptions.push(time::every(AUTOSAVE_DELAY).map(|_| Message::Autosave));
+        }
+        Subscription::batch(subscriptions)
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::ConfigChanged(config) => {
+                self.config = config;
+            }
+            Message::Edit(action) => {
+                let is_edit = action.is_edit();
+                self.content.perform(action);
+                if is_edit {
+                    self.text = self.content.text();
+                    self.edited = Some(Instant::now());
+                }
+            }
+            Message::Select(i) => {
+                if let Some(name) = self.names.get(i).cloned() {
+                    self.open(name);
+                }
+            }
+            Message::NewNote => {
+                self.new_name = Some(String::new());
+            }
+            Message::NewName(name) => {
+                self.new_name = Some(name);
+            }
+            Message::CreateNote => {
+                if let Some(name) = self.new_name.take().as_deref().and_then(notes::sanitize) {
+                    self.open(name);
+                }
+            }
+            Message::CancelNewNote => {
+                self.new_name = None;
+            }
+            Message::Delete => {
+                self.edited = None;
+                if let Err(err) = notes::delete(&self.current) {
+                    if err.kind() != std::io::ErrorKind::NotFound {
+                        tracing::error!("Failed to delete note {}: {}", self.current, err);
+                        return Command::none();
+                    }
+                }
+                self.names.retain(|name| name != &self.current);
+                let next = self
+                    .names
+                    .first()
+                    .cloned()
+                    .unwrap_or_else(|| fl!("default-note"));
+                self.open(next);
+            }
+            Message::Markdown(markdown) => {
+                self.save();
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_markdown(helper, markdown) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+            Message::Autosave => {
+                if self
+                    .edited
+                    .map_or(false, |edited| edited.elapsed() >= AUTOSAVE_DELAY)
+                {
+                    self.save();
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        core.applet
+            .icon_button(ICON)
+            .on_press(AppletMessage::TogglePopup)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let note: Element<_> = if self.config.markdown {
+            scrollable(markdown::view(&self.text))
+                .height(Length::Fixed(EDITOR_HEIGHT))
+                .into()
+        } else {
+            text_editor(&self.content)
+                .on_action(Message::Edit)
+                .height(Length::Fixed(EDITOR_HEIGHT))
+                .padding(8)
+                .into()
+        };
+
+        let content = vec![
+            padded_control(self.header()).into(),
+            padded_control(note).into(),
+            padded_control(divider::horizontal::default()).into(),
+            padded_control(
+                toggler(
+                    Some(fl!("format-markdown")),
+                    self.config.markdown,
+                    Message::Markdown,
+                )
+                .text_size(14)
+                .width(Length::Fill),
+            )
+            .into(),
+        ];
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+
+    fn popup_limits(&self, _core: &Core) -> Limits {
+        PopupWidth::Standard.limits().min_height(200.0)
+    }
+}
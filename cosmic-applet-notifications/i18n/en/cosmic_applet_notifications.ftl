@@ -12,4 +12,16 @@ clear-group = Clear group
 clear-all = Clear all notifications
 do-not-disturb = Do Not Disturb
 notification-settings = Notification settings...
-no-notifications = No notifications
\ No newline at end of file
+no-notifications = No notifications
+filters = Filters...
+back = Back
+filters-description = Notifications matching a filter are kept out of banners, but still listed here.
+any-app = Any app
+any-text = Any text
+filter-app-name = App name
+filter-keyword = Keyword
+add-filter = Add filter
+reply = Reply
+reply-placeholder = Reply…
+send = Send
+cancel = Cancel
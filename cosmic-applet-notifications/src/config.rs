@@ -0,0 +1,36 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use cosmic_notifications_util::Notification;
+use serde::{Deserialize, Serialize};
+
+pub const APP_ID: &str = "com.system76.CosmicAppletNotifications";
+
+/// Keeps matching notifications out of banners. They're still listed in the
+/// popup.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FilterRule {
+    /// Matches notifications of this app, or of any app when empty.
+    pub app_name: String,
+    /// Matches notifications with this in their summary or body, or any
+    /// notification when empty.
+    pub keyword: String,
+}
+
+impl FilterRule {
+    /// Letter case is ignored.
+    pub fn matches(&self, notification: &Notification) -> bool {
+        let keyword = self.keyword.to_lowercase();
+        (self.app_name.is_empty() || self.app_name.eq_ignore_ascii_case(&notification.app_name))
+            && (keyword.is_empty()
+                || notification.summary.to_lowercase().contains(&keyword)
+                || notification.body.to_lowercase().contains(&keyword))
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq, CosmicConfigEntry)]
+#[version = 1]
+pub struct NotificationsAppletConfig {
+    pub filters: Vec<FilterRule>,
+}
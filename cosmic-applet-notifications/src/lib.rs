@@ -1,58 +1,83 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
+mod config;
 mod localize;
 mod subscriptions;
+mod thumbnail;
 use cosmic::{
+    app::Core,
     applet::{
-        menu_control_padding, padded_control,
+        menu_button, menu_control_padding, padded_control,
         token::subscription::{activation_token_subscription, TokenRequest, TokenUpdate},
     },
     cctk::sctk::reexports::calloop,
     cosmic_config::{Config, CosmicConfigEntry},
     iced::{
-        wayland::popup::{destroy_popup, get_popup},
         widget::{column, row, text},
-        window, Alignment, Length, Limits, Subscription,
+        Alignment, Command, Length, Limits, Subscription,
     },
     iced_core::alignment::Horizontal,
-    Command,
 };
 
-use cosmic::{iced_futures::futures::executor::block_on, iced_style::application};
+use cosmic::iced_futures::futures::executor::block_on;
 
+use config::{FilterRule, NotificationsAppletConfig};
 use cosmic::{
     iced_widget::{scrollable, Column},
-    widget::{button, container, divider, icon},
-    Element, Theme,
+    widget::{button, container, divider, icon, text_input, Image as ImageWidget},
+    Element,
+};
+use cosmic_applet_helper::{
+    AppletMessage, CosmicApplet, ImageCache, PanelEvent, PopupRequest, PopupWidth, Thumbnail,
 };
 use cosmic_notifications_config::NotificationsConfig;
 use cosmic_notifications_util::{Image, Notification};
 use cosmic_time::{anim, chain, id, once_cell::sync::Lazy, Instant, Timeline};
-use std::{borrow::Cow, collections::HashMap, path::PathBuf};
-use subscriptions::notifications::NotificationsAppletProxy;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
+use subscriptions::{
+    kdeconnect::{PhoneNotification, PhoneUpdate},
+    notifications::NotificationsAppletProxy,
+};
 use tokio::sync::mpsc::Sender;
 use tracing::info;
 
 pub fn run() -> cosmic::iced::Result {
     localize::localize();
-    cosmic::applet::run::<Notifications>(false, ())
+    cosmic_applet_helper::run_applet::<Notifications>()
 }
 
 static DO_NOT_DISTURB: Lazy<id::Toggler> = Lazy::new(id::Toggler::unique);
 
 struct Notifications {
-    core: cosmic::app::Core,
     config: NotificationsConfig,
     config_helper: Option<Config>,
     icon_name: String,
-    popup: Option<window::Id>,
     // notifications: Vec<Notification>,
     timeline: Timeline,
     dbus_sender: Option<Sender<subscriptions::dbus::Input>>,
     cards: Vec<(id::Cards, Vec<Notification>, bool, String, String, String)>,
     token_tx: Option<calloop::channel::Sender<TokenRequest>>,
     proxy: NotificationsAppletProxy<'static>,
+    thumbnails: ImageCache<u32>,
+    applet_config: NotificationsAppletConfig,
+    applet_config_helper: Option<Config>,
+    /// Notifications closed as soon as they came in, for a filter rule. The
+    /// daemon shows banners itself, so closing them is how they're hidden,
+    /// and they stay listed here.
+    filtered: HashSet<u32>,
+    filters_page: bool,
+    filter_app_name: String,
+    filter_keyword: String,
+    /// Notifications forwarded from phones by KDE Connect.
+    phone_notifications: Vec<PhoneNotification>,
+    /// The device and public id of the phone notification being replied to.
+    phone_reply: Option<(String, String)>,
+    phone_reply_text: String,
 }
 
 impl Notifications {
@@ -71,19 +96,201 @@ impl Notifications {
     fn update_icon(&mut self) {
         self.icon_name = if self.config.do_not_disturb {
             "cosmic-applet-notification-disabled-symbolic"
-        } else if self.cards.is_empty() {
+        } else if self.cards.is_empty() && self.phone_notifications.is_empty() {
             "cosmic-applet-notification-symbolic"
         } else {
             "cosmic-applet-notification-new-symbolic"
         }
         .to_string();
     }
+
+    fn forget_dismissed(&mut self) {
+        let cards = &self.cards;
+        self.thumbnails
+            .retain(|id| cards.iter().any(|c| c.1.iter().any(|n| n.id == *id)));
+        self.filtered
+            .retain(|id| cards.iter().any(|c| c.1.iter().any(|n| n.id == *id)));
+    }
+
+    /// Asks the daemon to close the notification, unless it already was.
+    fn close(&self, id: u32) {
+        if self.filtered.contains(&id) {
+            return;
+        }
+        if let Some(tx) = &self.dbus_sender {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                if let Err(err) = tx.send(subscriptions::dbus::Input::Dismiss(id)).await {
+                    tracing::error!("{:?}", err);
+                }
+            });
+        }
+    }
+
+    /// Dismisses the phone notification here and on the phone.
+    fn dismiss_phone(&mut self, device_id: String, public_id: String) -> Command<Message> {
+        self.phone_notifications
+            .retain(|n| n.device_id != device_id || n.public_id != public_id);
+        Command::perform(
+            subscriptions::kdeconnect::dismiss(device_id, public_id),
+            |res| Message::PhoneRequestDone(res.map_err(|e| e.to_string())),
+        )
+    }
+
+    /// Phone notifications, grouped under the name of their device.
+    fn phone_view(&self) -> Vec<Element<Message>> {
+        let mut groups: Vec<(&str, Vec<&PhoneNotification>)> = Vec::new();
+        for n in &self.phone_notifications {
+            match groups.iter_mut().find(|g| g.0 == n.device_id) {
+                Some(group) => group.1.push(n),
+                None => groups.push((&n.device_id, vec![n])),
+            }
+        }
+
+        let mut elems = Vec::with_capacity(groups.len());
+        for (_, notifications) in groups {
+            let mut group = column![row![
+                text_icon("phone-symbolic", 16),
+                text(&notifications[0].device_name).size(14),
+            ]
+            .spacing(8)
+            .align_items(Alignment::Center)]
+            .spacing(8);
+            for n in notifications {
+                let mut header = row![text(&n.app_name).size(12).width(Length::Fill)]
+                    .spacing(8)
+                    .align_items(Alignment::Center);
+                if n.dismissable {
+                    header = header.push(
+                        button(
+                            icon::from_name("window-close-symbolic")
+                                .size(16)
+                                .symbolic(true),
+                        )
+                        .on_press(Message::DismissPhone(
+                            n.device_id.clone(),
+                            n.public_id.clone(),
+                        ))
+                        .style(cosmic::theme::Button::Text),
+                    );
+                }
+                let mut notification = column![
+                    header,
+                    text(n.title.lines().next().unwrap_or_default())
+                        .width(Length::Fill)
+                        .size(14),
+                    text(n.text.lines().next().unwrap_or_default())
+                        .width(Length::Fill)
+                        .size(12),
+                ]
+                .spacing(4);
+                let replying = self
+                    .phone_reply
+                    .as_ref()
+                    .is_some_and(|(device_id, public_id)| {
+                        *device_id == n.device_id && *public_id == n.public_id
+                    });
+                if replying {
+                    let can_send = !self.phone_reply_text.trim().is_empty();
+                    notification = notification.push(
+                        row![
+                            text_input(fl!("reply-placeholder"), &self.phone_reply_text)
+                                .on_input(Message::PhoneReplyText)
+                                .on_submit(Message::SendPhoneReply)
+                                .width(Length::Fill),
+                            button::suggested(fl!("send"))
+                                .on_press_maybe(can_send.then_some(Message::SendPhoneReply)),
+                            button::standard(fl!("cancel")).on_press(Message::PhoneReply(None)),
+                        ]
+                        .spacing(8)
+                        .align_items(Alignment::Center),
+                    );
+                } else if !n.reply_id.is_empty() {
+                    let reply = (n.device_id.clone(), n.public_id.clone());
+                    notification = notification.push(
+                        container(
+                            button::text(fl!("reply")).on_press(Message::PhoneReply(Some(reply))),
+                        )
+                        .width(Length::Fill)
+                        .align_x(Horizontal::Right),
+                    );
+                }
+                group = group.push(
+                    container(notification)
+                        .padding(8)
+                        .style(cosmic::theme::Container::Card),
+                );
+            }
+            elems.push(group.into());
+        }
+        elems
+    }
+
+    fn write_applet_config(&self) {
+        if let Some(helper) = &self.applet_config_helper {
+            if let Err(err) = self.applet_config.write_entry(helper) {
+                tracing::error!(?err, "Error writing config");
+            }
+        }
+    }
+
+    fn filters_view(&self) -> Element<Message> {
+        let mut content = column![padded_control(text(fl!("filters-description")).size(12))];
+        for (i, rule) in self.applet_config.filters.iter().enumerate() {
+            let app_name = if rule.app_name.is_empty() {
+                fl!("any-app")
+            } else {
+                rule.app_name.clone()
+            };
+            let keyword = if rule.keyword.is_empty() {
+                fl!("any-text")
+            } else {
+                format!("“{}”", rule.keyword)
+            };
+            content = content.push(padded_control(
+                row![
+                    column![text(app_name).size(14), text(keyword).size(12)].width(Length::Fill),
+                    button(
+                        icon::from_name("edit-delete-symbolic")
+                            .size(16)
+                            .symbolic(true)
+                    )
+                    .on_press(Message::RemoveFilter(i))
+                    .style(cosmic::theme::Button::Text),
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center),
+            ));
+        }
+        let rule = FilterRule {
+            app_name: self.filter_app_name.trim().to_string(),
+            keyword: self.filter_keyword.trim().to_string(),
+        };
+        let can_add = !(rule.app_name.is_empty() && rule.keyword.is_empty())
+            && !self.applet_config.filters.contains(&rule);
+        content = content.push(padded_control(
+            column![
+                text_input(fl!("filter-app-name"), &self.filter_app_name)
+                    .on_input(Message::FilterAppName)
+                    .on_submit(Message::AddFilter),
+                text_input(fl!("filter-keyword"), &self.filter_keyword)
+                    .on_input(Message::FilterKeyword)
+                    .on_submit(Message::AddFilter),
+                container(
+                    button::standard(fl!("add-filter"))
+                        .on_press_maybe(can_add.then_some(Message::AddFilter)),
+                )
+                .width(Length::Fill)
+                .align_x(Horizontal::Right),
+            ]
+            .spacing(8),
+        ));
+        content.into()
+    }
 }
 
 #[derive(Debug, Clone)]
 enum Message {
-    TogglePopup,
-    CloseRequested(window::Id),
     DoNotDisturb(chain::Toggler, bool),
     Frame(Instant),
     NotificationEvent(Notification),
@@ -94,21 +301,29 @@ enum Message {
     CardsToggled(String, bool),
     Token(TokenUpdate),
     OpenSettings,
+    Thumbnail(u32, Option<Thumbnail>),
+    OpenImage(u32),
+    AppletConfig(NotificationsAppletConfig),
+    ToggleFilters,
+    FilterAppName(String),
+    FilterKeyword(String),
+    AddFilter,
+    RemoveFilter(usize),
+    Phone(PhoneUpdate),
+    DismissPhone(String, String),
+    /// Starts replying to a phone notification, or stops.
+    PhoneReply(Option<(String, String)>),
+    PhoneReplyText(String),
+    SendPhoneReply,
+    PhoneRequestDone(Result<(), String>),
 }
 
-impl cosmic::Application for Notifications {
+impl CosmicApplet for Notifications {
     type Message = Message;
-    type Executor = cosmic::SingleThreadExecutor;
-    type Flags = ();
     const APP_ID: &'static str = "com.system76.CosmicAppletNotifications";
+    const AUTOSIZE: bool = false;
 
-    fn init(
-        core: cosmic::app::Core,
-        _flags: Self::Flags,
-    ) -> (
-        Self,
-        cosmic::iced::Command<cosmic::app::Message<Self::Message>>,
-    ) {
+    fn init(_core: &Core) -> (Self, Command<Message>) {
         let helper = Config::new(
             cosmic_notifications_config::ID,
             NotificationsConfig::VERSION,
@@ -126,86 +341,87 @@ impl cosmic::Application for Notifications {
                 })
             })
             .unwrap_or_default();
+        let applet_config_helper =
+            Config::new(config::APP_ID, NotificationsAppletConfig::VERSION).ok();
+        let applet_config = applet_config_helper
+            .as_ref()
+            .map(|helper| {
+                NotificationsAppletConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
         let mut _self = Self {
-            core,
             config_helper: helper,
             config,
             icon_name: Default::default(),
-            popup: None,
             timeline: Default::default(),
             dbus_sender: Default::default(),
             cards: Vec::new(),
             token_tx: Default::default(),
             proxy: block_on(crate::subscriptions::notifications::get_proxy())
                 .expect("Failed to get proxy"),
+            thumbnails: ImageCache::new(thumbnail::CACHE_BYTES),
+            applet_config,
+            applet_config_helper,
+            filtered: HashSet::new(),
+            filters_page: false,
+            filter_app_name: String::new(),
+            filter_keyword: String::new(),
+            phone_notifications: Vec::new(),
+            phone_reply: None,
+            phone_reply_text: String::new(),
         };
         _self.update_icon();
         (_self, Command::none())
     }
 
-    fn core(&self) -> &cosmic::app::Core {
-        &self.core
-    }
-
-    fn core_mut(&mut self) -> &mut cosmic::app::Core {
-        &mut self.core
-    }
-
-    fn style(&self) -> Option<<Theme as application::StyleSheet>::Style> {
-        Some(cosmic::applet::style())
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        match event {
+            PanelEvent::PopupOpened => self.timeline = Timeline::new(),
+            PanelEvent::PopupClosed => self.filters_page = false,
+        }
+        Command::none()
     }
 
-    fn subscription(&self) -> Subscription<Message> {
+    fn subscription(&self, core: &Core) -> Subscription<Message> {
         Subscription::batch(vec![
-            self.core
-                .watch_config(cosmic_notifications_config::ID)
+            core.watch_config(cosmic_notifications_config::ID)
                 .map(|res| {
                     for err in res.errors {
                         tracing::error!("{:?}", err);
                     }
                     Message::Config(res.config)
                 }),
-            self.timeline
-                .as_subscription()
-                .map(|(_, now)| Message::Frame(now)),
+            core.watch_config::<NotificationsAppletConfig>(config::APP_ID)
+                .map(|update| {
+                    for err in update.errors {
+                        tracing::error!(?err, "Error watching config");
+                    }
+                    Message::AppletConfig(update.config)
+                }),
             subscriptions::dbus::proxy().map(Message::DbusEvent),
             subscriptions::notifications::notifications(self.proxy.clone())
                 .map(Message::NotificationEvent),
             activation_token_subscription(0).map(Message::Token),
+            subscriptions::kdeconnect::phone_subscription().map(Message::Phone),
         ])
     }
 
-    fn update(
-        &mut self,
-        message: Self::Message,
-    ) -> cosmic::iced::Command<cosmic::app::Message<Self::Message>> {
+    fn popup_subscription(&self, _core: &Core) -> Subscription<Message> {
+        self.timeline
+            .as_subscription()
+            .map(|(_, now)| Message::Frame(now))
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
         match message {
             Message::Frame(now) => {
                 self.timeline.now(now);
             }
-            Message::TogglePopup => {
-                if let Some(p) = self.popup.take() {
-                    return destroy_popup(p);
-                } else {
-                    let new_id = window::Id::unique();
-                    self.popup.replace(new_id);
-                    self.timeline = Timeline::new();
-
-                    let mut popup_settings = self.core.applet.get_popup_settings(
-                        window::Id::MAIN,
-                        new_id,
-                        None,
-                        None,
-                        None,
-                    );
-                    popup_settings.positioner.size_limits = Limits::NONE
-                        .min_width(1.0)
-                        .max_width(444.0)
-                        .min_height(100.0)
-                        .max_height(900.0);
-                    return get_popup(popup_settings);
-                }
-            }
             Message::DoNotDisturb(chain, b) => {
                 self.timeline.set_chain(chain).start();
                 self.config.do_not_disturb = b;
@@ -216,6 +432,19 @@ impl cosmic::Application for Notifications {
                 }
             }
             Message::NotificationEvent(n) => {
+                let id = n.id;
+                let image = n.image().cloned();
+                let is_new = !self.cards.iter().any(|c| c.1.iter().any(|n| n.id == id));
+                if is_new
+                    && self
+                        .applet_config
+                        .filters
+                        .iter()
+                        .any(|rule| rule.matches(&n))
+                {
+                    self.close(id);
+                    self.filtered.insert(id);
+                }
                 if let Some(c) = self
                     .cards
                     .iter_mut()
@@ -240,31 +469,140 @@ impl cosmic::Application for Notifications {
                         fl!("clear-group"),
                     ));
                 }
+                self.thumbnails.remove(&id);
+                if let Some(image) = image {
+                    self.update_icon();
+                    return Command::perform(
+                        tokio::task::spawn_blocking(move || thumbnail::decode(&image)),
+                        move |thumbnail| Message::Thumbnail(id, thumbnail.ok().flatten()),
+                    );
+                }
+            }
+            Message::Thumbnail(id, thumbnail) => {
+                let shown = self.cards.iter().any(|c| c.1.iter().any(|n| n.id == id));
+                if let Some(thumbnail) = thumbnail.filter(|_| shown) {
+                    self.thumbnails.insert(id, thumbnail);
+                }
+            }
+            Message::OpenImage(id) => {
+                let image = self
+                    .cards
+                    .iter()
+                    .flat_map(|c| c.1.iter())
+                    .find(|n| n.id == id)
+                    .and_then(|n| n.image().cloned());
+                if let Some(image) = image {
+                    tokio::spawn(async move {
+                        if let Err(err) = thumbnail::open(id, image).await {
+                            tracing::error!("Failed to open image: {:?}", err);
+                        }
+                    });
+                }
             }
             Message::Config(config) => {
                 self.config = config;
             }
+            Message::AppletConfig(config) => {
+                self.applet_config = config;
+            }
+            Message::ToggleFilters => {
+                self.filters_page = !self.filters_page;
+                self.filter_app_name.clear();
+                self.filter_keyword.clear();
+            }
+            Message::FilterAppName(app_name) => {
+                self.filter_app_name = app_name;
+            }
+            Message::FilterKeyword(keyword) => {
+                self.filter_keyword = keyword;
+            }
+            Message::AddFilter => {
+                let rule = FilterRule {
+                    app_name: self.filter_app_name.trim().to_string(),
+                    keyword: self.filter_keyword.trim().to_string(),
+                };
+                if (rule.app_name.is_empty() && rule.keyword.is_empty())
+                    || self.applet_config.filters.contains(&rule)
+                {
+                    return Command::none();
+                }
+                self.applet_config.filters.push(rule);
+                self.write_applet_config();
+                self.filter_app_name.clear();
+                self.filter_keyword.clear();
+            }
+            Message::RemoveFilter(i) => {
+                if i < self.applet_config.filters.len() {
+                    self.applet_config.filters.remove(i);
+                    self.write_applet_config();
+                }
+            }
+            Message::Phone(PhoneUpdate::Notifications(notifications)) => {
+                self.phone_notifications = notifications;
+                let stale = match &self.phone_reply {
+                    Some((device_id, public_id)) => !self
+                        .phone_notifications
+                        .iter()
+                        .any(|n| n.device_id == *device_id && n.public_id == *public_id),
+                    None => false,
+                };
+                if stale {
+                    self.phone_reply = None;
+                }
+            }
+            Message::Phone(PhoneUpdate::Unavailable) => {
+                self.phone_notifications.clear();
+                self.phone_reply = None;
+            }
+            Message::DismissPhone(device_id, public_id) => {
+                let command = self.dismiss_phone(device_id, public_id);
+                self.update_icon();
+                return command;
+            }
+            Message::PhoneReply(reply) => {
+                self.phone_reply = reply;
+                self.phone_reply_text.clear();
+            }
+            Message::PhoneReplyText(text) => {
+                self.phone_reply_text = text;
+            }
+            Message::SendPhoneReply => {
+                let message = self.phone_reply_text.trim().to_string();
+                let reply_id = self.phone_reply.take().and_then(|(device_id, public_id)| {
+                    self.phone_notifications
+                        .iter()
+                        .find(|n| n.device_id == device_id && n.public_id == public_id)
+                        .map(|n| (device_id, n.reply_id.clone()))
+                });
+                self.phone_reply_text.clear();
+                if let Some((device_id, reply_id)) = reply_id.filter(|_| !message.is_empty()) {
+                    return Command::perform(
+                        subscriptions::kdeconnect::reply(device_id, reply_id, message),
+                        |res| Message::PhoneRequestDone(res.map_err(|e| e.to_string())),
+                    );
+                }
+            }
+            Message::PhoneRequestDone(Ok(())) => {}
+            Message::PhoneRequestDone(Err(err)) => {
+                tracing::error!("Failed to reach the phone: {}", err);
+            }
             Message::Dismissed(id) => {
                 info!("Dismissed {}", id);
                 for c in &mut self.cards {
                     c.1.retain(|n| n.id != id);
                 }
                 self.cards.retain(|c| !c.1.is_empty());
-
-                if let Some(tx) = &self.dbus_sender {
-                    let tx = tx.clone();
-                    tokio::spawn(async move {
-                        if let Err(err) = tx.send(subscriptions::dbus::Input::Dismiss(id)).await {
-                            tracing::error!("{:?}", err);
-                        }
-                    });
-                }
+                self.close(id);
+                self.forget_dismissed();
             }
             Message::DbusEvent(e) => match e {
                 subscriptions::dbus::Output::Ready(tx) => {
                     self.dbus_sender.replace(tx);
                 }
                 subscriptions::dbus::Output::CloseEvent(id) => {
+                    if self.filtered.contains(&id) {
+                        return Command::none();
+                    }
                     for c in &mut self.cards {
                         c.1.retain(|n| n.id != id);
                         c.3 = fl!(
@@ -273,6 +611,7 @@ impl cosmic::Application for Notifications {
                         );
                     }
                     self.cards.retain(|c| !c.1.is_empty());
+                    self.forget_dismissed();
                 }
             },
             Message::ClearAll(Some(app_name)) => {
@@ -281,33 +620,30 @@ impl cosmic::Application for Notifications {
                     .iter_mut()
                     .position(|c| c.1.iter().any(|notif| app_name == notif.app_name))
                 {
-                    for n in self.cards.remove(pos).1 {
-                        if let Some(tx) = &self.dbus_sender {
-                            let tx = tx.clone();
-                            tokio::spawn(async move {
-                                if let Err(err) =
-                                    tx.send(subscriptions::dbus::Input::Dismiss(n.id)).await
-                                {
-                                    tracing::error!("{:?}", err);
-                                }
-                            });
-                        }
+                    let notifications = self.cards.remove(pos).1;
+                    for n in notifications {
+                        self.close(n.id);
                     }
+                    self.forget_dismissed();
                 }
             }
             Message::ClearAll(None) => {
-                for n in self.cards.drain(..).map(|n| n.1).flatten() {
-                    if let Some(tx) = &self.dbus_sender {
-                        let tx = tx.clone();
-                        tokio::spawn(async move {
-                            if let Err(err) =
-                                tx.send(subscriptions::dbus::Input::Dismiss(n.id)).await
-                            {
-                                tracing::error!("{:?}", err);
-                            }
-                        });
-                    }
+                self.thumbnails.clear();
+                for n in std::mem::take(&mut self.cards)
+                    .into_iter()
+                    .flat_map(|c| c.1)
+                {
+                    self.close(n.id);
                 }
+                self.filtered.clear();
+                let phone: Vec<_> = std::mem::take(&mut self.phone_notifications)
+                    .into_iter()
+                    .filter(|n| n.dismissable)
+                    .map(|n| self.dismiss_phone(n.device_id, n.public_id))
+                    .collect();
+                self.phone_reply = None;
+                self.update_icon();
+                return Command::batch(phone);
             }
             Message::CardsToggled(name, expanded) => {
                 let id = if let Some((id, _, n_expanded, ..)) = self
@@ -322,11 +658,6 @@ impl cosmic::Application for Notifications {
                 };
                 self.update_cards(id);
             }
-            Message::CloseRequested(id) => {
-                if Some(id) == self.popup {
-                    self.popup = None;
-                }
-            }
             Message::OpenSettings => {
                 let exec = "cosmic-settings notifications".to_string();
                 if let Some(tx) = self.token_tx.as_ref() {
@@ -358,15 +689,14 @@ impl cosmic::Application for Notifications {
         Command::none()
     }
 
-    fn view(&self) -> Element<Message> {
-        self.core
-            .applet
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        core.applet
             .icon_button(&self.icon_name)
-            .on_press(Message::TogglePopup)
+            .on_press(AppletMessage::TogglePopup)
             .into()
     }
 
-    fn view_window(&self, _id: window::Id) -> Element<Message> {
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
         let do_not_disturb = padded_control(row![anim!(
             DO_NOT_DISTURB,
             &self.timeline,
@@ -377,7 +707,7 @@ impl cosmic::Application for Notifications {
         .text_size(14)
         .width(Length::Fill)]);
 
-        let notifications = if self.cards.is_empty() {
+        let notifications = if self.cards.is_empty() && self.phone_notifications.is_empty() {
             row![container(
                 column![
                     text_icon("cosmic-applet-notification-symbolic", 40),
@@ -399,6 +729,7 @@ impl cosmic::Application for Notifications {
                 .align_x(Horizontal::Right)
                 .into(),
             );
+            notifs.extend(self.phone_view());
             for c in self.cards.iter().rev() {
                 if c.1.is_empty() {
                     continue;
@@ -479,8 +810,23 @@ impl cosmic::Application for Notifications {
                                     text(n.body.lines().next().unwrap_or_default())
                                         .width(Length::Fill)
                                         .size(12)
-                                ]
+                                ],
+                                self.thumbnails.get(&n.id).map_or_else(
+                                    || Element::from(row![]),
+                                    |thumbnail| {
+                                        button(
+                                            ImageWidget::new(thumbnail.handle.clone())
+                                                .width(Length::Fixed(thumbnail.width as f32))
+                                                .height(Length::Fixed(thumbnail.height as f32)),
+                                        )
+                                        .padding(0)
+                                        .on_press(Message::OpenImage(n.id))
+                                        .style(cosmic::theme::Button::Image)
+                                        .into()
+                                    }
+                                )
                             )
+                            .spacing(4)
                             .width(Length::Fill),
                         )
                     })
@@ -536,20 +882,39 @@ impl cosmic::Application for Notifications {
             .padding(menu_control_padding())
         };
 
+        let main_content: Element<_> = if self.filters_page {
+            self.filters_view()
+        } else {
+            notifications.into()
+        };
         let main_content = column![
             padded_control(divider::horizontal::default()),
-            notifications,
+            main_content,
+            padded_control(divider::horizontal::default()),
+            menu_button(
+                text(if self.filters_page {
+                    fl!("back")
+                } else {
+                    fl!("filters")
+                })
+                .size(14)
+            )
+            .on_press(Message::ToggleFilters),
         ];
 
         let content = column![do_not_disturb, main_content]
             .align_items(Alignment::Start)
             .padding([8, 0]);
 
-        self.core.applet.popup_container(content).into()
+        Element::from(content).map(AppletMessage::App)
+    }
+
+    fn popup_request(&self, message: &Message) -> Option<PopupRequest> {
+        matches!(message, Message::OpenImage(_)).then_some(PopupRequest::Close)
     }
 
-    fn on_close_requested(&self, id: window::Id) -> Option<Message> {
-        Some(Message::CloseRequested(id))
+    fn popup_limits(&self, _core: &Core) -> Limits {
+        PopupWidth::Standard.limits().min_height(100.0)
     }
 }
 
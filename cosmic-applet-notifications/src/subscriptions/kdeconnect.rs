@@ -0,0 +1,232 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Notifications of paired phones, forwarded by the KDE Connect daemon, or a
+//! service implementing its D-Bus interface.
+//!
+//! The daemon keeps the notifications active on each phone, and dismissing one
+//! here dismisses it on the phone too. Some apps let a notification be replied
+//! to, like messaging apps, which the daemon sends to the phone.
+
+use std::time::Duration;
+
+use cosmic::{
+    iced::{self, subscription},
+    iced_futures::futures::{self, stream, FutureExt, SinkExt, StreamExt},
+};
+use zbus::{message::Type as MessageType, proxy, Connection, MatchRule, MessageStream};
+
+const SERVICE: &str = "org.kde.kdeconnect";
+const DEVICES_PATH: &str = "/modules/kdeconnect/devices";
+
+#[proxy(
+    interface = "org.kde.kdeconnect.daemon",
+    default_service = "org.kde.kdeconnect",
+    default_path = "/modules/kdeconnect"
+)]
+trait Daemon {
+    /// devices method
+    fn devices(&self, only_reachable: bool, only_paired: bool) -> zbus::Result<Vec<String>>;
+}
+
+#[proxy(
+    interface = "org.kde.kdeconnect.device",
+    default_service = "org.kde.kdeconnect"
+)]
+trait Device {
+    /// name property
+    #[zbus(property)]
+    fn name(&self) -> zbus::Result<String>;
+}
+
+#[proxy(
+    interface = "org.kde.kdeconnect.device.notifications",
+    default_service = "org.kde.kdeconnect"
+)]
+trait DeviceNotifications {
+    /// activeNotifications method
+    #[zbus(name = "activeNotifications")]
+    fn active_notifications(&self) -> zbus::Result<Vec<String>>;
+
+    /// sendReply method
+    #[zbus(name = "sendReply")]
+    fn send_reply(&self, reply_id: &str, message: &str) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.kde.kdeconnect.device.notifications.notification",
+    default_service = "org.kde.kdeconnect"
+)]
+trait PhoneNotificationObject {
+    /// dismiss method
+    fn dismiss(&self) -> zbus::Result<()>;
+
+    /// appName property
+    #[zbus(property, name = "appName")]
+    fn app_name(&self) -> zbus::Result<String>;
+
+    /// title property
+    #[zbus(property)]
+    fn title(&self) -> zbus::Result<String>;
+
+    /// text property
+    #[zbus(property)]
+    fn text(&self) -> zbus::Result<String>;
+
+    /// dismissable property
+    #[zbus(property)]
+    fn dismissable(&self) -> zbus::Result<bool>;
+
+    /// replyId property
+    #[zbus(property, name = "replyId")]
+    fn reply_id(&self) -> zbus::Result<String>;
+}
+
+/// A notification active on a phone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhoneNotification {
+    pub device_id: String,
+    pub device_name: String,
+    /// Identifies the notification among the device's.
+    pub public_id: String,
+    pub app_name: String,
+    pub title: String,
+    pub text: String,
+    pub dismissable: bool,
+    /// Empty unless the notification can be replied to.
+    pub reply_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum PhoneUpdate {
+    /// The active notifications of every reachable, paired phone.
+    Notifications(Vec<PhoneNotification>),
+    /// The daemon isn't running.
+    Unavailable,
+}
+
+pub fn phone_subscription() -> iced::Subscription<PhoneUpdate> {
+    struct PhoneWorker;
+
+    subscription::channel(
+        std::any::TypeId::of::<PhoneWorker>(),
+        10,
+        |mut output| async move {
+            if let Err(err) = run(&mut output).await {
+                tracing::error!("Failed to watch for phone notifications: {}", err);
+            }
+            _ = output.send(PhoneUpdate::Unavailable).await;
+            futures::future::pending().await
+        },
+    )
+}
+
+async fn run(output: &mut futures::channel::mpsc::Sender<PhoneUpdate>) -> zbus::Result<()> {
+    let conn = Connection::session().await?;
+    let changes = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(SERVICE)?
+        .build();
+    // Also notice the daemon starting and stopping
+    let owner = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender("org.freedesktop.DBus")?
+        .member("NameOwnerChanged")?
+        .arg(0, SERVICE)?
+        .build();
+    let mut signals = stream::select(
+        MessageStream::for_match_rule(changes, &conn, None).await?,
+        MessageStream::for_match_rule(owner, &conn, None).await?,
+    );
+
+    loop {
+        let update = match notifications(&conn).await {
+            Ok(notifications) => PhoneUpdate::Notifications(notifications),
+            Err(err) => {
+                tracing::debug!("KDE Connect unavailable: {}", err);
+                PhoneUpdate::Unavailable
+            }
+        };
+        _ = output.send(update).await;
+
+        if signals.next().await.is_none() {
+            return Ok(());
+        }
+        // A phone connecting posts all its notifications at once
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        while let Some(Some(_)) = signals.next().now_or_never() {}
+    }
+}
+
+async fn notifications(conn: &Connection) -> zbus::Result<Vec<PhoneNotification>> {
+    let mut notifications = Vec::new();
+    for device_id in DaemonProxy::new(conn).await?.devices(true, true).await? {
+        let device_path = format!("{DEVICES_PATH}/{device_id}");
+        let device_name = DeviceProxy::builder(conn)
+            .path(device_path.clone())?
+            .cache_properties(zbus::CacheProperties::No)
+            .build()
+            .await?
+            .name()
+            .await?;
+        // Fails when the phone's notifications plugin is disabled
+        let Ok(public_ids) = device_notifications(conn, &device_id)
+            .await?
+            .active_notifications()
+            .await
+        else {
+            continue;
+        };
+
+        for public_id in public_ids {
+            let notification = PhoneNotificationObjectProxy::builder(conn)
+                .path(format!("{device_path}/notifications/{public_id}"))?
+                .cache_properties(zbus::CacheProperties::No)
+                .build()
+                .await?;
+            notifications.push(PhoneNotification {
+                device_id: device_id.clone(),
+                device_name: device_name.clone(),
+                public_id,
+                app_name: notification.app_name().await?,
+                title: notification.title().await?,
+                text: notification.text().await?,
+                dismissable: notification.dismissable().await.unwrap_or_default(),
+                reply_id: notification.reply_id().await.unwrap_or_default(),
+            });
+        }
+    }
+    Ok(notifications)
+}
+
+async fn device_notifications(
+    conn: &Connection,
+    device_id: &str,
+) -> zbus::Result<DeviceNotificationsProxy<'static>> {
+    DeviceNotificationsProxy::builder(conn)
+        .path(format!("{DEVICES_PATH}/{device_id}/notifications"))?
+        .build()
+        .await
+}
+
+/// Dismisses the notification on the phone.
+pub async fn dismiss(device_id: String, public_id: String) -> zbus::Result<()> {
+    let conn = Connection::session().await?;
+    PhoneNotificationObjectProxy::builder(&conn)
+        .path(format!(
+            "{DEVICES_PATH}/{device_id}/notifications/{public_id}"
+        ))?
+        .build()
+        .await?
+        .dismiss()
+        .await
+}
+
+/// Replies to the notification with `reply_id` from the phone.
+pub async fn reply(device_id: String, reply_id: String, message: String) -> zbus::Result<()> {
+    let conn = Connection::session().await?;
+    device_notifications(&conn, &device_id)
+        .await?
+        .send_reply(&reply_id, &message)
+        .await
+}
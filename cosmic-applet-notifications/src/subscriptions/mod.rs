@@ -3,4 +3,5 @@
 
 pub mod dbus;
 mod freedesktop_proxy;
+pub mod kdeconnect;
 pub mod notifications;
@@ -0,0 +1,71 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Thumbnails of the images attached to notifications, such as screenshots.
+
+use std::{io, path::PathBuf};
+
+use cosmic_applet_helper::{fit, Thumbnail};
+use cosmic_notifications_util::Image;
+
+/// Images smaller than this on both sides are icons, only shown in the
+/// notification's header.
+const MIN_SIZE: u32 = 64;
+const MAX_WIDTH: u32 = 360;
+const MAX_HEIGHT: u32 = 200;
+/// How much decoded pixel data is kept at most.
+pub const CACHE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Decodes and scales down the image of a notification. Blocks, so it's run
+/// with [`tokio::task::spawn_blocking`].
+pub fn decode(image: &Image) -> Option<Thumbnail> {
+    let img = match image {
+        Image::Name(_) => return None,
+        Image::File(path) => match image::open(path) {
+            Ok(img) => img.into_rgba8(),
+            Err(err) => {
+                tracing::warn!(?err, "Failed to decode {}", path.display());
+                return None;
+            }
+        },
+        Image::Data {
+            width,
+            height,
+            data,
+        } => image::RgbaImage::from_raw(*width, *height, data.clone())?,
+    };
+    if img.width() < MIN_SIZE && img.height() < MIN_SIZE {
+        return None;
+    }
+    Some(Thumbnail::new(fit(img, MAX_WIDTH, MAX_HEIGHT)))
+}
+
+/// Opens the full image in the default viewer. Images sent as pixel data are
+/// saved to the runtime directory first.
+pub async fn open(id: u32, image: Image) -> io::Result<()> {
+    let path = match image {
+        Image::Name(_) => return Ok(()),
+        Image::File(path) => path,
+        Image::Data {
+            width,
+            height,
+            data,
+        } => {
+            let path = std::env::var_os("XDG_RUNTIME_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(std::env::temp_dir)
+                .join(format!("cosmic-applet-notifications-{id}.png"));
+            let save_path = path.clone();
+            tokio::task::spawn_blocking(move || {
+                image::save_buffer(&save_path, &data, width, height, image::ColorType::Rgba8)
+            })
+            .await?
+            .map_err(io::Error::other)?;
+            path
+        }
+    };
+    let mut cmd = std::process::Command::new("xdg-open");
+    cmd.arg(path);
+    cosmic::process::spawn(cmd);
+    Ok(())
+}
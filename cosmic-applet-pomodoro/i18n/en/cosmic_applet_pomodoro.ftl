@@ -0,0 +1,27 @@
+minutes = { $minutes } min
+ready = Ready to focus
+focusing = Focusing
+short-break = Short break
+long-break = Long break
+start-focus = Start focus
+resume = Resume
+pause = Pause
+skip = Skip
+reset = Reset
+today = Today
+today-stats = { $sessions ->
+    [one] 1 session
+    *[other] { $sessions } sessions
+}, { $minutes } minutes of focus
+total-stats = { $sessions ->
+    [one] 1 session in total
+    *[other] { $sessions } sessions in total
+}
+focus-length = Focus length
+break-length = Break length
+dnd-while-focusing = Do Not Disturb while focusing
+break-started = Time for a break
+long-break-started = Time for a long break
+break-for = Take { $minutes } minutes away from the screen.
+break-over = Break is over
+ready-to-focus = Start another focus session when you're ready.
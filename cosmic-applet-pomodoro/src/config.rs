@@ -0,0 +1,69 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use chrono::{Local, NaiveDate};
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
+pub const APP_ID: &str = "com.system76.CosmicAppletPomodoro";
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Stats {
+    /// The day `sessions_today` and `minutes_today` count.
+    pub day: Option<NaiveDate>,
+    pub sessions_today: u32,
+    pub minutes_today: u64,
+    pub sessions_total: u64,
+}
+
+impl Stats {
+    /// Stats with a completed focus session of `minutes` counted.
+    pub fn with_session(&self, minutes: u64) -> Self {
+        let today = Local::now().date_naive();
+        let mut stats = self.clone();
+        if stats.day != Some(today) {
+            stats.day = Some(today);
+            stats.sessions_today = 0;
+            stats.minutes_today = 0;
+        }
+        stats.sessions_today += 1;
+        stats.minutes_today += minutes;
+        stats.sessions_total += 1;
+        stats
+    }
+
+    /// Sessions and minutes of focus today.
+    pub fn today(&self) -> (u32, u64) {
+        if self.day == Some(Local::now().date_naive()) {
+            (self.sessions_today, self.minutes_today)
+        } else {
+            (0, 0)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, CosmicConfigEntry)]
+#[version = 1]
+pub struct PomodoroConfig {
+    pub focus_minutes: u64,
+    pub short_break_minutes: u64,
+    pub long_break_minutes: u64,
+    /// Focus sessions between long breaks.
+    pub sessions_per_long_break: u32,
+    /// Turn on Do Not Disturb while focusing.
+    pub do_not_disturb: bool,
+    pub stats: Stats,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self {
+            focus_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 15,
+            sessions_per_long_break: 4,
+            do_not_disturb: true,
+            stats: Stats::default(),
+        }
+    }
+}
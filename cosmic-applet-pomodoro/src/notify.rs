@@ -0,0 +1,46 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::collections::HashMap;
+
+use zbus::{proxy, zvariant::Value, Connection};
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    /// Notify method
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
+
+/// Shows a notification, for the end of a focus session or break.
+pub async fn notify(summary: String, body: String) -> zbus::Result<()> {
+    let conn = Connection::session().await?;
+    NotificationsProxy::new(&conn)
+        .await?
+        .notify(
+            "Focus Timer",
+            0,
+            "com.system76.CosmicAppletPomodoro-symbolic",
+            &summary,
+            &body,
+            &[],
+            HashMap::new(),
+            -1,
+        )
+        .await?;
+    Ok(())
+}
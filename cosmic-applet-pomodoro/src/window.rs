@@ -0,0 +1,442 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::time::{Duration, Instant};
+
+use crate::{
+    config::{self, PomodoroConfig},
+    fl, notify,
+};
+use cosmic::{
+    app::Core,
+    applet::{cosmic_panel_config::PanelAnchor, padded_control},
+    cosmic_config::{Config, CosmicConfigEntry},
+    iced::{
+        alignment::Horizontal,
+        time,
+        widget::{column, row},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_widget::{Column, Row},
+    widget::{
+        button, divider,
+        segmented_button::{Entity, SingleSelectModel},
+        segmented_control, text, toggler,
+    },
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet};
+use cosmic_notifications_config::NotificationsConfig;
+
+const ICON: &str = "com.system76.CosmicAppletPomodoro-symbolic";
+const FOCUS_MINUTES: [u64; 4] = [15, 25, 45, 60];
+const BREAK_MINUTES: [u64; 3] = [5, 10, 15];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Phase {
+    #[default]
+    Idle,
+    Focus,
+    ShortBreak,
+    LongBreak,
+}
+
+#[derive(Default)]
+pub struct Window {
+    config: PomodoroConfig,
+    config_helper: Option<Config>,
+    notifications: NotificationsConfig,
+    notifications_helper: Option<Config>,
+    phase: Phase,
+    /// When the current phase ends, while running.
+    until: Option<Instant>,
+    /// Time left in the current phase, while paused.
+    paused: Option<Duration>,
+    /// Focus sessions completed since the last long break.
+    sessions: u32,
+    /// Whether Do Not Disturb was turned on by us, and should be turned off
+    /// again after focusing.
+    enabled_dnd: bool,
+    focus_durations: SingleSelectModel,
+    break_durations: SingleSelectModel,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    ConfigChanged(PomodoroConfig),
+    NotificationsChanged(NotificationsConfig),
+    Start,
+    Pause,
+    Resume,
+    Skip,
+    Reset,
+    Tick,
+    FocusDuration(Entity),
+    BreakDuration(Entity),
+    DoNotDisturb(bool),
+    Notified(Result<(), String>),
+}
+
+fn durations(minutes: &[u64], selected: u64) -> SingleSelectModel {
+    let mut model = SingleSelectModel::default();
+    for m in minutes {
+        let entity = model
+            .insert()
+            .text(fl!("minutes", minutes = m))
+            .data(*m)
+            .id();
+        if *m == selected {
+            model.activate(entity);
+        }
+    }
+    model
+}
+
+fn format_remaining(remaining: Duration) -> String {
+    let secs = remaining.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+impl Window {
+    fn remaining(&self) -> Option<Duration> {
+        self.paused.or_else(|| {
+            self.until
+                .map(|until| until.saturating_duration_since(Instant::now()))
+        })
+    }
+
+    fn phase_minutes(&self, phase: Phase) -> u64 {
+        match phase {
+            Phase::Idle => 0,
+            Phase::Focus => self.config.focus_minutes,
+            Phase::ShortBreak => self.config.short_break_minutes,
+            Phase::LongBreak => self.config.long_break_minutes,
+        }
+    }
+
+    fn set_do_not_disturb(&mut self, do_not_disturb: bool) {
+        self.notifications.do_not_disturb = do_not_disturb;
+        if let Some(helper) = &self.notifications_helper {
+            if let Err(err) = self.notifications.write_entry(helper) {
+                tracing::error!(?err, "Error writing notifications config");
+            }
+        }
+    }
+
+    fn enter(&mut self, phase: Phase) {
+        let focusing = phase == Phase::Focus;
+        if focusing && self.config.do_not_disturb && !self.notifications.do_not_disturb {
+            self.set_do_not_disturb(true);
+            self.enabled_dnd = true;
+        } else if !focusing && self.enabled_dnd {
+            self.set_do_not_disturb(false);
+            self.enabled_dnd = false;
+        }
+
+        self.phase = phase;
+        self.paused = None;
+        self.until = (phase != Phase::Idle)
+            .then(|| Instant::now() + Duration::from_secs(self.phase_minutes(phase) * 60));
+    }
+
+    /// Moves on from the current phase, counting it if it was completed.
+    fn advance(&mut self, completed: bool) -> Command<Message> {
+        let (next, summary) = match self.phase {
+            Phase::Focus => {
+                if completed {
+                    self.sessions += 1;
+                    let stats = self.config.stats.with_session(self.config.focus_minutes);
+                    if let Some(helper) = &self.config_helper {
+                        if let Err(err) = self.config.set_stats(helper, stats) {
+                            tracing::error!(?err, "Error writing config");
+                        }
+                    }
+                }
+                if self.sessions >= self.config.sessions_per_long_break {
+                    self.sessions = 0;
+                    (Phase::LongBreak, fl!("long-break-started"))
+                } else {
+                    (Phase::ShortBreak, fl!("break-started"))
+                }
+            }
+            Phase::ShortBreak | Phase::LongBreak => (Phase::Idle, fl!("break-over")),
+            Phase::Idle => return Command::none(),
+        };
+        self.enter(next);
+
+        if !completed {
+            return Command::none();
+        }
+        let body = match next {
+            Phase::Idle => fl!("ready-to-focus"),
+            _ => fl!("break-for", minutes = self.phase_minutes(next)),
+        };
+        Command::perform(notify::notify(summary, body), |res| {
+            Message::Notified(res.map_err(|err| err.to_string()))
+        })
+    }
+
+    fn phase_label(&self) -> String {
+        match self.phase {
+            Phase::Idle => fl!("ready"),
+            Phase::Focus => fl!("focusing"),
+            Phase::ShortBreak => fl!("short-break"),
+            Phase::LongBreak => fl!("long-break"),
+        }
+    }
+
+    fn controls(&self) -> Element<Message> {
+        let mut controls = Row::new().spacing(8);
+        controls = match (self.phase, self.paused.is_some()) {
+            (Phase::Idle, _) => {
+                controls.push(button::suggested(fl!("start-focus")).on_press(Message::Start))
+            }
+            (_, true) => controls.push(button::suggested(fl!("resume")).on_press(Message::Resume)),
+            (_, false) => controls.push(button::standard(fl!("pause")).on_press(Message::Pause)),
+        };
+        if self.phase != Phase::Idle {
+            controls = controls
+                .push(button::standard(fl!("skip")).on_press(Message::Skip))
+                .push(button::standard(fl!("reset")).on_press(Message::Reset));
+        }
+        controls.into()
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = config::APP_ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::APP_ID, PomodoroConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                PomodoroConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+        let notifications_helper = Config::new(
+            cosmic_notifications_config::ID,
+            NotificationsConfig::VERSION,
+        )
+        .ok();
+        let notifications = notifications_helper
+            .as_ref()
+            .map(|helper| {
+                NotificationsConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading notifications config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let window = Self {
+            focus_durations: durations(&FOCUS_MINUTES, config.focus_minutes),
+            break_durations: durations(&BREAK_MINUTES, config.short_break_minutes),
+            config,
+            config_helper,
+            notifications,
+            notifications_helper,
+            ..Default::default()
+        };
+        (window, Command::none())
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        let mut subscriptions = vec![
+            core.watch_config::<PomodoroConfig>(config::APP_ID)
+                .map(|u| Message::ConfigChanged(u.config)),
+            core.watch_config::<NotificationsConfig>(cosmic_notifications_config::ID)
+                .map(|u| Message::NotificationsChanged(u.config)),
+        ];
+        if self.until.is_some() {
+            subscriptions.push(time::every(Duration::from_secs(1)).map(|_| Message::Tick));
+        }
+        Subscription::batch(subscriptions)
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::ConfigChanged(config) => {
+                self.config = config;
+            }
+            Message::NotificationsChanged(notifications) => {
+                // Turned off by the user while focusing, so leave it alone
+                if !notifications.do_not_disturb {
+                    self.enabled_dnd = false;
+                }
+                self.notifications = notifications;
+            }
+            Message::Start => {
+                self.enter(Phase::Focus);
+            }
+            Message::Pause => {
+                self.paused = self.remaining();
+                self.until = None;
+            }
+            Message::Resume => {
+                if let Some(remaining) = self.paused.take() {
+                    self.until = Some(Instant::now() + remaining);
+                }
+            }
+            Message::Skip => {
+                return self.advance(false);
+            }
+            Message::Reset => {
+                self.sessions = 0;
+                self.enter(Phase::Idle);
+            }
+            Message::Tick => {
+                if self.paused.is_none() && self.remaining() == Some(Duration::ZERO) {
+                    return self.advance(true);
+                }
+            }
+            Message::FocusDuration(entity) => {
+                self.focus_durations.activate(entity);
+                if let (Some(helper), Some(minutes)) = (
+                    &self.config_helper,
+                    self.focus_durations.data::<u64>(entity).copied(),
+                ) {
+                    if let Err(err) = self.config.set_focus_minutes(helper, minutes) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+            Message::BreakDuration(entity) => {
+                self.break_durations.activate(entity);
+                if let (Some(helper), Some(minutes)) = (
+                    &self.config_helper,
+                    self.break_durations.data::<u64>(entity).copied(),
+                ) {
+                    if let Err(err) = self.config.set_short_break_minutes(helper, minutes) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+            Message::DoNotDisturb(do_not_disturb) => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_do_not_disturb(helper, do_not_disturb) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+                if self.phase == Phase::Focus {
+                    if do_not_disturb && !self.notifications.do_not_disturb {
+                        self.set_do_not_disturb(true);
+                        self.enabled_dnd = true;
+                    } else if !do_not_disturb && self.enabled_dnd {
+                        self.set_do_not_disturb(false);
+                        self.enabled_dnd = false;
+                    }
+                }
+            }
+            Message::Notified(res) => {
+                if let Err(err) = res {
+                    tracing::error!("Failed to send notification: {}", err);
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let btn = core
+            .applet
+            .icon_button(ICON)
+            .on_press(AppletMessage::TogglePopup)
+            .into();
+        let Some(remaining) = self.remaining() else {
+            return btn;
+        };
+        let remaining = text(format_remaining(remaining)).size(14).into();
+        match core.applet.anchor {
+            PanelAnchor::Left | PanelAnchor::Right => Column::with_children(vec![btn, remaining])
+                .align_items(Alignment::Center)
+                .into(),
+            PanelAnchor::Top | PanelAnchor::Bottom => Row::with_children(vec![btn, remaining])
+                .align_items(Alignment::Center)
+                .spacing(4)
+                .into(),
+        }
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let remaining = self
+            .remaining()
+            .unwrap_or(Duration::from_secs(self.config.focus_minutes * 60));
+        let (sessions_today, minutes_today) = self.config.stats.today();
+
+        let content = vec![
+            padded_control(
+                column![
+                    text(format_remaining(remaining))
+                        .size(40)
+                        .horizontal_alignment(Horizontal::Center)
+                        .width(Length::Fill),
+                    text(self.phase_label())
+                        .size(14)
+                        .horizontal_alignment(Horizontal::Center)
+                        .width(Length::Fill),
+                ]
+                .spacing(4),
+            )
+            .into(),
+            padded_control(
+                row![self.controls()]
+                    .width(Length::Fill)
+                    .align_items(Alignment::Center),
+            )
+            .into(),
+            padded_control(divider::horizontal::default()).into(),
+            padded_control(
+                column![
+                    text(fl!("today")).size(14),
+                    text(fl!(
+                        "today-stats",
+                        sessions = sessions_today,
+                        minutes = minutes_today
+                    ))
+                    .size(10),
+                    text(fl!(
+                        "total-stats",
+                        sessions = self.config.stats.sessions_total
+                    ))
+                    .size(10),
+                ]
+                .spacing(2),
+            )
+            .into(),
+            padded_control(divider::horizontal::default()).into(),
+            padded_control(
+                column![
+                    text(fl!("focus-length")).size(14),
+                    segmented_control::horizontal(&self.focus_durations)
+                        .on_activate(Message::FocusDuration),
+                    text(fl!("break-length")).size(14),
+                    segmented_control::horizontal(&self.break_durations)
+                        .on_activate(Message::BreakDuration),
+                ]
+                .spacing(8),
+            )
+            .into(),
+            padded_control(
+                toggler(
+                    Some(fl!("dnd-while-focusing")),
+                    self.config.do_not_disturb,
+                    Message::DoNotDisturb,
+                )
+                .text_size(14)
+                .width(Length::Fill),
+            )
+            .into(),
+        ];
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+}
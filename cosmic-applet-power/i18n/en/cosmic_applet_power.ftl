@@ -5,6 +5,7 @@ lock-screen-shortcut = Super + Escape
 log-out = Log Out
 log-out-shortcut = Ctrl + Alt + Delete
 suspend = Suspend
+hibernate = Hibernate
 restart = Restart
 shutdown = Shutdown
 confirm = Confirm
@@ -13,6 +14,7 @@ confirm-button = {
     $action -> 
         [restart] { restart }
         [suspend] { suspend}
+        [hibernate] { hibernate }
         [shutdown] Power off
         [log-out] { log-out }
         *[other] { confirm}
@@ -21,6 +23,7 @@ confirm-title =
     { $action -> 
         [restart] { restart }
         [suspend] { suspend }
+        [hibernate] { hibernate }
         [shutdown] { shutdown }
         [log-out] Quit all applications and log out
         *[other] Apply the selected action
@@ -29,9 +32,50 @@ confirm-body =
     The system will { $action ->
         [restart] restart
         [suspend] suspend
+        [hibernate] hibernate
         [shutdown] power off
         [lock-screen] lock the screen
         [log-out] log out
         *[other] apply the selected action
     } automatically in { $countdown } seconds.
 
+confirm-body-blocked =
+    Some applications are preventing the system from { $action ->
+        [restart] restarting
+        [suspend] suspending
+        [hibernate] hibernating
+        [shutdown] powering off
+        *[other] applying the selected action
+    }.
+confirm-override =
+    { $action ->
+        [restart] Restart anyway
+        [suspend] Suspend anyway
+        [hibernate] Hibernate anyway
+        [shutdown] Power off anyway
+        *[other] Continue anyway
+    }
+inhibitor-blocking = Blocking
+inhibitor-delaying = Delaying
+schedule = Shut down in…
+schedule-confirm = Schedule
+in-minutes = In { $minutes } minutes
+in-hours = In { $hours ->
+        [one] 1 hour
+       *[other] { $hours } hours
+    }
+custom-minutes = Minutes
+scheduled-shutdown = Shutting down in { $remaining }
+scheduled-restart = Restarting in { $remaining }
+switch-user = Switch User…
+idle-screen-off = Turn off screen
+idle-suspend = Automatic suspend
+idle-after = After { $minutes ->
+        [one] 1 minute
+       *[other] { $minutes } minutes
+    }
+idle-minutes = { $minutes } minutes
+idle-never = Never
+idle-on-battery = On battery
+idle-plugged-in = Plugged in
+skip-confirmation = Log out, restart and power off without confirming
@@ -0,0 +1,83 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! What happens when the session is idle, as cosmic-idle reads it from the
+//! config cosmic-settings writes.
+
+use std::fs;
+
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
+pub const ID: &str = "com.system76.CosmicIdle";
+
+/// Timeouts offered in the popup, in minutes, where `None` is never.
+pub const PRESETS: [Option<u32>; 4] = [Some(5), Some(15), Some(30), None];
+
+/// Timeouts in milliseconds, or `None` to never time out.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, CosmicConfigEntry)]
+#[version = 1]
+pub struct IdleConfig {
+    /// Turns the screen off, which locks the session.
+    pub screen_off_time: Option<u32>,
+    pub suspend_on_ac_time: Option<u32>,
+    pub suspend_on_battery_time: Option<u32>,
+}
+
+/// The defaults of cosmic-idle, for settings never changed.
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self {
+            screen_off_time: Some(15 * 60 * 1000),
+            suspend_on_ac_time: Some(30 * 60 * 1000),
+            suspend_on_battery_time: Some(15 * 60 * 1000),
+        }
+    }
+}
+
+impl IdleConfig {
+    /// The suspend timeout of the current power source.
+    pub fn suspend_time(&self, on_battery: bool) -> Option<u32> {
+        if on_battery {
+            self.suspend_on_battery_time
+        } else {
+            self.suspend_on_ac_time
+        }
+    }
+}
+
+pub fn minutes_to_millis(minutes: Option<u32>) -> Option<u32> {
+    minutes.map(|minutes| minutes * 60 * 1000)
+}
+
+/// Where `millis` is among the presets.
+pub fn preset_position(millis: Option<u32>) -> Option<usize> {
+    PRESETS
+        .iter()
+        .position(|minutes| minutes_to_millis(*minutes) == millis)
+}
+
+/// Whether the system runs on battery, having mains power that's offline.
+/// Desktops often don't report their mains power at all.
+pub fn on_battery() -> bool {
+    let Ok(entries) = fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+    let mut on_battery = false;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let kind = fs::read_to_string(path.join("type")).unwrap_or_default();
+        if kind.trim() != "Mains" {
+            continue;
+        }
+        match fs::read_to_string(path.join("online"))
+            .as_deref()
+            .map(str::trim)
+        {
+            Ok("1") => return false,
+            Ok(_) => on_battery = true,
+            Err(_) => {}
+        }
+    }
+    on_battery
+}
@@ -0,0 +1,32 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Applications blocking or delaying sleep and shutdown, through logind's
+//! inhibitor locks.
+
+use logind_zbus::manager::{InhibitType, ManagerProxy, Mode};
+use zbus::Connection;
+
+#[derive(Debug, Clone)]
+pub struct Inhibitor {
+    pub who: String,
+    pub why: String,
+    /// Whether the action is blocked, rather than only delayed.
+    pub blocking: bool,
+}
+
+/// Inhibitors of `what`, such as sleep or shutdown.
+pub async fn inhibitors(what: InhibitType) -> zbus::Result<Vec<Inhibitor>> {
+    let connection = Connection::system().await?;
+    let manager_proxy = ManagerProxy::new(&connection).await?;
+    let inhibitors = manager_proxy.list_inhibitors().await?;
+    Ok(inhibitors
+        .into_iter()
+        .filter(|inhibitor| inhibitor.what().types().contains(&what))
+        .map(|inhibitor| Inhibitor {
+            who: inhibitor.who().to_string(),
+            why: inhibitor.why().to_string(),
+            blocking: inhibitor.mode() == Mode::Block,
+        })
+        .collect())
+}
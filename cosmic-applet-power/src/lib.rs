@@ -4,8 +4,9 @@
 use std::{collections::HashMap, process, time::Duration};
 
 use cosmic::{
-    app::Command,
-    applet::{menu_button, padded_control},
+    app::Core,
+    applet::{cosmic_panel_config::PanelAnchor, menu_button, padded_control},
+    cosmic_config::{Config, CosmicConfigEntry},
     iced,
     iced::{
         alignment::{Horizontal, Vertical},
@@ -15,26 +16,27 @@ use cosmic::{
             PlatformSpecific,
         },
         time,
-        wayland::{
-            actions::layer_surface::SctkLayerSurfaceSettings,
-            popup::{destroy_popup, get_popup},
-        },
+        wayland::actions::layer_surface::SctkLayerSurfaceSettings,
         widget::{self, column, container, row, space::Space, text},
-        window, Alignment, Length, Subscription,
+        window::{self, Id},
+        Alignment, Command, Length, Limits, Subscription,
     },
-    iced_runtime::core::layout::Limits,
     iced_sctk::commands::layer_surface::{
         destroy_layer_surface, get_layer_surface, Anchor, KeyboardInteractivity,
     },
-    iced_style::application,
     iced_widget::mouse_area,
     theme,
-    widget::{button, divider, horizontal_space, icon, vertical_space, Column},
-    Element, Renderer, Theme,
+    widget::{
+        button, divider, dropdown, horizontal_space, icon,
+        segmented_button::{Entity, SingleSelectModel},
+        segmented_control, text_input, toggler, vertical_space, Column, Row,
+    },
+    Element, Renderer,
 };
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent, PopupRequest};
 
 use logind_zbus::{
-    manager::ManagerProxy,
+    manager::{InhibitType, IsSupported, ManagerProxy},
     session::{SessionProxy, SessionType},
     user::UserProxy,
 };
@@ -42,27 +44,58 @@ use once_cell::sync::Lazy;
 use rustix::process::getuid;
 use zbus::Connection;
 
+mod config;
 pub mod cosmic_session;
+mod idle;
+mod inhibitor;
 mod localize;
+mod schedule;
 pub mod session_manager;
-
-use crate::{cosmic_session::CosmicSessionProxy, session_manager::SessionManagerProxy};
+mod sessions;
+
+use crate::{
+    config::PowerConfig,
+    cosmic_session::CosmicSessionProxy,
+    idle::IdleConfig,
+    inhibitor::{inhibitors, Inhibitor},
+    schedule::{format_remaining, ScheduleKind, Scheduled},
+    session_manager::SessionManagerProxy,
+    sessions::{Greeter, Sessions, UserSession},
+};
 
 pub fn run() -> cosmic::iced::Result {
     localize::localize();
 
-    cosmic::applet::run::<Power>(false, ())
+    cosmic_applet_helper::run_applet::<Power>()
 }
 
 const COUNTDOWN_LENGTH: u8 = 60;
+/// Delays offered for a scheduled shutdown or restart, in minutes.
+const SCHEDULE_PRESETS: [u64; 4] = [15, 30, 60, 120];
 static CONFIRM_ID: Lazy<iced::id::Id> = Lazy::new(|| iced::id::Id::new("confirm-id"));
 
 #[derive(Default)]
 struct Power {
-    core: cosmic::app::Core,
     icon_name: String,
-    popup: Option<window::Id>,
     action_to_confirm: Option<(window::Id, PowerAction, u8)>,
+    /// Inhibitors of the action to confirm.
+    inhibitors: Vec<Inhibitor>,
+    config: PowerConfig,
+    config_helper: Option<Config>,
+    can_suspend: bool,
+    can_hibernate: bool,
+    scheduled: Option<Scheduled>,
+    schedule_expanded: bool,
+    schedule_kinds: SingleSelectModel,
+    custom_delay: String,
+    /// Sessions of other users.
+    sessions: Vec<UserSession>,
+    /// Switches to the login screen, if the display manager supports it.
+    greeter: Option<Greeter>,
+    idle_config: IdleConfig,
+    idle_helper: Option<Config>,
+    idle_labels: Vec<String>,
+    on_battery: bool,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -70,19 +103,29 @@ enum PowerAction {
     Lock,
     LogOut,
     Suspend,
+    Hibernate,
     Restart,
     Shutdown,
 }
 
 impl PowerAction {
-    fn perform(self) -> iced::Command<cosmic::app::Message<Message>> {
-        let msg = |m| cosmic::app::message::app(Message::Zbus(m));
+    /// The logind inhibitor lock type that applies to this action.
+    fn inhibitor_type(self) -> Option<InhibitType> {
         match self {
-            PowerAction::Lock => iced::Command::perform(lock(), msg),
-            PowerAction::LogOut => iced::Command::perform(log_out(), msg),
-            PowerAction::Suspend => iced::Command::perform(suspend(), msg),
-            PowerAction::Restart => iced::Command::perform(restart(), msg),
-            PowerAction::Shutdown => iced::Command::perform(shutdown(), msg),
+            PowerAction::Suspend | PowerAction::Hibernate => Some(InhibitType::Sleep),
+            PowerAction::Restart | PowerAction::Shutdown => Some(InhibitType::Shutdown),
+            PowerAction::Lock | PowerAction::LogOut => None,
+        }
+    }
+
+    fn perform(self) -> Command<Message> {
+        match self {
+            PowerAction::Lock => Command::perform(lock(), Message::Zbus),
+            PowerAction::LogOut => Command::perform(log_out(), Message::Zbus),
+            PowerAction::Suspend => Command::perform(suspend(), Message::Zbus),
+            PowerAction::Hibernate => Command::perform(hibernate(), Message::Zbus),
+            PowerAction::Restart => Command::perform(restart(), Message::Zbus),
+            PowerAction::Shutdown => Command::perform(shutdown(), Message::Zbus),
         }
     }
 }
@@ -91,46 +134,359 @@ impl PowerAction {
 enum Message {
     Countdown,
     Action(PowerAction),
-    TogglePopup,
+    Inhibitors(PowerAction, Vec<Inhibitor>),
     Settings,
     Confirm,
     Cancel,
     Zbus(Result<(), zbus::Error>),
-    Closed(window::Id),
+    Capabilities(bool, bool),
+    ConfigChanged(PowerConfig),
+    SkipConfirmation(bool),
+    ToggleSchedule,
+    ScheduleKind(Entity),
+    ScheduleIn(u64),
+    CustomDelay(String),
+    SubmitCustomDelay,
+    CancelSchedule,
+    Scheduled(Option<Scheduled>),
+    Tick,
+    Sessions(Sessions),
+    ActivateSession(String),
+    TerminateSession(String),
+    SwitchUser,
+    IdleConfigChanged(IdleConfig),
+    SetScreenOffTime(usize),
+    SetSuspendTime(usize),
     LayerFocus,
 }
 
-impl cosmic::Application for Power {
-    type Executor = cosmic::SingleThreadExecutor;
-    type Flags = ();
-    type Message = Message;
-    const APP_ID: &'static str = "com.system76.CosmicAppletPower";
+impl Power {
+    fn schedule(&mut self, minutes: u64) -> Command<Message> {
+        let kind = self
+            .schedule_kinds
+            .active_data::<ScheduleKind>()
+            .copied()
+            .unwrap_or(ScheduleKind::Shutdown);
+        self.schedule_expanded = false;
+        self.custom_delay.clear();
+        Command::perform(
+            schedule::schedule(kind, Duration::from_secs(minutes * 60)),
+            scheduled_message,
+        )
+    }
 
-    fn core(&self) -> &cosmic::app::Core {
-        &self.core
+    fn sessions_view(&self) -> Element<Message> {
+        let mut content = Vec::with_capacity(self.sessions.len() + 1);
+        for session in &self.sessions {
+            let avatar: Element<_> = if let Some(path) = session.icon.as_ref() {
+                widget::image(path.as_path())
+                    .width(Length::Fixed(24.0))
+                    .height(Length::Fixed(24.0))
+                    .into()
+            } else {
+                text_icon("avatar-default-symbolic", 24).into()
+            };
+            content.push(
+                menu_button(
+                    row![
+                        avatar,
+                        column![
+                            text(session.display_name()).size(14),
+                            text(&session.user_name).size(10),
+                        ]
+                        .width(Length::Fill),
+                        button::icon(icon::from_name("system-log-out-symbolic"))
+                            .on_press(Message::TerminateSession(session.id.clone())),
+                    ]
+                    .align_items(Alignment::Center)
+                    .spacing(8),
+                )
+                .on_press(Message::ActivateSession(session.id.clone()))
+                .into(),
+            );
+        }
+        if self.greeter.is_some() {
+            content.push(
+                menu_button(
+                    row![
+                        text_icon("system-switch-user-symbolic", 24),
+                        text(fl!("switch-user")).size(14),
+                    ]
+                    .align_items(Alignment::Center)
+                    .spacing(8),
+                )
+                .on_press(Message::SwitchUser)
+                .into(),
+            );
+        }
+        // The section is left out when there's nothing to switch to
+        if !content.is_empty() {
+            content.push(padded_control(divider::horizontal::default()).into());
+        }
+        Column::with_children(content).into()
     }
 
-    fn core_mut(&mut self) -> &mut cosmic::app::Core {
-        &mut self.core
+    fn schedule_view(&self) -> Element<Message> {
+        let mut content = vec![menu_button(
+            row![
+                text_icon("alarm-symbolic", 24),
+                text(fl!("schedule")).size(14),
+                Space::with_width(Length::Fill),
+                text_icon(
+                    if self.schedule_expanded {
+                        "go-up-symbolic"
+                    } else {
+                        "go-down-symbolic"
+                    },
+                    14
+                ),
+            ]
+            .align_items(Alignment::Center)
+            .spacing(8),
+        )
+        .on_press(Message::ToggleSchedule)
+        .into()];
+
+        if let Some(scheduled) = self.scheduled.as_ref() {
+            let remaining = format_remaining(scheduled.remaining());
+            content.push(
+                padded_control(
+                    row![
+                        text(match scheduled.kind {
+                            ScheduleKind::Shutdown => {
+                                fl!("scheduled-shutdown", remaining = remaining)
+                            }
+                            ScheduleKind::Restart => {
+                                fl!("scheduled-restart", remaining = remaining)
+                            }
+                        })
+                        .size(14)
+                        .width(Length::Fill),
+                        button::standard(fl!("cancel")).on_press(Message::CancelSchedule),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+        }
+
+        if self.schedule_expanded {
+            content.push(
+                padded_control(
+                    segmented_control::horizontal(&self.schedule_kinds)
+                        .on_activate(Message::ScheduleKind),
+                )
+                .into(),
+            );
+            for minutes in SCHEDULE_PRESETS {
+                let label = if minutes % 60 == 0 {
+                    fl!("in-hours", hours = minutes / 60)
+                } else {
+                    fl!("in-minutes", minutes = minutes)
+                };
+                content.push(
+                    menu_button(text(label).size(14))
+                        .on_press(Message::ScheduleIn(minutes))
+                        .into(),
+                );
+            }
+            let custom_delay = self.custom_delay.trim().parse::<u64>().ok();
+            content.push(
+                padded_control(
+                    row![
+                        text_input(fl!("custom-minutes"), &self.custom_delay)
+                            .on_input(Message::CustomDelay)
+                            .on_submit(Message::SubmitCustomDelay),
+                        button::standard(fl!("schedule-confirm")).on_press_maybe(
+                            custom_delay
+                                .filter(|minutes| *minutes > 0)
+                                .map(|_| Message::SubmitCustomDelay)
+                        ),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+        }
+
+        Column::with_children(content).into()
+    }
+
+    fn idle_view(&self) -> Element<Message> {
+        let status = |millis: Option<u32>| match millis {
+            Some(millis) => fl!("idle-after", minutes = millis / 60_000),
+            None => fl!("idle-never"),
+        };
+        let idle_row = |label: String,
+                        status: String,
+                        millis: Option<u32>,
+                        on_select: fn(usize) -> Message|
+         -> Element<Message> {
+            padded_control(
+                row![
+                    column![text(label).size(14), text(status).size(10)].width(Length::Fill),
+                    dropdown(&self.idle_labels, idle::preset_position(millis), on_select),
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center),
+            )
+            .into()
+        };
+
+        let mut content = vec![idle_row(
+            fl!("idle-screen-off"),
+            status(self.idle_config.screen_off_time),
+            self.idle_config.screen_off_time,
+            Message::SetScreenOffTime,
+        )];
+        if self.can_suspend {
+            let suspend_time = self.idle_config.suspend_time(self.on_battery);
+            let source = if self.on_battery {
+                fl!("idle-on-battery")
+            } else {
+                fl!("idle-plugged-in")
+            };
+            content.push(idle_row(
+                fl!("idle-suspend"),
+                format!("{} · {}", status(suspend_time), source),
+                suspend_time,
+                Message::SetSuspendTime,
+            ));
+        }
+        Column::with_children(content).into()
+    }
+
+    fn request(&mut self, action: PowerAction, inhibitors: Vec<Inhibitor>) -> Command<Message> {
+        // Ask for user confirmation of non-destructive actions only, unless
+        // something is blocking them. Delaying them only holds them up for
+        // a moment, and most systems have a service that always does.
+        if !inhibitors.iter().any(|i| i.blocking)
+            && (matches!(
+                action,
+                PowerAction::Lock | PowerAction::Suspend | PowerAction::Hibernate
+            ) || matches!(action, PowerAction::Restart)
+                && matches!(self.action_to_confirm, Some((_, PowerAction::Shutdown, _)))
+                || self.config.skip_confirmation)
+        {
+            return action.perform();
+        }
+
+        let mut commands = Vec::with_capacity(2);
+        // Replaces the dialog, when choosing to restart instead of shutting down
+        if let Some((id, _, _)) = self.action_to_confirm.take() {
+            commands.push(destroy_layer_surface(id));
+        }
+        let id = window::Id::unique();
+        self.action_to_confirm = Some((id, action, COUNTDOWN_LENGTH));
+        self.inhibitors = inhibitors;
+        commands.push(get_layer_surface(SctkLayerSurfaceSettings {
+            id,
+            keyboard_interactivity: KeyboardInteractivity::None,
+            anchor: Anchor::all(),
+            namespace: "dialog".into(),
+            size: Some((None, None)),
+            size_limits: Limits::NONE.min_width(1.0).min_height(1.0),
+            ..Default::default()
+        }));
+        Command::batch(commands)
     }
+}
 
-    fn init(core: cosmic::app::Core, _flags: ()) -> (Self, Command<Message>) {
+impl CosmicApplet for Power {
+    type Message = Message;
+    const APP_ID: &'static str = "com.system76.CosmicAppletPower";
+    const AUTOSIZE: bool = false;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::APP_ID, PowerConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                PowerConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+        let idle_helper = Config::new(idle::ID, IdleConfig::VERSION).ok();
+        let idle_config = idle_helper
+            .as_ref()
+            .map(|helper| {
+                IdleConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading idle config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+        let idle_labels = idle::PRESETS
+            .iter()
+            .map(|minutes| match minutes {
+                Some(minutes) => fl!("idle-minutes", minutes = *minutes),
+                None => fl!("idle-never"),
+            })
+            .collect();
+        let mut schedule_kinds = SingleSelectModel::default();
+        schedule_kinds
+            .insert()
+            .text(fl!("shutdown"))
+            .data(ScheduleKind::Shutdown)
+            .activate();
+        schedule_kinds
+            .insert()
+            .text(fl!("restart"))
+            .data(ScheduleKind::Restart);
         (
             Self {
-                core,
                 icon_name: "system-shutdown-symbolic".to_string(),
+                config,
+                config_helper,
+                // Assume support until logind says otherwise
+                can_suspend: true,
+                schedule_kinds,
+                idle_config,
+                idle_helper,
+                idle_labels,
+                on_battery: idle::on_battery(),
                 ..Default::default()
             },
-            Command::none(),
+            Command::batch([
+                Command::perform(capabilities(), |(can_suspend, can_hibernate)| {
+                    Message::Capabilities(can_suspend, can_hibernate)
+                }),
+                Command::perform(schedule::scheduled(), scheduled_message),
+            ]),
         )
     }
 
-    fn on_close_requested(&self, id: window::Id) -> Option<Message> {
-        Some(Message::Closed(id))
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        if event != PanelEvent::PopupOpened {
+            return Command::none();
+        }
+        self.on_battery = idle::on_battery();
+        Command::perform(sessions::sessions(), |sessions| {
+            Message::Sessions(sessions.unwrap_or_else(|err| {
+                tracing::error!("Failed to list sessions: {}", err);
+                Sessions::default()
+            }))
+        })
     }
 
-    fn subscription(&self) -> Subscription<Message> {
-        let mut subscriptions = Vec::with_capacity(2);
+    fn subscription(&self, core: &Core) -> Subscription<Message> {
+        let mut subscriptions = Vec::with_capacity(5);
+        subscriptions.push(
+            core.watch_config::<PowerConfig>(config::APP_ID)
+                .map(|u| Message::ConfigChanged(u.config)),
+        );
+        subscriptions.push(
+            core.watch_config::<IdleConfig>(idle::ID)
+                .map(|u| Message::IdleConfigChanged(u.config)),
+        );
         subscriptions.push(listen_with(|e, _status| match e {
             cosmic::iced::Event::PlatformSpecific(PlatformSpecific::Wayland(
                 wayland::Event::Layer(LayerEvent::Unfocused, ..),
@@ -140,62 +496,45 @@ impl cosmic::Application for Power {
             )) => Some(Message::LayerFocus),
             _ => None,
         }));
-        if self.action_to_confirm.is_some() {
+        // Blocked actions are only performed if the user overrides them
+        if self.action_to_confirm.is_some() && !self.inhibitors.iter().any(|i| i.blocking) {
             subscriptions
                 .push(time::every(Duration::from_millis(1000)).map(|_| Message::Countdown));
         }
         Subscription::batch(subscriptions)
     }
 
-    fn update(&mut self, message: Message) -> Command<Message> {
+    fn panel_subscription(&self, _core: &Core) -> Subscription<Message> {
+        // The countdown to a scheduled shutdown only updates what's shown
+        if self.scheduled.is_some() {
+            time::every(Duration::from_millis(1000)).map(|_| Message::Tick)
+        } else {
+            Subscription::none()
+        }
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
         match message {
-            Message::TogglePopup => {
-                if let Some(p) = self.popup.take() {
-                    destroy_popup(p)
-                } else {
-                    let new_id = window::Id::unique();
-                    self.popup.replace(new_id);
-
-                    let mut popup_settings = self.core.applet.get_popup_settings(
-                        window::Id::MAIN,
-                        new_id,
-                        None,
-                        None,
-                        None,
-                    );
-                    popup_settings.positioner.size_limits = Limits::NONE
-                        .min_width(100.0)
-                        .min_height(100.0)
-                        .max_height(400.0)
-                        .max_width(500.0);
-                    get_popup(popup_settings)
-                }
-            }
             Message::Settings => {
                 let _ = process::Command::new("cosmic-settings").spawn();
                 Command::none()
             }
             Message::Action(action) => {
-                // Ask for user confirmation of non-destructive actions only
-                if matches!(action, PowerAction::Lock | PowerAction::Suspend)
-                    || matches!(action, PowerAction::Restart)
-                        && matches!(self.action_to_confirm, Some((_, PowerAction::Shutdown, _)))
-                {
-                    action.perform()
-                } else {
-                    let id = window::Id::unique();
-                    self.action_to_confirm = Some((id, action, COUNTDOWN_LENGTH));
-                    get_layer_surface(SctkLayerSurfaceSettings {
-                        id,
-                        keyboard_interactivity: KeyboardInteractivity::None,
-                        anchor: Anchor::all(),
-                        namespace: "dialog".into(),
-                        size: Some((None, None)),
-                        size_limits: Limits::NONE.min_width(1.0).min_height(1.0),
-                        ..Default::default()
+                if let Some(what) = action.inhibitor_type() {
+                    Command::perform(inhibitors(what), move |inhibitors| {
+                        Message::Inhibitors(
+                            action,
+                            inhibitors.unwrap_or_else(|err| {
+                                tracing::error!("Failed to list inhibitors: {}", err);
+                                Vec::new()
+                            }),
+                        )
                     })
+                } else {
+                    self.request(action, Vec::new())
                 }
             }
+            Message::Inhibitors(action, inhibitors) => self.request(action, inhibitors),
             Message::Zbus(result) => {
                 if let Err(e) = result {
                     eprintln!("cosmic-applet-power ERROR: '{}'", e);
@@ -204,6 +543,7 @@ impl cosmic::Application for Power {
             }
             Message::Confirm => {
                 if let Some((id, a, _)) = self.action_to_confirm.take() {
+                    self.inhibitors.clear();
                     Command::batch(vec![destroy_layer_surface(id), a.perform()])
                 } else {
                     Command::none()
@@ -211,6 +551,7 @@ impl cosmic::Application for Power {
             }
             Message::Cancel => {
                 if let Some((id, _, _)) = self.action_to_confirm.take() {
+                    self.inhibitors.clear();
                     return destroy_layer_surface(id);
                 }
                 Command::none()
@@ -228,9 +569,86 @@ impl cosmic::Application for Power {
                 }
                 Command::none()
             }
-            Message::Closed(id) => {
-                if self.popup == Some(id) {
-                    self.popup = None;
+            Message::Capabilities(can_suspend, can_hibernate) => {
+                self.can_suspend = can_suspend;
+                self.can_hibernate = can_hibernate;
+                Command::none()
+            }
+            Message::ConfigChanged(config) => {
+                self.config = config;
+                Command::none()
+            }
+            Message::SkipConfirmation(skip_confirmation) => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_skip_confirmation(helper, skip_confirmation) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+                Command::none()
+            }
+            Message::ToggleSchedule => {
+                self.schedule_expanded = !self.schedule_expanded;
+                Command::none()
+            }
+            Message::ScheduleKind(entity) => {
+                self.schedule_kinds.activate(entity);
+                Command::none()
+            }
+            Message::ScheduleIn(minutes) => self.schedule(minutes),
+            Message::CustomDelay(delay) => {
+                self.custom_delay = delay;
+                Command::none()
+            }
+            Message::SubmitCustomDelay => match self.custom_delay.trim().parse::<u64>() {
+                Ok(minutes) if minutes > 0 => self.schedule(minutes),
+                _ => Command::none(),
+            },
+            Message::CancelSchedule => Command::perform(schedule::cancel(), scheduled_message),
+            Message::Scheduled(scheduled) => {
+                self.scheduled = scheduled;
+                Command::none()
+            }
+            Message::Tick => Command::none(),
+            Message::Sessions(sessions) => {
+                self.sessions = sessions.users;
+                self.greeter = sessions.greeter;
+                Command::none()
+            }
+            Message::ActivateSession(id) => Command::perform(sessions::activate(id), Message::Zbus),
+            Message::TerminateSession(id) => {
+                self.sessions.retain(|session| session.id != id);
+                Command::perform(sessions::terminate(id), Message::Zbus)
+            }
+            Message::SwitchUser => {
+                let Some(greeter) = self.greeter.clone() else {
+                    return Command::none();
+                };
+                Command::perform(sessions::switch_to_greeter(greeter), Message::Zbus)
+            }
+            Message::IdleConfigChanged(config) => {
+                self.idle_config = config;
+                Command::none()
+            }
+            Message::SetScreenOffTime(i) => {
+                let millis = idle::minutes_to_millis(idle::PRESETS[i]);
+                if let Some(helper) = &self.idle_helper {
+                    if let Err(err) = self.idle_config.set_screen_off_time(helper, millis) {
+                        tracing::error!(?err, "Error writing idle config");
+                    }
+                }
+                Command::none()
+            }
+            Message::SetSuspendTime(i) => {
+                let millis = idle::minutes_to_millis(idle::PRESETS[i]);
+                if let Some(helper) = &self.idle_helper {
+                    let res = if self.on_battery {
+                        self.idle_config.set_suspend_on_battery_time(helper, millis)
+                    } else {
+                        self.idle_config.set_suspend_on_ac_time(helper, millis)
+                    };
+                    if let Err(err) = res {
+                        tracing::error!(?err, "Error writing idle config");
+                    }
                 }
                 Command::none()
             }
@@ -238,133 +656,205 @@ impl cosmic::Application for Power {
         }
     }
 
-    fn view(&self) -> Element<Message> {
-        self.core
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let btn = core
             .applet
             .icon_button(&self.icon_name)
-            .on_press(Message::TogglePopup)
-            .into()
+            .on_press(AppletMessage::TogglePopup)
+            .into();
+
+        let Some(scheduled) = self.scheduled.as_ref() else {
+            return btn;
+        };
+        // Countdown next to the icon until the scheduled shutdown or restart
+        let countdown = text(format_remaining(scheduled.remaining()))
+            .size(12)
+            .into();
+        match core.applet.anchor {
+            PanelAnchor::Left | PanelAnchor::Right => Column::with_children(vec![btn, countdown])
+                .align_items(Alignment::Center)
+                .into(),
+            PanelAnchor::Top | PanelAnchor::Bottom => Row::with_children(vec![btn, countdown])
+                .align_items(Alignment::Center)
+                .spacing(4)
+                .into(),
+        }
     }
 
-    fn view_window(&self, id: window::Id) -> Element<Message> {
-        if matches!(self.popup, Some(p) if p == id) {
-            let settings = menu_button(text(fl!("settings")).size(14)).on_press(Message::Settings);
-
-            let session = column![
-                menu_button(
-                    row![
-                        text_icon("system-lock-screen-symbolic", 24),
-                        text(fl!("lock-screen")).size(14),
-                        Space::with_width(Length::Fill),
-                        text(fl!("lock-screen-shortcut")).size(14),
-                    ]
-                    .align_items(Alignment::Center)
-                    .spacing(8)
-                )
-                .on_press(Message::Action(PowerAction::Lock)),
-                menu_button(
-                    row![
-                        text_icon("system-log-out-symbolic", 24),
-                        text(fl!("log-out")).size(14),
-                        Space::with_width(Length::Fill),
-                        text(fl!("log-out-shortcut")).size(14),
-                    ]
-                    .align_items(Alignment::Center)
-                    .spacing(8)
-                )
-                .on_press(Message::Action(PowerAction::LogOut)),
-            ];
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let settings = menu_button(text(fl!("settings")).size(14)).on_press(Message::Settings);
+
+        let session = column![
+            menu_button(
+                row![
+                    text_icon("system-lock-screen-symbolic", 24),
+                    text(fl!("lock-screen")).size(14),
+                    Space::with_width(Length::Fill),
+                    text(fl!("lock-screen-shortcut")).size(14),
+                ]
+                .align_items(Alignment::Center)
+                .spacing(8)
+            )
+            .on_press(Message::Action(PowerAction::Lock)),
+            menu_button(
+                row![
+                    text_icon("system-log-out-symbolic", 24),
+                    text(fl!("log-out")).size(14),
+                    Space::with_width(Length::Fill),
+                    text(fl!("log-out-shortcut")).size(14),
+                ]
+                .align_items(Alignment::Center)
+                .spacing(8)
+            )
+            .on_press(Message::Action(PowerAction::LogOut)),
+        ];
 
-            let power = row![
+        let mut power = Vec::with_capacity(4);
+        if self.can_suspend {
+            power.push(
                 power_buttons("system-suspend-symbolic", fl!("suspend"))
-                    .on_press(Message::Action(PowerAction::Suspend)),
-                power_buttons("system-restart-symbolic", fl!("restart"))
-                    .on_press(Message::Action(PowerAction::Restart)),
-                power_buttons("system-shutdown-symbolic", fl!("shutdown"))
-                    .on_press(Message::Action(PowerAction::Shutdown)),
-            ]
-            .spacing(24)
-            .padding([0, 24]);
-
-            let content = column![
-                settings,
-                padded_control(divider::horizontal::default()),
-                session,
-                padded_control(divider::horizontal::default()),
-                power
-            ]
-            .align_items(Alignment::Start)
-            .padding([8, 0]);
-
-            self.core.applet.popup_container(content).into()
-        } else if matches!(self.action_to_confirm, Some((c_id, _, _)) if c_id == id) {
-            let cosmic_theme = self.core.system_theme().cosmic();
-            let (_, power_action, countdown) = self.action_to_confirm.as_ref().unwrap();
-            let action = match power_action {
-                PowerAction::Lock => "lock-screen",
-                PowerAction::LogOut => "log-out",
-                PowerAction::Suspend => "suspend",
-                PowerAction::Restart => "restart",
-                PowerAction::Shutdown => "shutdown",
-            };
+                    .on_press(Message::Action(PowerAction::Suspend))
+                    .into(),
+            );
+        }
+        if self.can_hibernate {
+            power.push(
+                power_buttons("system-hibernate-symbolic", fl!("hibernate"))
+                    .on_press(Message::Action(PowerAction::Hibernate))
+                    .into(),
+            );
+        }
+        power.push(
+            power_buttons("system-restart-symbolic", fl!("restart"))
+                .on_press(Message::Action(PowerAction::Restart))
+                .into(),
+        );
+        power.push(
+            power_buttons("system-shutdown-symbolic", fl!("shutdown"))
+                .on_press(Message::Action(PowerAction::Shutdown))
+                .into(),
+        );
+        let power = Row::with_children(power).spacing(24).padding([0, 24]);
+
+        let content = column![
+            settings,
+            padded_control(divider::horizontal::default()),
+            session,
+            padded_control(divider::horizontal::default()),
+            self.idle_view(),
+            padded_control(toggler(
+                Some(fl!("skip-confirmation")),
+                self.config.skip_confirmation,
+                Message::SkipConfirmation,
+            )),
+            padded_control(divider::horizontal::default()),
+            self.sessions_view(),
+            self.schedule_view(),
+            padded_control(divider::horizontal::default()),
+            power
+        ]
+        .align_items(Alignment::Start)
+        .padding([8, 0]);
+
+        Element::from(content).map(AppletMessage::App)
+    }
 
-            let title = fl!(
-                "confirm-title",
+    fn surface_view<'a>(&'a self, core: &'a Core, id: Id) -> Element<'a, AppletMessage<Message>> {
+        let Some((_, power_action, countdown)) = self
+            .action_to_confirm
+            .as_ref()
+            .filter(|(c_id, _, _)| *c_id == id)
+        else {
+            return widget::text("").into();
+        };
+        let cosmic_theme = core.system_theme().cosmic();
+        let action = match power_action {
+            PowerAction::Lock => "lock-screen",
+            PowerAction::LogOut => "log-out",
+            PowerAction::Suspend => "suspend",
+            PowerAction::Hibernate => "hibernate",
+            PowerAction::Restart => "restart",
+            PowerAction::Shutdown => "shutdown",
+        };
+
+        let title = fl!(
+            "confirm-title",
+            HashMap::from_iter(vec![("action", action)])
+        );
+        let countdown = &countdown.to_string();
+        let blocked = self.inhibitors.iter().any(|i| i.blocking);
+        let body = if blocked {
+            fl!(
+                "confirm-body-blocked",
                 HashMap::from_iter(vec![("action", action)])
-            );
-            let countdown = &countdown.to_string();
-            let mut dialog = cosmic::widget::dialog(title)
-                .body(fl!(
-                    "confirm-body",
-                    HashMap::from_iter(vec![("action", action), ("countdown", countdown)])
+            )
+        } else {
+            fl!(
+                "confirm-body",
+                HashMap::from_iter(vec![("action", action), ("countdown", countdown)])
+            )
+        };
+        let confirm = if blocked {
+            fl!(
+                "confirm-override",
+                HashMap::from_iter(vec![("action", action)])
+            )
+        } else {
+            fl!("confirm", HashMap::from_iter(vec![("action", action)]))
+        };
+        let mut dialog = cosmic::widget::dialog(title)
+            .body(body)
+            .primary_action(
+                button(min_width_and_height(
+                    text(confirm).size(14).into(),
+                    142.0,
+                    32.0,
                 ))
-                .primary_action(
-                    button(min_width_and_height(
-                        text(fl!("confirm", HashMap::from_iter(vec![("action", action)])))
-                            .size(14)
-                            .into(),
-                        142.0,
-                        32.0,
-                    ))
-                    .padding([0, cosmic_theme.space_s()])
-                    .id(CONFIRM_ID.clone())
-                    .style(theme::Button::Suggested)
-                    .on_press(Message::Confirm),
-                )
-                .secondary_action(
-                    button(min_width_and_height(
-                        text(fl!("cancel")).size(14).into(),
-                        142.0,
-                        32.0,
-                    ))
-                    .padding([0, cosmic_theme.space_s()])
-                    .style(theme::Button::Standard)
-                    .on_press(Message::Cancel),
-                )
-                .icon(text_icon(
-                    match power_action {
-                        PowerAction::Lock => "system-lock-screen-symbolic",
-                        PowerAction::LogOut => "system-log-out-symbolic",
-                        PowerAction::Suspend => "system-suspend-symbolic",
-                        PowerAction::Restart => "system-restart-symbolic",
-                        PowerAction::Shutdown => "system-shutdown-symbolic",
-                    },
-                    60,
-                ));
-
-            if matches!(power_action, PowerAction::Shutdown) {
-                dialog = dialog.tertiary_action(
-                    button(min_width_and_height(
-                        text(fl!("restart")).size(14).into(),
-                        Length::Shrink,
-                        32.0,
-                    ))
-                    .padding([0, cosmic_theme.space_s()])
-                    .style(theme::Button::Link)
-                    .on_press(Message::Action(PowerAction::Restart)),
-                );
-            }
+                .padding([0, cosmic_theme.space_s()])
+                .id(CONFIRM_ID.clone())
+                .style(theme::Button::Suggested)
+                .on_press(Message::Confirm),
+            )
+            .secondary_action(
+                button(min_width_and_height(
+                    text(fl!("cancel")).size(14).into(),
+                    142.0,
+                    32.0,
+                ))
+                .padding([0, cosmic_theme.space_s()])
+                .style(theme::Button::Standard)
+                .on_press(Message::Cancel),
+            )
+            .icon(text_icon(
+                match power_action {
+                    PowerAction::Lock => "system-lock-screen-symbolic",
+                    PowerAction::LogOut => "system-log-out-symbolic",
+                    PowerAction::Suspend => "system-suspend-symbolic",
+                    PowerAction::Hibernate => "system-hibernate-symbolic",
+                    PowerAction::Restart => "system-restart-symbolic",
+                    PowerAction::Shutdown => "system-shutdown-symbolic",
+                },
+                60,
+            ));
+
+        if !self.inhibitors.is_empty() {
+            dialog = dialog.control(inhibitor_list(&self.inhibitors));
+        }
 
+        if matches!(power_action, PowerAction::Shutdown) {
+            dialog = dialog.tertiary_action(
+                button(min_width_and_height(
+                    text(fl!("restart")).size(14).into(),
+                    Length::Shrink,
+                    32.0,
+                ))
+                .padding([0, cosmic_theme.space_s()])
+                .style(theme::Button::Link)
+                .on_press(Message::Action(PowerAction::Restart)),
+            );
+        }
+
+        Element::from(
             mouse_area(
                 container(dialog)
                     .align_x(Horizontal::Center)
@@ -374,19 +864,42 @@ impl cosmic::Application for Power {
             )
             .on_press(Message::Cancel)
             .on_right_press(Message::Cancel)
-            .on_middle_press(Message::Cancel)
-            .into()
-        } else {
-            //panic!("no view for window {}", id.0)
-            widget::text("").into()
-        }
+            .on_middle_press(Message::Cancel),
+        )
+        .map(AppletMessage::App)
     }
 
-    fn style(&self) -> Option<<Theme as application::StyleSheet>::Style> {
-        Some(cosmic::applet::style())
+    fn popup_request(&self, message: &Message) -> Option<PopupRequest> {
+        matches!(message, Message::ActivateSession(_) | Message::SwitchUser)
+            .then_some(PopupRequest::Close)
     }
 }
 
+fn inhibitor_list(inhibitors: &[Inhibitor]) -> Element<Message> {
+    Column::with_children(
+        inhibitors
+            .iter()
+            .map(|inhibitor| {
+                row![
+                    column![text(&inhibitor.who).size(14), text(&inhibitor.why).size(12),]
+                        .width(Length::Fill),
+                    text(if inhibitor.blocking {
+                        fl!("inhibitor-blocking")
+                    } else {
+                        fl!("inhibitor-delaying")
+                    })
+                    .size(12),
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center)
+                .into()
+            })
+            .collect(),
+    )
+    .spacing(8)
+    .into()
+}
+
 fn power_buttons(name: &str, msg: String) -> cosmic::widget::Button<Message> {
     cosmic::widget::button(
         column![text_icon(name, 40), text(msg).size(14)]
@@ -399,6 +912,13 @@ fn power_buttons(name: &str, msg: String) -> cosmic::widget::Button<Message> {
     .style(theme::Button::Text)
 }
 
+fn scheduled_message(scheduled: zbus::Result<Option<Scheduled>>) -> Message {
+    Message::Scheduled(scheduled.unwrap_or_else(|err| {
+        tracing::error!("Failed to get scheduled shutdown: {}", err);
+        None
+    }))
+}
+
 fn text_icon(name: &str, size: u16) -> cosmic::widget::Icon {
     icon::from_name(name).size(size).symbolic(true).icon()
 }
@@ -423,6 +943,30 @@ async fn suspend() -> zbus::Result<()> {
     manager_proxy.suspend(true).await
 }
 
+async fn hibernate() -> zbus::Result<()> {
+    let connection = Connection::system().await?;
+    let manager_proxy = ManagerProxy::new(&connection).await?;
+    manager_proxy.hibernate(true).await
+}
+
+/// Whether suspending and hibernating are supported, possibly after
+/// authenticating.
+async fn capabilities() -> (bool, bool) {
+    let supported = |res: zbus::Result<IsSupported>| {
+        matches!(res, Ok(IsSupported::Yes | IsSupported::Challenge))
+    };
+    let Ok(connection) = Connection::system().await else {
+        return (true, false);
+    };
+    let Ok(manager_proxy) = ManagerProxy::new(&connection).await else {
+        return (true, false);
+    };
+    (
+        supported(manager_proxy.can_suspend().await),
+        supported(manager_proxy.can_hibernate().await),
+    )
+}
+
 async fn lock() -> zbus::Result<()> {
     let connection = Connection::system().await?;
     let manager_proxy = ManagerProxy::new(&connection).await?;
@@ -0,0 +1,89 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Delayed shutdown and restart, through logind's scheduled shutdown API.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use logind_zbus::manager::ManagerProxy;
+use zbus::Connection;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleKind {
+    Shutdown,
+    Restart,
+}
+
+impl ScheduleKind {
+    fn logind_type(self) -> &'static str {
+        match self {
+            ScheduleKind::Shutdown => "poweroff",
+            ScheduleKind::Restart => "reboot",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Scheduled {
+    pub kind: ScheduleKind,
+    pub at: SystemTime,
+}
+
+impl Scheduled {
+    pub fn remaining(&self) -> Duration {
+        self.at
+            .duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO)
+    }
+}
+
+/// Formats a remaining duration as `h:mm:ss` or `m:ss`.
+pub fn format_remaining(remaining: Duration) -> String {
+    let secs = remaining.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+async fn manager() -> zbus::Result<ManagerProxy<'static>> {
+    let connection = Connection::system().await?;
+    ManagerProxy::new(&connection).await
+}
+
+/// The currently scheduled shutdown or restart, if there is one.
+pub async fn scheduled() -> zbus::Result<Option<Scheduled>> {
+    let scheduled = manager().await?.scheduled_shutdown().await?;
+    let usec = scheduled.time();
+    let kind = match scheduled.id() {
+        "poweroff" => ScheduleKind::Shutdown,
+        "reboot" => ScheduleKind::Restart,
+        _ => return Ok(None),
+    };
+    Ok((usec != 0).then(|| Scheduled {
+        kind,
+        at: UNIX_EPOCH + Duration::from_micros(usec),
+    }))
+}
+
+pub async fn schedule(kind: ScheduleKind, delay: Duration) -> zbus::Result<Option<Scheduled>> {
+    let at = SystemTime::now() + delay;
+    let usec = at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_micros() as u64;
+    // `ManagerProxy::schedule_shutdown` sends its arguments as one struct,
+    // which logind doesn't accept, so the method is called directly
+    manager()
+        .await?
+        .inner()
+        .call_method("ScheduleShutdown", &(kind.logind_type(), usec))
+        .await?;
+    scheduled().await
+}
+
+pub async fn cancel() -> zbus::Result<Option<Scheduled>> {
+    manager().await?.cancel_scheduled_shutdown().await?;
+    scheduled().await
+}
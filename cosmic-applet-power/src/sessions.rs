@@ -0,0 +1,227 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Graphical sessions of other users, from logind and AccountsService, and
+//! the greeter starting new ones.
+
+use std::path::PathBuf;
+
+use logind_zbus::{
+    manager::{ManagerProxy, SessionInfo},
+    session::{SessionClass, SessionProxy, SessionType},
+};
+use zbus::{proxy, zvariant::OwnedObjectPath, Connection};
+
+#[proxy(
+    interface = "org.freedesktop.Accounts",
+    default_service = "org.freedesktop.Accounts",
+    default_path = "/org/freedesktop/Accounts"
+)]
+trait Accounts {
+    /// FindUserById method
+    fn find_user_by_id(&self, id: i64) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.Accounts.User",
+    default_service = "org.freedesktop.Accounts"
+)]
+trait User {
+    /// IconFile property
+    #[zbus(property)]
+    fn icon_file(&self) -> zbus::Result<String>;
+
+    /// RealName property
+    #[zbus(property)]
+    fn real_name(&self) -> zbus::Result<String>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.DisplayManager.Seat",
+    default_service = "org.freedesktop.DisplayManager"
+)]
+trait DisplayManagerSeat {
+    /// SwitchToGreeter method
+    fn switch_to_greeter(&self) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.gnome.DisplayManager.LocalDisplayFactory",
+    default_service = "org.gnome.DisplayManager",
+    default_path = "/org/gnome/DisplayManager/LocalDisplayFactory"
+)]
+trait LocalDisplayFactory {
+    /// CreateTransientDisplay method
+    fn create_transient_display(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+/// Where the login screen is shown from, to start a session for another user.
+#[derive(Debug, Clone)]
+pub enum Greeter {
+    /// A greeter's session on this seat, like that of cosmic-greeter, which is
+    /// switched to.
+    Session(String),
+    /// The path of this seat in LightDM, which starts a greeter when asked.
+    DisplayManagerSeat(String),
+    /// GDM, which starts a greeter on a new display when asked.
+    Gdm,
+}
+
+/// Sessions on the system, other than the one the applet is running in.
+#[derive(Debug, Clone, Default)]
+pub struct Sessions {
+    /// Graphical user sessions.
+    pub users: Vec<UserSession>,
+    /// The greeter to switch to, if the display manager has one. Without one
+    /// there's no switching to another user.
+    pub greeter: Option<Greeter>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UserSession {
+    pub id: String,
+    pub user_name: String,
+    pub real_name: String,
+    pub icon: Option<PathBuf>,
+}
+
+impl UserSession {
+    pub fn display_name(&self) -> &str {
+        if self.real_name.is_empty() {
+            &self.user_name
+        } else {
+            &self.real_name
+        }
+    }
+}
+
+async fn user_info(connection: &Connection, uid: u32) -> zbus::Result<(String, Option<PathBuf>)> {
+    let accounts = AccountsProxy::new(connection).await?;
+    let path = accounts.find_user_by_id(uid.into()).await?;
+    let user = UserProxy::builder(connection).path(path)?.build().await?;
+    let icon = user
+        .icon_file()
+        .await
+        .ok()
+        .map(PathBuf::from)
+        .filter(|path| path.is_file());
+    Ok((user.real_name().await?, icon))
+}
+
+/// The class and type of a session, or `None` if it ended or can't be read.
+async fn class_and_type(
+    connection: &Connection,
+    info: &SessionInfo,
+) -> Option<(SessionClass, SessionType)> {
+    let session = SessionProxy::builder(connection)
+        .path(info.path().clone())
+        .ok()?
+        .build()
+        .await
+        .ok()?;
+    Some((session.class().await.ok()?, session.type_().await.ok()?))
+}
+
+pub async fn sessions() -> zbus::Result<Sessions> {
+    let connection = Connection::system().await?;
+    let manager = ManagerProxy::new(&connection).await?;
+    let our_session = std::env::var("XDG_SESSION_ID").ok();
+    let our_seat = std::env::var("XDG_SEAT").ok();
+
+    let mut sessions = Sessions::default();
+    for info in manager.list_sessions().await? {
+        if our_session.as_deref() == Some(info.sid()) {
+            continue;
+        }
+        // Sessions ending while they're listed are skipped
+        match class_and_type(&connection, &info).await {
+            Some((SessionClass::User, SessionType::Wayland | SessionType::X11)) => {
+                let (real_name, icon) = user_info(&connection, info.uid())
+                    .await
+                    .unwrap_or_else(|_| (String::new(), None));
+                sessions.users.push(UserSession {
+                    id: info.sid().to_string(),
+                    user_name: info.user().to_string(),
+                    real_name,
+                    icon,
+                });
+            }
+            Some((SessionClass::Greeter, _))
+                if our_seat.as_deref().map_or(true, |seat| seat == info.seat()) =>
+            {
+                sessions.greeter = Some(Greeter::Session(info.sid().to_string()));
+            }
+            _ => {}
+        }
+    }
+    // LightDM and GDM start a greeter when asked, rather than keeping one
+    // running
+    if sessions.greeter.is_none() {
+        sessions.greeter = display_manager_greeter(&connection)
+            .await
+            .unwrap_or_default();
+    }
+    Ok(sessions)
+}
+
+/// The greeter of a display manager that starts one when asked, if it's
+/// running.
+async fn display_manager_greeter(connection: &Connection) -> zbus::Result<Option<Greeter>> {
+    let dbus = zbus::fdo::DBusProxy::new(connection).await?;
+    if let Ok(path) = std::env::var("XDG_SEAT_PATH") {
+        if dbus
+            .name_has_owner("org.freedesktop.DisplayManager".try_into()?)
+            .await?
+        {
+            return Ok(Some(Greeter::DisplayManagerSeat(path)));
+        }
+    }
+    if dbus
+        .name_has_owner("org.gnome.DisplayManager".try_into()?)
+        .await?
+    {
+        return Ok(Some(Greeter::Gdm));
+    }
+    Ok(None)
+}
+
+pub async fn activate(id: String) -> zbus::Result<()> {
+    let connection = Connection::system().await?;
+    ManagerProxy::new(&connection)
+        .await?
+        .activate_session(&id)
+        .await
+}
+
+/// Ends another session, which asks for authentication through polkit.
+pub async fn terminate(id: String) -> zbus::Result<()> {
+    let connection = Connection::system().await?;
+    ManagerProxy::new(&connection)
+        .await?
+        .terminate_session(&id)
+        .await
+}
+
+/// Shows the login screen, to start a session for another user.
+pub async fn switch_to_greeter(greeter: Greeter) -> zbus::Result<()> {
+    match greeter {
+        Greeter::Session(id) => activate(id).await,
+        Greeter::DisplayManagerSeat(path) => {
+            let connection = Connection::system().await?;
+            DisplayManagerSeatProxy::builder(&connection)
+                .path(path)?
+                .build()
+                .await?
+                .switch_to_greeter()
+                .await
+        }
+        Greeter::Gdm => {
+            let connection = Connection::system().await?;
+            LocalDisplayFactoryProxy::new(&connection)
+                .await?
+                .create_transient_display()
+                .await
+                .map(|_display| ())
+        }
+    }
+}
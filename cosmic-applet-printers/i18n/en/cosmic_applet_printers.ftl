@@ -0,0 +1,14 @@
+no-jobs = No print jobs
+pending = Waiting
+paused = Paused
+printing = Printing
+printing-page = Printing page { $page }
+stopped = Stopped
+out-of-paper = Out of paper
+paper-jam = Paper jam
+out-of-toner = Out of toner
+door-open = Door open
+offline = Offline
+needs-attention = Needs attention
+printer-settings = Printer settings...
+failed = Failed: { $error }
@@ -0,0 +1,205 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Printers and print jobs of the local CUPS server, over IPP.
+
+use crate::ipp::{
+    self, Attributes, Request, TAG_BOOLEAN, TAG_INTEGER, TAG_KEYWORD, TAG_NAME, TAG_URI,
+};
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+type Result<T> = std::result::Result<T, Error>;
+
+const SERVER_URL: &str = "http://localhost:631/";
+const SERVER_URI: &str = "ipp://localhost/";
+
+const OP_CANCEL_JOB: u16 = 0x0008;
+const OP_GET_JOBS: u16 = 0x000a;
+const OP_HOLD_JOB: u16 = 0x000c;
+const OP_RELEASE_JOB: u16 = 0x000d;
+const OP_CUPS_GET_PRINTERS: u16 = 0x4002;
+
+const TAG_JOB_ATTRIBUTES: u8 = 0x02;
+const TAG_PRINTER_ATTRIBUTES: u8 = 0x04;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    Pending,
+    Held,
+    Processing,
+    Stopped,
+}
+
+impl JobState {
+    fn from_ipp(state: i32) -> Option<Self> {
+        match state {
+            3 => Some(JobState::Pending),
+            4 => Some(JobState::Held),
+            5 => Some(JobState::Processing),
+            6 => Some(JobState::Stopped),
+            // Canceled, aborted or completed
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: i32,
+    pub name: String,
+    pub printer: String,
+    pub state: JobState,
+    pub pages_completed: i32,
+}
+
+/// Problems a printer reports through `printer-state-reasons`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    OutOfPaper,
+    PaperJam,
+    OutOfToner,
+    DoorOpen,
+    Offline,
+    Other,
+}
+
+impl Problem {
+    fn from_reason(reason: &str) -> Option<Self> {
+        // Reasons may be suffixed with -report, -warning or -error
+        let (reason, severity) = reason
+            .rsplit_once('-')
+            .filter(|(_, severity)| matches!(*severity, "report" | "warning" | "error"))
+            .unwrap_or((reason, "error"));
+        if severity != "error" {
+            return None;
+        }
+        match reason {
+            "none" | "paused" | "moving-to-paused" => None,
+            "media-empty" | "media-needed" => Some(Problem::OutOfPaper),
+            "media-jam" => Some(Problem::PaperJam),
+            "toner-empty" | "marker-supply-empty" => Some(Problem::OutOfToner),
+            "door-open" | "cover-open" => Some(Problem::DoorOpen),
+            "offline" | "connecting-to-device" => Some(Problem::Offline),
+            _ => Some(Problem::Other),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Printer {
+    pub name: String,
+    pub problem: Option<Problem>,
+    pub message: String,
+}
+
+async fn send(request: Request) -> Result<Vec<(u8, Attributes)>> {
+    let body = reqwest::Client::new()
+        .post(SERVER_URL)
+        .header(reqwest::header::CONTENT_TYPE, "application/ipp")
+        .body(request.finish())
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    Ok(ipp::parse_response(&body)?)
+}
+
+fn int(attributes: &Attributes, name: &str) -> Option<i32> {
+    attributes.get(name)?.first()?.as_i32()
+}
+
+fn string(attributes: &Attributes, name: &str) -> Option<String> {
+    Some(attributes.get(name)?.first()?.as_str()?.to_string())
+}
+
+fn user_name() -> String {
+    std::env::var("USER").unwrap_or_default()
+}
+
+/// Jobs that haven't completed, on every printer.
+pub async fn jobs() -> Result<Vec<Job>> {
+    let request = Request::new(OP_GET_JOBS)
+        .attribute(TAG_URI, "printer-uri", SERVER_URI.as_bytes())
+        .attribute(TAG_NAME, "requesting-user-name", user_name().as_bytes())
+        .attribute(TAG_KEYWORD, "which-jobs", b"not-completed")
+        .attribute(TAG_BOOLEAN, "my-jobs", &[1])
+        .attributes(
+            TAG_KEYWORD,
+            "requested-attributes",
+            &[
+                "job-id",
+                "job-name",
+                "job-state",
+                "job-printer-uri",
+                "job-media-sheets-completed",
+            ],
+        );
+    Ok(send(request)
+        .await?
+        .into_iter()
+        .filter(|(tag, _)| *tag == TAG_JOB_ATTRIBUTES)
+        .filter_map(|(_, attributes)| {
+            Some(Job {
+                id: int(&attributes, "job-id")?,
+                name: string(&attributes, "job-name").unwrap_or_default(),
+                printer: string(&attributes, "job-printer-uri")
+                    .and_then(|uri| uri.rsplit('/').next().map(str::to_string))
+                    .unwrap_or_default(),
+                state: JobState::from_ipp(int(&attributes, "job-state")?)?,
+                pages_completed: int(&attributes, "job-media-sheets-completed").unwrap_or(0),
+            })
+        })
+        .collect())
+}
+
+pub async fn printers() -> Result<Vec<Printer>> {
+    let request = Request::new(OP_CUPS_GET_PRINTERS).attributes(
+        TAG_KEYWORD,
+        "requested-attributes",
+        &[
+            "printer-name",
+            "printer-state-reasons",
+            "printer-state-message",
+        ],
+    );
+    Ok(send(request)
+        .await?
+        .into_iter()
+        .filter(|(tag, _)| *tag == TAG_PRINTER_ATTRIBUTES)
+        .filter_map(|(_, attributes)| {
+            let reasons = attributes
+                .get("printer-state-reasons")
+                .map(|reasons| {
+                    reasons
+                        .iter()
+                        .filter_map(|r| r.as_str())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            Some(Printer {
+                name: string(&attributes, "printer-name")?,
+                problem: reasons.iter().find_map(|r| Problem::from_reason(r)),
+                message: string(&attributes, "printer-state-message").unwrap_or_default(),
+            })
+        })
+        .collect())
+}
+
+async fn job_operation(operation: u16, id: i32) -> Result<()> {
+    let request = Request::new(operation)
+        .attribute(TAG_URI, "printer-uri", SERVER_URI.as_bytes())
+        .attribute(TAG_INTEGER, "job-id", &id.to_be_bytes())
+        .attribute(TAG_NAME, "requesting-user-name", user_name().as_bytes());
+    send(request).await?;
+    Ok(())
+}
+
+pub async fn cancel(id: i32) -> Result<()> {
+    job_operation(OP_CANCEL_JOB, id).await
+}
+
+/// Holds a job, or releases a held job.
+pub async fn set_held(id: i32, held: bool) -> Result<()> {
+    job_operation(if held { OP_HOLD_JOB } else { OP_RELEASE_JOB }, id).await
+}
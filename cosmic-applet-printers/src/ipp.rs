@@ -0,0 +1,311 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Just enough of the IPP wire format to talk to the local CUPS server.
+
+use std::collections::HashMap;
+
+const VERSION: [u8; 2] = [2, 0];
+
+const TAG_OPERATION_ATTRIBUTES: u8 = 0x01;
+const TAG_END: u8 = 0x03;
+/// Tags below this delimit attribute groups, rather than being values.
+const TAG_FIRST_VALUE: u8 = 0x10;
+
+pub const TAG_INTEGER: u8 = 0x21;
+pub const TAG_BOOLEAN: u8 = 0x22;
+const TAG_ENUM: u8 = 0x23;
+pub const TAG_NAME: u8 = 0x42;
+pub const TAG_KEYWORD: u8 = 0x44;
+pub const TAG_URI: u8 = 0x45;
+const TAG_CHARSET: u8 = 0x47;
+const TAG_NATURAL_LANGUAGE: u8 = 0x48;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Integer(i32),
+    Boolean(bool),
+    String(String),
+    /// Dates, resolutions, collections and other values we have no use for.
+    Other,
+}
+
+impl Value {
+    pub fn as_i32(&self) -> Option<i32> {
+        match self {
+            Value::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// A group of attributes, such as one job or printer.
+pub type Attributes = HashMap<String, Vec<Value>>;
+
+pub struct Request {
+    buf: Vec<u8>,
+}
+
+impl Request {
+    pub fn new(operation: u16) -> Self {
+        let mut buf = VERSION.to_vec();
+        buf.extend(operation.to_be_bytes());
+        buf.extend(1u32.to_be_bytes());
+        buf.push(TAG_OPERATION_ATTRIBUTES);
+        Self { buf }
+            .attribute(TAG_CHARSET, "attributes-charset", b"utf-8")
+            .attribute(TAG_NATURAL_LANGUAGE, "attributes-natural-language", b"en")
+    }
+
+    pub fn attribute(mut self, tag: u8, name: &str, value: &[u8]) -> Self {
+        self.buf.push(tag);
+        self.buf.extend((name.len() as u16).to_be_bytes());
+        self.buf.extend(name.as_bytes());
+        self.buf.extend((value.len() as u16).to_be_bytes());
+        self.buf.extend(value);
+        self
+    }
+
+    /// An attribute with several values, such as `requested-attributes`.
+    pub fn attributes(mut self, tag: u8, name: &str, values: &[&str]) -> Self {
+        for (i, value) in values.iter().enumerate() {
+            self = self.attribute(tag, if i == 0 { name } else { "" }, value.as_bytes());
+        }
+        self
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        self.buf.push(TAG_END);
+        self.buf
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Truncated,
+    /// An IPP status code other than success.
+    Status(u16),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Truncated => write!(f, "truncated IPP response"),
+            Error::Status(status) => write!(f, "IPP status {:#06x}", status),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.buf.len() < len {
+            return Err(Error::Truncated);
+        }
+        let (taken, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(taken)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn sized(&mut self) -> Result<&'a [u8], Error> {
+        let len = self.u16()?;
+        self.take(len.into())
+    }
+}
+
+fn decode_value(tag: u8, bytes: &[u8]) -> Value {
+    match tag {
+        TAG_INTEGER | TAG_ENUM if bytes.len() == 4 => {
+            Value::Integer(i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        }
+        TAG_BOOLEAN if bytes.len() == 1 => Value::Boolean(bytes[0] != 0),
+        // Text, names, keywords, URIs and other string types
+        0x41..=0x49 => Value::String(String::from_utf8_lossy(bytes).into_owned()),
+        _ => Value::Other,
+    }
+}
+
+/// Parses a response into its attribute groups, other than the operation
+/// attributes.
+pub fn parse_response(buf: &[u8]) -> Result<Vec<(u8, Attributes)>, Error> {
+    let mut reader = Reader { buf };
+    reader.take(2)?;
+    let status = reader.u16()?;
+    reader.take(4)?;
+    // Successful, possibly with some attributes ignored
+    if status >= 0x0100 {
+        return Err(Error::Status(status));
+    }
+
+    let mut groups: Vec<(u8, Attributes)> = Vec::new();
+    let mut last_name = String::new();
+    loop {
+        let tag = reader.u8()?;
+        if tag == TAG_END {
+            break;
+        }
+        if tag < TAG_FIRST_VALUE {
+            groups.push((tag, Attributes::new()));
+            continue;
+        }
+        let name = reader.sized()?;
+        let value = decode_value(tag, reader.sized()?);
+        // An empty name is another value of the previous attribute
+        if !name.is_empty() {
+            last_name = String::from_utf8_lossy(name).into_owned();
+        }
+        if let Some((_, attributes)) = groups.last_mut() {
+            attributes.entry(last_name.clone()).or_default().push(value);
+        }
+    }
+
+    Ok(groups
+        .into_iter()
+        .filter(|(tag, _)| *tag != TAG_OPERATION_ATTRIBUTES)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TAG_PRINTER_ATTRIBUTES: u8 = 0x04;
+    const TAG_JOB_ATTRIBUTES: u8 = 0x02;
+
+    /// A response with `status`, followed by `body` and the end tag.
+    fn response(status: u16, body: &[u8]) -> Vec<u8> {
+        let mut buf = VERSION.to_vec();
+        buf.extend(status.to_be_bytes());
+        buf.extend(1u32.to_be_bytes());
+        buf.extend(body);
+        buf.push(TAG_END);
+        buf
+    }
+
+    fn attribute(tag: u8, name: &str, value: &[u8]) -> Vec<u8> {
+        let mut buf = vec![tag];
+        buf.extend((name.len() as u16).to_be_bytes());
+        buf.extend(name.as_bytes());
+        buf.extend((value.len() as u16).to_be_bytes());
+        buf.extend(value);
+        buf
+    }
+
+    #[test]
+    fn groups_and_values() {
+        let body = [
+            vec![TAG_OPERATION_ATTRIBUTES],
+            attribute(TAG_CHARSET, "attributes-charset", b"utf-8"),
+            vec![TAG_PRINTER_ATTRIBUTES],
+            attribute(TAG_NAME, "printer-name", b"Office"),
+            attribute(TAG_ENUM, "printer-state", &3i32.to_be_bytes()),
+            attribute(TAG_BOOLEAN, "printer-is-shared", &[1]),
+            attribute(TAG_KEYWORD, "printer-state-reasons", b"toner-low"),
+            attribute(TAG_KEYWORD, "", b"media-empty"),
+            // A dateTime
+            attribute(0x31, "printer-current-time", &[0; 11]),
+            vec![TAG_PRINTER_ATTRIBUTES],
+            attribute(TAG_NAME, "printer-name", b"Home"),
+        ]
+        .concat();
+        let groups = parse_response(&response(0, &body)).unwrap();
+
+        assert_eq!(groups.len(), 2);
+        let (tag, office) = &groups[0];
+        assert_eq!(*tag, TAG_PRINTER_ATTRIBUTES);
+        assert_eq!(
+            office["printer-name"],
+            [Value::String("Office".to_string())]
+        );
+        assert_eq!(office["printer-state"][0].as_i32(), Some(3));
+        assert_eq!(office["printer-is-shared"], [Value::Boolean(true)]);
+        let reasons = office["printer-state-reasons"]
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>();
+        assert_eq!(reasons, ["toner-low", "media-empty"]);
+        assert_eq!(office["printer-current-time"], [Value::Other]);
+        assert_eq!(groups[1].1["printer-name"][0].as_str(), Some("Home"));
+    }
+
+    #[test]
+    fn values_of_the_wrong_size() {
+        assert_eq!(decode_value(TAG_INTEGER, &[0, 1]), Value::Other);
+        assert_eq!(decode_value(TAG_BOOLEAN, &[]), Value::Other);
+        assert_eq!(
+            decode_value(TAG_INTEGER, &(-1i32).to_be_bytes()),
+            Value::Integer(-1)
+        );
+    }
+
+    #[test]
+    fn successful_with_ignored_attributes() {
+        let body = [
+            vec![TAG_JOB_ATTRIBUTES],
+            attribute(TAG_INTEGER, "job-id", &[0, 0, 0, 7]),
+        ]
+        .concat();
+        let groups = parse_response(&response(0x0001, &body)).unwrap();
+        assert_eq!(groups[0].1["job-id"], [Value::Integer(7)]);
+    }
+
+    #[test]
+    fn error_status() {
+        // client-error-not-found
+        let err = parse_response(&response(0x0406, &[])).unwrap_err();
+        assert!(matches!(err, Error::Status(0x0406)));
+    }
+
+    #[test]
+    fn truncated_responses() {
+        let body = [
+            vec![TAG_PRINTER_ATTRIBUTES],
+            attribute(TAG_NAME, "printer-name", b"Office"),
+        ]
+        .concat();
+        let full = response(0, &body);
+        // Without the end tag, or cut within a value
+        for len in [full.len() - 1, full.len() - 3, 5] {
+            let err = parse_response(&full[..len]).unwrap_err();
+            assert!(matches!(err, Error::Truncated), "cut at {len}");
+        }
+    }
+
+    #[test]
+    fn requests() {
+        let request = Request::new(0x000b)
+            .attributes(TAG_KEYWORD, "requested-attributes", &["a", "b"])
+            .finish();
+        let expected = [
+            vec![2, 0, 0x00, 0x0b, 0, 0, 0, 1, TAG_OPERATION_ATTRIBUTES],
+            attribute(TAG_CHARSET, "attributes-charset", b"utf-8"),
+            attribute(TAG_NATURAL_LANGUAGE, "attributes-natural-language", b"en"),
+            attribute(TAG_KEYWORD, "requested-attributes", b"a"),
+            attribute(TAG_KEYWORD, "", b"b"),
+            vec![TAG_END],
+        ]
+        .concat();
+        assert_eq!(request, expected);
+    }
+}
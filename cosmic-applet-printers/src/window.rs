@@ -0,0 +1,255 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::{collections::HashSet, time::Duration};
+
+use crate::{
+    cups::{self, Job, JobState, Printer, Problem},
+    fl,
+};
+use cosmic::{
+    app::Core,
+    applet::{menu_button, padded_control},
+    iced::{
+        time,
+        widget::{column, row},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_widget::{Column, Row},
+    widget::{button, divider, horizontal_space, icon, text},
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent, PopupRequest};
+
+const ID: &str = "com.system76.CosmicAppletPrinters";
+const ICON: &str = "com.system76.CosmicAppletPrinters-symbolic";
+const ERROR_ICON: &str = "printer-error-symbolic";
+/// CUPS' web interface, for when no printer settings app is installed.
+const CUPS_PRINTERS_URL: &str = "http://localhost:631/printers/";
+
+#[derive(Default)]
+pub struct Window {
+    jobs: Vec<Job>,
+    printers: Vec<Printer>,
+    /// Jobs with a hold, release or cancel request in flight.
+    pending: HashSet<i32>,
+    error: Option<String>,
+    /// Keeps the button around while its popup is open.
+    popup_open: bool,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    Refresh,
+    Status(Result<(Vec<Job>, Vec<Printer>), String>),
+    SetHeld(i32, bool),
+    Cancel(i32),
+    JobChanged(i32, Result<(), String>),
+    OpenSettings,
+}
+
+fn problem_text(problem: Problem) -> String {
+    match problem {
+        Problem::OutOfPaper => fl!("out-of-paper"),
+        Problem::PaperJam => fl!("paper-jam"),
+        Problem::OutOfToner => fl!("out-of-toner"),
+        Problem::DoorOpen => fl!("door-open"),
+        Problem::Offline => fl!("offline"),
+        Problem::Other => fl!("needs-attention"),
+    }
+}
+
+impl Window {
+    /// Whether the applet has anything to show.
+    fn is_active(&self) -> bool {
+        !self.jobs.is_empty() || self.printers.iter().any(|p| p.problem.is_some())
+    }
+
+    fn refresh() -> Command<Message> {
+        Command::perform(
+            async { Ok::<_, cups::Error>((cups::jobs().await?, cups::printers().await?)) },
+            |res| Message::Status(res.map_err(|err| err.to_string())),
+        )
+    }
+
+    fn job_view<'a>(&self, job: &'a Job) -> Element<'a, Message> {
+        let status = match job.state {
+            JobState::Pending => fl!("pending"),
+            JobState::Held => fl!("paused"),
+            JobState::Processing if job.pages_completed > 0 => {
+                fl!("printing-page", page = job.pages_completed + 1)
+            }
+            JobState::Processing => fl!("printing"),
+            JobState::Stopped => fl!("stopped"),
+        };
+        let pending = self.pending.contains(&job.id);
+        let held = job.state == JobState::Held;
+
+        padded_control(
+            row![
+                column![
+                    text(&job.name).size(14),
+                    text(format!("{} · {}", job.printer, status)).size(10),
+                ],
+                horizontal_space(Length::Fill),
+                button::icon(
+                    icon::from_name(if held {
+                        "media-playback-start-symbolic"
+                    } else {
+                        "media-playback-pause-symbolic"
+                    })
+                    .size(16)
+                )
+                .on_press_maybe((!pending).then_some(Message::SetHeld(job.id, !held))),
+                button::icon(icon::from_name("window-close-symbolic").size(16))
+                    .on_press_maybe((!pending).then_some(Message::Cancel(job.id))),
+            ]
+            .spacing(4)
+            .align_items(Alignment::Center),
+        )
+        .into()
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        (Self::default(), Self::refresh())
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        self.popup_open = event == PanelEvent::PopupOpened;
+        if self.popup_open {
+            self.error = None;
+        }
+        Command::none()
+    }
+
+    fn panel_subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        // Poll faster while jobs are printing, to follow their progress
+        let interval = if self.is_active() || self.popup_open {
+            Duration::from_secs(2)
+        } else {
+            Duration::from_secs(10)
+        };
+        time::every(interval).map(|_| Message::Refresh)
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::Refresh => {
+                return Self::refresh();
+            }
+            Message::Status(res) => match res {
+                Ok((jobs, printers)) => {
+                    self.jobs = jobs;
+                    self.printers = printers;
+                }
+                Err(err) => {
+                    tracing::debug!("Failed to get CUPS status: {}", err);
+                    self.jobs.clear();
+                    self.printers.clear();
+                }
+            },
+            Message::SetHeld(id, held) => {
+                self.pending.insert(id);
+                return Command::perform(cups::set_held(id, held), move |res| {
+                    Message::JobChanged(id, res.map_err(|err| err.to_string()))
+                });
+            }
+            Message::Cancel(id) => {
+                self.pending.insert(id);
+                return Command::perform(cups::cancel(id), move |res| {
+                    Message::JobChanged(id, res.map_err(|err| err.to_string()))
+                });
+            }
+            Message::JobChanged(id, res) => {
+                self.pending.remove(&id);
+                if let Err(err) = res {
+                    tracing::error!("Failed to change print job {}: {}", id, err);
+                    self.error = Some(err);
+                }
+                return Self::refresh();
+            }
+            Message::OpenSettings => {
+                if std::process::Command::new("system-config-printer")
+                    .spawn()
+                    .is_err()
+                {
+                    let mut cmd = std::process::Command::new("xdg-open");
+                    cmd.arg(CUPS_PRINTERS_URL);
+                    cosmic::process::spawn(cmd);
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        // Only shown while there's something to print or a printer problem
+        if !self.is_active() && !self.popup_open {
+            return Row::new().into();
+        }
+        let has_problem = self.printers.iter().any(|p| p.problem.is_some());
+        core.applet
+            .icon_button(if has_problem { ERROR_ICON } else { ICON })
+            .on_press(AppletMessage::TogglePopup)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = Vec::new();
+
+        for printer in &self.printers {
+            let Some(problem) = printer.problem else {
+                continue;
+            };
+            let mut details =
+                column![text(format!("{}: {}", printer.name, problem_text(problem))).size(14)];
+            if !printer.message.is_empty() {
+                details = details.push(text(&printer.message).size(10));
+            }
+            content.push(
+                padded_control(
+                    row![
+                        icon::from_name("dialog-warning-symbolic")
+                            .size(16)
+                            .symbolic(true),
+                        details,
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+        }
+        if !content.is_empty() {
+            content.push(padded_control(divider::horizontal::default()).into());
+        }
+
+        if self.jobs.is_empty() {
+            content.push(padded_control(text(fl!("no-jobs")).size(14)).into());
+        }
+        for job in &self.jobs {
+            content.push(self.job_view(job));
+        }
+        if let Some(err) = self.error.as_ref() {
+            content.push(padded_control(text(fl!("failed", error = err.as_str())).size(10)).into());
+        }
+
+        content.push(padded_control(divider::horizontal::default()).into());
+        content.push(
+            menu_button(text(fl!("printer-settings")).size(14))
+                .on_press(Message::OpenSettings)
+                .into(),
+        );
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+
+    fn popup_request(&self, message: &Message) -> Option<PopupRequest> {
+        matches!(message, Message::OpenSettings).then_some(PopupRequest::Close)
+    }
+}
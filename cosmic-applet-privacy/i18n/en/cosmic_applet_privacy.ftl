@@ -0,0 +1,5 @@
+microphone = Microphone in use
+mute-microphone = Mute microphone
+camera = Camera in use
+screen-sharing = Screen being shared
+not-in-use = Microphone, camera and screen aren't in use
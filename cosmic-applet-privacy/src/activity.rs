@@ -0,0 +1,187 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Which applications use the camera or screen, from PipeWire's nodes and
+//! from processes holding video devices open. Microphone use comes from the
+//! PulseAudio client's recording streams instead.
+//!
+//! The nodes are followed with a long-running `pw-dump --monitor`, while the
+//! video devices are only scanned when asked to, since that reads every
+//! process's open files.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Debug,
+    fs,
+    hash::Hash,
+    io,
+    process::Stdio,
+};
+
+use cosmic::{
+    iced::{self, subscription},
+    iced_futures::futures::{self, SinkExt},
+};
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Activity {
+    pub camera: BTreeSet<String>,
+    /// Screen casts, by the application that asked for them when known.
+    pub screen: BTreeSet<String>,
+}
+
+#[derive(Deserialize)]
+struct Object {
+    id: u32,
+    #[serde(rename = "type", default)]
+    type_: String,
+    #[serde(default)]
+    info: Option<Info>,
+}
+
+#[derive(Deserialize)]
+struct Info {
+    #[serde(default)]
+    state: Option<String>,
+    #[serde(default)]
+    props: HashMap<String, serde_json::Value>,
+}
+
+impl Info {
+    fn prop(&self, key: &str) -> Option<&str> {
+        self.props.get(key).and_then(|value| value.as_str())
+    }
+
+    fn app_name(&self) -> Option<String> {
+        self.prop("application.name")
+            .or_else(|| self.prop("application.process.binary"))
+            .or_else(|| self.prop("node.name"))
+            .map(str::to_string)
+    }
+}
+
+impl Activity {
+    fn from_nodes(nodes: &HashMap<u32, Info>) -> Self {
+        let mut activity = Self::default();
+        for info in nodes
+            .values()
+            .filter(|info| info.state.as_deref() == Some("running"))
+        {
+            let Some(name) = info.app_name() else {
+                continue;
+            };
+            match info.prop("media.class") {
+                Some("Stream/Input/Video") => {
+                    activity.camera.insert(name);
+                }
+                // Screen casts are video sources without a camera behind them
+                Some("Video/Source") if info.prop("device.api").is_none() => {
+                    activity.screen.insert(name);
+                }
+                _ => {}
+            }
+        }
+        activity
+    }
+}
+
+/// The camera and screen use in PipeWire's graph, sent again whenever one of
+/// its nodes changes.
+pub fn pipewire<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+) -> iced::Subscription<Activity> {
+    subscription::channel(id, 20, move |mut output| async move {
+        if let Err(err) = monitor(&mut output).await {
+            tracing::error!("Failed to watch PipeWire nodes: {}", err);
+        }
+        // Nothing is known to be in use any more
+        _ = output.send(Activity::default()).await;
+        futures::future::pending().await
+    })
+}
+
+async fn monitor(output: &mut futures::channel::mpsc::Sender<Activity>) -> io::Result<()> {
+    // Prints every object once, then each change as it happens
+    let mut child = tokio::process::Command::new("pw-dump")
+        .args(["--monitor", "--no-colors"])
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| io::Error::other("no stdout"))?;
+    let mut lines = BufReader::new(stdout).lines();
+
+    let mut nodes = HashMap::new();
+    let mut batch = String::new();
+    while let Some(line) = lines.next_line().await? {
+        batch.push_str(&line);
+        batch.push('\n');
+        // Each batch of changes is an array, closed at the start of a line
+        if line != "]" {
+            continue;
+        }
+        let objects = serde_json::from_str::<Vec<Object>>(&batch);
+        batch.clear();
+        let objects = match objects {
+            Ok(objects) => objects,
+            Err(err) => {
+                tracing::debug!("Failed to parse PipeWire objects: {}", err);
+                continue;
+            }
+        };
+        let mut changed = false;
+        for object in objects {
+            changed |= match object.info {
+                Some(info) if object.type_ == "PipeWire:Interface:Node" => {
+                    nodes.insert(object.id, info);
+                    true
+                }
+                // Removed objects have no info
+                _ => nodes.remove(&object.id).is_some(),
+            };
+        }
+        if changed {
+            _ = output.send(Activity::from_nodes(&nodes)).await;
+        }
+    }
+    Ok(())
+}
+
+fn scan_video_devices() -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    let Ok(processes) = fs::read_dir("/proc") else {
+        return names;
+    };
+    for process in processes.flatten() {
+        let path = process.path();
+        let Ok(fds) = fs::read_dir(path.join("fd")) else {
+            continue;
+        };
+        let uses_camera = fds.flatten().any(|fd| {
+            fs::read_link(fd.path()).map_or(false, |target| {
+                target.to_string_lossy().starts_with("/dev/video")
+            })
+        });
+        if uses_camera {
+            if let Ok(comm) = fs::read_to_string(path.join("comm")) {
+                names.insert(comm.trim().to_string());
+            }
+        }
+    }
+    // PipeWire's camera support holds the devices open itself
+    names.remove("pipewire");
+    names.remove("wireplumber");
+    names
+}
+
+/// Processes with a `/dev/video*` device open, which also finds cameras used
+/// without PipeWire.
+pub async fn video_devices() -> BTreeSet<String> {
+    tokio::task::spawn_blocking(scan_video_devices)
+        .await
+        .unwrap_or_default()
+}
@@ -0,0 +1,136 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Applications sharing the screen through the desktop portal.
+//!
+//! Sessions are started and closed with calls to the portal, which are only
+//! seen by other clients when monitoring the session bus. A session is kept
+//! from the `ScreenCast.Start` call until it's closed or its caller leaves the
+//! bus.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Debug,
+    fs,
+    hash::Hash,
+};
+
+use cosmic::{
+    iced::{self, subscription},
+    iced_futures::futures::{self, SinkExt, StreamExt},
+};
+use zbus::{
+    fdo::{DBusProxy, MonitoringProxy},
+    message::Type as MessageType,
+    names::{BusName, OwnedUniqueName, UniqueName},
+    zvariant::{OwnedObjectPath, OwnedValue},
+    Connection, MatchRule, MessageStream,
+};
+
+const SCREEN_CAST: &str = "org.freedesktop.portal.ScreenCast";
+const SESSION: &str = "org.freedesktop.portal.Session";
+
+/// The applications with a screen cast session open, sent again whenever one
+/// is started or closed.
+pub fn screen_casts<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+) -> iced::Subscription<BTreeSet<String>> {
+    subscription::channel(id, 20, move |mut output| async move {
+        if let Err(err) = watch(&mut output).await {
+            tracing::error!("Failed to watch screen cast sessions: {}", err);
+        }
+        futures::future::pending().await
+    })
+}
+
+async fn watch(output: &mut futures::channel::mpsc::Sender<BTreeSet<String>>) -> zbus::Result<()> {
+    // A monitor can't make calls, so callers are looked up on another connection
+    let conn = Connection::session().await?;
+    let dbus = DBusProxy::new(&conn).await?;
+
+    let monitor = Connection::session().await?;
+    let rules = [
+        MatchRule::builder()
+            .msg_type(MessageType::MethodCall)
+            .interface(SCREEN_CAST)?
+            .member("Start")?
+            .build(),
+        MatchRule::builder()
+            .msg_type(MessageType::MethodCall)
+            .interface(SESSION)?
+            .member("Close")?
+            .build(),
+        MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .interface(SESSION)?
+            .member("Closed")?
+            .build(),
+        MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .sender("org.freedesktop.DBus")?
+            .member("NameOwnerChanged")?
+            .build(),
+    ];
+    MonitoringProxy::new(&monitor)
+        .await?
+        .become_monitor(&rules, 0)
+        .await?;
+    let mut messages = MessageStream::from(&monitor);
+
+    // Session handles, with the caller that started each and its name
+    let mut sessions = HashMap::<OwnedObjectPath, (OwnedUniqueName, String)>::new();
+    while let Some(message) = messages.next().await {
+        let message = message?;
+        let header = message.header();
+        let changed = match (header.message_type(), header.member().map(|m| m.as_str())) {
+            (MessageType::MethodCall, Some("Start")) => {
+                let Some(sender) = header.sender().map(|sender| sender.to_owned()) else {
+                    continue;
+                };
+                let Ok((session, _, _)) =
+                    message
+                        .body()
+                        .deserialize::<(OwnedObjectPath, String, HashMap<String, OwnedValue>)>()
+                else {
+                    continue;
+                };
+                let name = app_name(&dbus, &sender).await;
+                sessions.insert(session, (sender.into(), name)).is_none()
+            }
+            (MessageType::MethodCall, Some("Close")) | (MessageType::Signal, Some("Closed")) => {
+                header.path().map_or(false, |path| {
+                    sessions
+                        .remove(&OwnedObjectPath::from(path.to_owned()))
+                        .is_some()
+                })
+            }
+            (MessageType::Signal, Some("NameOwnerChanged")) => {
+                let Ok((name, _, new_owner)) =
+                    message.body().deserialize::<(String, String, String)>()
+                else {
+                    continue;
+                };
+                let count = sessions.len();
+                if new_owner.is_empty() {
+                    sessions.retain(|_, (sender, _)| sender.as_str() != name);
+                }
+                sessions.len() != count
+            }
+            _ => false,
+        };
+        if changed {
+            let names = sessions.values().map(|(_, name)| name.clone()).collect();
+            _ = output.send(names).await;
+        }
+    }
+    Ok(())
+}
+
+/// The process name of a bus client, or its unique name if that's unknown.
+async fn app_name(dbus: &DBusProxy<'_>, sender: &UniqueName<'_>) -> String {
+    dbus.get_connection_unix_process_id(BusName::from(sender.clone()))
+        .await
+        .ok()
+        .and_then(|pid| fs::read_to_string(format!("/proc/{pid}/comm")).ok())
+        .map_or_else(|| sender.to_string(), |comm| comm.trim().to_string())
+}
@@ -0,0 +1,286 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::collections::BTreeSet;
+
+use crate::{
+    activity::{self, Activity},
+    fl, portal,
+};
+use cosmic::{
+    app::Core,
+    applet::{cosmic_panel_config::PanelAnchor, padded_control},
+    iced::{widget::row, Alignment, Command, Length, Subscription},
+    iced_widget::{Column, Row},
+    widget::{button, divider, icon, text, toggler},
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent};
+use cosmic_pulse_client as pulse;
+
+const ID: &str = "com.system76.CosmicAppletPrivacy";
+const MICROPHONE: &str = "audio-input-microphone-symbolic";
+const CAMERA: &str = "camera-web-symbolic";
+const SCREEN: &str = "video-display-symbolic";
+
+#[derive(Default)]
+pub struct Window {
+    popup_open: bool,
+    /// What's shown, from all of the sources below.
+    activity: Activity,
+    /// Camera and screen use in PipeWire's graph.
+    pipewire: Activity,
+    /// Processes holding a camera open.
+    video_devices: BTreeSet<String>,
+    scanning: bool,
+    /// Whether the graph changed during a scan.
+    rescan: bool,
+    /// Applications with a screen cast session open through the portal.
+    screen_casts: BTreeSet<String>,
+    /// Applications recording from a microphone.
+    microphone: BTreeSet<String>,
+    pulse: Option<pulse::Connection>,
+    audio: pulse::Model,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    PipeWire(Activity),
+    VideoDevices(BTreeSet<String>),
+    ScreenCasts(BTreeSet<String>),
+    Pulse(pulse::Event),
+    PulseChanged(pulse::Facility),
+    MuteMicrophone(bool),
+}
+
+impl Window {
+    fn request_audio(&mut self) {
+        if let Some(conn) = self.pulse.as_mut().filter(|_| self.audio.connected) {
+            conn.send(pulse::Message::GetSources);
+            conn.send(pulse::Message::GetStreams);
+            conn.send(pulse::Message::GetDefaultSource);
+        }
+    }
+
+    fn scan_video_devices(&mut self) -> Command<Message> {
+        if self.scanning {
+            self.rescan = true;
+            return Command::none();
+        }
+        self.scanning = true;
+        Command::perform(activity::video_devices(), Message::VideoDevices)
+    }
+
+    fn update_activity(&mut self) {
+        self.activity.camera = self
+            .pipewire
+            .camera
+            .union(&self.video_devices)
+            .cloned()
+            .collect();
+        // The portal's sessions name the applications behind its nodes, but
+        // only count while one of them is streaming
+        self.activity.screen = if self.pipewire.screen.is_empty() || self.screen_casts.is_empty() {
+            self.pipewire.screen.clone()
+        } else {
+            self.screen_casts.clone()
+        };
+    }
+
+    fn microphone_muted(&self) -> bool {
+        self.audio
+            .default_source
+            .as_ref()
+            .map_or(false, |source| source.mute)
+    }
+
+    fn icons(&self) -> Vec<&'static str> {
+        [
+            (MICROPHONE, &self.microphone),
+            (CAMERA, &self.activity.camera),
+            (SCREEN, &self.activity.screen),
+        ]
+        .into_iter()
+        .filter(|(_, apps)| !apps.is_empty())
+        .map(|(icon, _)| icon)
+        .collect()
+    }
+
+    fn section<'a>(
+        icon_name: &'static str,
+        label: String,
+        apps: &'a BTreeSet<String>,
+    ) -> Vec<Element<'a, AppletMessage<Message>>> {
+        let mut content = vec![padded_control(
+            row![
+                icon::from_name(icon_name).size(16).symbolic(true),
+                text(label).size(14),
+            ]
+            .spacing(8)
+            .align_items(Alignment::Center),
+        )
+        .into()];
+        for app in apps {
+            content.push(padded_control(text(app).size(12)).into());
+        }
+        content
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let mut window = Self::default();
+        let command = window.scan_video_devices();
+        (window, command)
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        self.popup_open = event == PanelEvent::PopupOpened;
+        // Cameras used without PipeWire don't change its graph
+        if self.popup_open {
+            return self.scan_video_devices();
+        }
+        Command::none()
+    }
+
+    fn subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        Subscription::batch([
+            pulse::connect().map(Message::Pulse),
+            pulse::server_events(0).map(Message::PulseChanged),
+            activity::pipewire(0).map(Message::PipeWire),
+            portal::screen_casts(0).map(Message::ScreenCasts),
+        ])
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::PipeWire(activity) => {
+                self.pipewire = activity;
+                self.update_activity();
+                return self.scan_video_devices();
+            }
+            Message::VideoDevices(names) => {
+                self.scanning = false;
+                self.video_devices = names;
+                self.update_activity();
+                if std::mem::take(&mut self.rescan) {
+                    return self.scan_video_devices();
+                }
+            }
+            Message::ScreenCasts(names) => {
+                self.screen_casts = names;
+                self.update_activity();
+            }
+            Message::Pulse(event) => {
+                self.audio.update(&event);
+                match event {
+                    pulse::Event::Init(mut conn) => {
+                        conn.send(pulse::Message::UpdateConnection);
+                        self.pulse = Some(conn);
+                    }
+                    pulse::Event::Connected => self.request_audio(),
+                    _ => {}
+                }
+                self.microphone = self.audio.recording_applications().into_iter().collect();
+            }
+            Message::PulseChanged(facility) => {
+                if matches!(
+                    facility,
+                    pulse::Facility::Source
+                        | pulse::Facility::SourceOutput
+                        | pulse::Facility::Server
+                ) {
+                    self.request_audio();
+                }
+            }
+            Message::MuteMicrophone(muted) => {
+                let name = self.audio.default_source.as_mut().and_then(|source| {
+                    source.mute = muted;
+                    source.name.clone()
+                });
+                if let (Some(conn), Some(name)) = (self.pulse.as_mut(), name) {
+                    conn.send(pulse::Message::SetSourceMuteByName(name, muted));
+                    conn.send(pulse::Message::GetDefaultSource);
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let icons = self.icons();
+        // Only shown while something is in use
+        if icons.is_empty() && !self.popup_open {
+            return Row::new().into();
+        }
+
+        let size = core.applet.suggested_size(true).0;
+        let icons = icons
+            .into_iter()
+            .map(|name| icon::from_name(name).size(size).symbolic(true).into())
+            .collect::<Vec<Element<_>>>();
+        let content: Element<_> = match core.applet.anchor {
+            PanelAnchor::Left | PanelAnchor::Right => Column::with_children(icons)
+                .spacing(2)
+                .align_items(Alignment::Center)
+                .into(),
+            PanelAnchor::Top | PanelAnchor::Bottom => Row::with_children(icons)
+                .spacing(2)
+                .align_items(Alignment::Center)
+                .into(),
+        };
+        button(content)
+            .padding(core.applet.suggested_padding(true))
+            .on_press(AppletMessage::TogglePopup)
+            .style(cosmic::theme::Button::AppletIcon)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = Vec::new();
+
+        if !self.microphone.is_empty() {
+            content.extend(Self::section(
+                MICROPHONE,
+                fl!("microphone"),
+                &self.microphone,
+            ));
+            content.push(
+                padded_control(
+                    toggler(
+                        Some(fl!("mute-microphone")),
+                        self.microphone_muted(),
+                        |muted| Message::MuteMicrophone(muted).into(),
+                    )
+                    .text_size(14)
+                    .width(Length::Fill),
+                )
+                .into(),
+            );
+        }
+        if !self.activity.camera.is_empty() {
+            if !content.is_empty() {
+                content.push(padded_control(divider::horizontal::default()).into());
+            }
+            content.extend(Self::section(CAMERA, fl!("camera"), &self.activity.camera));
+        }
+        if !self.activity.screen.is_empty() {
+            if !content.is_empty() {
+                content.push(padded_control(divider::horizontal::default()).into());
+            }
+            content.extend(Self::section(
+                SCREEN,
+                fl!("screen-sharing"),
+                &self.activity.screen,
+            ));
+        }
+        if content.is_empty() {
+            content.push(padded_control(text(fl!("not-in-use")).size(14)).into());
+        }
+
+        Column::with_children(content).padding([8, 0]).into()
+    }
+}
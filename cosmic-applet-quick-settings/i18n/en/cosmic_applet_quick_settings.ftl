@@ -0,0 +1,11 @@
+on = On
+off = Off
+not-connected = Not connected
+devices-connected = { $count } devices
+wifi = Wi-Fi
+bluetooth = Bluetooth
+do-not-disturb = Do not disturb
+night-light = Night light
+power-saver = Power saver
+balanced = Balanced
+performance = Performance
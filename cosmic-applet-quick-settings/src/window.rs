@@ -0,0 +1,563 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Tiles for the settings people change most, driven by the same backends as
+//! the network, bluetooth, audio, battery, notifications and night light
+//! applets.
+
+use crate::fl;
+use cosmic::{
+    app::Core,
+    applet::padded_control,
+    cosmic_config::{Config, CosmicConfigEntry},
+    iced::{
+        alignment::Horizontal,
+        widget::{column, row},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_futures::futures,
+    iced_widget::Column,
+    theme,
+    widget::{button, divider, icon, slider, text},
+    Element,
+};
+use cosmic_applet_battery::backend::{
+    power_profile_subscription, Power, PowerProfileRequest, PowerProfileUpdate,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent};
+use cosmic_applet_night_light::config::{self as night_light, NightLightConfig};
+use cosmic_bluez_client::{
+    bluetooth_subscription, BluerDeviceStatus, BluerEvent, BluerRequest, BluerState,
+};
+use cosmic_nm_client::{
+    active_conns::active_conns_subscription, current_networks::ActiveConnectionInfo,
+    network_manager_subscription, wireless_enabled::wireless_enabled_subscription,
+    NetworkManagerEvent, NetworkManagerRequest, NetworkManagerState,
+};
+use cosmic_notifications_config::NotificationsConfig;
+use cosmic_pulse_client::{self as pulse, percent_to_volume, volume_to_percent};
+use cosmic_settings_subscriptions::{pulse as sub_pulse, settings_daemon};
+use tokio::sync::mpsc::{Sender, UnboundedSender};
+
+const ID: &str = "com.system76.CosmicAppletQuickSettings";
+const ICON: &str = "com.system76.CosmicAppletQuickSettings-symbolic";
+
+#[derive(Default)]
+pub struct Window {
+    zbus_connection: Option<zbus::Connection>,
+
+    nm_sender: Option<futures::channel::mpsc::UnboundedSender<NetworkManagerRequest>>,
+    nm_connection: Option<zbus::Connection>,
+    nm_state: NetworkManagerState,
+
+    bluer_sender: Option<Sender<BluerRequest>>,
+    bluer_state: BluerState,
+
+    pulse: Option<pulse::Connection>,
+    output: Option<pulse::DeviceInfo>,
+
+    settings_daemon_sender: Option<UnboundedSender<settings_daemon::Request>>,
+    max_brightness: Option<i32>,
+    brightness: Option<i32>,
+
+    notifications: NotificationsConfig,
+    notifications_helper: Option<Config>,
+    night_light: NightLightConfig,
+    night_light_helper: Option<Config>,
+
+    power_sender: Option<UnboundedSender<PowerProfileRequest>>,
+    power_profile: Option<Power>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    ZbusConnection(Result<zbus::Connection, String>),
+    Network(NetworkManagerEvent),
+    Bluetooth(BluerEvent),
+    Pulse(pulse::Event),
+    PulseSub(sub_pulse::Event),
+    SettingsDaemon(settings_daemon::Event),
+    Power(PowerProfileUpdate),
+    NotificationsConfig(NotificationsConfig),
+    NightLightConfig(NightLightConfig),
+    ToggleWifi,
+    ToggleBluetooth,
+    ToggleDnd,
+    ToggleNightLight,
+    SetVolume(f64),
+    ToggleMute,
+    SetBrightness(i32),
+    SetPowerProfile(Power),
+}
+
+fn load_config<T: CosmicConfigEntry + Default>(id: &str) -> (T, Option<Config>) {
+    let helper = Config::new(id, T::VERSION).ok();
+    let config = helper
+        .as_ref()
+        .map(|helper| {
+            T::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                for err in errs {
+                    tracing::error!(?err, "Error loading config");
+                }
+                config
+            })
+        })
+        .unwrap_or_default();
+    (config, helper)
+}
+
+fn tile<'a>(
+    icon_name: &'a str,
+    title: String,
+    subtitle: String,
+    active: bool,
+    on_press: Option<Message>,
+) -> Element<'a, Message> {
+    button(
+        row![
+            icon::from_name(icon_name).size(20).symbolic(true),
+            column![text(title).size(14), text(subtitle).size(10)].width(Length::Fill),
+        ]
+        .spacing(8)
+        .align_items(Alignment::Center),
+    )
+    .padding([8, 12])
+    .width(Length::Fill)
+    .style(if active {
+        theme::Button::Suggested
+    } else {
+        theme::Button::Standard
+    })
+    .on_press_maybe(on_press)
+    .into()
+}
+
+impl Window {
+    fn wifi_subtitle(&self) -> String {
+        if !self.nm_state.wifi_enabled {
+            return fl!("off");
+        }
+        self.nm_state
+            .active_conns
+            .iter()
+            .find_map(|conn| match conn {
+                ActiveConnectionInfo::WiFi { name, .. } => Some(name.clone()),
+                _ => None,
+            })
+            .unwrap_or_else(|| fl!("not-connected"))
+    }
+
+    fn bluetooth_subtitle(&self) -> String {
+        if !self.bluer_state.bluetooth_enabled {
+            return fl!("off");
+        }
+        let mut connected = self
+            .bluer_state
+            .devices
+            .iter()
+            .filter(|device| device.status == BluerDeviceStatus::Connected);
+        match (connected.next(), connected.count()) {
+            (None, _) => fl!("not-connected"),
+            (Some(device), 0) => device.name.clone(),
+            (Some(_), others) => fl!("devices-connected", count = others + 1),
+        }
+    }
+
+    fn volume(&self) -> f64 {
+        self.output
+            .as_ref()
+            .map_or(0.0, |output| volume_to_percent(output.volume.avg()))
+    }
+
+    fn muted(&self) -> bool {
+        self.output.as_ref().is_some_and(|output| output.mute)
+    }
+
+    fn power_profile_button(&self, power: Power, label: String) -> Element<Message> {
+        button(
+            text(label)
+                .size(14)
+                .horizontal_alignment(Horizontal::Center),
+        )
+        .padding([8, 12])
+        .width(Length::Fill)
+        .style(if self.power_profile == Some(power) {
+            theme::Button::Suggested
+        } else {
+            theme::Button::Standard
+        })
+        .on_press_maybe(
+            self.power_sender
+                .is_some()
+                .then_some(Message::SetPowerProfile(power)),
+        )
+        .into()
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let (notifications, notifications_helper) =
+            load_config::<NotificationsConfig>(cosmic_notifications_config::ID);
+        let (night_light, night_light_helper) =
+            load_config::<NightLightConfig>(night_light::APP_ID);
+
+        let window = Self {
+            notifications,
+            notifications_helper,
+            night_light,
+            night_light_helper,
+            ..Default::default()
+        };
+        (
+            window,
+            Command::perform(zbus::Connection::session(), |res| {
+                Message::ZbusConnection(res.map_err(|err| err.to_string()))
+            }),
+        )
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        if event == PanelEvent::PopupOpened {
+            if let Some(conn) = self.pulse.as_mut() {
+                conn.send(pulse::Message::GetDefaultSink);
+            }
+            if let Some(tx) = &self.power_sender {
+                let _ = tx.send(PowerProfileRequest::Get);
+            }
+        }
+        Command::none()
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        let mut subscriptions = vec![
+            network_manager_subscription(0).map(Message::Network),
+            bluetooth_subscription(0).map(Message::Bluetooth),
+            pulse::connect().map(Message::Pulse),
+            sub_pulse::subscription().map(Message::PulseSub),
+            power_profile_subscription(0).map(Message::Power),
+            core.watch_config::<NotificationsConfig>(cosmic_notifications_config::ID)
+                .map(|u| Message::NotificationsConfig(u.config)),
+            core.watch_config::<NightLightConfig>(night_light::APP_ID)
+                .map(|u| Message::NightLightConfig(u.config)),
+        ];
+        if let Some(conn) = self.nm_connection.clone() {
+            subscriptions
+                .push(wireless_enabled_subscription(0, conn.clone()).map(Message::Network));
+            subscriptions.push(active_conns_subscription(0, conn).map(Message::Network));
+        }
+        if let Some(conn) = self.zbus_connection.clone() {
+            subscriptions.push(settings_daemon::subscription(conn).map(Message::SettingsDaemon));
+        }
+        Subscription::batch(subscriptions)
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::ZbusConnection(res) => match res {
+                Ok(conn) => self.zbus_connection = Some(conn),
+                Err(err) => tracing::error!("Failed to connect to session dbus: {}", err),
+            },
+            Message::Network(event) => match event {
+                NetworkManagerEvent::Init {
+                    conn,
+                    sender,
+                    state,
+                } => {
+                    self.nm_connection = Some(conn);
+                    self.nm_sender = Some(sender);
+                    self.nm_state = state;
+                }
+                NetworkManagerEvent::RequestResponse { state, .. }
+                | NetworkManagerEvent::WiFiEnabled(state)
+                | NetworkManagerEvent::WirelessAccessPoints(state)
+                | NetworkManagerEvent::ActiveConns(state) => {
+                    self.nm_state = state;
+                }
+            },
+            Message::Bluetooth(event) => match event {
+                BluerEvent::Init { sender, state } => {
+                    self.bluer_sender = Some(sender);
+                    self.bluer_state = state;
+                }
+                BluerEvent::RequestResponse { state, .. }
+                | BluerEvent::DevicesChanged { state } => {
+                    self.bluer_state = state;
+                }
+                BluerEvent::Finished => {
+                    self.bluer_sender = None;
+                }
+                BluerEvent::AgentEvent(_) => {}
+            },
+            Message::Pulse(event) => match event {
+                pulse::Event::Init(mut conn) => {
+                    conn.send(pulse::Message::UpdateConnection);
+                    self.pulse = Some(conn);
+                }
+                pulse::Event::Connected => {
+                    if let Some(conn) = self.pulse.as_mut() {
+                        conn.send(pulse::Message::GetDefaultSink);
+                    }
+                }
+                pulse::Event::MessageReceived(pulse::Message::SetDefaultSink(sink)) => {
+                    self.output = Some(sink);
+                }
+                pulse::Event::MessageReceived(_) => {}
+                pulse::Event::Disconnected => {
+                    self.output = None;
+                }
+            },
+            Message::PulseSub(event) => match event {
+                sub_pulse::Event::SinkVolume(value) => {
+                    if let Some(output) = self.output.as_mut() {
+                        output
+                            .volume
+                            .set(output.volume.len(), percent_to_volume(value as f64));
+                    }
+                }
+                sub_pulse::Event::SinkMute(value) => {
+                    if let Some(output) = self.output.as_mut() {
+                        output.mute = value;
+                    }
+                }
+                _ => {}
+            },
+            Message::SettingsDaemon(event) => match event {
+                settings_daemon::Event::Sender(tx) => {
+                    self.settings_daemon_sender = Some(tx);
+                }
+                settings_daemon::Event::MaxDisplayBrightness(max_brightness) => {
+                    self.max_brightness = Some(max_brightness);
+                }
+                settings_daemon::Event::DisplayBrightness(brightness) => {
+                    self.brightness = Some(brightness);
+                }
+            },
+            Message::Power(update) => match update {
+                PowerProfileUpdate::Init(profile, tx) => {
+                    self.power_profile = Some(profile);
+                    self.power_sender = Some(tx);
+                }
+                PowerProfileUpdate::Update { profile } => {
+                    self.power_profile = Some(profile);
+                }
+                PowerProfileUpdate::Error(err) => {
+                    tracing::debug!("Power profile backend unavailable: {}", err);
+                }
+            },
+            Message::NotificationsConfig(config) => {
+                self.notifications = config;
+            }
+            Message::NightLightConfig(config) => {
+                self.night_light = config;
+            }
+            Message::ToggleWifi => {
+                if let Some(tx) = &self.nm_sender {
+                    let _ = tx.unbounded_send(NetworkManagerRequest::SetWiFi(
+                        !self.nm_state.wifi_enabled,
+                    ));
+                }
+            }
+            Message::ToggleBluetooth => {
+                if let Some(tx) = &self.bluer_sender {
+                    let _ = tx.try_send(BluerRequest::SetBluetoothEnabled(
+                        !self.bluer_state.bluetooth_enabled,
+                    ));
+                }
+            }
+            Message::ToggleDnd => {
+                if let Some(helper) = &self.notifications_helper {
+                    if let Err(err) = self
+                        .notifications
+                        .set_do_not_disturb(helper, !self.notifications.do_not_disturb)
+                    {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+            Message::ToggleNightLight => {
+                if let Some(helper) = &self.night_light_helper {
+                    if let Err(err) = self
+                        .night_light
+                        .set_enabled(helper, !self.night_light.enabled)
+                    {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+            Message::SetVolume(volume) => {
+                if let Some(output) = self.output.as_mut() {
+                    output
+                        .volume
+                        .set(output.volume.len(), percent_to_volume(volume));
+                    if let (Some(conn), Some(name)) = (self.pulse.as_mut(), &output.name) {
+                        conn.send(pulse::Message::SetSinkVolumeByName(
+                            name.clone(),
+                            output.volume,
+                        ));
+                    }
+                }
+            }
+            Message::ToggleMute => {
+                if let Some(output) = self.output.as_mut() {
+                    output.mute = !output.mute;
+                    if let (Some(conn), Some(name)) = (self.pulse.as_mut(), &output.name) {
+                        conn.send(pulse::Message::SetSinkMuteByName(name.clone(), output.mute));
+                    }
+                }
+            }
+            Message::SetBrightness(brightness) => {
+                self.brightness = Some(brightness);
+                if let Some(tx) = &self.settings_daemon_sender {
+                    let _ = tx.send(settings_daemon::Request::SetDisplayBrightness(brightness));
+                }
+            }
+            Message::SetPowerProfile(profile) => {
+                self.power_profile = Some(profile);
+                if let Some(tx) = &self.power_sender {
+                    let _ = tx.send(PowerProfileRequest::Set(profile));
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        core.applet
+            .icon_button(ICON)
+            .on_press(AppletMessage::TogglePopup)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let tiles = column![
+            row![
+                tile(
+                    if self.nm_state.wifi_enabled {
+                        "network-wireless-symbolic"
+                    } else {
+                        "network-wireless-disabled-symbolic"
+                    },
+                    fl!("wifi"),
+                    self.wifi_subtitle(),
+                    self.nm_state.wifi_enabled,
+                    self.nm_sender.is_some().then_some(Message::ToggleWifi),
+                ),
+                tile(
+                    if self.bluer_state.bluetooth_enabled {
+                        "bluetooth-active-symbolic"
+                    } else {
+                        "bluetooth-disabled-symbolic"
+                    },
+                    fl!("bluetooth"),
+                    self.bluetooth_subtitle(),
+                    self.bluer_state.bluetooth_enabled,
+                    self.bluer_sender
+                        .is_some()
+                        .then_some(Message::ToggleBluetooth),
+                ),
+            ]
+            .spacing(8),
+            row![
+                tile(
+                    "notification-disabled-symbolic",
+                    fl!("do-not-disturb"),
+                    if self.notifications.do_not_disturb {
+                        fl!("on")
+                    } else {
+                        fl!("off")
+                    },
+                    self.notifications.do_not_disturb,
+                    self.notifications_helper
+                        .is_some()
+                        .then_some(Message::ToggleDnd),
+                ),
+                tile(
+                    "night-light-symbolic",
+                    fl!("night-light"),
+                    if self.night_light.enabled {
+                        fl!("on")
+                    } else {
+                        fl!("off")
+                    },
+                    self.night_light.enabled,
+                    self.night_light_helper
+                        .is_some()
+                        .then_some(Message::ToggleNightLight),
+                ),
+            ]
+            .spacing(8),
+        ]
+        .spacing(8);
+
+        let mut content = vec![
+            padded_control(tiles).into(),
+            padded_control(divider::horizontal::default()).into(),
+        ];
+
+        if self.output.is_some() {
+            let volume = self.volume();
+            content.push(
+                padded_control(
+                    row![
+                        button::icon(icon::from_name(if self.muted() {
+                            "audio-volume-muted-symbolic"
+                        } else {
+                            "audio-volume-high-symbolic"
+                        }))
+                        .on_press(Message::ToggleMute),
+                        slider(0.0..=100.0, volume, Message::SetVolume),
+                        text(format!("{}%", volume.round()))
+                            .size(16)
+                            .width(Length::Fixed(40.0))
+                            .horizontal_alignment(Horizontal::Right),
+                    ]
+                    .spacing(12)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+        }
+
+        if let (Some(max_brightness), Some(brightness)) = (self.max_brightness, self.brightness) {
+            let percent = brightness as f64 / max_brightness.max(1) as f64 * 100.0;
+            content.push(
+                padded_control(
+                    row![
+                        icon::from_name("display-brightness-symbolic")
+                            .size(24)
+                            .symbolic(true),
+                        slider(1..=max_brightness, brightness, Message::SetBrightness),
+                        text(format!("{:.0}%", percent))
+                            .size(16)
+                            .width(Length::Fixed(40.0))
+                            .horizontal_alignment(Horizontal::Right),
+                    ]
+                    .spacing(12)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+        }
+
+        if self.power_profile.is_some() {
+            content.push(padded_control(divider::horizontal::default()).into());
+            content.push(
+                padded_control(
+                    row![
+                        self.power_profile_button(Power::Battery, fl!("power-saver")),
+                        self.power_profile_button(Power::Balanced, fl!("balanced")),
+                        self.power_profile_button(Power::Performance, fl!("performance")),
+                    ]
+                    .spacing(8),
+                )
+                .into(),
+            );
+        }
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+}
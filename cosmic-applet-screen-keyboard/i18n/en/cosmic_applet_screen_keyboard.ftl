@@ -0,0 +1,7 @@
+screen-keyboard = Screen keyboard
+running = Click the panel icon to show or hide it
+not-running = Will be started when shown
+auto-show = Show automatically
+auto-show-description = Whenever a text field is focused on a touch screen
+keyboard-command = Keyboard command
+failed = Failed: { $error }
@@ -0,0 +1,25 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
+pub const APP_ID: &str = "com.system76.CosmicAppletScreenKeyboard";
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, CosmicConfigEntry)]
+#[version = 1]
+pub struct ScreenKeyboardConfig {
+    /// Started when the keyboard is shown and isn't running yet.
+    pub command: String,
+    /// Show the keyboard whenever a text field is focused.
+    pub auto_show: bool,
+}
+
+impl Default for ScreenKeyboardConfig {
+    fn default() -> Self {
+        Self {
+            command: "squeekboard".to_string(),
+            auto_show: false,
+        }
+    }
+}
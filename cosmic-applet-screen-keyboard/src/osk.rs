@@ -0,0 +1,127 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! On-screen keyboards implementing the `sm.puri.OSK0` interface, such as
+//! Squeekboard. The keyboard itself types through the input-method and
+//! virtual-keyboard protocols.
+
+use std::{fmt::Debug, hash::Hash, time::Duration};
+
+use cosmic::{
+    iced::{self, subscription},
+    iced_futures::futures::{self, SinkExt, StreamExt},
+};
+use zbus::{fdo::DBusProxy, proxy, Connection};
+
+const SERVICE: &str = "sm.puri.OSK0";
+/// How long to wait for a keyboard we started to appear on the bus.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[proxy(
+    interface = "sm.puri.OSK0",
+    default_service = "sm.puri.OSK0",
+    default_path = "/sm/puri/OSK0"
+)]
+trait Osk {
+    /// SetVisible method
+    fn set_visible(&self, visible: bool) -> zbus::Result<()>;
+
+    /// Visible property
+    #[zbus(property)]
+    fn visible(&self) -> zbus::Result<bool>;
+}
+
+/// Shows or hides the keyboard, starting it with `command` if it isn't
+/// running.
+pub async fn set_visible(visible: bool, command: String) -> zbus::Result<()> {
+    let conn = Connection::session().await?;
+    let dbus = DBusProxy::new(&conn).await?;
+    let name = SERVICE.try_into()?;
+    if !dbus.name_has_owner(name).await? {
+        if !visible {
+            return Ok(());
+        }
+        // Subscribe first, so the keyboard can't appear unnoticed
+        let mut owner_changed = dbus.receive_name_owner_changed().await?;
+        let mut args = command.split_whitespace();
+        let program = args
+            .next()
+            .ok_or_else(|| zbus::Error::Failure("no keyboard command".to_string()))?;
+        let mut cmd = std::process::Command::new(program);
+        cmd.args(args);
+        cosmic::process::spawn(cmd);
+
+        let started = async {
+            while let Some(signal) = owner_changed.next().await {
+                if signal.args().map_or(false, |args| {
+                    args.name().as_str() == SERVICE && args.new_owner().is_some()
+                }) {
+                    return true;
+                }
+            }
+            false
+        };
+        if !tokio::time::timeout(STARTUP_TIMEOUT, started)
+            .await
+            .unwrap_or(false)
+        {
+            return Err(zbus::Error::Failure(format!("{} didn't start", program)));
+        }
+    }
+    OskProxy::new(&conn).await?.set_visible(visible).await
+}
+
+/// Whether the keyboard is shown, or `None` if it isn't running.
+pub fn osk_subscription<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+) -> iced::Subscription<Option<bool>> {
+    subscription::channel(id, 50, move |mut output| async move {
+        if let Err(err) = run(&mut output).await {
+            tracing::error!("Failed to monitor screen keyboard: {}", err);
+        }
+        let _ = output.send(None).await;
+        futures::future::pending().await
+    })
+}
+
+async fn run(output: &mut futures::channel::mpsc::Sender<Option<bool>>) -> zbus::Result<()> {
+    let conn = Connection::session().await?;
+    let osk = OskProxy::new(&conn).await?;
+    let mut owner_changed = osk.inner().receive_owner_changed().await?;
+    let mut visible_changed = osk.receive_visible_changed().await;
+
+    loop {
+        // Fails when no keyboard owns the name
+        _ = output.send(osk.visible().await.ok()).await;
+
+        tokio::select! {
+            Some(_) = owner_changed.next() => {
+                // The property stream follows the old owner
+                visible_changed = osk.receive_visible_changed().await;
+            }
+            Some(_) = visible_changed.next() => {}
+            else => return Ok(()),
+        }
+    }
+}
+
+/// Makes the keyboard show itself when a text field is focused, through the
+/// setting Squeekboard follows.
+pub async fn set_auto_show(auto_show: bool) -> std::io::Result<()> {
+    let status = tokio::process::Command::new("gsettings")
+        .args([
+            "set",
+            "org.gnome.desktop.a11y.applications",
+            "screen-keyboard-enabled",
+            if auto_show { "true" } else { "false" },
+        ])
+        .status()
+        .await?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "gsettings exited with {status}"
+        )))
+    }
+}
@@ -0,0 +1,218 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::{
+    config::{self, ScreenKeyboardConfig},
+    fl, osk,
+};
+use cosmic::{
+    app::Core,
+    applet::padded_control,
+    cosmic_config::{Config, CosmicConfigEntry},
+    iced::{
+        widget::{column, mouse_area},
+        Command, Length, Subscription,
+    },
+    iced_widget::Column,
+    widget::{divider, text, text_input, toggler},
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent};
+
+const ICON: &str = "com.system76.CosmicAppletScreenKeyboard-symbolic";
+
+#[derive(Default)]
+pub struct Window {
+    config: ScreenKeyboardConfig,
+    config_helper: Option<Config>,
+    /// `None` while no keyboard is running.
+    visible: Option<bool>,
+    /// Edited separately, so the keyboard isn't started on every keystroke.
+    command: String,
+    /// Whether the popup, and with it the command field, is open.
+    editing: bool,
+    error: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    ConfigChanged(ScreenKeyboardConfig),
+    Visible(Option<bool>),
+    Toggle,
+    Toggled(Result<(), String>),
+    AutoShow(bool),
+    AutoShowSet(Result<(), String>),
+    Command(String),
+    SaveCommand,
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = config::APP_ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::APP_ID, ScreenKeyboardConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                ScreenKeyboardConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let window = Self {
+            command: config.command.clone(),
+            config,
+            config_helper,
+            ..Default::default()
+        };
+        (window, Command::none())
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        match event {
+            PanelEvent::PopupOpened => {
+                self.editing = true;
+                self.command = self.config.command.clone();
+            }
+            PanelEvent::PopupClosed => self.editing = false,
+        }
+        Command::none()
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        Subscription::batch(vec![
+            osk::osk_subscription(0).map(Message::Visible),
+            core.watch_config::<ScreenKeyboardConfig>(config::APP_ID)
+                .map(|u| Message::ConfigChanged(u.config)),
+        ])
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::ConfigChanged(config) => {
+                if !self.editing {
+                    self.command = config.command.clone();
+                }
+                self.config = config;
+            }
+            Message::Visible(visible) => {
+                self.visible = visible;
+            }
+            Message::Toggle => {
+                self.error = None;
+                let visible = !self.visible.unwrap_or(false);
+                return Command::perform(
+                    osk::set_visible(visible, self.config.command.clone()),
+                    |res| Message::Toggled(res.map_err(|err| err.to_string())),
+                );
+            }
+            Message::Toggled(res) => {
+                if let Err(err) = res {
+                    tracing::error!("Failed to toggle screen keyboard: {}", err);
+                    self.error = Some(err);
+                }
+            }
+            Message::AutoShow(auto_show) => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_auto_show(helper, auto_show) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+                return Command::perform(osk::set_auto_show(auto_show), |res| {
+                    Message::AutoShowSet(res.map_err(|err| err.to_string()))
+                });
+            }
+            Message::AutoShowSet(res) => {
+                if let Err(err) = res {
+                    tracing::error!("Failed to set automatic screen keyboard: {}", err);
+                    self.error = Some(err);
+                }
+            }
+            Message::Command(command) => {
+                self.command = command;
+            }
+            Message::SaveCommand => {
+                let command = self.command.trim().to_string();
+                if command.is_empty() {
+                    self.command = self.config.command.clone();
+                } else if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_command(helper, command) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        // Clicking shows or hides the keyboard, the settings are a right click away
+        mouse_area(
+            core.applet
+                .icon_button(ICON)
+                .on_press(Message::Toggle.into()),
+        )
+        .on_right_press(AppletMessage::TogglePopup)
+        .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = vec![
+            padded_control(
+                column![
+                    toggler(
+                        Some(fl!("screen-keyboard")),
+                        self.visible.unwrap_or(false),
+                        |_| Message::Toggle
+                    )
+                    .text_size(14)
+                    .width(Length::Fill),
+                    text(if self.visible.is_some() {
+                        fl!("running")
+                    } else {
+                        fl!("not-running")
+                    })
+                    .size(10),
+                ]
+                .spacing(2),
+            )
+            .into(),
+            padded_control(
+                column![
+                    toggler(
+                        Some(fl!("auto-show")),
+                        self.config.auto_show,
+                        Message::AutoShow
+                    )
+                    .text_size(14)
+                    .width(Length::Fill),
+                    text(fl!("auto-show-description")).size(10),
+                ]
+                .spacing(2),
+            )
+            .into(),
+            padded_control(divider::horizontal::default()).into(),
+            padded_control(
+                column![
+                    text(fl!("keyboard-command")).size(14),
+                    text_input("squeekboard", &self.command)
+                        .on_input(Message::Command)
+                        .on_submit(Message::SaveCommand),
+                ]
+                .spacing(4),
+            )
+            .into(),
+        ];
+
+        if let Some(err) = &self.error {
+            content.push(padded_control(text(fl!("failed", error = err.as_str())).size(10)).into());
+        }
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+}
@@ -0,0 +1,11 @@
+screenshot = Screenshot
+entire-screen = Entire screen
+window = Window
+region = Region
+screen-recording = Screen recording
+record-screen = Record screen
+record-window = Record window
+recording = Recording { $elapsed }
+stop-recording = Stop recording
+open-saved = Open { $name }
+failed = Failed: { $error }
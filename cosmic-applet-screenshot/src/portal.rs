@@ -0,0 +1,163 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Screenshots and screen recordings, through the XDG desktop portal.
+
+use std::{
+    os::fd::AsRawFd,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use ashpd::{
+    desktop::{
+        screencast::{CursorMode, PersistMode, Screencast, SourceType},
+        screenshot::Screenshot,
+    },
+    WindowIdentifier,
+};
+use tokio::sync::oneshot;
+
+/// Gives the popup time to close, so it isn't captured.
+const POPUP_CLOSE_DELAY: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capture {
+    Screen,
+    Window,
+    /// Screenshots only; the portal can't record part of a screen.
+    Region,
+}
+
+/// Takes a screenshot, returning where the portal saved it.
+///
+/// The portal has no separate window and region modes, so both open its
+/// interactive picker.
+pub async fn screenshot(capture: Capture) -> anyhow::Result<PathBuf> {
+    tokio::time::sleep(POPUP_CLOSE_DELAY).await;
+    let response = Screenshot::request()
+        .interactive(capture != Capture::Screen)
+        .modal(true)
+        .send()
+        .await?
+        .response()?;
+    response
+        .uri()
+        .to_file_path()
+        .map_err(|_| anyhow::anyhow!("screenshot saved to non-file uri {}", response.uri()))
+}
+
+#[derive(Debug, Clone)]
+pub struct Recording {
+    pub path: PathBuf,
+    pub started: Instant,
+    pid: u32,
+    /// Resolves once the encoder has finished writing the file.
+    done: Arc<Mutex<Option<oneshot::Receiver<()>>>>,
+}
+
+fn recording_path() -> anyhow::Result<PathBuf> {
+    let dir = dirs::video_dir()
+        .or_else(dirs::home_dir)
+        .context("no videos directory")?
+        .join("Screencasts");
+    std::fs::create_dir_all(&dir)?;
+    let name = chrono::Local::now()
+        .format("screencast-%Y-%m-%d_%H-%M-%S.webm")
+        .to_string();
+    Ok(dir.join(name))
+}
+
+/// Asks the portal for a screen or window to record, and records it to a
+/// WebM file with GStreamer until [`stop_recording`] is called.
+pub async fn start_recording(capture: Capture) -> anyhow::Result<Recording> {
+    tokio::time::sleep(POPUP_CLOSE_DELAY).await;
+    let source_type = match capture {
+        Capture::Window => SourceType::Window,
+        Capture::Screen | Capture::Region => SourceType::Monitor,
+    };
+
+    let proxy = Screencast::new().await?;
+    let session = proxy.create_session().await?;
+    proxy
+        .select_sources(
+            &session,
+            CursorMode::Embedded,
+            source_type.into(),
+            false,
+            None,
+            PersistMode::DoNot,
+        )
+        .await?;
+    let streams = proxy
+        .start(&session, &WindowIdentifier::default())
+        .await?
+        .response()?;
+    let node = streams
+        .streams()
+        .first()
+        .context("portal returned no streams")?
+        .pipe_wire_node_id();
+    let fd = proxy.open_pipe_wire_remote(&session).await?;
+    // Let gst-launch inherit the PipeWire connection
+    rustix::io::fcntl_setfd(&fd, rustix::io::FdFlags::empty())?;
+
+    let path = recording_path()?;
+    let mut child = tokio::process::Command::new("gst-launch-1.0")
+        // Finish the file on SIGINT
+        .arg("-e")
+        .args([
+            "pipewiresrc".to_string(),
+            format!("fd={}", fd.as_raw_fd()),
+            format!("path={node}"),
+            "do-timestamp=true".to_string(),
+            "!".to_string(),
+            "videoconvert".to_string(),
+            "!".to_string(),
+            "queue".to_string(),
+            "!".to_string(),
+            "vp8enc".to_string(),
+            "deadline=1".to_string(),
+            "cpu-used=8".to_string(),
+            "!".to_string(),
+            "webmmux".to_string(),
+            "!".to_string(),
+            "filesink".to_string(),
+            format!("location=\"{}\"", path.display()),
+        ])
+        .spawn()
+        .context("failed to start gst-launch-1.0")?;
+    drop(fd);
+    let pid = child.id().context("gst-launch-1.0 exited immediately")?;
+
+    let (done_tx, done_rx) = oneshot::channel();
+    tokio::spawn(async move {
+        if let Err(err) = child.wait().await {
+            tracing::error!("Failed to wait for gst-launch-1.0: {}", err);
+        }
+        if let Err(err) = session.close().await {
+            tracing::error!("Failed to close screencast session: {}", err);
+        }
+        let _ = done_tx.send(());
+    });
+
+    Ok(Recording {
+        path,
+        started: Instant::now(),
+        pid,
+        done: Arc::new(Mutex::new(Some(done_rx))),
+    })
+}
+
+/// Stops a recording, returning the file once it's complete.
+pub async fn stop_recording(recording: Recording) -> anyhow::Result<PathBuf> {
+    let pid = rustix::process::Pid::from_raw(recording.pid as i32).context("invalid pid")?;
+    rustix::process::kill_process(pid, rustix::process::Signal::Int)?;
+    let done = recording.done.lock().unwrap().take();
+    if let Some(done) = done {
+        let _ = done.await;
+    }
+    Ok(recording.path)
+}
@@ -0,0 +1,265 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::{path::PathBuf, time::Duration};
+
+use crate::{
+    fl,
+    portal::{self, Capture, Recording},
+};
+use cosmic::{
+    app::Core,
+    applet::{cosmic_panel_config::PanelAnchor, menu_button, padded_control},
+    iced::{time, widget::row, Alignment, Command, Length, Subscription},
+    iced_widget::{Column, Row},
+    widget::{divider, horizontal_space, icon, text},
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PopupRequest};
+
+const ID: &str = "com.system76.CosmicAppletScreenshot";
+const ICON: &str = "com.system76.CosmicAppletScreenshot-symbolic";
+const RECORDING_ICON: &str = "media-record-symbolic";
+
+#[derive(Default)]
+pub struct Window {
+    recording: Option<Recording>,
+    /// Whether a portal request is in flight.
+    pending: bool,
+    /// The last screenshot or recording saved.
+    saved: Option<PathBuf>,
+    error: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    Screenshot(Capture),
+    Screenshotted(Result<PathBuf, String>),
+    StartRecording(Capture),
+    RecordingStarted(Result<Recording, String>),
+    StopRecording,
+    RecordingStopped(Result<PathBuf, String>),
+    OpenSaved,
+    Tick,
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+fn action<'a>(
+    icon_name: &'static str,
+    label: String,
+    message: Option<Message>,
+) -> Element<'a, Message> {
+    menu_button(
+        row![
+            icon::from_name(icon_name).size(16).symbolic(true),
+            text(label).size(14),
+            horizontal_space(Length::Fill),
+        ]
+        .spacing(8)
+        .align_items(Alignment::Center),
+    )
+    .on_press_maybe(message)
+    .into()
+}
+
+impl Window {
+    fn finished(&mut self, res: Result<PathBuf, String>, what: &str) {
+        self.pending = false;
+        match res {
+            Ok(path) => {
+                self.saved = Some(path);
+                self.error = None;
+            }
+            Err(err) => {
+                tracing::error!("Failed to {}: {}", what, err);
+                self.error = Some(err);
+            }
+        }
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        (Self::default(), Command::none())
+    }
+
+    fn panel_subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        if self.recording.is_some() {
+            time::every(Duration::from_secs(1)).map(|_| Message::Tick)
+        } else {
+            Subscription::none()
+        }
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::Screenshot(capture) => {
+                self.pending = true;
+                return Command::perform(portal::screenshot(capture), |res| {
+                    Message::Screenshotted(res.map_err(|err| err.to_string()))
+                });
+            }
+            Message::Screenshotted(res) => {
+                self.finished(res, "take screenshot");
+            }
+            Message::StartRecording(capture) => {
+                self.pending = true;
+                return Command::perform(portal::start_recording(capture), |res| {
+                    Message::RecordingStarted(res.map_err(|err| err.to_string()))
+                });
+            }
+            Message::RecordingStarted(res) => {
+                self.pending = false;
+                match res {
+                    Ok(recording) => {
+                        self.recording = Some(recording);
+                        self.error = None;
+                    }
+                    Err(err) => {
+                        tracing::error!("Failed to start recording: {}", err);
+                        self.error = Some(err);
+                    }
+                }
+            }
+            Message::StopRecording => {
+                if let Some(recording) = self.recording.take() {
+                    self.pending = true;
+                    return Command::perform(portal::stop_recording(recording), |res| {
+                        Message::RecordingStopped(res.map_err(|err| err.to_string()))
+                    });
+                }
+            }
+            Message::RecordingStopped(res) => {
+                self.finished(res, "stop recording");
+            }
+            Message::OpenSaved => {
+                if let Some(path) = self.saved.as_ref() {
+                    let mut cmd = std::process::Command::new("xdg-open");
+                    cmd.arg(path);
+                    cosmic::process::spawn(cmd);
+                }
+            }
+            // Redraws the panel timer
+            Message::Tick => {}
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let Some(recording) = self.recording.as_ref() else {
+            return core
+                .applet
+                .icon_button(ICON)
+                .on_press(AppletMessage::TogglePopup)
+                .into();
+        };
+        let btn = core
+            .applet
+            .icon_button(RECORDING_ICON)
+            .on_press(AppletMessage::TogglePopup)
+            .into();
+        let elapsed = text(format_elapsed(recording.started.elapsed()))
+            .size(14)
+            .into();
+        match core.applet.anchor {
+            PanelAnchor::Left | PanelAnchor::Right => Column::with_children(vec![btn, elapsed])
+                .align_items(Alignment::Center)
+                .into(),
+            PanelAnchor::Top | PanelAnchor::Bottom => Row::with_children(vec![btn, elapsed])
+                .align_items(Alignment::Center)
+                .spacing(4)
+                .into(),
+        }
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let idle = !self.pending && self.recording.is_none();
+        let when_idle = |message| idle.then_some(message);
+
+        let mut content = vec![
+            padded_control(text(fl!("screenshot")).size(14)).into(),
+            action(
+                "video-display-symbolic",
+                fl!("entire-screen"),
+                when_idle(Message::Screenshot(Capture::Screen)),
+            ),
+            action(
+                "focus-windows-symbolic",
+                fl!("window"),
+                when_idle(Message::Screenshot(Capture::Window)),
+            ),
+            action(
+                "selection-mode-symbolic",
+                fl!("region"),
+                when_idle(Message::Screenshot(Capture::Region)),
+            ),
+            padded_control(divider::horizontal::default()).into(),
+        ];
+
+        if let Some(recording) = self.recording.as_ref() {
+            content.push(
+                padded_control(
+                    text(fl!(
+                        "recording",
+                        elapsed = format_elapsed(recording.started.elapsed())
+                    ))
+                    .size(14),
+                )
+                .into(),
+            );
+            content.push(action(
+                "media-playback-stop-symbolic",
+                fl!("stop-recording"),
+                (!self.pending).then_some(Message::StopRecording),
+            ));
+        } else {
+            content.push(padded_control(text(fl!("screen-recording")).size(14)).into());
+            content.push(action(
+                "video-display-symbolic",
+                fl!("record-screen"),
+                when_idle(Message::StartRecording(Capture::Screen)),
+            ));
+            content.push(action(
+                "focus-windows-symbolic",
+                fl!("record-window"),
+                when_idle(Message::StartRecording(Capture::Window)),
+            ));
+        }
+
+        if let Some(err) = self.error.as_ref() {
+            content.push(padded_control(divider::horizontal::default()).into());
+            content.push(padded_control(text(fl!("failed", error = err.as_str())).size(10)).into());
+        } else if let Some(path) = self.saved.as_ref() {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            content.push(padded_control(divider::horizontal::default()).into());
+            content.push(action(
+                "document-open-symbolic",
+                fl!("open-saved", name = name),
+                Some(Message::OpenSaved),
+            ));
+        }
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+
+    fn popup_request(&self, message: &Message) -> Option<PopupRequest> {
+        match message {
+            // Closed so it isn't captured along with the screen
+            Message::Screenshot(_)
+            | Message::StartRecording(_)
+            | Message::StopRecording
+            | Message::OpenSaved => Some(PopupRequest::Close),
+            _ => None,
+        }
+    }
+}
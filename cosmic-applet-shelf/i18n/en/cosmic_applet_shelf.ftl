@@ -0,0 +1,3 @@
+put-away = Put a window on the shelf
+no-windows = No open windows
+restore-all = Restore all
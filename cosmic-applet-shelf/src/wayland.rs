@@ -0,0 +1,248 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Toplevels from cosmic-comp, and requests to manage them.
+
+use std::os::{
+    fd::{FromRawFd, RawFd},
+    unix::net::UnixStream,
+};
+
+use cosmic::{
+    cctk::{
+        self,
+        cosmic_protocols::{
+            toplevel_info::v1::client::zcosmic_toplevel_handle_v1::ZcosmicToplevelHandleV1,
+            toplevel_management::v1::client::zcosmic_toplevel_manager_v1,
+        },
+        sctk::{
+            self,
+            reexports::{calloop, calloop_wayland_source::WaylandSource},
+            registry::{ProvidesRegistryState, RegistryState},
+            seat::{Capability, SeatHandler, SeatState},
+        },
+        toplevel_info::{ToplevelInfo, ToplevelInfoHandler, ToplevelInfoState},
+        toplevel_management::{ToplevelManagerHandler, ToplevelManagerState},
+        wayland_client::{
+            globals::registry_queue_init, protocol::wl_seat::WlSeat, Connection, QueueHandle, WEnum,
+        },
+    },
+    iced::{self, subscription},
+    iced_futures::futures::{
+        self,
+        channel::mpsc::{unbounded, UnboundedSender},
+        SinkExt, StreamExt,
+    },
+};
+
+#[derive(Clone, Debug)]
+pub enum WaylandUpdate {
+    Init(calloop::channel::Sender<ToplevelRequest>),
+    Finished,
+    Toplevel(ZcosmicToplevelHandleV1, ToplevelInfo),
+    Closed(ZcosmicToplevelHandleV1),
+}
+
+#[derive(Clone, Debug)]
+pub enum ToplevelRequest {
+    Minimize(ZcosmicToplevelHandleV1),
+    Activate(ZcosmicToplevelHandleV1),
+}
+
+pub fn wayland_subscription() -> iced::Subscription<WaylandUpdate> {
+    subscription::channel(
+        std::any::TypeId::of::<WaylandUpdate>(),
+        50,
+        move |mut output| async move {
+            let (calloop_tx, calloop_rx) = calloop::channel::channel();
+            let (tx, mut rx) = unbounded();
+            std::thread::spawn(move || wayland_handler(tx, calloop_rx));
+            _ = output.send(WaylandUpdate::Init(calloop_tx)).await;
+
+            while let Some(update) = rx.next().await {
+                _ = output.send(update).await;
+            }
+            tracing::error!("Wayland handler thread died");
+            _ = output.send(WaylandUpdate::Finished).await;
+            futures::future::pending().await
+        },
+    )
+}
+
+struct AppData {
+    exit: bool,
+    tx: UnboundedSender<WaylandUpdate>,
+    registry_state: RegistryState,
+    seat_state: SeatState,
+    toplevel_info_state: ToplevelInfoState,
+    toplevel_manager_state: ToplevelManagerState,
+}
+
+impl AppData {
+    fn send_info(&self, toplevel: &ZcosmicToplevelHandleV1) {
+        if let Some(info) = self.toplevel_info_state.info(toplevel) {
+            let _ = self
+                .tx
+                .unbounded_send(WaylandUpdate::Toplevel(toplevel.clone(), info.clone()));
+        }
+    }
+}
+
+impl ProvidesRegistryState for AppData {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+
+    sctk::registry_handlers!();
+}
+
+impl SeatHandler for AppData {
+    fn seat_state(&mut self) -> &mut SeatState {
+        &mut self.seat_state
+    }
+
+    fn new_seat(&mut self, _: &Connection, _: &QueueHandle<Self>, _: WlSeat) {}
+
+    fn new_capability(&mut self, _: &Connection, _: &QueueHandle<Self>, _: WlSeat, _: Capability) {}
+
+    fn remove_capability(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: WlSeat,
+        _: Capability,
+    ) {
+    }
+
+    fn remove_seat(&mut self, _: &Connection, _: &QueueHandle<Self>, _: WlSeat) {}
+}
+
+impl ToplevelManagerHandler for AppData {
+    fn toplevel_manager_state(&mut self) -> &mut ToplevelManagerState {
+        &mut self.toplevel_manager_state
+    }
+
+    fn capabilities(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: Vec<WEnum<zcosmic_toplevel_manager_v1::ZcosmicToplelevelManagementCapabilitiesV1>>,
+    ) {
+    }
+}
+
+impl ToplevelInfoHandler for AppData {
+    fn toplevel_info_state(&mut self) -> &mut ToplevelInfoState {
+        &mut self.toplevel_info_state
+    }
+
+    fn new_toplevel(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        toplevel: &ZcosmicToplevelHandleV1,
+    ) {
+        self.send_info(toplevel);
+    }
+
+    fn update_toplevel(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        toplevel: &ZcosmicToplevelHandleV1,
+    ) {
+        self.send_info(toplevel);
+    }
+
+    fn toplevel_closed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        toplevel: &ZcosmicToplevelHandleV1,
+    ) {
+        let _ = self
+            .tx
+            .unbounded_send(WaylandUpdate::Closed(toplevel.clone()));
+    }
+}
+
+fn wayland_handler(
+    tx: UnboundedSender<WaylandUpdate>,
+    rx: calloop::channel::Channel<ToplevelRequest>,
+) {
+    let socket = std::env::var("X_PRIVILEGED_WAYLAND_SOCKET")
+        .ok()
+        .and_then(|fd| {
+            fd.parse::<RawFd>()
+                .ok()
+                .map(|fd| unsafe { UnixStream::from_raw_fd(fd) })
+        });
+
+    let conn = if let Some(socket) = socket {
+        Connection::from_socket(socket)
+    } else {
+        Connection::connect_to_env()
+    };
+    let conn = match conn {
+        Ok(conn) => conn,
+        Err(err) => {
+            tracing::error!("Failed to connect to the compositor: {}", err);
+            return;
+        }
+    };
+    let Ok((globals, event_queue)) = registry_queue_init(&conn) else {
+        tracing::error!("Failed to initialize the Wayland registry");
+        return;
+    };
+
+    let mut event_loop = calloop::EventLoop::<AppData>::try_new().unwrap();
+    let qh = event_queue.handle();
+    let wayland_source = WaylandSource::new(conn.clone(), event_queue);
+    let handle = event_loop.handle();
+    wayland_source
+        .insert(handle.clone())
+        .expect("Failed to insert wayland source.");
+
+    if handle
+        .insert_source(rx, |event, _, state| match event {
+            calloop::channel::Event::Msg(req) => {
+                let manager = &state.toplevel_manager_state.manager;
+                match req {
+                    ToplevelRequest::Minimize(handle) => manager.set_minimized(&handle),
+                    ToplevelRequest::Activate(handle) => {
+                        if let Some(seat) = state.seat_state.seats().next() {
+                            manager.activate(&handle, &seat);
+                        }
+                    }
+                }
+            }
+            calloop::channel::Event::Closed => {
+                state.exit = true;
+            }
+        })
+        .is_err()
+    {
+        return;
+    }
+
+    let registry_state = RegistryState::new(&globals);
+    let mut app_data = AppData {
+        exit: false,
+        tx,
+        seat_state: SeatState::new(&globals, &qh),
+        toplevel_info_state: ToplevelInfoState::new(&registry_state, &qh),
+        toplevel_manager_state: ToplevelManagerState::new(&registry_state, &qh),
+        registry_state,
+    };
+
+    while !app_data.exit {
+        if event_loop.dispatch(None, &mut app_data).is_err() {
+            break;
+        }
+    }
+}
+
+sctk::delegate_seat!(AppData);
+sctk::delegate_registry!(AppData);
+cctk::delegate_toplevel_info!(AppData);
+cctk::delegate_toplevel_manager!(AppData);
@@ -0,0 +1,247 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::{
+    fl,
+    wayland::{wayland_subscription, ToplevelRequest, WaylandUpdate},
+};
+use cosmic::{
+    app::Core,
+    applet::{cosmic_panel_config::PanelAnchor, menu_button, padded_control},
+    cctk::{
+        cosmic_protocols::toplevel_info::v1::client::zcosmic_toplevel_handle_v1::{
+            State, ZcosmicToplevelHandleV1,
+        },
+        sctk::reexports::calloop,
+        toplevel_info::ToplevelInfo,
+    },
+    desktop::DesktopEntryData,
+    iced::{
+        widget::{column, row},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_widget::{Column, Row},
+    widget::{button, divider, text, tooltip},
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PopupRequest};
+
+const ID: &str = "com.system76.CosmicAppletShelf";
+const ICON: &str = "com.system76.CosmicAppletShelf-symbolic";
+
+struct Toplevel {
+    handle: ZcosmicToplevelHandleV1,
+    info: ToplevelInfo,
+    data: DesktopEntryData,
+}
+
+struct Shelved {
+    handle: ZcosmicToplevelHandleV1,
+    /// Whether the compositor has minimized the window yet. Until then an
+    /// update that isn't minimized doesn't mean it was restored elsewhere.
+    minimized: bool,
+}
+
+#[derive(Default)]
+pub struct Window {
+    tx: Option<calloop::channel::Sender<ToplevelRequest>>,
+    toplevels: Vec<Toplevel>,
+    shelved: Vec<Shelved>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    Wayland(WaylandUpdate),
+    Shelve(ZcosmicToplevelHandleV1),
+    Restore(ZcosmicToplevelHandleV1),
+    RestoreAll,
+}
+
+fn desktop_entry(app_id: &str) -> DesktopEntryData {
+    cosmic::desktop::load_applications_for_app_ids(None, std::iter::once(app_id), true, false)
+        .remove(0)
+}
+
+impl Window {
+    fn toplevel(&self, handle: &ZcosmicToplevelHandleV1) -> Option<&Toplevel> {
+        self.toplevels.iter().find(|t| t.handle == *handle)
+    }
+
+    fn is_shelved(&self, handle: &ZcosmicToplevelHandleV1) -> bool {
+        self.shelved.iter().any(|s| s.handle == *handle)
+    }
+
+    fn request(&self, request: ToplevelRequest) {
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(request);
+        }
+    }
+
+    fn restore(&mut self, handle: ZcosmicToplevelHandleV1) {
+        self.shelved.retain(|s| s.handle != handle);
+        self.request(ToplevelRequest::Activate(handle));
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        (Self::default(), Command::none())
+    }
+
+    fn subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        wayland_subscription().map(Message::Wayland)
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::Wayland(update) => match update {
+                WaylandUpdate::Init(tx) => {
+                    self.tx = Some(tx);
+                }
+                WaylandUpdate::Finished => {
+                    self.tx = None;
+                    self.toplevels.clear();
+                    self.shelved.clear();
+                }
+                WaylandUpdate::Toplevel(handle, info) => {
+                    let minimized = info.state.contains(&State::Minimized);
+                    // Restored some other way, such as from the app list
+                    if let Some(pos) = self.shelved.iter().position(|s| s.handle == handle) {
+                        if minimized {
+                            self.shelved[pos].minimized = true;
+                        } else if self.shelved[pos].minimized {
+                            self.shelved.remove(pos);
+                        }
+                    }
+
+                    if let Some(toplevel) = self.toplevels.iter_mut().find(|t| t.handle == handle) {
+                        if toplevel.info.app_id != info.app_id {
+                            toplevel.data = desktop_entry(&info.app_id);
+                        }
+                        toplevel.info = info;
+                    } else {
+                        let data = desktop_entry(&info.app_id);
+                        self.toplevels.push(Toplevel { handle, info, data });
+                    }
+                }
+                WaylandUpdate::Closed(handle) => {
+                    self.toplevels.retain(|t| t.handle != handle);
+                    self.shelved.retain(|s| s.handle != handle);
+                }
+            },
+            Message::Shelve(handle) => {
+                if !self.is_shelved(&handle) {
+                    self.shelved.push(Shelved {
+                        handle: handle.clone(),
+                        minimized: false,
+                    });
+                }
+                self.request(ToplevelRequest::Minimize(handle));
+            }
+            Message::Restore(handle) => {
+                self.restore(handle);
+            }
+            Message::RestoreAll => {
+                for shelved in std::mem::take(&mut self.shelved) {
+                    self.request(ToplevelRequest::Activate(shelved.handle));
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let (icon_size, _) = core.applet.suggested_size(true);
+        let padding = core.applet.suggested_padding(true);
+        let tooltip_position = match core.applet.anchor {
+            PanelAnchor::Left => tooltip::Position::Right,
+            PanelAnchor::Right => tooltip::Position::Left,
+            PanelAnchor::Top => tooltip::Position::Bottom,
+            PanelAnchor::Bottom => tooltip::Position::Top,
+        };
+
+        let mut children = self
+            .shelved
+            .iter()
+            .filter_map(|shelved| self.toplevel(&shelved.handle))
+            .map(|toplevel| {
+                tooltip(
+                    button(toplevel.data.icon.as_cosmic_icon().size(icon_size))
+                        .padding(padding)
+                        .on_press(Message::Restore(toplevel.handle.clone()).into())
+                        .style(cosmic::theme::Button::AppletIcon),
+                    toplevel.info.title.clone(),
+                    tooltip_position,
+                )
+                .snap_within_viewport(false)
+                .into()
+            })
+            .collect::<Vec<Element<_>>>();
+        children.push(
+            core.applet
+                .icon_button(ICON)
+                .on_press(AppletMessage::TogglePopup)
+                .into(),
+        );
+
+        let space_xxs = core.system_theme().cosmic().space_xxs();
+        if matches!(core.applet.anchor, PanelAnchor::Top | PanelAnchor::Bottom) {
+            Row::with_children(children)
+                .align_items(Alignment::Center)
+                .spacing(space_xxs)
+                .into()
+        } else {
+            Column::with_children(children)
+                .align_items(Alignment::Center)
+                .spacing(space_xxs)
+                .into()
+        }
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = vec![padded_control(text(fl!("put-away")).size(14)).into()];
+
+        let open = self
+            .toplevels
+            .iter()
+            .filter(|t| !self.is_shelved(&t.handle))
+            .collect::<Vec<_>>();
+        if open.is_empty() {
+            content.push(padded_control(text(fl!("no-windows")).size(10)).into());
+        }
+        for toplevel in open {
+            content.push(
+                menu_button(
+                    row![
+                        toplevel.data.icon.as_cosmic_icon().size(24),
+                        column![
+                            text(&toplevel.data.name).size(14),
+                            text(&toplevel.info.title).size(10),
+                        ]
+                        .width(Length::Fill),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .on_press(Message::Shelve(toplevel.handle.clone()))
+                .into(),
+            );
+        }
+
+        content.push(padded_control(divider::horizontal::default()).into());
+        content.push(
+            menu_button(text(fl!("restore-all")).size(14))
+                .on_press_maybe((!self.shelved.is_empty()).then_some(Message::RestoreAll))
+                .into(),
+        );
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+
+    fn popup_request(&self, message: &Message) -> Option<PopupRequest> {
+        matches!(message, Message::RestoreAll).then_some(PopupRequest::Close)
+    }
+}
@@ -0,0 +1,11 @@
+search = Search shortcuts
+windows = Windows
+workspaces = Workspaces
+displays = Displays
+system = System
+applications = Applications
+other = Other
+launch = Launch { $command }
+no-shortcuts = No shortcuts found
+no-matches = No matching shortcuts
+keyboard-settings = Keyboard settings...
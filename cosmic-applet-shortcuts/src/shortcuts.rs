@@ -0,0 +1,138 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Keybindings from cosmic-config, with the user's changes applied over the
+//! defaults.
+
+use cosmic_settings_config::shortcuts;
+
+use crate::fl;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    Windows,
+    Workspaces,
+    Displays,
+    System,
+    Applications,
+    Other,
+}
+
+impl Category {
+    /// Groups an action by its name, so new actions still land somewhere
+    /// sensible.
+    fn of(action: &str) -> Self {
+        if action.starts_with("Spawn") {
+            Category::Applications
+        } else if action.starts_with("System") || action == "Terminate" || action == "Debug" {
+            Category::System
+        } else if action.contains("Output") {
+            Category::Displays
+        } else if action.contains("Workspace") {
+            Category::Workspaces
+        } else if [
+            "Focus",
+            "Move",
+            "Close",
+            "Swap",
+            "Toggle",
+            "Maximize",
+            "Minimize",
+            "Fullscreen",
+            "Resizing",
+            "Orientation",
+        ]
+        .iter()
+        .any(|prefix| action.starts_with(prefix))
+        {
+            Category::Windows
+        } else {
+            Category::Other
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            Category::Windows => fl!("windows"),
+            Category::Workspaces => fl!("workspaces"),
+            Category::Displays => fl!("displays"),
+            Category::System => fl!("system"),
+            Category::Applications => fl!("applications"),
+            Category::Other => fl!("other"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Shortcut {
+    pub category: Category,
+    pub description: String,
+    pub binding: String,
+}
+
+impl Shortcut {
+    pub fn matches(&self, search: &str) -> bool {
+        let search = search.to_lowercase();
+        self.description.to_lowercase().contains(&search)
+            || self.binding.to_lowercase().contains(&search)
+    }
+}
+
+/// Splits `CamelCase` into lowercase words.
+fn words(name: &str) -> String {
+    let mut words = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            words.push(' ');
+        }
+        words.extend(c.to_lowercase());
+    }
+    words
+}
+
+/// Turns an action's debug name, such as `MoveToWorkspace(2)`, into a
+/// description like "Move to workspace 2".
+fn describe(action: &str) -> String {
+    let (name, argument) = match action.split_once('(') {
+        Some((name, argument)) => (name, Some(argument.trim_end_matches(')').trim_matches('"'))),
+        None => (action, None),
+    };
+    let description = match (name, argument) {
+        ("Spawn", Some(command)) => fl!("launch", command = command),
+        ("System", Some(action)) => words(action),
+        (name, Some(argument)) => format!("{} {}", words(name), words(argument)),
+        (name, None) => words(name),
+    };
+    let mut chars = description.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// Every keybinding, sorted by category and description.
+pub fn load() -> Vec<Shortcut> {
+    let context = match shortcuts::context() {
+        Ok(context) => context,
+        Err(err) => {
+            tracing::error!(?err, "Error loading shortcuts");
+            return Vec::new();
+        }
+    };
+    let mut list = shortcuts::shortcuts(&context)
+        .0
+        .into_iter()
+        .map(|(binding, action)| {
+            let action = format!("{:?}", action);
+            Shortcut {
+                category: Category::of(&action),
+                description: describe(&action),
+                binding: binding.to_string(),
+            }
+        })
+        .collect::<Vec<_>>();
+    list.sort_by(|a, b| {
+        (a.category, &a.description, &a.binding).cmp(&(b.category, &b.description, &b.binding))
+    });
+    list
+}
@@ -0,0 +1,136 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::{
+    fl,
+    shortcuts::{self, Shortcut},
+};
+use cosmic::{
+    app::Core,
+    applet::{menu_button, padded_control},
+    iced::{widget::row, Alignment, Command, Length, Limits},
+    iced_widget::Column,
+    widget::{divider, scrollable, search_input, text},
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent, PopupRequest, PopupWidth};
+
+const ID: &str = "com.system76.CosmicAppletShortcuts";
+const ICON: &str = "com.system76.CosmicAppletShortcuts-symbolic";
+
+#[derive(Default)]
+pub struct Window {
+    shortcuts: Vec<Shortcut>,
+    search: String,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    Search(String),
+    OpenSettings,
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        (Self::default(), Command::none())
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        if event == PanelEvent::PopupOpened {
+            // Read when opened, so changes made in Settings show up
+            self.shortcuts = shortcuts::load();
+            self.search.clear();
+        }
+        Command::none()
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::Search(search) => {
+                self.search = search;
+            }
+            Message::OpenSettings => {
+                let mut cmd = std::process::Command::new("cosmic-settings");
+                cmd.arg("keyboard");
+                cosmic::process::spawn(cmd);
+            }
+        }
+        Command::none()
+    }
+
+    fn popup_request(&self, message: &Message) -> Option<PopupRequest> {
+        matches!(message, Message::OpenSettings).then_some(PopupRequest::Close)
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        core.applet
+            .icon_button(ICON)
+            .on_press(AppletMessage::TogglePopup)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = vec![padded_control(
+            search_input(fl!("search"), &self.search)
+                .on_input(|search| Message::Search(search).into())
+                .on_clear(Message::Search(String::new()).into()),
+        )
+        .into()];
+
+        let mut list = Vec::new();
+        let mut category = None;
+        for shortcut in self.shortcuts.iter().filter(|s| s.matches(&self.search)) {
+            if category != Some(shortcut.category) {
+                category = Some(shortcut.category);
+                list.push(padded_control(text(shortcut.category.label()).size(10)).into());
+            }
+            list.push(
+                padded_control(
+                    row![
+                        text(&shortcut.description).size(14).width(Length::Fill),
+                        text(&shortcut.binding).size(12),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+        }
+
+        if list.is_empty() {
+            content.push(
+                padded_control(
+                    text(if self.shortcuts.is_empty() {
+                        fl!("no-shortcuts")
+                    } else {
+                        fl!("no-matches")
+                    })
+                    .size(14),
+                )
+                .into(),
+            );
+        } else {
+            content.push(
+                scrollable(Column::with_children(list))
+                    .height(Length::Shrink)
+                    .into(),
+            );
+        }
+
+        content.push(padded_control(divider::horizontal::default()).into());
+        content.push(
+            menu_button(text(fl!("keyboard-settings")).size(14))
+                .on_press(Message::OpenSettings.into())
+                .into(),
+        );
+
+        Column::with_children(content).padding([8, 0]).into()
+    }
+
+    fn popup_limits(&self, _core: &Core) -> Limits {
+        PopupWidth::Standard.limits().min_height(200.0)
+    }
+}
@@ -0,0 +1,6 @@
+tray-settings = Tray settings...
+back = Back
+always-show = Always show
+show-in-overflow = Show in overflow
+never-show = Never show
+more-items = More tray items
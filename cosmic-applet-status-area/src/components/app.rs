@@ -2,42 +2,86 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use cosmic::{
-    app::{self, Command},
-    applet::cosmic_panel_config::PanelAnchor,
+    app::Core,
+    applet::{cosmic_panel_config::PanelAnchor, menu_button, padded_control},
+    cosmic_config::{Config, CosmicConfigEntry},
     iced::{
         self,
-        wayland::{
-            popup::{destroy_popup, get_popup},
-            window::resize_window,
-        },
-        window, Subscription,
+        event::listen_with,
+        mouse::{self, ScrollDelta},
+        wayland::window::resize_window,
+        window, Alignment, Command, Event, Length, Rectangle, Subscription,
     },
-    iced_style::application,
-    widget::mouse_area,
-    Theme,
+    widget::{divider, dropdown, icon, text, tooltip, Column, Row},
+};
+use cosmic_applet_helper::{
+    icon_button, icon_button_from_handle, AppletMessage, CosmicApplet, Dismissal, MouseArea,
+    PanelEvent, PopupRequest,
 };
 use std::collections::BTreeMap;
 
-use crate::{components::status_menu, subscriptions::status_notifier_watcher};
+use crate::{
+    components::status_menu,
+    config::{self, ItemVisibility, StatusAreaConfig},
+    fl,
+    subscriptions::status_notifier_watcher,
+};
+
+// Approximate height of a line, for converting pixel scroll deltas into the
+// discrete steps expected by `Scroll`.
+const PIXELS_PER_LINE: f32 = 40.;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverflowPage {
+    Items,
+    Settings,
+}
+
+/// What a popup of the status area shows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Popup {
+    Menu(usize),
+    Overflow,
+}
 
 #[derive(Clone, Debug)]
 pub enum Msg {
-    Closed(window::Id),
     // XXX don't use index (unique window id? or I guess that's created and destroyed)
     StatusMenu((usize, status_menu::Msg)),
     StatusNotifier(status_notifier_watcher::Event),
     TogglePopup(usize),
     Hovered(usize),
+    HoveredOverflow,
+    Activate(usize),
+    Activated(usize, bool),
+    SecondaryActivate(usize),
+    Scroll(usize, ScrollDelta),
+    ToggleOverflow,
+    OverflowPage(OverflowPage),
+    SetVisibility(usize, usize),
+    DragStart(usize),
+    DragOver(usize),
+    DragEnd,
+    ConfigChanged(StatusAreaConfig),
 }
 
 #[derive(Default)]
 struct App {
-    core: app::Core,
     connection: Option<zbus::Connection>,
     menus: BTreeMap<usize, status_menu::State>,
+    // The key of each menu's item in the config. Usually the item's `Id`, but
+    // items can share an `Id`.
+    config_ids: BTreeMap<usize, String>,
     open_menu: Option<usize>,
+    overflow_page: Option<OverflowPage>,
     max_menu_id: usize,
-    popup: Option<window::Id>,
+    // Scrolling not yet amounting to a step, by menu.
+    scroll_remainders: BTreeMap<usize, (f32, f32)>,
+    config: StatusAreaConfig,
+    config_helper: Option<Config>,
+    // Index into the configured items of the item being dragged.
+    dragging: Option<usize>,
+    visibility_options: Vec<String>,
 }
 
 impl App {
@@ -46,59 +90,326 @@ impl App {
         self.max_menu_id
     }
 
-    fn next_popup_id(&mut self) -> window::Id {
-        window::Id::unique()
+    /// A config key for the item of `state` not used by another menu: its
+    /// `Id`, or if that's taken, the `Id` with its title and if need be a
+    /// number.
+    fn unique_config_id(&self, state: &status_menu::State) -> String {
+        let taken = |key: &str| self.config_ids.values().any(|id| id == key);
+        let id = state.id();
+        if !taken(id) {
+            return id.to_string();
+        }
+        let with_title = format!("{id}:{}", state.title());
+        if !taken(&with_title) {
+            return with_title;
+        }
+        (2..)
+            .map(|n| format!("{with_title}:{n}"))
+            .find(|key| !taken(key))
+            .unwrap()
+    }
+
+    fn config_id(&self, id: usize) -> &str {
+        self.config_ids.get(&id).map_or("", String::as_str)
+    }
+
+    /// Ids of menus with the given visibility, in configured order.
+    fn menus_with_visibility(&self, visibility: ItemVisibility) -> Vec<usize> {
+        let mut ids = self
+            .menus
+            .keys()
+            .filter(|id| self.config.visibility(self.config_id(**id)) == visibility)
+            .map(|id| {
+                (
+                    self.config
+                        .position(self.config_id(*id))
+                        .unwrap_or(usize::MAX),
+                    *id,
+                )
+            })
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.into_iter().map(|(_, id)| id).collect()
+    }
+
+    fn panel_menus(&self) -> Vec<usize> {
+        self.menus_with_visibility(ItemVisibility::AlwaysShow)
+    }
+
+    /// Position in the panel to anchor a menu's popup at. Menus not shown in
+    /// the panel are anchored at the overflow button.
+    fn popup_index(&self, id: usize) -> usize {
+        let panel_menus = self.panel_menus();
+        panel_menus
+            .iter()
+            .position(|&i| i == id)
+            .unwrap_or(panel_menus.len())
+    }
+
+    /// What the popup shows, if it's open.
+    fn shown(&self) -> Option<Popup> {
+        match (self.open_menu, self.overflow_page) {
+            (Some(id), _) => Some(Popup::Menu(id)),
+            (None, Some(_)) => Some(Popup::Overflow),
+            (None, None) => None,
+        }
+    }
+
+    /// Shows `popup` in place of what the popup showed.
+    fn switch_popup(&mut self, popup: Popup) {
+        self.clear_popup();
+        match popup {
+            Popup::Menu(id) => {
+                if let Some(menu) = self.menus.get(&id) {
+                    menu.opened();
+                    self.open_menu = Some(id);
+                }
+            }
+            Popup::Overflow => self.overflow_page = Some(OverflowPage::Items),
+        }
+    }
+
+    /// Closes the popup if it shows `popup`, or opens it again pointing at
+    /// the button of `popup`.
+    fn toggle_request(&self, popup: Popup) -> PopupRequest {
+        if self.shown() == Some(popup) {
+            PopupRequest::Close
+        } else {
+            PopupRequest::Reopen
+        }
+    }
+
+    /// Whether moving the pointer onto another item opens its popup, as when
+    /// scrubbing through the menus of a menu bar. The settings page is kept
+    /// open while it's being used.
+    fn scrubbing(&self) -> bool {
+        self.open_menu.is_some() || self.overflow_page == Some(OverflowPage::Items)
     }
 
-    fn resize_window(&self) -> Command<Msg> {
-        let icon_size = self.core.applet.suggested_size(true).0 as u32
-            + self.core.applet.suggested_padding(true) as u32 * 2;
-        let n = self.menus.len() as u32;
+    /// Forgets what the popup showed, once it's closed or about to show
+    /// something else.
+    fn clear_popup(&mut self) {
+        if let Some(id) = self.open_menu.take() {
+            if let Some(menu) = self.menus.get(&id) {
+                menu.closed();
+            }
+        }
+        self.overflow_page = None;
+        self.end_drag();
+    }
+
+    fn end_drag(&mut self) {
+        if self.dragging.take().is_some() {
+            self.config.write(self.config_helper.as_ref());
+        }
+    }
+
+    fn resize_window(&self, core: &Core) -> Command<Msg> {
+        let icon_size = core.applet.suggested_size(true).0 as u32
+            + core.applet.suggested_padding(true) as u32 * 2;
+        let mut n = self.panel_menus().len() as u32;
+        if !self.menus.is_empty() {
+            // Overflow button
+            n += 1;
+        }
         resize_window(window::Id::MAIN, 1.max(icon_size * n), icon_size)
     }
+
+    fn overflow_view(&self) -> cosmic::Element<'_, Msg> {
+        let items = self
+            .menus_with_visibility(ItemVisibility::Overflow)
+            .into_iter()
+            .filter_map(|id| {
+                let menu = self.menus.get(&id)?;
+                let content = Row::with_children(vec![
+                    icon(menu.icon(16)).size(16).into(),
+                    text(menu.title()).width(Length::Fill).into(),
+                ])
+                .spacing(8)
+                .align_items(Alignment::Center);
+                Some(
+                    MouseArea::new(menu_button(content).on_press(Msg::Activate(id)))
+                        .on_right_press(Msg::TogglePopup(id))
+                        .on_middle_press(Msg::SecondaryActivate(id))
+                        .into(),
+                )
+            })
+            .collect::<Vec<cosmic::Element<'_, Msg>>>();
+
+        let mut content = Column::new();
+        if !items.is_empty() {
+            content = content
+                .push(Column::with_children(items))
+                .push(padded_control(divider::horizontal::default()));
+        }
+        content
+            .push(
+                menu_button(text(fl!("tray-settings")))
+                    .on_press(Msg::OverflowPage(OverflowPage::Settings)),
+            )
+            .padding([8, 0])
+            .into()
+    }
+
+    fn settings_view(&self) -> cosmic::Element<'_, Msg> {
+        let rows = self.config.items.iter().enumerate().map(|(i, item)| {
+            let mut children: Vec<cosmic::Element<'_, Msg>> =
+                vec![icon::from_name("list-drag-handle-symbolic")
+                    .size(16)
+                    .symbolic(true)
+                    .into()];
+            if let Some(menu) = self
+                .config_ids
+                .iter()
+                .find(|(_, config_id)| **config_id == item.id)
+                .and_then(|(id, _)| self.menus.get(id))
+            {
+                children.push(icon(menu.icon(16)).size(16).into());
+            }
+            children.push(text(&item.title).width(Length::Fill).into());
+            let selected = ItemVisibility::ALL
+                .iter()
+                .position(|visibility| *visibility == item.visibility);
+            children.push(
+                dropdown(&self.visibility_options, selected, move |v| {
+                    Msg::SetVisibility(i, v)
+                })
+                .into(),
+            );
+
+            MouseArea::new(padded_control(
+                Row::with_children(children)
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+            ))
+            .on_drag(Msg::DragStart(i))
+            .on_mouse_enter(Msg::DragOver(i))
+            .on_release(Msg::DragEnd)
+            .into()
+        });
+
+        Column::new()
+            .push(
+                menu_button(
+                    Row::with_children(vec![
+                        icon::from_name("go-previous-symbolic")
+                            .size(16)
+                            .symbolic(true)
+                            .into(),
+                        text(fl!("back")).into(),
+                    ])
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .on_press(Msg::OverflowPage(OverflowPage::Items)),
+            )
+            .push(padded_control(divider::horizontal::default()))
+            .push(Column::with_children(rows.collect::<Vec<_>>()))
+            .padding([8, 0])
+            .into()
+    }
 }
 
-impl cosmic::Application for App {
+impl CosmicApplet for App {
     type Message = Msg;
-    type Executor = iced::executor::Default;
-    type Flags = ();
-    const APP_ID: &'static str = "com.system76.CosmicAppletStatusArea";
+    const APP_ID: &'static str = config::APP_ID;
 
-    fn init(core: app::Core, _flags: ()) -> (Self, app::Command<Msg>) {
+    fn init(_core: &Core) -> (Self, Command<Msg>) {
+        let config_helper = Config::new(config::APP_ID, StatusAreaConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                StatusAreaConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
         (
             Self {
-                core,
+                config,
+                config_helper,
+                visibility_options: vec![
+                    fl!("always-show"),
+                    fl!("show-in-overflow"),
+                    fl!("never-show"),
+                ],
                 ..Self::default()
             },
             Command::none(),
         )
     }
 
-    fn core(&self) -> &app::Core {
-        &self.core
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Msg> {
+        if event == PanelEvent::PopupClosed {
+            self.clear_popup();
+        }
+        Command::none()
+    }
+
+    fn on_dismiss(&mut self, _core: &Core, _dismissal: Dismissal) -> bool {
+        // Keep the settings open while an item is dragged out of them
+        self.dragging.is_none()
     }
 
-    fn core_mut(&mut self) -> &mut app::Core {
-        &mut self.core
+    fn popup_request(&self, message: &Msg) -> Option<PopupRequest> {
+        match message {
+            Msg::TogglePopup(id) => Some(self.toggle_request(Popup::Menu(*id))),
+            Msg::Activate(id) => self
+                .menus
+                .get(id)
+                .filter(|menu| menu.item_is_menu())
+                .map(|_| self.toggle_request(Popup::Menu(*id))),
+            Msg::Activated(_, true) => Some(PopupRequest::Close),
+            Msg::Activated(id, false) => (self.menus.contains_key(id)
+                && self.open_menu != Some(*id))
+            .then_some(PopupRequest::Reopen),
+            Msg::Hovered(id) => {
+                (self.scrubbing() && self.open_menu != Some(*id)).then_some(PopupRequest::Reopen)
+            }
+            Msg::HoveredOverflow => {
+                (self.scrubbing() && self.overflow_page.is_none()).then_some(PopupRequest::Reopen)
+            }
+            Msg::ToggleOverflow => Some(self.toggle_request(Popup::Overflow)),
+            Msg::StatusNotifier(status_notifier_watcher::Event::Unregistered(name)) => self
+                .open_menu
+                .and_then(|id| self.menus.get(&id))
+                .filter(|menu| menu.name() == name)
+                .map(|_| PopupRequest::Close),
+            _ => None,
+        }
     }
 
-    fn style(&self) -> Option<<Theme as application::StyleSheet>::Style> {
-        Some(cosmic::applet::style())
+    fn popup_anchor(&self, core: &Core) -> Option<Rectangle<i32>> {
+        let i = match self.shown()? {
+            Popup::Menu(id) => self.popup_index(id),
+            Popup::Overflow => self.panel_menus().len(),
+        } as i32;
+        let (width, height) = core.applet.suggested_size(false);
+        let padding = 2 * core.applet.suggested_padding(false);
+        let (width, height) = ((width + padding) as i32, (height + padding) as i32);
+        Some(match core.applet.anchor {
+            PanelAnchor::Left | PanelAnchor::Right => Rectangle {
+                x: 0,
+                y: i * height,
+                width,
+                height,
+            },
+            PanelAnchor::Top | PanelAnchor::Bottom => Rectangle {
+                x: i * width,
+                y: 0,
+                width,
+                height,
+            },
+        })
     }
 
-    fn update(&mut self, message: Msg) -> Command<Msg> {
+    fn update(&mut self, core: &Core, message: Msg) -> Command<Msg> {
         match message {
-            Msg::Closed(surface) => {
-                if self.popup == Some(surface) {
-                    self.popup = None;
-                    self.open_menu = None;
-                }
-                Command::none()
-            }
             Msg::StatusMenu((id, msg)) => match self.menus.get_mut(&id) {
-                Some(state) => state
-                    .update(msg)
-                    .map(move |msg| app::message::app(Msg::StatusMenu((id, msg)))),
+                Some(state) => state.update(msg).map(move |msg| Msg::StatusMenu((id, msg))),
                 None => Command::none(),
             },
             Msg::StatusNotifier(event) => match event {
@@ -108,20 +419,27 @@ impl cosmic::Application for App {
                 }
                 status_notifier_watcher::Event::Registered(name) => {
                     let (state, cmd) = status_menu::State::new(name);
-                    if let Some((id, m)) = self
+                    let id = match self
                         .menus
-                        .iter_mut()
+                        .iter()
                         .find(|(_, prev_state)| prev_state.name() == state.name())
                     {
-                        *m = state;
-                        let id = *id;
-                        return cmd.map(move |msg| app::message::app(Msg::StatusMenu((id, msg))));
-                    }
-                    let id = self.next_menu_id();
+                        // Its `Id` may have changed with it
+                        Some((id, _)) => {
+                            let id = *id;
+                            self.config_ids.remove(&id);
+                            id
+                        }
+                        None => self.next_menu_id(),
+                    };
+                    let config_id = self.unique_config_id(&state);
+                    self.config
+                        .add_item(&config_id, state.title(), self.config_helper.as_ref());
+                    self.config_ids.insert(id, config_id);
                     self.menus.insert(id, state);
                     Command::batch([
-                        self.resize_window(),
-                        cmd.map(move |msg| app::message::app(Msg::StatusMenu((id, msg)))),
+                        self.resize_window(core),
+                        cmd.map(move |msg| Msg::StatusMenu((id, msg))),
                     ])
                 }
                 status_notifier_watcher::Event::Unregistered(name) => {
@@ -129,163 +447,224 @@ impl cosmic::Application for App {
                     {
                         let id = *id;
                         self.menus.remove(&id);
-                        if self.open_menu == Some(id) {
-                            self.open_menu = None;
-                            if let Some(popup_id) = self.popup {
-                                return destroy_popup(popup_id);
-                            }
-                        }
+                        self.config_ids.remove(&id);
+                        self.scroll_remainders.remove(&id);
                     }
-                    self.resize_window()
+                    // The popup of its menu is closed by `popup_request`
+                    self.resize_window(core)
                 }
                 status_notifier_watcher::Event::Error(err) => {
-                    eprintln!("Status notifier error: {}", err);
+                    tracing::error!("Status notifier error: {}", err);
                     Command::none()
                 }
             },
             Msg::TogglePopup(id) => {
-                self.open_menu = if self.open_menu != Some(id) {
-                    Some(id)
-                } else {
-                    None
-                };
-                if self.open_menu.is_some() {
-                    self.menus[&id].opened();
-
-                    let mut cmds = Vec::new();
-                    if let Some(popup_id) = self.popup.take() {
-                        cmds.push(destroy_popup(popup_id));
-                    }
-                    let popup_id = self.next_popup_id();
-                    let mut popup_settings = self.core.applet.get_popup_settings(
-                        window::Id::MAIN,
-                        popup_id,
-                        None,
-                        None,
-                        None,
-                    );
-                    self.popup = Some(popup_id);
-                    let i = self.menus.keys().position(|&i| i == id).unwrap();
-                    if matches!(
-                        self.core.applet.anchor,
-                        PanelAnchor::Left | PanelAnchor::Right
-                    ) {
-                        let suggested_size = self.core.applet.suggested_size(false).1
-                            + 2 * self.core.applet.suggested_padding(false);
-                        popup_settings.positioner.anchor_rect.y = i as i32 * suggested_size as i32;
-                    } else {
-                        let suggested_size = self.core.applet.suggested_size(false).0
-                            + 2 * self.core.applet.suggested_padding(false);
-                        popup_settings.positioner.anchor_rect.x = i as i32 * suggested_size as i32;
-                    }
-                    cmds.push(get_popup(popup_settings));
-                    return Command::batch(cmds);
-                } else if let Some(popup_id) = self.popup {
-                    self.menus[&id].closed();
-
-                    return destroy_popup(popup_id);
+                // Closing it is asked for by `popup_request`
+                if self.open_menu != Some(id) {
+                    self.switch_popup(Popup::Menu(id));
                 }
                 Command::none()
             }
             Msg::Hovered(id) => {
-                let mut cmds = Vec::new();
-                if let Some(old_id) = self.open_menu.take() {
-                    if old_id != id {
-                        if let Some(popup_id) = self.popup.take() {
-                            cmds.push(destroy_popup(popup_id));
-                        }
-                        self.open_menu = Some(id);
-                    } else {
-                        self.open_menu = Some(old_id);
-                        return Command::none();
+                if self.scrubbing() && self.open_menu != Some(id) {
+                    self.switch_popup(Popup::Menu(id));
+                }
+                Command::none()
+            }
+            Msg::HoveredOverflow => {
+                if self.scrubbing() && self.overflow_page.is_none() {
+                    self.switch_popup(Popup::Overflow);
+                }
+                Command::none()
+            }
+            Msg::Activate(id) => match self.menus.get(&id) {
+                // Items that only provide a menu expect it to be shown on
+                // primary activation.
+                Some(menu) if menu.item_is_menu() => self.update(core, Msg::TogglePopup(id)),
+                Some(menu) => Command::perform(menu.activate(), move |res| {
+                    if let Err(err) = &res {
+                        tracing::debug!("Activate failed, showing menu instead: {}", err);
                     }
-                } else {
+                    Msg::Activated(id, res.is_ok())
+                }),
+                None => Command::none(),
+            },
+            Msg::Activated(id, success) => {
+                if !success && self.open_menu != Some(id) {
+                    self.switch_popup(Popup::Menu(id));
+                }
+                Command::none()
+            }
+            Msg::SecondaryActivate(id) => {
+                if let Some(menu) = self.menus.get(&id) {
+                    menu.secondary_activate();
+                }
+                Command::none()
+            }
+            Msg::Scroll(id, delta) => {
+                let Some(menu) = self.menus.get(&id) else {
                     return Command::none();
+                };
+                let (x, y) = match delta {
+                    ScrollDelta::Lines { x, y } => (x, y),
+                    ScrollDelta::Pixels { x, y } => (x / PIXELS_PER_LINE, y / PIXELS_PER_LINE),
+                };
+                // Accumulate fractional deltas, so smooth scrolling still
+                // produces discrete steps.
+                let remainder = self.scroll_remainders.entry(id).or_default();
+                let (x, y) = (remainder.0 + x, remainder.1 + y);
+                *remainder = (x.fract(), y.fract());
+                if y.trunc() != 0. {
+                    menu.scroll(y.trunc() as i32, "vertical");
                 }
-                let popup_id = self.next_popup_id();
-                let mut popup_settings = self.core.applet.get_popup_settings(
-                    window::Id::MAIN,
-                    popup_id,
-                    None,
-                    None,
-                    None,
-                );
-                self.popup = Some(popup_id);
-                let i = self.menus.keys().position(|&i| i == id).unwrap();
-                if matches!(
-                    self.core.applet.anchor,
-                    PanelAnchor::Left | PanelAnchor::Right
-                ) {
-                    let suggested_size = self.core.applet.suggested_size(false).1
-                        + 2 * self.core.applet.suggested_padding(false);
-                    popup_settings.positioner.anchor_rect.y = i as i32 * suggested_size as i32;
-                } else {
-                    let suggested_size = self.core.applet.suggested_size(false).0
-                        + 2 * self.core.applet.suggested_padding(false);
-                    popup_settings.positioner.anchor_rect.x = i as i32 * suggested_size as i32;
+                if x.trunc() != 0. {
+                    menu.scroll(x.trunc() as i32, "horizontal");
                 }
-                cmds.push(get_popup(popup_settings));
-                Command::batch(cmds)
+                Command::none()
+            }
+            Msg::ToggleOverflow => {
+                if self.overflow_page.is_none() {
+                    self.switch_popup(Popup::Overflow);
+                }
+                Command::none()
+            }
+            Msg::OverflowPage(page) => {
+                self.end_drag();
+                self.overflow_page = Some(page);
+                Command::none()
+            }
+            Msg::SetVisibility(i, visibility) => {
+                if let Some(visibility) = ItemVisibility::ALL.get(visibility) {
+                    self.config
+                        .set_visibility(i, *visibility, self.config_helper.as_ref());
+                }
+                self.resize_window(core)
+            }
+            Msg::DragStart(i) => {
+                self.dragging = Some(i);
+                Command::none()
+            }
+            Msg::DragOver(i) => {
+                if let Some(from) = self.dragging {
+                    self.config.move_item(from, i);
+                    self.dragging = Some(i);
+                }
+                Command::none()
+            }
+            Msg::DragEnd => {
+                self.end_drag();
+                Command::none()
+            }
+            Msg::ConfigChanged(config) => {
+                // Don't clobber the order of an item being dragged
+                if self.dragging.is_none() {
+                    self.config = config;
+                }
+                self.resize_window(core)
             }
         }
     }
 
-    fn subscription(&self) -> Subscription<Msg> {
+    fn subscription(&self, core: &Core) -> Subscription<Msg> {
         let mut subscriptions = Vec::new();
 
         subscriptions.push(status_notifier_watcher::subscription().map(Msg::StatusNotifier));
+        subscriptions.push(core.watch_config(config::APP_ID).map(|u| {
+            for err in u.errors {
+                tracing::error!(?err, "Error watching config");
+            }
+            Msg::ConfigChanged(u.config)
+        }));
 
         for (id, menu) in self.menus.iter() {
             subscriptions.push(menu.subscription().with(*id).map(Msg::StatusMenu));
         }
 
+        // A drag ends wherever it's dropped, not only on a row
+        if self.dragging.is_some() {
+            subscriptions.push(listen_with(|event, _status| match event {
+                Event::Mouse(
+                    mouse::Event::ButtonReleased(mouse::Button::Left) | mouse::Event::CursorLeft,
+                ) => Some(Msg::DragEnd),
+                _ => None,
+            }));
+        }
+
         iced::Subscription::batch(subscriptions)
     }
 
-    fn view(&self) -> cosmic::Element<'_, Msg> {
-        let children = self.menus.iter().map(|(id, menu)| {
-            mouse_area(
-                match menu.icon_pixmap() {
-                    Some(icon) if menu.icon_name() == "" => self
-                        .core
-                        .applet
-                        .icon_button_from_handle(icon.clone().symbolic(true)),
-                    _ => self.core.applet.icon_button(menu.icon_name()),
-                }
-                .on_press(Msg::TogglePopup(*id)),
-            )
-            .on_mouse_enter(Msg::Hovered(*id))
-            .into()
-        });
-        if matches!(
-            self.core.applet.anchor,
-            PanelAnchor::Left | PanelAnchor::Right
-        ) {
-            iced::widget::column(children).into()
-        } else {
-            iced::widget::row(children).into()
+    fn view<'a>(&'a self, core: &'a Core) -> cosmic::Element<'a, AppletMessage<Msg>> {
+        let mut children = self
+            .panel_menus()
+            .into_iter()
+            .filter_map(|id| {
+                let menu = self.menus.get(&id)?;
+                let button = MouseArea::new(
+                    icon_button_from_handle(
+                        core,
+                        menu.icon(core.applet.suggested_size(true).0),
+                        menu.title(),
+                    )
+                    .on_press(Msg::Activate(id)),
+                )
+                .on_right_press(Msg::TogglePopup(id))
+                .on_middle_press(Msg::SecondaryActivate(id))
+                .on_mouse_wheel(move |delta| Msg::Scroll(id, delta))
+                .on_mouse_enter(Msg::Hovered(id));
+                Some(match menu.tooltip_text() {
+                    // Don't cover the item's open menu
+                    Some(tooltip_text) if self.open_menu != Some(id) => tooltip(
+                        button,
+                        tooltip_text,
+                        match core.applet.anchor {
+                            PanelAnchor::Left => tooltip::Position::Right,
+                            PanelAnchor::Right => tooltip::Position::Left,
+                            PanelAnchor::Top => tooltip::Position::Bottom,
+                            PanelAnchor::Bottom => tooltip::Position::Top,
+                        },
+                    )
+                    .snap_within_viewport(false)
+                    .text_shaping(iced::widget::text::Shaping::Advanced)
+                    .into(),
+                    _ => button.into(),
+                })
+            })
+            .collect::<Vec<cosmic::Element<'_, Msg>>>();
+        if !self.menus.is_empty() {
+            children.push(
+                MouseArea::new(
+                    icon_button(core, "view-more-symbolic", fl!("more-items"))
+                        .on_press(Msg::ToggleOverflow),
+                )
+                .on_mouse_enter(Msg::HoveredOverflow)
+                .into(),
+            );
         }
+        let content: cosmic::Element<'_, Msg> =
+            if matches!(core.applet.anchor, PanelAnchor::Left | PanelAnchor::Right) {
+                iced::widget::column(children).into()
+            } else {
+                iced::widget::row(children).into()
+            };
+        content.map(AppletMessage::App)
     }
 
-    fn view_window(&self, _surface: window::Id) -> cosmic::Element<'_, Msg> {
-        match self.open_menu {
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> cosmic::Element<'a, AppletMessage<Msg>> {
+        let content = match self.open_menu {
             Some(id) => match self.menus.get(&id) {
-                Some(menu) => self
-                    .core
-                    .applet
-                    .popup_container(menu.popup_view().map(move |msg| Msg::StatusMenu((id, msg))))
-                    .into(),
-                None => unreachable!(),
+                Some(menu) => menu.popup_view().map(move |msg| Msg::StatusMenu((id, msg))),
+                None => iced::widget::text("").into(),
             },
-            None => iced::widget::text("").into(),
-        }
-    }
-
-    fn on_close_requested(&self, id: window::Id) -> Option<Msg> {
-        Some(Msg::Closed(id))
+            None => match self.overflow_page {
+                Some(OverflowPage::Items) => self.overflow_view(),
+                Some(OverflowPage::Settings) => self.settings_view(),
+                None => iced::widget::text("").into(),
+            },
+        };
+        content.map(AppletMessage::App)
     }
 }
 
 pub fn main() -> iced::Result {
-    cosmic::applet::run::<App>(true, ())
+    cosmic_applet_helper::run_applet::<App>()
 }
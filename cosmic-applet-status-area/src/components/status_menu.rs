@@ -2,18 +2,21 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use cosmic::{applet::menu_button, iced, widget::icon};
+use cosmic_applet_helper::icon_handle;
 
-use crate::subscriptions::status_notifier_item::{Layout, StatusNotifierItem};
+use crate::subscriptions::status_notifier_item::{Layout, StatusNotifierItem, ToolTip};
 
 #[derive(Clone, Debug)]
 pub enum Msg {
     Layout(Result<Layout, String>),
+    ToolTip(Option<ToolTip>),
     Click(i32, bool),
 }
 
 pub struct State {
     item: StatusNotifierItem,
     layout: Option<Layout>,
+    tooltip: Option<ToolTip>,
     expanded: Option<i32>,
 }
 
@@ -23,6 +26,7 @@ impl State {
             Self {
                 item,
                 layout: None,
+                tooltip: None,
                 expanded: None,
             },
             iced::Command::none(),
@@ -36,10 +40,14 @@ impl State {
                     Ok(layout) => {
                         self.layout = Some(layout);
                     }
-                    Err(err) => eprintln!("Error getting layout from icon: {}", err),
+                    Err(err) => tracing::error!("Error getting layout from icon: {}", err),
                 }
                 iced::Command::none()
             }
+            Msg::ToolTip(tooltip) => {
+                self.tooltip = tooltip;
+                iced::Command::none()
+            }
             Msg::Click(id, is_submenu) => {
                 let menu_proxy = self.item.menu_proxy().clone();
                 tokio::spawn(async move {
@@ -63,12 +71,65 @@ impl State {
         self.item.name()
     }
 
-    pub fn icon_name(&self) -> &str {
-        self.item.icon_name()
+    pub fn id(&self) -> &str {
+        self.item.id()
+    }
+
+    pub fn title(&self) -> &str {
+        self.item.title()
+    }
+
+    /// The item's pixmap if it has no icon name, or else its named icon,
+    /// looked up in the item's own icon directory before the icon theme.
+    pub fn icon(&self, size: u16) -> icon::Handle {
+        let name = self.item.icon_name();
+        match (self.item.icon_pixmap(), self.item.icon_path()) {
+            (Some(icon), _) if name.is_empty() => icon.clone().symbolic(true),
+            (_, Some(path)) => {
+                let mut handle = icon::from_path(path.to_path_buf());
+                handle.symbolic = name.ends_with("-symbolic");
+                handle
+            }
+            _ => icon_handle(name, size, true),
+        }
     }
 
-    pub fn icon_pixmap(&self) -> Option<&icon::Handle> {
-        self.item.icon_pixmap()
+    /// Plain text for the item's tooltip, if it has a non-empty one.
+    pub fn tooltip_text(&self) -> Option<String> {
+        let tooltip = self.tooltip.as_ref()?;
+        let description = strip_markup(tooltip.description());
+        let text = match (tooltip.title().trim(), description.trim()) {
+            ("", "") => return None,
+            (title, "") => title.to_string(),
+            ("", description) => description.to_string(),
+            (title, description) => format!("{}\n{}", title, description),
+        };
+        Some(text)
+    }
+
+    pub fn item_is_menu(&self) -> bool {
+        self.item.item_is_menu()
+    }
+
+    /// Calls `Activate` on the item. Resolves to an error if the item doesn't
+    /// implement it, in which case the menu should be shown instead.
+    pub fn activate(&self) -> impl std::future::Future<Output = zbus::Result<()>> + Send + 'static {
+        let item_proxy = self.item.item_proxy().clone();
+        async move { item_proxy.activate(0, 0).await }
+    }
+
+    pub fn secondary_activate(&self) {
+        let item_proxy = self.item.item_proxy().clone();
+        tokio::spawn(async move {
+            let _ = item_proxy.secondary_activate(0, 0).await;
+        });
+    }
+
+    pub fn scroll(&self, delta: i32, orientation: &'static str) {
+        let item_proxy = self.item.item_proxy().clone();
+        tokio::spawn(async move {
+            let _ = item_proxy.scroll(delta, orientation).await;
+        });
     }
 
     pub fn popup_view(&self) -> cosmic::Element<Msg> {
@@ -80,7 +141,10 @@ impl State {
     }
 
     pub fn subscription(&self) -> iced::Subscription<Msg> {
-        self.item.layout_subscription().map(Msg::Layout)
+        iced::Subscription::batch([
+            self.item.layout_subscription().map(Msg::Layout),
+            self.item.tooltip_subscription().map(Msg::ToolTip),
+        ])
     }
 
     pub fn opened(&self) {
@@ -99,6 +163,38 @@ impl State {
     }
 }
 
+/// Strips the HTML subset allowed in tooltip descriptions, since they're
+/// displayed as plain text.
+fn strip_markup(markup: &str) -> String {
+    let mut text = String::with_capacity(markup.len());
+    let mut tag: Option<String> = None;
+    for c in markup.chars() {
+        match tag.as_mut() {
+            None if c == '<' => tag = Some(String::new()),
+            None => text.push(c),
+            Some(name) if c == '>' => {
+                let name = name
+                    .trim()
+                    .trim_end_matches('/')
+                    .trim()
+                    .to_ascii_lowercase();
+                if name == "br" || name == "/p" {
+                    text.push('\n');
+                }
+                tag = None;
+            }
+            Some(name) => name.push(c),
+        }
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
 fn layout_view(layout: &Layout, expanded: Option<i32>) -> cosmic::Element<Msg> {
     iced::widget::column(layout.children().iter().filter_map(|i| {
         if !i.visible() {
@@ -125,28 +221,28 @@ fn layout_view(layout: &Layout, expanded: Option<i32>) -> cosmic::Element<Msg> {
 
             let mut children: Vec<cosmic::Element<_>> = vec![text.into()];
             if is_submenu {
-                let icon = cosmic::widget::icon::from_name(if is_expanded {
-                    "go-down-symbolic"
-                } else {
-                    "go-next-symbolic"
-                })
-                .size(14)
-                .symbolic(true);
+                let icon = cosmic::widget::icon::icon(icon_handle(
+                    if is_expanded {
+                        "go-down-symbolic"
+                    } else {
+                        "go-next-symbolic"
+                    },
+                    14,
+                    true,
+                ))
+                .size(14);
                 children.push(icon.into());
             }
             if let Some(icon_data) = i.icon_data() {
                 let handle = iced::widget::image::Handle::from_memory(icon_data.to_vec());
                 children.insert(0, iced::widget::Image::new(handle).into());
             } else if let Some(icon_name) = i.icon_name() {
-                let icon = cosmic::widget::icon::from_name(icon_name)
-                    .size(14)
-                    .symbolic(true);
+                let icon = cosmic::widget::icon::icon(icon_handle(icon_name, 14, true)).size(14);
                 children.insert(0, icon.into());
             }
             if i.toggle_state() == Some(1) {
-                let icon = cosmic::widget::icon::from_name("emblem-ok-symbolic")
-                    .size(14)
-                    .symbolic(true);
+                let icon = cosmic::widget::icon::icon(icon_handle("emblem-ok-symbolic", 14, true))
+                    .size(14);
                 children.push(icon.into());
             }
             let button = row_button(children).on_press(Msg::Click(i.id(), is_submenu));
@@ -0,0 +1,94 @@
+// Copyright 2023 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::cosmic_config::{
+    self, cosmic_config_derive::CosmicConfigEntry, Config, CosmicConfigEntry,
+};
+use serde::{Deserialize, Serialize};
+
+pub const APP_ID: &str = "com.system76.CosmicAppletStatusArea";
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Default, PartialEq, Eq)]
+pub enum ItemVisibility {
+    #[default]
+    AlwaysShow,
+    Overflow,
+    Never,
+}
+
+impl ItemVisibility {
+    pub const ALL: [Self; 3] = [Self::AlwaysShow, Self::Overflow, Self::Never];
+}
+
+/// Preferences for a tray item, keyed by its `Id` property, or with its title
+/// added if another item has the same `Id`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ItemConfig {
+    pub id: String,
+    pub title: String,
+    pub visibility: ItemVisibility,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq, CosmicConfigEntry)]
+#[version = 1]
+pub struct StatusAreaConfig {
+    /// Known items, in display order.
+    pub items: Vec<ItemConfig>,
+}
+
+impl StatusAreaConfig {
+    pub fn item(&self, id: &str) -> Option<&ItemConfig> {
+        self.items.iter().find(|item| item.id == id)
+    }
+
+    pub fn position(&self, id: &str) -> Option<usize> {
+        self.items.iter().position(|item| item.id == id)
+    }
+
+    pub fn visibility(&self, id: &str) -> ItemVisibility {
+        self.item(id)
+            .map(|item| item.visibility)
+            .unwrap_or_default()
+    }
+
+    /// Remembers an item that hasn't been seen before, placing it last.
+    pub fn add_item(&mut self, id: &str, title: &str, config: Option<&Config>) {
+        if self.item(id).is_none() {
+            self.items.push(ItemConfig {
+                id: id.to_string(),
+                title: title.to_string(),
+                visibility: ItemVisibility::default(),
+            });
+            self.write(config);
+        }
+    }
+
+    pub fn set_visibility(
+        &mut self,
+        index: usize,
+        visibility: ItemVisibility,
+        config: Option<&Config>,
+    ) {
+        if let Some(item) = self.items.get_mut(index) {
+            item.visibility = visibility;
+            self.write(config);
+        }
+    }
+
+    /// Reorders an item. Not persisted until [`Self::write`] is called, so a
+    /// drag only writes the config once it's dropped.
+    pub fn move_item(&mut self, from: usize, to: usize) {
+        if from != to && from < self.items.len() && to < self.items.len() {
+            let item = self.items.remove(from);
+            self.items.insert(to, item);
+        }
+    }
+
+    pub fn write(&self, config: Option<&Config>) {
+        if let Some(config) = config {
+            if let Err(err) = self.write_entry(config) {
+                tracing::error!(?err, "Error writing config");
+            }
+        }
+    }
+}
@@ -2,8 +2,12 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 mod components;
+mod config;
+mod localize;
 mod subscriptions;
 
 pub fn run() -> cosmic::iced::Result {
+    localize::localize();
+
     components::app::main()
 }
@@ -1,5 +1,6 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
+mod sandbox;
 pub mod status_notifier_item;
 pub mod status_notifier_watcher;
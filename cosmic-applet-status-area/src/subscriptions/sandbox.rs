@@ -0,0 +1,63 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! The Flatpak sandbox an item's app runs in, if any.
+//!
+//! Sandboxed apps name their icon directories by paths inside the sandbox,
+//! like `/app/share/icons`, which are reached through the app's
+//! `/proc/<pid>/root`. The app id, from the `.flatpak-info` Flatpak puts in
+//! every sandbox, is stable between runs, unlike the bus names the app's items
+//! are found at.
+
+use std::path::{Path, PathBuf};
+
+use zbus::{fdo::DBusProxy, names::BusName};
+
+#[derive(Clone, Debug)]
+pub struct Sandbox {
+    root: PathBuf,
+    app_id: String,
+}
+
+impl Sandbox {
+    /// The sandbox of the process owning `bus_name`, if it's sandboxed.
+    pub async fn of(connection: &zbus::Connection, bus_name: &str) -> Option<Self> {
+        let bus_name = BusName::try_from(bus_name).ok()?;
+        let pid = DBusProxy::new(connection)
+            .await
+            .ok()?
+            .get_connection_unix_process_id(bus_name)
+            .await
+            .ok()?;
+        let root = PathBuf::from(format!("/proc/{pid}/root"));
+        let info = std::fs::read_to_string(root.join(".flatpak-info")).ok()?;
+        Some(Self {
+            app_id: app_id(&info)?,
+            root,
+        })
+    }
+
+    pub fn app_id(&self) -> &str {
+        &self.app_id
+    }
+
+    /// Where `path`, from inside the sandbox, is reached from outside of it.
+    pub fn path(&self, path: &Path) -> PathBuf {
+        self.root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+}
+
+/// The `name` of the `[Application]` group of a `.flatpak-info`.
+fn app_id(info: &str) -> Option<String> {
+    let mut in_application = false;
+    for line in info.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_application = line == "[Application]";
+        } else if in_application {
+            if let Some(("name", value)) = line.split_once('=').map(|(k, v)| (k.trim(), v)) {
+                return Some(value.trim().to_string());
+            }
+        }
+    }
+    None
+}
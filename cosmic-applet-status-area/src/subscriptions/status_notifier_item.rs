@@ -1,17 +1,26 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
+use std::path::{Path, PathBuf};
+
 use cosmic::{iced, widget::icon};
 use futures::{FutureExt, StreamExt};
 use zbus::zvariant::{self, OwnedValue};
 
+use super::sandbox::Sandbox;
+
 #[derive(Clone, Debug)]
 pub struct StatusNotifierItem {
     name: String,
+    id: String,
+    title: String,
     icon_name: String,
+    /// The named icon found in the item's `IconThemePath`.
+    icon_path: Option<PathBuf>,
     // TODO Handle icon with multiple sizes?
     icon_pixmap: Option<icon::Handle>,
-    _item_proxy: StatusNotifierItemProxy<'static>,
+    item_is_menu: bool,
+    item_proxy: StatusNotifierItemProxy<'static>,
     menu_proxy: DBusMenuProxy<'static>,
 }
 
@@ -22,6 +31,46 @@ pub struct Icon {
     bytes: Vec<u8>,
 }
 
+#[derive(Clone, Debug)]
+pub struct ToolTip {
+    title: String,
+    description: String,
+}
+
+impl TryFrom<OwnedValue> for ToolTip {
+    type Error = zvariant::Error;
+
+    /// Reads the `(sa(iiay)ss)` of the property. Its icon name and pixmaps
+    /// come first, and are skipped, since tooltips are text-only.
+    fn try_from(value: OwnedValue) -> Result<Self, Self::Error> {
+        let mut fields = zvariant::Structure::try_from(zvariant::Value::from(value))?
+            .into_fields()
+            .into_iter()
+            .skip(2);
+        let mut next = || {
+            fields
+                .next()
+                .ok_or(zvariant::Error::IncorrectType)
+                .and_then(String::try_from)
+        };
+        Ok(Self {
+            title: next()?,
+            description: next()?,
+        })
+    }
+}
+
+impl ToolTip {
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// The description may contain a subset of HTML markup.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
 impl StatusNotifierItem {
     pub async fn new(connection: &zbus::Connection, name: String) -> zbus::Result<Self> {
         let (dest, path) = if let Some(idx) = name.find('/') {
@@ -36,7 +85,33 @@ impl StatusNotifierItem {
             .build()
             .await?;
 
-        let icon_name = item_proxy.icon_name().await.unwrap_or_default();
+        let sandbox = Sandbox::of(connection, dest).await;
+
+        // Bus names aren't stable between runs, so prefer the application
+        // provided id for identifying the item in the config, or else the
+        // Flatpak app id.
+        let id = match item_proxy.id().await {
+            Ok(id) if !id.is_empty() => id,
+            _ => match &sandbox {
+                Some(sandbox) => sandbox.app_id().to_string(),
+                None => dest.to_string(),
+            },
+        };
+        let title = match item_proxy.title().await {
+            Ok(title) if !title.is_empty() => title,
+            _ => id.clone(),
+        };
+        let mut icon_name = item_proxy.icon_name().await.unwrap_or_default();
+        let icon_path = match item_proxy.icon_theme_path().await {
+            Ok(theme_path) if !theme_path.is_empty() && !icon_name.is_empty() => {
+                let theme_path = Path::new(&theme_path);
+                find_icon(theme_path, &icon_name).or_else(|| {
+                    let theme_path = sandbox.as_ref()?.path(theme_path);
+                    find_icon(&theme_path, &icon_name)
+                })
+            }
+            _ => None,
+        };
         let icon_pixmap = item_proxy
             .icon_pixmap()
             .await
@@ -50,6 +125,14 @@ impl StatusNotifierItem {
                 }
                 icon::from_raster_pixels(i.width as u32, i.height as u32, i.bytes)
             });
+        // Flatpak exports the app's icon, named after it, to the host
+        if icon_name.is_empty() && icon_pixmap.is_none() {
+            if let Some(sandbox) = &sandbox {
+                icon_name = sandbox.app_id().to_string();
+            }
+        }
+
+        let item_is_menu = item_proxy.item_is_menu().await.unwrap_or(false);
 
         let menu_path = item_proxy.menu().await?;
         let menu_proxy = DBusMenuProxy::builder(connection)
@@ -60,9 +143,13 @@ impl StatusNotifierItem {
 
         Ok(Self {
             name,
+            id,
+            title,
             icon_name,
+            icon_path,
             icon_pixmap,
-            _item_proxy: item_proxy,
+            item_is_menu,
+            item_proxy,
             menu_proxy,
         })
     }
@@ -71,14 +158,31 @@ impl StatusNotifierItem {
         &self.name
     }
 
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
     pub fn icon_name(&self) -> &str {
         &self.icon_name
     }
 
+    pub fn icon_path(&self) -> Option<&Path> {
+        self.icon_path.as_deref()
+    }
+
     pub fn icon_pixmap(&self) -> Option<&icon::Handle> {
         self.icon_pixmap.as_ref()
     }
 
+    /// Whether the item only supports showing its menu, rather than `Activate`.
+    pub fn item_is_menu(&self) -> bool {
+        self.item_is_menu
+    }
+
     // TODO: Only fetch changed part of layout, if that's any faster
     pub fn layout_subscription(&self) -> iced::Subscription<Result<Layout, String>> {
         let menu_proxy = self.menu_proxy.clone();
@@ -94,11 +198,89 @@ impl StatusNotifierItem {
         )
     }
 
+    pub fn tooltip_subscription(&self) -> iced::Subscription<Option<ToolTip>> {
+        let item_proxy = self.item_proxy.clone();
+        iced::subscription::run_with_id(
+            format!("status-notifier-item-tooltip-{}", &self.name),
+            async move {
+                let initial = futures::stream::once(get_tooltip(item_proxy.clone()));
+                let updates = match item_proxy.receive_new_tool_tip().await {
+                    Ok(stream) => stream
+                        .then(move |_| get_tooltip(item_proxy.clone()))
+                        .boxed(),
+                    Err(_) => futures::stream::empty().boxed(),
+                };
+                initial.chain(updates)
+            }
+            .flatten_stream(),
+        )
+    }
+
+    pub fn item_proxy(&self) -> &StatusNotifierItemProxy<'static> {
+        &self.item_proxy
+    }
+
     pub fn menu_proxy(&self) -> &DBusMenuProxy<'static> {
         &self.menu_proxy
     }
 }
 
+/// Looks for the icon `name` in an app provided directory, as libappindicator
+/// apps set for their own icons. The directory either holds the icons
+/// directly, or is laid out like an icon theme, in which case the scalable or
+/// largest one is used.
+fn find_icon(dir: &Path, name: &str) -> Option<PathBuf> {
+    const EXTENSIONS: [&str; 3] = ["svg", "png", "xpm"];
+
+    let in_dir = |dir: &Path| {
+        EXTENSIONS
+            .iter()
+            .map(|ext| dir.join(format!("{name}.{ext}")))
+            .find(|path| path.is_file())
+    };
+    let subdirs = |dir: &Path| {
+        std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect::<Vec<_>>()
+    };
+    // "scalable" first, then sizes like "48x48" or "48x48@2" from large to small.
+    let size = |path: &Path| -> u32 {
+        let size_dir = path
+            .parent()
+            .and_then(Path::parent)
+            .and_then(Path::file_name)
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        if size_dir == "scalable" {
+            return u32::MAX;
+        }
+        size_dir
+            .split(['x', '@'])
+            .next()
+            .and_then(|size| size.parse().ok())
+            .unwrap_or_default()
+    };
+
+    if let Some(path) = in_dir(dir) {
+        return Some(path);
+    }
+    // <theme>/<size>/<context>/<name>, or <size>/<context>/<name> without the
+    // theme directory.
+    let mut size_dirs = subdirs(dir);
+    for theme in size_dirs.clone() {
+        size_dirs.extend(subdirs(&theme));
+    }
+    size_dirs
+        .iter()
+        .flat_map(|size_dir| subdirs(size_dir))
+        .filter_map(|context| in_dir(&context))
+        .max_by_key(|path| size(path))
+}
+
 async fn get_layout(menu_proxy: DBusMenuProxy<'static>) -> Result<Layout, String> {
     match menu_proxy.get_layout(0, -1, &[]).await {
         Ok((_, layout)) => Ok(layout),
@@ -106,8 +288,24 @@ async fn get_layout(menu_proxy: DBusMenuProxy<'static>) -> Result<Layout, String
     }
 }
 
+async fn get_tooltip(item_proxy: StatusNotifierItemProxy<'static>) -> Option<ToolTip> {
+    item_proxy.tool_tip().await.ok()
+}
+
 #[zbus::proxy(interface = "org.kde.StatusNotifierItem")]
 trait StatusNotifierItem {
+    fn activate(&self, x: i32, y: i32) -> zbus::Result<()>;
+
+    fn secondary_activate(&self, x: i32, y: i32) -> zbus::Result<()>;
+
+    fn scroll(&self, delta: i32, orientation: &str) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn id(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn title(&self) -> zbus::Result<String>;
+
     #[zbus(property)]
     fn icon_name(&self) -> zbus::Result<String>;
 
@@ -115,6 +313,21 @@ trait StatusNotifierItem {
     #[zbus(property)]
     fn icon_pixmap(&self) -> zbus::Result<Vec<Icon>>;
 
+    // Not part of the specification, but set by libappindicator
+    #[zbus(property)]
+    fn icon_theme_path(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn item_is_menu(&self) -> zbus::Result<bool>;
+
+    // Changes are announced with `NewToolTip` rather than `PropertiesChanged`,
+    // so this can't be cached.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn tool_tip(&self) -> zbus::Result<ToolTip>;
+
+    #[zbus(signal)]
+    fn new_tool_tip(&self) -> zbus::Result<()>;
+
     #[zbus(property)]
     fn menu(&self) -> zbus::Result<zvariant::OwnedObjectPath>;
 }
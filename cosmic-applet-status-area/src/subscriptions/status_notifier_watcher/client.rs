@@ -1,15 +1,13 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
-use futures::{Stream, StreamExt};
-use std::pin::Pin;
+use futures::{channel::mpsc, SinkExt, StreamExt};
+use std::pin::pin;
+use zbus::fdo::DBusProxy;
 
-use super::Event;
+use super::{Event, NAME};
 use crate::subscriptions::status_notifier_item::StatusNotifierItem;
 
-// TODO: Don't use trait object
-pub type EventStream = Pin<Box<dyn Stream<Item = Event> + Send>>;
-
 #[zbus::proxy(
     interface = "org.kde.StatusNotifierWatcher",
     default_service = "org.kde.StatusNotifierWatcher",
@@ -18,6 +16,8 @@ pub type EventStream = Pin<Box<dyn Stream<Item = Event> + Send>>;
 trait StatusNotifierWatcher {
     fn register_status_notifier_host(&self, name: &str) -> zbus::Result<()>;
 
+    fn register_status_notifier_item(&self, name: &str) -> zbus::Result<()>;
+
     #[zbus(property)]
     fn registered_status_notifier_items(&self) -> zbus::Result<Vec<String>>;
 
@@ -28,47 +28,110 @@ trait StatusNotifierWatcher {
     fn status_notifier_item_unregistered(&self, name: &str) -> zbus::Result<()>;
 }
 
-pub async fn watch(connection: &zbus::Connection) -> zbus::Result<EventStream> {
-    let watcher = StatusNotifierWatcherProxy::new(connection).await?;
-
-    let name = connection.unique_name().unwrap().as_str();
-    if let Err(err) = watcher.register_status_notifier_host(name).await {
-        eprintln!("Failed to register status notifier host: {}", err);
-    }
+enum Change {
+    Registered(zbus::Result<String>),
+    Unregistered(zbus::Result<String>),
+    /// Another process took over the watcher's name.
+    WatcherReplaced,
+}
 
-    let connection_clone = connection.clone();
-    let registered_stream = watcher
+/// Shows the items of the watcher, whichever process it is, and follows it
+/// when it's replaced, like after a crash.
+pub async fn watch(
+    connection: &zbus::Connection,
+    output: &mut mpsc::Sender<Event>,
+) -> zbus::Result<()> {
+    let watcher = StatusNotifierWatcherProxy::builder(connection)
+        .cache_properties(zbus::CacheProperties::No)
+        .build()
+        .await?;
+    let dbus_proxy = DBusProxy::new(connection).await?;
+    // Until a queued watcher, maybe this applet's own, takes over, there's
+    // no new owner
+    let replaced = dbus_proxy
+        .receive_name_owner_changed_with_args(&[(0, NAME.as_str())])
+        .await?
+        .filter_map(|evt| async move {
+            let args = evt.args().ok()?;
+            args.new_owner.is_some().then_some(Change::WatcherReplaced)
+        });
+    let registered = watcher
         .receive_status_notifier_item_registered()
         .await?
-        .then(move |evt| Box::pin(item_registered(connection_clone.clone(), evt)));
-    let unregistered_stream = watcher
+        .map(|evt| Change::Registered(evt.args().map(|args| args.name.to_string())));
+    let unregistered = watcher
         .receive_status_notifier_item_unregistered()
         .await?
-        .map(|evt| match evt.args() {
-            Ok(args) => Event::Unregistered(args.name.to_string()),
-            Err(err) => Event::Error(err.to_string()),
-        });
+        .map(|evt| Change::Unregistered(evt.args().map(|args| args.name.to_string())));
+    let mut changes = pin!(futures::stream_select!(replaced, registered, unregistered));
 
-    let items = watcher.registered_status_notifier_items().await?;
-    let connection = connection.clone();
-    let items_stream = futures::stream::iter(items.into_iter())
-        .then(move |name| status_notifier_item(connection.clone(), name));
+    // The items shown, for handing them to a new watcher
+    let mut items = Vec::new();
+    sync(connection, &watcher, &mut items, output).await?;
 
-    Ok(Box::pin(items_stream.chain(futures::stream_select!(
-        registered_stream,
-        unregistered_stream
-    ))))
+    while let Some(change) = changes.next().await {
+        match change {
+            Change::Registered(Ok(name)) => {
+                if !items.contains(&name) {
+                    items.push(name.clone());
+                }
+                let event = status_notifier_item(connection, name).await;
+                _ = output.send(event).await;
+            }
+            Change::Unregistered(Ok(name)) => {
+                items.retain(|item| *item != name);
+                _ = output.send(Event::Unregistered(name)).await;
+            }
+            Change::Registered(Err(err)) | Change::Unregistered(Err(err)) => {
+                _ = output.send(Event::Error(err.to_string())).await;
+            }
+            Change::WatcherReplaced => {
+                // Items only register again when they notice the watcher
+                // changed, if at all
+                for name in &items {
+                    if let Err(err) = watcher.register_status_notifier_item(name).await {
+                        tracing::error!(
+                            "Failed to register {} with the new watcher: {}",
+                            name,
+                            err
+                        );
+                    }
+                }
+                sync(connection, &watcher, &mut items, output).await?;
+            }
+        }
+    }
+    Ok(())
 }
 
-async fn item_registered(connection: zbus::Connection, evt: StatusNotifierItemRegistered) -> Event {
-    match evt.args() {
-        Ok(args) => status_notifier_item(connection, args.name.to_string()).await,
-        Err(err) => Event::Error(err.to_string()),
+/// Registers as a host with the watcher and shows its items.
+async fn sync(
+    connection: &zbus::Connection,
+    watcher: &StatusNotifierWatcherProxy<'_>,
+    items: &mut Vec<String>,
+    output: &mut mpsc::Sender<Event>,
+) -> zbus::Result<()> {
+    if let Some(name) = connection.unique_name() {
+        if let Err(err) = watcher.register_status_notifier_host(name.as_str()).await {
+            tracing::error!("Failed to register status notifier host: {}", err);
+        }
+    }
+
+    let registered = watcher.registered_status_notifier_items().await?;
+    for name in items.iter().filter(|name| !registered.contains(name)) {
+        _ = output.send(Event::Unregistered(name.clone())).await;
+    }
+    for name in &registered {
+        _ = output
+            .send(status_notifier_item(connection, name.clone()).await)
+            .await;
     }
+    *items = registered;
+    Ok(())
 }
 
-async fn status_notifier_item(connection: zbus::Connection, name: String) -> Event {
-    match StatusNotifierItem::new(&connection, name).await {
+async fn status_notifier_item(connection: &zbus::Connection, name: String) -> Event {
+    match StatusNotifierItem::new(connection, name).await {
         Ok(item) => Event::Registered(item),
         Err(err) => Event::Error(err.to_string()),
     }
@@ -4,13 +4,17 @@
 // TODO: Both this and server proxy could emit same events, have way to generate stream from either?
 
 use cosmic::iced;
-use futures::StreamExt;
+use futures::{channel::mpsc, SinkExt};
+use zbus::names::WellKnownName;
 
 use crate::subscriptions::status_notifier_item::StatusNotifierItem;
 
 mod client;
 mod server;
 
+const NAME: WellKnownName =
+    WellKnownName::from_static_str_unchecked("org.kde.StatusNotifierWatcher");
+
 #[derive(Clone, Debug)]
 pub enum Event {
     Connected(zbus::Connection),
@@ -19,43 +23,24 @@ pub enum Event {
     Error(String), // XXX
 }
 
-enum State {
-    NotConnected,
-    Connected(client::EventStream),
-    Failed,
-}
-
 pub fn subscription() -> iced::Subscription<Event> {
-    iced::subscription::unfold(
-        "status-notifier-watcher",
-        State::NotConnected,
-        |state| async move {
-            match state {
-                State::NotConnected => match connect().await {
-                    Ok((connection, stream)) => {
-                        (Event::Connected(connection), State::Connected(stream))
-                    }
-                    Err(err) => (Event::Error(err.to_string()), State::Failed),
-                },
-                State::Connected(mut stream) => match stream.next().await {
-                    Some(event) => (event, State::Connected(stream)),
-                    None => iced::futures::future::pending().await,
-                },
-                State::Failed => iced::futures::future::pending().await,
-            }
-        },
-    )
+    iced::subscription::channel("status-notifier-watcher", 50, |mut output| async move {
+        if let Err(err) = run(&mut output).await {
+            _ = output.send(Event::Error(err.to_string())).await;
+        }
+        iced::futures::future::pending().await
+    })
 }
 
-async fn connect() -> zbus::Result<(zbus::Connection, client::EventStream)> {
+async fn run(output: &mut mpsc::Sender<Event>) -> zbus::Result<()> {
     // Connect to session dbus socket
     let connection = zbus::Connection::session().await?;
 
-    // Start `StatusNotifierWatcher` service, if there isn't one running already
+    // Start `StatusNotifierWatcher` service, or queue to take over if
+    // another one stops
     server::create_service(&connection).await?;
+    _ = output.send(Event::Connected(connection.clone())).await;
 
-    // Connect client and listen for registered/unregistered
-    let stream = client::watch(&connection).await?;
-
-    Ok((connection, stream))
+    // Listen for registered/unregistered, from whichever watcher is running
+    client::watch(&connection, output).await
 }
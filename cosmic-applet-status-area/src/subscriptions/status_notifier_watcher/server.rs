@@ -8,17 +8,20 @@
 use futures::prelude::*;
 use zbus::{
     fdo::{DBusProxy, RequestNameFlags, RequestNameReply},
-    names::{BusName, UniqueName, WellKnownName},
+    names::{BusName, UniqueName},
     MessageHeader, Result, SignalContext,
 };
 
-const NAME: WellKnownName =
-    WellKnownName::from_static_str_unchecked("org.kde.StatusNotifierWatcher");
+use super::NAME;
+
 const OBJECT_PATH: &str = "/StatusNotifierWatcher";
+/// Where items are by default.
+const ITEM_PATH: &str = "/StatusNotifierItem";
 
 #[derive(Default)]
 struct StatusNotifierWatcher {
     items: Vec<(UniqueName<'static>, String)>,
+    hosts: Vec<(UniqueName<'static>, String)>,
 }
 
 #[zbus::interface(name = "org.kde.StatusNotifierWatcher")]
@@ -27,23 +30,62 @@ impl StatusNotifierWatcher {
         &mut self,
         service: &str,
         #[zbus(header)] hdr: MessageHeader<'_>,
+        #[zbus(connection)] connection: &zbus::Connection,
         #[zbus(signal_context)] ctxt: SignalContext<'_>,
     ) {
-        let sender = hdr.sender().unwrap();
-        let service = if service.starts_with('/') {
-            format!("{}{}", sender, service)
+        let Some(sender) = hdr.sender() else {
+            return;
+        };
+        let (owner, service) = if service.starts_with('/') {
+            (sender.to_owned(), format!("{}{}", sender, service))
         } else {
-            service.to_string()
+            // Hosts hand their items over to a new watcher, so the item
+            // isn't necessarily the sender
+            let bus_name = service.split('/').next().unwrap_or(service);
+            let own = connection
+                .unique_name()
+                .is_some_and(|name| name.as_str() == sender.as_str());
+            let handover = own || self.hosts.iter().any(|(host, _)| host == sender);
+            match name_owner(connection, bus_name).await {
+                Some(owner) if owner == *sender || handover => (owner, service.to_string()),
+                // Flatpak apps have a PID namespace of their own, so those
+                // naming their item `org.kde.StatusNotifierItem-<pid>-<n>`
+                // pick the same name, which only the first one gets. The
+                // others' items are still on their own connection.
+                _ if !handover => (sender.to_owned(), format!("{}{}", sender, ITEM_PATH)),
+                _ => return,
+            }
         };
-        Self::status_notifier_item_registered(&ctxt, &service)
-            .await
-            .unwrap();
+        if self.items.iter().any(|(_, x)| *x == service) {
+            return;
+        }
+        if let Err(err) = Self::status_notifier_item_registered(&ctxt, &service).await {
+            tracing::error!("Failed to emit item registered: {}", err);
+        }
 
-        self.items.push((sender.to_owned(), service));
+        self.items.push((owner, service));
     }
 
-    fn register_status_notifier_host(&self, _service: &str) {
-        // XXX emit registed/unregistered
+    async fn register_status_notifier_host(
+        &mut self,
+        service: &str,
+        #[zbus(header)] hdr: MessageHeader<'_>,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) {
+        let Some(sender) = hdr.sender() else {
+            return;
+        };
+        if self.hosts.iter().any(|(_, x)| x == service) {
+            return;
+        }
+        let first = self.hosts.is_empty();
+        self.hosts.push((sender.to_owned(), service.to_string()));
+        if let Err(err) = Self::status_notifier_host_registered(&ctxt).await {
+            tracing::error!("Failed to emit host registered: {}", err);
+        }
+        if first {
+            _ = self.is_status_notifier_host_registered_changed(&ctxt).await;
+        }
     }
 
     #[zbus(property)]
@@ -53,7 +95,7 @@ impl StatusNotifierWatcher {
 
     #[zbus(property)]
     fn is_status_notifier_host_registered(&self) -> bool {
-        true
+        !self.hosts.is_empty()
     }
 
     #[zbus(property)]
@@ -91,9 +133,11 @@ pub async fn create_service(connection: &zbus::Connection) -> zbus::Result<()> {
     let dbus_proxy = DBusProxy::new(connection).await?;
     let mut name_owner_changed_stream = dbus_proxy.receive_name_owner_changed().await?;
 
+    // Without `DoNotQueue`, the name comes back to this applet once another
+    // watcher replacing it, or queued before it, goes away
     let flags = RequestNameFlags::AllowReplacement.into();
     if dbus_proxy.request_name(NAME.as_ref(), flags).await? == RequestNameReply::InQueue {
-        eprintln!("Bus name '{}' already owned", NAME);
+        tracing::warn!("Bus name '{}' already owned", NAME);
     }
 
     let connection = connection.clone();
@@ -109,24 +153,42 @@ pub async fn create_service(connection: &zbus::Connection) -> zbus::Result<()> {
             };
             if args.name.as_ref() == NAME {
                 if args.new_owner.as_ref() == unique_name.as_ref() {
-                    eprintln!("Acquired bus name: {}", NAME);
+                    tracing::info!("Acquired bus name: {}", NAME);
                     have_bus_name = true;
                 } else if have_bus_name {
-                    eprintln!("Lost bus name: {}", NAME);
+                    tracing::info!("Lost bus name: {}", NAME);
                     have_bus_name = false;
                 }
             } else if let BusName::Unique(name) = &args.name {
+                if args.new_owner.is_some() {
+                    continue;
+                }
+                let ctxt = zbus::SignalContext::new(&connection, OBJECT_PATH).unwrap();
                 let mut interface = interface.get_mut().await;
                 if let Some(idx) = interface
                     .items
                     .iter()
                     .position(|(unique_name, _)| unique_name == name)
                 {
-                    let ctxt = zbus::SignalContext::new(&connection, OBJECT_PATH).unwrap();
                     let service = interface.items.remove(idx).1;
-                    StatusNotifierWatcher::status_notifier_item_unregistered(&ctxt, &service)
-                        .await
-                        .unwrap();
+                    if let Err(err) =
+                        StatusNotifierWatcher::status_notifier_item_unregistered(&ctxt, &service)
+                            .await
+                    {
+                        tracing::error!("Failed to emit item unregistered: {}", err);
+                    }
+                }
+                let hosts = interface.hosts.len();
+                interface
+                    .hosts
+                    .retain(|(unique_name, _)| unique_name != name);
+                if interface.hosts.len() != hosts {
+                    _ = StatusNotifierWatcher::status_notifier_host_unregistered(&ctxt).await;
+                    if interface.hosts.is_empty() {
+                        _ = interface
+                            .is_status_notifier_host_registered_changed(&ctxt)
+                            .await;
+                    }
                 }
             }
         }
@@ -134,3 +196,14 @@ pub async fn create_service(connection: &zbus::Connection) -> zbus::Result<()> {
 
     Ok(())
 }
+
+/// The unique name owning `bus_name`, if any.
+async fn name_owner(connection: &zbus::Connection, bus_name: &str) -> Option<UniqueName<'static>> {
+    if let Ok(name) = UniqueName::try_from(bus_name) {
+        return Some(name.into_owned());
+    }
+    let bus_name = BusName::try_from(bus_name).ok()?;
+    let dbus_proxy = DBusProxy::new(connection).await.ok()?;
+    let owner = dbus_proxy.get_name_owner(bus_name).await.ok()?;
+    Some(owner.into_inner())
+}
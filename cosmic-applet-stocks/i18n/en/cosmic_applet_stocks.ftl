@@ -0,0 +1,14 @@
+provider = Data source
+symbols = Symbols
+symbols-description = Separated by commas; the first is shown on the panel
+api-key = API key
+optional = Optional
+update-interval = Update interval
+minutes = { $minutes } min
+save = Save
+no-symbols = No symbols configured
+loading = Loading...
+failed = Failed: { $error }
+refresh = Refresh
+settings = Settings...
+back = Back
@@ -0,0 +1,162 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Prices and the last day's history from Yahoo Finance or CoinGecko.
+
+use serde::Deserialize;
+
+use crate::config::Provider;
+
+const YAHOO_URL: &str = "https://query1.finance.yahoo.com/v8/finance/chart";
+const COINGECKO_URL: &str = "https://api.coingecko.com/api/v3/coins";
+/// Yahoo rejects requests without a browser-like user agent.
+const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64)";
+
+#[derive(Debug, Clone)]
+pub struct Quote {
+    pub symbol: String,
+    pub price: f64,
+    /// Percentage change since the previous close, or over the last day.
+    pub change: f64,
+    /// Prices over the last day, oldest first.
+    pub history: Vec<f64>,
+}
+
+mod response {
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    pub struct Chart {
+        pub chart: ChartResult,
+    }
+
+    #[derive(Deserialize)]
+    pub struct ChartResult {
+        #[serde(default)]
+        pub result: Vec<ChartData>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct ChartData {
+        pub meta: Meta,
+        pub indicators: Indicators,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Meta {
+        pub regular_market_price: f64,
+        pub chart_previous_close: f64,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Indicators {
+        #[serde(default)]
+        pub quote: Vec<Prices>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Prices {
+        /// Missing for intervals without trades.
+        #[serde(default)]
+        pub close: Vec<Option<f64>>,
+    }
+}
+
+#[derive(Deserialize)]
+struct MarketChart {
+    /// Timestamp and price pairs.
+    prices: Vec<(f64, f64)>,
+}
+
+pub async fn quote(provider: Provider, symbol: String, api_key: String) -> Result<Quote, String> {
+    match provider {
+        Provider::Yahoo => yahoo(symbol).await,
+        Provider::CoinGecko => coingecko(symbol, api_key).await,
+    }
+}
+
+async fn yahoo(symbol: String) -> Result<Quote, String> {
+    let response = reqwest::Client::new()
+        .get(format!("{YAHOO_URL}/{symbol}"))
+        .query(&[("range", "1d"), ("interval", "15m")])
+        .header(reqwest::header::USER_AGENT, USER_AGENT)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|err| err.to_string())?
+        .json::<response::Chart>()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let data = response
+        .chart
+        .result
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("no data for {symbol}"))?;
+    let previous = data.meta.chart_previous_close;
+    Ok(Quote {
+        price: data.meta.regular_market_price,
+        change: percent_change(previous, data.meta.regular_market_price),
+        history: data
+            .indicators
+            .quote
+            .into_iter()
+            .next()
+            .map(|prices| prices.close.into_iter().flatten().collect())
+            .unwrap_or_default(),
+        symbol,
+    })
+}
+
+async fn coingecko(id: String, api_key: String) -> Result<Quote, String> {
+    let mut request = reqwest::Client::new()
+        .get(format!("{COINGECKO_URL}/{id}/market_chart"))
+        .query(&[("vs_currency", "usd"), ("days", "1")]);
+    if !api_key.is_empty() {
+        request = request.header("x-cg-demo-api-key", api_key);
+    }
+    let response = request
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|err| err.to_string())?
+        .json::<MarketChart>()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let history = response
+        .prices
+        .into_iter()
+        .map(|(_, price)| price)
+        .collect::<Vec<_>>();
+    let (Some(first), Some(last)) = (history.first(), history.last()) else {
+        return Err(format!("no data for {id}"));
+    };
+    Ok(Quote {
+        price: *last,
+        change: percent_change(*first, *last),
+        history,
+        symbol: id,
+    })
+}
+
+fn percent_change(from: f64, to: f64) -> f64 {
+    if from == 0.0 {
+        0.0
+    } else {
+        (to - from) / from * 100.0
+    }
+}
+
+/// Formats a price with precision that suits its magnitude.
+pub fn format_price(price: f64) -> String {
+    if price >= 1000.0 {
+        format!("{:.0}", price)
+    } else if price >= 1.0 {
+        format!("{:.2}", price)
+    } else {
+        format!("{:.4}", price)
+    }
+}
@@ -0,0 +1,43 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
+pub const APP_ID: &str = "com.system76.CosmicAppletStocks";
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum Provider {
+    /// Stocks, funds and indices by ticker, such as `AAPL` or `^GSPC`.
+    #[default]
+    Yahoo,
+    /// Cryptocurrencies by CoinGecko id, such as `bitcoin`.
+    CoinGecko,
+}
+
+impl Provider {
+    pub const ALL: [Provider; 2] = [Provider::Yahoo, Provider::CoinGecko];
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, CosmicConfigEntry)]
+#[version = 1]
+pub struct StocksConfig {
+    pub provider: Provider,
+    /// The first symbol is shown on the panel.
+    pub symbols: Vec<String>,
+    /// Only needed for higher rate limits; sent to CoinGecko as a demo key.
+    pub api_key: String,
+    /// Minutes between price updates.
+    pub refresh_interval: u64,
+}
+
+impl Default for StocksConfig {
+    fn default() -> Self {
+        Self {
+            provider: Provider::default(),
+            symbols: vec!["^GSPC".to_string()],
+            api_key: String::new(),
+            refresh_interval: 15,
+        }
+    }
+}
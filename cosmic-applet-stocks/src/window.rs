@@ -0,0 +1,464 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::time::Duration;
+
+use crate::{
+    api::{self, format_price, Quote},
+    config::{self, Provider, StocksConfig},
+    fl,
+};
+use cosmic::{
+    app::Core,
+    applet::{cosmic_panel_config::PanelAnchor, menu_button, padded_control},
+    cosmic_config::{Config, CosmicConfigEntry},
+    iced::{
+        time,
+        widget::{column, container, row},
+        Alignment, Color, Command, Length, Subscription,
+    },
+    iced_core::{Background, Border, Shadow},
+    iced_futures::futures::future::join_all,
+    iced_widget::{Column, Row},
+    widget::{
+        button, divider, horizontal_space,
+        segmented_button::{Entity, SingleSelectModel},
+        segmented_control, text, text_input, vertical_space,
+    },
+    Element, Theme,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent};
+
+const ICON: &str = "com.system76.CosmicAppletStocks-symbolic";
+const INTERVALS: [u64; 4] = [5, 15, 30, 60];
+const SPARKLINE_BARS: usize = 48;
+const SPARKLINE_HEIGHT: f32 = 32.0;
+const BAR_WIDTH: f32 = 1.5;
+
+pub struct Window {
+    config: StocksConfig,
+    config_helper: Option<Config>,
+    quotes: Vec<Quote>,
+    errors: Vec<String>,
+    loading: bool,
+    settings: bool,
+    symbols: String,
+    api_key: String,
+    providers: SingleSelectModel,
+    intervals: SingleSelectModel,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    ConfigChanged(StocksConfig),
+    Refresh,
+    Quotes(Vec<Result<Quote, String>>),
+    ToggleSettings,
+    Symbols(String),
+    ApiKey(String),
+    Save,
+    Provider(Entity),
+    Interval(Entity),
+}
+
+/// Whether a change is shown as a gain or a loss.
+fn change_color(theme: &Theme, change: f64) -> Color {
+    if change < 0.0 {
+        theme.cosmic().destructive_color().into()
+    } else {
+        theme.cosmic().success_color().into()
+    }
+}
+
+fn format_change(change: f64) -> String {
+    format!(
+        "{}{:.2}%",
+        if change < 0.0 { "▼" } else { "▲" },
+        change.abs()
+    )
+}
+
+/// Bar graph of the last day's prices, scaled between the lowest and highest.
+fn sparkline<'a>(history: &[f64], color: Color) -> Element<'a, Message> {
+    let step = (history.len() as f32 / SPARKLINE_BARS as f32).max(1.0);
+    let values = (0..SPARKLINE_BARS.min(history.len()))
+        .filter_map(|i| history.get((i as f32 * step) as usize).copied())
+        .collect::<Vec<_>>();
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = (max - min).max(f64::EPSILON);
+
+    let bars = values
+        .into_iter()
+        .map(|value| {
+            let height = ((value - min) / range) as f32 * SPARKLINE_HEIGHT;
+            container(vertical_space(Length::Fixed(0.0)))
+                .width(Length::Fixed(BAR_WIDTH))
+                .height(Length::Fixed(height.max(1.0)))
+                .style(<Theme as container::StyleSheet>::Style::Custom(Box::new(
+                    move |_: &Theme| container::Appearance {
+                        text_color: None,
+                        background: Some(Background::Color(color)),
+                        border: Border {
+                            radius: 0.0.into(),
+                            width: 0.0,
+                            color: Color::TRANSPARENT,
+                        },
+                        shadow: Shadow::default(),
+                        icon_color: None,
+                    },
+                )))
+                .into()
+        })
+        .collect::<Vec<_>>();
+    Row::with_children(bars)
+        .height(Length::Fixed(SPARKLINE_HEIGHT))
+        .align_items(Alignment::End)
+        .into()
+}
+
+impl Window {
+    fn refresh(&mut self) -> Command<Message> {
+        if self.loading || self.config.symbols.is_empty() {
+            return Command::none();
+        }
+        self.loading = true;
+        let requests = self.config.symbols.iter().map(|symbol| {
+            api::quote(
+                self.config.provider,
+                symbol.clone(),
+                self.config.api_key.clone(),
+            )
+        });
+        Command::perform(join_all(requests), |quotes| Message::Quotes(quotes))
+    }
+
+    fn sync_models(&mut self) {
+        if let Some(entity) = self
+            .providers
+            .iter()
+            .find(|e| self.providers.data::<Provider>(*e) == Some(&self.config.provider))
+        {
+            self.providers.activate(entity);
+        }
+        if let Some(entity) = self
+            .intervals
+            .iter()
+            .find(|e| self.intervals.data::<u64>(*e) == Some(&self.config.refresh_interval))
+        {
+            self.intervals.activate(entity);
+        }
+    }
+
+    fn settings_view(&self) -> Vec<Element<Message>> {
+        vec![
+            padded_control(
+                column![
+                    text(fl!("provider")).size(14),
+                    segmented_control::horizontal(&self.providers).on_activate(Message::Provider),
+                ]
+                .spacing(4),
+            )
+            .into(),
+            padded_control(
+                column![
+                    text(fl!("symbols")).size(14),
+                    text_input(
+                        match self.config.provider {
+                            Provider::Yahoo => "AAPL, ^GSPC",
+                            Provider::CoinGecko => "bitcoin, ethereum",
+                        },
+                        &self.symbols
+                    )
+                    .on_input(Message::Symbols)
+                    .on_submit(Message::Save),
+                    text(fl!("symbols-description")).size(10),
+                ]
+                .spacing(4),
+            )
+            .into(),
+            padded_control(
+                column![
+                    text(fl!("api-key")).size(14),
+                    text_input(fl!("optional"), &self.api_key)
+                        .on_input(Message::ApiKey)
+                        .on_submit(Message::Save),
+                ]
+                .spacing(4),
+            )
+            .into(),
+            padded_control(
+                column![
+                    text(fl!("update-interval")).size(14),
+                    segmented_control::horizontal(&self.intervals).on_activate(Message::Interval),
+                ]
+                .spacing(4),
+            )
+            .into(),
+            padded_control(
+                row![
+                    horizontal_space(Length::Fill),
+                    button::suggested(fl!("save")).on_press(Message::Save),
+                ]
+                .spacing(8),
+            )
+            .into(),
+        ]
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = config::APP_ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::APP_ID, StocksConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                StocksConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let mut providers = SingleSelectModel::default();
+        for provider in Provider::ALL {
+            providers
+                .insert()
+                .text(match provider {
+                    Provider::Yahoo => "Yahoo Finance",
+                    Provider::CoinGecko => "CoinGecko",
+                })
+                .data(provider);
+        }
+        let mut intervals = SingleSelectModel::default();
+        for minutes in INTERVALS {
+            intervals
+                .insert()
+                .text(fl!("minutes", minutes = minutes))
+                .data(minutes);
+        }
+
+        let mut window = Self {
+            symbols: config.symbols.join(", "),
+            api_key: config.api_key.clone(),
+            config,
+            config_helper,
+            quotes: Vec::new(),
+            errors: Vec::new(),
+            loading: false,
+            settings: false,
+            providers,
+            intervals,
+        };
+        window.sync_models();
+        let command = window.refresh();
+        (window, command)
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        if event == PanelEvent::PopupOpened {
+            self.settings = false;
+        }
+        Command::none()
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        Subscription::batch(vec![
+            core.watch_config::<StocksConfig>(config::APP_ID)
+                .map(|u| Message::ConfigChanged(u.config)),
+            time::every(Duration::from_secs(
+                self.config.refresh_interval.max(1) * 60,
+            ))
+            .map(|_| Message::Refresh),
+        ])
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::ConfigChanged(config) => {
+                let changed = config.provider != self.config.provider
+                    || config.symbols != self.config.symbols
+                    || config.api_key != self.config.api_key;
+                self.config = config;
+                self.sync_models();
+                if changed {
+                    self.quotes.clear();
+                    return self.refresh();
+                }
+            }
+            Message::Refresh => {
+                return self.refresh();
+            }
+            Message::Quotes(results) => {
+                self.loading = false;
+                self.quotes.clear();
+                self.errors.clear();
+                for result in results {
+                    match result {
+                        Ok(quote) => self.quotes.push(quote),
+                        Err(err) => {
+                            tracing::error!("Failed to get quote: {}", err);
+                            self.errors.push(err);
+                        }
+                    }
+                }
+            }
+            Message::ToggleSettings => {
+                self.settings = !self.settings;
+                self.symbols = self.config.symbols.join(", ");
+                self.api_key = self.config.api_key.clone();
+            }
+            Message::Symbols(symbols) => {
+                self.symbols = symbols;
+            }
+            Message::ApiKey(api_key) => {
+                self.api_key = api_key;
+            }
+            Message::Save => {
+                let symbols = self
+                    .symbols
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string)
+                    .collect::<Vec<_>>();
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_symbols(helper, symbols) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                    if let Err(err) = self
+                        .config
+                        .set_api_key(helper, self.api_key.trim().to_string())
+                    {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+                self.settings = false;
+                self.quotes.clear();
+                return self.refresh();
+            }
+            Message::Provider(entity) => {
+                self.providers.activate(entity);
+                if let (Some(helper), Some(provider)) = (
+                    &self.config_helper,
+                    self.providers.data::<Provider>(entity).copied(),
+                ) {
+                    if let Err(err) = self.config.set_provider(helper, provider) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+            Message::Interval(entity) => {
+                self.intervals.activate(entity);
+                if let (Some(helper), Some(interval)) = (
+                    &self.config_helper,
+                    self.intervals.data::<u64>(entity).copied(),
+                ) {
+                    if let Err(err) = self.config.set_refresh_interval(helper, interval) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let quote = self
+            .quotes
+            .first()
+            .filter(|_| matches!(core.applet.anchor, PanelAnchor::Top | PanelAnchor::Bottom));
+        let Some(quote) = quote else {
+            return core
+                .applet
+                .icon_button(ICON)
+                .on_press(AppletMessage::TogglePopup)
+                .into();
+        };
+
+        let color = change_color(core.system_theme(), quote.change);
+        let content = row![
+            text(&quote.symbol).size(14),
+            text(format_price(quote.price)).size(14),
+            text(format_change(quote.change))
+                .size(12)
+                .style(cosmic::theme::Text::Color(color)),
+        ]
+        .spacing(4)
+        .align_items(Alignment::Center);
+        button(content)
+            .padding(core.applet.suggested_padding(true))
+            .on_press(AppletMessage::TogglePopup)
+            .style(cosmic::theme::Button::AppletIcon)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = Vec::new();
+
+        if self.settings {
+            content.extend(self.settings_view());
+        } else {
+            if self.config.symbols.is_empty() {
+                content.push(padded_control(text(fl!("no-symbols")).size(14)).into());
+            } else if self.quotes.is_empty() && self.loading {
+                content.push(padded_control(text(fl!("loading")).size(14)).into());
+            }
+            for quote in &self.quotes {
+                let color = change_color(core.system_theme(), quote.change);
+                content.push(
+                    padded_control(
+                        row![
+                            column![
+                                text(&quote.symbol).size(14),
+                                row![
+                                    text(format_price(quote.price)).size(12),
+                                    text(format_change(quote.change))
+                                        .size(12)
+                                        .style(cosmic::theme::Text::Color(color)),
+                                ]
+                                .spacing(4),
+                            ]
+                            .width(Length::Fill),
+                            sparkline(&quote.history, color),
+                        ]
+                        .spacing(8)
+                        .align_items(Alignment::Center),
+                    )
+                    .into(),
+                );
+            }
+            for err in &self.errors {
+                content.push(
+                    padded_control(text(fl!("failed", error = err.as_str())).size(10)).into(),
+                );
+            }
+        }
+
+        content.push(padded_control(divider::horizontal::default()).into());
+        content.push(
+            menu_button(text(fl!("refresh")).size(14))
+                .on_press_maybe((!self.loading).then_some(Message::Refresh))
+                .into(),
+        );
+        content.push(
+            menu_button(
+                text(if self.settings {
+                    fl!("back")
+                } else {
+                    fl!("settings")
+                })
+                .size(14),
+            )
+            .on_press(Message::ToggleSettings)
+            .into(),
+        );
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+}
@@ -0,0 +1,7 @@
+cpu = CPU
+memory = Memory
+network = Network
+disk = Disk
+top-processes = Top processes
+show-on-panel = Show on panel
+update-interval = Update interval
@@ -0,0 +1,30 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
+pub const APP_ID: &str = "com.system76.CosmicAppletSystemMonitor";
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, CosmicConfigEntry)]
+#[version = 1]
+pub struct SystemMonitorConfig {
+    /// Milliseconds between samples.
+    pub interval: u64,
+    pub show_cpu: bool,
+    pub show_memory: bool,
+    pub show_network: bool,
+    pub show_disk: bool,
+}
+
+impl Default for SystemMonitorConfig {
+    fn default() -> Self {
+        Self {
+            interval: 1000,
+            show_cpu: true,
+            show_memory: true,
+            show_network: false,
+            show_disk: false,
+        }
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+/// Fixed capacity history, overwriting the oldest value once full.
+#[derive(Debug, Clone)]
+pub struct RingBuffer<T> {
+    data: Vec<T>,
+    /// Index of the oldest value once the buffer is full.
+    start: usize,
+    capacity: usize,
+}
+
+impl<T: Copy> RingBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            data: Vec::with_capacity(capacity),
+            start: 0,
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, value: T) {
+        if self.data.len() < self.capacity {
+            self.data.push(value);
+        } else {
+            self.data[self.start] = value;
+            self.start = (self.start + 1) % self.capacity;
+        }
+    }
+
+    /// Values from oldest to newest.
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        let (newer, older) = self.data.split_at(self.start);
+        older.iter().chain(newer).copied()
+    }
+}
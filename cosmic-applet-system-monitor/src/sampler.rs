@@ -0,0 +1,276 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Resource usage, sampled from `/proc`.
+
+use std::{collections::HashMap, fs, path::Path, time::Instant};
+
+/// How many processes are listed in the popup.
+const TOP_PROCESSES: usize = 5;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct CpuTimes {
+    busy: u64,
+    total: u64,
+}
+
+impl CpuTimes {
+    fn parse(line: &str) -> Option<Self> {
+        let values = line
+            .split_whitespace()
+            .skip(1)
+            .map(|v| v.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        // Guest time is already included in user and nice
+        let total = values.iter().take(8).sum::<u64>();
+        let idle = values.get(3)? + values.get(4).unwrap_or(&0);
+        Some(Self {
+            busy: total - idle,
+            total,
+        })
+    }
+
+    /// Percentage busy since `previous`.
+    fn usage(&self, previous: &Self) -> f32 {
+        let total = self.total.saturating_sub(previous.total);
+        if total == 0 {
+            return 0.0;
+        }
+        self.busy.saturating_sub(previous.busy) as f32 / total as f32 * 100.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct IoCounters {
+    read: u64,
+    written: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessUsage {
+    pub pid: u32,
+    pub name: String,
+    /// Percentage of total CPU time.
+    pub cpu: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Sample {
+    pub cpu: f32,
+    pub cores: Vec<f32>,
+    pub memory_used: u64,
+    pub memory_total: u64,
+    /// Bytes per second received and sent.
+    pub network: (u64, u64),
+    /// Bytes per second read and written.
+    pub disk: (u64, u64),
+    /// Only sampled when requested, as it reads every process's stat.
+    pub processes: Vec<ProcessUsage>,
+}
+
+impl Sample {
+    pub fn memory(&self) -> f32 {
+        if self.memory_total == 0 {
+            return 0.0;
+        }
+        self.memory_used as f32 / self.memory_total as f32 * 100.0
+    }
+}
+
+/// Keeps the previous counters, to turn them into rates.
+#[derive(Default)]
+pub struct Sampler {
+    cpu: CpuTimes,
+    cores: Vec<CpuTimes>,
+    network: IoCounters,
+    disk: IoCounters,
+    processes: HashMap<u32, u64>,
+    last: Option<Instant>,
+}
+
+impl Sampler {
+    pub fn sample(&mut self, processes: bool) -> Sample {
+        let now = Instant::now();
+        let elapsed = self
+            .last
+            .replace(now)
+            .map_or(0.0, |last| now.duration_since(last).as_secs_f64());
+
+        let mut sample = Sample::default();
+        let previous_cpu = self.cpu;
+        self.sample_cpu(&mut sample);
+        self.sample_memory(&mut sample);
+
+        let network = network_counters();
+        let disk = disk_counters();
+        if elapsed > 0.0 {
+            let rate = |now: u64, before: u64| (now.saturating_sub(before) as f64 / elapsed) as u64;
+            sample.network = (
+                rate(network.read, self.network.read),
+                rate(network.written, self.network.written),
+            );
+            sample.disk = (
+                rate(disk.read, self.disk.read),
+                rate(disk.written, self.disk.written),
+            );
+        }
+        self.network = network;
+        self.disk = disk;
+
+        if processes {
+            let total = self.cpu.total.saturating_sub(previous_cpu.total);
+            sample.processes = self.sample_processes(total);
+        } else {
+            self.processes.clear();
+        }
+        sample
+    }
+
+    fn sample_cpu(&mut self, sample: &mut Sample) {
+        let Ok(stat) = fs::read_to_string("/proc/stat") else {
+            return;
+        };
+        let mut cores = Vec::new();
+        for line in stat.lines() {
+            if line.starts_with("cpu ") {
+                if let Some(times) = CpuTimes::parse(line) {
+                    sample.cpu = times.usage(&self.cpu);
+                    self.cpu = times;
+                }
+            } else if line.starts_with("cpu") {
+                if let Some(times) = CpuTimes::parse(line) {
+                    let previous = self.cores.get(cores.len()).copied().unwrap_or_default();
+                    sample.cores.push(times.usage(&previous));
+                    cores.push(times);
+                }
+            }
+        }
+        self.cores = cores;
+    }
+
+    fn sample_memory(&self, sample: &mut Sample) {
+        let Ok(meminfo) = fs::read_to_string("/proc/meminfo") else {
+            return;
+        };
+        let mut available = 0;
+        for line in meminfo.lines() {
+            let mut fields = line.split_whitespace();
+            let (Some(key), Some(Ok(kib))) = (fields.next(), fields.next().map(str::parse::<u64>))
+            else {
+                continue;
+            };
+            match key {
+                "MemTotal:" => sample.memory_total = kib * 1024,
+                "MemAvailable:" => available = kib * 1024,
+                _ => {}
+            }
+        }
+        sample.memory_used = sample.memory_total.saturating_sub(available);
+    }
+
+    fn sample_processes(&mut self, total: u64) -> Vec<ProcessUsage> {
+        let Ok(entries) = fs::read_dir("/proc") else {
+            return Vec::new();
+        };
+        let mut times = HashMap::new();
+        let mut usage = Vec::new();
+        for entry in entries.flatten() {
+            let Some(pid) = entry
+                .file_name()
+                .to_str()
+                .and_then(|p| p.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            let Some((name, time)) = process_time(&entry.path().join("stat")) else {
+                continue;
+            };
+            if let Some(previous) = self.processes.get(&pid) {
+                if total > 0 {
+                    usage.push(ProcessUsage {
+                        pid,
+                        name,
+                        cpu: time.saturating_sub(*previous) as f32 / total as f32 * 100.0,
+                    });
+                }
+            }
+            times.insert(pid, time);
+        }
+        self.processes = times;
+
+        usage.sort_by(|a, b| b.cpu.total_cmp(&a.cpu));
+        usage.truncate(TOP_PROCESSES);
+        usage
+    }
+}
+
+/// Command name and CPU time in clock ticks, from `/proc/<pid>/stat`.
+fn process_time(path: &Path) -> Option<(String, u64)> {
+    let stat = fs::read_to_string(path).ok()?;
+    // The name is in parentheses and may contain spaces
+    let name_start = stat.find('(')? + 1;
+    let name_end = stat.rfind(')')?;
+    let mut fields = stat[name_end + 1..].split_whitespace();
+    // utime and stime are the 14th and 15th fields, counting from the pid
+    let utime = fields.nth(11)?.parse::<u64>().ok()?;
+    let stime = fields.next()?.parse::<u64>().ok()?;
+    Some((stat[name_start..name_end].to_string(), utime + stime))
+}
+
+fn network_counters() -> IoCounters {
+    let mut counters = IoCounters::default();
+    let Ok(dev) = fs::read_to_string("/proc/net/dev") else {
+        return counters;
+    };
+    // The first two lines are headers
+    for line in dev.lines().skip(2) {
+        let Some((interface, values)) = line.split_once(':') else {
+            continue;
+        };
+        if interface.trim() == "lo" {
+            continue;
+        }
+        let values = values
+            .split_whitespace()
+            .filter_map(|v| v.parse::<u64>().ok())
+            .collect::<Vec<_>>();
+        if let (Some(received), Some(sent)) = (values.first(), values.get(8)) {
+            counters.read += received;
+            counters.written += sent;
+        }
+    }
+    counters
+}
+
+fn disk_counters() -> IoCounters {
+    const SECTOR_SIZE: u64 = 512;
+
+    let mut counters = IoCounters::default();
+    let Ok(diskstats) = fs::read_to_string("/proc/diskstats") else {
+        return counters;
+    };
+    for line in diskstats.lines() {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        let Some(name) = fields.get(2) else {
+            continue;
+        };
+        // Whole disks only, so partitions aren't counted twice
+        if name.starts_with("loop")
+            || name.starts_with("ram")
+            || name.starts_with("zram")
+            || !Path::new("/sys/block").join(name).exists()
+        {
+            continue;
+        }
+        let sectors = |i: usize| {
+            fields
+                .get(i)
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(0)
+        };
+        counters.read += sectors(5) * SECTOR_SIZE;
+        counters.written += sectors(9) * SECTOR_SIZE;
+    }
+    counters
+}
@@ -0,0 +1,29 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::time::Duration;
+
+use cosmic::{
+    iced::{self, subscription},
+    iced_futures::futures::SinkExt,
+};
+
+use crate::sampler::{Sample, Sampler};
+
+/// Samples every `interval`, including the top processes if `processes` is
+/// set. Changing either restarts sampling.
+pub fn sample_subscription(interval: Duration, processes: bool) -> iced::Subscription<Sample> {
+    subscription::channel(
+        (std::any::TypeId::of::<Sample>(), interval, processes),
+        10,
+        move |mut output| async move {
+            let mut sampler = Sampler::default();
+            let mut timer = tokio::time::interval(interval);
+            timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                timer.tick().await;
+                _ = output.send(sampler.sample(processes)).await;
+            }
+        },
+    )
+}
@@ -0,0 +1,381 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::time::Duration;
+
+use crate::{
+    config::{self, SystemMonitorConfig},
+    fl,
+    ring_buffer::RingBuffer,
+    sampler::Sample,
+    subscription::sample_subscription,
+};
+use cosmic::{
+    app::Core,
+    applet::{cosmic_panel_config::PanelAnchor, padded_control},
+    cosmic_config::{Config, CosmicConfigEntry},
+    iced::{
+        widget::{column, container, progress_bar, row},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_core::{Background, Border, Color, Shadow},
+    iced_widget::{Column, Row},
+    widget::{
+        button, divider, horizontal_space,
+        segmented_button::{Entity, SingleSelectModel},
+        segmented_control, text, toggler, vertical_space,
+    },
+    Element, Theme,
+};
+use cosmic_applet_helper::{format_bytes, format_rate, AppletMessage, CosmicApplet, PanelEvent};
+
+/// Samples shown in each panel graph.
+const HISTORY: usize = 30;
+const BAR_WIDTH: f32 = 1.5;
+const INTERVALS: [u64; 4] = [500, 1000, 2000, 5000];
+
+#[derive(Clone, Copy, Debug)]
+pub enum Metric {
+    Cpu,
+    Memory,
+    Network,
+    Disk,
+}
+
+pub struct Window {
+    config: SystemMonitorConfig,
+    config_helper: Option<Config>,
+    sample: Sample,
+    cpu: RingBuffer<f32>,
+    memory: RingBuffer<f32>,
+    /// Bytes per second, received and sent combined.
+    network: RingBuffer<u64>,
+    /// Bytes per second, read and written combined.
+    disk: RingBuffer<u64>,
+    intervals: SingleSelectModel,
+    /// Processes are only sampled while the popup shows them.
+    popup_open: bool,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    ConfigChanged(SystemMonitorConfig),
+    Sample(Sample),
+    ShowMetric(Metric, bool),
+    Interval(Entity),
+}
+
+fn bar_style(theme: &Theme) -> container::Appearance {
+    container::Appearance {
+        text_color: None,
+        background: Some(Background::Color(theme.cosmic().accent_color().into())),
+        border: Border {
+            radius: 0.0.into(),
+            width: 0.0,
+            color: Color::TRANSPARENT,
+        },
+        shadow: Shadow::default(),
+        icon_color: None,
+    }
+}
+
+/// Bar graph of `values` between 0 and 1, oldest first.
+fn graph<'a>(values: impl Iterator<Item = f32>, height: f32) -> Element<'a, Message> {
+    let values = values.collect::<Vec<_>>();
+    let bars = std::iter::repeat(0.0)
+        .take(HISTORY.saturating_sub(values.len()))
+        .chain(values)
+        .map(|value| {
+            container(vertical_space(Length::Fixed(0.0)))
+                .width(Length::Fixed(BAR_WIDTH))
+                .height(Length::Fixed((value.clamp(0.0, 1.0) * height).max(1.0)))
+                .style(<Theme as container::StyleSheet>::Style::Custom(Box::new(
+                    bar_style,
+                )))
+                .into()
+        })
+        .collect::<Vec<_>>();
+    Row::with_children(bars)
+        .height(Length::Fixed(height))
+        .align_items(Alignment::End)
+        .into()
+}
+
+/// Scales rates to the largest in view, so graphs stay readable.
+fn scaled(history: &RingBuffer<u64>) -> impl Iterator<Item = f32> + '_ {
+    let max = history.iter().max().unwrap_or(0).max(1024) as f32;
+    history.iter().map(move |value| value as f32 / max)
+}
+
+impl Window {
+    fn graphs(&self, core: &Core) -> Vec<Element<Message>> {
+        let height = core.applet.suggested_size(true).1 as f32;
+        let mut graphs = Vec::new();
+        if self.config.show_cpu {
+            graphs.push(graph(self.cpu.iter().map(|v| v / 100.0), height));
+        }
+        if self.config.show_memory {
+            graphs.push(graph(self.memory.iter().map(|v| v / 100.0), height));
+        }
+        if self.config.show_network {
+            graphs.push(graph(scaled(&self.network), height));
+        }
+        if self.config.show_disk {
+            graphs.push(graph(scaled(&self.disk), height));
+        }
+        graphs
+    }
+
+    fn usage_view(label: String, detail: String, value: f32) -> Element<'static, Message> {
+        padded_control(
+            column![
+                row![
+                    text(label).size(14).width(Length::Fill),
+                    text(detail).size(14),
+                ],
+                progress_bar(0.0..=100.0, value).height(Length::Fixed(4.0)),
+            ]
+            .spacing(4),
+        )
+        .into()
+    }
+
+    fn cores_view(&self) -> Element<Message> {
+        let rows = self
+            .sample
+            .cores
+            .chunks(4)
+            .map(|cores| {
+                Row::with_children(
+                    cores
+                        .iter()
+                        .map(|core| {
+                            progress_bar(0.0..=100.0, *core)
+                                .height(Length::Fixed(4.0))
+                                .into()
+                        })
+                        .collect(),
+                )
+                .spacing(8)
+                .into()
+            })
+            .collect();
+        padded_control(Column::with_children(rows).spacing(6)).into()
+    }
+
+    fn rates_view(label: String, (down, up): (u64, u64)) -> Element<'static, Message> {
+        padded_control(
+            row![
+                text(label).size(14).width(Length::Fill),
+                text(format!("↓ {}  ↑ {}", format_rate(down), format_rate(up))).size(14),
+            ]
+            .align_items(Alignment::Center),
+        )
+        .into()
+    }
+
+    fn processes_view(&self) -> Vec<Element<Message>> {
+        let mut content = vec![padded_control(text(fl!("top-processes")).size(14)).into()];
+        for process in &self.sample.processes {
+            content.push(
+                padded_control(
+                    row![
+                        text(&process.name).size(12).width(Length::Fill),
+                        text(process.pid.to_string()).size(10),
+                        horizontal_space(Length::Fixed(8.0)),
+                        text(format!("{:.1}%", process.cpu)).size(12),
+                    ]
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+        }
+        content
+    }
+
+    fn settings_view(&self) -> Vec<Element<Message>> {
+        let mut content = vec![padded_control(text(fl!("show-on-panel")).size(14)).into()];
+        for (metric, label, shown) in [
+            (Metric::Cpu, fl!("cpu"), self.config.show_cpu),
+            (Metric::Memory, fl!("memory"), self.config.show_memory),
+            (Metric::Network, fl!("network"), self.config.show_network),
+            (Metric::Disk, fl!("disk"), self.config.show_disk),
+        ] {
+            content.push(
+                padded_control(
+                    toggler(Some(label), shown, move |shown| {
+                        Message::ShowMetric(metric, shown)
+                    })
+                    .text_size(14)
+                    .width(Length::Fill),
+                )
+                .into(),
+            );
+        }
+        content.push(padded_control(text(fl!("update-interval")).size(14)).into());
+        content.push(
+            padded_control(
+                segmented_control::horizontal(&self.intervals).on_activate(Message::Interval),
+            )
+            .into(),
+        );
+        content
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = config::APP_ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::APP_ID, SystemMonitorConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                SystemMonitorConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let mut intervals = SingleSelectModel::default();
+        for interval in INTERVALS {
+            let label = format!("{}s", interval as f32 / 1000.0);
+            let entity = intervals.insert().text(label).data(interval).id();
+            if interval == config.interval {
+                intervals.activate(entity);
+            }
+        }
+
+        let window = Self {
+            config,
+            config_helper,
+            sample: Sample::default(),
+            cpu: RingBuffer::new(HISTORY),
+            memory: RingBuffer::new(HISTORY),
+            network: RingBuffer::new(HISTORY),
+            disk: RingBuffer::new(HISTORY),
+            intervals,
+            popup_open: false,
+        };
+        (window, Command::none())
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        self.popup_open = event == PanelEvent::PopupOpened;
+        Command::none()
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        core.watch_config::<SystemMonitorConfig>(config::APP_ID)
+            .map(|u| Message::ConfigChanged(u.config))
+    }
+
+    fn panel_subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        sample_subscription(
+            Duration::from_millis(self.config.interval.max(100)),
+            self.popup_open,
+        )
+        .map(Message::Sample)
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::ConfigChanged(config) => {
+                self.config = config;
+            }
+            Message::Sample(mut sample) => {
+                self.cpu.push(sample.cpu);
+                self.memory.push(sample.memory());
+                self.network.push(sample.network.0 + sample.network.1);
+                self.disk.push(sample.disk.0 + sample.disk.1);
+                // Keep the last process list while the sampler restarts
+                if sample.processes.is_empty() && self.popup_open {
+                    sample.processes = std::mem::take(&mut self.sample.processes);
+                }
+                self.sample = sample;
+            }
+            Message::ShowMetric(metric, shown) => {
+                if let Some(helper) = &self.config_helper {
+                    let res = match metric {
+                        Metric::Cpu => self.config.set_show_cpu(helper, shown),
+                        Metric::Memory => self.config.set_show_memory(helper, shown),
+                        Metric::Network => self.config.set_show_network(helper, shown),
+                        Metric::Disk => self.config.set_show_disk(helper, shown),
+                    };
+                    if let Err(err) = res {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+            Message::Interval(entity) => {
+                self.intervals.activate(entity);
+                if let (Some(helper), Some(interval)) = (
+                    &self.config_helper,
+                    self.intervals.data::<u64>(entity).copied(),
+                ) {
+                    if let Err(err) = self.config.set_interval(helper, interval) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let graphs = self.graphs(core);
+        if graphs.is_empty() {
+            return core
+                .applet
+                .icon_button("com.system76.CosmicAppletSystemMonitor-symbolic")
+                .on_press(AppletMessage::TogglePopup)
+                .into();
+        }
+
+        let padding = core.applet.suggested_padding(true);
+        let content: Element<Message> = match core.applet.anchor {
+            PanelAnchor::Left | PanelAnchor::Right => Column::with_children(graphs)
+                .spacing(4)
+                .align_items(Alignment::Center)
+                .into(),
+            PanelAnchor::Top | PanelAnchor::Bottom => Row::with_children(graphs)
+                .spacing(4)
+                .align_items(Alignment::Center)
+                .into(),
+        };
+        button(content.map(AppletMessage::App))
+            .padding(padding)
+            .on_press(AppletMessage::TogglePopup)
+            .style(cosmic::theme::Button::AppletIcon)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let sample = &self.sample;
+        let mut content = vec![
+            Self::usage_view(fl!("cpu"), format!("{:.0}%", sample.cpu), sample.cpu),
+            self.cores_view(),
+            Self::usage_view(
+                fl!("memory"),
+                format!(
+                    "{} / {}",
+                    format_bytes(sample.memory_used),
+                    format_bytes(sample.memory_total)
+                ),
+                sample.memory(),
+            ),
+            Self::rates_view(fl!("network"), sample.network),
+            Self::rates_view(fl!("disk"), sample.disk),
+            padded_control(divider::horizontal::default()).into(),
+        ];
+        content.extend(self.processes_view());
+        content.push(padded_control(divider::horizontal::default()).into());
+        content.extend(self.settings_view());
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+}
@@ -17,4 +17,14 @@ arrow-keys = arrows
 tiled = Tiled
 floating = Floating
 autotile-behavior = Tile windows on workspaces
-new-workspace = New workspace behavior
\ No newline at end of file
+new-workspace = New workspace behavior
+outer-gap = Outer gap
+inner-gap = Inner gap
+active-hint-width = Active hint width
+no-exceptions = No exceptions
+any = Any
+app-id-regex = Application ID (regex)
+title-regex = Window title (regex)
+add-exception = Add exception…
+cancel = Cancel
+save = Save
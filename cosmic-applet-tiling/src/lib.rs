@@ -4,12 +4,14 @@
 use crate::window::Window;
 
 mod localize;
+mod theme;
 mod wayland;
 mod wayland_subscription;
 mod window;
+mod window_rules;
 
 pub fn run() -> cosmic::iced::Result {
     localize::localize();
 
-    cosmic::applet::run::<Window>(false, ())
+    cosmic_applet_helper::run_applet::<Window>()
 }
@@ -0,0 +1,44 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Gaps and the active hint width are part of the COSMIC theme, which
+//! cosmic-comp reloads as soon as it changes.
+
+use cosmic::{
+    cosmic_config::{self, Config, CosmicConfigEntry},
+    cosmic_theme::{Theme, ThemeBuilder},
+};
+
+/// Updates both the light and dark themes, and the builders they are
+/// generated from, so the value survives switching modes or regenerating.
+fn update(
+    update_builder: impl Fn(&mut ThemeBuilder, &Config) -> Result<bool, cosmic_config::Error>,
+    update_theme: impl Fn(&mut Theme, &Config) -> Result<bool, cosmic_config::Error>,
+) -> Result<(), cosmic_config::Error> {
+    for (builder_config, theme_config) in [
+        (ThemeBuilder::dark_config()?, Theme::dark_config()?),
+        (ThemeBuilder::light_config()?, Theme::light_config()?),
+    ] {
+        let mut builder =
+            ThemeBuilder::get_entry(&builder_config).unwrap_or_else(|(_, builder)| builder);
+        update_builder(&mut builder, &builder_config)?;
+        let mut theme = Theme::get_entry(&theme_config).unwrap_or_else(|(_, theme)| theme);
+        update_theme(&mut theme, &theme_config)?;
+    }
+    Ok(())
+}
+
+/// Sets the outer and inner gaps.
+pub fn set_gaps(gaps: (u32, u32)) -> Result<(), cosmic_config::Error> {
+    update(
+        |builder, config| builder.set_gaps(config, gaps),
+        |theme, config| theme.set_gaps(config, gaps),
+    )
+}
+
+pub fn set_active_hint(active_hint: u32) -> Result<(), cosmic_config::Error> {
+    update(
+        |builder, config| builder.set_active_hint(config, active_hint),
+        |theme, config| theme.set_active_hint(config, active_hint),
+    )
+}
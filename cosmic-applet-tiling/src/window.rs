@@ -2,27 +2,27 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use crate::{
-    fl, wayland::AppRequest, wayland_subscription, wayland_subscription::WorkspacesUpdate,
+    fl, theme,
+    wayland::AppRequest,
+    wayland_subscription,
+    wayland_subscription::WorkspacesUpdate,
+    window_rules::{ApplicationException, WindowRules},
 };
 use cctk::sctk::reexports::calloop::channel::SyncSender;
 use cosmic::{
     app::Core,
-    applet::padded_control,
+    applet::{menu_button, padded_control},
     cosmic_config::{Config, ConfigSet, CosmicConfigEntry},
-    iced::{
-        wayland::popup::{destroy_popup, get_popup},
-        window::Id,
-        Command, Length, Limits, Subscription,
-    },
-    iced_style::application,
+    iced::{Alignment, Command, Length, Limits, Subscription},
     iced_widget::{column, row},
     widget::{
-        container, divider, segmented_button,
+        button, container, divider, icon, segmented_button,
         segmented_button::{Entity, SingleSelectModel},
-        segmented_control, spin_button, text,
+        segmented_control, spin_button, text, text_input, toggler,
     },
-    Element, Theme,
+    Element,
 };
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent, PopupWidth};
 use cosmic_comp_config::{CosmicCompConfig, TileBehavior};
 use cosmic_protocols::workspace::v1::client::zcosmic_workspace_handle_v1::TilingState;
 use cosmic_time::{anim, chain, id, Timeline};
@@ -34,8 +34,6 @@ const ON: &str = "com.system76.CosmicAppletTiling.On";
 const OFF: &str = "com.system76.CosmicAppletTiling.Off";
 
 pub struct Window {
-    core: Core,
-    popup: Option<Id>,
     timeline: Timeline,
     config: CosmicCompConfig,
     config_helper: Config,
@@ -46,42 +44,154 @@ pub struct Window {
     workspace_tx: Option<SyncSender<AppRequest>>,
     tile_windows: id::Toggler,
     active_hint: id::Toggler,
+    outer_gap: spin_button::Model<i32>,
+    inner_gap: spin_button::Model<i32>,
+    active_hint_width: spin_button::Model<i32>,
+    window_rules: WindowRules,
+    /// Shown instead of the main page while managing exceptions.
+    exceptions: Option<Vec<ApplicationException>>,
+    /// Exception being added or edited, and its index when editing.
+    editing: Option<(Option<usize>, ApplicationException)>,
+    /// Togglers are only animated while they're shown.
+    popup_open: bool,
 }
 
 #[derive(Clone, Debug)]
 pub enum Message {
-    TogglePopup,
-    PopupClosed(Id),
     Frame(Instant),
     ToggleTileWindows(chain::Toggler, bool),
     ToggleActiveHint(chain::Toggler, bool),
     MyConfigUpdate(Box<CosmicCompConfig>),
     WorkspaceUpdate(WorkspacesUpdate),
     NewWorkspace(Entity),
+    OuterGap(spin_button::Message),
+    InnerGap(spin_button::Message),
+    ActiveHintWidth(spin_button::Message),
+    OpenExceptions,
+    CloseExceptions,
+    ToggleException(usize, bool),
+    RemoveException(usize),
+    AddException,
+    EditException(usize),
+    EditAppId(String),
+    EditTitle(String),
+    SaveException,
+    CancelEdit,
 }
 
-impl cosmic::Application for Window {
-    type Executor = cosmic::SingleThreadExecutor;
-    type Flags = ();
-    type Message = Message;
-    const APP_ID: &'static str = ID;
+impl Window {
+    fn write_exceptions(&self) {
+        if let Some(exceptions) = self.exceptions.clone() {
+            self.window_rules.set_exceptions(exceptions);
+        }
+    }
+
+    fn exceptions_view(&self, exceptions: &[ApplicationException]) -> Element<Message> {
+        let mut content = column![
+            menu_button(
+                row![
+                    icon::from_name("go-previous-symbolic")
+                        .size(16)
+                        .symbolic(true),
+                    text(fl!("floating-window-exceptions")).size(14),
+                ]
+                .spacing(8)
+            )
+            .on_press(Message::CloseExceptions),
+            padded_control(divider::horizontal::default()),
+        ]
+        .padding([8, 0]);
+
+        if exceptions.is_empty() && self.editing.is_none() {
+            content = content.push(padded_control(text(fl!("no-exceptions")).size(14)));
+        }
+        for (i, exception) in exceptions.iter().enumerate() {
+            let any = fl!("any");
+            content = content.push(padded_control(
+                row![
+                    toggler(None, exception.enabled, move |enabled| {
+                        Message::ToggleException(i, enabled)
+                    })
+                    .width(Length::Shrink),
+                    column![
+                        text(if exception.appid.is_empty() {
+                            &any
+                        } else {
+                            &exception.appid
+                        })
+                        .size(14),
+                        text(if exception.title.is_empty() {
+                            &any
+                        } else {
+                            &exception.title
+                        })
+                        .size(10),
+                    ]
+                    .width(Length::Fill),
+                    button::icon(icon::from_name("edit-symbolic"))
+                        .on_press(Message::EditException(i)),
+                    button::icon(icon::from_name("edit-delete-symbolic"))
+                        .on_press(Message::RemoveException(i)),
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center),
+            ));
+        }
+
+        content = content.push(padded_control(divider::horizontal::default()));
+        content = if let Some((_, exception)) = self.editing.as_ref() {
+            content.push(padded_control(
+                column![
+                    text(fl!("app-id-regex")).size(14),
+                    text_input("", &exception.appid)
+                        .on_input(Message::EditAppId)
+                        .on_submit(Message::SaveException),
+                    text(fl!("title-regex")).size(14),
+                    text_input("", &exception.title)
+                        .on_input(Message::EditTitle)
+                        .on_submit(Message::SaveException),
+                    row![
+                        button::standard(fl!("cancel")).on_press(Message::CancelEdit),
+                        button::suggested(fl!("save")).on_press_maybe(
+                            (!exception.appid.is_empty() || !exception.title.is_empty())
+                                .then_some(Message::SaveException)
+                        ),
+                    ]
+                    .spacing(8),
+                ]
+                .spacing(8),
+            ))
+        } else {
+            content.push(
+                menu_button(text(fl!("add-exception")).size(14)).on_press(Message::AddException),
+            )
+        };
 
-    fn core(&self) -> &Core {
-        &self.core
+        content.into()
     }
 
-    fn core_mut(&mut self) -> &mut Core {
-        &mut self.core
+    fn write_gaps(&self) {
+        let gaps = (self.outer_gap.value as u32, self.inner_gap.value as u32);
+        thread::spawn(move || {
+            if let Err(err) = theme::set_gaps(gaps) {
+                error!(?err, "Failed to set gaps {gaps:?}");
+            }
+        });
     }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = ID;
+    const AUTOSIZE: bool = false;
 
-    fn init(
-        core: Core,
-        _flags: Self::Flags,
-    ) -> (Self, Command<cosmic::app::Message<Self::Message>>) {
-        let mut gaps = spin_button::Model::default().max(99).min(0).step(1);
-        gaps.value = core.system_theme().cosmic().gaps.1 as i32;
-        let mut active_hint = spin_button::Model::default().max(99).min(0).step(1);
-        active_hint.value = core.system_theme().cosmic().active_hint as i32;
+    fn init(core: &Core) -> (Self, Command<Message>) {
+        let mut outer_gap = spin_button::Model::default().max(99).min(0).step(1);
+        outer_gap.value = core.system_theme().cosmic().gaps.0 as i32;
+        let mut inner_gap = spin_button::Model::default().max(99).min(0).step(1);
+        inner_gap.value = core.system_theme().cosmic().gaps.1 as i32;
+        let mut active_hint_width = spin_button::Model::default().max(99).min(0).step(1);
+        active_hint_width.value = core.system_theme().cosmic().active_hint as i32;
         let config_helper =
             Config::new("com.system76.CosmicComp", CosmicCompConfig::VERSION).unwrap();
         let mut config = CosmicCompConfig::get_entry(&config_helper).unwrap_or_else(|(errs, c)| {
@@ -112,8 +222,6 @@ impl cosmic::Application for Window {
         });
 
         let window = Self {
-            core,
-            popup: None,
             timeline: Default::default(),
             autotiled: config.autotile,
             config,
@@ -123,34 +231,49 @@ impl cosmic::Application for Window {
             workspace_tx: None,
             tile_windows: id::Toggler::unique(),
             active_hint: id::Toggler::unique(),
+            outer_gap,
+            inner_gap,
+            active_hint_width,
+            window_rules: WindowRules::new(),
+            exceptions: None,
+            editing: None,
+            popup_open: false,
         };
         (window, Command::none())
     }
 
-    fn on_close_requested(&self, id: Id) -> Option<Message> {
-        Some(Message::PopupClosed(id))
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        self.popup_open = event == PanelEvent::PopupOpened;
+        if self.popup_open {
+            self.exceptions = None;
+            self.editing = None;
+            self.timeline = Timeline::default();
+            self.tile_windows = id::Toggler::unique();
+            self.active_hint = id::Toggler::unique();
+        }
+        Command::none()
     }
 
-    fn subscription(&self) -> Subscription<Self::Message> {
-        let timeline = self
-            .timeline
-            .as_subscription()
-            .map(|(_, now)| Message::Frame(now));
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
         Subscription::batch(vec![
-            timeline,
-            self.core
-                .watch_config::<CosmicCompConfig>("com.system76.CosmicComp")
+            core.watch_config::<CosmicCompConfig>("com.system76.CosmicComp")
                 .map(|u| Message::MyConfigUpdate(Box::new(u.config))),
             wayland_subscription::workspaces().map(Message::WorkspaceUpdate),
         ])
     }
 
-    fn update(&mut self, message: Self::Message) -> Command<cosmic::app::Message<Self::Message>> {
+    fn popup_subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        self.timeline
+            .as_subscription()
+            .map(|(_, now)| Message::Frame(now))
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
         match message {
             Message::WorkspaceUpdate(msg) => match msg {
                 WorkspacesUpdate::State(state) => {
                     self.autotiled = matches!(state, TilingState::TilingEnabled);
-                    if self.popup.is_some() {
+                    if self.popup_open {
                         self.timeline
                             .set_chain(if self.autotiled {
                                 cosmic_time::chain::Toggler::on(self.tile_windows.clone(), 1.0)
@@ -167,32 +290,6 @@ impl cosmic::Application for Window {
                     error!("Workspaces subscription failed...");
                 }
             },
-            Message::TogglePopup => {
-                return if let Some(p) = self.popup.take() {
-                    destroy_popup(p)
-                } else {
-                    self.timeline = Timeline::default();
-                    self.tile_windows = id::Toggler::unique();
-                    self.active_hint = id::Toggler::unique();
-                    let new_id = Id::unique();
-                    self.popup.replace(new_id);
-                    let mut popup_settings =
-                        self.core
-                            .applet
-                            .get_popup_settings(Id::MAIN, new_id, None, None, None);
-                    popup_settings.positioner.size_limits = Limits::NONE
-                        .max_width(400.0)
-                        .min_width(300.0)
-                        .min_height(200.0)
-                        .max_height(1080.0);
-                    get_popup(popup_settings)
-                }
-            }
-            Message::PopupClosed(id) => {
-                if self.popup.as_ref() == Some(&id) {
-                    self.popup = None;
-                }
-            }
             Message::Frame(now) => self.timeline.now(now),
             Message::ToggleTileWindows(chain, toggled) => {
                 self.timeline.set_chain(chain).start();
@@ -229,7 +326,7 @@ impl cosmic::Application for Window {
                 }
 
                 if c.active_hint != self.config.active_hint {
-                    if self.popup.is_some() {
+                    if self.popup_open {
                         self.timeline
                             .set_chain(if c.active_hint {
                                 cosmic_time::chain::Toggler::on(self.active_hint.clone(), 1.0)
@@ -267,19 +364,101 @@ impl cosmic::Application for Window {
                     }
                 });
             }
+            Message::OuterGap(msg) => {
+                self.outer_gap.update(msg);
+                self.write_gaps();
+            }
+            Message::InnerGap(msg) => {
+                self.inner_gap.update(msg);
+                self.write_gaps();
+            }
+            Message::OpenExceptions => {
+                self.exceptions = Some(self.window_rules.exceptions());
+            }
+            Message::CloseExceptions => {
+                self.exceptions = None;
+                self.editing = None;
+            }
+            Message::ToggleException(i, enabled) => {
+                if let Some(exception) = self.exceptions.as_mut().and_then(|e| e.get_mut(i)) {
+                    exception.enabled = enabled;
+                    self.write_exceptions();
+                }
+            }
+            Message::RemoveException(i) => {
+                if let Some(exceptions) = self.exceptions.as_mut() {
+                    if i < exceptions.len() {
+                        exceptions.remove(i);
+                        self.editing = None;
+                        self.write_exceptions();
+                    }
+                }
+            }
+            Message::AddException => {
+                self.editing = Some((
+                    None,
+                    ApplicationException {
+                        enabled: true,
+                        ..Default::default()
+                    },
+                ));
+            }
+            Message::EditException(i) => {
+                self.editing = self
+                    .exceptions
+                    .as_ref()
+                    .and_then(|e| e.get(i))
+                    .map(|exception| (Some(i), exception.clone()));
+            }
+            Message::EditAppId(appid) => {
+                if let Some((_, exception)) = self.editing.as_mut() {
+                    exception.appid = appid;
+                }
+            }
+            Message::EditTitle(title) => {
+                if let Some((_, exception)) = self.editing.as_mut() {
+                    exception.title = title;
+                }
+            }
+            Message::SaveException => {
+                if let (Some((index, exception)), Some(exceptions)) =
+                    (self.editing.take(), self.exceptions.as_mut())
+                {
+                    match index.and_then(|i| exceptions.get_mut(i)) {
+                        Some(existing) => *existing = exception,
+                        None => exceptions.push(exception),
+                    }
+                    self.write_exceptions();
+                }
+            }
+            Message::CancelEdit => {
+                self.editing = None;
+            }
+            Message::ActiveHintWidth(msg) => {
+                self.active_hint_width.update(msg);
+                let active_hint = self.active_hint_width.value as u32;
+                thread::spawn(move || {
+                    if let Err(err) = theme::set_active_hint(active_hint) {
+                        error!(?err, "Failed to set active hint width {active_hint}");
+                    }
+                });
+            }
         }
         Command::none()
     }
 
-    fn view(&self) -> Element<Self::Message> {
-        self.core
-            .applet
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        core.applet
             .icon_button(if self.autotiled { ON } else { OFF })
-            .on_press(Message::TogglePopup)
+            .on_press(AppletMessage::TogglePopup)
             .into()
     }
 
-    fn view_window(&self, _id: Id) -> Element<Self::Message> {
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        if let Some(exceptions) = self.exceptions.as_ref() {
+            return self.exceptions_view(exceptions).map(AppletMessage::App);
+        }
+
         let new_workspace_behavior_button =
             segmented_control::horizontal(&self.new_workspace_behavior_model)
                 .on_activate(Message::NewWorkspace);
@@ -323,6 +502,15 @@ impl cosmic::Application for Window {
                 text(format!("{} + G", fl!("super"))).size(14),
             )),
             padded_control(divider::horizontal::default()),
+            padded_control(row!(
+                text(fl!("outer-gap")).size(14).width(Length::Fill),
+                spin_button(self.outer_gap.value.to_string(), Message::OuterGap),
+            )),
+            padded_control(row!(
+                text(fl!("inner-gap")).size(14).width(Length::Fill),
+                spin_button(self.inner_gap.value.to_string(), Message::InnerGap),
+            )),
+            padded_control(divider::horizontal::default()),
             padded_control(
                 anim!(
                     self.active_hint,
@@ -336,11 +524,29 @@ impl cosmic::Application for Window {
             ),
         ]
         .padding([8, 0]);
+        // The width only matters while the hint is shown
+        let content_list = if self.config.active_hint {
+            content_list.push(padded_control(row!(
+                text(fl!("active-hint-width")).size(14).width(Length::Fill),
+                spin_button(
+                    self.active_hint_width.value.to_string(),
+                    Message::ActiveHintWidth
+                ),
+            )))
+        } else {
+            content_list
+        };
+        let content_list = content_list
+            .push(padded_control(divider::horizontal::default()))
+            .push(
+                menu_button(text(fl!("floating-window-exceptions")).size(14))
+                    .on_press(Message::OpenExceptions),
+            );
 
-        self.core.applet.popup_container(content_list).into()
+        Element::from(content_list).map(AppletMessage::App)
     }
 
-    fn style(&self) -> Option<<Theme as application::StyleSheet>::Style> {
-        Some(cosmic::applet::style())
+    fn popup_limits(&self, _core: &Core) -> Limits {
+        PopupWidth::Standard.limits().min_height(200.0)
     }
 }
@@ -0,0 +1,54 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Rules for windows cosmic-comp always floats, shared with COSMIC Settings.
+
+use cosmic::cosmic_config::{Config, ConfigGet, ConfigSet};
+use serde::{Deserialize, Serialize};
+use std::thread;
+use tracing::error;
+
+const ID: &str = "com.system76.CosmicSettings.WindowRules";
+const CUSTOM_EXCEPTIONS: &str = "tiling_exception_custom";
+
+/// A window matching both regexes is never tiled. An empty regex matches
+/// anything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApplicationException {
+    pub appid: String,
+    pub title: String,
+    pub enabled: bool,
+}
+
+pub struct WindowRules {
+    config: Option<Config>,
+}
+
+impl WindowRules {
+    pub fn new() -> Self {
+        let config = Config::new(ID, 1)
+            .map_err(|err| error!(?err, "Failed to open window rules config"))
+            .ok();
+        Self { config }
+    }
+
+    pub fn exceptions(&self) -> Vec<ApplicationException> {
+        let Some(config) = self.config.as_ref() else {
+            return Vec::new();
+        };
+        config
+            .get::<Vec<ApplicationException>>(CUSTOM_EXCEPTIONS)
+            .unwrap_or_default()
+    }
+
+    pub fn set_exceptions(&self, exceptions: Vec<ApplicationException>) {
+        let Some(config) = self.config.clone() else {
+            return;
+        };
+        thread::spawn(move || {
+            if let Err(err) = config.set(CUSTOM_EXCEPTIONS, exceptions) {
+                error!(?err, "Failed to write window exceptions");
+            }
+        });
+    }
+}
@@ -1 +1,27 @@
-datetime-settings = Date, Time and Calendar Settings...
\ No newline at end of file
+datetime-settings = Date, Time and Calendar Settings...
+quick-add-placeholder = Add an event, like “Lunch Friday 12:30”
+quick-add-invalid = Enter a title for the event
+quick-add-added = Added to your calendar
+quick-add-saved = Saved to { $path }
+quick-add-failed = Failed to add the event
+toggle-calendar = Open the calendar
+show-week-numbers = Show week numbers
+secondary-calendar = Secondary calendar
+calendar-none = None
+calendar-hebrew = Hebrew
+calendar-islamic-civil = Islamic (civil)
+calendar-persian = Persian
+event-reminders = Event reminders
+remind = Remind
+minutes-before = { $minutes } min before
+untitled-event = Event
+calendar = Calendar
+event-starts-in = Starts in { $minutes } min, at { $time }
+events-starting = { $count } events starting soon
+next-event-minutes = Next event in { $minutes } min
+next-event-hours = Next event in { $hours } h { $minutes } min
+copy-time = Copy the date and time
+converter-placeholder = Unix timestamp or date, like 2024-06-14 12:30
+converter-invalid = Enter a Unix timestamp, or a date like 2024-06-14
+copy = Copy
+unix-epoch = Unix epoch
@@ -0,0 +1,364 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Upcoming events of the calendar, for reminders and the next event shown
+//! when hovering the clock.
+//!
+//! Events come from the personal calendar of Evolution Data Server and the ICS
+//! files events are saved to when it isn't running. Only events at a time of
+//! day are reminded of. Its D-Bus interface only lists recurring events as
+//! written, so their occurrences are found from their rules here, leaving out
+//! those excluded or changed on their own.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use zbus::{proxy, zvariant::Value, Connection};
+
+use crate::{calendar, fl, recurrence::Rule};
+
+/// How far ahead events are read, in hours.
+const LOOKAHEAD_HOURS: i64 = 24;
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AgendaEvent {
+    /// In local time.
+    pub start: NaiveDateTime,
+    pub summary: String,
+}
+
+/// The events starting within the next day, by when they start. An event in
+/// both the calendar and a file, or listed twice, is only kept once.
+pub async fn upcoming(directory: Option<String>) -> Vec<AgendaEvent> {
+    let lookahead = TimeDelta::hours(LOOKAHEAD_HOURS);
+    let now = Utc::now();
+    let mut objects = match calendar::eds_events(now, now + lookahead).await {
+        Ok(objects) => objects,
+        Err(err) => {
+            tracing::debug!(?err, "Failed to read events from Evolution Data Server");
+            Vec::new()
+        }
+    };
+    match calendar::file_events(directory).await {
+        Ok(files) => objects.extend(files),
+        Err(err) => tracing::warn!(?err, "Failed to read event files"),
+    }
+
+    let now = now.with_timezone(&Local).naive_local();
+    let end = now + lookahead;
+    let vevents = objects
+        .iter()
+        .flat_map(|object| parse(object))
+        .collect::<Vec<_>>();
+    // Occurrences changed on their own replace those of their recurring event
+    let overrides = vevents
+        .iter()
+        .filter_map(|vevent| Some((vevent.uid.as_str(), vevent.recurrence_id?)))
+        .collect::<HashSet<_>>();
+    let mut events = vevents
+        .iter()
+        .flat_map(|vevent| {
+            let overrides = &overrides;
+            vevent
+                .starts(end)
+                .into_iter()
+                .filter(move |start| {
+                    vevent.recurrence_id.is_some()
+                        || !overrides.contains(&(vevent.uid.as_str(), *start))
+                })
+                .map(move |start| AgendaEvent {
+                    start,
+                    summary: vevent.summary.clone(),
+                })
+        })
+        .filter(|event| event.start > now && event.start <= end)
+        .collect::<Vec<_>>();
+    events.sort_by(|a, b| (a.start, &a.summary).cmp(&(b.start, &b.summary)));
+    events.dedup();
+    events
+}
+
+/// The time zone of a `DATE-TIME` value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Zone {
+    /// Floating times, which are in whatever the local time zone is.
+    #[default]
+    Local,
+    Utc,
+    Named(Tz),
+}
+
+impl Zone {
+    /// The zone of a value with the parameters of its property, and the value
+    /// without its UTC suffix.
+    fn of<'a>(value: &'a str, params: &str) -> (Self, &'a str) {
+        if let Some(utc) = value.strip_suffix('Z') {
+            return (Self::Utc, utc);
+        }
+        let Some(tzid) = param(params, "TZID") else {
+            return (Self::Local, value);
+        };
+        match time_zone(tzid) {
+            Some(tz) => (Self::Named(tz), value),
+            None => {
+                tracing::debug!("Unknown time zone `{tzid}`, taken as local time");
+                (Self::Local, value)
+            }
+        }
+    }
+
+    /// `time` in this zone, in local time. Times skipped by a daylight saving
+    /// change don't exist.
+    fn to_local(self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let time = match self {
+            Self::Local => return Some(time),
+            Self::Utc => Utc.from_utc_datetime(&time).with_timezone(&Local),
+            Self::Named(tz) => tz
+                .from_local_datetime(&time)
+                .earliest()?
+                .with_timezone(&Local),
+        };
+        Some(time.naive_local())
+    }
+}
+
+/// A time zone by its name in the tz database, also after a prefix like
+/// the `/freeassociation.sourceforge.net/` older versions of Evolution add.
+fn time_zone(tzid: &str) -> Option<Tz> {
+    let mut tzid = tzid;
+    loop {
+        if let Ok(tz) = tzid.parse() {
+            return Some(tz);
+        }
+        tzid = tzid.split_once('/')?.1;
+    }
+}
+
+/// The value of a property's parameter, without quotes.
+fn param<'a>(params: &'a str, name: &str) -> Option<&'a str> {
+    params.split(';').find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        (key == name).then(|| value.trim_matches('"'))
+    })
+}
+
+/// A `DATE-TIME` value, in the time zone it's in.
+fn parse_time(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()
+}
+
+/// A `DATE-TIME` value of a property, in local time.
+fn parse_local_time(value: &str, params: &str) -> Option<NaiveDateTime> {
+    let (zone, value) = Zone::of(value, params);
+    zone.to_local(parse_time(value)?)
+}
+
+/// An event as written, which may occur more than once.
+#[derive(Debug, Default)]
+struct VEvent {
+    uid: String,
+    summary: String,
+    /// In `zone`.
+    start: Option<NaiveDateTime>,
+    zone: Zone,
+    rule: Option<Rule>,
+    /// Occurrences left out of the rule, in local time.
+    exdates: Vec<NaiveDateTime>,
+    /// The occurrence of a recurring event this one replaces, in local time.
+    recurrence_id: Option<NaiveDateTime>,
+}
+
+impl VEvent {
+    /// When each occurrence up to `end` starts, in local time.
+    fn starts(&self, end: NaiveDateTime) -> Vec<NaiveDateTime> {
+        let Some(start) = self.start else {
+            return Vec::new();
+        };
+        let Some(rule) = self.rule.as_ref().filter(|_| self.recurrence_id.is_none()) else {
+            return self.zone.to_local(start).into_iter().collect();
+        };
+        // A date is until the end of that day
+        let until = rule.until.as_deref().and_then(|until| match until.len() {
+            8 => NaiveDate::parse_from_str(until, "%Y%m%d")
+                .ok()?
+                .and_hms_opt(23, 59, 59),
+            _ => {
+                let (zone, until) = match until.strip_suffix('Z') {
+                    Some(utc) => (Zone::Utc, utc),
+                    None => (self.zone, until),
+                };
+                zone.to_local(parse_time(until)?)
+            }
+        });
+        let end = until.map_or(end, |until| until.min(end));
+        rule.occurrences(start)
+            .filter_map(|start| self.zone.to_local(start))
+            .take_while(|start| *start <= end)
+            .filter(|start| !self.exdates.contains(start))
+            .collect()
+    }
+}
+
+/// The timed events in an iCalendar object.
+fn parse(object: &str) -> Vec<VEvent> {
+    // Long lines are folded, continuing on lines starting with a space or tab
+    let unfolded = object
+        .replace("\r\n ", "")
+        .replace("\r\n\t", "")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+
+    let mut events = Vec::new();
+    let mut event = None::<VEvent>;
+    // Alarms within an event have properties of their own
+    let mut nested = 0;
+    for line in unfolded.lines() {
+        let line = line.trim_end_matches('\r');
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, params) = name.split_once(';').unwrap_or((name, ""));
+        match (name, event.as_mut()) {
+            ("BEGIN", None) if value == "VEVENT" => event = Some(VEvent::default()),
+            ("BEGIN", Some(_)) => nested += 1,
+            ("END", Some(_)) if nested > 0 => nested -= 1,
+            ("END", Some(_)) if value == "VEVENT" => events.extend(event.take()),
+            (_, Some(_)) if nested > 0 => {}
+            ("UID", Some(event)) => event.uid = value.to_string(),
+            ("SUMMARY", Some(event)) => event.summary = unescape(value),
+            ("DTSTART", Some(event)) if param(params, "VALUE") != Some("DATE") => {
+                let (zone, value) = Zone::of(value, params);
+                event.start = parse_time(value);
+                event.zone = zone;
+            }
+            ("RRULE", Some(event)) => event.rule = Rule::parse(value),
+            ("EXDATE", Some(event)) => event.exdates.extend(
+                value
+                    .split(',')
+                    .filter_map(|value| parse_local_time(value, params)),
+            ),
+            ("RECURRENCE-ID", Some(event)) => {
+                event.recurrence_id = parse_local_time(value, params);
+            }
+            _ => {}
+        }
+    }
+    // Only timed events are reminded of
+    events.retain(|event| event.start.is_some());
+    events
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+/// Whole minutes until `start`, rounded up.
+pub fn minutes_until(start: NaiveDateTime, now: NaiveDateTime) -> i64 {
+    ((start - now).num_seconds() + 59) / 60
+}
+
+/// Shows a reminder of upcoming events.
+pub async fn notify(summary: String, body: String) -> zbus::Result<()> {
+    let conn = Connection::session().await?;
+    NotificationsProxy::new(&conn)
+        .await?
+        .notify(
+            &fl!("calendar"),
+            0,
+            "x-office-calendar-symbolic",
+            &summary,
+            &body,
+            &[],
+            HashMap::new(),
+            -1,
+        )
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folded_lines_are_unfolded() {
+        let object = "BEGIN:VEVENT\r\n\
+                      UID:1\r\n\
+                      SUMMARY:A meeting with a title long enough to be fol\r\n \
+                      ded onto a se\r\n\tcond and third line\r\n\
+                      DTSTART:2024061\r\n 4T103000Z\r\n\
+                      END:VEVENT\r\n";
+        let events = parse(object);
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0].summary,
+            "A meeting with a title long enough to be folded onto a second and third line"
+        );
+        assert_eq!(events[0].start, parse_time("20240614T103000"));
+    }
+
+    #[test]
+    fn folded_lines_with_bare_newlines() {
+        let object = "BEGIN:VEVENT\nUID:1\nSUMMARY:Lun\n ch\nDTSTART:20240614T123000\nEND:VEVENT\n";
+        let events = parse(object);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Lunch");
+    }
+
+    #[test]
+    fn alarms_keep_their_own_properties() {
+        let object = "BEGIN:VEVENT\r\n\
+                      SUMMARY:Lunch\r\n\
+                      DTSTART:20240614T123000\r\n\
+                      BEGIN:VALARM\r\n\
+                      SUMMARY:Reminder\r\n\
+                      END:VALARM\r\n\
+                      END:VEVENT\r\n";
+        let events = parse(object);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].summary, "Lunch");
+    }
+
+    #[test]
+    fn all_day_events_are_left_out() {
+        let object = "BEGIN:VEVENT\r\n\
+                      SUMMARY:Holiday\r\n\
+                      DTSTART;VALUE=DATE:20240614\r\n\
+                      END:VEVENT\r\n";
+        assert!(parse(object).is_empty());
+    }
+}
@@ -0,0 +1,65 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Calendars shown alongside the Gregorian one, converting its dates with
+//! ICU4X.
+
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDate};
+use icu::{
+    calendar::{AnyCalendar, AnyCalendarKind, Date, Ref},
+    locid::Locale,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
+pub enum SecondaryCalendar {
+    #[default]
+    None,
+    Hebrew,
+    /// The arithmetic Islamic calendar, rather than one following
+    /// observations of the moon.
+    IslamicCivil,
+    /// The Solar Hijri calendar.
+    Persian,
+}
+
+impl SecondaryCalendar {
+    pub const ALL: [Self; 4] = [Self::None, Self::Hebrew, Self::IslamicCivil, Self::Persian];
+
+    fn kind(self) -> Option<AnyCalendarKind> {
+        match self {
+            Self::None => None,
+            Self::Hebrew => Some(AnyCalendarKind::Hebrew),
+            Self::IslamicCivil => Some(AnyCalendarKind::IslamicCivil),
+            Self::Persian => Some(AnyCalendarKind::Persian),
+        }
+    }
+
+    /// `locale`, formatting dates in this calendar.
+    pub fn locale(self, locale: &Locale) -> Option<Locale> {
+        let calendar = self.kind()?.as_bcp47_string();
+        Locale::from_str(&format!("{}-u-ca-{calendar}", locale.id)).ok()
+    }
+}
+
+/// Converts Gregorian dates to a secondary calendar.
+pub struct Converter {
+    calendar: AnyCalendar,
+}
+
+impl Converter {
+    pub fn new(calendar: SecondaryCalendar) -> Option<Self> {
+        Some(Self {
+            calendar: AnyCalendar::new(calendar.kind()?),
+        })
+    }
+
+    /// The day of the month of `date`.
+    pub fn day_of_month(&self, date: NaiveDate) -> Option<u32> {
+        let date =
+            Date::try_new_iso_date(date.year(), date.month() as u8, date.day() as u8).ok()?;
+        Some(date.to_calendar(Ref(&self.calendar)).day_of_month().0)
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Adding events to the personal calendar of Evolution Data Server, or to an
+//! ICS file when it isn't running, and reading them back for reminders.
+
+use std::{io, path::PathBuf};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use zbus::{proxy, Connection};
+
+use crate::quick_add::QuickEvent;
+
+/// The calendar Evolution Data Server always has.
+const SYSTEM_CALENDAR: &str = "system-calendar";
+
+#[proxy(
+    interface = "org.gnome.evolution.dataserver.CalendarFactory",
+    default_service = "org.gnome.evolution.dataserver.Calendar8",
+    default_path = "/org/gnome/evolution/dataserver/CalendarFactory"
+)]
+trait CalendarFactory {
+    /// Returns the object path and bus name of the calendar.
+    fn open_calendar(&self, source_uid: &str) -> zbus::Result<(String, String)>;
+}
+
+#[proxy(interface = "org.gnome.evolution.dataserver.Calendar")]
+trait Calendar {
+    fn open(&self) -> zbus::Result<Vec<String>>;
+
+    fn create_objects(&self, ics_objects: &[&str], opflags: u32) -> zbus::Result<Vec<String>>;
+
+    /// Returns the events matching an S-expression, in iCalendar format.
+    fn get_object_list(&self, sexp: &str) -> zbus::Result<Vec<String>>;
+}
+
+/// Where an event was saved.
+#[derive(Debug, Clone)]
+pub enum Saved {
+    Calendar,
+    File(PathBuf),
+}
+
+/// Adds `event` to the personal calendar, or writes it to an ICS file in
+/// `directory` if that fails.
+pub async fn add_event(event: QuickEvent, directory: Option<String>) -> io::Result<Saved> {
+    let uid = format!(
+        "{}-{}",
+        Utc::now().format("%Y%m%dT%H%M%S%.f"),
+        std::process::id()
+    );
+    let vevent = vevent(&event, &uid);
+
+    match add_to_eds(&vevent).await {
+        Ok(()) => return Ok(Saved::Calendar),
+        Err(err) => tracing::warn!(?err, "Failed to add event to Evolution Data Server"),
+    }
+
+    let directory = directory
+        .map(PathBuf::from)
+        .unwrap_or_else(default_directory);
+    tokio::fs::create_dir_all(&directory).await?;
+    let path = directory.join(format!("{uid}.ics"));
+    let calendar = format!(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//System76//COSMIC Time Applet//EN\r\n{vevent}END:VCALENDAR\r\n"
+    );
+    tokio::fs::write(&path, calendar).await?;
+    Ok(Saved::File(path))
+}
+
+async fn open_eds(conn: &Connection) -> zbus::Result<CalendarProxy<'_>> {
+    let factory = CalendarFactoryProxy::new(conn).await?;
+    let (path, service) = factory.open_calendar(SYSTEM_CALENDAR).await?;
+    let calendar = CalendarProxy::builder(conn)
+        .destination(service)?
+        .path(path)?
+        .build()
+        .await?;
+    calendar.open().await?;
+    Ok(calendar)
+}
+
+async fn add_to_eds(vevent: &str) -> zbus::Result<()> {
+    let conn = Connection::session().await?;
+    open_eds(&conn).await?.create_objects(&[vevent], 0).await?;
+    Ok(())
+}
+
+/// The events of the personal calendar that occur between `start` and `end`,
+/// in iCalendar format.
+pub async fn eds_events(start: DateTime<Utc>, end: DateTime<Utc>) -> zbus::Result<Vec<String>> {
+    let conn = Connection::session().await?;
+    let sexp = format!(
+        "(occur-in-time-range? (make-time \"{}\") (make-time \"{}\"))",
+        start.format("%Y%m%dT%H%M%SZ"),
+        end.format("%Y%m%dT%H%M%SZ")
+    );
+    open_eds(&conn).await?.get_object_list(&sexp).await
+}
+
+/// The events written to ICS files in `directory`, in iCalendar format.
+pub async fn file_events(directory: Option<String>) -> io::Result<Vec<String>> {
+    let directory = directory
+        .map(PathBuf::from)
+        .unwrap_or_else(default_directory);
+    let mut entries = match tokio::fs::read_dir(&directory).await {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut events = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "ics") {
+            events.push(tokio::fs::read_to_string(&path).await?);
+        }
+    }
+    Ok(events)
+}
+
+/// `$XDG_DATA_HOME/cosmic-applet-time/events`.
+fn default_directory() -> PathBuf {
+    std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .unwrap_or_default()
+        .join("cosmic-applet-time/events")
+}
+
+/// The event in iCalendar format, in local time. Timed events last an hour.
+fn vevent(event: &QuickEvent, uid: &str) -> String {
+    let (start, end) = match event.time {
+        Some(time) => {
+            let start = event.date.and_time(time);
+            let end = start + TimeDelta::hours(1);
+            (
+                format!("DTSTART:{}", start.format("%Y%m%dT%H%M%S")),
+                format!("DTEND:{}", end.format("%Y%m%dT%H%M%S")),
+            )
+        }
+        None => (
+            format!("DTSTART;VALUE=DATE:{}", event.date.format("%Y%m%d")),
+            format!(
+                "DTEND;VALUE=DATE:{}",
+                event.date.succ_opt().unwrap_or(event.date).format("%Y%m%d")
+            ),
+        ),
+    };
+    format!(
+        "BEGIN:VEVENT\r\nUID:{uid}@cosmic-applet-time\r\nDTSTAMP:{}\r\n{start}\r\n{end}\r\nSUMMARY:{}\r\nEND:VEVENT\r\n",
+        Utc::now().format("%Y%m%dT%H%M%SZ"),
+        escape(&event.summary)
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
@@ -3,6 +3,8 @@
 
 use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
 
+use crate::alt_calendar::SecondaryCalendar;
+
 #[derive(Debug, Clone, CosmicConfigEntry, PartialEq, Eq)]
 #[version = 1]
 pub struct TimeAppletConfig {
@@ -10,6 +12,18 @@ pub struct TimeAppletConfig {
     pub first_day_of_week: u8,
     pub show_date_in_top_panel: bool,
     pub show_weekday: bool,
+    /// Where events from the quick-add field are written as ICS files when
+    /// Evolution Data Server isn't running.
+    pub event_directory: Option<String>,
+    /// Shows the ISO week number of each row of the calendar.
+    pub show_week_numbers: bool,
+    /// Shows the day in this calendar under each day of the month.
+    pub secondary_calendar: SecondaryCalendar,
+    /// Reminds of upcoming events of the calendar, and shows the next one
+    /// when hovering the clock.
+    pub agenda_reminders: bool,
+    /// How long before an event starts it's reminded of.
+    pub reminder_minutes: u32,
 }
 
 impl Default for TimeAppletConfig {
@@ -19,6 +33,11 @@ impl Default for TimeAppletConfig {
             first_day_of_week: 6,
             show_date_in_top_panel: true,
             show_weekday: false,
+            event_directory: None,
+            show_week_numbers: false,
+            secondary_calendar: SecondaryCalendar::None,
+            agenda_reminders: false,
+            reminder_minutes: 10,
         }
     }
 }
@@ -1,9 +1,15 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
+mod agenda;
+mod alt_calendar;
+mod calendar;
 mod config;
 mod localize;
+mod quick_add;
+mod recurrence;
 mod time;
+mod timestamp;
 mod window;
 
 use window::Window;
@@ -11,5 +17,5 @@ use window::Window;
 pub fn run() -> cosmic::iced::Result {
     localize::localize();
 
-    cosmic::applet::run::<Window>(true, ())
+    cosmic_applet_helper::run_applet::<Window>()
 }
@@ -0,0 +1,202 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Parsing the quick-add field, like "Lunch Friday 12:30", into an event.
+//!
+//! Only English day names and times are understood: `today`, `tomorrow`,
+//! weekdays, `2024-06-14`, `12:30`, `3pm`, `3:30pm` and `noon`. The remaining
+//! words are the event's title.
+
+use chrono::{Datelike, NaiveDate, NaiveTime, TimeDelta, Weekday};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuickEvent {
+    pub summary: String,
+    pub date: NaiveDate,
+    /// `None` for an all-day event.
+    pub time: Option<NaiveTime>,
+}
+
+/// Parses `input`, placing the event on `default_date` unless it names a day.
+/// Weekdays are the next one from `today`, including today.
+pub fn parse(input: &str, today: NaiveDate, default_date: NaiveDate) -> Option<QuickEvent> {
+    let mut date = None;
+    let mut time = None;
+    let mut summary: Vec<&str> = Vec::new();
+
+    for word in input.split_whitespace() {
+        let lower = word
+            .trim_end_matches(|c: char| c == ',' || c == '.')
+            .to_lowercase();
+        if date.is_none() {
+            if let Some(d) = parse_date(&lower, today) {
+                date = Some(d);
+                drop_preposition(&mut summary);
+                continue;
+            }
+        }
+        if time.is_none() {
+            if let Some(t) = parse_time(&lower) {
+                time = Some(t);
+                drop_preposition(&mut summary);
+                continue;
+            }
+        }
+        summary.push(word);
+    }
+
+    if summary.is_empty() {
+        return None;
+    }
+
+    Some(QuickEvent {
+        summary: summary.join(" "),
+        date: date.unwrap_or(default_date),
+        time,
+    })
+}
+
+/// Drops the "at" of "Lunch at 12:30" or the "on" of "Lunch on Friday".
+fn drop_preposition(summary: &mut Vec<&str>) {
+    if summary.last().map_or(false, |w| {
+        w.eq_ignore_ascii_case("at") || w.eq_ignore_ascii_case("on")
+    }) {
+        summary.pop();
+    }
+}
+
+fn parse_date(word: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match word {
+        "today" => return Some(today),
+        "tomorrow" => return today.succ_opt(),
+        _ => {}
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(word, "%Y-%m-%d") {
+        return Some(date);
+    }
+    let weekday = parse_weekday(word)?;
+    let days = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    today.checked_add_signed(TimeDelta::days(days.into()))
+}
+
+fn parse_weekday(word: &str) -> Option<Weekday> {
+    const NAMES: [(&str, Weekday); 7] = [
+        ("monday", Weekday::Mon),
+        ("tuesday", Weekday::Tue),
+        ("wednesday", Weekday::Wed),
+        ("thursday", Weekday::Thu),
+        ("friday", Weekday::Fri),
+        ("saturday", Weekday::Sat),
+        ("sunday", Weekday::Sun),
+    ];
+    // Accepts "fri" as well as "friday".
+    NAMES
+        .iter()
+        .find(|(name, _)| word.len() >= 3 && name.starts_with(word))
+        .map(|(_, weekday)| *weekday)
+}
+
+fn parse_time(word: &str) -> Option<NaiveTime> {
+    if word == "noon" {
+        return NaiveTime::from_hms_opt(12, 0, 0);
+    }
+    let (digits, offset) = if let Some(digits) = word.strip_suffix("am") {
+        (digits, Some(0))
+    } else if let Some(digits) = word.strip_suffix("pm") {
+        (digits, Some(12))
+    } else {
+        (word, None)
+    };
+    let (hour, minute): (u32, u32) = match digits.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour.parse().ok()?, minute.parse().ok()?),
+        // A bare number is more likely part of the title than a time.
+        None if offset.is_some() => (digits.parse().ok()?, 0),
+        _ => return None,
+    };
+    let hour = match offset {
+        Some(offset) if (1..=12).contains(&hour) => hour % 12 + offset,
+        Some(_) => return None,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn time(hour: u32, minute: u32) -> Option<NaiveTime> {
+        NaiveTime::from_hms_opt(hour, minute, 0)
+    }
+
+    #[test]
+    fn twelve_am_and_pm() {
+        assert_eq!(parse_time("12am"), time(0, 0));
+        assert_eq!(parse_time("12:30am"), time(0, 30));
+        assert_eq!(parse_time("12pm"), time(12, 0));
+        assert_eq!(parse_time("12:30pm"), time(12, 30));
+        assert_eq!(parse_time("1am"), time(1, 0));
+        assert_eq!(parse_time("11pm"), time(23, 0));
+        assert_eq!(parse_time("0am"), None);
+        assert_eq!(parse_time("13pm"), None);
+    }
+
+    #[test]
+    fn bare_numbers_are_not_times() {
+        let today = date(2024, 6, 14);
+        let event = parse("Buy 3 apples", today, today).unwrap();
+        assert_eq!(event.summary, "Buy 3 apples");
+        assert_eq!(event.time, None);
+    }
+
+    #[test]
+    fn weekday_prefixes() {
+        // A Friday
+        let today = date(2024, 6, 14);
+        assert_eq!(parse_weekday("fri"), Some(Weekday::Fri));
+        assert_eq!(parse_weekday("thurs"), Some(Weekday::Thu));
+        assert_eq!(parse_weekday("fr"), None);
+        assert_eq!(parse_weekday("fridays"), None);
+        assert_eq!(parse_date("fri", today), Some(today));
+        assert_eq!(parse_date("mon", today), Some(date(2024, 6, 17)));
+        assert_eq!(parse_date("thu", today), Some(date(2024, 6, 20)));
+    }
+
+    #[test]
+    fn tomorrow_across_month_ends() {
+        assert_eq!(
+            parse_date("tomorrow", date(2024, 1, 31)),
+            Some(date(2024, 2, 1))
+        );
+        assert_eq!(
+            parse_date("tomorrow", date(2024, 2, 28)),
+            Some(date(2024, 2, 29))
+        );
+        assert_eq!(
+            parse_date("tomorrow", date(2023, 2, 28)),
+            Some(date(2023, 3, 1))
+        );
+        assert_eq!(
+            parse_date("tomorrow", date(2024, 12, 31)),
+            Some(date(2025, 1, 1))
+        );
+    }
+
+    #[test]
+    fn prepositions_are_dropped() {
+        let today = date(2024, 6, 14);
+        let event = parse("Lunch on Monday at 12:30", today, today).unwrap();
+        assert_eq!(
+            event,
+            QuickEvent {
+                summary: "Lunch".to_string(),
+                date: date(2024, 6, 17),
+                time: time(12, 30),
+            }
+        );
+    }
+}
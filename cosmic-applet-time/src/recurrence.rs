@@ -0,0 +1,199 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Occurrences of recurring events, from their `RRULE`.
+//!
+//! Every frequency from daily to yearly is supported, with `INTERVAL`,
+//! `COUNT` and `UNTIL`, `BYDAY` in weekly and monthly rules and `BYMONTHDAY`
+//! in monthly ones. Other parts are ignored, so an event using them may be
+//! found more often than it occurs.
+
+use chrono::{Datelike, Days, Months, NaiveDate, NaiveDateTime, Weekday};
+
+/// How many periods of a rule are looked at, at most, so rules only
+/// occurring on dates that don't exist end.
+const MAX_PERIODS: u64 = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    frequency: Frequency,
+    interval: u64,
+    count: Option<usize>,
+    /// The value of `UNTIL`, whose time zone is that of the event's start.
+    pub until: Option<String>,
+    /// Weekdays, with the one of the month they are for monthly rules,
+    /// counting from the end when negative.
+    by_day: Vec<(Option<i32>, Weekday)>,
+    /// Days of the month, counting from the end when negative.
+    by_month_day: Vec<i32>,
+}
+
+impl Rule {
+    /// Parses the value of an `RRULE`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut frequency = None;
+        let mut rule = Self {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+        };
+        for part in value.split(';') {
+            let (name, value) = part.split_once('=')?;
+            match name {
+                "FREQ" => {
+                    frequency = Some(match value {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        // Hourly and more often isn't used for events
+                        _ => return None,
+                    });
+                }
+                "INTERVAL" => rule.interval = value.parse().ok().filter(|i| *i > 0)?,
+                "COUNT" => rule.count = Some(value.parse().ok()?),
+                "UNTIL" => rule.until = Some(value.to_string()),
+                "BYDAY" => {
+                    rule.by_day = value.split(',').map(parse_weekday).collect::<Option<_>>()?;
+                }
+                "BYMONTHDAY" => {
+                    rule.by_month_day = value
+                        .split(',')
+                        .map(|day| day.parse().ok())
+                        .collect::<Option<_>>()?;
+                }
+                _ => {}
+            }
+        }
+        rule.frequency = frequency?;
+        Some(rule)
+    }
+
+    /// The start of every occurrence, in the time zone of `start`, which is
+    /// the first. `UNTIL` is left to the caller, which knows the time zones.
+    pub fn occurrences(&self, start: NaiveDateTime) -> impl Iterator<Item = NaiveDateTime> + '_ {
+        (0..MAX_PERIODS)
+            .flat_map(move |period| self.period(start, period))
+            .filter(move |time| *time >= start)
+            .take(self.count.unwrap_or(usize::MAX))
+    }
+
+    /// The occurrences in the `n`th period since `start`, in order.
+    fn period(&self, start: NaiveDateTime, n: u64) -> Vec<NaiveDateTime> {
+        let date = start.date();
+        let Some(n) = n.checked_mul(self.interval) else {
+            return Vec::new();
+        };
+        let mut dates = match self.frequency {
+            Frequency::Daily => date.checked_add_days(Days::new(n)).into_iter().collect(),
+            Frequency::Weekly => {
+                let Some(monday) = date
+                    .week(Weekday::Mon)
+                    .first_day()
+                    .checked_add_days(Days::new(n.saturating_mul(7)))
+                else {
+                    return Vec::new();
+                };
+                if self.by_day.is_empty() {
+                    vec![monday + Days::new(date.weekday().num_days_from_monday().into())]
+                } else {
+                    self.by_day
+                        .iter()
+                        .map(|(_, day)| monday + Days::new(day.num_days_from_monday().into()))
+                        .collect()
+                }
+            }
+            Frequency::Monthly => {
+                let Some(first) = u32::try_from(n)
+                    .ok()
+                    .and_then(|n| date.with_day(1)?.checked_add_months(Months::new(n)))
+                else {
+                    return Vec::new();
+                };
+                self.month_days(first, date.day())
+            }
+            Frequency::Yearly => i32::try_from(n)
+                .ok()
+                .and_then(|n| NaiveDate::from_ymd_opt(date.year() + n, date.month(), date.day()))
+                .into_iter()
+                .collect(),
+        };
+        dates.sort();
+        dates.dedup();
+        dates
+            .into_iter()
+            .map(|date| date.and_time(start.time()))
+            .collect()
+    }
+
+    /// The days of the month starting on `first`. Without `BYMONTHDAY` or
+    /// `BYDAY`, that's `day` of the month, unless the month is too short.
+    fn month_days(&self, first: NaiveDate, day: u32) -> Vec<NaiveDate> {
+        let length = first
+            .checked_add_months(Months::new(1))
+            .map_or(31, |next| (next - first).num_days() as i32);
+        let nth = |n: i32| {
+            let day = if n > 0 { n } else { length + n + 1 };
+            (1..=length)
+                .contains(&day)
+                .then(|| first.with_day(day as u32))
+                .flatten()
+        };
+
+        if !self.by_month_day.is_empty() {
+            return self.by_month_day.iter().filter_map(|n| nth(*n)).collect();
+        }
+        if self.by_day.is_empty() {
+            return first.with_day(day).into_iter().collect();
+        }
+        let mut dates = Vec::new();
+        for (ordinal, weekday) in &self.by_day {
+            let days = (1..=length)
+                .filter_map(|n| first.with_day(n as u32))
+                .filter(|date| date.weekday() == *weekday)
+                .collect::<Vec<_>>();
+            match ordinal {
+                Some(n) if *n > 0 => dates.extend(days.get(*n as usize - 1)),
+                Some(n) => dates.extend(
+                    days.len()
+                        .checked_sub(n.unsigned_abs() as usize)
+                        .map(|i| days[i]),
+                ),
+                None => dates.extend(days),
+            }
+        }
+        dates
+    }
+}
+
+/// A weekday of `BYDAY`, like `MO`, `2TU` or `-1FR`.
+fn parse_weekday(value: &str) -> Option<(Option<i32>, Weekday)> {
+    let split = value.len().checked_sub(2)?;
+    let (ordinal, day) = (value.get(..split)?, value.get(split..)?);
+    let ordinal = match ordinal {
+        "" => None,
+        ordinal => Some(ordinal.parse().ok().filter(|n| *n != 0)?),
+    };
+    let day = match day {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    };
+    Some((ordinal, day))
+}
@@ -0,0 +1,122 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Dates and times in the formats programs use, to copy, and converting Unix
+//! timestamps to dates and back.
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+
+use crate::fl;
+
+/// Timestamps beyond this many seconds are taken to be in milliseconds, as
+/// JavaScript and Java write them. It's in the year 5138.
+const MAX_SECONDS: i64 = 100_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeFormat {
+    /// Local time with its offset, like `2024-06-14T12:30:00+02:00`.
+    Iso8601,
+    /// UTC, like `2024-06-14T10:30:00Z`.
+    Rfc3339,
+    /// Seconds since 1970 began, in UTC.
+    UnixEpoch,
+}
+
+impl TimeFormat {
+    pub const ALL: [Self; 3] = [Self::Iso8601, Self::Rfc3339, Self::UnixEpoch];
+
+    pub fn name(self) -> String {
+        match self {
+            Self::Iso8601 => "ISO 8601".to_string(),
+            Self::Rfc3339 => "RFC 3339".to_string(),
+            Self::UnixEpoch => fl!("unix-epoch"),
+        }
+    }
+
+    pub fn format(self, time: DateTime<Local>) -> String {
+        match self {
+            Self::Iso8601 => time.to_rfc3339_opts(SecondsFormat::Secs, false),
+            Self::Rfc3339 => time
+                .with_timezone(&Utc)
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+            Self::UnixEpoch => time.timestamp().to_string(),
+        }
+    }
+}
+
+/// `input` converted: a Unix timestamp to the local time, or a date and time
+/// to its timestamp. Dates and times without an offset are local.
+pub fn convert(input: &str) -> Option<String> {
+    let input = input.trim();
+    if let Ok(timestamp) = input.parse::<i64>() {
+        let time = if timestamp.abs() > MAX_SECONDS {
+            Utc.timestamp_millis_opt(timestamp).single()?
+        } else {
+            Utc.timestamp_opt(timestamp, 0).single()?
+        };
+        return Some(TimeFormat::Iso8601.format(time.with_timezone(&Local)));
+    }
+
+    let time = DateTime::parse_from_rfc3339(input)
+        .map(|time| time.with_timezone(&Local))
+        .ok()
+        .or_else(|| {
+            let naive = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+                .or_else(|| {
+                    NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                        .ok()
+                        .and_then(|date| date.and_hms_opt(0, 0, 0))
+                })?;
+            Local.from_local_datetime(&naive).earliest()
+        })?;
+    Some(TimeFormat::UnixEpoch.format(time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timestamp(converted: &str) -> i64 {
+        DateTime::parse_from_rfc3339(converted).unwrap().timestamp()
+    }
+
+    #[test]
+    fn seconds_and_milliseconds() {
+        let seconds = convert("1718361000").unwrap();
+        let millis = convert("1718361000000").unwrap();
+        assert_eq!(seconds, millis);
+        assert_eq!(timestamp(&seconds), 1_718_361_000);
+    }
+
+    #[test]
+    fn largest_seconds_and_smallest_milliseconds() {
+        let seconds = convert(&MAX_SECONDS.to_string()).unwrap();
+        assert_eq!(timestamp(&seconds), MAX_SECONDS);
+        let millis = convert(&(MAX_SECONDS + 1).to_string()).unwrap();
+        assert_eq!(timestamp(&millis), MAX_SECONDS / 1000);
+    }
+
+    #[test]
+    fn negative_timestamps() {
+        assert_eq!(timestamp(&convert("-86400").unwrap()), -86_400);
+        assert_eq!(
+            timestamp(&convert("-1718361000000").unwrap()),
+            -1_718_361_000
+        );
+    }
+
+    #[test]
+    fn dates_to_timestamps() {
+        assert_eq!(
+            convert("2024-06-14T10:30:00Z").as_deref(),
+            Some("1718361000")
+        );
+        assert_eq!(
+            convert("2024-06-14T12:30:00+02:00").as_deref(),
+            Some("1718361000")
+        );
+        assert_eq!(convert("not a time"), None);
+    }
+}
@@ -1,27 +1,26 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
-use std::{borrow::Cow, str::FromStr};
+use std::{borrow::Cow, collections::HashSet, str::FromStr};
 
 use chrono::{Datelike, DurationRound, Timelike};
 use cosmic::{
-    app,
+    app::Core,
     applet::{cosmic_panel_config::PanelAnchor, menu_button, padded_control},
     cctk::sctk::reexports::calloop,
+    cosmic_config::{Config, CosmicConfigEntry},
     iced::{
-        subscription,
-        wayland::popup::{destroy_popup, get_popup},
+        clipboard, subscription,
         widget::{column, row, text, vertical_space},
-        window, Alignment, Length, Rectangle, Subscription,
+        Alignment, Command, Length, Rectangle, Subscription,
     },
     iced_core::alignment::{Horizontal, Vertical},
-    iced_style::application,
     iced_widget::{horizontal_rule, Column},
     widget::{
-        button, container, divider, grid, horizontal_space, icon, rectangle_tracker::*, Button,
-        Grid, Space,
+        button, container, divider, dropdown, grid, horizontal_space, icon, rectangle_tracker::*,
+        text_input, toggler, Button, Grid, Space,
     },
-    Command, Element, Theme,
+    Element,
 };
 
 use icu::{
@@ -36,30 +35,80 @@ use icu::{
     locid::Locale,
 };
 
-use crate::{config::TimeAppletConfig, fl, time::get_calender_first};
+use crate::{
+    agenda::{self, AgendaEvent},
+    alt_calendar::{Converter, SecondaryCalendar},
+    calendar::{self, Saved},
+    config::TimeAppletConfig,
+    fl, quick_add,
+    time::get_calender_first,
+    timestamp::{self, TimeFormat},
+};
 use cosmic::applet::token::subscription::{
     activation_token_subscription, TokenRequest, TokenUpdate,
 };
+use cosmic_applet_helper::{
+    global_shortcuts, AppletMessage, CosmicApplet, MouseArea, PanelEvent, PopupRequest, Shortcut,
+    ShortcutEvent,
+};
+
+const TOGGLE_CALENDAR: &str = "toggle-calendar";
+
+/// Width of the column of week numbers.
+const WEEK_NUMBER_WIDTH: f32 = 28.0;
+
+/// How long before events reminders may be shown, in minutes.
+const REMINDER_MINUTES: [u32; 5] = [5, 10, 15, 30, 60];
+
+/// How often upcoming events are read again, in minutes.
+const AGENDA_REFRESH_MINUTES: u32 = 5;
+
+fn secondary_calendar_label(calendar: SecondaryCalendar) -> String {
+    match calendar {
+        SecondaryCalendar::None => fl!("calendar-none"),
+        SecondaryCalendar::Hebrew => fl!("calendar-hebrew"),
+        SecondaryCalendar::IslamicCivil => fl!("calendar-islamic-civil"),
+        SecondaryCalendar::Persian => fl!("calendar-persian"),
+    }
+}
 
 /// In order to keep the understandable, the chrono types are not globals,
 /// to avoid conflict with icu
 
 pub struct Window {
-    core: cosmic::app::Core,
-    popup: Option<window::Id>,
+    /// Whether the popup is open.
+    popup_open: bool,
+    /// Whether the popup is the menu of formats to copy the time in, opened
+    /// by right-clicking, rather than the calendar.
+    copy_menu: bool,
     now: chrono::DateTime<chrono::Local>,
     date_selected: chrono::NaiveDate,
     rectangle_tracker: Option<RectangleTracker<u32>>,
     rectangle: Rectangle,
     token_tx: Option<calloop::channel::Sender<TokenRequest>>,
+    config_helper: Option<Config>,
     config: TimeAppletConfig,
     locale: Locale,
+    /// For `config.secondary_calendar`.
+    secondary_calendar: Option<Converter>,
+    secondary_calendar_labels: Vec<String>,
+    quick_add: String,
+    /// The outcome of the last quick-add.
+    quick_add_status: Option<String>,
+    reminder_labels: Vec<String>,
+    /// Events of the next day, when reminders are on.
+    agenda: Vec<AgendaEvent>,
+    /// Events already reminded of, until they start.
+    reminded: HashSet<AgendaEvent>,
+    /// Whether the pointer is over the clock.
+    hovered: bool,
+    /// A timestamp, or a date and time, to convert.
+    converter: String,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
-    TogglePopup,
-    CloseRequested(window::Id),
+    ToggleCopyMenu,
     Tick,
     Rectangle(RectangleUpdate<u32>),
     SelectDay(u32),
@@ -68,14 +117,33 @@ pub enum Message {
     OpenDateTimeSettings,
     Token(TokenUpdate),
     ConfigChanged(TimeAppletConfig),
+    QuickAddInput(String),
+    QuickAdd,
+    EventAdded(Result<Saved, String>),
+    Shortcut(ShortcutEvent),
+    ShowWeekNumbers(bool),
+    SetSecondaryCalendar(usize),
+    SetAgendaReminders(bool),
+    SetReminderMinutes(usize),
+    Agenda(Vec<AgendaEvent>),
+    Notified(Result<(), String>),
+    Hovered(bool),
+    /// Copies the current time.
+    CopyTime(TimeFormat),
+    Copy(String),
+    ConverterInput(String),
 }
 
 impl Window {
     fn format<D: Datelike>(&self, bag: Bag, date: &D) -> String {
+        self.format_in(&self.locale, bag, date)
+    }
+
+    /// Formats `date` for `locale`, which may ask for another calendar.
+    fn format_in<D: Datelike>(&self, locale: &Locale, bag: Bag, date: &D) -> String {
         let options = DateTimeFormatterOptions::Components(bag);
 
-        let dtf =
-            DateTimeFormatter::try_new_experimental(&self.locale.clone().into(), options).unwrap();
+        let dtf = DateTimeFormatter::try_new_experimental(&locale.clone().into(), options).unwrap();
 
         let datetime = DateTime::try_new_gregorian_datetime(
             date.year(),
@@ -95,18 +163,116 @@ impl Window {
             .expect("can't format value")
             .to_string()
     }
+
+    fn format_event_time(&self, event: &AgendaEvent) -> String {
+        let format = if self.config.military_time {
+            "%H:%M"
+        } else {
+            "%-I:%M %p"
+        };
+        event.start.format(format).to_string()
+    }
+
+    /// Reads upcoming events again, if reminders are on.
+    fn refresh_agenda(&self) -> Command<Message> {
+        if !self.config.agenda_reminders {
+            return Command::none();
+        }
+        Command::perform(
+            agenda::upcoming(self.config.event_directory.clone()),
+            |events| Message::Agenda(events),
+        )
+    }
+
+    /// Reminds of the events starting soon that weren't yet, in a single
+    /// notification.
+    fn remind(&mut self) -> Command<Message> {
+        let now = chrono::Local::now().naive_local();
+        self.reminded.retain(|event| event.start > now);
+        if !self.config.agenda_reminders {
+            return Command::none();
+        }
+        let before = chrono::TimeDelta::minutes(self.config.reminder_minutes.into());
+        let due = self
+            .agenda
+            .iter()
+            .filter(|event| event.start > now && event.start - now <= before)
+            .filter(|event| !self.reminded.contains(*event))
+            .cloned()
+            .collect::<Vec<_>>();
+        let (summary, body) = match due.as_slice() {
+            [] => return Command::none(),
+            [event] => (
+                if event.summary.is_empty() {
+                    fl!("untitled-event")
+                } else {
+                    event.summary.clone()
+                },
+                fl!(
+                    "event-starts-in",
+                    minutes = agenda::minutes_until(event.start, now),
+                    time = self.format_event_time(event)
+                ),
+            ),
+            events => (
+                fl!("events-starting", count = events.len()),
+                events
+                    .iter()
+                    .map(|event| format!("{} {}", self.format_event_time(event), event.summary))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+        };
+        self.reminded.extend(due);
+        Command::perform(agenda::notify(summary, body), |res| {
+            Message::Notified(res.map_err(|err| err.to_string()))
+        })
+    }
+
+    fn copy_menu_view(&self) -> Element<Message> {
+        let now = chrono::Local::now();
+        let mut content = column![padded_control(text(fl!("copy-time")).size(14))];
+        for format in TimeFormat::ALL {
+            content = content.push(
+                menu_button(
+                    row![
+                        text(format.name()).size(14).width(Length::Fill),
+                        text(format.format(now)).size(12),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .on_press(Message::CopyTime(format)),
+            );
+        }
+        content.padding([8, 0]).into()
+    }
+
+    /// When the next event starts, shown under the clock when hovering it.
+    fn next_event_label(&self) -> Option<String> {
+        if !self.config.agenda_reminders || !self.hovered {
+            return None;
+        }
+        let now = self.now.naive_local();
+        let next = self.agenda.iter().find(|event| event.start > now)?;
+        let minutes = agenda::minutes_until(next.start, now);
+        Some(if minutes < 60 {
+            fl!("next-event-minutes", minutes = minutes)
+        } else {
+            fl!(
+                "next-event-hours",
+                hours = minutes / 60,
+                minutes = minutes % 60
+            )
+        })
+    }
 }
 
-impl cosmic::Application for Window {
+impl CosmicApplet for Window {
     type Message = Message;
-    type Executor = cosmic::SingleThreadExecutor;
-    type Flags = ();
     const APP_ID: &'static str = "com.system76.CosmicAppletTime";
 
-    fn init(
-        core: app::Core,
-        _flags: Self::Flags,
-    ) -> (Self, cosmic::iced::Command<app::Message<Self::Message>>) {
+    fn init(_core: &Core) -> (Self, Command<Message>) {
         fn get_local() -> Result<Locale, Box<dyn std::error::Error>> {
             let locale = std::env::var("LANG")?;
             let locale = locale
@@ -128,35 +294,62 @@ impl cosmic::Application for Window {
 
         let now: chrono::prelude::DateTime<chrono::prelude::Local> = chrono::Local::now();
 
-        (
-            Self {
-                core,
-                popup: None,
-                now,
-                date_selected: chrono::NaiveDate::from(now.naive_local()),
-                rectangle_tracker: None,
-                rectangle: Rectangle::default(),
-                token_tx: None,
-                config: TimeAppletConfig::default(),
-                locale,
-            },
-            Command::none(),
-        )
-    }
-
-    fn core(&self) -> &cosmic::app::Core {
-        &self.core
-    }
-
-    fn core_mut(&mut self) -> &mut cosmic::app::Core {
-        &mut self.core
+        let config_helper = Config::new(Self::APP_ID, TimeAppletConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                TimeAppletConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let window = Self {
+            popup_open: false,
+            copy_menu: false,
+            now,
+            date_selected: chrono::NaiveDate::from(now.naive_local()),
+            rectangle_tracker: None,
+            rectangle: Rectangle::default(),
+            token_tx: None,
+            config_helper,
+            secondary_calendar: Converter::new(config.secondary_calendar),
+            secondary_calendar_labels: SecondaryCalendar::ALL
+                .into_iter()
+                .map(secondary_calendar_label)
+                .collect(),
+            config,
+            locale,
+            quick_add: String::new(),
+            quick_add_status: None,
+            reminder_labels: REMINDER_MINUTES
+                .into_iter()
+                .map(|minutes| fl!("minutes-before", minutes = minutes))
+                .collect(),
+            agenda: Vec::new(),
+            reminded: HashSet::new(),
+            hovered: false,
+            converter: String::new(),
+        };
+        let command = window.refresh_agenda();
+        (window, command)
     }
 
-    fn style(&self) -> Option<<Theme as application::StyleSheet>::Style> {
-        Some(cosmic::applet::style())
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        self.popup_open = event == PanelEvent::PopupOpened;
+        if !self.popup_open {
+            self.copy_menu = false;
+        } else if !self.copy_menu {
+            self.date_selected = chrono::NaiveDate::from(self.now.naive_local());
+            self.quick_add_status = None;
+        }
+        Command::none()
     }
 
-    fn subscription(&self) -> Subscription<Message> {
+    fn subscription(&self, core: &Core) -> Subscription<Message> {
         fn time_subscription() -> Subscription<()> {
             subscription::unfold("time-sub", (), move |()| async move {
                 let now = chrono::Local::now();
@@ -174,7 +367,16 @@ impl cosmic::Application for Window {
             rectangle_tracker_subscription(0).map(|e| Message::Rectangle(e.1)),
             time_subscription().map(|_| Message::Tick),
             activation_token_subscription(0).map(Message::Token),
-            self.core.watch_config(Self::APP_ID).map(|u| {
+            global_shortcuts(
+                0,
+                vec![Shortcut {
+                    id: TOGGLE_CALENDAR.to_string(),
+                    description: fl!("toggle-calendar"),
+                    preferred_trigger: Some("LOGO+c".to_string()),
+                }],
+            )
+            .map(Message::Shortcut),
+            core.watch_config(Self::APP_ID).map(|u| {
                 for err in u.errors {
                     tracing::error!(?err, "Error watching config");
                 }
@@ -183,46 +385,55 @@ impl cosmic::Application for Window {
         ])
     }
 
-    fn update(
-        &mut self,
-        message: Self::Message,
-    ) -> cosmic::iced::Command<app::Message<Self::Message>> {
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
         match message {
-            Message::TogglePopup => {
-                if let Some(p) = self.popup.take() {
-                    destroy_popup(p)
-                } else {
-                    self.date_selected = chrono::NaiveDate::from(self.now.naive_local());
-
-                    let new_id = window::Id::unique();
-                    self.popup.replace(new_id);
-
-                    let mut popup_settings = self.core.applet.get_popup_settings(
-                        window::Id::MAIN,
-                        new_id,
-                        None,
-                        None,
-                        None,
-                    );
-                    let Rectangle {
-                        x,
-                        y,
-                        width,
-                        height,
-                    } = self.rectangle;
-                    popup_settings.positioner.anchor_rect = Rectangle::<i32> {
-                        x: x as i32,
-                        y: y as i32,
-                        width: width as i32,
-                        height: height as i32,
-                    };
-                    get_popup(popup_settings)
+            Message::ToggleCopyMenu => {
+                // Otherwise the popup request closes whichever popup is open
+                if !self.popup_open {
+                    self.copy_menu = true;
                 }
+                Command::none()
+            }
+            Message::CopyTime(format) => clipboard::write(format.format(chrono::Local::now())),
+            Message::Copy(value) => clipboard::write(value),
+            Message::ConverterInput(input) => {
+                self.converter = input;
+                Command::none()
             }
             Message::Tick => {
                 self.now = chrono::Local::now();
+                if self.now.minute() % AGENDA_REFRESH_MINUTES == 0 {
+                    Command::batch(vec![self.remind(), self.refresh_agenda()])
+                } else {
+                    self.remind()
+                }
+            }
+            Message::Agenda(events) => {
+                self.agenda = events;
+                self.remind()
+            }
+            Message::Notified(Ok(())) => Command::none(),
+            Message::Notified(Err(err)) => {
+                tracing::error!(%err, "Failed to show event reminder");
+                Command::none()
+            }
+            Message::Hovered(hovered) => {
+                self.hovered = hovered;
                 Command::none()
             }
+            Message::Shortcut(event) => match event {
+                ShortcutEvent::Bound { conflicts, .. } => {
+                    if !conflicts.is_empty() {
+                        tracing::warn!(?conflicts, "Shortcuts already used by another application");
+                    }
+                    Command::none()
+                }
+                ShortcutEvent::Error(err) => {
+                    tracing::warn!(%err, "Failed to register shortcuts");
+                    Command::none()
+                }
+                ShortcutEvent::Activated(_) | ShortcutEvent::Deactivated(_) => Command::none(),
+            },
             Message::Rectangle(u) => {
                 match u {
                     RectangleUpdate::Rectangle(r) => {
@@ -234,12 +445,6 @@ impl cosmic::Application for Window {
                 }
                 Command::none()
             }
-            Message::CloseRequested(id) => {
-                if Some(id) == self.popup {
-                    self.popup = None;
-                }
-                Command::none()
-            }
             Message::SelectDay(_day) => {
                 if let Some(date) = self.date_selected.with_day(_day) {
                     self.date_selected = date;
@@ -303,17 +508,105 @@ impl cosmic::Application for Window {
                 Command::none()
             }
             Message::ConfigChanged(c) => {
+                if c.secondary_calendar != self.config.secondary_calendar {
+                    self.secondary_calendar = Converter::new(c.secondary_calendar);
+                }
+                let refresh = c.agenda_reminders && !self.config.agenda_reminders
+                    || c.event_directory != self.config.event_directory;
                 self.config = c;
+                if !self.config.agenda_reminders {
+                    self.agenda.clear();
+                    Command::none()
+                } else if refresh {
+                    self.refresh_agenda()
+                } else {
+                    self.remind()
+                }
+            }
+            Message::SetAgendaReminders(enabled) => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_agenda_reminders(helper, enabled) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+                if enabled {
+                    self.refresh_agenda()
+                } else {
+                    self.agenda.clear();
+                    Command::none()
+                }
+            }
+            Message::SetReminderMinutes(i) => {
+                let Some(&minutes) = REMINDER_MINUTES.get(i) else {
+                    return Command::none();
+                };
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_reminder_minutes(helper, minutes) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+                Command::none()
+            }
+            Message::ShowWeekNumbers(show) => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_show_week_numbers(helper, show) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
                 Command::none()
             }
+            Message::SetSecondaryCalendar(i) => {
+                let Some(&calendar) = SecondaryCalendar::ALL.get(i) else {
+                    return Command::none();
+                };
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_secondary_calendar(helper, calendar) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+                self.secondary_calendar = Converter::new(calendar);
+                Command::none()
+            }
+            Message::QuickAddInput(input) => {
+                self.quick_add = input;
+                self.quick_add_status = None;
+                Command::none()
+            }
+            Message::QuickAdd => {
+                let today = chrono::NaiveDate::from(self.now.naive_local());
+                let Some(event) = quick_add::parse(&self.quick_add, today, self.date_selected)
+                else {
+                    self.quick_add_status = Some(fl!("quick-add-invalid"));
+                    return Command::none();
+                };
+                self.date_selected = event.date;
+                Command::perform(
+                    calendar::add_event(event, self.config.event_directory.clone()),
+                    |r| Message::EventAdded(r.map_err(|e| e.to_string())),
+                )
+            }
+            Message::EventAdded(result) => {
+                self.quick_add_status = Some(match result {
+                    Ok(Saved::Calendar) => {
+                        self.quick_add.clear();
+                        fl!("quick-add-added")
+                    }
+                    Ok(Saved::File(path)) => {
+                        self.quick_add.clear();
+                        fl!("quick-add-saved", path = path.display().to_string())
+                    }
+                    Err(err) => {
+                        tracing::error!(%err, "Failed to add event");
+                        fl!("quick-add-failed")
+                    }
+                });
+                self.refresh_agenda()
+            }
         }
     }
 
-    fn view(&self) -> Element<Message> {
-        let horizontal = matches!(
-            self.core.applet.anchor,
-            PanelAnchor::Top | PanelAnchor::Bottom
-        );
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let horizontal = matches!(core.applet.anchor, PanelAnchor::Top | PanelAnchor::Bottom);
 
         let button = cosmic::widget::button(if horizontal {
             let mut time: Vec<Cow<'static, str>> = Vec::new();
@@ -346,12 +639,17 @@ impl cosmic::Application for Window {
 
             time.push(self.format(time_bag, &self.now).into());
 
+            // Vertical panels are too narrow for the next event
+            let mut clock = column![core.applet.text(time.concat())].align_items(Alignment::Center);
+            if let Some(next_event) = self.next_event_label() {
+                clock = clock.push(text(next_event).size(10));
+            }
             Element::from(
                 row!(
-                    self.core.applet.text(time.concat()),
+                    clock,
                     container(vertical_space(Length::Fixed(
-                        (self.core.applet.suggested_size(true).1
-                            + 2 * self.core.applet.suggested_padding(true))
+                        (core.applet.suggested_size(true).1
+                            + 2 * core.applet.suggested_padding(true))
                             as f32
                     )))
                 )
@@ -371,12 +669,12 @@ impl cosmic::Application for Window {
                 let formated = self.format(date_bag, &self.now);
 
                 for p in formated.split_whitespace() {
-                    elements.push(self.core.applet.text(p.to_owned()).into());
+                    elements.push(core.applet.text(p.to_owned()).into());
                 }
 
                 elements.push(
                     horizontal_rule(2)
-                        .width(self.core.applet.suggested_size(true).0)
+                        .width(core.applet.suggested_size(true).0)
                         .into(),
                 )
             }
@@ -399,7 +697,7 @@ impl cosmic::Application for Window {
             // todo: split using formatToParts when it is implemented
             // https://github.com/unicode-org/icu4x/issues/4936#issuecomment-2128812667
             for p in formated.split_whitespace().flat_map(|s| s.split(':')) {
-                elements.push(self.core.applet.text(p.to_owned()).into());
+                elements.push(core.applet.text(p.to_owned()).into());
             }
 
             let date_time_col = Column::with_children(elements)
@@ -410,8 +708,8 @@ impl cosmic::Application for Window {
                 column!(
                     date_time_col,
                     horizontal_space(Length::Fixed(
-                        (self.core.applet.suggested_size(true).0
-                            + 2 * self.core.applet.suggested_padding(true))
+                        (core.applet.suggested_size(true).0
+                            + 2 * core.applet.suggested_padding(true))
                             as f32
                     ))
                 )
@@ -419,21 +717,30 @@ impl cosmic::Application for Window {
             )
         })
         .padding(if horizontal {
-            [0, self.core.applet.suggested_padding(true)]
+            [0, core.applet.suggested_padding(true)]
         } else {
-            [self.core.applet.suggested_padding(true), 0]
+            [core.applet.suggested_padding(true), 0]
         })
-        .on_press(Message::TogglePopup)
+        .on_press(AppletMessage::TogglePopup)
         .style(cosmic::theme::Button::AppletIcon);
 
-        if let Some(tracker) = self.rectangle_tracker.as_ref() {
+        let button: Element<_> = if let Some(tracker) = self.rectangle_tracker.as_ref() {
             tracker.container(0, button).ignore_bounds(true).into()
         } else {
             button.into()
-        }
+        };
+        MouseArea::new(button)
+            .on_mouse_enter(Message::Hovered(true).into())
+            .on_mouse_exit(Message::Hovered(false).into())
+            .on_right_press(Message::ToggleCopyMenu.into())
+            .into()
     }
 
-    fn view_window(&self, _id: window::Id) -> Element<Message> {
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        if self.copy_menu {
+            return self.copy_menu_view().map(AppletMessage::App);
+        }
+
         let mut date_bag = Bag::empty();
         date_bag.month = Some(components::Month::Long);
         date_bag.day = Some(components::Day::NumericDayOfMonth);
@@ -446,6 +753,12 @@ impl cosmic::Application for Window {
 
         let day_of_week = text(self.format(day_of_week_bag, &self.date_selected)).size(14);
 
+        let mut date_column = column![date, day_of_week];
+        if let Some(locale) = self.config.secondary_calendar.locale(&self.locale) {
+            date_column = date_column
+                .push(text(self.format_in(&locale, date_bag, &self.date_selected)).size(12));
+        }
+
         let month_controls = row![
             button::icon(icon::from_name("go-previous-symbolic"))
                 .padding([0, 12])
@@ -472,6 +785,9 @@ impl cosmic::Application for Window {
 
         let mut day_iter = first_day.iter_days();
 
+        if self.config.show_week_numbers {
+            calender = calender.push(Space::with_width(Length::Fixed(WEEK_NUMBER_WIDTH)));
+        }
         for _ in 0..7 {
             calender = calender.push(
                 text(self.format(weekday_bag, &day_iter.next().unwrap()))
@@ -484,6 +800,12 @@ impl cosmic::Application for Window {
         }
         calender = calender.insert_row();
 
+        // Taller, to fit the secondary calendar's day under the day
+        let day_height = if self.secondary_calendar.is_some() {
+            44.0
+        } else {
+            36.0
+        };
         let mut day_iter = first_day.iter_days();
         for i in 0..42 {
             if i > 0 && i % 7 == 0 {
@@ -491,54 +813,191 @@ impl cosmic::Application for Window {
             }
 
             let date = day_iter.next().unwrap();
+            if i % 7 == 0 && self.config.show_week_numbers {
+                calender = calender.push(week_number(date, day_height));
+            }
             let is_month = date.month() == self.date_selected.month()
                 && date.year_ce() == self.date_selected.year_ce();
             let is_day = date.day() == self.date_selected.day() && is_month;
-
-            calender = calender.push(date_button(date.day(), is_month, is_day));
+            let secondary_day = self
+                .secondary_calendar
+                .as_ref()
+                .and_then(|calendar| calendar.day_of_month(date));
+
+            calender = calender.push(date_button(
+                date.day(),
+                secondary_day,
+                day_height,
+                is_month,
+                is_day,
+            ));
         }
 
         // content
-        let content_list = column![
-            row![
-                column![date, day_of_week],
-                Space::with_width(Length::Fill),
-                month_controls,
-            ]
-            .padding([12, 20]),
+        let mut content_list = column![
+            row![date_column, Space::with_width(Length::Fill), month_controls,].padding([12, 20]),
             calender.padding([0, 12].into()),
-            padded_control(divider::horizontal::default()),
-            menu_button(text(fl!("datetime-settings")).size(14))
-                .on_press(Message::OpenDateTimeSettings),
-        ]
-        .padding([8, 0]);
-
-        self.core
-            .applet
-            .popup_container(container(content_list))
-            .into()
+            padded_control(
+                text_input(fl!("quick-add-placeholder"), &self.quick_add)
+                    .on_input(Message::QuickAddInput)
+                    .on_paste(Message::QuickAddInput)
+                    .on_submit(Message::QuickAdd)
+            ),
+        ];
+        if let Some(status) = &self.quick_add_status {
+            content_list = content_list.push(padded_control(text(status).size(12)));
+        }
+        content_list = content_list.push(padded_control(
+            text_input(fl!("converter-placeholder"), &self.converter)
+                .on_input(Message::ConverterInput)
+                .on_paste(Message::ConverterInput),
+        ));
+        if let Some(converted) = timestamp::convert(&self.converter) {
+            content_list = content_list.push(padded_control(
+                row![
+                    text(converted.clone()).size(14).width(Length::Fill),
+                    button::text(fl!("copy")).on_press(Message::Copy(converted)),
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center),
+            ));
+        } else if !self.converter.trim().is_empty() {
+            content_list =
+                content_list.push(padded_control(text(fl!("converter-invalid")).size(12)));
+        }
+        let selected_calendar = SecondaryCalendar::ALL
+            .iter()
+            .position(|calendar| *calendar == self.config.secondary_calendar);
+        let content_list = content_list
+            .push(padded_control(divider::horizontal::default()))
+            .push(padded_control(
+                toggler(
+                    Some(fl!("show-week-numbers")),
+                    self.config.show_week_numbers,
+                    Message::ShowWeekNumbers,
+                )
+                .text_size(14)
+                .width(Length::Fill),
+            ))
+            .push(padded_control(
+                row![
+                    text(fl!("secondary-calendar")).size(14).width(Length::Fill),
+                    dropdown(
+                        &self.secondary_calendar_labels,
+                        selected_calendar,
+                        Message::SetSecondaryCalendar
+                    ),
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center),
+            ))
+            .push(padded_control(
+                toggler(
+                    Some(fl!("event-reminders")),
+                    self.config.agenda_reminders,
+                    Message::SetAgendaReminders,
+                )
+                .text_size(14)
+                .width(Length::Fill),
+            ));
+        let content_list = if self.config.agenda_reminders {
+            let selected_minutes = REMINDER_MINUTES
+                .iter()
+                .position(|minutes| *minutes == self.config.reminder_minutes);
+            content_list.push(padded_control(
+                row![
+                    text(fl!("remind")).size(14).width(Length::Fill),
+                    dropdown(
+                        &self.reminder_labels,
+                        selected_minutes,
+                        Message::SetReminderMinutes
+                    ),
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center),
+            ))
+        } else {
+            content_list
+        };
+        let content_list = content_list
+            .push(padded_control(divider::horizontal::default()))
+            .push(
+                menu_button(text(fl!("datetime-settings")).size(14))
+                    .on_press(Message::OpenDateTimeSettings),
+            )
+            .padding([8, 0]);
+
+        Element::from(content_list).map(AppletMessage::App)
+    }
+
+    fn popup_request(&self, message: &Message) -> Option<PopupRequest> {
+        match message {
+            Message::ToggleCopyMenu => Some(PopupRequest::Toggle),
+            Message::CopyTime(_) => Some(PopupRequest::Close),
+            Message::Shortcut(ShortcutEvent::Activated(id)) if id == TOGGLE_CALENDAR => {
+                Some(PopupRequest::Toggle)
+            }
+            _ => None,
+        }
     }
 
-    fn on_close_requested(&self, id: window::Id) -> Option<Message> {
-        Some(Message::CloseRequested(id))
+    /// The popup points at the clock, rather than the whole panel surface.
+    fn popup_anchor(&self, _core: &Core) -> Option<Rectangle<i32>> {
+        let Rectangle {
+            x,
+            y,
+            width,
+            height,
+        } = self.rectangle;
+        Some(Rectangle::<i32> {
+            x: x as i32,
+            y: y as i32,
+            width: width as i32,
+            height: height as i32,
+        })
     }
 }
 
-fn date_button(day: u32, is_month: bool, is_day: bool) -> Button<'static, Message> {
+/// The ISO week number of the calendar row starting on `date`, going by the
+/// week most of the row is in, as rows may not start on Monday.
+fn week_number(date: chrono::NaiveDate, height: f32) -> Element<'static, Message> {
+    let week = (date + chrono::Days::new(3)).iso_week().week();
+    text(format!("{week}"))
+        .size(12)
+        .width(Length::Fixed(WEEK_NUMBER_WIDTH))
+        .height(Length::Fixed(height))
+        .horizontal_alignment(Horizontal::Center)
+        .vertical_alignment(Vertical::Center)
+        .into()
+}
+
+fn date_button(
+    day: u32,
+    secondary_day: Option<u32>,
+    height: f32,
+    is_month: bool,
+    is_day: bool,
+) -> Button<'static, Message> {
     let style = if is_day {
         cosmic::widget::button::Style::Suggested
     } else {
         cosmic::widget::button::Style::Text
     };
 
+    let mut label = column![text(format!("{day}")).size(14.0)].align_items(Alignment::Center);
+    if let Some(secondary_day) = secondary_day {
+        label = label.push(text(format!("{secondary_day}")).size(10.0));
+    }
+
     let button = button(
-        text(format!("{day}"))
-            .size(14.0)
-            .horizontal_alignment(Horizontal::Center)
-            .vertical_alignment(Vertical::Center),
+        container(label)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .align_x(Horizontal::Center)
+            .align_y(Vertical::Center),
     )
     .style(style)
-    .height(Length::Fixed(36.0))
+    .height(Length::Fixed(height))
     .width(Length::Fixed(36.0));
 
     if is_month {
@@ -0,0 +1,21 @@
+checking = Checking for updates...
+up-to-date = Up to date
+updates-available = { $count ->
+    [one] 1 update available
+   *[other] { $count } updates available
+}
+restart-to-install = Updates will be installed on restart
+system = System
+applications = Applications
+failed = Failed: { $error }
+restart = Restart and install
+install-on-restart = Install on restart
+downloading = Downloading updates...
+check-now = Check now
+open-store = Open COSMIC Store...
+include-flatpak = Include Flatpak applications
+check-every = Check every
+hours = { $hours ->
+    [one] 1 hour
+   *[other] { $hours } hours
+}
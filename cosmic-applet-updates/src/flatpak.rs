@@ -0,0 +1,40 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Flatpak updates, from both user and system installations.
+
+use std::io;
+
+use crate::Update;
+
+pub async fn updates() -> io::Result<Vec<Update>> {
+    let output = match tokio::process::Command::new("flatpak")
+        .args(["remote-ls", "--updates", "--columns=application,version"])
+        .output()
+        .await
+    {
+        Ok(output) => output,
+        // Flatpak isn't installed
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut columns = line.split('\t');
+            let name = columns.next().filter(|name| !name.is_empty())?;
+            Some(Update {
+                name: name.to_string(),
+                version: columns.next().unwrap_or_default().to_string(),
+                security: false,
+                package_id: None,
+            })
+        })
+        .collect())
+}
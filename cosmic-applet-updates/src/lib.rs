@@ -0,0 +1,25 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+mod config;
+mod flatpak;
+mod localize;
+mod packagekit;
+mod window;
+
+use window::Window;
+
+/// A pending update, from PackageKit or Flatpak.
+#[derive(Debug, Clone)]
+pub struct Update {
+    pub name: String,
+    pub version: String,
+    pub security: bool,
+    /// PackageKit's id, or `None` for Flatpak applications.
+    pub package_id: Option<String>,
+}
+
+pub fn run() -> cosmic::iced::Result {
+    localize::localize();
+    cosmic_applet_helper::run_applet::<Window>()
+}
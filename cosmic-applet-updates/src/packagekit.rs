@@ -0,0 +1,160 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! System package updates through PackageKit, installed offline on the next
+//! restart.
+
+use cosmic::iced_futures::futures::StreamExt;
+use zbus::{proxy, zvariant::OwnedObjectPath, Connection};
+
+use crate::Update;
+
+/// `PK_FILTER_ENUM_NONE`, as a bitfield.
+const FILTER_NONE: u64 = 1 << 1;
+/// `PK_TRANSACTION_FLAG_ENUM_ONLY_TRUSTED` and `ONLY_DOWNLOAD`, as a bitfield.
+const FLAGS_DOWNLOAD: u64 = (1 << 1) | (1 << 3);
+/// `PK_INFO_ENUM_SECURITY`.
+const INFO_SECURITY: u32 = 8;
+
+#[proxy(
+    interface = "org.freedesktop.PackageKit",
+    default_service = "org.freedesktop.PackageKit",
+    default_path = "/org/freedesktop/PackageKit"
+)]
+trait PackageKit {
+    /// CreateTransaction method
+    fn create_transaction(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.PackageKit.Offline",
+    default_service = "org.freedesktop.PackageKit",
+    default_path = "/org/freedesktop/PackageKit"
+)]
+trait Offline {
+    /// Trigger method
+    fn trigger(&self, action: &str) -> zbus::Result<()>;
+
+    /// UpdatePrepared property
+    #[zbus(property)]
+    fn update_prepared(&self) -> zbus::Result<bool>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.PackageKit.Transaction",
+    default_service = "org.freedesktop.PackageKit"
+)]
+trait Transaction {
+    /// RefreshCache method
+    fn refresh_cache(&self, force: bool) -> zbus::Result<()>;
+
+    /// GetUpdates method
+    fn get_updates(&self, filter: u64) -> zbus::Result<()>;
+
+    /// UpdatePackages method
+    fn update_packages(&self, transaction_flags: u64, package_ids: &[&str]) -> zbus::Result<()>;
+
+    /// Package signal
+    #[zbus(signal)]
+    fn package(&self, info: u32, package_id: &str, summary: &str) -> zbus::Result<()>;
+
+    /// ErrorCode signal
+    #[zbus(signal)]
+    fn error_code(&self, code: u32, details: &str) -> zbus::Result<()>;
+
+    /// Finished signal
+    #[zbus(signal)]
+    fn finished(&self, exit: u32, runtime: u32) -> zbus::Result<()>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Manager {
+    /// Reboot method
+    fn reboot(&self, interactive: bool) -> zbus::Result<()>;
+}
+
+async fn transaction(conn: &Connection) -> zbus::Result<TransactionProxy<'static>> {
+    let path = PackageKitProxy::new(conn)
+        .await?
+        .create_transaction()
+        .await?;
+    TransactionProxy::builder(conn).path(path)?.build().await
+}
+
+/// Runs `start` on a new transaction, collecting the packages it reports.
+async fn run<F, Fut>(conn: &Connection, start: F) -> zbus::Result<Vec<(u32, String)>>
+where
+    F: FnOnce(TransactionProxy<'static>) -> Fut,
+    Fut: std::future::Future<Output = zbus::Result<()>>,
+{
+    let transaction = transaction(conn).await?;
+    // Subscribe before starting, so no signal is missed
+    let mut packages = transaction.receive_package().await?;
+    let mut errors = transaction.receive_error_code().await?;
+    let mut finished = transaction.receive_finished().await?;
+    start(transaction).await?;
+
+    let mut result = Vec::new();
+    loop {
+        tokio::select! {
+            // Packages are reported before the transaction finishes
+            biased;
+            Some(signal) = packages.next() => {
+                let args = signal.args()?;
+                result.push((*args.info(), args.package_id().to_string()));
+            }
+            Some(signal) = errors.next() => {
+                return Err(zbus::Error::Failure(signal.args()?.details().to_string()));
+            }
+            Some(_) = finished.next() => return Ok(result),
+            else => return Err(zbus::Error::Failure("transaction ended".to_string())),
+        }
+    }
+}
+
+/// Refreshes the package cache and lists available updates.
+pub async fn updates() -> zbus::Result<Vec<Update>> {
+    let conn = Connection::system().await?;
+    run(&conn, |t| async move { t.refresh_cache(false).await }).await?;
+    let packages = run(&conn, |t| async move { t.get_updates(FILTER_NONE).await }).await?;
+
+    Ok(packages
+        .into_iter()
+        .map(|(info, package_id)| {
+            // Package ids are `name;version;arch;repository`
+            let mut fields = package_id.split(';');
+            Update {
+                name: fields.next().unwrap_or_default().to_string(),
+                version: fields.next().unwrap_or_default().to_string(),
+                security: info == INFO_SECURITY,
+                package_id: Some(package_id.clone()),
+            }
+        })
+        .collect())
+}
+
+/// Downloads `package_ids` and prepares them to be installed on restart.
+pub async fn prepare_offline_update(package_ids: Vec<String>) -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    run(&conn, |t| async move {
+        let ids = package_ids.iter().map(String::as_str).collect::<Vec<_>>();
+        t.update_packages(FLAGS_DOWNLOAD, &ids).await
+    })
+    .await?;
+    OfflineProxy::new(&conn).await?.trigger("reboot").await
+}
+
+/// Whether an offline update is waiting for a restart.
+pub async fn update_prepared() -> zbus::Result<bool> {
+    let conn = Connection::system().await?;
+    OfflineProxy::new(&conn).await?.update_prepared().await
+}
+
+pub async fn restart() -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    ManagerProxy::new(&conn).await?.reboot(true).await
+}
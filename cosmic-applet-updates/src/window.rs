@@ -0,0 +1,378 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::time::Duration;
+
+use crate::{
+    config::{self, UpdatesConfig},
+    fl, flatpak, packagekit, Update,
+};
+use cosmic::{
+    app::Core,
+    applet::{cosmic_panel_config::PanelAnchor, menu_button, padded_control},
+    cosmic_config::{Config, CosmicConfigEntry},
+    iced::{
+        time,
+        widget::{column, row},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_widget::{Column, Row},
+    widget::{
+        divider, icon, scrollable,
+        segmented_button::{Entity, SingleSelectModel},
+        segmented_control, text, toggler,
+    },
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PopupRequest};
+
+const ICON: &str = "com.system76.CosmicAppletUpdates-symbolic";
+const INTERVALS: [u64; 3] = [1, 6, 24];
+
+#[derive(Default)]
+pub struct Window {
+    config: UpdatesConfig,
+    config_helper: Option<Config>,
+    system: Vec<Update>,
+    flatpak: Vec<Update>,
+    checking: bool,
+    /// Downloading updates to install on restart.
+    preparing: bool,
+    /// Updates are waiting to be installed on restart.
+    prepared: bool,
+    error: Option<String>,
+    intervals: SingleSelectModel,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    ConfigChanged(UpdatesConfig),
+    Check,
+    Checked(Result<Vec<Update>, String>, Result<Vec<Update>, String>),
+    Prepared(Result<bool, String>),
+    InstallOnRestart,
+    Restart,
+    OpenStore,
+    IncludeFlatpak(bool),
+    Interval(Entity),
+    Restarted(Result<(), String>),
+}
+
+impl Window {
+    fn count(&self) -> usize {
+        self.system.len() + self.flatpak.len()
+    }
+
+    fn check(&mut self) -> Command<Message> {
+        if self.checking || self.preparing {
+            return Command::none();
+        }
+        self.checking = true;
+        self.error = None;
+        let include_flatpak = self.config.include_flatpak;
+        Command::perform(
+            async move {
+                let flatpak = async {
+                    if include_flatpak {
+                        flatpak::updates().await
+                    } else {
+                        Ok(Vec::new())
+                    }
+                };
+                tokio::join!(packagekit::updates(), flatpak)
+            },
+            |(system, flatpak)| {
+                Message::Checked(
+                    system.map_err(|err| err.to_string()),
+                    flatpak.map_err(|err| err.to_string()),
+                )
+            },
+        )
+    }
+
+    fn update_view(update: &Update) -> Element<Message> {
+        let mut name = row![text(&update.name).size(14).width(Length::Fill)]
+            .spacing(4)
+            .align_items(Alignment::Center);
+        if update.security {
+            name = name.push(
+                icon::from_name("security-high-symbolic")
+                    .size(16)
+                    .symbolic(true),
+            );
+        }
+        padded_control(column![name, text(&update.version).size(10)]).into()
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = config::APP_ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::APP_ID, UpdatesConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                UpdatesConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let mut intervals = SingleSelectModel::default();
+        for hours in INTERVALS {
+            let entity = intervals
+                .insert()
+                .text(fl!("hours", hours = hours))
+                .data(hours)
+                .id();
+            if hours == config.check_interval {
+                intervals.activate(entity);
+            }
+        }
+
+        let mut window = Self {
+            config,
+            config_helper,
+            intervals,
+            ..Default::default()
+        };
+        let check = window.check();
+        let prepared = Command::perform(packagekit::update_prepared(), |res| {
+            Message::Prepared(res.map_err(|err| err.to_string()))
+        });
+        (window, Command::batch([check, prepared]))
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        Subscription::batch(vec![
+            core.watch_config::<UpdatesConfig>(config::APP_ID)
+                .map(|u| Message::ConfigChanged(u.config)),
+            time::every(Duration::from_secs(
+                self.config.check_interval.max(1) * 60 * 60,
+            ))
+            .map(|_| Message::Check),
+        ])
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::ConfigChanged(config) => {
+                let recheck = config.include_flatpak != self.config.include_flatpak;
+                self.config = config;
+                if recheck {
+                    return self.check();
+                }
+            }
+            Message::Check => {
+                return self.check();
+            }
+            Message::Checked(system, flatpak) => {
+                self.checking = false;
+                // Keep the previous list of a source that failed
+                match system {
+                    Ok(updates) => self.system = updates,
+                    Err(err) => {
+                        tracing::error!("Failed to check for system updates: {}", err);
+                        self.error = Some(err);
+                    }
+                }
+                match flatpak {
+                    Ok(updates) => self.flatpak = updates,
+                    Err(err) => {
+                        tracing::error!("Failed to check for Flatpak updates: {}", err);
+                        self.error = Some(err);
+                    }
+                }
+            }
+            Message::Prepared(res) => {
+                self.preparing = false;
+                match res {
+                    Ok(prepared) => self.prepared = prepared,
+                    Err(err) => {
+                        tracing::error!("Failed to prepare offline update: {}", err);
+                        self.error = Some(err);
+                    }
+                }
+            }
+            Message::InstallOnRestart => {
+                let package_ids = self
+                    .system
+                    .iter()
+                    .filter_map(|update| update.package_id.clone())
+                    .collect::<Vec<_>>();
+                if package_ids.is_empty() || self.preparing {
+                    return Command::none();
+                }
+                self.preparing = true;
+                self.error = None;
+                return Command::perform(
+                    async move {
+                        packagekit::prepare_offline_update(package_ids)
+                            .await
+                            .map(|()| true)
+                    },
+                    |res| Message::Prepared(res.map_err(|err| err.to_string())),
+                );
+            }
+            Message::Restart => {
+                return Command::perform(packagekit::restart(), |res| {
+                    Message::Restarted(res.map_err(|err| err.to_string()))
+                });
+            }
+            Message::OpenStore => {
+                cosmic::process::spawn(std::process::Command::new("cosmic-store"));
+            }
+            Message::IncludeFlatpak(include_flatpak) => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_include_flatpak(helper, include_flatpak) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+                if !include_flatpak {
+                    self.flatpak.clear();
+                }
+            }
+            Message::Interval(entity) => {
+                self.intervals.activate(entity);
+                if let (Some(helper), Some(hours)) = (
+                    &self.config_helper,
+                    self.intervals.data::<u64>(entity).copied(),
+                ) {
+                    if let Err(err) = self.config.set_check_interval(helper, hours) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+            Message::Restarted(res) => {
+                if let Err(err) = res {
+                    tracing::error!("Failed to restart: {}", err);
+                    self.error = Some(err);
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let btn = core
+            .applet
+            .icon_button(ICON)
+            .on_press(AppletMessage::TogglePopup)
+            .into();
+        let count = self.count();
+        if count == 0 {
+            return btn;
+        }
+        let count = text(count.to_string()).size(14).into();
+        match core.applet.anchor {
+            PanelAnchor::Left | PanelAnchor::Right => Column::with_children(vec![btn, count])
+                .align_items(Alignment::Center)
+                .into(),
+            PanelAnchor::Top | PanelAnchor::Bottom => Row::with_children(vec![btn, count])
+                .align_items(Alignment::Center)
+                .spacing(4)
+                .into(),
+        }
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let status = if self.checking {
+            fl!("checking")
+        } else if self.prepared {
+            fl!("restart-to-install")
+        } else if self.count() == 0 {
+            fl!("up-to-date")
+        } else {
+            fl!("updates-available", count = self.count())
+        };
+        let mut content = vec![padded_control(text(status).size(14)).into()];
+
+        let mut updates = Vec::new();
+        if !self.system.is_empty() {
+            updates.push(padded_control(text(fl!("system")).size(10)).into());
+            updates.extend(self.system.iter().map(Self::update_view));
+        }
+        if !self.flatpak.is_empty() {
+            updates.push(padded_control(text(fl!("applications")).size(10)).into());
+            updates.extend(self.flatpak.iter().map(Self::update_view));
+        }
+        if !updates.is_empty() {
+            content.push(
+                scrollable(Column::with_children(updates))
+                    .height(Length::Shrink)
+                    .into(),
+            );
+        }
+
+        if let Some(err) = &self.error {
+            content.push(padded_control(text(fl!("failed", error = err.as_str())).size(10)).into());
+        }
+
+        content.push(padded_control(divider::horizontal::default()).into());
+        if self.prepared {
+            content.push(
+                menu_button(text(fl!("restart")).size(14))
+                    .on_press(Message::Restart)
+                    .into(),
+            );
+        } else if !self.system.is_empty() {
+            content.push(
+                menu_button(
+                    text(if self.preparing {
+                        fl!("downloading")
+                    } else {
+                        fl!("install-on-restart")
+                    })
+                    .size(14),
+                )
+                .on_press_maybe((!self.preparing).then_some(Message::InstallOnRestart))
+                .into(),
+            );
+        }
+        content.push(
+            menu_button(text(fl!("check-now")).size(14))
+                .on_press_maybe((!self.checking && !self.preparing).then_some(Message::Check))
+                .into(),
+        );
+        content.push(
+            menu_button(text(fl!("open-store")).size(14))
+                .on_press(Message::OpenStore)
+                .into(),
+        );
+
+        content.push(padded_control(divider::horizontal::default()).into());
+        content.push(
+            padded_control(
+                toggler(
+                    Some(fl!("include-flatpak")),
+                    self.config.include_flatpak,
+                    Message::IncludeFlatpak,
+                )
+                .text_size(14)
+                .width(Length::Fill),
+            )
+            .into(),
+        );
+        content.push(
+            padded_control(
+                column![
+                    text(fl!("check-every")).size(14),
+                    segmented_control::horizontal(&self.intervals).on_activate(Message::Interval),
+                ]
+                .spacing(4),
+            )
+            .into(),
+        );
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+
+    fn popup_request(&self, message: &Message) -> Option<PopupRequest> {
+        matches!(message, Message::OpenStore).then_some(PopupRequest::Close)
+    }
+}
@@ -0,0 +1,13 @@
+wireguard = WireGuard
+tailscale = Tailscale
+connected = Connected
+connecting = Connecting...
+disconnected = Disconnected
+needs-login = Logged out, run "tailscale up" to log in
+exit-node = Exit node
+none = None
+devices = Devices
+latency = { $ms } ms
+no-vpns = No VPN connections
+failed = Failed: { $error }
+network-settings = Network settings...
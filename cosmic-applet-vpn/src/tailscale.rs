@@ -0,0 +1,232 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Tailscale status and preferences, through tailscaled's local API.
+
+use std::{collections::HashMap, time::Duration};
+
+use futures::future::join_all;
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::UnixStream,
+};
+
+const SOCKET: &str = "/var/run/tailscale/tailscaled.sock";
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    /// A response other than success, with its status line and body.
+    Http(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Json(err) => write!(f, "{}", err),
+            Error::Http(response) => write!(f, "{}", response),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+
+/// Makes an HTTP/1.0 request, so the response is read until the connection
+/// closes rather than decoded from chunks.
+async fn request(method: &str, path: &str, body: Option<&str>) -> Result<Vec<u8>, Error> {
+    let mut stream = UnixStream::connect(SOCKET).await?;
+    let body = body.unwrap_or_default();
+    let request = format!(
+        "{method} {path} HTTP/1.0\r\nHost: local-tailscaled.sock\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    let header_end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| Error::Http("malformed response".to_string()))?;
+    let headers = String::from_utf8_lossy(&response[..header_end]);
+    let status_line = headers.lines().next().unwrap_or_default();
+    let body = response.split_off(header_end + 4);
+    if !status_line
+        .split_whitespace()
+        .nth(1)
+        .map_or(false, |s| s.starts_with('2'))
+    {
+        return Err(Error::Http(format!(
+            "{}: {}",
+            status_line,
+            String::from_utf8_lossy(&body).trim()
+        )));
+    }
+    Ok(body)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct StatusResponse {
+    backend_state: String,
+    #[serde(rename = "Self")]
+    self_: Option<PeerResponse>,
+    #[serde(default)]
+    peer: Option<HashMap<String, PeerResponse>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PeerResponse {
+    #[serde(rename = "ID")]
+    id: String,
+    host_name: String,
+    #[serde(rename = "TailscaleIPs", default)]
+    tailscale_ips: Option<Vec<String>>,
+    #[serde(default)]
+    online: bool,
+    #[serde(default)]
+    exit_node: bool,
+    #[serde(default)]
+    exit_node_option: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PingResponse {
+    #[serde(default)]
+    latency_seconds: f64,
+    #[serde(default)]
+    err: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Peer {
+    /// Stable node ID, used to pick an exit node.
+    pub id: String,
+    pub name: String,
+    pub ip: Option<String>,
+    pub online: bool,
+    pub exit_node_option: bool,
+    pub latency: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Tailscale {
+    pub running: bool,
+    /// Needs the user to log in with `tailscale up`.
+    pub needs_login: bool,
+    pub ips: Vec<String>,
+    pub peers: Vec<Peer>,
+    /// ID of the peer used as an exit node.
+    pub exit_node: Option<String>,
+}
+
+async fn ping(ip: String) -> Option<Duration> {
+    let path = format!("/localapi/v0/ping?ip={ip}&type=disco");
+    let body = tokio::time::timeout(PING_TIMEOUT, request("POST", &path, None))
+        .await
+        .ok()?
+        .ok()?;
+    let response: PingResponse = serde_json::from_slice(&body).ok()?;
+    response
+        .err
+        .is_empty()
+        .then(|| Duration::from_secs_f64(response.latency_seconds))
+}
+
+/// Tailscale's status, or `None` when it isn't installed. Online peers are
+/// pinged when `latency` is set.
+pub async fn status(latency: bool) -> Result<Option<Tailscale>, Error> {
+    let body = match request("GET", "/localapi/v0/status", None).await {
+        Ok(body) => body,
+        Err(Error::Io(err))
+            if matches!(
+                err.kind(),
+                std::io::ErrorKind::NotFound | std::io::ErrorKind::ConnectionRefused
+            ) =>
+        {
+            return Ok(None)
+        }
+        Err(err) => return Err(err),
+    };
+    let status: StatusResponse = serde_json::from_slice(&body)?;
+
+    let mut exit_node = None;
+    let mut peers: Vec<Peer> = status
+        .peer
+        .unwrap_or_default()
+        .into_values()
+        .map(|peer| {
+            if peer.exit_node {
+                exit_node = Some(peer.id.clone());
+            }
+            Peer {
+                id: peer.id,
+                name: peer.host_name,
+                ip: peer.tailscale_ips.and_then(|ips| ips.into_iter().next()),
+                online: peer.online,
+                exit_node_option: peer.exit_node_option,
+                latency: None,
+            }
+        })
+        .collect();
+    peers.sort_by(|a, b| b.online.cmp(&a.online).then_with(|| a.name.cmp(&b.name)));
+
+    let running = status.backend_state == "Running";
+    if latency && running {
+        let pings = peers
+            .iter()
+            .map(|peer| match peer.ip.clone().filter(|_| peer.online) {
+                Some(ip) => futures::future::Either::Left(ping(ip)),
+                None => futures::future::Either::Right(futures::future::ready(None)),
+            });
+        for (peer, latency) in peers.iter_mut().zip(join_all(pings).await) {
+            peer.latency = latency;
+        }
+    }
+
+    Ok(Some(Tailscale {
+        running,
+        needs_login: status.backend_state == "NeedsLogin",
+        ips: status
+            .self_
+            .and_then(|self_| self_.tailscale_ips)
+            .unwrap_or_default(),
+        peers,
+        exit_node,
+    }))
+}
+
+/// Brings Tailscale up or down, which needs the user to be its operator.
+pub async fn set_running(running: bool) -> Result<(), Error> {
+    let body = serde_json::json!({ "WantRunningSet": true, "WantRunning": running });
+    request("PATCH", "/localapi/v0/prefs", Some(&body.to_string())).await?;
+    Ok(())
+}
+
+/// Routes traffic through the peer with this ID, or directly if `None`.
+pub async fn set_exit_node(id: Option<String>) -> Result<(), Error> {
+    let body = serde_json::json!({
+        "ExitNodeIDSet": true,
+        "ExitNodeID": id.unwrap_or_default(),
+        "ExitNodeIPSet": true,
+        "ExitNodeIP": "",
+    });
+    request("PATCH", "/localapi/v0/prefs", Some(&body.to_string())).await?;
+    Ok(())
+}
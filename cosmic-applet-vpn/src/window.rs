@@ -0,0 +1,330 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::time::Duration;
+
+use crate::{
+    fl,
+    tailscale::{self, Peer, Tailscale},
+};
+use cosmic::{
+    app::Core,
+    applet::{menu_button, padded_control},
+    iced::{
+        time,
+        widget::{column, row},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_widget::Column,
+    widget::{divider, horizontal_space, icon, text, toggler},
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PanelEvent, PopupRequest};
+use cosmic_nm_client::wireguard::{self, WireGuard};
+
+const ID: &str = "com.system76.CosmicAppletVpn";
+const CONNECTED_ICON: &str = "network-vpn-symbolic";
+const DISCONNECTED_ICON: &str = "network-vpn-disconnected-symbolic";
+
+#[derive(Default)]
+pub struct Window {
+    wireguard: Vec<WireGuard>,
+    tailscale: Option<Tailscale>,
+    error: Option<String>,
+    popup_open: bool,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    Refresh,
+    WireGuard(Result<Vec<WireGuard>, String>),
+    Tailscale(Result<Option<Tailscale>, String>),
+    /// Connects or disconnects the WireGuard connection at the index.
+    ToggleWireGuard(usize, bool),
+    ToggleTailscale(bool),
+    ExitNode(Option<String>),
+    Changed(Result<(), String>),
+    OpenSettings,
+}
+
+impl Window {
+    fn is_connected(&self) -> bool {
+        self.wireguard.iter().any(|c| c.connected)
+            || self.tailscale.as_ref().map_or(false, |t| t.running)
+    }
+
+    /// Reads the state of every VPN, pinging Tailscale peers while the popup
+    /// shows their latency.
+    fn refresh(&self) -> Command<Message> {
+        let latency = self.popup_open;
+        Command::batch([
+            Command::perform(wireguard::connections(), |res| {
+                Message::WireGuard(res.map_err(|err| err.to_string()))
+            }),
+            Command::perform(tailscale::status(latency), |res| {
+                Message::Tailscale(res.map_err(|err| err.to_string()))
+            }),
+        ])
+    }
+
+    fn changed<E: ToString>(res: Result<(), E>) -> Message {
+        Message::Changed(res.map_err(|err| err.to_string()))
+    }
+
+    fn wireguard_view(&self) -> Vec<Element<Message>> {
+        let mut content = vec![padded_control(text(fl!("wireguard")).size(14)).into()];
+        for (i, connection) in self.wireguard.iter().enumerate() {
+            let status = match (&connection.active, connection.connected) {
+                (None, _) => fl!("disconnected"),
+                (Some(_), false) => fl!("connecting"),
+                (Some(_), true) if connection.addresses.is_empty() => fl!("connected"),
+                (Some(_), true) => connection.addresses.join(", "),
+            };
+            content.push(
+                padded_control(
+                    column![
+                        toggler(
+                            Some(connection.name.clone()),
+                            connection.active.is_some(),
+                            move |on| Message::ToggleWireGuard(i, on)
+                        )
+                        .text_size(14)
+                        .width(Length::Fill),
+                        text(status).size(10),
+                    ]
+                    .spacing(2),
+                )
+                .into(),
+            );
+        }
+        content
+    }
+
+    /// An exit node choice, for routing through `peer` or directly if `None`.
+    fn exit_node_button<'a>(&self, name: String, peer: Option<&'a Peer>) -> Element<'a, Message> {
+        let id = peer.map(|peer| &peer.id);
+        let selected = self
+            .tailscale
+            .as_ref()
+            .map_or(false, |t| t.exit_node.as_ref() == id);
+        let mut content = row![text(name).size(14), horizontal_space(Length::Fill)]
+            .spacing(8)
+            .align_items(Alignment::Center);
+        if selected {
+            content = content.push(icon::from_name("object-select-symbolic").size(16));
+        }
+        let online = peer.map_or(true, |peer| peer.online);
+        menu_button(content)
+            .on_press_maybe(online.then(|| Message::ExitNode(id.cloned())))
+            .into()
+    }
+
+    fn tailscale_view<'a>(&'a self, tailscale: &'a Tailscale) -> Vec<Element<'a, Message>> {
+        let status = if tailscale.needs_login {
+            fl!("needs-login")
+        } else if !tailscale.running {
+            fl!("disconnected")
+        } else {
+            tailscale.ips.join(", ")
+        };
+        let mut content = vec![padded_control(
+            column![
+                toggler(
+                    Some(fl!("tailscale")),
+                    tailscale.running,
+                    Message::ToggleTailscale
+                )
+                .text_size(14)
+                .width(Length::Fill),
+                text(status).size(10),
+            ]
+            .spacing(2),
+        )
+        .into()];
+        if !tailscale.running {
+            return content;
+        }
+
+        let exit_nodes = tailscale
+            .peers
+            .iter()
+            .filter(|peer| peer.exit_node_option)
+            .collect::<Vec<_>>();
+        if !exit_nodes.is_empty() {
+            content.push(padded_control(divider::horizontal::default()).into());
+            content.push(padded_control(text(fl!("exit-node")).size(14)).into());
+            content.push(self.exit_node_button(fl!("none"), None));
+            for peer in exit_nodes {
+                content.push(self.exit_node_button(peer.name.clone(), Some(peer)));
+            }
+        }
+
+        let online = tailscale
+            .peers
+            .iter()
+            .filter(|peer| peer.online)
+            .collect::<Vec<_>>();
+        if !online.is_empty() {
+            content.push(padded_control(divider::horizontal::default()).into());
+            content.push(padded_control(text(fl!("devices")).size(14)).into());
+            for peer in online {
+                let latency = peer
+                    .latency
+                    .map(|latency| fl!("latency", ms = latency.as_millis().to_string()))
+                    .unwrap_or_default();
+                content.push(
+                    padded_control(
+                        row![
+                            column![
+                                text(&peer.name).size(14),
+                                text(peer.ip.as_deref().unwrap_or_default()).size(10),
+                            ],
+                            horizontal_space(Length::Fill),
+                            text(latency).size(10),
+                        ]
+                        .spacing(8)
+                        .align_items(Alignment::Center),
+                    )
+                    .into(),
+                );
+            }
+        }
+        content
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let window = Self {
+            ..Default::default()
+        };
+        let command = window.refresh();
+        (window, command)
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        self.popup_open = event == PanelEvent::PopupOpened;
+        if self.popup_open {
+            self.error = None;
+            return self.refresh();
+        }
+        Command::none()
+    }
+
+    fn panel_subscription(&self, _core: &Core) -> Subscription<Self::Message> {
+        let interval = if self.popup_open {
+            Duration::from_secs(5)
+        } else {
+            Duration::from_secs(15)
+        };
+        time::every(interval).map(|_| Message::Refresh)
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::Refresh => {
+                return self.refresh();
+            }
+            Message::WireGuard(res) => match res {
+                Ok(connections) => self.wireguard = connections,
+                Err(err) => {
+                    tracing::error!("Failed to list WireGuard connections: {}", err);
+                    self.wireguard.clear();
+                }
+            },
+            Message::Tailscale(res) => match res {
+                Ok(tailscale) => self.tailscale = tailscale,
+                Err(err) => {
+                    tracing::error!("Failed to get Tailscale status: {}", err);
+                    self.tailscale = None;
+                }
+            },
+            Message::ToggleWireGuard(i, on) => {
+                let Some(connection) = self.wireguard.get(i) else {
+                    return Command::none();
+                };
+                return match (on, connection.active.clone()) {
+                    (true, None) => {
+                        Command::perform(wireguard::connect(connection.path.clone()), Self::changed)
+                    }
+                    (false, Some(active)) => {
+                        Command::perform(wireguard::disconnect(active), Self::changed)
+                    }
+                    _ => Command::none(),
+                };
+            }
+            Message::ToggleTailscale(on) => {
+                if let Some(tailscale) = self.tailscale.as_mut() {
+                    tailscale.running = on;
+                }
+                return Command::perform(tailscale::set_running(on), Self::changed);
+            }
+            Message::ExitNode(id) => {
+                if let Some(tailscale) = self.tailscale.as_mut() {
+                    tailscale.exit_node = id.clone();
+                }
+                return Command::perform(tailscale::set_exit_node(id), Self::changed);
+            }
+            Message::Changed(res) => {
+                if let Err(err) = res {
+                    tracing::error!("Failed to change VPN: {}", err);
+                    self.error = Some(err);
+                }
+                return self.refresh();
+            }
+            Message::OpenSettings => {
+                let mut cmd = std::process::Command::new("cosmic-settings");
+                cmd.arg("network");
+                cosmic::process::spawn(cmd);
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        core.applet
+            .icon_button(if self.is_connected() {
+                CONNECTED_ICON
+            } else {
+                DISCONNECTED_ICON
+            })
+            .on_press(AppletMessage::TogglePopup)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = Vec::new();
+
+        if !self.wireguard.is_empty() {
+            content.extend(self.wireguard_view());
+        }
+        if let Some(tailscale) = self.tailscale.as_ref() {
+            if !content.is_empty() {
+                content.push(padded_control(divider::horizontal::default()).into());
+            }
+            content.extend(self.tailscale_view(tailscale));
+        }
+        if content.is_empty() {
+            content.push(padded_control(text(fl!("no-vpns")).size(14)).into());
+        }
+        if let Some(err) = self.error.as_ref() {
+            content.push(padded_control(text(fl!("failed", error = err.as_str())).size(10)).into());
+        }
+
+        content.push(padded_control(divider::horizontal::default()).into());
+        content.push(
+            menu_button(text(fl!("network-settings")).size(14))
+                .on_press(Message::OpenSettings)
+                .into(),
+        );
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+
+    fn popup_request(&self, message: &Message) -> Option<PopupRequest> {
+        matches!(message, Message::OpenSettings).then_some(PopupRequest::Close)
+    }
+}
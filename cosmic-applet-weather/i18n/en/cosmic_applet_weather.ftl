@@ -0,0 +1,19 @@
+current-location = Current location
+humidity-wind = Humidity { $humidity }% · Wind { $wind }
+loading = Loading weather...
+unavailable = Weather unavailable
+settings = Settings
+search-location = City name
+search = Search
+use-current-location = Use current location
+celsius = Celsius
+fahrenheit = Fahrenheit
+clear = Clear
+partly-cloudy = Partly cloudy
+cloudy = Cloudy
+fog = Fog
+drizzle = Drizzle
+rain = Rain
+snow = Snow
+showers = Showers
+thunderstorm = Thunderstorm
@@ -0,0 +1,258 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Forecasts and place search from Open-Meteo, which needs no API key.
+
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::Deserialize;
+
+use crate::config::{Location, Units};
+
+const FORECAST_URL: &str = "https://api.open-meteo.com/v1/forecast";
+const GEOCODING_URL: &str = "https://geocoding-api.open-meteo.com/v1/search";
+const FORECAST_DAYS: usize = 5;
+
+/// Conditions from a WMO weather interpretation code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Clear,
+    PartlyCloudy,
+    Cloudy,
+    Fog,
+    Drizzle,
+    Rain,
+    Snow,
+    Showers,
+    Thunderstorm,
+}
+
+impl Condition {
+    fn from_wmo(code: u8) -> Self {
+        match code {
+            0 => Condition::Clear,
+            1 | 2 => Condition::PartlyCloudy,
+            3 => Condition::Cloudy,
+            45 | 48 => Condition::Fog,
+            51..=57 => Condition::Drizzle,
+            61..=67 => Condition::Rain,
+            71..=77 | 85 | 86 => Condition::Snow,
+            80..=82 => Condition::Showers,
+            95..=99 => Condition::Thunderstorm,
+            _ => Condition::Cloudy,
+        }
+    }
+
+    pub fn icon_name(self, is_day: bool) -> &'static str {
+        match (self, is_day) {
+            (Condition::Clear, true) => "weather-clear-symbolic",
+            (Condition::Clear, false) => "weather-clear-night-symbolic",
+            (Condition::PartlyCloudy, true) => "weather-few-clouds-symbolic",
+            (Condition::PartlyCloudy, false) => "weather-few-clouds-night-symbolic",
+            (Condition::Cloudy, _) => "weather-overcast-symbolic",
+            (Condition::Fog, _) => "weather-fog-symbolic",
+            (Condition::Drizzle | Condition::Showers, _) => "weather-showers-scattered-symbolic",
+            (Condition::Rain, _) => "weather-showers-symbolic",
+            (Condition::Snow, _) => "weather-snow-symbolic",
+            (Condition::Thunderstorm, _) => "weather-storm-symbolic",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Current {
+    pub temperature: f64,
+    pub condition: Condition,
+    pub is_day: bool,
+    pub humidity: f64,
+    pub wind_speed: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Hour {
+    pub time: NaiveDateTime,
+    pub temperature: f64,
+    pub condition: Condition,
+}
+
+#[derive(Debug, Clone)]
+pub struct Day {
+    pub date: NaiveDate,
+    pub condition: Condition,
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Forecast {
+    pub current: Current,
+    /// The next 24 hours.
+    pub hourly: Vec<Hour>,
+    pub daily: Vec<Day>,
+}
+
+mod response {
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    pub struct Forecast {
+        pub current: Current,
+        pub hourly: Hourly,
+        pub daily: Daily,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Current {
+        pub time: String,
+        pub temperature_2m: f64,
+        pub weather_code: u8,
+        pub is_day: u8,
+        pub relative_humidity_2m: f64,
+        pub wind_speed_10m: f64,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Hourly {
+        pub time: Vec<String>,
+        pub temperature_2m: Vec<f64>,
+        pub weather_code: Vec<u8>,
+    }
+
+    #[derive(Deserialize)]
+    pub struct Daily {
+        pub time: Vec<String>,
+        pub weather_code: Vec<u8>,
+        pub temperature_2m_min: Vec<f64>,
+        pub temperature_2m_max: Vec<f64>,
+    }
+}
+
+/// Open-Meteo returns local times without an offset when `timezone=auto`.
+fn parse_time(time: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M").ok()
+}
+
+pub async fn forecast(latitude: f64, longitude: f64, units: Units) -> reqwest::Result<Forecast> {
+    let mut query = vec![
+        ("latitude", latitude.to_string()),
+        ("longitude", longitude.to_string()),
+        (
+            "current",
+            "temperature_2m,weather_code,is_day,relative_humidity_2m,wind_speed_10m".to_string(),
+        ),
+        ("hourly", "temperature_2m,weather_code".to_string()),
+        (
+            "daily",
+            "weather_code,temperature_2m_min,temperature_2m_max".to_string(),
+        ),
+        ("timezone", "auto".to_string()),
+        ("forecast_days", FORECAST_DAYS.to_string()),
+    ];
+    if units == Units::Imperial {
+        query.push(("temperature_unit", "fahrenheit".to_string()));
+        query.push(("wind_speed_unit", "mph".to_string()));
+    }
+
+    let response = reqwest::Client::new()
+        .get(FORECAST_URL)
+        .query(&query)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<response::Forecast>()
+        .await?;
+
+    let now = parse_time(&response.current.time);
+    let hourly = response
+        .hourly
+        .time
+        .iter()
+        .zip(response.hourly.temperature_2m)
+        .zip(response.hourly.weather_code)
+        .filter_map(|((time, temperature), code)| {
+            Some(Hour {
+                time: parse_time(time)?,
+                temperature,
+                condition: Condition::from_wmo(code),
+            })
+        })
+        .skip_while(|hour| now.map_or(false, |now| hour.time + chrono::Duration::hours(1) <= now))
+        .take(24)
+        .collect();
+    let daily = response
+        .daily
+        .time
+        .iter()
+        .zip(response.daily.weather_code)
+        .zip(
+            response
+                .daily
+                .temperature_2m_min
+                .into_iter()
+                .zip(response.daily.temperature_2m_max),
+        )
+        .filter_map(|((date, code), (min, max))| {
+            Some(Day {
+                date: NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?,
+                condition: Condition::from_wmo(code),
+                min,
+                max,
+            })
+        })
+        .collect();
+
+    Ok(Forecast {
+        current: Current {
+            temperature: response.current.temperature_2m,
+            condition: Condition::from_wmo(response.current.weather_code),
+            is_day: response.current.is_day != 0,
+            humidity: response.current.relative_humidity_2m,
+            wind_speed: response.current.wind_speed_10m,
+        },
+        hourly,
+        daily,
+    })
+}
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    results: Vec<Place>,
+}
+
+#[derive(Deserialize)]
+struct Place {
+    name: String,
+    latitude: f64,
+    longitude: f64,
+    country: Option<String>,
+    admin1: Option<String>,
+}
+
+/// Places matching `name`, for choosing a location by hand.
+pub async fn search(name: String) -> reqwest::Result<Vec<Location>> {
+    let response = reqwest::Client::new()
+        .get(GEOCODING_URL)
+        .query(&[("name", name.as_str()), ("count", "5")])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<SearchResponse>()
+        .await?;
+
+    Ok(response
+        .results
+        .into_iter()
+        .map(|place| {
+            let name = [Some(place.name), place.admin1, place.country]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(", ");
+            Location {
+                name,
+                latitude: place.latitude,
+                longitude: place.longitude,
+            }
+        })
+        .collect())
+}
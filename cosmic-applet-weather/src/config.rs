@@ -0,0 +1,41 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
+pub const APP_ID: &str = "com.system76.CosmicAppletWeather";
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum Units {
+    #[default]
+    Metric,
+    Imperial,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct Location {
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, CosmicConfigEntry)]
+#[version = 1]
+pub struct WeatherConfig {
+    /// Location chosen by the user, instead of GeoClue's.
+    pub location: Option<Location>,
+    pub units: Units,
+    /// Minutes between forecast updates.
+    pub refresh_interval: u64,
+}
+
+impl Default for WeatherConfig {
+    fn default() -> Self {
+        Self {
+            location: None,
+            units: Units::default(),
+            refresh_interval: 30,
+        }
+    }
+}
@@ -0,0 +1,454 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use std::time::Duration;
+
+use crate::{
+    api::{self, Condition, Forecast},
+    config::{self, Location, Units, WeatherConfig},
+    fl,
+};
+use cosmic::{
+    app::Core,
+    applet::{cosmic_panel_config::PanelAnchor, menu_button, padded_control},
+    cosmic_config::{Config, CosmicConfigEntry},
+    iced::{
+        time,
+        widget::{column, row},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_widget::{Column, Row},
+    widget::{
+        button, divider, horizontal_space, icon,
+        segmented_button::{Entity, SingleSelectModel},
+        segmented_control, text, text_input,
+    },
+    Element,
+};
+use cosmic_applet_helper::{geoclue, AppletMessage, CosmicApplet, PanelEvent};
+
+const ICON: &str = "com.system76.CosmicAppletWeather-symbolic";
+
+#[derive(Default)]
+pub struct Window {
+    config: WeatherConfig,
+    config_helper: Option<Config>,
+    /// Location from GeoClue, when none was chosen.
+    geolocation: Option<(f64, f64)>,
+    forecast: Option<Forecast>,
+    loading: bool,
+    failed: bool,
+    settings_expanded: bool,
+    location_input: String,
+    search_results: Vec<Location>,
+    units: SingleSelectModel,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    ConfigChanged(WeatherConfig),
+    Refresh,
+    Located(Result<(f64, f64), String>),
+    Forecast(Result<Forecast, String>),
+    ToggleSettings,
+    LocationInput(String),
+    SearchLocation,
+    SearchResults(Result<Vec<Location>, String>),
+    SelectLocation(usize),
+    UseCurrentLocation,
+    Units(Entity),
+}
+
+fn condition_text(condition: Condition) -> String {
+    match condition {
+        Condition::Clear => fl!("clear"),
+        Condition::PartlyCloudy => fl!("partly-cloudy"),
+        Condition::Cloudy => fl!("cloudy"),
+        Condition::Fog => fl!("fog"),
+        Condition::Drizzle => fl!("drizzle"),
+        Condition::Rain => fl!("rain"),
+        Condition::Snow => fl!("snow"),
+        Condition::Showers => fl!("showers"),
+        Condition::Thunderstorm => fl!("thunderstorm"),
+    }
+}
+
+fn temperature(value: f64) -> String {
+    format!("{:.0}°", value)
+}
+
+impl Window {
+    fn refresh(&mut self) -> Command<Message> {
+        let location = self
+            .config
+            .location
+            .as_ref()
+            .map(|location| (location.latitude, location.longitude))
+            .or(self.geolocation);
+
+        self.loading = true;
+        if let Some((latitude, longitude)) = location {
+            Command::perform(
+                api::forecast(latitude, longitude, self.config.units),
+                |res| Message::Forecast(res.map_err(|err| err.to_string())),
+            )
+        } else {
+            Command::perform(geoclue::location(config::APP_ID), |res| {
+                Message::Located(res.map_err(|err| err.to_string()))
+            })
+        }
+    }
+
+    fn set_config<T>(
+        &mut self,
+        set: impl FnOnce(&mut WeatherConfig, &Config, T) -> Result<bool, cosmic::cosmic_config::Error>,
+        value: T,
+    ) {
+        if let Some(helper) = &self.config_helper {
+            if let Err(err) = set(&mut self.config, helper, value) {
+                tracing::error!(?err, "Error writing config");
+            }
+        }
+    }
+
+    fn current_view(&self, forecast: &Forecast) -> Element<Message> {
+        let current = &forecast.current;
+        let location = self
+            .config
+            .location
+            .as_ref()
+            .map_or_else(|| fl!("current-location"), |location| location.name.clone());
+        let wind_unit = match self.config.units {
+            Units::Metric => "km/h",
+            Units::Imperial => "mph",
+        };
+
+        padded_control(
+            row![
+                icon::from_name(current.condition.icon_name(current.is_day))
+                    .size(48)
+                    .symbolic(true),
+                column![
+                    text(location).size(14),
+                    text(format!(
+                        "{} · {}",
+                        temperature(current.temperature),
+                        condition_text(current.condition)
+                    ))
+                    .size(20),
+                    text(fl!(
+                        "humidity-wind",
+                        humidity = format!("{:.0}", current.humidity),
+                        wind = format!("{:.0} {}", current.wind_speed, wind_unit)
+                    ))
+                    .size(10),
+                ]
+                .spacing(2),
+            ]
+            .spacing(12)
+            .align_items(Alignment::Center),
+        )
+        .into()
+    }
+
+    fn hourly_view(forecast: &Forecast) -> Element<Message> {
+        let hours = forecast
+            .hourly
+            .iter()
+            .step_by(3)
+            .map(|hour| {
+                column![
+                    text(hour.time.format("%H:%M").to_string()).size(10),
+                    icon::from_name(hour.condition.icon_name(true))
+                        .size(16)
+                        .symbolic(true),
+                    text(temperature(hour.temperature)).size(12),
+                ]
+                .spacing(4)
+                .align_items(Alignment::Center)
+                .width(Length::Fill)
+                .into()
+            })
+            .collect::<Vec<_>>();
+        padded_control(Row::with_children(hours)).into()
+    }
+
+    fn daily_view(forecast: &Forecast) -> Vec<Element<Message>> {
+        forecast
+            .daily
+            .iter()
+            .map(|day| {
+                padded_control(
+                    row![
+                        text(day.date.format("%a").to_string())
+                            .size(14)
+                            .width(Length::Fixed(48.0)),
+                        icon::from_name(day.condition.icon_name(true))
+                            .size(16)
+                            .symbolic(true),
+                        text(condition_text(day.condition))
+                            .size(14)
+                            .width(Length::Fill),
+                        text(format!(
+                            "{} / {}",
+                            temperature(day.min),
+                            temperature(day.max)
+                        ))
+                        .size(14),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .into()
+            })
+            .collect()
+    }
+
+    fn settings_view(&self) -> Vec<Element<Message>> {
+        let mut content = vec![
+            padded_control(segmented_control::horizontal(&self.units).on_activate(Message::Units))
+                .into(),
+            padded_control(
+                row![
+                    text_input(fl!("search-location"), &self.location_input)
+                        .on_input(Message::LocationInput)
+                        .on_submit(Message::SearchLocation),
+                    button::standard(fl!("search")).on_press_maybe(
+                        (!self.location_input.trim().is_empty()).then_some(Message::SearchLocation)
+                    ),
+                ]
+                .spacing(8)
+                .align_items(Alignment::Center),
+            )
+            .into(),
+        ];
+        for (i, location) in self.search_results.iter().enumerate() {
+            content.push(
+                menu_button(text(&location.name).size(14))
+                    .on_press(Message::SelectLocation(i))
+                    .into(),
+            );
+        }
+        if self.config.location.is_some() {
+            content.push(
+                menu_button(text(fl!("use-current-location")).size(14))
+                    .on_press(Message::UseCurrentLocation)
+                    .into(),
+            );
+        }
+        content
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = config::APP_ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::APP_ID, WeatherConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                WeatherConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let mut units = SingleSelectModel::default();
+        for (label, value) in [
+            (fl!("celsius"), Units::Metric),
+            (fl!("fahrenheit"), Units::Imperial),
+        ] {
+            let entity = units.insert().text(label).data(value).id();
+            if value == config.units {
+                units.activate(entity);
+            }
+        }
+
+        let mut window = Self {
+            config,
+            config_helper,
+            units,
+            ..Default::default()
+        };
+        let command = window.refresh();
+        (window, command)
+    }
+
+    fn on_panel_event(&mut self, _core: &Core, event: PanelEvent) -> Command<Message> {
+        if event == PanelEvent::PopupOpened {
+            self.settings_expanded = false;
+        }
+        Command::none()
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        Subscription::batch(vec![
+            core.watch_config::<WeatherConfig>(config::APP_ID)
+                .map(|u| Message::ConfigChanged(u.config)),
+            time::every(Duration::from_secs(
+                self.config.refresh_interval.max(5) * 60,
+            ))
+            .map(|_| Message::Refresh),
+        ])
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::ConfigChanged(config) => {
+                let changed =
+                    config.location != self.config.location || config.units != self.config.units;
+                self.config = config;
+                if changed {
+                    return self.refresh();
+                }
+            }
+            Message::Refresh => {
+                if !self.loading {
+                    return self.refresh();
+                }
+            }
+            Message::Located(res) => match res {
+                Ok(location) => {
+                    self.geolocation = Some(location);
+                    return self.refresh();
+                }
+                Err(err) => {
+                    tracing::error!("Failed to get location: {}", err);
+                    self.loading = false;
+                    self.failed = true;
+                }
+            },
+            Message::Forecast(res) => {
+                self.loading = false;
+                match res {
+                    Ok(forecast) => {
+                        self.failed = false;
+                        self.forecast = Some(forecast);
+                    }
+                    Err(err) => {
+                        tracing::error!("Failed to get forecast: {}", err);
+                        self.failed = true;
+                    }
+                }
+            }
+            Message::ToggleSettings => {
+                self.settings_expanded = !self.settings_expanded;
+            }
+            Message::LocationInput(input) => {
+                self.location_input = input;
+            }
+            Message::SearchLocation => {
+                let name = self.location_input.trim().to_string();
+                if !name.is_empty() {
+                    return Command::perform(api::search(name), |res| {
+                        Message::SearchResults(res.map_err(|err| err.to_string()))
+                    });
+                }
+            }
+            Message::SearchResults(res) => match res {
+                Ok(results) => self.search_results = results,
+                Err(err) => tracing::error!("Failed to search for location: {}", err),
+            },
+            Message::SelectLocation(i) => {
+                if i < self.search_results.len() {
+                    let location = self.search_results.swap_remove(i);
+                    self.search_results.clear();
+                    self.location_input.clear();
+                    self.settings_expanded = false;
+                    self.set_config(WeatherConfig::set_location, Some(location));
+                    return self.refresh();
+                }
+            }
+            Message::UseCurrentLocation => {
+                self.set_config(WeatherConfig::set_location, None);
+                return self.refresh();
+            }
+            Message::Units(entity) => {
+                self.units.activate(entity);
+                if let Some(units) = self.units.data::<Units>(entity).copied() {
+                    self.set_config(WeatherConfig::set_units, units);
+                    return self.refresh();
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let Some(forecast) = self.forecast.as_ref() else {
+            return core
+                .applet
+                .icon_button(ICON)
+                .on_press(AppletMessage::TogglePopup)
+                .into();
+        };
+        let current = &forecast.current;
+        let btn = core
+            .applet
+            .icon_button(current.condition.icon_name(current.is_day))
+            .on_press(AppletMessage::TogglePopup)
+            .into();
+        let temperature = text(temperature(current.temperature)).size(14).into();
+        match core.applet.anchor {
+            PanelAnchor::Left | PanelAnchor::Right => Column::with_children(vec![btn, temperature])
+                .align_items(Alignment::Center)
+                .into(),
+            PanelAnchor::Top | PanelAnchor::Bottom => Row::with_children(vec![btn, temperature])
+                .align_items(Alignment::Center)
+                .spacing(4)
+                .into(),
+        }
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = Vec::new();
+
+        match self.forecast.as_ref() {
+            Some(forecast) => {
+                content.push(self.current_view(forecast));
+                content.push(padded_control(divider::horizontal::default()).into());
+                content.push(Self::hourly_view(forecast));
+                content.push(padded_control(divider::horizontal::default()).into());
+                content.extend(Self::daily_view(forecast));
+            }
+            None => {
+                let status = if self.failed {
+                    fl!("unavailable")
+                } else {
+                    fl!("loading")
+                };
+                content.push(padded_control(text(status).size(14)).into());
+            }
+        }
+
+        content.push(padded_control(divider::horizontal::default()).into());
+        content.push(
+            menu_button(
+                row![
+                    text(fl!("settings")).size(14).width(Length::Fill),
+                    horizontal_space(Length::Fixed(8.0)),
+                    icon::from_name(if self.settings_expanded {
+                        "go-up-symbolic"
+                    } else {
+                        "go-down-symbolic"
+                    })
+                    .size(16)
+                    .symbolic(true),
+                ]
+                .align_items(Alignment::Center),
+            )
+            .on_press(Message::ToggleSettings)
+            .into(),
+        );
+        if self.settings_expanded {
+            content.extend(self.settings_view());
+        }
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+}
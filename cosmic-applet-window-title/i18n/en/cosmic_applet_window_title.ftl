@@ -0,0 +1,5 @@
+minimize = Minimize
+maximize = Maximize
+restore = Restore
+close = Close
+show-title = Show window title
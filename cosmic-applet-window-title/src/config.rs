@@ -0,0 +1,25 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
+pub const APP_ID: &str = "com.system76.CosmicAppletWindowTitle";
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, CosmicConfigEntry)]
+#[version = 1]
+pub struct WindowTitleConfig {
+    /// Show the window's title after the app name, rather than the name alone.
+    pub show_title: bool,
+    /// Titles longer than this many characters are shortened.
+    pub max_length: usize,
+}
+
+impl Default for WindowTitleConfig {
+    fn default() -> Self {
+        Self {
+            show_title: true,
+            max_length: 48,
+        }
+    }
+}
@@ -0,0 +1,292 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+use crate::{
+    config::{self, WindowTitleConfig},
+    fl,
+    wayland::{wayland_subscription, ToplevelRequest, WaylandUpdate},
+};
+use cosmic::{
+    app::Core,
+    applet::{cosmic_panel_config::PanelAnchor, menu_button, padded_control},
+    cctk::{
+        cosmic_protocols::toplevel_info::v1::client::zcosmic_toplevel_handle_v1::{
+            State, ZcosmicToplevelHandleV1,
+        },
+        sctk::reexports::calloop,
+        toplevel_info::ToplevelInfo,
+    },
+    cosmic_config::{Config, CosmicConfigEntry},
+    desktop::DesktopEntryData,
+    iced::{
+        widget::{column, row},
+        Alignment, Command, Length, Subscription,
+    },
+    iced_widget::{Column, Row},
+    widget::{button, divider, text, toggler},
+    Element,
+};
+use cosmic_applet_helper::{AppletMessage, CosmicApplet, PopupRequest};
+
+#[derive(Default)]
+pub struct Window {
+    config: WindowTitleConfig,
+    config_helper: Option<Config>,
+    tx: Option<calloop::channel::Sender<ToplevelRequest>>,
+    toplevels: Vec<(ZcosmicToplevelHandleV1, ToplevelInfo)>,
+    /// The focused window, and its desktop entry.
+    focused: Option<(ZcosmicToplevelHandleV1, DesktopEntryData)>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Message {
+    ConfigChanged(WindowTitleConfig),
+    Wayland(WaylandUpdate),
+    Request(ToplevelRequest),
+    ShowTitle(bool),
+}
+
+fn truncate(s: &str, max_length: usize) -> String {
+    if s.chars().count() <= max_length {
+        s.to_string()
+    } else {
+        let mut s = s
+            .chars()
+            .take(max_length.saturating_sub(1))
+            .collect::<String>();
+        s.push('…');
+        s
+    }
+}
+
+impl Window {
+    fn info(&self, handle: &ZcosmicToplevelHandleV1) -> Option<&ToplevelInfo> {
+        self.toplevels
+            .iter()
+            .find(|(h, _)| h == handle)
+            .map(|(_, info)| info)
+    }
+
+    /// Follows focus, keeping the last window while none is focused, such as
+    /// when the popup is open.
+    fn update_focused(&mut self) {
+        let Some((handle, info)) = self
+            .toplevels
+            .iter()
+            .find(|(_, info)| info.state.contains(&State::Activated))
+        else {
+            if let Some((handle, _)) = &self.focused {
+                if self.info(handle).is_none() {
+                    self.focused = None;
+                }
+            }
+            return;
+        };
+        if self
+            .focused
+            .as_ref()
+            .is_some_and(|(h, data)| h == handle && data.id == info.app_id)
+        {
+            return;
+        }
+        let data = cosmic::desktop::load_applications_for_app_ids(
+            None,
+            std::iter::once(info.app_id.as_str()),
+            true,
+            false,
+        )
+        .remove(0);
+        self.focused = Some((handle.clone(), data));
+    }
+
+    fn request(&self, request: ToplevelRequest) {
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(request);
+        }
+    }
+}
+
+impl CosmicApplet for Window {
+    type Message = Message;
+    const APP_ID: &'static str = config::APP_ID;
+
+    fn init(_core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::APP_ID, WindowTitleConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                WindowTitleConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+
+        let window = Self {
+            config,
+            config_helper,
+            ..Default::default()
+        };
+        (window, Command::none())
+    }
+
+    fn subscription(&self, core: &Core) -> Subscription<Self::Message> {
+        Subscription::batch(vec![
+            wayland_subscription().map(Message::Wayland),
+            core.watch_config::<WindowTitleConfig>(config::APP_ID)
+                .map(|u| Message::ConfigChanged(u.config)),
+        ])
+    }
+
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
+        match message {
+            Message::ConfigChanged(config) => {
+                self.config = config;
+            }
+            Message::Wayland(update) => match update {
+                WaylandUpdate::Init(tx) => {
+                    self.tx = Some(tx);
+                }
+                WaylandUpdate::Finished => {
+                    self.tx = None;
+                    self.toplevels.clear();
+                    self.focused = None;
+                }
+                WaylandUpdate::Toplevel(handle, info) => {
+                    if let Some(toplevel) = self.toplevels.iter_mut().find(|(h, _)| *h == handle) {
+                        toplevel.1 = info;
+                    } else {
+                        self.toplevels.push((handle, info));
+                    }
+                    self.update_focused();
+                }
+                WaylandUpdate::Closed(handle) => {
+                    self.toplevels.retain(|(h, _)| *h != handle);
+                    self.update_focused();
+                }
+            },
+            Message::Request(request) => {
+                self.request(request);
+            }
+            Message::ShowTitle(show_title) => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_show_title(helper, show_title) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+        }
+        Command::none()
+    }
+
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let Some((info, data)) = self
+            .focused
+            .as_ref()
+            .and_then(|(handle, data)| Some((self.info(handle)?, data)))
+        else {
+            return Row::new().into();
+        };
+
+        let (icon_size, _) = core.applet.suggested_size(true);
+        let icon = data.icon.as_cosmic_icon().size(icon_size);
+        let horizontal = matches!(core.applet.anchor, PanelAnchor::Top | PanelAnchor::Bottom);
+        let content: Element<_> = if horizontal {
+            let label = if self.config.show_title && !info.title.is_empty() {
+                format!("{} — {}", data.name, info.title)
+            } else {
+                data.name.clone()
+            };
+            row![
+                icon,
+                text(truncate(&label, self.config.max_length)).size(14)
+            ]
+            .spacing(8)
+            .align_items(Alignment::Center)
+            .into()
+        } else {
+            icon.into()
+        };
+
+        button(content)
+            .padding(core.applet.suggested_padding(true))
+            .on_press(AppletMessage::TogglePopup)
+            .style(cosmic::theme::Button::AppletIcon)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let mut content = Vec::new();
+
+        if let Some((handle, data, info)) = self
+            .focused
+            .as_ref()
+            .and_then(|(handle, data)| Some((handle, data, self.info(handle)?)))
+        {
+            let maximized = info.state.contains(&State::Maximized);
+            content.push(
+                padded_control(
+                    row![
+                        data.icon.as_cosmic_icon().size(32),
+                        column![
+                            text(&data.name).size(14),
+                            text(truncate(&info.title, 64)).size(10),
+                        ]
+                        .spacing(2),
+                    ]
+                    .spacing(8)
+                    .align_items(Alignment::Center),
+                )
+                .into(),
+            );
+            content.push(padded_control(divider::horizontal::default()).into());
+            content.push(
+                menu_button(text(fl!("minimize")).size(14))
+                    .on_press(Message::Request(ToplevelRequest::Minimize(handle.clone())))
+                    .into(),
+            );
+            content.push(
+                menu_button(
+                    text(if maximized {
+                        fl!("restore")
+                    } else {
+                        fl!("maximize")
+                    })
+                    .size(14),
+                )
+                .on_press(Message::Request(ToplevelRequest::Maximize(
+                    handle.clone(),
+                    !maximized,
+                )))
+                .into(),
+            );
+            content.push(
+                menu_button(text(fl!("close")).size(14))
+                    .on_press(Message::Request(ToplevelRequest::Close(handle.clone())))
+                    .into(),
+            );
+            content.push(padded_control(divider::horizontal::default()).into());
+        }
+
+        content.push(
+            padded_control(
+                toggler(
+                    Some(fl!("show-title")),
+                    self.config.show_title,
+                    Message::ShowTitle,
+                )
+                .text_size(14)
+                .width(Length::Fill),
+            )
+            .into(),
+        );
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
+    }
+
+    fn popup_request(&self, message: &Message) -> Option<PopupRequest> {
+        matches!(message, Message::Request(_)).then_some(PopupRequest::Close)
+    }
+}
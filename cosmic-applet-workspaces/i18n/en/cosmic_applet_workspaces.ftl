@@ -1 +1,8 @@
 cosmic-applet-workspaces = Cosmic Workspaces
+display = Display
+display-numbers = Numbers
+display-dots = Dots
+display-pills = Names
+display-active-only = Active workspace only
+show-workspace-numbers = Show workspace numbers while held
+show-apps = Show open applications
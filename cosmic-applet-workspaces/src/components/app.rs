@@ -3,35 +3,57 @@
 
 use cctk::sctk::reexports::{calloop::channel::SyncSender, client::backend::ObjectId};
 use cosmic::{
-    applet::cosmic_panel_config::PanelAnchor,
+    app::Core,
+    applet::{cosmic_panel_config::PanelAnchor, menu_button, padded_control},
+    cosmic_config::{Config, CosmicConfigEntry},
+    desktop::IconSource,
     font::FONT_BOLD,
     iced::{
         alignment::{Horizontal, Vertical},
         event,
         mouse::{self, ScrollDelta},
-        widget::{button, column, row},
+        time,
+        widget::{button, column, container, mouse_area, row},
+        Alignment, Color, Command,
         Event::Mouse,
         Length, Subscription,
     },
-    iced_core::{Background, Border},
-    iced_style::application,
-    widget::{container, horizontal_space, vertical_space},
-    Command, Element, Theme,
+    iced_core::{Background, Border, Shadow},
+    iced_widget::{Column, Row},
+    widget::{divider, horizontal_space, icon, text, toggler, vertical_space},
+    Element, Theme,
+};
+use cosmic_applet_helper::{
+    global_shortcuts, AppletMessage, CosmicApplet, Shortcut, ShortcutEvent,
 };
 
 use cosmic_protocols::workspace::v1::client::zcosmic_workspace_handle_v1;
-use std::cmp::Ordering;
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
 use crate::{
-    config,
+    config::{self, DisplayMode, WorkspacesConfig},
+    fl,
     wayland::{WorkspaceEvent, WorkspaceList},
     wayland_subscription::{workspaces, WorkspacesUpdate},
 };
 
 use std::process::Command as ShellCommand;
 
+/// How long a workspace takes to appear or disappear.
+const TRANSITION: Duration = Duration::from_millis(200);
+/// Shortcut showing the workspace numbers while it's held, meant to be Super.
+const SHOW_NUMBERS: &str = "show-numbers";
+/// How much wider workspaces get while their numbers are shown.
+const NUMBERS_GROWTH: f32 = 0.5;
+/// The most app icons shown on a workspace.
+const MAX_APPS: usize = 3;
+
 pub fn run() -> cosmic::iced::Result {
-    cosmic::applet::run::<IcedWorkspacesApplet>(true, ())
+    cosmic_applet_helper::run_applet::<IcedWorkspacesApplet>()
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -40,11 +62,36 @@ pub enum Layout {
     Column,
 }
 
+type Workspace = (
+    String,
+    Option<zcosmic_workspace_handle_v1::State>,
+    ObjectId,
+    Vec<String>,
+);
+
+/// A workspace being added or removed, or the numbers being shown or hidden.
+struct Transition {
+    started: Instant,
+    adding: bool,
+}
+
 struct IcedWorkspacesApplet {
-    core: cosmic::app::Core,
     workspaces: WorkspaceList,
     workspace_tx: Option<SyncSender<WorkspaceEvent>>,
     layout: Layout,
+    config: WorkspacesConfig,
+    config_helper: Option<Config>,
+    transitions: HashMap<ObjectId, Transition>,
+    /// Removed workspaces, shown at their former position until they've faded
+    /// out.
+    removed: Vec<(usize, Workspace)>,
+    /// The numbers being shown or hidden, after the shortcut was pressed or
+    /// released.
+    numbers_transition: Option<Transition>,
+    numbers_held: bool,
+    now: Instant,
+    /// Icons of the apps open on the workspaces, by app ID.
+    app_icons: HashMap<String, IconSource>,
 }
 
 #[derive(Debug, Clone)]
@@ -53,47 +100,426 @@ enum Message {
     WorkspacePressed(ObjectId),
     WheelScrolled(ScrollDelta),
     WorkspaceOverview,
+    ConfigChanged(WorkspacesConfig),
+    SetDisplayMode(DisplayMode),
+    SetShowApps(bool),
+    Shortcut(ShortcutEvent),
+    Frame(Instant),
+}
+
+impl IcedWorkspacesApplet {
+    /// Starts the transitions of workspaces which were added or removed.
+    fn start_transitions(&mut self, list: &WorkspaceList) {
+        // Don't animate the workspaces shown at startup
+        if self.workspaces.is_empty() {
+            return;
+        }
+        let started = Instant::now();
+        for w in list {
+            if !self.workspaces.iter().any(|old| old.2 == w.2) {
+                self.transitions.insert(
+                    w.2.clone(),
+                    Transition {
+                        started,
+                        adding: true,
+                    },
+                );
+            }
+        }
+        for (i, old) in self.workspaces.iter().enumerate() {
+            if !list.iter().any(|w| w.2 == old.2) {
+                self.transitions.insert(
+                    old.2.clone(),
+                    Transition {
+                        started,
+                        adding: false,
+                    },
+                );
+                self.removed.push((i, old.clone()));
+            }
+        }
+        self.now = started;
+    }
+
+    /// How far a workspace has appeared, from 0 to 1.
+    fn visibility(&self, id: &ObjectId) -> f32 {
+        match self.transitions.get(id) {
+            Some(transition) => self.progress(transition),
+            None => 1.0,
+        }
+    }
+
+    /// How far the enlarged numbers are shown, from 0 to 1.
+    fn numbers_shown(&self) -> f32 {
+        match &self.numbers_transition {
+            Some(transition) => self.progress(transition),
+            None if self.numbers_held => 1.0,
+            None => 0.0,
+        }
+    }
+
+    /// How far `transition` has gone towards showing its subject, from 0 to 1.
+    fn progress(&self, transition: &Transition) -> f32 {
+        let progress = (self
+            .now
+            .saturating_duration_since(transition.started)
+            .as_secs_f32()
+            / TRANSITION.as_secs_f32())
+        .clamp(0.0, 1.0);
+        // Ease out
+        let progress = progress * (2.0 - progress);
+        if transition.adding {
+            progress
+        } else {
+            1.0 - progress
+        }
+    }
+
+    /// Shows or hides the enlarged numbers, from wherever they are.
+    fn show_numbers(&mut self, shown: bool) {
+        if self.numbers_held == shown {
+            return;
+        }
+        let now = Instant::now();
+        self.now = now;
+        // Reversing midway continues from the current size
+        let remaining = if shown {
+            1.0 - self.numbers_shown()
+        } else {
+            self.numbers_shown()
+        };
+        let elapsed = TRANSITION.mul_f32(1.0 - remaining.sqrt());
+        self.numbers_held = shown;
+        self.numbers_transition = Some(Transition {
+            started: now - elapsed,
+            adding: shown,
+        });
+    }
+
+    /// Loads the icons of apps that were opened since.
+    fn load_app_icons(&mut self) {
+        if !self.config.show_apps {
+            return;
+        }
+        let mut app_ids = Vec::new();
+        for w in &self.workspaces {
+            for app_id in w.3.iter().take(MAX_APPS) {
+                if !self.app_icons.contains_key(app_id) && !app_ids.contains(app_id) {
+                    app_ids.push(app_id.clone());
+                }
+            }
+        }
+        if app_ids.is_empty() {
+            return;
+        }
+        let entries = cosmic::desktop::load_applications_for_app_ids(
+            None,
+            app_ids.iter().map(String::as_str),
+            true,
+            false,
+        );
+        for (app_id, entry) in app_ids.into_iter().zip(entries) {
+            self.app_icons.insert(app_id, entry.icon);
+        }
+    }
+
+    /// Icons of the first apps open on a workspace, in a row or column.
+    fn apps_view(
+        &self,
+        w: &Workspace,
+        size: u16,
+        horizontal: bool,
+    ) -> Option<Element<'static, Message>> {
+        if !self.config.show_apps || w.3.is_empty() {
+            return None;
+        }
+        let icons = w.3.iter().take(MAX_APPS).filter_map(|app_id| {
+            let icon = self.app_icons.get(app_id)?;
+            Some(icon.as_cosmic_icon().size(size).into())
+        });
+        let icons = icons.collect::<Vec<_>>();
+        Some(if horizontal {
+            Row::with_children(icons)
+                .spacing(2)
+                .align_items(Alignment::Center)
+                .into()
+        } else {
+            Column::with_children(icons)
+                .spacing(2)
+                .align_items(Alignment::Center)
+                .into()
+        })
+    }
+
+    /// The display mode, which shows every number while the numbers are held.
+    fn display_mode(&self) -> DisplayMode {
+        match self.config.display_mode {
+            DisplayMode::Dots | DisplayMode::ActiveOnly if self.numbers_shown() > 0.0 => {
+                DisplayMode::Numbers
+            }
+            mode => mode,
+        }
+    }
+
+    fn workspace_button<'a>(
+        &self,
+        core: &Core,
+        index: usize,
+        w: &'a Workspace,
+    ) -> Option<Element<'a, Message>> {
+        let horizontal = self.layout == Layout::Row;
+        let visibility = self.visibility(&w.2);
+        let active = matches!(w.1, Some(zcosmic_workspace_handle_v1::State::Active));
+        let display_mode = self.display_mode();
+        let dots = display_mode == DisplayMode::Dots;
+        let (suggested_width, suggested_height) = core.applet.suggested_size(true);
+        let padding = core.applet.suggested_padding(true);
+        let full_width = (suggested_width + 2 * padding) as f32;
+        let full_height = (suggested_height + 2 * padding) as f32;
+        let numbers_shown = self.numbers_shown();
+        let growth = 1.0 + NUMBERS_GROWTH * numbers_shown;
+        // Workspaces grow and shrink along the panel while being added or
+        // removed, and grow while their numbers are held
+        let (width, height) = if horizontal {
+            (
+                Length::Fixed(full_width * visibility * growth),
+                Length::Fixed(full_height),
+            )
+        } else {
+            (
+                Length::Fixed(full_width),
+                Length::Fixed(full_height * visibility * growth),
+            )
+        };
+        let label = |label: String| {
+            let text = core.applet.text(label).font(FONT_BOLD);
+            if numbers_shown > 0.0 {
+                // Up to most of the panel's thickness
+                let size = full_width.min(full_height) * (0.4 + 0.3 * numbers_shown);
+                text.size(size)
+            } else {
+                text
+            }
+        };
+
+        let icon_size = (suggested_width.min(suggested_height) / 2).max(8);
+
+        let content: Element<_> = match display_mode {
+            DisplayMode::Numbers | DisplayMode::ActiveOnly => {
+                let label = label((index + 1).to_string());
+                match self.apps_view(w, icon_size, horizontal) {
+                    Some(apps) => {
+                        // The icons follow the number, making the workspace
+                        // longer
+                        let extra = (w.3.len().min(MAX_APPS) as f32 * f32::from(icon_size + 2)
+                            + padding as f32)
+                            * visibility;
+                        let (width, height, content): (_, _, Element<_>) = if horizontal {
+                            (
+                                Length::Fixed(full_width * visibility * growth + extra),
+                                height,
+                                row![label, apps]
+                                    .spacing(padding)
+                                    .align_items(Alignment::Center)
+                                    .into(),
+                            )
+                        } else {
+                            (
+                                width,
+                                Length::Fixed(full_height * visibility * growth + extra),
+                                column![label, apps]
+                                    .spacing(padding)
+                                    .align_items(Alignment::Center)
+                                    .into(),
+                            )
+                        };
+                        container(content)
+                    }
+                    None => container(label),
+                }
+                .width(width)
+                .height(height)
+                .align_x(Horizontal::Center)
+                .align_y(Vertical::Center)
+                .into()
+            }
+            DisplayMode::Pills => {
+                let mut children = vec![label(w.0.clone()).into()];
+                // Beside the name, also in vertical panels
+                children.extend(self.apps_view(w, icon_size, true));
+                children.push(vertical_space(Length::Fixed(full_height)).into());
+                let content = Row::with_children(children)
+                    .spacing(padding)
+                    .align_items(Alignment::Center);
+                let content = column!(content, horizontal_space(Length::Fixed(full_width)))
+                    .align_items(Alignment::Center);
+                container(content)
+                    .padding(if horizontal {
+                        [0, padding * 2]
+                    } else {
+                        [padding * 2, 0]
+                    })
+                    .align_x(Horizontal::Center)
+                    .align_y(Vertical::Center)
+                    .into()
+            }
+            DisplayMode::Dots => {
+                let size = (suggested_width / 2).max(4) as f32;
+                // The active workspace is a longer dash
+                let length = if active { size * 2.5 } else { size } * visibility;
+                let (dot_width, dot_height, width, height) = if horizontal {
+                    (length, size, length + 2.0 * padding as f32, full_height)
+                } else {
+                    (size, length, full_width, length + 2.0 * padding as f32)
+                };
+                let state = w.1;
+                let dot = container(vertical_space(Length::Fixed(0.0)))
+                    .width(Length::Fixed(dot_width))
+                    .height(Length::Fixed(dot_height))
+                    .style(<Theme as container::StyleSheet>::Style::Custom(Box::new(
+                        move |theme| {
+                            let color: Color = match state {
+                                Some(zcosmic_workspace_handle_v1::State::Active) => {
+                                    theme.cosmic().accent_color().into()
+                                }
+                                Some(zcosmic_workspace_handle_v1::State::Urgent) => {
+                                    theme.cosmic().destructive_color().into()
+                                }
+                                _ => faded(theme.current_container().component.on.into(), 0.5),
+                            };
+                            container::Appearance {
+                                text_color: None,
+                                background: Some(Background::Color(faded(color, visibility))),
+                                border: Border {
+                                    radius: (size / 2.0).into(),
+                                    width: 0.0,
+                                    color: Color::TRANSPARENT,
+                                },
+                                shadow: Shadow::default(),
+                                icon_color: None,
+                            }
+                        },
+                    )));
+                container(dot)
+                    .width(Length::Fixed(width))
+                    .height(Length::Fixed(height))
+                    .align_x(Horizontal::Center)
+                    .align_y(Vertical::Center)
+                    .into()
+            }
+        };
+
+        let state = w.1;
+        let style = match state {
+            Some(zcosmic_workspace_handle_v1::State::Active)
+            | Some(zcosmic_workspace_handle_v1::State::Urgent)
+            | None => cosmic::theme::iced::Button::Custom {
+                active: Box::new(move |theme| button_appearance(theme, state, dots, visibility)),
+                hover: Box::new(move |theme| button::Appearance {
+                    background: Some(Background::Color(faded(
+                        if active && !dots {
+                            theme.cosmic().accent_button.hover.into()
+                        } else {
+                            theme.current_container().component.hover.into()
+                        },
+                        visibility,
+                    ))),
+                    ..button_appearance(theme, state, dots, visibility)
+                }),
+            },
+            _ => return None,
+        };
+
+        Some(
+            button(content)
+                .padding(0)
+                .on_press(if active {
+                    Message::WorkspaceOverview
+                } else {
+                    Message::WorkspacePressed(w.2.clone())
+                })
+                .style(style)
+                .into(),
+        )
+    }
+}
+
+fn faded(mut color: Color, visibility: f32) -> Color {
+    color.a *= visibility;
+    color
+}
+
+fn button_appearance(
+    theme: &Theme,
+    state: Option<zcosmic_workspace_handle_v1::State>,
+    dots: bool,
+    visibility: f32,
+) -> button::Appearance {
+    let cosmic = theme.cosmic();
+    let (background, text_color): (Option<Color>, Color) = match state {
+        // Dots show the state themselves
+        _ if dots => (None, theme.current_container().component.on.into()),
+        Some(zcosmic_workspace_handle_v1::State::Active) => (
+            Some(cosmic.accent_button.base.into()),
+            cosmic.accent_button.on.into(),
+        ),
+        Some(zcosmic_workspace_handle_v1::State::Urgent) => (
+            Some(cosmic.palette.neutral_3.into()),
+            cosmic.destructive_button.base.into(),
+        ),
+        _ => (None, theme.current_container().component.on.into()),
+    };
+    button::Appearance {
+        background: background.map(|color| Background::Color(faded(color, visibility))),
+        border: Border {
+            radius: cosmic.radius_xl().into(),
+            ..Default::default()
+        },
+        border_radius: cosmic.radius_xl().into(),
+        text_color: faded(text_color, visibility),
+        ..button::Appearance::default()
+    }
 }
 
-impl cosmic::Application for IcedWorkspacesApplet {
+impl CosmicApplet for IcedWorkspacesApplet {
     type Message = Message;
-    type Executor = cosmic::SingleThreadExecutor;
-    type Flags = ();
     const APP_ID: &'static str = config::APP_ID;
 
-    fn init(
-        core: cosmic::app::Core,
-        _flags: Self::Flags,
-    ) -> (
-        Self,
-        cosmic::iced::Command<cosmic::app::Message<Self::Message>>,
-    ) {
+    fn init(core: &Core) -> (Self, Command<Message>) {
+        let config_helper = Config::new(config::APP_ID, WorkspacesConfig::VERSION).ok();
+        let config = config_helper
+            .as_ref()
+            .map(|helper| {
+                WorkspacesConfig::get_entry(helper).unwrap_or_else(|(errs, config)| {
+                    for err in errs {
+                        tracing::error!(?err, "Error loading config");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
         (
             Self {
                 layout: match &core.applet.anchor {
                     PanelAnchor::Left | PanelAnchor::Right => Layout::Column,
                     PanelAnchor::Top | PanelAnchor::Bottom => Layout::Row,
                 },
-                core,
                 workspaces: Vec::new(),
                 workspace_tx: Default::default(),
+                config,
+                config_helper,
+                transitions: HashMap::new(),
+                removed: Vec::new(),
+                numbers_transition: None,
+                numbers_held: false,
+                now: Instant::now(),
+                app_icons: HashMap::new(),
             },
             Command::none(),
         )
     }
 
-    fn core(&self) -> &cosmic::app::Core {
-        &self.core
-    }
-
-    fn core_mut(&mut self) -> &mut cosmic::app::Core {
-        &mut self.core
-    }
-
-    fn update(
-        &mut self,
-        message: Self::Message,
-    ) -> cosmic::iced::Command<cosmic::app::Message<Self::Message>> {
+    fn update(&mut self, _core: &Core, message: Message) -> Command<Message> {
         match message {
             Message::WorkspaceUpdate(msg) => match msg {
                 WorkspacesUpdate::Workspaces(mut list) => {
@@ -105,7 +531,9 @@ impl cosmic::Application for IcedWorkspacesApplet {
                         Ordering::Less => Ordering::Less,
                         Ordering::Greater => Ordering::Greater,
                     });
+                    self.start_transitions(&list);
                     self.workspaces = list;
+                    self.load_app_icons();
                 }
                 WorkspacesUpdate::Started(tx) => {
                     self.workspace_tx.replace(tx);
@@ -131,134 +559,162 @@ impl cosmic::Application for IcedWorkspacesApplet {
             Message::WorkspaceOverview => {
                 let _ = ShellCommand::new("cosmic-workspaces").spawn();
             }
+            Message::ConfigChanged(config) => {
+                self.config = config;
+                self.load_app_icons();
+            }
+            Message::SetDisplayMode(display_mode) => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_display_mode(helper, display_mode) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+            }
+            Message::SetShowApps(show_apps) => {
+                if let Some(helper) = &self.config_helper {
+                    if let Err(err) = self.config.set_show_apps(helper, show_apps) {
+                        tracing::error!(?err, "Error writing config");
+                    }
+                }
+                self.load_app_icons();
+            }
+            Message::Frame(now) => {
+                self.now = now;
+                self.transitions
+                    .retain(|_, t| now.saturating_duration_since(t.started) < TRANSITION);
+                let transitions = &self.transitions;
+                self.removed.retain(|(_, w)| transitions.contains_key(&w.2));
+                if self
+                    .numbers_transition
+                    .as_ref()
+                    .is_some_and(|t| now.saturating_duration_since(t.started) >= TRANSITION)
+                {
+                    self.numbers_transition = None;
+                }
+            }
+            Message::Shortcut(event) => match event {
+                ShortcutEvent::Activated(id) if id == SHOW_NUMBERS => self.show_numbers(true),
+                ShortcutEvent::Deactivated(id) if id == SHOW_NUMBERS => self.show_numbers(false),
+                ShortcutEvent::Bound { conflicts, .. } => {
+                    // The compositor may keep Super to itself, until the user
+                    // picks another key
+                    if !conflicts.is_empty() {
+                        tracing::warn!(?conflicts, "Shortcuts already used by another application");
+                    }
+                }
+                ShortcutEvent::Error(err) => {
+                    tracing::warn!(%err, "Failed to register shortcuts");
+                }
+                ShortcutEvent::Activated(_) | ShortcutEvent::Deactivated(_) => {}
+            },
         }
         Command::none()
     }
 
-    fn view(&self) -> Element<Message> {
+    fn view<'a>(&'a self, core: &'a Core) -> Element<'a, AppletMessage<Message>> {
         if self.workspaces.is_empty() {
-            return row![].padding(8).into();
+            return mouse_area(row![].padding(8))
+                .on_right_press(AppletMessage::TogglePopup)
+                .into();
         }
-        let horizontal = matches!(
-            self.core.applet.anchor,
-            PanelAnchor::Top | PanelAnchor::Bottom
-        );
-        let buttons = self.workspaces.iter().filter_map(|w| {
-            let content = self.core.applet.text(w.0.clone()).font(FONT_BOLD);
-
-            let content = row!(
-                content,
-                vertical_space(Length::Fixed(
-                    (self.core.applet.suggested_size(true).1
-                        + 2 * self.core.applet.suggested_padding(true)) as f32
-                ))
-            )
-            .align_items(cosmic::iced::Alignment::Center);
-
-            let content = column!(
-                content,
-                horizontal_space(Length::Fixed(
-                    (self.core.applet.suggested_size(true).0
-                        + 2 * self.core.applet.suggested_padding(true)) as f32
-                ))
-            )
-            .align_items(cosmic::iced::Alignment::Center);
 
-            let btn = button(
-                container(content)
-                    .align_x(Horizontal::Center)
-                    .align_y(Vertical::Center),
-            )
-            .padding(if horizontal {
-                [0, self.core.applet.suggested_padding(true)]
-            } else {
-                [self.core.applet.suggested_padding(true), 0]
-            })
-            .on_press(match w.1 {
-                Some(zcosmic_workspace_handle_v1::State::Active) => Message::WorkspaceOverview,
-                _ => Message::WorkspacePressed(w.2.clone()),
-            })
-            .padding(0);
+        let mut shown: Vec<(usize, &Workspace)> = self.workspaces.iter().enumerate().collect();
+        for (i, w) in &self.removed {
+            shown.insert((*i).min(shown.len()), (*i, w));
+        }
+        let display_mode = self.display_mode();
+        let buttons = shown.into_iter().filter_map(|(i, w)| {
+            if display_mode == DisplayMode::ActiveOnly
+                && !matches!(w.1, Some(zcosmic_workspace_handle_v1::State::Active))
+            {
+                return None;
+            }
+            self.workspace_button(core, i, w)
+        });
+        let spacing = match display_mode {
+            DisplayMode::Dots => 0,
+            _ => 4,
+        };
+        let layout_section: Element<_> = match self.layout {
+            Layout::Row => row(buttons)
+                .spacing(spacing)
+                .align_items(Alignment::Center)
+                .into(),
+            Layout::Column => column(buttons)
+                .spacing(spacing)
+                .align_items(Alignment::Center)
+                .into(),
+        };
 
-            Some(
-                btn.style(match w.1 {
-                    Some(zcosmic_workspace_handle_v1::State::Active) => {
-                        cosmic::theme::iced::Button::Primary
-                    }
-                    Some(zcosmic_workspace_handle_v1::State::Urgent) => {
-                        let appearance = |theme: &Theme| {
-                            let cosmic = theme.cosmic();
-                            button::Appearance {
-                                background: Some(Background::Color(
-                                    cosmic.palette.neutral_3.into(),
-                                )),
-                                border: Border {
-                                    radius: cosmic.radius_xl().into(),
-                                    ..Default::default()
-                                },
-                                border_radius: theme.cosmic().radius_xl().into(),
-                                text_color: theme.cosmic().destructive_button.base.into(),
-                                ..button::Appearance::default()
-                            }
-                        };
-                        cosmic::theme::iced::Button::Custom {
-                            active: Box::new(appearance),
-                            hover: Box::new(move |theme| button::Appearance {
-                                background: Some(Background::Color(
-                                    theme.current_container().component.hover.into(),
-                                )),
-                                border: Border {
-                                    radius: theme.cosmic().radius_xl().into(),
-                                    ..Default::default()
-                                },
-                                ..appearance(theme)
-                            }),
-                        }
-                    }
-                    None => {
-                        let appearance = |theme: &Theme| {
-                            let cosmic = theme.cosmic();
-                            button::Appearance {
-                                background: None,
-                                border: Border {
-                                    radius: cosmic.radius_xl().into(),
-                                    ..Default::default()
-                                },
-                                border_radius: cosmic.radius_xl().into(),
-                                text_color: theme.current_container().component.on.into(),
-                                ..button::Appearance::default()
-                            }
-                        };
-                        cosmic::theme::iced::Button::Custom {
-                            active: Box::new(appearance),
-                            hover: Box::new(move |theme| button::Appearance {
-                                background: Some(Background::Color(
-                                    theme.current_container().component.hover.into(),
-                                )),
-                                border: Border {
-                                    radius: theme.cosmic().radius_xl().into(),
-                                    ..Default::default()
-                                },
-                                ..appearance(theme)
-                            }),
-                        }
+        // Clicking switches workspaces, the settings are a right click away
+        mouse_area(container(layout_section.map(AppletMessage::App)).padding(0))
+            .on_right_press(AppletMessage::TogglePopup)
+            .into()
+    }
+
+    fn popup_view<'a>(&'a self, _core: &'a Core) -> Element<'a, AppletMessage<Message>> {
+        let modes = DisplayMode::ALL.into_iter().map(|mode| {
+            let label = match mode {
+                DisplayMode::Numbers => fl!("display-numbers"),
+                DisplayMode::Dots => fl!("display-dots"),
+                DisplayMode::Pills => fl!("display-pills"),
+                DisplayMode::ActiveOnly => fl!("display-active-only"),
+            };
+            menu_button(
+                row![
+                    text(label).size(14).width(Length::Fill),
+                    if self.config.display_mode == mode {
+                        container(
+                            icon::from_name("emblem-ok-symbolic")
+                                .size(12)
+                                .symbolic(true),
+                        )
+                    } else {
+                        container(horizontal_space(1.0))
                     }
-                    _ => return None,
-                })
-                .into(),
+                ]
+                .align_items(Alignment::Center),
             )
+            .on_press(Message::SetDisplayMode(mode))
+            .into()
         });
-        let layout_section: Element<_> = match self.layout {
-            Layout::Row => row(buttons).spacing(4).into(),
-            Layout::Column => column(buttons).spacing(4).into(),
-        };
 
-        container(layout_section).padding(0).into()
+        let mut content = vec![
+            padded_control(text(fl!("display")).size(14)).into(),
+            padded_control(divider::horizontal::default()).into(),
+        ];
+        content.extend(modes);
+        content.push(padded_control(divider::horizontal::default()).into());
+        content.push(
+            padded_control(
+                toggler(
+                    Some(fl!("show-apps")),
+                    self.config.show_apps,
+                    Message::SetShowApps,
+                )
+                .text_size(14)
+                .width(Length::Fill),
+            )
+            .into(),
+        );
+
+        Element::from(Column::with_children(content).padding([8, 0])).map(AppletMessage::App)
     }
 
-    fn subscription(&self) -> Subscription<Message> {
+    fn subscription(&self, core: &Core) -> Subscription<Message> {
         Subscription::batch(vec![
             workspaces().map(Message::WorkspaceUpdate),
+            core.watch_config::<WorkspacesConfig>(config::APP_ID)
+                .map(|u| Message::ConfigChanged(u.config)),
+            global_shortcuts(
+                0,
+                vec![Shortcut {
+                    id: SHOW_NUMBERS.to_string(),
+                    description: fl!("show-workspace-numbers"),
+                    preferred_trigger: Some("LOGO".to_string()),
+                }],
+            )
+            .map(Message::Shortcut),
             event::listen_with(|e, _| match e {
                 Mouse(mouse::Event::WheelScrolled { delta }) => Some(Message::WheelScrolled(delta)),
                 _ => None,
@@ -266,7 +722,11 @@ impl cosmic::Application for IcedWorkspacesApplet {
         ])
     }
 
-    fn style(&self) -> Option<<Theme as application::StyleSheet>::Style> {
-        Some(cosmic::applet::style())
+    fn panel_subscription(&self, _core: &Core) -> Subscription<Message> {
+        if self.transitions.is_empty() && self.numbers_transition.is_none() {
+            Subscription::none()
+        } else {
+            time::every(Duration::from_millis(16)).map(Message::Frame)
+        }
     }
 }
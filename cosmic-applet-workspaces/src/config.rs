@@ -1,4 +1,31 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+use serde::{Deserialize, Serialize};
+
 pub const APP_ID: &str = "com.system76.CosmicWorkspacesApplet";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DisplayMode {
+    /// A button with the workspace's position.
+    #[default]
+    Numbers,
+    Dots,
+    /// A wider button with the workspace's name.
+    Pills,
+    /// Only the active workspace's number.
+    ActiveOnly,
+}
+
+impl DisplayMode {
+    pub const ALL: [Self; 4] = [Self::Numbers, Self::Dots, Self::Pills, Self::ActiveOnly];
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize, CosmicConfigEntry)]
+#[version = 1]
+pub struct WorkspacesConfig {
+    pub display_mode: DisplayMode,
+    /// Show the icons of the apps open on each workspace.
+    pub show_apps: bool,
+}
@@ -13,9 +13,13 @@ use cctk::{
         },
         registry::{ProvidesRegistryState, RegistryState},
     },
+    toplevel_info::{ToplevelInfoHandler, ToplevelInfoState},
     workspace::{WorkspaceHandler, WorkspaceState},
 };
-use cosmic_protocols::workspace::v1::client::zcosmic_workspace_handle_v1;
+use cosmic_protocols::{
+    toplevel_info::v1::client::zcosmic_toplevel_handle_v1::ZcosmicToplevelHandleV1,
+    workspace::v1::client::zcosmic_workspace_handle_v1,
+};
 use futures::{channel::mpsc, executor::block_on, SinkExt};
 use std::{
     os::{
@@ -36,7 +40,14 @@ pub enum WorkspaceEvent {
     Activate(ObjectId),
     Scroll(f64, bool),
 }
-pub type WorkspaceList = Vec<(String, Option<zcosmic_workspace_handle_v1::State>, ObjectId)>;
+/// The name, state and ID of each workspace, and the app IDs of the windows
+/// on it.
+pub type WorkspaceList = Vec<(
+    String,
+    Option<zcosmic_workspace_handle_v1::State>,
+    ObjectId,
+    Vec<String>,
+)>;
 
 pub fn spawn_workspaces(tx: mpsc::Sender<WorkspaceList>) -> SyncSender<WorkspaceEvent> {
     let (workspaces_tx, workspaces_rx) = calloop::channel::sync_channel(100);
@@ -76,6 +87,9 @@ pub fn spawn_workspaces(tx: mpsc::Sender<WorkspaceList>) -> SyncSender<Workspace
                 output_state: OutputState::new(&globals, &qhandle),
                 configured_output,
                 workspace_state: WorkspaceState::new(&registry_state, &qhandle),
+                // Must be after `WorkspaceState`, for windows to be placed on
+                // the workspaces
+                toplevel_info_state: ToplevelInfoState::new(&registry_state, &qhandle),
                 registry_state,
                 expected_output: None,
                 tx,
@@ -209,6 +223,7 @@ pub struct State {
     output_state: OutputState,
     registry_state: RegistryState,
     workspace_state: WorkspaceState,
+    toplevel_info_state: ToplevelInfoState,
     have_workspaces: bool,
     scroll: f64,
     next_scroll: Option<Instant>,
@@ -216,9 +231,12 @@ pub struct State {
 }
 
 impl State {
-    pub fn workspace_list(
-        &self,
-    ) -> Vec<(String, Option<zcosmic_workspace_handle_v1::State>, ObjectId)> {
+    pub fn workspace_list(&self) -> WorkspaceList {
+        self.workspace_list_without(None)
+    }
+
+    /// The workspaces, leaving out a window that is being closed.
+    fn workspace_list_without(&self, closed: Option<&ZcosmicToplevelHandleV1>) -> WorkspaceList {
         self.workspace_state
             .workspace_groups()
             .iter()
@@ -252,6 +270,7 @@ impl State {
                                 _ => None,
                             },
                             w.handle.id(),
+                            self.app_ids(&w.handle, closed),
                         )
                     }))
                 } else {
@@ -261,6 +280,35 @@ impl State {
             .flatten()
             .collect()
     }
+
+    /// The app IDs of the windows on a workspace, in the order they were
+    /// opened, each listed once.
+    fn app_ids(
+        &self,
+        workspace: &zcosmic_workspace_handle_v1::ZcosmicWorkspaceHandleV1,
+        closed: Option<&ZcosmicToplevelHandleV1>,
+    ) -> Vec<String> {
+        let mut app_ids = Vec::new();
+        for (toplevel, info) in self.toplevel_info_state.toplevels() {
+            if Some(toplevel) == closed {
+                continue;
+            }
+            let Some(info) = info else {
+                continue;
+            };
+            if info.workspace.contains(workspace) && !app_ids.contains(&info.app_id) {
+                app_ids.push(info.app_id.clone());
+            }
+        }
+        app_ids
+    }
+
+    fn send_workspaces(&mut self, closed: Option<&ZcosmicToplevelHandleV1>) {
+        if self.have_workspaces {
+            let list = self.workspace_list_without(closed);
+            let _ = block_on(self.tx.send(list));
+        }
+    }
 }
 
 impl ProvidesRegistryState for State {
@@ -318,6 +366,40 @@ impl WorkspaceHandler for State {
     }
 }
 
+impl ToplevelInfoHandler for State {
+    fn toplevel_info_state(&mut self) -> &mut ToplevelInfoState {
+        &mut self.toplevel_info_state
+    }
+
+    fn new_toplevel(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _toplevel: &ZcosmicToplevelHandleV1,
+    ) {
+        self.send_workspaces(None);
+    }
+
+    fn update_toplevel(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _toplevel: &ZcosmicToplevelHandleV1,
+    ) {
+        self.send_workspaces(None);
+    }
+
+    fn toplevel_closed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        toplevel: &ZcosmicToplevelHandleV1,
+    ) {
+        self.send_workspaces(Some(toplevel));
+    }
+}
+
 cctk::delegate_workspace!(State);
+cctk::delegate_toplevel_info!(State);
 sctk::delegate_output!(State);
 sctk::delegate_registry!(State);
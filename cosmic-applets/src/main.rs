@@ -1,12 +1,11 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
+use cosmic_applet_helper::logging;
+
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 fn main() -> cosmic::iced::Result {
-    tracing_subscriber::fmt::init();
-    let _ = tracing_log::LogTracer::init();
-
     let Some(applet) = std::env::args().next() else {
         return Ok(());
     };
@@ -14,7 +13,11 @@ fn main() -> cosmic::iced::Result {
     let start = applet.rfind('/').map(|v| v + 1).unwrap_or(0);
     let cmd = &applet.as_str()[start..];
 
+    logging::init(cmd);
+    let _span = logging::applet_span().entered();
+
     tracing::info!("Starting `{cmd}` with version {VERSION}");
+    cosmic_applet_helper::install_panic_hook(cmd, VERSION);
 
     match cmd {
         "cosmic-app-list" => cosmic_app_list::run(),
@@ -30,6 +33,36 @@ fn main() -> cosmic::iced::Result {
         "cosmic-applet-time" => cosmic_applet_time::run(),
         "cosmic-applet-workspaces" => cosmic_applet_workspaces::run(),
         "cosmic-applet-input-sources" => cosmic_applet_input_sources::run(),
+        "cosmic-applet-graphics" => cosmic_applet_graphics::run(),
+        "cosmic-applet-clipboard" => cosmic_applet_clipboard::run(),
+        "cosmic-applet-night-light" => cosmic_applet_night_light::run(),
+        "cosmic-applet-weather" => cosmic_applet_weather::run(),
+        "cosmic-applet-system-monitor" => cosmic_applet_system_monitor::run(),
+        "cosmic-applet-media" => cosmic_applet_media::run(),
+        "cosmic-applet-caffeine" => cosmic_applet_caffeine::run(),
+        "cosmic-applet-screenshot" => cosmic_applet_screenshot::run(),
+        "cosmic-applet-emoji" => cosmic_applet_emoji::run(),
+        "cosmic-applet-drives" => cosmic_applet_drives::run(),
+        "cosmic-applet-printers" => cosmic_applet_printers::run(),
+        "cosmic-applet-vpn" => cosmic_applet_vpn::run(),
+        "cosmic-applet-notes" => cosmic_applet_notes::run(),
+        "cosmic-applet-color-picker" => cosmic_applet_color_picker::run(),
+        "cosmic-applet-pomodoro" => cosmic_applet_pomodoro::run(),
+        "cosmic-applet-location" => cosmic_applet_location::run(),
+        "cosmic-applet-screen-keyboard" => cosmic_applet_screen_keyboard::run(),
+        "cosmic-applet-appearance" => cosmic_applet_appearance::run(),
+        "cosmic-applet-desktop-toggles" => cosmic_applet_desktop_toggles::run(),
+        "cosmic-applet-stocks" => cosmic_applet_stocks::run(),
+        "cosmic-applet-updates" => cosmic_applet_updates::run(),
+        "cosmic-applet-firewall" => cosmic_applet_firewall::run(),
+        "cosmic-applet-monitor-control" => cosmic_applet_monitor_control::run(),
+        "cosmic-applet-shortcuts" => cosmic_applet_shortcuts::run(),
+        "cosmic-applet-autostart" => cosmic_applet_autostart::run(),
+        "cosmic-applet-privacy" => cosmic_applet_privacy::run(),
+        "cosmic-applet-mic-mute" => cosmic_applet_mic_mute::run(),
+        "cosmic-applet-window-title" => cosmic_applet_window_title::run(),
+        "cosmic-applet-shelf" => cosmic_applet_shelf::run(),
+        "cosmic-applet-quick-settings" => cosmic_applet_quick_settings::run(),
         _ => return Ok(()),
     }
 }
@@ -0,0 +1,119 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Standard GATT services of Bluetooth LE devices. BlueZ only offers the
+//! Battery Service as `org.bluez.Battery1` when its battery plugin claimed it,
+//! and doesn't read the heart rate at all, so they're read here.
+
+use std::time::Duration;
+
+use bluer::{gatt::remote::Characteristic, Device, Uuid, UuidExt};
+use futures::{
+    future,
+    stream::{self, BoxStream},
+    StreamExt,
+};
+
+const BATTERY_SERVICE: u16 = 0x180f;
+const BATTERY_LEVEL: u16 = 0x2a19;
+const HEART_RATE_SERVICE: u16 = 0x180d;
+const HEART_RATE_MEASUREMENT: u16 = 0x2a37;
+
+/// How long to wait for BlueZ to discover the services of a device that just
+/// connected.
+const SERVICES_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy)]
+pub enum Reading {
+    /// Battery level, in percent.
+    Battery(u8),
+    /// Heart rate, in beats per minute.
+    HeartRate(u16),
+}
+
+/// The last readings of a device.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Readings {
+    pub battery: Option<u8>,
+    pub heart_rate: Option<u16>,
+}
+
+impl Readings {
+    pub fn update(&mut self, reading: Reading) {
+        match reading {
+            Reading::Battery(level) => self.battery = Some(level),
+            Reading::HeartRate(bpm) => self.heart_rate = Some(bpm),
+        }
+    }
+}
+
+/// Readings of the connected `device`, as it notifies them. Empty for a device
+/// without the services.
+pub async fn readings(device: &Device) -> bluer::Result<BoxStream<'static, Reading>> {
+    let mut waited = Duration::ZERO;
+    while !device.is_services_resolved().await? {
+        if waited >= SERVICES_TIMEOUT {
+            return Ok(stream::empty().boxed());
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        waited += Duration::from_millis(500);
+    }
+
+    let mut streams = Vec::new();
+    if let Some(level) = characteristic(device, BATTERY_SERVICE, BATTERY_LEVEL).await? {
+        if let Some(percentage) = level.read().await?.first().copied() {
+            let reading = Reading::Battery(percentage.min(100));
+            streams.push(stream::once(future::ready(reading)).boxed());
+        }
+        // Notifying is optional for the battery level
+        if level.flags().await?.notify {
+            let notifications = level.notify().await?.filter_map(|value| async move {
+                value
+                    .first()
+                    .map(|percentage| Reading::Battery((*percentage).min(100)))
+            });
+            streams.push(notifications.boxed());
+        }
+    }
+    if let Some(measurement) =
+        characteristic(device, HEART_RATE_SERVICE, HEART_RATE_MEASUREMENT).await?
+    {
+        let notifications = measurement
+            .notify()
+            .await?
+            .filter_map(|value| async move { heart_rate(&value).map(Reading::HeartRate) });
+        streams.push(notifications.boxed());
+    }
+    Ok(stream::select_all(streams).boxed())
+}
+
+/// The characteristic `uuid` of the service `service`, given as their
+/// assigned numbers.
+async fn characteristic(
+    device: &Device,
+    service: u16,
+    uuid: u16,
+) -> bluer::Result<Option<Characteristic>> {
+    let (service, uuid) = (Uuid::from_u16(service), Uuid::from_u16(uuid));
+    for s in device.services().await? {
+        if s.uuid().await? != service {
+            continue;
+        }
+        for c in s.characteristics().await? {
+            if c.uuid().await? == uuid {
+                return Ok(Some(c));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// The heart rate in a Heart Rate Measurement, whose first bit of flags tells
+/// whether it's 8 or 16 bits wide.
+fn heart_rate(value: &[u8]) -> Option<u16> {
+    match value {
+        [flags, low, high, ..] if flags & 1 == 1 => Some(u16::from_le_bytes([*low, *high])),
+        [flags, bpm, ..] if flags & 1 == 0 => Some(u16::from(*bpm)),
+        _ => None,
+    }
+}
@@ -1,7 +1,30 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
-use std::{collections::HashMap, fmt::Debug, hash::Hash, mem, sync::Arc, time::Duration};
+//! BlueZ client shared by the applets.
+//!
+//! [`bluetooth_subscription`] opens a session on the default adapter and
+//! registers a pairing agent. Its first event is [`BluerEvent::Init`], carrying
+//! the current [`BluerState`] and a sender for [`BluerRequest`]s. Each request
+//! is answered with [`BluerEvent::RequestResponse`], changes made elsewhere
+//! arrive as [`BluerEvent::DevicesChanged`], and pairing prompts as
+//! [`BluerEvent::AgentEvent`].
+//!
+//! The device list is cached for the session. Listing devices costs a
+//! `GetManagedObjects` call plus a property read per device, so it's only done
+//! when BlueZ reports that devices changed or the adapter was powered on or
+//! off; a request about one device only refreshes that device.
+//!
+//! Connected devices are also followed for the GATT services BlueZ doesn't
+//! expose, like the battery level of many Bluetooth LE devices.
+//!
+//! Phones sharing their connection can be connected to the internet through,
+//! as described in [`tethering`].
+
+mod gatt;
+pub mod tethering;
+
+use std::{collections::HashMap, fmt::Debug, hash::Hash, sync::Arc, time::Duration};
 
 use bluer::{
     agent::{Agent, AgentHandle},
@@ -136,6 +159,9 @@ pub enum BluerRequest {
     ConnectDevice(Address),
     DisconnectDevice(Address),
     CancelConnect(Address),
+    /// Connects to the internet through the device.
+    ConnectTethering(Address),
+    DisconnectTethering(Address),
     StateUpdate,
 }
 
@@ -180,10 +206,25 @@ pub enum BluerDeviceStatus {
 #[derive(Debug, Clone)]
 pub struct BluerDevice {
     pub name: String,
+    /// Whether the device has a name, rather than `name` being its address.
+    pub has_name: bool,
     pub address: Address,
     pub status: BluerDeviceStatus,
     pub properties: Vec<DeviceProperty>,
     pub icon: String,
+    /// Signal strength in dBm, only known for devices seen while discovering.
+    pub rssi: Option<i16>,
+    /// Whether the device only seems to speak Bluetooth LE, having no Class of
+    /// Device. These connect without pairing, pairing on demand.
+    pub le_only: bool,
+    /// Battery level in percent, from BlueZ or the device's Battery Service.
+    pub battery: Option<u8>,
+    /// Heart rate in beats per minute, while connected.
+    pub heart_rate: Option<u16>,
+    /// Whether the device shares its internet connection, like a phone.
+    pub nap: bool,
+    /// Whether the internet is connected to through the device.
+    pub tethered: bool,
 }
 
 impl Eq for BluerDevice {}
@@ -216,12 +257,9 @@ impl PartialEq for BluerDevice {
 
 impl BluerDevice {
     pub async fn from_device(device: &bluer::Device) -> Self {
-        let mut name = device
-            .name()
-            .await
-            .unwrap_or_default()
-            .unwrap_or_else(|| device.address().to_string());
-        if name.is_empty() {
+        let mut name = device.name().await.unwrap_or_default().unwrap_or_default();
+        let has_name = !name.is_empty();
+        if !has_name {
             name = device.address().to_string();
         };
         let is_paired = device.is_paired().await.unwrap_or_default();
@@ -236,21 +274,47 @@ impl BluerDevice {
         };
         let icon = properties
             .iter()
-            .find_map(|p| {
-                if let DeviceProperty::Icon(icon) = p {
-                    Some(icon.clone())
-                } else {
-                    None
-                }
+            .find_map(|p| match p {
+                DeviceProperty::Icon(icon) => Some(icon.clone()),
+                _ => None,
+            })
+            .or_else(|| {
+                properties.iter().find_map(|p| match p {
+                    DeviceProperty::Class(class) => icon_from_class(*class),
+                    DeviceProperty::Appearance(appearance) => icon_from_appearance(*appearance),
+                    _ => None,
+                })
             })
             .unwrap_or_else(|| "bluetooth-symbolic".into());
+        let rssi = properties.iter().find_map(|p| match p {
+            DeviceProperty::Rssi(rssi) => Some(*rssi),
+            _ => None,
+        });
+        let le_only = !properties
+            .iter()
+            .any(|p| matches!(p, DeviceProperty::Class(_)));
+        let battery = properties.iter().find_map(|p| match p {
+            DeviceProperty::BatteryPercentage(percentage) => Some(*percentage),
+            _ => None,
+        });
+        let nap = properties.iter().any(|p| match p {
+            DeviceProperty::Uuids(uuids) => uuids.contains(&tethering::NAP_UUID),
+            _ => false,
+        });
 
         Self {
             name,
+            has_name,
             address: device.address(),
             status,
             properties,
             icon,
+            rssi,
+            le_only,
+            battery,
+            heart_rate: None,
+            nap,
+            tethered: false,
         }
     }
 
@@ -268,6 +332,64 @@ impl BluerDevice {
     }
 }
 
+/// Icon for a device's Class of Device, for devices BlueZ found no icon for.
+fn icon_from_class(class: u32) -> Option<String> {
+    let major = (class >> 8) & 0x1f;
+    let minor = (class >> 2) & 0x3f;
+    let icon = match major {
+        0x01 => "computer",
+        0x02 => "phone",
+        0x03 => "network-wireless",
+        0x04 => match minor {
+            0x01 | 0x02 => "audio-headset",
+            0x06 => "audio-headphones",
+            0x0b | 0x0c | 0x0d => "camera-video",
+            _ => "audio-speakers",
+        },
+        0x05 => match minor & 0x30 {
+            0x10 => "input-keyboard",
+            0x20 => "input-mouse",
+            _ => match minor & 0x0f {
+                0x01 | 0x02 => "input-gaming",
+                0x05 => "input-tablet",
+                _ => "input-keyboard",
+            },
+        },
+        0x06 => {
+            if minor & 0x20 != 0 {
+                "printer"
+            } else if minor & 0x08 != 0 {
+                "camera-photo"
+            } else {
+                "video-display"
+            }
+        }
+        _ => return None,
+    };
+    Some(icon.to_string())
+}
+
+/// Icon for a Bluetooth Low Energy device's appearance.
+fn icon_from_appearance(appearance: u16) -> Option<String> {
+    let icon = match appearance >> 6 {
+        0x01 => "phone",
+        0x02 => "computer",
+        0x03 | 0x04 => "preferences-system-time",
+        0x05 => "video-display",
+        0x0f => match appearance & 0x3f {
+            0x01 => "input-keyboard",
+            0x02 => "input-mouse",
+            0x03 | 0x04 => "input-gaming",
+            0x05 => "input-tablet",
+            _ => "input-keyboard",
+        },
+        0x21 => "audio-speakers",
+        0x25 => "audio-headphones",
+        _ => return None,
+    };
+    Some(icon.to_string())
+}
+
 #[derive(Debug, Clone)]
 pub enum BluerSessionEvent {
     RequestResponse {
@@ -295,7 +417,8 @@ pub struct BluerSessionState {
     _session: Session,
     _agent_handle: AgentHandle,
     pub adapter: Adapter,
-    pub devices: Arc<Mutex<Vec<BluerDevice>>>,
+    devices: DeviceCache,
+    gatt: GattMonitors,
     pub rx: Option<Receiver<BluerSessionEvent>>,
     pub req_tx: Sender<BluerRequest>,
     tx: Sender<BluerSessionEvent>,
@@ -303,9 +426,10 @@ pub struct BluerSessionState {
 }
 
 impl BluerSessionState {
-    pub(crate) async fn new(session: Session) -> anyhow::Result<Self> {
+    pub async fn new(session: Session) -> anyhow::Result<Self> {
         let adapter = session.default_adapter().await?;
-        let devices = build_device_list(&adapter).await;
+        let devices = DeviceCache::default();
+        devices.refresh(&adapter).await;
         let (tx, rx) = tokio::sync::mpsc::channel(100);
         let (req_tx, req_rx) = channel(100);
         let tx_clone_1 = tx.clone();
@@ -483,11 +607,18 @@ impl BluerSessionState {
         };
         let _agent_handle = session.register_agent(_agent).await?;
 
+        let gatt = GattMonitors {
+            adapter: adapter.clone(),
+            devices: devices.clone(),
+            tx: tx.clone(),
+            tasks: Default::default(),
+        };
         let self_ = Self {
             _agent_handle,
             _session: session,
             adapter,
-            devices: Arc::new(Mutex::new(devices)),
+            devices,
+            gatt,
             rx: Some(rx),
             req_tx,
             tx,
@@ -504,6 +635,8 @@ impl BluerSessionState {
         let tx = self.tx.clone();
         let req_tx = self.req_tx.clone();
         let adapter_clone = self.adapter.clone();
+        let devices = self.devices.clone();
+        let gatt = self.gatt.clone();
         let _handle: JoinHandle<anyhow::Result<()>> = spawn(async move {
             let mut status = adapter_clone.is_powered().await.unwrap_or_default();
             loop {
@@ -511,12 +644,9 @@ impl BluerSessionState {
                 let new_status = adapter_clone.is_powered().await.unwrap_or_default();
                 if new_status != status {
                     status = new_status;
-                    let state = BluerState {
-                        devices: build_device_list(&adapter_clone).await,
-                        bluetooth_enabled: status,
-                        discoverable: adapter_clone.is_discoverable().await.unwrap_or_default(),
-                        pairable: adapter_clone.is_pairable().await.unwrap_or_default(),
-                    };
+                    let state =
+                        adapter_state(&adapter_clone, devices.refresh(&adapter_clone).await).await;
+                    gatt.update(&state.devices).await;
                     if state.bluetooth_enabled {
                         for d in &state.devices {
                             if d.paired_and_trusted() {
@@ -532,10 +662,12 @@ impl BluerSessionState {
     }
 
     // Note: For some reason, this doesn't actually seem to work so well. it seems unreliable...
-    pub(crate) fn process_changes(&self) {
+    fn process_changes(&self) {
         let tx = self.tx.clone();
         let req_tx = self.req_tx.clone();
         let adapter_clone = self.adapter.clone();
+        let cache = self.devices.clone();
+        let gatt = self.gatt.clone();
         let _monitor_devices: tokio::task::JoinHandle<Result<(), anyhow::Error>> =
             spawn(async move {
                 let mut change_stream = adapter_clone.discover_devices_with_changes().await?;
@@ -552,29 +684,21 @@ impl BluerSessionState {
                         changed = true;
                     }
                     if changed {
-                        let mut new_devices = build_device_list(&adapter_clone).await;
+                        let new_devices = cache.refresh(&adapter_clone).await;
                         for d in new_devices
                             .iter()
                             .filter(|d| !devices.contains(d) && d.paired_and_trusted())
                         {
                             _ = req_tx.send(BluerRequest::ConnectDevice(d.address)).await;
                         }
-                        devices = mem::take(&mut new_devices);
+                        devices = new_devices.clone();
+                        gatt.update(&devices).await;
 
                         changed = false;
                         let _ = tx
-                            .send(BluerSessionEvent::ChangesProcessed(BluerState {
-                                devices: build_device_list(&adapter_clone).await,
-                                bluetooth_enabled: adapter_clone
-                                    .is_powered()
-                                    .await
-                                    .unwrap_or_default(),
-                                discoverable: adapter_clone
-                                    .is_discoverable()
-                                    .await
-                                    .unwrap_or_default(),
-                                pairable: adapter_clone.is_pairable().await.unwrap_or_default(),
-                            }))
+                            .send(BluerSessionEvent::ChangesProcessed(
+                                adapter_state(&adapter_clone, new_devices).await,
+                            ))
                             .await;
                         // reset timeout
                         milli_timeout = 10;
@@ -588,9 +712,11 @@ impl BluerSessionState {
             });
     }
 
-    pub(crate) fn process_requests(&self, request_rx: Receiver<BluerRequest>) {
+    fn process_requests(&self, request_rx: Receiver<BluerRequest>) {
         let active_requests = self.active_requests.clone();
         let adapter = self.adapter.clone();
+        let devices = self.devices.clone();
+        let gatt = self.gatt.clone();
         let tx = self.tx.clone();
 
         let _handle: JoinHandle<anyhow::Result<()>> = spawn(async move {
@@ -602,6 +728,8 @@ impl BluerSessionState {
                 let active_requests_clone = active_requests.clone();
                 let tx_clone = tx.clone();
                 let adapter_clone = adapter.clone();
+                let devices = devices.clone();
+                let gatt = gatt.clone();
                 let handle = spawn(async move {
                     let mut err_msg = None;
                     match &req_clone {
@@ -660,6 +788,20 @@ impl BluerSessionState {
                                 err_msg = Some("No active connection request found".to_string());
                             }
                         }
+                        BluerRequest::ConnectTethering(address) => {
+                            let name = match adapter_clone.device(*address) {
+                                Ok(device) => device.alias().await.unwrap_or_default(),
+                                Err(_) => String::new(),
+                            };
+                            if let Err(err) = tethering::connect(*address, &name).await {
+                                err_msg = Some(err.to_string());
+                            }
+                        }
+                        BluerRequest::DisconnectTethering(address) => {
+                            if let Err(err) = tethering::disconnect(*address).await {
+                                err_msg = Some(err.to_string());
+                            }
+                        }
                         BluerRequest::StateUpdate => {}
                         BluerRequest::SetPairable(enabled) => {
                             let res = adapter_clone.set_pairable(*enabled).await;
@@ -675,12 +817,22 @@ impl BluerSessionState {
                         }
                     };
 
-                    let state = BluerState {
-                        devices: build_device_list(&adapter_clone).await,
-                        bluetooth_enabled: adapter_clone.is_powered().await.unwrap_or_default(),
-                        discoverable: adapter_clone.is_discoverable().await.unwrap_or_default(),
-                        pairable: adapter_clone.is_pairable().await.unwrap_or_default(),
+                    let devices = match &req_clone {
+                        BluerRequest::PairDevice(address)
+                        | BluerRequest::ConnectDevice(address)
+                        | BluerRequest::DisconnectDevice(address)
+                        | BluerRequest::CancelConnect(address)
+                        | BluerRequest::ConnectTethering(address)
+                        | BluerRequest::DisconnectTethering(address) => {
+                            devices.refresh_device(&adapter_clone, *address).await
+                        }
+                        BluerRequest::SetBluetoothEnabled(_)
+                        | BluerRequest::SetPairable(_)
+                        | BluerRequest::SetDiscoverable(_)
+                        | BluerRequest::StateUpdate => devices.get().await,
                     };
+                    gatt.update(&devices).await;
+                    let state = adapter_state(&adapter_clone, devices).await;
 
                     let _ = tx_clone
                         .send(BluerSessionEvent::RequestResponse {
@@ -701,14 +853,174 @@ impl BluerSessionState {
         });
     }
 
-    pub(crate) async fn bluer_state(&self) -> BluerState {
-        BluerState {
-            devices: build_device_list(&self.adapter).await,
-            // TODO is this a proper way of checking if bluetooth is enabled?
-            bluetooth_enabled: self.adapter.is_powered().await.unwrap_or_default(),
-            discoverable: self.adapter.is_discoverable().await.unwrap_or_default(),
-            pairable: self.adapter.is_pairable().await.unwrap_or_default(),
+    pub async fn bluer_state(&self) -> BluerState {
+        adapter_state(&self.adapter, self.devices.get().await).await
+    }
+}
+
+/// The adapter's devices, as last read from BlueZ.
+#[derive(Clone, Default)]
+struct DeviceCache {
+    devices: Arc<Mutex<Vec<BluerDevice>>>,
+    /// Readings of GATT services, kept across refreshes.
+    readings: Arc<Mutex<HashMap<Address, gatt::Readings>>>,
+}
+
+impl DeviceCache {
+    async fn get(&self) -> Vec<BluerDevice> {
+        self.devices.lock().await.clone()
+    }
+
+    /// Lists every device again, for when devices were added or removed.
+    async fn refresh(&self, adapter: &Adapter) -> Vec<BluerDevice> {
+        let mut devices = build_device_list(adapter).await;
+        self.apply_readings(&mut devices).await;
+        apply_tethering(&mut devices).await;
+        *self.devices.lock().await = devices.clone();
+        devices
+    }
+
+    /// Reads one device again, for when only it changed.
+    async fn refresh_device(&self, adapter: &Adapter, address: Address) -> Vec<BluerDevice> {
+        let mut device = match adapter.device(address) {
+            Ok(device) => vec![BluerDevice::from_device(&device).await],
+            Err(_) => Vec::new(),
+        };
+        self.apply_readings(&mut device).await;
+        apply_tethering(&mut device).await;
+        let mut devices = self.devices.lock().await;
+        devices.retain(|d| d.address != address);
+        devices.extend(device);
+        devices.sort();
+        devices.clone()
+    }
+
+    /// Records a reading of a device's GATT services.
+    async fn set_reading(&self, address: Address, reading: gatt::Reading) -> Vec<BluerDevice> {
+        let mut readings = self.readings.lock().await;
+        let entry = readings.entry(address).or_default();
+        entry.update(reading);
+        let entry = *entry;
+        drop(readings);
+
+        let mut devices = self.devices.lock().await;
+        for d in devices.iter_mut().filter(|d| d.address == address) {
+            apply_reading(d, entry);
+        }
+        devices.clone()
+    }
+
+    /// Forgets the readings of devices `keep` returns `false` for.
+    async fn retain_readings(&self, keep: impl Fn(&Address) -> bool) {
+        self.readings
+            .lock()
+            .await
+            .retain(|address, _| keep(address));
+    }
+
+    async fn apply_readings(&self, devices: &mut [BluerDevice]) {
+        let readings = self.readings.lock().await;
+        for d in devices
+            .iter_mut()
+            .filter(|d| d.status == BluerDeviceStatus::Connected)
+        {
+            if let Some(r) = readings.get(&d.address) {
+                apply_reading(d, *r);
+            }
+        }
+    }
+}
+
+/// Marks the devices the internet is connected to through. Only connected
+/// devices sharing their connection are looked up.
+async fn apply_tethering(devices: &mut [BluerDevice]) {
+    let is_tethering = |d: &BluerDevice| d.nap && d.status == BluerDeviceStatus::Connected;
+    if !devices.iter().any(is_tethering) {
+        return;
+    }
+    let active = tethering::active().await;
+    for d in devices.iter_mut().filter(|d| is_tethering(d)) {
+        d.tethered = active.contains(&d.address);
+    }
+}
+
+fn apply_reading(device: &mut BluerDevice, readings: gatt::Readings) {
+    // BlueZ's own battery level wins, if it has one
+    device.battery = device.battery.or(readings.battery);
+    device.heart_rate = readings.heart_rate;
+}
+
+/// Tasks following the GATT services of connected devices, recording their
+/// readings in the device cache.
+#[derive(Clone)]
+struct GattMonitors {
+    adapter: Adapter,
+    devices: DeviceCache,
+    tx: Sender<BluerSessionEvent>,
+    tasks: Arc<Mutex<HashMap<Address, JoinHandle<()>>>>,
+}
+
+impl GattMonitors {
+    /// Follows devices that connected and stops following those that
+    /// disconnected.
+    async fn update(&self, devices: &[BluerDevice]) {
+        let is_connected = |address: &Address| {
+            devices
+                .iter()
+                .any(|d| d.address == *address && d.status == BluerDeviceStatus::Connected)
+        };
+        let mut tasks = self.tasks.lock().await;
+        tasks.retain(|address, task| {
+            let keep = is_connected(address);
+            if !keep {
+                task.abort();
+            }
+            keep
+        });
+
+        for d in devices {
+            if d.status != BluerDeviceStatus::Connected || tasks.contains_key(&d.address) {
+                continue;
+            }
+            let Ok(device) = self.adapter.device(d.address) else {
+                continue;
+            };
+            let monitors = self.clone();
+            let task = spawn(async move {
+                let mut readings = match gatt::readings(&device).await {
+                    Ok(readings) => readings,
+                    Err(err) => {
+                        tracing::debug!(?err, "Failed to read GATT services.");
+                        return;
+                    }
+                };
+                while let Some(reading) = readings.next().await {
+                    let devices = monitors
+                        .devices
+                        .set_reading(device.address(), reading)
+                        .await;
+                    let state = adapter_state(&monitors.adapter, devices).await;
+                    _ = monitors
+                        .tx
+                        .send(BluerSessionEvent::ChangesProcessed(state))
+                        .await;
+                }
+            });
+            tasks.insert(d.address, task);
         }
+        self.devices
+            .retain_readings(|address| tasks.contains_key(address))
+            .await;
+    }
+}
+
+async fn adapter_state(adapter: &Adapter, devices: Vec<BluerDevice>) -> BluerState {
+    BluerState {
+        devices,
+        // TODO is this a proper way of checking if bluetooth is enabled?
+        bluetooth_enabled: adapter.is_powered().await.unwrap_or_default(),
+        discoverable: adapter.is_discoverable().await.unwrap_or_default(),
+        pairable: adapter.is_pairable().await.unwrap_or_default(),
     }
 }
 
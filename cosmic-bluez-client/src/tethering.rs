@@ -0,0 +1,110 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! Internet through a phone's Bluetooth tethering (PAN).
+//!
+//! Phones sharing their connection advertise the Network Access Point service.
+//! Connecting to it is left to a NetworkManager connection of the `panu` kind
+//! for the phone, added the first time: activating it has BlueZ connect to the
+//! NAP through its `Network1` interface, and NetworkManager then configures the
+//! `bnep` interface it creates, so it's listed with the other active
+//! connections.
+
+use std::{collections::HashSet, process::Output, str::FromStr};
+
+use anyhow::{bail, Context};
+use bluer::{Address, Uuid};
+use tokio::process::Command;
+
+/// The Network Access Point service, which phones sharing their connection
+/// advertise.
+pub const NAP_UUID: Uuid = Uuid::from_u128(0x0000_1116_0000_1000_8000_0080_5f9b_34fb);
+
+async fn nmcli(args: &[&str]) -> anyhow::Result<String> {
+    let Output {
+        status,
+        stdout,
+        stderr,
+    } = Command::new("nmcli")
+        .args(args)
+        .output()
+        .await
+        .context("Failed to run nmcli")?;
+    if !status.success() {
+        bail!("{}", String::from_utf8_lossy(&stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&stdout).into_owned())
+}
+
+/// `nmcli` escapes colons in the values of terse output.
+fn unescape(value: &str) -> String {
+    value.replace("\\:", ":")
+}
+
+/// The UUID of the NetworkManager connection tethering through the device.
+async fn connection(address: Address) -> anyhow::Result<Option<String>> {
+    let connections = nmcli(&["-g", "UUID,TYPE", "connection", "show"]).await?;
+    for uuid in connections
+        .lines()
+        .filter_map(|line| line.strip_suffix(":bluetooth"))
+    {
+        let bdaddr = nmcli(&["-g", "bluetooth.bdaddr", "connection", "show", "uuid", uuid]).await?;
+        if Address::from_str(&unescape(bdaddr.trim())).is_ok_and(|a| a == address) {
+            return Ok(Some(uuid.to_string()));
+        }
+    }
+    Ok(None)
+}
+
+/// Connects to the internet through the device, adding a connection for it the
+/// first time.
+pub async fn connect(address: Address, name: &str) -> anyhow::Result<()> {
+    let uuid = match connection(address).await? {
+        Some(uuid) => uuid,
+        None => {
+            let bdaddr = address.to_string();
+            nmcli(&[
+                "connection",
+                "add",
+                "type",
+                "bluetooth",
+                "bt-type",
+                "panu",
+                "bluetooth.bdaddr",
+                &bdaddr,
+                "con-name",
+                &format!("{name} Network"),
+            ])
+            .await?;
+            connection(address)
+                .await?
+                .context("The added connection wasn't found")?
+        }
+    };
+    nmcli(&["connection", "up", "uuid", &uuid]).await?;
+    Ok(())
+}
+
+pub async fn disconnect(address: Address) -> anyhow::Result<()> {
+    if let Some(uuid) = connection(address).await? {
+        nmcli(&["connection", "down", "uuid", &uuid]).await?;
+    }
+    Ok(())
+}
+
+/// The devices tethering is active through. NetworkManager names Bluetooth
+/// devices after their address.
+pub async fn active() -> HashSet<Address> {
+    let active = match nmcli(&["-g", "TYPE,DEVICE", "connection", "show", "--active"]).await {
+        Ok(active) => active,
+        Err(err) => {
+            tracing::debug!(?err, "Failed to list active connections.");
+            return HashSet::new();
+        }
+    };
+    active
+        .lines()
+        .filter_map(|line| line.strip_prefix("bluetooth:"))
+        .filter_map(|device| Address::from_str(&unescape(device)).ok())
+        .collect()
+}
@@ -24,15 +24,23 @@ pub async fn handle_wireless_device(device: WirelessDevice<'_>) -> zbus::Result<
         .unwrap_or_default()
         .map(|s| s.into())
         .unwrap_or_else(|| DeviceState::Unknown);
-    // Sort by strength and remove duplicates
+    // Sort by strength and merge access points of the same network
     let mut aps = HashMap::<String, AccessPoint>::new();
     for ap in access_points {
         let ssid = String::from_utf8_lossy(&ap.ssid().await?.clone()).into_owned();
         let strength = ap.strength().await?;
-        if let Some(access_point) = aps.get(&ssid) {
-            if access_point.strength > strength {
-                continue;
+        let bssid = Bssid {
+            hw_address: ap.hw_address().await?,
+            strength,
+            frequency: ap.frequency().await?,
+        };
+        if let Some(access_point) = aps.get_mut(&ssid) {
+            access_point.bssids.push(bssid);
+            if access_point.strength <= strength {
+                access_point.strength = strength;
+                access_point.path = ap.inner().path().to_owned();
             }
+            continue;
         }
         aps.insert(
             ssid.clone(),
@@ -42,11 +50,17 @@ pub async fn handle_wireless_device(device: WirelessDevice<'_>) -> zbus::Result<
                 state,
                 working: false,
                 path: ap.inner().path().to_owned(),
+                bssids: vec![bssid],
+                pinned_bssid: None,
             },
         );
     }
     let aps = aps
         .into_values()
+        .map(|mut ap| {
+            ap.bssids.sort_by(|a, b| b.strength.cmp(&a.strength));
+            ap
+        })
         .sorted_by(|a, b| b.strength.cmp(&a.strength))
         .collect();
     Ok(aps)
@@ -59,4 +73,17 @@ pub struct AccessPoint {
     pub state: DeviceState,
     pub working: bool,
     pub path: ObjectPath<'static>,
+    /// The network's access points, strongest first.
+    pub bssids: Vec<Bssid>,
+    /// The access point a known network's connection is restricted to.
+    pub pinned_bssid: Option<String>,
+}
+
+/// A single access point of a network, such as one node of a mesh.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bssid {
+    pub hw_address: String,
+    pub strength: u8,
+    /// In MHz.
+    pub frequency: u32,
 }
@@ -53,6 +53,7 @@ pub async fn active_connections(
                             hw_address: wireless_device.hw_address().await?,
                             state,
                             strength: access_point.strength().await.unwrap_or_default(),
+                            bssid: access_point.hw_address().await.unwrap_or_default(),
                         });
                     }
                 }
@@ -93,6 +94,8 @@ pub enum ActiveConnectionInfo {
         hw_address: String,
         state: ActiveConnectionState,
         strength: u8,
+        /// The access point connected to.
+        bssid: String,
     },
     Vpn {
         name: String,
@@ -0,0 +1,202 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! The IPv6 settings of the active connections: whether IPv6 is used, and
+//! whether addresses are made with stable privacy rather than from the
+//! hardware address (EUI-64), which tells networks where a device has been.
+
+use std::collections::HashMap;
+
+use tokio::process::Command;
+use zbus::{
+    proxy,
+    zvariant::{OwnedObjectPath, OwnedValue},
+    Connection,
+};
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager"
+)]
+trait Manager {
+    #[zbus(property)]
+    fn active_connections(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Connection.Active",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait Active {
+    #[zbus(property)]
+    fn connection(&self) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn ip6_config(&self) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn vpn(&self) -> zbus::Result<bool>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Settings.Connection",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait SettingsConnection {
+    fn get_settings(&self) -> zbus::Result<HashMap<String, HashMap<String, OwnedValue>>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.IP6Config",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait Ip6Config {
+    #[zbus(property)]
+    fn address_data(&self) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+}
+
+/// How the connection gets its IPv6 addresses, the `ipv6.method` setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ipv6Method {
+    /// Router advertisements, and DHCPv6 if they ask for it.
+    Auto,
+    Dhcp,
+    Manual,
+    LinkLocal,
+    Shared,
+    /// Left to whatever else configures the interface.
+    Ignore,
+    Disabled,
+}
+
+impl Ipv6Method {
+    fn parse(method: &str) -> Self {
+        match method {
+            "dhcp" => Self::Dhcp,
+            "manual" => Self::Manual,
+            "link-local" => Self::LinkLocal,
+            "shared" => Self::Shared,
+            "ignore" => Self::Ignore,
+            "disabled" => Self::Disabled,
+            _ => Self::Auto,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ipv6Settings {
+    pub uuid: String,
+    pub method: Ipv6Method,
+    /// Addresses are made with stable privacy, rather than EUI-64.
+    pub private: bool,
+    pub addresses: Vec<String>,
+}
+
+impl Ipv6Settings {
+    pub fn enabled(&self) -> bool {
+        !matches!(self.method, Ipv6Method::Disabled | Ipv6Method::Ignore)
+    }
+}
+
+/// A change to the IPv6 settings of a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ipv6Change {
+    Enabled(bool),
+    Private(bool),
+}
+
+type ConnectionSettings = HashMap<String, HashMap<String, OwnedValue>>;
+
+fn setting<T: TryFrom<OwnedValue>>(
+    settings: &ConnectionSettings,
+    group: &str,
+    key: &str,
+) -> Option<T> {
+    T::try_from(settings.get(group)?.get(key)?.try_clone().ok()?).ok()
+}
+
+/// The IPv6 settings of the active connections other than VPNs, by the name
+/// they're listed under: the SSID of wifi connections, and the name of others.
+pub async fn active_settings(conn: &Connection) -> zbus::Result<HashMap<String, Ipv6Settings>> {
+    let mut active_settings = HashMap::new();
+    for path in ManagerProxy::new(conn).await?.active_connections().await? {
+        let active = ActiveProxy::builder(conn).path(path)?.build().await?;
+        if active.vpn().await.unwrap_or_default() {
+            continue;
+        }
+        let settings = SettingsConnectionProxy::builder(conn)
+            .path(active.connection().await?)?
+            .build()
+            .await?
+            .get_settings()
+            .await?;
+        let name = match setting::<Vec<u8>>(&settings, "802-11-wireless", "ssid") {
+            Some(ssid) => String::from_utf8_lossy(&ssid).into_owned(),
+            None => setting::<String>(&settings, "connection", "id").unwrap_or_default(),
+        };
+        let Some(uuid) = setting::<String>(&settings, "connection", "uuid") else {
+            continue;
+        };
+        let method = setting::<String>(&settings, "ipv6", "method")
+            .map_or(Ipv6Method::Auto, |method| Ipv6Method::parse(&method));
+        // `eui64` and `default-or-eui64`, where the global default isn't set;
+        // new connections are made with stable privacy
+        let private = !matches!(
+            setting::<i32>(&settings, "ipv6", "addr-gen-mode"),
+            Some(0 | 2)
+        );
+        let addresses = match active.ip6_config().await {
+            Ok(path) if path.as_str() != "/" => Ip6ConfigProxy::builder(conn)
+                .path(path)?
+                .build()
+                .await?
+                .address_data()
+                .await
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|data| String::try_from(data.get("address")?.try_clone().ok()?).ok())
+                .collect(),
+            _ => Vec::new(),
+        };
+        active_settings.insert(
+            name,
+            Ipv6Settings {
+                uuid,
+                method,
+                private,
+                addresses,
+            },
+        );
+    }
+    Ok(active_settings)
+}
+
+/// Changes the IPv6 settings of the connection, and activates it again for
+/// them to take effect.
+pub async fn set_ipv6(uuid: &str, change: Ipv6Change) -> anyhow::Result<()> {
+    let (key, value) = match change {
+        Ipv6Change::Enabled(enabled) => ("ipv6.method", if enabled { "auto" } else { "disabled" }),
+        Ipv6Change::Private(private) => (
+            "ipv6.addr-gen-mode",
+            if private { "stable-privacy" } else { "eui64" },
+        ),
+    };
+    // Like for pinning an access point, `nmcli` keeps the secrets
+    let status = Command::new("nmcli")
+        .args(["connection", "modify", "uuid", uuid, key, value])
+        .status()
+        .await?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("nmcli exited with {status}"));
+    }
+
+    // Addresses are only made again when the connection is activated
+    let status = Command::new("nmcli")
+        .args(["connection", "up", "uuid", uuid])
+        .status()
+        .await?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("nmcli exited with {status}"));
+    }
+    Ok(())
+}
@@ -1,7 +1,19 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! NetworkManager client shared by the applets: the connection state model,
+//! subscriptions watching devices, active connections and the radio, and the
+//! requests that change them. [`proxy`] reads and writes the proxy settings,
+//! [`metered`] whether the connection is metered, and [`ipv6`] how
+//! connections use IPv6.
+
 pub mod active_conns;
 pub mod available_wifi;
 pub mod current_networks;
 pub mod devices;
+pub mod ipv6;
+pub mod metered;
+pub mod proxy;
+pub mod wireguard;
 pub mod wireless_enabled;
 
 use std::{collections::HashMap, fmt::Debug, time::Duration};
@@ -21,6 +33,7 @@ use futures::{
     channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender},
     SinkExt, StreamExt,
 };
+use itertools::Itertools;
 use tokio::process::Command;
 use zbus::{
     zvariant::{self, OwnedValue, Value},
@@ -30,6 +43,7 @@ use zbus::{
 use self::{
     available_wifi::{handle_wireless_device, AccessPoint},
     current_networks::{active_connections, ActiveConnectionInfo},
+    ipv6::{Ipv6Change, Ipv6Settings},
 };
 
 #[derive(Debug)]
@@ -208,6 +222,38 @@ async fn start_listening(
                         })
                         .await;
                 }
+                Some(NetworkManagerRequest::PinBssid { ssid, bssid }) => {
+                    let success = match pin_bssid(&conn, &ssid, bssid.as_deref()).await {
+                        Ok(()) => true,
+                        Err(err) => {
+                            tracing::error!("Failed to pin access point: {:?}", err);
+                            false
+                        }
+                    };
+                    _ = output
+                        .send(NetworkManagerEvent::RequestResponse {
+                            req: NetworkManagerRequest::PinBssid { ssid, bssid },
+                            success,
+                            state: NetworkManagerState::new(&conn).await.unwrap_or_default(),
+                        })
+                        .await;
+                }
+                Some(NetworkManagerRequest::SetIpv6 { uuid, change }) => {
+                    let success = match ipv6::set_ipv6(&uuid, change).await {
+                        Ok(()) => true,
+                        Err(err) => {
+                            tracing::error!("Failed to change IPv6 settings: {:?}", err);
+                            false
+                        }
+                    };
+                    _ = output
+                        .send(NetworkManagerEvent::RequestResponse {
+                            req: NetworkManagerRequest::SetIpv6 { uuid, change },
+                            success,
+                            state: NetworkManagerState::new(&conn).await.unwrap_or_default(),
+                        })
+                        .await;
+                }
                 Some(NetworkManagerRequest::Reload) => {
                     let state = NetworkManagerState::new(&conn).await.unwrap_or_default();
                     _ = output
@@ -256,6 +302,73 @@ async fn start_listening(
     }
 }
 
+type ConnectionSettings = HashMap<String, HashMap<String, OwnedValue>>;
+
+/// The BSSID a wifi connection is restricted to, formatted like NetworkManager
+/// formats hardware addresses.
+fn bssid_setting(settings: &ConnectionSettings) -> Option<String> {
+    let bssid = settings
+        .get("802-11-wireless")?
+        .get("bssid")?
+        .try_clone()
+        .ok()?;
+    let bssid = Vec::<u8>::try_from(bssid).ok()?;
+    (!bssid.is_empty()).then(|| bssid.iter().map(|b| format!("{b:02X}")).join(":"))
+}
+
+async fn pin_bssid(conn: &Connection, ssid: &str, bssid: Option<&str>) -> anyhow::Result<()> {
+    let s = NetworkManagerSettings::new(conn).await?;
+    let mut uuid = None;
+    for c in s.list_connections().await.unwrap_or_default() {
+        let settings = c.get_settings().await.ok().unwrap_or_default();
+        let connection_uuid = settings
+            .get("connection")
+            .and_then(|c| c.get("uuid"))
+            .and_then(|uuid| uuid.try_clone().ok())
+            .and_then(|uuid| String::try_from(uuid).ok());
+        if Settings::new(settings)
+            .wifi
+            .and_then(|w| w.ssid)
+            .and_then(|ssid| String::from_utf8(ssid).ok())
+            .is_some_and(|s| s == ssid)
+        {
+            uuid = connection_uuid;
+            break;
+        }
+    }
+    let uuid = uuid.ok_or_else(|| anyhow::anyhow!("No connection for {ssid}"))?;
+
+    // Updating the connection over D-Bus replaces all of its settings,
+    // including the secrets it doesn't return, which `nmcli` takes care of.
+    let status = Command::new("nmcli")
+        .args([
+            "connection",
+            "modify",
+            "uuid",
+            &uuid,
+            "802-11-wireless.bssid",
+        ])
+        .arg(bssid.unwrap_or_default())
+        .status()
+        .await?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("nmcli exited with {status}"));
+    }
+
+    // Reconnect for the change to take effect
+    let nm = NetworkManager::new(conn).await?;
+    for c in nm.active_connections().await.unwrap_or_default() {
+        if c.id().await.unwrap_or_default() == ssid {
+            Command::new("nmcli")
+                .args(["connection", "up", "uuid", &uuid])
+                .status()
+                .await?;
+            break;
+        }
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 pub enum NetworkManagerRequest {
     SetAirplaneMode(bool),
@@ -264,6 +377,17 @@ pub enum NetworkManagerRequest {
     Disconnect(String),
     Password(String, String),
     Forget(String),
+    /// Restricts the known network's connection to one of its access points,
+    /// or lets it roam again if `None`.
+    PinBssid {
+        ssid: String,
+        bssid: Option<String>,
+    },
+    /// Changes how the connection uses IPv6, activating it again.
+    SetIpv6 {
+        uuid: String,
+        change: Ipv6Change,
+    },
     Reload,
 }
 
@@ -292,6 +416,8 @@ pub struct NetworkManagerState {
     pub wifi_enabled: bool,
     pub airplane_mode: bool,
     pub connectivity: NmConnectivityState,
+    /// IPv6 settings of the active connections, by their name.
+    pub ipv6: HashMap<String, Ipv6Settings>,
 }
 
 impl Default for NetworkManagerState {
@@ -303,6 +429,7 @@ impl Default for NetworkManagerState {
             wifi_enabled: false,
             airplane_mode: false,
             connectivity: NmConnectivityState::Unknown,
+            ipv6: HashMap::new(),
         }
     }
 }
@@ -363,6 +490,7 @@ impl NetworkManagerState {
         let mut known_ssid = Vec::with_capacity(known_conns.len());
         for c in known_conns {
             let s = c.get_settings().await.unwrap();
+            let pinned_bssid = bssid_setting(&s);
             let s = Settings::new(s);
             if let Some(cur_ssid) = s
                 .wifi
@@ -370,6 +498,12 @@ impl NetworkManagerState {
                 .and_then(|w| w.ssid)
                 .and_then(|ssid| String::from_utf8(ssid).ok())
             {
+                for ap in wireless_access_points
+                    .iter_mut()
+                    .filter(|ap| ap.ssid == cur_ssid)
+                {
+                    ap.pinned_bssid.clone_from(&pinned_bssid);
+                }
                 known_ssid.push(cur_ssid);
             }
         }
@@ -385,6 +519,7 @@ impl NetworkManagerState {
         self_.active_conns = active_conns;
         self_.known_access_points = known_access_points;
         self_.connectivity = network_manager.connectivity().await?;
+        self_.ipv6 = ipv6::active_settings(conn).await.unwrap_or_default();
 
         Ok(self_)
     }
@@ -0,0 +1,99 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Whether the network connection is metered, so large downloads can wait.
+
+use std::collections::HashMap;
+
+use futures::{Stream, StreamExt};
+use tokio::process::Command;
+use zbus::{
+    proxy,
+    zvariant::{OwnedObjectPath, Value},
+    Connection,
+};
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager"
+)]
+trait Manager {
+    #[zbus(property)]
+    fn metered(&self) -> zbus::Result<u32>;
+
+    #[zbus(property)]
+    fn primary_connection(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Connection.Active",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait Active {
+    #[zbus(property)]
+    fn uuid(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn devices(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Device",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait Device {
+    fn reapply(
+        &self,
+        connection: HashMap<&str, HashMap<&str, Value<'_>>>,
+        version_id: u64,
+        flags: u32,
+    ) -> zbus::Result<()>;
+}
+
+/// Whether an `NMMetered` value is metered, set so or guessed, like for a
+/// phone's hotspot.
+fn is_metered(value: u32) -> bool {
+    matches!(value, 1 | 3)
+}
+
+/// Whether the primary connection is metered, starting with the current state.
+pub async fn metered_changes(conn: &Connection) -> zbus::Result<impl Stream<Item = bool>> {
+    let manager = ManagerProxy::new(conn).await?;
+    Ok(manager
+        .receive_metered_changed()
+        .await
+        .then(|change| async move { change.get().await.map_or(false, is_metered) }))
+}
+
+/// Marks the primary connection as metered or not, overriding NetworkManager's
+/// guess.
+pub async fn set_metered(conn: &Connection, metered: bool) -> anyhow::Result<()> {
+    let manager = ManagerProxy::new(conn).await?;
+    let path = manager.primary_connection().await?;
+    if path.as_str() == "/" {
+        return Err(anyhow::anyhow!("No primary connection"));
+    }
+    let active = ActiveProxy::builder(conn).path(path)?.build().await?;
+    let uuid = active.uuid().await?;
+
+    // Like for pinning an access point, `nmcli` keeps the secrets
+    let status = Command::new("nmcli")
+        .args(["connection", "modify", "uuid", &uuid, "connection.metered"])
+        .arg(if metered { "yes" } else { "no" })
+        .status()
+        .await?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("nmcli exited with {status}"));
+    }
+
+    // Applies the change without reconnecting
+    for device in active.devices().await? {
+        DeviceProxy::builder(conn)
+            .path(device)?
+            .build()
+            .await?
+            .reapply(HashMap::new(), 0, 0)
+            .await?;
+    }
+    Ok(())
+}
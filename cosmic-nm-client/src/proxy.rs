@@ -0,0 +1,149 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! The desktop's proxy settings, which GLib and libproxy based applications
+//! follow, and the proxy settings of the active NetworkManager connections.
+
+use cosmic_dbus_networkmanager::nm::NetworkManager;
+use std::io;
+use tokio::process::Command;
+use zbus::Connection;
+
+const SCHEMA: &str = "org.gnome.system.proxy";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProxyMode {
+    #[default]
+    None,
+    /// Configured by a PAC file.
+    Auto,
+    Manual,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProxyKind {
+    #[default]
+    Http,
+    Socks,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProxySettings {
+    pub mode: ProxyMode,
+    pub pac_url: String,
+    /// The kind of manual proxy.
+    pub kind: ProxyKind,
+    pub host: String,
+    pub port: u16,
+}
+
+async fn gsettings_get(schema: &str, key: &str) -> io::Result<String> {
+    let output = Command::new("gsettings")
+        .args(["get", schema, key])
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "gsettings exited with {}",
+            output.status
+        )));
+    }
+    let value = String::from_utf8_lossy(&output.stdout);
+    Ok(value.trim().trim_matches('\'').to_string())
+}
+
+async fn gsettings_set(schema: &str, key: &str, value: &str) -> io::Result<()> {
+    let status = Command::new("gsettings")
+        .args(["set", schema, key, value])
+        .status()
+        .await?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("gsettings exited with {status}")))
+    }
+}
+
+/// Reads the desktop's proxy settings. A manual SOCKS proxy is only reported
+/// if there's no HTTP proxy.
+pub async fn proxy_settings() -> io::Result<ProxySettings> {
+    let mode = match gsettings_get(SCHEMA, "mode").await?.as_str() {
+        "auto" => ProxyMode::Auto,
+        "manual" => ProxyMode::Manual,
+        _ => ProxyMode::None,
+    };
+    let pac_url = gsettings_get(SCHEMA, "autoconfig-url").await?;
+
+    let http_schema = format!("{SCHEMA}.http");
+    let socks_schema = format!("{SCHEMA}.socks");
+    let mut kind = ProxyKind::Http;
+    let mut host = gsettings_get(&http_schema, "host").await?;
+    let mut port = gsettings_get(&http_schema, "port").await?;
+    if host.is_empty() {
+        let socks_host = gsettings_get(&socks_schema, "host").await?;
+        if !socks_host.is_empty() {
+            kind = ProxyKind::Socks;
+            host = socks_host;
+            port = gsettings_get(&socks_schema, "port").await?;
+        }
+    }
+
+    Ok(ProxySettings {
+        mode,
+        pac_url,
+        kind,
+        host,
+        port: port.parse().unwrap_or_default(),
+    })
+}
+
+/// Writes the desktop's proxy settings, and sets the active connections to use
+/// the PAC file too.
+///
+/// NetworkManager only knows PAC files, so its connections are set to use no
+/// proxy for a manual one. Their settings apply the next time they connect.
+pub async fn set_proxy_settings(conn: &Connection, settings: &ProxySettings) -> io::Result<()> {
+    let (mode, nm_method) = match settings.mode {
+        ProxyMode::None => ("none", "none"),
+        ProxyMode::Auto => ("auto", "auto"),
+        ProxyMode::Manual => ("manual", "none"),
+    };
+    gsettings_set(SCHEMA, "mode", mode).await?;
+    match settings.mode {
+        ProxyMode::None => {}
+        ProxyMode::Auto => gsettings_set(SCHEMA, "autoconfig-url", &settings.pac_url).await?,
+        ProxyMode::Manual => {
+            let (schema, other) = match settings.kind {
+                ProxyKind::Http => ("http", "socks"),
+                ProxyKind::Socks => ("socks", "http"),
+            };
+            let schema = format!("{SCHEMA}.{schema}");
+            gsettings_set(&schema, "host", &settings.host).await?;
+            gsettings_set(&schema, "port", &settings.port.to_string()).await?;
+            gsettings_set(&format!("{SCHEMA}.{other}"), "host", "").await?;
+        }
+    }
+
+    let nm = NetworkManager::new(conn).await.map_err(io::Error::other)?;
+    for c in nm.active_connections().await.unwrap_or_default() {
+        if c.vpn().await.unwrap_or_default() {
+            continue;
+        }
+        let Ok(uuid) = c.uuid().await else {
+            continue;
+        };
+        let pac_url = if settings.mode == ProxyMode::Auto {
+            settings.pac_url.as_str()
+        } else {
+            ""
+        };
+        let status = Command::new("nmcli")
+            .args(["connection", "modify", "uuid", &uuid])
+            .args(["proxy.method", nm_method, "proxy.pac-url", pac_url])
+            .status()
+            .await?;
+        if !status.success() {
+            tracing::error!("Failed to set proxy of connection {}: {}", uuid, status);
+        }
+    }
+    Ok(())
+}
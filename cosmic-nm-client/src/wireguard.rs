@@ -0,0 +1,187 @@
+// Copyright 2024 System76 <info@system76.com>
+// SPDX-License-Identifier: GPL-3.0-only
+
+//! WireGuard connections managed by NetworkManager.
+
+use std::collections::HashMap;
+
+use zbus::{
+    proxy,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue},
+    Connection,
+};
+
+/// `NM_ACTIVE_CONNECTION_STATE_ACTIVATED`
+const STATE_ACTIVATED: u32 = 2;
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager"
+)]
+trait NetworkManager {
+    /// ActivateConnection method
+    fn activate_connection(
+        &self,
+        connection: &ObjectPath<'_>,
+        device: &ObjectPath<'_>,
+        specific_object: &ObjectPath<'_>,
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    /// DeactivateConnection method
+    fn deactivate_connection(&self, active_connection: &ObjectPath<'_>) -> zbus::Result<()>;
+
+    /// ActiveConnections property
+    #[zbus(property)]
+    fn active_connections(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Settings",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager/Settings"
+)]
+trait Settings {
+    /// ListConnections method
+    fn list_connections(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Settings.Connection",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait SettingsConnection {
+    /// GetSettings method
+    fn get_settings(&self) -> zbus::Result<HashMap<String, HashMap<String, OwnedValue>>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.Connection.Active",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait ActiveConnection {
+    /// Connection property
+    #[zbus(property)]
+    fn connection(&self) -> zbus::Result<OwnedObjectPath>;
+
+    /// State property
+    #[zbus(property)]
+    fn state(&self) -> zbus::Result<u32>;
+
+    /// Ip4Config property
+    #[zbus(property)]
+    fn ip4_config(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.IP4Config",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait Ip4Config {
+    /// AddressData property
+    #[zbus(property)]
+    fn address_data(&self) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+}
+
+#[derive(Debug, Clone)]
+pub struct WireGuard {
+    /// Object path of the connection's settings.
+    pub path: String,
+    pub name: String,
+    /// Object path of the active connection, when it's up.
+    pub active: Option<String>,
+    /// Connected, rather than still activating.
+    pub connected: bool,
+    pub addresses: Vec<String>,
+}
+
+async fn addresses(
+    conn: &Connection,
+    active: &ActiveConnectionProxy<'_>,
+) -> zbus::Result<Vec<String>> {
+    let path = active.ip4_config().await?;
+    if path.as_str() == "/" {
+        return Ok(Vec::new());
+    }
+    let config = Ip4ConfigProxy::builder(conn).path(path)?.build().await?;
+    Ok(config
+        .address_data()
+        .await?
+        .iter()
+        .filter_map(|data| String::try_from(data.get("address")?.try_clone().ok()?).ok())
+        .collect())
+}
+
+pub async fn connections() -> zbus::Result<Vec<WireGuard>> {
+    let conn = Connection::system().await?;
+
+    let mut connections = Vec::new();
+    for path in SettingsProxy::new(&conn).await?.list_connections().await? {
+        let settings = SettingsConnectionProxy::builder(&conn)
+            .path(path.clone())?
+            .build()
+            .await?
+            .get_settings()
+            .await?;
+        let Some(connection) = settings.get("connection") else {
+            continue;
+        };
+        let field = |name: &str| {
+            connection
+                .get(name)
+                .and_then(|value| String::try_from(value.try_clone().ok()?).ok())
+        };
+        if field("type").as_deref() != Some("wireguard") {
+            continue;
+        }
+        connections.push(WireGuard {
+            path: path.to_string(),
+            name: field("id").unwrap_or_default(),
+            active: None,
+            connected: false,
+            addresses: Vec::new(),
+        });
+    }
+
+    for active_path in NetworkManagerProxy::new(&conn)
+        .await?
+        .active_connections()
+        .await?
+    {
+        let active = ActiveConnectionProxy::builder(&conn)
+            .path(active_path.clone())?
+            .build()
+            .await?;
+        let settings_path = active.connection().await?;
+        let Some(wireguard) = connections
+            .iter_mut()
+            .find(|c| c.path == settings_path.as_str())
+        else {
+            continue;
+        };
+        wireguard.active = Some(active_path.to_string());
+        wireguard.connected = active.state().await? == STATE_ACTIVATED;
+        wireguard.addresses = addresses(&conn, &active).await.unwrap_or_default();
+    }
+
+    connections.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(connections)
+}
+
+pub async fn connect(path: String) -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    let none = ObjectPath::from_static_str_unchecked("/");
+    NetworkManagerProxy::new(&conn)
+        .await?
+        .activate_connection(&ObjectPath::try_from(path.as_str())?, &none, &none)
+        .await?;
+    Ok(())
+}
+
+pub async fn disconnect(active: String) -> zbus::Result<()> {
+    let conn = Connection::system().await?;
+    NetworkManagerProxy::new(&conn)
+        .await?
+        .deactivate_connection(&ObjectPath::try_from(active.as_str())?)
+        .await
+}
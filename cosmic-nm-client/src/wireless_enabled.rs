@@ -1,4 +1,4 @@
-use super::{NetworkManagerEvent, NetworkManagerState};
+use crate::{NetworkManagerEvent, NetworkManagerState};
 use cosmic::iced::{self, subscription};
 use cosmic_dbus_networkmanager::nm::NetworkManager;
 use futures::{SinkExt, StreamExt};
@@ -15,12 +15,14 @@ use cosmic::{
     theme::Theme,
     widget::vertical_space,
 };
+use cosmic_applet_helper::logging;
 use cosmic_config::{Config, CosmicConfigEntry};
 use freedesktop_desktop_entry::DesktopEntry;
 use std::{env, fs, process::Command};
 
 mod config;
 
+const APPLET: &str = env!("CARGO_PKG_NAME");
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[derive(Debug, Clone, Default)]
@@ -144,9 +146,11 @@ impl cosmic::Application for Button {
 }
 
 pub fn main() -> iced::Result {
-    tracing_subscriber::fmt::init();
-    let _ = tracing_log::LogTracer::init();
+    logging::init(APPLET);
+    let _span = logging::applet_span().entered();
+
     tracing::info!("Starting panel button applet with version {VERSION}");
+    cosmic_applet_helper::install_panic_hook(APPLET, VERSION);
 
     let id = env::args()
         .nth(1)
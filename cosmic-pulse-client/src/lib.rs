@@ -1,7 +1,23 @@
 // Copyright 2023 System76 <info@system76.com>
 // SPDX-License-Identifier: GPL-3.0-only
 
-use std::{cell::RefCell, rc::Rc, thread};
+//! PulseAudio client shared by the applets, which also works with PipeWire's
+//! PulseAudio server.
+//!
+//! [`connect`] starts a thread that owns the connection to the server. Its
+//! first event is [`Event::Init`], carrying a [`Connection`] for sending
+//! [`Message`]s, whose answers arrive as [`Event::MessageReceived`]. The
+//! thread is started once per process and kept in [`FROM_PULSE`], so the
+//! panel button and popup of an applet, or several subscriptions, all share a
+//! single connection.
+//!
+//! [`Model`] holds the sinks, sources and streams, and is kept up to date by
+//! passing it every [`Event`].
+//!
+//! [`sink_events`] reports sinks being added and removed as they happen, and
+//! [`server_events`] anything changing on the server.
+
+use std::{cell::RefCell, fmt::Debug, hash::Hash, io, process::Stdio, rc::Rc, thread};
 
 extern crate libpulse_binding as pulse;
 
@@ -14,16 +30,20 @@ use cosmic_time::once_cell::sync::Lazy;
 use libpulse_binding::{
     callbacks::ListResult,
     context::{
-        introspect::{Introspector, SinkInfo, SourceInfo},
+        introspect::{Introspector, SinkInfo, SinkInputInfo, SourceInfo, SourceOutputInfo},
         Context,
     },
     error::PAErr,
     mainloop::standard::{IterateResult, Mainloop},
     proplist::Proplist,
-    volume::ChannelVolumes,
+    volume::{ChannelVolumes, Volume},
 };
 
-use tokio::sync::{mpsc, Mutex};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader, Lines},
+    process::{Child, ChildStdout},
+    sync::{mpsc, Mutex},
+};
 
 pub static FROM_PULSE: Lazy<Mutex<Option<(mpsc::Receiver<Message>, mpsc::Sender<Message>)>>> =
     Lazy::new(|| Mutex::new(None));
@@ -44,6 +64,111 @@ pub fn connect() -> iced::Subscription<Event> {
     )
 }
 
+/// A sink appearing or going away, with its index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkEvent {
+    New(u32),
+    Removed(u32),
+}
+
+/// What a change on the server was made to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Facility {
+    Sink,
+    Source,
+    /// A playback stream.
+    SinkInput,
+    /// A recording stream.
+    SourceOutput,
+    /// The server itself, such as its default sink or source.
+    Server,
+    Other,
+}
+
+/// Sinks being added and removed, such as when headphones are plugged in.
+pub fn sink_events<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+) -> iced::Subscription<SinkEvent> {
+    subscription::channel(id, 50, move |mut output| async move {
+        if let Err(err) = watch_sinks(&mut output).await {
+            tracing::error!("Failed to watch audio sinks: {}", err);
+        }
+        futures::future::pending().await
+    })
+}
+
+/// Anything on the server being added, changed or removed, so it can be
+/// requested again.
+pub fn server_events<I: 'static + Hash + Copy + Send + Sync + Debug>(
+    id: I,
+) -> iced::Subscription<Facility> {
+    subscription::channel(id, 50, move |mut output| async move {
+        if let Err(err) = watch_server(&mut output).await {
+            tracing::error!("Failed to watch audio server: {}", err);
+        }
+        futures::future::pending().await
+    })
+}
+
+/// Runs `pactl subscribe`, whose lines are read with [`parse_event`].
+///
+/// The connection's mainloop is only iterated while waiting on requests, so
+/// server events are read from `pactl` instead.
+fn pactl_subscribe() -> io::Result<(Child, Lines<BufReader<ChildStdout>>)> {
+    let mut child = tokio::process::Command::new("pactl")
+        .arg("subscribe")
+        // The output is parsed, so it mustn't be translated
+        .env("LC_ALL", "C")
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| io::Error::other("no stdout"))?;
+    Ok((child, BufReader::new(stdout).lines()))
+}
+
+/// The kind of event, facility and index of a line such as
+/// `Event 'new' on sink #52`.
+fn parse_event(line: &str) -> Option<(&str, &str, u32)> {
+    let (kind, rest) = line.strip_prefix("Event '")?.split_once("' on ")?;
+    let (facility, index) = rest.split_once(" #")?;
+    Some((kind, facility, index.trim().parse().ok()?))
+}
+
+async fn watch_sinks(output: &mut futures::channel::mpsc::Sender<SinkEvent>) -> io::Result<()> {
+    let (_child, mut lines) = pactl_subscribe()?;
+    while let Some(line) = lines.next_line().await? {
+        let event = match parse_event(&line) {
+            Some(("new", "sink", index)) => SinkEvent::New(index),
+            Some(("remove", "sink", index)) => SinkEvent::Removed(index),
+            _ => continue,
+        };
+        _ = output.send(event).await;
+    }
+    Ok(())
+}
+
+async fn watch_server(output: &mut futures::channel::mpsc::Sender<Facility>) -> io::Result<()> {
+    let (_child, mut lines) = pactl_subscribe()?;
+    while let Some(line) = lines.next_line().await? {
+        let Some((_, facility, _)) = parse_event(&line) else {
+            continue;
+        };
+        let facility = match facility {
+            "sink" => Facility::Sink,
+            "source" => Facility::Source,
+            "sink-input" => Facility::SinkInput,
+            "source-output" => Facility::SourceOutput,
+            "server" => Facility::Server,
+            _ => Facility::Other,
+        };
+        _ = output.send(facility).await;
+    }
+    Ok(())
+}
+
 async fn start_listening(
     state: State,
     output: &mut futures::channel::mpsc::Sender<Event>,
@@ -108,6 +233,12 @@ async fn start_listening(
                         .await;
                     State::Connected
                 }
+                Some(Message::SetStreams(streams)) => {
+                    _ = output
+                        .send(Event::MessageReceived(Message::SetStreams(streams)))
+                        .await;
+                    State::Connected
+                }
                 Some(Message::SetDefaultSink(sink)) => {
                     _ = output
                         .send(Event::MessageReceived(Message::SetDefaultSink(sink)))
@@ -120,6 +251,12 @@ async fn start_listening(
                         .await;
                     State::Connected
                 }
+                Some(Message::LoopbackLoaded(module)) => {
+                    _ = output
+                        .send(Event::MessageReceived(Message::LoopbackLoaded(module)))
+                        .await;
+                    State::Connected
+                }
                 Some(Message::Disconnected) => {
                     _ = output.send(Event::Disconnected).await;
                     State::Connecting
@@ -165,9 +302,11 @@ pub enum Message {
     Disconnected,
     GetSinks,
     GetSources,
+    GetStreams,
     UpdateConnection,
     SetSinks(Vec<DeviceInfo>),
     SetSources(Vec<DeviceInfo>),
+    SetStreams(Vec<StreamInfo>),
     GetDefaultSink,
     GetDefaultSource,
     SetDefaultSink(DeviceInfo),
@@ -176,6 +315,18 @@ pub enum Message {
     SetSourceVolumeByName(String, ChannelVolumes),
     SetSinkMuteByName(String, bool),
     SetSourceMuteByName(String, bool),
+    /// Mutes or unmutes a recording stream, by its index.
+    SetSourceOutputMute(u32, bool),
+    /// Disconnects a recording stream from its source, by its index.
+    KillSourceOutput(u32),
+    /// Plays a source on the default sink, answered with the loaded module's
+    /// index.
+    LoadLoopback {
+        source: String,
+        latency_msec: u32,
+    },
+    LoopbackLoaded(u32),
+    UnloadModule(u32),
 }
 
 struct PulseHandle {
@@ -279,6 +430,22 @@ impl PulseHandle {
                                     Err(_) => Self::send_disconnected(&from_pulse_send).await,
                                 }
                             }
+                            Message::GetStreams => {
+                                let server = match server.as_mut() {
+                                    Some(s) => s,
+                                    None => continue,
+                                };
+                                match server.get_streams() {
+                                    Ok(streams) => {
+                                        if let Err(err) =
+                                            from_pulse_send.send(Message::SetStreams(streams)).await
+                                        {
+                                            tracing::error!("ERROR! {}", err);
+                                        }
+                                    }
+                                    Err(_) => Self::send_disconnected(&from_pulse_send).await,
+                                }
+                            }
                             Message::SetSinkVolumeByName(name, channel_volumes) => {
                                 let server = match server.as_mut() {
                                     Some(s) => s,
@@ -303,6 +470,30 @@ impl PulseHandle {
                                     server.introspector.set_sink_mute_by_name(&name, mute, None);
                                 server.wait_for_result(op).ok();
                             }
+                            Message::LoadLoopback {
+                                source,
+                                latency_msec,
+                            } => {
+                                let server = match server.as_mut() {
+                                    Some(s) => s,
+                                    None => continue,
+                                };
+                                if let Some(module) = server.load_loopback(&source, latency_msec) {
+                                    if let Err(err) =
+                                        from_pulse_send.send(Message::LoopbackLoaded(module)).await
+                                    {
+                                        tracing::error!("ERROR! {:?}", err);
+                                    }
+                                }
+                            }
+                            Message::UnloadModule(module) => {
+                                let server = match server.as_mut() {
+                                    Some(s) => s,
+                                    None => continue,
+                                };
+                                let op = server.introspector.unload_module(module, |_| {});
+                                server.wait_for_result(op).ok();
+                            }
                             Message::SetSourceMuteByName(name, mute) => {
                                 let server = match server.as_mut() {
                                     Some(s) => s,
@@ -314,6 +505,26 @@ impl PulseHandle {
                                     .set_source_mute_by_name(&name, mute, None);
                                 server.wait_for_result(op).ok();
                             }
+                            Message::SetSourceOutputMute(index, mute) => {
+                                let server = match server.as_mut() {
+                                    Some(s) => s,
+                                    None => continue,
+                                };
+
+                                let op = server
+                                    .introspector
+                                    .set_source_output_mute(index, mute, None);
+                                server.wait_for_result(op).ok();
+                            }
+                            Message::KillSourceOutput(index) => {
+                                let server = match server.as_mut() {
+                                    Some(s) => s,
+                                    None => continue,
+                                };
+
+                                let op = server.introspector.kill_source_output(index, |_| {});
+                                server.wait_for_result(op).ok();
+                            }
                             Message::UpdateConnection => {
                                 tracing::info!(
                                     "Updating Connection, server exists: {:?}",
@@ -531,6 +742,35 @@ impl PulseServer {
         })
     }
 
+    // Get a list of playback and recording streams
+    pub fn get_streams(&self) -> Result<Vec<StreamInfo>, PulseServerError> {
+        let list: Rc<RefCell<Option<Vec<StreamInfo>>>> = Rc::new(RefCell::new(Some(Vec::new())));
+
+        let list_ref = list.clone();
+        let operation = self.introspector.get_sink_input_info_list(
+            move |input_list: ListResult<&SinkInputInfo>| {
+                if let ListResult::Item(item) = input_list {
+                    list_ref.borrow_mut().as_mut().unwrap().push(item.into());
+                }
+            },
+        );
+        self.wait_for_result(operation)?;
+
+        let list_ref = list.clone();
+        let operation = self.introspector.get_source_output_info_list(
+            move |output_list: ListResult<&SourceOutputInfo>| {
+                if let ListResult::Item(item) = output_list {
+                    list_ref.borrow_mut().as_mut().unwrap().push(item.into());
+                }
+            },
+        );
+        self.wait_for_result(operation).and_then(|_| {
+            list.borrow_mut().take().ok_or(PulseServerError::Misc(
+                "get_streams(): failed to wait for operation",
+            ))
+        })
+    }
+
     pub fn get_server_info(&mut self) -> Result<ServerInfo, PulseServerError> {
         let info = Rc::new(RefCell::new(Some(None)));
         let info_ref = info.clone();
@@ -679,6 +919,23 @@ impl PulseServer {
         let _ = self.wait_for_result(op);
     }
 
+    fn load_loopback(&mut self, source: &str, latency_msec: u32) -> Option<u32> {
+        let module = Rc::new(RefCell::new(None));
+        let module_ref = module.clone();
+        // Without a `sink`, the loopback follows the default sink.
+        let argument = format!("source={source} latency_msec={latency_msec} source_dont_move=true");
+        let op = self
+            .introspector
+            .load_module("module-loopback", &argument, move |index| {
+                // `u32::MAX` is `PA_INVALID_INDEX`, for modules that failed to load
+                if index != u32::MAX {
+                    module_ref.borrow_mut().replace(index);
+                }
+            });
+        self.wait_for_result(op).ok();
+        module.take()
+    }
+
     fn get_source_outputs(&mut self, source: u32) -> Vec<u32> {
         let result = Rc::new(RefCell::new(Vec::new()));
         let result_ref = Rc::new(RefCell::new(Vec::new()));
@@ -746,6 +1003,10 @@ pub struct DeviceInfo {
     pub volume: ChannelVolumes,
     pub mute: bool,
     pub index: u32,
+    /// Such as `headphones`, `headset` or `speaker`.
+    pub form_factor: Option<String>,
+    /// Such as `pci`, `usb` or `bluetooth`.
+    pub bus: Option<String>,
 }
 
 impl<'a> From<&SinkInfo<'a>> for DeviceInfo {
@@ -756,6 +1017,12 @@ impl<'a> From<&SinkInfo<'a>> for DeviceInfo {
             volume: info.volume,
             mute: info.mute,
             index: info.index,
+            form_factor: info
+                .proplist
+                .get_str(pulse::proplist::properties::DEVICE_FORM_FACTOR),
+            bus: info
+                .proplist
+                .get_str(pulse::proplist::properties::DEVICE_BUS),
         }
     }
 }
@@ -768,12 +1035,141 @@ impl<'a> From<&SourceInfo<'a>> for DeviceInfo {
             volume: info.volume,
             mute: info.mute,
             index: info.index,
+            form_factor: info
+                .proplist
+                .get_str(pulse::proplist::properties::DEVICE_FORM_FACTOR),
+            bus: info
+                .proplist
+                .get_str(pulse::proplist::properties::DEVICE_BUS),
         }
     }
 }
 
 impl Eq for DeviceInfo {}
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamKind {
+    /// A sink input, playing to a sink.
+    Playback,
+    /// A source output, recording from a source.
+    Recording,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamInfo {
+    pub index: u32,
+    pub kind: StreamKind,
+    /// Index of the sink or source the stream is connected to.
+    pub device: u32,
+    pub application: Option<String>,
+    pub volume: ChannelVolumes,
+    pub mute: bool,
+    /// Whether the stream is paused.
+    pub corked: bool,
+}
+
+impl StreamInfo {
+    fn application(proplist: &Proplist) -> Option<String> {
+        proplist
+            .get_str(pulse::proplist::properties::APPLICATION_NAME)
+            .or_else(|| proplist.get_str(pulse::proplist::properties::APPLICATION_PROCESS_BINARY))
+    }
+}
+
+impl<'a> From<&SinkInputInfo<'a>> for StreamInfo {
+    fn from(info: &SinkInputInfo<'a>) -> Self {
+        Self {
+            index: info.index,
+            kind: StreamKind::Playback,
+            device: info.sink,
+            application: Self::application(&info.proplist),
+            volume: info.volume,
+            mute: info.mute,
+            corked: info.corked,
+        }
+    }
+}
+
+impl<'a> From<&SourceOutputInfo<'a>> for StreamInfo {
+    fn from(info: &SourceOutputInfo<'a>) -> Self {
+        Self {
+            index: info.index,
+            kind: StreamKind::Recording,
+            device: info.source,
+            application: Self::application(&info.proplist),
+            volume: info.volume,
+            mute: info.mute,
+            corked: info.corked,
+        }
+    }
+}
+
+impl Eq for StreamInfo {}
+
+/// Sinks, sources and streams, as last reported by the server.
+#[derive(Debug, Clone, Default)]
+pub struct Model {
+    pub connected: bool,
+    pub sinks: Vec<DeviceInfo>,
+    pub sources: Vec<DeviceInfo>,
+    pub default_sink: Option<DeviceInfo>,
+    pub default_source: Option<DeviceInfo>,
+    pub streams: Vec<StreamInfo>,
+}
+
+impl Model {
+    pub fn update(&mut self, event: &Event) {
+        match event {
+            Event::Init(_) => {}
+            Event::Connected => self.connected = true,
+            Event::Disconnected => *self = Self::default(),
+            Event::MessageReceived(message) => match message {
+                Message::SetSinks(sinks) => self.sinks = sinks.clone(),
+                Message::SetSources(sources) => self.sources = sources.clone(),
+                Message::SetStreams(streams) => self.streams = streams.clone(),
+                Message::SetDefaultSink(sink) => self.default_sink = Some(sink.clone()),
+                Message::SetDefaultSource(source) => self.default_source = Some(source.clone()),
+                Message::Disconnected => *self = Self::default(),
+                _ => {}
+            },
+        }
+    }
+
+    /// Applications recording from a source other than a monitor of a sink,
+    /// sorted and without duplicates.
+    pub fn recording_applications(&self) -> Vec<String> {
+        let mut applications = self
+            .streams
+            .iter()
+            .filter(|stream| stream.kind == StreamKind::Recording && !stream.corked)
+            .filter(|stream| {
+                !self
+                    .sources
+                    .iter()
+                    .find(|source| source.index == stream.device)
+                    .and_then(|source| source.name.as_ref())
+                    .map_or(false, |name| name.contains("monitor"))
+            })
+            .filter_map(|stream| stream.application.clone())
+            .collect::<Vec<_>>();
+        applications.sort();
+        applications.dedup();
+        applications
+    }
+}
+
+pub fn volume_to_percent(volume: Volume) -> f64 {
+    volume.0 as f64 * 100. / Volume::NORMAL.0 as f64
+}
+
+pub fn percent_to_volume(percent: f64) -> Volume {
+    Volume(
+        (percent / 100. * Volume::NORMAL.0 as f64)
+            .clamp(0., Volume::NORMAL.0 as f64)
+            .round() as u32,
+    )
+}
+
 #[derive(Debug)]
 pub struct ServerInfo {
     /// User name of the daemon process.